@@ -0,0 +1,67 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+// Generate the C header for the FFI layer into the output directory of the build. The header checked in at
+// `include/aleo.h` is regenerated with `scripts/generate-header.sh`.
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap();
+
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Unable to generate the C header")
+        .write_to_file(format!("{out_dir}/aleo.h"));
+
+    println!("cargo:rerun-if-changed=src/ffi");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
+
+// Expose the snarkVM version pinned in the manifest as `ALEO_SNARKVM_VERSION`, failing the build if the snarkVM
+// crates are not all pinned to the same exact version, as their types would then not match those of downstream crates.
+fn pin_snarkvm_version() {
+    let manifest = std::fs::read_to_string("Cargo.toml").expect("Unable to read the manifest");
+    let mut versions = vec![];
+    let mut section = "";
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            section = line;
+        } else if let Some(version) = line.strip_prefix("version = ") {
+            if section.starts_with("[dependencies.snarkvm-") {
+                versions.push((section, version.trim_matches('"')));
+            }
+        }
+    }
+    let pinned = versions.first().and_then(|(_, version)| version.strip_prefix('=')).expect("snarkVM is not pinned");
+    for (section, version) in &versions {
+        assert_eq!(version.strip_prefix('='), Some(pinned), "{section} must be pinned to snarkVM ={pinned}");
+    }
+    println!("cargo:rustc-env=ALEO_SNARKVM_VERSION={pinned}");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+}
+
+// The build script, which pins the snarkVM version, and generates the C header when the `ffi` feature is enabled.
+fn main() {
+    pin_snarkvm_version();
+
+    #[cfg(feature = "ffi")]
+    generate_header();
+
+    println!("cargo:rerun-if-changed=build.rs");
+}
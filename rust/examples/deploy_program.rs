@@ -0,0 +1,160 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Deploy a program, paying its fee with a record of the deployer, and check that the node serves it.
+//!
+//! ```text
+//! cargo run --example deploy_program --features devnet -- --endpoint https://vm.aleo.org/api \
+//!     --private-key APrivateKey1... --program main.aleo --fee-record "{ owner: aleo1..., ... }" --fee 600000
+//! ```
+//!
+//! Without an endpoint, a counter program is deployed to a local ledger by a new account it funds.
+
+use aleo_rust::{
+    snarkvm::{Address, Network, Plaintext, PrivateKey, Program, Record, Testnet3, ViewKey},
+    AleoAPIClient,
+    LocalLedgerClient,
+    ProgramManager,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::{fs, str::FromStr};
+
+type N = Testnet3;
+
+// The program deployed when none is given
+const COUNTER_PROGRAM: &str = "program example_counter.aleo;
+
+mapping counts:
+    key left as u8.public;
+    value right as u64.public;
+
+function bump:
+    input r0 as u64.public;
+    finalize r0;
+
+finalize bump:
+    input r0 as u64.public;
+    increment counts[0u8] by r0;
+";
+
+// The arguments of the example
+struct Args {
+    endpoint: Option<String>,
+    private_key: Option<PrivateKey<N>>,
+    program: Program<N>,
+    fee_record: Option<Record<N, Plaintext<N>>>,
+    fee: u64,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self {
+            endpoint: None,
+            private_key: None,
+            program: Program::from_str(COUNTER_PROGRAM)?,
+            fee_record: None,
+            fee: 600_000,
+        };
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| anyhow!("Missing the value of '{flag}'"))?;
+            match flag.as_str() {
+                "--endpoint" => parsed.endpoint = Some(value),
+                "--private-key" => parsed.private_key = Some(PrivateKey::from_str(&value)?),
+                "--program" => {
+                    let source = fs::read_to_string(&value).with_context(|| format!("Failed to read '{value}'"))?;
+                    parsed.program = Program::from_str(&source).map_err(|_| anyhow!("'{value}' is not a program"))?;
+                }
+                "--fee-record" => parsed.fee_record = Some(Record::from_str(&value)?),
+                "--fee" => parsed.fee = value.parse()?,
+                _ => bail!("Unknown argument '{flag}'"),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+// The deployment broadcast by the example
+struct Deployed {
+    transaction_id: <N as Network>::TransactionID,
+    api_client: AleoAPIClient<N>,
+    // The local ledger serving the client, if any, kept alive for the caller
+    _ledger: Option<LocalLedgerClient<N>>,
+}
+
+// Deploy the program, returning the ID of the deployment
+fn run(args: Args) -> Result<Deployed> {
+    let (api_client, private_key, fee_record, ledger) = match args.endpoint {
+        Some(endpoint) => {
+            let private_key =
+                args.private_key.ok_or_else(|| anyhow!("Deploying to an endpoint needs a --private-key"))?;
+            let fee_record = args.fee_record.ok_or_else(|| anyhow!("Deploying to an endpoint needs a --fee-record"))?;
+            (AleoAPIClient::new(&endpoint, "testnet3"), private_key, fee_record, None)
+        }
+        None => {
+            let rng = &mut rand::thread_rng();
+            let ledger = LocalLedgerClient::<N>::new(PrivateKey::new(rng)?)?;
+            let private_key = PrivateKey::<N>::new(rng)?;
+            let (_, fee_record) = ledger.fund(Address::try_from(private_key)?, args.fee)?;
+            let fee_record = fee_record.decrypt(&ViewKey::try_from(private_key)?)?;
+            (ledger.api_client().clone(), private_key, fee_record, Some(ledger))
+        }
+    };
+    let program_manager = ProgramManager::new(private_key, api_client.clone());
+    let transaction_id = program_manager.deploy(&args.program, &[], args.fee, fee_record)?;
+    Ok(Deployed { transaction_id, api_client, _ledger: ledger })
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse(std::env::args().skip(1))?;
+    let program_id = *args.program.id();
+    let deployed = run(args)?;
+    println!("Deployed {program_id} to {} in transaction {}", deployed.api_client.base_url(), deployed.transaction_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_deploy_program() {
+        let deployed = run(Args::parse(args(&[])).unwrap()).unwrap();
+        let transaction = deployed.api_client.get_transaction(deployed.transaction_id).unwrap();
+        assert_eq!(transaction.id(), deployed.transaction_id);
+        let program = deployed.api_client.get_program("example_counter.aleo").unwrap();
+        assert_eq!(program, Program::from_str(COUNTER_PROGRAM).unwrap());
+    }
+
+    #[test]
+    fn test_deploy_program_invalid_arguments() {
+        let path = std::env::temp_dir().join(format!("aleo-example-{}.aleo", std::process::id()));
+        fs::write(&path, "program broken.aleo;\n\nfunction main:\n    add r0 into r1;\n").unwrap();
+        let error = Args::parse(args(&["--program", path.to_str().unwrap()])).err().unwrap();
+        assert_eq!(error.to_string(), format!("'{}' is not a program", path.display()));
+        fs::remove_file(&path).unwrap();
+
+        let error = Args::parse(args(&["--program", path.to_str().unwrap()])).err().unwrap();
+        assert_eq!(error.to_string(), format!("Failed to read '{}'", path.display()));
+        let error = run(Args::parse(args(&["--endpoint", "http://127.0.0.1:1"])).unwrap()).err().unwrap();
+        assert_eq!(error.to_string(), "Deploying to an endpoint needs a --private-key");
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Follow the chain from a height, printing each block as the latest height of the node reaches it.
+//!
+//! ```text
+//! cargo run --example follow_chain --features devnet -- --endpoint https://vm.aleo.org/api --from 1000 --blocks 10
+//! ```
+//!
+//! Without an endpoint, the chain of a local ledger is followed while another thread produces its blocks.
+
+use aleo_rust::{
+    snarkvm::{PrivateKey, Testnet3},
+    AleoAPIClient,
+    BlockHeight,
+    HeightWatcher,
+    LocalLedgerClient,
+    WatchOptions,
+};
+
+use anyhow::{anyhow, bail, Result};
+use std::{thread, time::Duration};
+
+type N = Testnet3;
+
+// The arguments of the example
+struct Args {
+    endpoint: Option<String>,
+    from: u32,
+    blocks: u32,
+    timeout: Duration,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self { endpoint: None, from: 1, blocks: 3, timeout: Duration::from_secs(60) };
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| anyhow!("Missing the value of '{flag}'"))?;
+            match flag.as_str() {
+                "--endpoint" => parsed.endpoint = Some(value),
+                "--from" => parsed.from = value.parse()?,
+                "--blocks" => parsed.blocks = value.parse()?,
+                "--timeout" => parsed.timeout = Duration::from_secs(value.parse()?),
+                _ => bail!("Unknown argument '{flag}'"),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+// A block followed by the example
+#[derive(Debug, PartialEq, Eq)]
+struct Followed {
+    height: u32,
+    hash: String,
+    transactions: usize,
+}
+
+// Follow the chain, calling `on_block` with each block as it is reached
+fn run(args: Args, mut on_block: impl FnMut(&Followed)) -> Result<Vec<Followed>> {
+    let (api_client, _ledger) = match args.endpoint {
+        Some(endpoint) => (AleoAPIClient::<N>::new(&endpoint, "testnet3"), None),
+        None => {
+            let ledger = LocalLedgerClient::<N>::new(PrivateKey::new(&mut rand::thread_rng())?)?;
+            let producer = ledger.clone();
+            let blocks = args.from.saturating_add(args.blocks);
+            thread::spawn(move || {
+                while producer.latest_height() < blocks {
+                    thread::sleep(Duration::from_millis(100));
+                    if producer.advance_block().is_err() {
+                        break;
+                    }
+                }
+            });
+            (ledger.api_client().clone(), Some(ledger))
+        }
+    };
+    let options = WatchOptions { poll_interval: Duration::from_millis(200), ..Default::default() };
+    let watcher = HeightWatcher::start(vec![api_client.clone()], options)?;
+    let mut followed = vec![];
+    for height in args.from..args.from.saturating_add(args.blocks) {
+        watcher.wait_for_height(height, args.timeout)?;
+        let block = api_client.get_block(BlockHeight(height))?;
+        let block = Followed {
+            height,
+            hash: block.hash().to_string(),
+            transactions: block.transactions().len(),
+        };
+        on_block(&block);
+        followed.push(block);
+    }
+    Ok(followed)
+}
+
+fn main() -> Result<()> {
+    run(Args::parse(std::env::args().skip(1))?, |block| {
+        println!("{} {} ({} transactions)", block.height, block.hash, block.transactions)
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_follow_chain() {
+        let mut reached = vec![];
+        let followed = run(Args::parse(args(&["--from", "1", "--blocks", "3"])).unwrap(), |block| {
+            reached.push(block.height)
+        })
+        .unwrap();
+        assert_eq!(reached, [1, 2, 3]);
+        assert_eq!(followed.iter().map(|block| block.transactions).collect::<Vec<_>>(), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_follow_chain_unreachable_node() {
+        let error = Args::parse(args(&["--blocks"])).err().unwrap();
+        assert_eq!(error.to_string(), "Missing the value of '--blocks'");
+
+        // A node that cannot be reached never returns the latest height, so following it times out.
+        let args = Args::parse(args(&["--endpoint", "http://127.0.0.1:1", "--timeout", "1"])).unwrap();
+        let error = run(args, |_| panic!("No block should be reached")).err().unwrap();
+        assert_eq!(error.to_string(), "The latest height did not reach 1 within 1s, no node returned it");
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Scan a range of blocks for the records of a view key, and print their commitments and gates.
+//!
+//! ```text
+//! cargo run --example scan_wallet --features devnet -- --endpoint https://vm.aleo.org/api --view-key AViewKey1... \
+//!     --start 0 --end 100
+//! ```
+//!
+//! Without an endpoint, the records are scanned from a local ledger funding a new account twice.
+
+use aleo_rust::{
+    snarkvm::{Address, Field, PrivateKey, Testnet3, ViewKey},
+    AleoAPIClient,
+    BlockHeight,
+    LocalLedgerClient,
+};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use std::str::FromStr;
+
+type N = Testnet3;
+
+// The arguments of the example
+struct Args {
+    endpoint: Option<String>,
+    view_key: Option<ViewKey<N>>,
+    start: BlockHeight,
+    end: Option<BlockHeight>,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self { endpoint: None, view_key: None, start: BlockHeight::GENESIS, end: None };
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| anyhow!("Missing the value of '{flag}'"))?;
+            match flag.as_str() {
+                "--endpoint" => parsed.endpoint = Some(value),
+                "--view-key" => {
+                    let view_key = ViewKey::from_str(&value).map_err(|_| anyhow!("Invalid view key '{value}'"))?;
+                    parsed.view_key = Some(view_key);
+                }
+                "--start" => parsed.start = value.parse()?,
+                "--end" => parsed.end = Some(value.parse()?),
+                _ => bail!("Unknown argument '{flag}'"),
+            }
+        }
+        if let Some(end) = parsed.end {
+            ensure!(parsed.start <= end, "The scan cannot end at {end}, before it starts at {}", parsed.start);
+        }
+        Ok(parsed)
+    }
+}
+
+// Scan the blocks for the records of the view key, returning their commitments and gates
+fn run(args: Args) -> Result<Vec<(Field<N>, u64)>> {
+    let (api_client, view_key, _ledger) = match args.endpoint {
+        Some(endpoint) => {
+            let view_key = args.view_key.ok_or_else(|| anyhow!("Scanning an endpoint needs a --view-key"))?;
+            (AleoAPIClient::new(&endpoint, "testnet3"), view_key, None)
+        }
+        None => {
+            let rng = &mut rand::thread_rng();
+            let ledger = LocalLedgerClient::<N>::new(PrivateKey::new(rng)?)?;
+            let private_key = PrivateKey::<N>::new(rng)?;
+            for amount in [100, 25] {
+                ledger.fund(Address::try_from(private_key)?, amount)?;
+            }
+            (ledger.api_client().clone(), ViewKey::try_from(private_key)?, Some(ledger))
+        }
+    };
+    let end = match args.end {
+        Some(end) => end,
+        None => api_client.latest_height()?,
+    };
+    let records = api_client.scan(view_key, args.start..=end)?;
+    records
+        .into_iter()
+        .map(|(commitment, record)| Ok((commitment, ***record.decrypt(&view_key)?.gates())))
+        .collect()
+}
+
+fn main() -> Result<()> {
+    let records = run(Args::parse(std::env::args().skip(1))?)?;
+    for (commitment, gates) in &records {
+        println!("{commitment} {gates} gates");
+    }
+    println!("{} records, {} gates", records.len(), records.iter().map(|(_, gates)| gates).sum::<u64>());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_scan_wallet() {
+        let records = run(Args::parse(args(&[])).unwrap()).unwrap();
+        let gates = records.iter().map(|(_, gates)| *gates).collect::<Vec<_>>();
+        assert_eq!(gates, [100, 25]);
+    }
+
+    #[test]
+    fn test_scan_wallet_invalid_arguments() {
+        let error = Args::parse(args(&["--view-key", "AViewKey1invalid"])).err().unwrap();
+        assert_eq!(error.to_string(), "Invalid view key 'AViewKey1invalid'");
+        let error = Args::parse(args(&["--start", "10", "--end", "5"])).err().unwrap();
+        assert_eq!(error.to_string(), "The scan cannot end at 5, before it starts at 10");
+        let error = run(Args::parse(args(&["--endpoint", "http://127.0.0.1:1"])).unwrap()).err().unwrap();
+        assert_eq!(error.to_string(), "Scanning an endpoint needs a --view-key");
+
+        // A node that cannot be reached fails the scan.
+        let view_key = ViewKey::try_from(PrivateKey::<N>::new(&mut rand::thread_rng()).unwrap()).unwrap();
+        let args = Args::parse(args(&["--endpoint", "http://127.0.0.1:1", "--view-key", &view_key.to_string()]));
+        assert!(run(args.unwrap()).is_err());
+    }
+}
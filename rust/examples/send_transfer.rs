@@ -0,0 +1,181 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Send gates to a recipient, paying the transfer and its fee with unspent records of the sender.
+//!
+//! ```text
+//! cargo run --example send_transfer --features devnet -- --endpoint https://vm.aleo.org/api \
+//!     --private-key APrivateKey1... --recipient aleo1... --amount 100 --fee 1
+//! ```
+//!
+//! Without an endpoint, the sender is a new account funded by a local ledger, and the recipient defaults to a new
+//! account as well.
+
+use aleo_rust::{
+    snarkvm::{Address, Network, Plaintext, PrivateKey, Record, Testnet3, ViewKey},
+    AleoAPIClient,
+    BlockHeight,
+    LocalLedgerClient,
+    Microcredits,
+    ProgramManager,
+};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use std::str::FromStr;
+
+type N = Testnet3;
+
+// The arguments of the example
+struct Args {
+    endpoint: Option<String>,
+    private_key: Option<PrivateKey<N>>,
+    recipient: Option<Address<N>>,
+    amount: u64,
+    fee: u64,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self { endpoint: None, private_key: None, recipient: None, amount: 0, fee: 1 };
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| anyhow!("Missing the value of '{flag}'"))?;
+            match flag.as_str() {
+                "--endpoint" => parsed.endpoint = Some(value),
+                "--private-key" => parsed.private_key = Some(PrivateKey::from_str(&value)?),
+                "--recipient" => {
+                    let recipient = Address::from_str(&value).map_err(|_| anyhow!("Invalid address '{value}'"))?;
+                    parsed.recipient = Some(recipient);
+                }
+                "--amount" => parsed.amount = value.parse()?,
+                "--fee" => parsed.fee = value.parse()?,
+                _ => bail!("Unknown argument '{flag}'"),
+            }
+        }
+        ensure!(parsed.amount > 0, "The --amount to send must be greater than zero");
+        Ok(parsed)
+    }
+}
+
+// The transfer sent by the example
+struct Sent {
+    transaction_id: <N as Network>::TransactionID,
+    recipient: Address<N>,
+    api_client: AleoAPIClient<N>,
+    // The local ledger serving the client, if any, kept alive for the caller
+    _ledger: Option<LocalLedgerClient<N>>,
+}
+
+// Returns the unspent records of the account, as found by scanning the whole chain
+fn unspent_records(api_client: &AleoAPIClient<N>, private_key: &PrivateKey<N>) -> Result<Vec<Record<N, Plaintext<N>>>> {
+    let view_key = ViewKey::try_from(private_key)?;
+    let mut unspent = vec![];
+    for (commitment, record) in api_client.scan(view_key, BlockHeight::GENESIS..=api_client.latest_height()?)? {
+        let serial_number = Record::<N, Plaintext<N>>::serial_number(*private_key, commitment)?;
+        if api_client.find_serial_number_height(serial_number)?.is_none() {
+            unspent.push(record.decrypt(&view_key)?);
+        }
+    }
+    Ok(unspent)
+}
+
+// Returns the record holding the fewest gates that still holds `gates`, removing it from the records
+fn take_record(records: &mut Vec<Record<N, Plaintext<N>>>, gates: u64) -> Result<Record<N, Plaintext<N>>> {
+    let index = records
+        .iter()
+        .enumerate()
+        .filter(|(_, record)| ***record.gates() >= gates)
+        .min_by_key(|(_, record)| ***record.gates())
+        .map(|(index, _)| index)
+        .ok_or_else(|| anyhow!("No unspent record holds {gates} gates"))?;
+    Ok(records.swap_remove(index))
+}
+
+// Send the transfer, returning its ID
+fn run(args: Args) -> Result<Sent> {
+    let (api_client, private_key, ledger) = match args.endpoint {
+        Some(endpoint) => {
+            let private_key =
+                args.private_key.ok_or_else(|| anyhow!("Sending from an endpoint needs a --private-key"))?;
+            (AleoAPIClient::new(&endpoint, "testnet3"), private_key, None)
+        }
+        None => {
+            let rng = &mut rand::thread_rng();
+            let ledger = LocalLedgerClient::<N>::new(PrivateKey::new(rng)?)?;
+            let private_key = args.private_key.map_or_else(|| PrivateKey::new(rng), Ok)?;
+            for amount in [100, 10] {
+                ledger.fund(Address::try_from(private_key)?, amount)?;
+            }
+            (ledger.api_client().clone(), private_key, Some(ledger))
+        }
+    };
+    let recipient = match args.recipient {
+        Some(recipient) => recipient,
+        None => Address::try_from(PrivateKey::<N>::new(&mut rand::thread_rng())?)?,
+    };
+
+    // The fee is paid first, so that the transfer is paid with another record.
+    let mut records = unspent_records(&api_client, &private_key)?;
+    let fee_record = take_record(&mut records, args.fee)?;
+    let input_record = take_record(&mut records, args.amount)?;
+    let program_manager = ProgramManager::new(private_key, api_client.clone());
+    let (amount, fee) = (Microcredits(args.amount), Microcredits(args.fee));
+    let transaction_id = program_manager.transfer(amount, fee, recipient, input_record, fee_record)?;
+    Ok(Sent { transaction_id, recipient, api_client, _ledger: ledger })
+}
+
+fn main() -> Result<()> {
+    let sent = run(Args::parse(std::env::args().skip(1))?)?;
+    println!("Sent transaction {} to {} through {}", sent.transaction_id, sent.recipient, sent.api_client.base_url());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_send_transfer() {
+        let private_key = PrivateKey::<N>::new(&mut rand::thread_rng()).unwrap();
+        let args = Args::parse(args(&["--private-key", &private_key.to_string(), "--amount", "60"])).unwrap();
+        let sent = run(args).unwrap();
+        let transaction = sent.api_client.get_transaction(sent.transaction_id).unwrap();
+        assert_eq!(transaction.id(), sent.transaction_id);
+
+        // The sender is left with the change of the transfer and of the fee, too little for another transfer of 60.
+        let mut records = unspent_records(&sent.api_client, &private_key).unwrap();
+        let mut gates = records.iter().map(|record| ***record.gates()).collect::<Vec<_>>();
+        gates.sort_unstable();
+        assert_eq!(gates, [9, 40]);
+        let error = take_record(&mut records, 60).unwrap_err();
+        assert_eq!(error.to_string(), "No unspent record holds 60 gates");
+    }
+
+    #[test]
+    fn test_send_transfer_invalid_arguments() {
+        let error = Args::parse(args(&["--recipient", "aleo1invalid", "--amount", "1"])).err().unwrap();
+        assert_eq!(error.to_string(), "Invalid address 'aleo1invalid'");
+        let error = Args::parse(args(&["--fee", "1"])).err().unwrap();
+        assert_eq!(error.to_string(), "The --amount to send must be greater than zero");
+        let args = Args::parse(args(&["--endpoint", "http://127.0.0.1:1", "--amount", "1"])).unwrap();
+        let error = run(args).err().unwrap();
+        assert_eq!(error.to_string(), "Sending from an endpoint needs a --private-key");
+    }
+}
@@ -0,0 +1,248 @@
+/*
+ * Aleo SDK C bindings.
+ *
+ * Every function returns an AleoErrorCode. When the code is not AleoErrorCode_Ok, a description of the
+ * failure can be retrieved with aleo_last_error_message() on the same thread.
+ *
+ * Memory rules:
+ * - Objects are handed out as opaque pointers written to an `out` parameter. Each object must be
+ *   released exactly once with its matching free function (aleo_account_free, aleo_client_free).
+ * - Strings returned by the library are NUL-terminated UTF-8 and owned by the caller, who must
+ *   release them with aleo_string_free(). Strings passed into the library remain owned by the caller
+ *   and are only read for the duration of the call.
+ * - `out` parameters are only written on success.
+ * - Passing a pointer that was not produced by this library, or one that was already freed, is
+ *   undefined behavior.
+ * - Panics never cross the boundary; they are reported as AleoErrorCode_Panic.
+ */
+
+#ifndef ALEO_H
+#define ALEO_H
+
+/* This file is generated by cbindgen from the `ffi` module of aleo-rust. Do not edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ Status codes returned by every FFI function
+ */
+typedef enum AleoErrorCode {
+  /*
+   The call succeeded
+   */
+  AleoErrorCode_Ok = 0,
+  /*
+   A required pointer argument was null
+   */
+  AleoErrorCode_NullPointer = 1,
+  /*
+   A string argument was not valid UTF-8
+   */
+  AleoErrorCode_InvalidUtf8 = 2,
+  /*
+   An argument could not be parsed or was rejected
+   */
+  AleoErrorCode_InvalidArgument = 3,
+  /*
+   The request to the Aleo node failed
+   */
+  AleoErrorCode_Network = 4,
+  /*
+   The operation failed for another reason
+   */
+  AleoErrorCode_Failure = 5,
+  /*
+   The library panicked; the panic was caught at the boundary
+   */
+  AleoErrorCode_Panic = 6,
+} AleoErrorCode;
+
+/*
+ An Aleo account: a private key with its derived view key and address
+ */
+typedef struct AleoAccount AleoAccount;
+
+/*
+ A client connected to an Aleo node
+ */
+typedef struct AleoClient AleoClient;
+
+/*
+ Copy the message of the last error raised on this thread into `out`.
+
+ Returns `InvalidArgument` when no error has occurred. The string must be released with
+ `aleo_string_free`.
+
+ # Safety
+
+ `out` must be null or point to writable memory for a `char*`.
+ */
+enum AleoErrorCode aleo_last_error_message(char **out);
+
+/*
+ Release a string returned by this library. Passing null is a no-op.
+
+ # Safety
+
+ `string` must be null or a string returned by this library that has not been freed yet.
+ */
+void aleo_string_free(char *string);
+
+/*
+ Generate a new random account and write it to `out`.
+
+ # Safety
+
+ `out` must be null or point to writable memory for an `AleoAccount*`. The account must be
+ released with `aleo_account_free`.
+ */
+enum AleoErrorCode aleo_account_new(struct AleoAccount **out);
+
+/*
+ Import an account from a private key string and write it to `out`.
+
+ # Safety
+
+ `private_key` must be null or a NUL-terminated string. `out` must be null or point to writable
+ memory for an `AleoAccount*`. The account must be released with `aleo_account_free`.
+ */
+enum AleoErrorCode aleo_account_from_private_key(const char *private_key, struct AleoAccount **out);
+
+/*
+ Write the private key of the account to `out` as a string.
+
+ # Safety
+
+ `account` must be null or a live account. `out` must be null or point to writable memory for a
+ `char*`. The string must be released with `aleo_string_free`.
+ */
+enum AleoErrorCode aleo_account_private_key(const struct AleoAccount *account, char **out);
+
+/*
+ Write the view key of the account to `out` as a string.
+
+ # Safety
+
+ `account` must be null or a live account. `out` must be null or point to writable memory for a
+ `char*`. The string must be released with `aleo_string_free`.
+ */
+enum AleoErrorCode aleo_account_view_key(const struct AleoAccount *account, char **out);
+
+/*
+ Write the address of the account to `out` as a string.
+
+ # Safety
+
+ `account` must be null or a live account. `out` must be null or point to writable memory for a
+ `char*`. The string must be released with `aleo_string_free`.
+ */
+enum AleoErrorCode aleo_account_address(const struct AleoAccount *account, char **out);
+
+/*
+ Release an account. Passing null is a no-op.
+
+ # Safety
+
+ `account` must be null or an account returned by this library that has not been freed yet.
+ */
+void aleo_account_free(struct AleoAccount *account);
+
+/*
+ Create a client for the node at `base_url` and write it to `out`.
+
+ # Safety
+
+ `base_url` must be null or a NUL-terminated string. `out` must be null or point to writable memory
+ for an `AleoClient*`. The client must be released with `aleo_client_free`.
+ */
+enum AleoErrorCode aleo_client_new(const char *base_url, struct AleoClient **out);
+
+/*
+ Write the latest block height known to the node to `out`.
+
+ # Safety
+
+ `client` must be null or a live client. `out` must be null or point to a writable `uint32_t`.
+ */
+enum AleoErrorCode aleo_client_latest_height(const struct AleoClient *client, uint32_t *out);
+
+/*
+ Scan blocks `start_height` (inclusive) to `end_height` (exclusive) for records owned by the view key.
+
+ The result is written to `out` as a JSON array of `{"commitment": "...", "record": "..."}` objects,
+ where each record is the ciphertext string.
+
+ # Safety
+
+ `client` must be null or a live client. `view_key` must be null or a NUL-terminated string. `out`
+ must be null or point to writable memory for a `char*`. The string must be released with
+ `aleo_string_free`.
+ */
+enum AleoErrorCode aleo_client_scan(const struct AleoClient *client,
+                                    const char *view_key,
+                                    uint32_t start_height,
+                                    uint32_t end_height,
+                                    char **out);
+
+/*
+ Broadcast a JSON-encoded transaction and write its transaction ID to `out`.
+
+ # Safety
+
+ `client` must be null or a live client. `transaction` must be null or a NUL-terminated string.
+ `out` must be null or point to writable memory for a `char*`. The string must be released with
+ `aleo_string_free`.
+ */
+enum AleoErrorCode aleo_client_broadcast(const struct AleoClient *client,
+                                         const char *transaction,
+                                         char **out);
+
+/*
+ Release a client. Passing null is a no-op.
+
+ # Safety
+
+ `client` must be null or a client returned by this library that has not been freed yet.
+ */
+void aleo_client_free(struct AleoClient *client);
+
+/*
+ Decrypt a record ciphertext owned by the account and write the plaintext record to `out`.
+
+ # Safety
+
+ `account` must be null or a live account. `ciphertext` must be null or a NUL-terminated string.
+ `out` must be null or point to writable memory for a `char*`. The string must be released with
+ `aleo_string_free`.
+ */
+enum AleoErrorCode aleo_record_decrypt(const struct AleoAccount *account,
+                                       const char *ciphertext,
+                                       char **out);
+
+/*
+ Build a transfer of `amount` gates from the account to `recipient` and write the JSON-encoded
+ transaction to `out`.
+
+ The transfer is funded by the plaintext record `input_record`, and the fee of `fee` gates is paid
+ by the plaintext record `fee_record`. The transaction is not broadcast; pass it to
+ `aleo_client_broadcast` to submit it.
+
+ # Safety
+
+ `client` and `account` must be null or live objects. `recipient`, `input_record`, and `fee_record`
+ must be null or NUL-terminated strings. `out` must be null or point to writable memory for a
+ `char*`. The string must be released with `aleo_string_free`.
+ */
+enum AleoErrorCode aleo_transfer_build(const struct AleoClient *client,
+                                       const struct AleoAccount *account,
+                                       const char *recipient,
+                                       uint64_t amount,
+                                       uint64_t fee,
+                                       const char *input_record,
+                                       const char *fee_record,
+                                       char **out);
+
+#endif /* ALEO_H */
@@ -0,0 +1,7 @@
+#!/usr/bin/env bash
+# Regenerate the C header checked in at include/aleo.h from the `ffi` module, with the cbindgen version of the
+# build dependency: cargo install cbindgen --version 0.24.5 --locked
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+cbindgen --config cbindgen.toml --crate aleo-rust --output include/aleo.h
@@ -0,0 +1,229 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm_console::{
+    account::{Address, PrivateKey, ViewKey},
+    network::Network,
+    program::{Ciphertext, Plaintext, Record},
+    types::Field,
+};
+#[cfg(not(feature = "wasm"))]
+use snarkvm_synthesizer::Block;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Whether a record was spent, as far as the key that found it can tell
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpentStatus {
+    /// No block found so far spends the record
+    Unspent,
+    /// The record was spent in the block at the given height
+    Spent { height: u32 },
+    /// The key that found the record cannot derive its serial number, so whether it was spent is unknown
+    Unknown,
+}
+
+impl fmt::Display for SpentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unspent => write!(f, "unspent"),
+            Self::Spent { height } => write!(f, "spent at height {height}"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// A key that sees the records an account receives, but not the records it spends, e.g. for the auditor of a
+/// treasury
+///
+/// An audit key holds the view key of the account, with which it finds and decrypts the records sent to the
+/// account. A record is marked spent by its serial number, which is derived from the private key, so an audit key
+/// does not learn which records the account spends, and reports their spent status as [`SpentStatus::Unknown`]
+/// rather than guessing it.
+///
+/// Unlike a [`crate::WatchOnlyAccount`], from which program managers and wallets are built that fail to sign at
+/// runtime, an audit key converts into no key that builds transactions, so passing one where a private key is
+/// expected fails to compile:
+///
+/// ```compile_fail
+/// use aleo_rust::{snarkvm::Testnet3, AleoAPIClient, AuditKey, ProgramManager};
+///
+/// fn build(audit_key: AuditKey<Testnet3>, api_client: AleoAPIClient<Testnet3>) -> ProgramManager<Testnet3> {
+///     ProgramManager::new(audit_key, api_client)
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AuditKey<N: Network> {
+    view_key: ViewKey<N>,
+    address: Address<N>,
+}
+
+impl<N: Network> AuditKey<N> {
+    /// Create the audit key of the account of the view key.
+    pub fn new(view_key: ViewKey<N>) -> Self {
+        Self { view_key, address: view_key.to_address() }
+    }
+
+    /// Derive the audit key of an account from its private key, e.g. to hand it to an auditor.
+    pub fn from_private_key(private_key: &PrivateKey<N>) -> Result<Self> {
+        Ok(Self::new(ViewKey::try_from(private_key)?))
+    }
+
+    /// Returns the view key of the account, which does not derive the serial numbers of its records either.
+    pub fn view_key(&self) -> &ViewKey<N> {
+        &self.view_key
+    }
+
+    /// Returns the address of the account.
+    pub fn address(&self) -> Address<N> {
+        self.address
+    }
+
+    /// Returns `true` if the record belongs to the account.
+    pub fn is_owner(&self, record: &Record<N, Ciphertext<N>>) -> bool {
+        record.is_owner(&self.view_key)
+    }
+
+    /// Decrypt a record of the account.
+    pub fn decrypt(&self, record: &Record<N, Ciphertext<N>>) -> Result<Record<N, Plaintext<N>>> {
+        record.decrypt(&self.view_key)
+    }
+
+    /// Returns the records the block creates for the account, in the order of the block.
+    #[cfg(not(feature = "wasm"))]
+    pub fn deposits(&self, block: &Block<N>) -> Result<Vec<Deposit<N>>> {
+        let mut deposits = vec![];
+        for transaction in block.transactions().iter() {
+            for (commitment, record) in transaction.transitions().flat_map(|transition| transition.records()) {
+                if self.is_owner(record) {
+                    let (height, transaction_id, commitment) = (block.height(), transaction.id(), *commitment);
+                    deposits.push(Deposit { height, transaction_id, commitment, record: self.decrypt(record)? });
+                }
+            }
+        }
+        Ok(deposits)
+    }
+}
+
+impl<N: Network> From<ViewKey<N>> for AuditKey<N> {
+    fn from(view_key: ViewKey<N>) -> Self {
+        Self::new(view_key)
+    }
+}
+
+/// A record received by the account of an [`AuditKey`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Deposit<N: Network> {
+    height: u32,
+    transaction_id: N::TransactionID,
+    commitment: Field<N>,
+    record: Record<N, Plaintext<N>>,
+}
+
+impl<N: Network> Deposit<N> {
+    /// Returns the height of the block holding the deposit.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the ID of the transaction that created the record.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the commitment of the record.
+    pub fn commitment(&self) -> Field<N> {
+        self.commitment
+    }
+
+    /// Returns the decrypted record.
+    pub fn record(&self) -> &Record<N, Plaintext<N>> {
+        &self.record
+    }
+
+    /// Returns the gates held by the record.
+    pub fn gates(&self) -> u64 {
+        ***self.record.gates()
+    }
+
+    /// Returns [`SpentStatus::Unknown`], as an audit key cannot tell whether the record was spent.
+    pub fn spent_status(&self) -> SpentStatus {
+        SpentStatus::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_block_with_transactions,
+            sample_output,
+            sample_transaction,
+            sample_transition,
+            CurrentNetwork,
+        },
+        RecordStore,
+    };
+    use snarkvm_console::prelude::Uniform;
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    #[test]
+    fn test_audit_key_deposits() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let audit_key = AuditKey::from_private_key(&private_key).unwrap();
+        assert_eq!(audit_key, AuditKey::from(ViewKey::try_from(&private_key).unwrap()));
+        let stranger = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+
+        // The deposit to the account is found and decrypted, and the record of the stranger is not.
+        let deposit = sample_output(audit_key.address(), 500, rng);
+        let outputs = [sample_output(stranger, 7, rng), deposit.clone()];
+        let fund = sample_transaction([sample_transition(&[Field::rand(rng)], &outputs, rng)]);
+        let transactions = [fund.clone()].into_iter().collect();
+        let block = sample_block_with_transactions(1, genesis_block().hash(), transactions, rng);
+        let deposits = audit_key.deposits(&block).unwrap();
+        let found = deposits.iter().map(|deposit| (deposit.height(), deposit.transaction_id(), deposit.commitment()));
+        assert_eq!(found.collect::<Vec<_>>(), [(1, fund.id(), deposit.0)]);
+        assert_eq!(deposits[0].gates(), 500);
+        assert_eq!(deposits[0].spent_status(), SpentStatus::Unknown);
+
+        // The account spends the record, which the audit key does not see, so the record is reported as unknown
+        // rather than unspent.
+        let serial_number = Record::<N, Plaintext<N>>::serial_number(private_key, deposit.0).unwrap();
+        let change = sample_output(stranger, 500, rng);
+        let spend = sample_transaction([sample_transition(&[serial_number], &[change], rng)]);
+        let block = sample_block_with_transactions(2, block.hash(), [spend].into_iter().collect(), rng);
+        assert!(audit_key.deposits(&block).unwrap().is_empty());
+        let mut records = RecordStore::new();
+        records.insert_watch_only(deposit.0, deposits[0].record().clone(), 1);
+        assert_eq!(records.get(&deposit.0).unwrap().spent_status(), SpentStatus::Unknown);
+        assert_eq!(SpentStatus::Unknown.to_string(), "unknown");
+
+        // Records found with the private key are unspent until a spend is found.
+        records.insert(deposit.0, deposits[0].record().clone(), 1);
+        assert_eq!(records.get(&deposit.0).unwrap().spent_status(), SpentStatus::Unspent);
+        records.mark_spent(&deposit.0, 2);
+        let status = records.get(&deposit.0).unwrap().spent_status();
+        assert_eq!((status, status.to_string()), (SpentStatus::Spent { height: 2 }, "spent at height 2".to_string()));
+    }
+}
@@ -0,0 +1,345 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+use crate::AleoAPIClient;
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+use anyhow::{anyhow, bail};
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+use snarkvm_console::{account::ViewKey, program::ValueType};
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+use snarkvm_synthesizer::{Output, Transition};
+
+use snarkvm_console::{
+    network::Network,
+    program::{Ciphertext, Identifier, Plaintext, ProgramID, Record},
+    types::{Field, Group},
+};
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// A decrypted record, disclosed together with evidence that it is the plaintext of an on-chain record
+///
+/// The disclosure reveals the view key of this record only, which a verifier uses to re-encrypt the
+/// plaintext and compare it with the ciphertext on-chain. The view key of the account stays private.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RecordDisclosure<N: Network> {
+    program_id: ProgramID<N>,
+    record_name: Identifier<N>,
+    record: Record<N, Plaintext<N>>,
+    record_view_key: Field<N>,
+    nonce: Group<N>,
+    commitment: Field<N>,
+    transition_id: N::TransitionID,
+    transaction_id: N::TransactionID,
+    height: u32,
+}
+
+impl<N: Network> RecordDisclosure<N> {
+    /// Disclose the record with the given commitment, which must be owned by the view key
+    #[cfg(not(any(feature = "async", feature = "wasm")))]
+    pub fn create(view_key: &ViewKey<N>, commitment: Field<N>, client: &AleoAPIClient<N>) -> Result<Self> {
+        let transition_id = client.find_transition_id(commitment)?;
+        let transaction_id = client.find_transaction_id(transition_id)?;
+        let transition = Self::find_transition(client, transaction_id, transition_id)?;
+        let (index, ciphertext) = Self::find_output(&transition, commitment)?;
+
+        // The output types of the function name the record, which is part of its commitment.
+        let program = client.get_program(*transition.program_id())?;
+        let function = program.get_function(transition.function_name())?;
+        let record_name = match function.outputs().get_index(index).map(|output| output.value_type()) {
+            Some(ValueType::Record(record_name)) => *record_name,
+            _ => bail!("Output '{commitment}' of transition '{transition_id}' is not a record of its program"),
+        };
+
+        ensure!(ciphertext.is_owner(view_key), "Record '{commitment}' is not owned by the view key");
+        let record_view_key = (*ciphertext.nonce() * **view_key).to_x_coordinate();
+        let record = ciphertext.decrypt_symmetric(&record_view_key)?;
+        let height = client.get_height(client.find_block_hash(transaction_id)?)?.0;
+
+        Ok(Self {
+            program_id: *transition.program_id(),
+            record_name,
+            nonce: *record.nonce(),
+            record,
+            record_view_key,
+            commitment,
+            transition_id,
+            transaction_id,
+            height,
+        })
+    }
+
+    /// Returns the decrypted record
+    pub fn record(&self) -> &Record<N, Plaintext<N>> {
+        &self.record
+    }
+
+    /// Returns the ID of the program that created the record
+    pub fn program_id(&self) -> &ProgramID<N> {
+        &self.program_id
+    }
+
+    /// Returns the name of the record in its program
+    pub fn record_name(&self) -> &Identifier<N> {
+        &self.record_name
+    }
+
+    /// Returns the nonce of the record
+    pub fn nonce(&self) -> Group<N> {
+        self.nonce
+    }
+
+    // Returns the record view key, which opens the ciphertext of the record
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) fn record_view_key(&self) -> Field<N> {
+        self.record_view_key
+    }
+
+    /// Returns the commitment of the record on-chain
+    pub fn commitment(&self) -> Field<N> {
+        self.commitment
+    }
+
+    /// Returns the ID of the transition that created the record
+    pub fn transition_id(&self) -> N::TransitionID {
+        self.transition_id
+    }
+
+    /// Returns the ID of the transaction that created the record
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the height of the block that created the record
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Verify the disclosure against the chain
+    ///
+    /// Checks that the transition, transaction, and block height are those of the commitment on-chain,
+    /// and that the record re-encrypts to the ciphertext created by the transition.
+    #[cfg(not(any(feature = "async", feature = "wasm")))]
+    pub fn verify(&self, client: &AleoAPIClient<N>) -> Result<()> {
+        let commitment = self.commitment;
+        ensure!(
+            client.find_transition_id(commitment)? == self.transition_id,
+            "Record '{commitment}' was not created by transition '{}'",
+            self.transition_id
+        );
+        ensure!(
+            client.find_transaction_id(self.transition_id)? == self.transaction_id,
+            "Transition '{}' is not part of transaction '{}'",
+            self.transition_id,
+            self.transaction_id
+        );
+        let height = client.get_height(client.find_block_hash(self.transaction_id)?)?.0;
+        ensure!(height == self.height, "Record '{commitment}' was created at block {height}, not {}", self.height);
+
+        let transition = Self::find_transition(client, self.transaction_id, self.transition_id)?;
+        ensure!(
+            *transition.program_id() == self.program_id,
+            "Record '{commitment}' was created by program '{}', not '{}'",
+            transition.program_id(),
+            self.program_id
+        );
+        let (_, ciphertext) = Self::find_output(&transition, commitment)?;
+        self.verify_ciphertext(&ciphertext)
+    }
+
+    /// Verify the disclosure against the on-chain ciphertext of its record, without querying the chain
+    ///
+    /// Checks that the record has the disclosed commitment and nonce, and re-encrypts to the ciphertext.
+    pub fn verify_ciphertext(&self, ciphertext: &Record<N, Ciphertext<N>>) -> Result<()> {
+        let commitment = self.commitment;
+        ensure!(
+            self.record.to_commitment(&self.program_id, &self.record_name)? == commitment,
+            "The disclosed record does not match commitment '{commitment}'"
+        );
+        ensure!(
+            *self.record.nonce() == self.nonce && *ciphertext.nonce() == self.nonce,
+            "The nonce of record '{commitment}' does not match the disclosure"
+        );
+        ensure!(
+            self.record.encrypt_symmetric(&self.record_view_key)? == *ciphertext,
+            "The disclosed record does not encrypt to the ciphertext of record '{commitment}'"
+        );
+        Ok(())
+    }
+
+    // Returns the transition with the given ID in the given transaction
+    #[cfg(not(any(feature = "async", feature = "wasm")))]
+    fn find_transition(
+        client: &AleoAPIClient<N>,
+        transaction_id: N::TransactionID,
+        transition_id: N::TransitionID,
+    ) -> Result<Transition<N>> {
+        let transaction = client.get_transaction(transaction_id)?;
+        let transition = transaction.transitions().find(|transition| *transition.id() == transition_id).cloned();
+        match transition {
+            Some(transition) => Ok(transition),
+            None => bail!("Transaction '{transaction_id}' does not contain transition '{transition_id}'"),
+        }
+    }
+
+    // Returns the index and ciphertext of the output record with the given commitment
+    #[cfg(not(any(feature = "async", feature = "wasm")))]
+    fn find_output(transition: &Transition<N>, commitment: Field<N>) -> Result<(usize, Record<N, Ciphertext<N>>)> {
+        let transition_id = transition.id();
+        for (index, output) in transition.outputs().iter().enumerate() {
+            if let Output::Record(output_commitment, _, ciphertext) = output {
+                if *output_commitment == commitment {
+                    let ciphertext = ciphertext.clone().ok_or_else(|| {
+                        anyhow!("Transition '{transition_id}' does not include the ciphertext of record '{commitment}'")
+                    })?;
+                    return Ok((index, ciphertext));
+                }
+            }
+        }
+        bail!("Transition '{transition_id}' does not create record '{commitment}'")
+    }
+}
+
+impl<N: Network> FromStr for RecordDisclosure<N> {
+    type Err = anyhow::Error;
+
+    /// Parse a record disclosure from JSON
+    fn from_str(disclosure: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(disclosure)?)
+    }
+}
+
+impl<N: Network> fmt::Display for RecordDisclosure<N> {
+    /// Serialize a record disclosure to JSON
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_output, sample_transaction, sample_transition, CurrentNetwork, MockResponse, MockServer},
+        testnet3,
+    };
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        prelude::Uniform,
+    };
+    use snarkvm_synthesizer::Program;
+    use snarkvm_utilities::TestRng;
+
+    use std::collections::HashMap;
+
+    type N = CurrentNetwork;
+
+    // Start a mock node serving a transaction at height 42 that creates a record for each of the given owners
+    fn mock_node(owners: &[Address<N>], rng: &mut TestRng) -> (MockServer, Vec<Field<N>>) {
+        let records = owners.iter().map(|owner| sample_output(*owner, 100, rng)).collect::<Vec<_>>();
+        let transition = sample_transition(&[Field::rand(rng)], &records, rng);
+        let transaction = sample_transaction([transition.clone()]);
+        let block_hash = <N as Network>::BlockHash::from(Field::rand(rng));
+
+        let (transition_id, transaction_id) = (transition.id(), transaction.id());
+        let mut responses = HashMap::new();
+        for (commitment, _) in &records {
+            responses.insert(format!("/testnet3/find/transitionID/{commitment}"), format!("\"{transition_id}\""));
+        }
+        responses.insert(format!("/testnet3/find/transactionID/{transition_id}"), format!("\"{transaction_id}\""));
+        responses.insert(format!("/testnet3/transaction/{transaction_id}"), transaction.to_string());
+        responses.insert(format!("/testnet3/find/blockHash/{transaction_id}"), format!("\"{block_hash}\""));
+        responses.insert(format!("/testnet3/height/{block_hash}"), "42".to_string());
+        let credits = serde_json::to_string(&Program::<N>::credits().unwrap()).unwrap();
+        responses.insert("/testnet3/program/credits.aleo".to_string(), credits);
+        let server = MockServer::start(move |request| responses.get(&request.path).map(MockResponse::json));
+        (server, records.into_iter().map(|(commitment, _)| commitment).collect())
+    }
+
+    #[test]
+    fn test_record_disclosure_verifies() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let view_key = ViewKey::try_from(&private_key).unwrap();
+        let address = Address::try_from(&private_key).unwrap();
+        let other_address = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let (node, commitments) = mock_node(&[other_address, address], rng);
+        let client = testnet3(node.base_url());
+
+        let disclosure = RecordDisclosure::create(&view_key, commitments[1], &client).unwrap();
+        assert_eq!(**disclosure.record().owner(), address);
+        assert_eq!(***disclosure.record().gates(), 100);
+        assert_eq!(disclosure.record_name().to_string(), "credits");
+        assert_eq!(disclosure.height(), 42);
+        disclosure.verify(&client).unwrap();
+
+        // The disclosure survives a round trip through JSON.
+        let parsed = RecordDisclosure::<N>::from_str(&disclosure.to_string()).unwrap();
+        assert_eq!(parsed, disclosure);
+        parsed.verify(&client).unwrap();
+
+        // Records of other accounts cannot be disclosed.
+        let error = RecordDisclosure::create(&view_key, commitments[0], &client).unwrap_err();
+        assert_eq!(error.to_string(), format!("Record '{}' is not owned by the view key", commitments[0]));
+    }
+
+    #[test]
+    fn test_record_disclosure_rejects_altered_fields() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let view_key = ViewKey::try_from(&private_key).unwrap();
+        let address = Address::try_from(&private_key).unwrap();
+        let (node, commitments) = mock_node(&[address, address], rng);
+        let client = testnet3(node.base_url());
+        let disclosure = RecordDisclosure::create(&view_key, commitments[0], &client).unwrap();
+        let other = RecordDisclosure::create(&view_key, commitments[1], &client).unwrap();
+        let verify = |disclosure: RecordDisclosure<N>| disclosure.verify(&client).unwrap_err().to_string();
+
+        let mut altered = disclosure.clone();
+        altered.record = other.record.clone();
+        assert!(verify(altered).starts_with("The disclosed record does not match commitment"));
+        let mut altered = disclosure.clone();
+        altered.record_name = Identifier::from_str("token").unwrap();
+        assert!(verify(altered).starts_with("The disclosed record does not match commitment"));
+        let mut altered = disclosure.clone();
+        altered.program_id = ProgramID::from_str("token.aleo").unwrap();
+        assert!(verify(altered).contains("was created by program 'credits.aleo', not 'token.aleo'"));
+        let mut altered = disclosure.clone();
+        altered.nonce = other.nonce;
+        assert!(verify(altered).starts_with("The nonce of record"));
+        let mut altered = disclosure.clone();
+        altered.record_view_key = other.record_view_key;
+        assert!(verify(altered).starts_with("The disclosed record does not encrypt to the ciphertext"));
+        let mut altered = disclosure.clone();
+        altered.height = 41;
+        assert!(verify(altered).contains("was created at block 42, not 41"));
+        let mut altered = disclosure.clone();
+        altered.transition_id = <N as Network>::TransitionID::from(Field::rand(rng));
+        assert!(verify(altered).contains("was not created by transition"));
+        let mut altered = disclosure.clone();
+        altered.transaction_id = <N as Network>::TransactionID::from(Field::rand(rng));
+        assert!(verify(altered).contains("is not part of transaction"));
+
+        // Swapping the commitment points the disclosure at a record whose ciphertext does not match.
+        let mut altered = disclosure;
+        altered.commitment = other.commitment;
+        assert!(verify(altered).starts_with("The disclosed record does not match commitment"));
+    }
+}
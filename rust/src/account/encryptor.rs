@@ -15,18 +15,22 @@
 // along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
 
 use snarkvm_console::{
-    account::PrivateKey,
+    account::{PrivateKey, ViewKey},
     network::Network,
+    prelude::{FromBits, ToBits, ToField},
     program::{Ciphertext, Identifier, Literal, Plaintext},
-    types::Field,
+    types::{Field, Scalar},
 };
 use snarkvm_utilities::Uniform;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use once_cell::sync::OnceCell;
 use std::{iter::FromIterator, str::FromStr};
 
 /// Tool for encrypting and decrypting Aleo key material into ciphertext
+///
+/// The last field of each ciphertext authenticates the others under the secret, so a wrong secret or a tampered
+/// ciphertext is detected before anything is decrypted.
 pub struct Encryptor<N: Network> {
     _phantom: std::marker::PhantomData<N>,
 }
@@ -38,9 +42,23 @@ impl<N: Network> Encryptor<N> {
     }
 
     /// Decrypt a private key from ciphertext using a secret
+    ///
+    /// Fails if the secret is wrong or the ciphertext was tampered with.
     pub fn decrypt_private_key_with_secret(ciphertext: &Ciphertext<N>, secret: &str) -> Result<PrivateKey<N>> {
-        let seed = Self::decrypt_field(ciphertext, secret, "private_key")?;
-        PrivateKey::try_from(seed)
+        PrivateKey::try_from(Self::decrypt_field(ciphertext, secret, "private_key")?)
+    }
+
+    /// Encrypt a view key into ciphertext using a secret
+    pub fn encrypt_view_key_with_secret(view_key: &ViewKey<N>, secret: &str) -> Result<Ciphertext<N>> {
+        Self::encrypt_field(&view_key.to_field()?, secret, "view_key")
+    }
+
+    /// Decrypt a view key from ciphertext using a secret
+    ///
+    /// Fails if the secret is wrong or the ciphertext was tampered with.
+    pub fn decrypt_view_key_with_secret(ciphertext: &Ciphertext<N>, secret: &str) -> Result<ViewKey<N>> {
+        let field = Self::decrypt_field(ciphertext, secret, "view_key")?;
+        Ok(ViewKey::from_scalar(Scalar::from_bits_le(&field.to_bits_le())?))
     }
 
     // Encrypted a field element into a ciphertext representation
@@ -63,13 +81,27 @@ impl<N: Network> Encryptor<N> {
             ]),
             OnceCell::new(),
         );
-        plaintext.encrypt_symmetric(secret)
+        let ciphertext = plaintext.encrypt_symmetric(secret)?;
+
+        // Append the checksum authenticating the ciphertext under the secret.
+        let checksum = Self::checksum(&ciphertext, domain, secret)?;
+        Ciphertext::try_from([&ciphertext[..], &[checksum]].concat())
     }
 
     // Recover a field element encrypted within ciphertext
     fn decrypt_field(ciphertext: &Ciphertext<N>, secret: &str, domain: &str) -> Result<Field<N>> {
         let domain = Field::<N>::new_domain_separator(domain);
         let secret = Field::<N>::new_domain_separator(secret);
+
+        // Check the ciphertext against its checksum before decrypting it, as snarkVM may panic on the garbage
+        // decrypted with a wrong secret.
+        let (checksum, ciphertext) = ciphertext.split_last().ok_or_else(|| anyhow!("The ciphertext is empty"))?;
+        let ciphertext = Ciphertext::try_from(ciphertext.to_vec())?;
+        ensure!(
+            Self::checksum(&ciphertext, domain, secret)? == *checksum,
+            "Failed to decrypt the ciphertext with the given secret"
+        );
+
         let decrypted = ciphertext.decrypt_symmetric(secret)?;
         let recovered_key = Self::extract_value(&decrypted, "key")?;
         let recovered_nonce = Self::extract_value(&decrypted, "nonce")?;
@@ -77,6 +109,12 @@ impl<N: Network> Encryptor<N> {
         Ok(recovered_key / recovered_blinding)
     }
 
+    // Compute the checksum of a ciphertext, a hash of its fields keyed with the secret
+    fn checksum(ciphertext: &Ciphertext<N>, domain: Field<N>, secret: Field<N>) -> Result<Field<N>> {
+        let checksum_domain = Field::<N>::new_domain_separator("AleoEncryptorChecksum0");
+        N::hash_psd8(&[&[checksum_domain, domain, secret], &ciphertext[..]].concat())
+    }
+
     // Extract a field element from a plaintext
     fn extract_value(plaintext: &Plaintext<N>, identifier: &str) -> Result<Field<N>> {
         let identity = Identifier::from_str(identifier)?;
@@ -95,7 +133,7 @@ impl<N: Network> Encryptor<N> {
 mod tests {
     use super::*;
 
-    use snarkvm_console::network::Testnet3 as CurrentNetwork;
+    use snarkvm_console::{network::Testnet3 as CurrentNetwork, prelude::One};
     use snarkvm_utilities::TestRng;
 
     #[test]
@@ -108,6 +146,15 @@ mod tests {
         assert_eq!(private_key, recovered_private_key);
     }
 
+    #[test]
+    fn test_encryptor_encrypt_and_decrypt_view_key() {
+        let mut rng = TestRng::default();
+        let view_key = ViewKey::try_from(PrivateKey::<CurrentNetwork>::new(&mut rng).unwrap()).unwrap();
+        let enc = Encryptor::encrypt_view_key_with_secret(&view_key, "mypassword").unwrap();
+        assert_eq!(Encryptor::decrypt_view_key_with_secret(&enc, "mypassword").unwrap(), view_key);
+        assert!(Encryptor::decrypt_view_key_with_secret(&enc, "wrong_password").is_err());
+    }
+
     #[test]
     fn test_encryptor_wrong_private_key_doesnt_decrypt() {
         let mut rng = TestRng::default();
@@ -118,6 +165,53 @@ mod tests {
         assert!(recovered_private_key.is_err())
     }
 
+    #[test]
+    fn test_encryptor_wrong_secrets_fail_without_panicking() {
+        let mut rng = TestRng::default();
+        let private_key = PrivateKey::<CurrentNetwork>::new(&mut rng).unwrap();
+        let view_key = ViewKey::try_from(private_key).unwrap();
+        let private_enc = Encryptor::encrypt_private_key_with_secret(&private_key, "mypassword").unwrap();
+        let view_enc = Encryptor::encrypt_view_key_with_secret(&view_key, "mypassword").unwrap();
+
+        // A wrong secret fails the checksum, before anything is decrypted.
+        for attempt in 0..64 {
+            let secret = format!("wrong_password_{attempt}");
+            let error = Encryptor::decrypt_private_key_with_secret(&private_enc, &secret).unwrap_err();
+            assert_eq!(error.to_string(), "Failed to decrypt the ciphertext with the given secret");
+            assert!(Encryptor::decrypt_view_key_with_secret(&view_enc, &secret).is_err());
+        }
+    }
+
+    #[test]
+    fn test_encryptor_decrypts_known_ciphertext() {
+        let private_key =
+            PrivateKey::<CurrentNetwork>::from_str("APrivateKey1zkpAYS46Dq4rnt9wdohyWMwdmjmTeMJKPZdp5AhvjXZDsVG")
+                .unwrap();
+        let ciphertext = Ciphertext::<CurrentNetwork>::from_str("ciphertext1qsqg7rgvam3xdcu55pw6m3v8zs4mt66nja3wvr7mxs5sq58g7c4yvrvqgnu8dczjj7nsq2yd03fzv9kke0z9puwd7c3s2vppcndsq26wpenr8g3fjv2xd92akrtwd8lxlu6tw87sczzg2r52n4fxr2z59jgq92hruanrywrgnty9j9x0jusyswepru2lffcdl02qkmywhy30j3ctty8zq4").unwrap();
+        assert_eq!(Encryptor::decrypt_private_key_with_secret(&ciphertext, "mypassword").unwrap(), private_key);
+        assert!(Encryptor::decrypt_private_key_with_secret(&ciphertext, "badpassword").is_err());
+    }
+
+    #[test]
+    fn test_encryptor_rejects_tampered_ciphertext() {
+        let mut rng = TestRng::default();
+        let private_key = PrivateKey::<CurrentNetwork>::new(&mut rng).unwrap();
+        let enc = Encryptor::encrypt_private_key_with_secret(&private_key, "mypassword").unwrap();
+
+        // Changing any field of the ciphertext, its checksum included, fails the checksum.
+        for index in 0..enc.len() {
+            let mut fields = enc.to_vec();
+            fields[index] += Field::one();
+            let tampered = Ciphertext::try_from(fields).unwrap();
+            let error = Encryptor::decrypt_private_key_with_secret(&tampered, "mypassword").unwrap_err();
+            assert_eq!(error.to_string(), "Failed to decrypt the ciphertext with the given secret");
+        }
+
+        // Dropping the checksum fails as well.
+        let truncated = Ciphertext::try_from(enc[..enc.len() - 1].to_vec()).unwrap();
+        assert!(Encryptor::decrypt_private_key_with_secret(&truncated, "mypassword").is_err());
+    }
+
     #[test]
     fn test_encryptor_same_secret_doesnt_produce_same_ciphertext_on_different_runs() {
         let mut rng = TestRng::default();
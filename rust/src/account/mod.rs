@@ -14,5 +14,23 @@
 // You should have received a copy of the GNU General Public License
 // along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
 
+pub mod audit_key;
+pub use audit_key::*;
+
+pub mod disclosure;
+pub use disclosure::*;
+
 pub mod encryptor;
 pub use encryptor::*;
+
+pub mod network_format;
+pub use network_format::*;
+
+pub mod ownership;
+pub use ownership::*;
+
+pub mod typed_data;
+pub use typed_data::*;
+
+pub mod watch_only;
+pub use watch_only::*;
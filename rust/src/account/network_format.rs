@@ -0,0 +1,236 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm_console::network::Network;
+
+use std::{error::Error, fmt};
+
+/// The prefixes with which the addresses, private keys, and view keys of a network are encoded
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AccountFormat {
+    /// The name of the network, as used by the command line tools, e.g. `testnet3`
+    pub network: &'static str,
+    /// The prefix of addresses, which is their bech32m human-readable part and separator
+    pub address_prefix: &'static str,
+    /// The prefix of private keys
+    pub private_key_prefix: &'static str,
+    /// The prefix of view keys
+    pub view_key_prefix: &'static str,
+}
+
+impl AccountFormat {
+    // Returns what the value is, e.g. `address`, if it starts with a prefix of the format
+    fn item_of(&self, value: &str) -> Option<&'static str> {
+        if value.starts_with(self.address_prefix) {
+            Some("address")
+        } else if value.starts_with(self.private_key_prefix) {
+            Some("private key")
+        } else if value.starts_with(self.view_key_prefix) {
+            Some("view key")
+        } else {
+            None
+        }
+    }
+}
+
+/// The account formats of the networks known to the SDK
+///
+/// snarkVM encodes the accounts of every network alike, so that an address or key does not tell which network it
+/// is for, and is accepted by clients of any network. Transfers to such addresses may be confirmed with
+/// [`crate::SpendingPolicy::with_network_confirmation`] instead.
+pub const ACCOUNT_FORMATS: &[AccountFormat] = &[
+    AccountFormat {
+        network: "testnet3",
+        address_prefix: "aleo1",
+        private_key_prefix: "APrivateKey1",
+        view_key_prefix: "AViewKey1",
+    },
+    AccountFormat {
+        network: "mainnet",
+        address_prefix: "aleo1",
+        private_key_prefix: "APrivateKey1",
+        view_key_prefix: "AViewKey1",
+    },
+];
+
+/// The error returned when an address, key, or file is for another network than the one of the client
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrongNetworkAddress {
+    item: String,
+    expected: String,
+    found: String,
+}
+
+impl WrongNetworkAddress {
+    pub(crate) fn new(item: &str, expected: &str, found: &str) -> Self {
+        Self { item: item.to_string(), expected: expected.to_string(), found: found.to_string() }
+    }
+
+    /// Returns what is for another network, e.g. `address` or `file`.
+    pub fn item(&self) -> &str {
+        &self.item
+    }
+
+    /// Returns the network of the client.
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// Returns the network the item is for.
+    pub fn found(&self) -> &str {
+        &self.found
+    }
+}
+
+impl fmt::Display for WrongNetworkAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The {} is for the '{}' network, but this client is for '{}'", self.item, self.found, self.expected)
+    }
+}
+
+impl Error for WrongNetworkAddress {}
+
+/// Returns the name of the network of `N` used by the command line tools, e.g. `testnet3` for "Aleo Testnet 3".
+pub fn network_name<N: Network>() -> String {
+    N::NAME.trim_start_matches("Aleo ").replace(' ', "").to_lowercase()
+}
+
+/// Check that an address, private key, or view key is encoded in the account format of the network of `N`.
+///
+/// Values whose prefix only belongs to the formats of other networks fail with [`WrongNetworkAddress`]. Values in
+/// the format of `N` pass, even when other networks share it, and so do values in no known format, for their parser
+/// to reject.
+pub fn check_account_format<N: Network>(value: &str) -> Result<(), WrongNetworkAddress> {
+    check_format(ACCOUNT_FORMATS, &network_name::<N>(), value)
+}
+
+/// Returns `true` if other networks encode their addresses as the network of `N` does, so that an address does not
+/// tell which network it is for.
+pub fn is_shared_address_format<N: Network>() -> bool {
+    let network = network_name::<N>();
+    ACCOUNT_FORMATS.iter().filter(|format| format.network == network).any(|format| {
+        ACCOUNT_FORMATS.iter().any(|other| other.network != network && other.address_prefix == format.address_prefix)
+    })
+}
+
+/// Check that a network named by a file, e.g. in the `NETWORK` variable of a Leo `.env` file, is the network of
+/// `N`, failing with [`WrongNetworkAddress`] otherwise.
+pub fn check_network_name<N: Network>(item: &str, network: &str) -> Result<(), WrongNetworkAddress> {
+    let (expected, network) = (network_name::<N>(), network.trim());
+    match network.eq_ignore_ascii_case(&expected) {
+        true => Ok(()),
+        false => Err(WrongNetworkAddress::new(item, &expected, network)),
+    }
+}
+
+// Check a value against the formats, for a client of the expected network. Networks without a known format accept
+// every value.
+fn check_format(formats: &[AccountFormat], expected: &str, value: &str) -> Result<(), WrongNetworkAddress> {
+    if !formats.iter().any(|format| format.network == expected) {
+        return Ok(());
+    }
+    let value = value.trim();
+    let mut found = None;
+    for format in formats {
+        if let Some(item) = format.item_of(value) {
+            if format.network == expected {
+                return Ok(());
+            }
+            found.get_or_insert((item, format.network));
+        }
+    }
+    match found {
+        Some((item, network)) => Err(WrongNetworkAddress::new(item, expected, network)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm_console::network::Testnet3;
+
+    type N = Testnet3;
+
+    const ADDRESS: &str = "aleo18x0yenrkceapvt85e6aqw2v8hq37hpt4ew6k6cgum6xlpmaxt5xqwnkuja";
+    const PRIVATE_KEY: &str = "APrivateKey1zkp5fCUVzS9b7my34CdraHBF9XzB58xYiPzFJQvjhmvv7A8";
+    const VIEW_KEY: &str = "AViewKey1oRpmvXibMYHar5JcsLp4sSirWpc9SFgXZrTSte9ce5D3";
+
+    // The formats of the SDK, and of a network with a format of its own, as snarkVM may add
+    const FORMATS: &[AccountFormat] = &[ACCOUNT_FORMATS[0], ACCOUNT_FORMATS[1], AccountFormat {
+        network: "canary",
+        address_prefix: "ac1",
+        private_key_prefix: "ACPrivateKey1",
+        view_key_prefix: "ACViewKey1",
+    }];
+
+    // A value, the network of the client, and the item and network the value is rejected for
+    type Case<'a> = (&'a str, &'a str, Option<(&'a str, &'a str)>);
+
+    #[test]
+    fn test_check_account_format_matrix() {
+        let canary_address = ADDRESS.replacen("aleo1", "ac1", 1);
+        let canary_private_key = PRIVATE_KEY.replacen("APrivateKey1", "ACPrivateKey1", 1);
+        let canary_view_key = VIEW_KEY.replacen("AViewKey1", "ACViewKey1", 1);
+
+        // The values of each network, against a client of each network.
+        let matrix: &[Case] = &[
+            (ADDRESS, "testnet3", None),
+            (ADDRESS, "mainnet", None),
+            (ADDRESS, "canary", Some(("address", "testnet3"))),
+            (PRIVATE_KEY, "testnet3", None),
+            (PRIVATE_KEY, "mainnet", None),
+            (PRIVATE_KEY, "canary", Some(("private key", "testnet3"))),
+            (VIEW_KEY, "testnet3", None),
+            (VIEW_KEY, "mainnet", None),
+            (VIEW_KEY, "canary", Some(("view key", "testnet3"))),
+            (&canary_address, "testnet3", Some(("address", "canary"))),
+            (&canary_address, "mainnet", Some(("address", "canary"))),
+            (&canary_address, "canary", None),
+            (&canary_private_key, "testnet3", Some(("private key", "canary"))),
+            (&canary_private_key, "canary", None),
+            (&canary_view_key, "mainnet", Some(("view key", "canary"))),
+            (&canary_view_key, "canary", None),
+            // Values in no known format are left to their parser, as are the values of unknown networks.
+            ("record1qyqsq", "testnet3", None),
+            (&canary_address, "private", None),
+        ];
+        for (value, client, rejected) in matrix {
+            let result = check_format(FORMATS, client, value);
+            match rejected {
+                None => assert!(result.is_ok(), "{value} on {client}: {result:?}"),
+                Some((item, found)) => assert_eq!(result, Err(WrongNetworkAddress::new(item, client, found))),
+            }
+        }
+
+        let error = check_format(FORMATS, "testnet3", &canary_address).unwrap_err();
+        assert_eq!(error.to_string(), "The address is for the 'canary' network, but this client is for 'testnet3'");
+    }
+
+    #[test]
+    fn test_check_account_format_of_network() {
+        // Every format of snarkVM is shared, so the values of every known network pass.
+        assert_eq!(network_name::<N>(), "testnet3");
+        assert!(is_shared_address_format::<N>());
+        for value in [ADDRESS, PRIVATE_KEY, VIEW_KEY] {
+            assert!(check_account_format::<N>(value).is_ok());
+        }
+
+        assert!(check_network_name::<N>("file", " TestNet3 ").is_ok());
+        let error = check_network_name::<N>("file", "mainnet").unwrap_err();
+        assert_eq!((error.item(), error.expected(), error.found()), ("file", "testnet3", "mainnet"));
+    }
+}
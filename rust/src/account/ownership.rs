@@ -0,0 +1,437 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+use crate::{api::is_not_found, AleoAPIClient};
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+use anyhow::bail;
+
+use snarkvm_console::{
+    account::{Address, ComputeKey, PrivateKey, Signature},
+    network::Network,
+    prelude::{FromBits, SizeInDataBits},
+    program::{Identifier, Plaintext, ProgramID, Record},
+    types::{Field, Group, Scalar},
+};
+use snarkvm_utilities::{bits_from_bytes_le, Uniform};
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt, str::FromStr};
+
+/// Proof that an address owned a set of unspent `credits.aleo` records at a block height
+///
+/// The proof reveals the records to the verifier, but neither the private key nor the view key.
+/// Each record carries a proof that its serial number was derived from the owner's key, so the
+/// verifier can check on-chain whether the record was spent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "", try_from = "UncheckedProof<N>")]
+pub struct ProofOfOwnership<N: Network> {
+    height: u32,
+    challenge: Vec<u8>,
+    records: Vec<OwnedRecord<N>>,
+    signature: Signature<N>,
+}
+
+// A parsed proof of ownership, before its records are checked to be distinct
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct UncheckedProof<N: Network> {
+    height: u32,
+    challenge: Vec<u8>,
+    records: Vec<OwnedRecord<N>>,
+    signature: Signature<N>,
+}
+
+impl<N: Network> TryFrom<UncheckedProof<N>> for ProofOfOwnership<N> {
+    type Error = anyhow::Error;
+
+    fn try_from(proof: UncheckedProof<N>) -> Result<Self> {
+        let UncheckedProof { height, challenge, records, signature } = proof;
+        Self::ensure_distinct(records.iter().map(|owned| &owned.record))?;
+        Ok(Self { height, challenge, records, signature })
+    }
+}
+
+/// A record in a proof of ownership, with a proof of its serial number
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct OwnedRecord<N: Network> {
+    record: Record<N, Plaintext<N>>,
+    gamma: Group<N>,
+    dleq_challenge: Scalar<N>,
+    dleq_response: Scalar<N>,
+}
+
+impl<N: Network> ProofOfOwnership<N> {
+    /// Prove ownership of the given `credits` records at `height`, bound to the verifier's challenge
+    pub fn generate(
+        private_key: &PrivateKey<N>,
+        records: &[Record<N, Plaintext<N>>],
+        height: u32,
+        challenge: &[u8],
+    ) -> Result<Self> {
+        ensure!(!records.is_empty(), "A proof of ownership requires at least one record");
+        Self::ensure_distinct(records)?;
+        let rng = &mut rand::thread_rng();
+        let address = Address::try_from(private_key)?;
+        let pk_sig = ComputeKey::try_from(private_key)?.pk_sig();
+        let sk_sig = private_key.sk_sig();
+
+        let records = records
+            .iter()
+            .map(|record| {
+                ensure!(**record.owner() == address, "Record is not owned by the private key");
+                let commitment = Self::commitment(record)?;
+
+                // Compute `gamma` as in the serial number derivation.
+                let h = Self::serial_number_generator(commitment)?;
+                let gamma = h * sk_sig;
+
+                // Prove that `gamma` and `pk_sig` share the discrete logarithm `sk_sig`.
+                let nonce = Scalar::rand(rng);
+                let (a, b) = (N::g_scalar_multiply(&nonce), h * nonce);
+                let dleq_challenge = Self::dleq_challenge(commitment, pk_sig, gamma, a, b)?;
+                let dleq_response = nonce + dleq_challenge * sk_sig;
+
+                Ok(OwnedRecord { record: record.clone(), gamma, dleq_challenge, dleq_response })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let message = Self::message(height, challenge, &records)?;
+        let signature = Signature::sign(private_key, &message, rng)?;
+        Ok(Self { height, challenge: challenge.to_vec(), records, signature })
+    }
+
+    /// Returns the block height the proof refers to
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the challenge the proof is bound to
+    pub fn challenge(&self) -> &[u8] {
+        &self.challenge
+    }
+
+    /// Returns the records included in the proof
+    pub fn records(&self) -> impl Iterator<Item = &Record<N, Plaintext<N>>> {
+        self.records.iter().map(|owned| &owned.record)
+    }
+
+    /// Returns the total number of gates held by the records in the proof
+    ///
+    /// Proofs listing a record more than once are rejected when generated or parsed, so no record
+    /// is counted twice.
+    pub fn gates(&self) -> u64 {
+        self.records().map(|record| ***record.gates()).sum()
+    }
+
+    /// Verify the proof against the chain and return the total number of gates it proves
+    ///
+    /// Checks the signature and serial number proofs, that every record was created at or before
+    /// the height of the proof, and that none were spent by then. The caller is responsible for
+    /// checking that [`ProofOfOwnership::challenge`] is the challenge it issued.
+    #[cfg(not(any(feature = "async", feature = "wasm")))]
+    pub fn verify(address: &Address<N>, proof: &Self, client: &AleoAPIClient<N>) -> Result<u64> {
+        proof.verify_signatures(address)?;
+
+        for owned in &proof.records {
+            let commitment = Self::commitment(&owned.record)?;
+            match Self::find_height(client, commitment)? {
+                Some(height) if height <= proof.height => (),
+                Some(height) => {
+                    bail!("Record '{commitment}' was created at block {height}, after block {}", proof.height)
+                }
+                None => bail!("Record '{commitment}' was not found on-chain"),
+            }
+
+            let serial_number = Record::<N, Plaintext<N>>::serial_number_from_gamma(&owned.gamma, commitment)?;
+            if let Some(height) = Self::find_height(client, serial_number)? {
+                if height <= proof.height {
+                    bail!("Record '{commitment}' was spent at block {height}");
+                }
+            }
+        }
+        Ok(proof.gates())
+    }
+
+    /// Verify the signature and the serial number proofs without querying the chain
+    pub fn verify_signatures(&self, address: &Address<N>) -> Result<()> {
+        let message = Self::message(self.height, &self.challenge, &self.records)?;
+        ensure!(self.signature.verify(address, &message), "Invalid signature for the proof of ownership");
+
+        Self::ensure_distinct(self.records())?;
+
+        // The signature binds its compute key to the address.
+        let pk_sig = self.signature.compute_key().pk_sig();
+        for owned in &self.records {
+            ensure!(**owned.record.owner() == *address, "Record is not owned by the address");
+            let commitment = Self::commitment(&owned.record)?;
+            let h = Self::serial_number_generator(commitment)?;
+            let (challenge, response) = (owned.dleq_challenge, owned.dleq_response);
+            let a = N::g_scalar_multiply(&response) - pk_sig * challenge;
+            let b = h * response - owned.gamma * challenge;
+            ensure!(
+                Self::dleq_challenge(commitment, pk_sig, owned.gamma, a, b)? == challenge,
+                "Invalid serial number proof for record '{commitment}'"
+            );
+        }
+        Ok(())
+    }
+
+    // Returns the height of the block containing the given input or output ID, if it is on-chain
+    #[cfg(not(any(feature = "async", feature = "wasm")))]
+    fn find_height(client: &AleoAPIClient<N>, input_or_output_id: Field<N>) -> Result<Option<u32>> {
+        let transition_id = match client.find_transition_id(input_or_output_id) {
+            Ok(transition_id) => transition_id,
+            Err(error) if is_not_found(&error) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let transaction_id = client.find_transaction_id(transition_id)?;
+        let block_hash = client.find_block_hash(transaction_id)?;
+        Ok(Some(client.get_height(block_hash)?.0))
+    }
+
+    // Ensure that no record is included twice, which would count its gates twice
+    fn ensure_distinct<'a>(records: impl IntoIterator<Item = &'a Record<N, Plaintext<N>>>) -> Result<()> {
+        let mut commitments = HashSet::new();
+        for record in records {
+            let commitment = Self::commitment(record)?;
+            ensure!(commitments.insert(commitment), "Record '{commitment}' is included more than once");
+        }
+        Ok(())
+    }
+
+    // Compute the commitment of a `credits.aleo` record
+    fn commitment(record: &Record<N, Plaintext<N>>) -> Result<Field<N>> {
+        record.to_commitment(&ProgramID::from_str("credits.aleo")?, &Identifier::from_str("credits")?)
+    }
+
+    // Compute the generator `H` used to derive the serial number of a record
+    fn serial_number_generator(commitment: Field<N>) -> Result<Group<N>> {
+        N::hash_to_group_psd2(&[N::serial_number_domain(), commitment])
+    }
+
+    // Compute the Fiat-Shamir challenge of a serial number proof
+    fn dleq_challenge(
+        commitment: Field<N>,
+        pk_sig: Group<N>,
+        gamma: Group<N>,
+        a: Group<N>,
+        b: Group<N>,
+    ) -> Result<Scalar<N>> {
+        let domain = Field::<N>::new_domain_separator("AleoProofOfOwnershipSerialNumber0");
+        let points = [pk_sig, gamma, a, b].map(|point| point.to_x_coordinate());
+        N::hash_to_scalar_psd8(&[&[domain, commitment][..], &points].concat())
+    }
+
+    // Construct the message signed by the proof
+    fn message(height: u32, challenge: &[u8], records: &[OwnedRecord<N>]) -> Result<Vec<Field<N>>> {
+        let mut message = vec![Field::new_domain_separator("AleoProofOfOwnership0"), Field::from_u32(height)];
+        message.push(Field::from_u64(challenge.len() as u64));
+        let challenge_bits = bits_from_bytes_le(challenge).collect::<Vec<_>>();
+        for chunk in challenge_bits.chunks(Field::<N>::size_in_data_bits()) {
+            message.push(Field::from_bits_le(chunk)?);
+        }
+        for owned in records {
+            message.push(Self::commitment(&owned.record)?);
+            message.push(owned.gamma.to_x_coordinate());
+        }
+        Ok(message)
+    }
+}
+
+impl<N: Network> FromStr for ProofOfOwnership<N> {
+    type Err = anyhow::Error;
+
+    /// Parse a proof of ownership from JSON
+    fn from_str(proof: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(proof)?)
+    }
+}
+
+impl<N: Network> fmt::Display for ProofOfOwnership<N> {
+    /// Serialize a proof of ownership to JSON
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_record, CurrentNetwork, MockResponse, MockServer},
+        testnet3,
+    };
+    use snarkvm_utilities::TestRng;
+
+    use std::collections::HashMap;
+
+    type N = CurrentNetwork;
+    type PlaintextRecord = Record<N, Plaintext<N>>;
+
+    const CHALLENGE: &[u8] = b"exchange-audit-2023-03-01";
+
+    // Start a mock node that reports each of the given input or output IDs as included at a height
+    fn mock_node(ids: &[(Field<N>, u32)], rng: &mut TestRng) -> MockServer {
+        let mut responses = HashMap::new();
+        for (id, height) in ids {
+            let transition_id = <N as Network>::TransitionID::from(Field::rand(rng));
+            let transaction_id = <N as Network>::TransactionID::from(Field::rand(rng));
+            let block_hash = <N as Network>::BlockHash::from(Field::rand(rng));
+            responses.insert(format!("/testnet3/find/transitionID/{id}"), format!("\"{transition_id}\""));
+            responses.insert(format!("/testnet3/find/transactionID/{transition_id}"), format!("\"{transaction_id}\""));
+            responses.insert(format!("/testnet3/find/blockHash/{transaction_id}"), format!("\"{block_hash}\""));
+            responses.insert(format!("/testnet3/height/{block_hash}"), height.to_string());
+        }
+        MockServer::start(move |request| responses.get(&request.path).map(MockResponse::json))
+    }
+
+    // Sample an account with two records and a proof of ownership over them at height 100
+    fn sample_proof(rng: &mut TestRng) -> (PrivateKey<N>, Vec<PlaintextRecord>, ProofOfOwnership<N>) {
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let address = Address::try_from(&private_key).unwrap();
+        let records = vec![sample_record(address, 100, rng).0, sample_record(address, 250, rng).0];
+        let proof = ProofOfOwnership::generate(&private_key, &records, 100, CHALLENGE).unwrap();
+        (private_key, records, proof)
+    }
+
+    // Compute the serial number of a record
+    fn serial_number(private_key: &PrivateKey<N>, record: &PlaintextRecord) -> Field<N> {
+        let commitment = ProofOfOwnership::commitment(record).unwrap();
+        PlaintextRecord::serial_number(*private_key, commitment).unwrap()
+    }
+
+    #[test]
+    fn test_proof_of_ownership_verifies() {
+        let rng = &mut TestRng::default();
+        let (private_key, records, proof) = sample_proof(rng);
+        let address = Address::try_from(&private_key).unwrap();
+        let commitments = records.iter().map(|record| ProofOfOwnership::commitment(record).unwrap());
+        let commitments = commitments.collect::<Vec<_>>();
+
+        // The first record is spent after the height of the proof, which does not invalidate it.
+        let node = mock_node(
+            &[(commitments[0], 10), (commitments[1], 100), (serial_number(&private_key, &records[0]), 101)],
+            rng,
+        );
+        let gates = ProofOfOwnership::verify(&address, &proof, &testnet3(node.base_url())).unwrap();
+        assert_eq!(gates, 350);
+        assert_eq!(proof.challenge(), CHALLENGE);
+
+        // The proof survives a round trip through JSON.
+        let json = proof.to_string();
+        let parsed = ProofOfOwnership::<N>::from_str(&json).unwrap();
+        assert_eq!(parsed, proof);
+        assert_eq!(ProofOfOwnership::verify(&address, &parsed, &testnet3(node.base_url())).unwrap(), 350);
+    }
+
+    #[test]
+    fn test_proof_of_ownership_rejects_spent_record() {
+        let rng = &mut TestRng::default();
+        let (private_key, records, proof) = sample_proof(rng);
+        let address = Address::try_from(&private_key).unwrap();
+        let commitments = records.iter().map(|record| ProofOfOwnership::commitment(record).unwrap());
+        let commitments = commitments.collect::<Vec<_>>();
+
+        // The second record was spent at the height of the proof.
+        let node = mock_node(
+            &[(commitments[0], 10), (commitments[1], 20), (serial_number(&private_key, &records[1]), 100)],
+            rng,
+        );
+        let error = ProofOfOwnership::verify(&address, &proof, &testnet3(node.base_url())).unwrap_err();
+        assert_eq!(error.to_string(), format!("Record '{}' was spent at block 100", commitments[1]));
+
+        // Records created after the height of the proof, or not on-chain at all, are rejected.
+        let node = mock_node(&[(commitments[0], 10), (commitments[1], 101)], rng);
+        let error = ProofOfOwnership::verify(&address, &proof, &testnet3(node.base_url())).unwrap_err();
+        assert!(error.to_string().contains("was created at block 101"), "{error}");
+        let node = mock_node(&[(commitments[0], 10)], rng);
+        let error = ProofOfOwnership::verify(&address, &proof, &testnet3(node.base_url())).unwrap_err();
+        assert!(error.to_string().contains("was not found on-chain"), "{error}");
+    }
+
+    #[test]
+    fn test_proof_of_ownership_rejects_forgeries() {
+        let rng = &mut TestRng::default();
+        let (private_key, _, proof) = sample_proof(rng);
+        let address = Address::try_from(&private_key).unwrap();
+
+        // Changing the height, challenge, or records invalidates the signature.
+        let mut forged = proof.clone();
+        forged.height = 1000;
+        let error = forged.verify_signatures(&address).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid signature for the proof of ownership");
+        let mut forged = proof.clone();
+        forged.challenge = b"another-challenge".to_vec();
+        assert!(forged.verify_signatures(&address).is_err());
+        let mut forged = proof.clone();
+        forged.records.pop();
+        assert!(forged.verify_signatures(&address).is_err());
+
+        // A signature from another key is rejected, even over the same records.
+        let other_key = PrivateKey::<N>::new(rng).unwrap();
+        let mut forged = proof.clone();
+        let message = ProofOfOwnership::message(100, CHALLENGE, &proof.records).unwrap();
+        forged.signature = Signature::sign(&other_key, &message, rng).unwrap();
+        assert!(forged.verify_signatures(&address).is_err());
+
+        // An attacker cannot swap in a gamma for a different serial number, even when re-signing.
+        let mut forged = proof.clone();
+        forged.records[0].gamma += N::g_scalar_multiply(&Scalar::rand(rng));
+        let message = ProofOfOwnership::message(100, CHALLENGE, &forged.records).unwrap();
+        forged.signature = Signature::sign(&private_key, &message, rng).unwrap();
+        let error = forged.verify_signatures(&address).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid serial number proof"), "{error}");
+
+        // Records of another address cannot be included.
+        let other_address = Address::try_from(&other_key).unwrap();
+        let (other_record, _) = sample_record(other_address, 1, rng);
+        assert!(ProofOfOwnership::generate(&private_key, &[other_record], 100, CHALLENGE).is_err());
+        assert!(ProofOfOwnership::<N>::generate(&private_key, &[], 100, CHALLENGE).is_err());
+        assert!(proof.verify_signatures(&other_address).is_err());
+    }
+
+    #[test]
+    fn test_proof_of_ownership_rejects_duplicate_records() {
+        let rng = &mut TestRng::default();
+        let (private_key, records, proof) = sample_proof(rng);
+        let address = Address::try_from(&private_key).unwrap();
+        let commitment = ProofOfOwnership::commitment(&records[0]).unwrap();
+        let expected = format!("Record '{commitment}' is included more than once");
+
+        // A record cannot be proven twice.
+        let duplicated = [records[0].clone(), records[1].clone(), records[0].clone()];
+        let error = ProofOfOwnership::generate(&private_key, &duplicated, 100, CHALLENGE).unwrap_err();
+        assert_eq!(error.to_string(), expected);
+
+        // A duplicated record fails verification, even when the proof is re-signed.
+        let mut forged = proof.clone();
+        forged.records.push(proof.records[0].clone());
+        let message = ProofOfOwnership::message(100, CHALLENGE, &forged.records).unwrap();
+        forged.signature = Signature::sign(&private_key, &message, rng).unwrap();
+        let error = forged.verify_signatures(&address).unwrap_err();
+        assert_eq!(error.to_string(), expected);
+        let node = mock_node(&[(commitment, 10), (ProofOfOwnership::commitment(&records[1]).unwrap(), 20)], rng);
+        let error = ProofOfOwnership::verify(&address, &forged, &testnet3(node.base_url())).unwrap_err();
+        assert_eq!(error.to_string(), expected);
+
+        // Nor can such a proof be parsed.
+        let error = ProofOfOwnership::<N>::from_str(&forged.to_string()).unwrap_err();
+        assert!(error.to_string().contains(&expected), "{error}");
+    }
+}
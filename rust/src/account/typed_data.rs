@@ -0,0 +1,366 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm_console::{
+    account::{Address, PrivateKey, Signature},
+    network::Network,
+    prelude::{FromBits, SizeInDataBits, ToBits},
+    program::{Identifier, Literal, Plaintext, PlaintextType, ProgramID, Struct},
+    types::Field,
+};
+use snarkvm_utilities::bits_from_bytes_le;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// The domain of a typed message, which binds its signature to a program, chain, and version of the message format
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TypedDataDomain<N: Network> {
+    /// The program the message is addressed to
+    pub program_id: ProgramID<N>,
+    /// The chain the message is valid on, e.g. `testnet3`
+    pub chain: String,
+    /// The version of the message format, increased by the program when the format changes
+    pub version: u32,
+}
+
+/// A structured off-chain message, signed so that wallets render it field by field and verifiers hash it the same way
+///
+/// The message is a plaintext of the `primary_type` struct, whose members, and those of nested structs, are declared
+/// in `types` with Aleo types. The message is hashed to a field element from its domain, the canonical form of its
+/// types, and its values in the order of their declaration, each prefixed with its length, so that distinct messages
+/// have distinct encodings. The order of the members of the message itself does not affect the hash, but the order
+/// of the members of its types does.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct AleoTypedData<N: Network> {
+    domain: TypedDataDomain<N>,
+    types: Vec<Struct<N>>,
+    primary_type: Identifier<N>,
+    message: Plaintext<N>,
+}
+
+impl<N: Network> AleoTypedData<N> {
+    /// Create a typed message, checking that the message is a plaintext of the primary type
+    pub fn new(
+        domain: TypedDataDomain<N>,
+        types: Vec<Struct<N>>,
+        primary_type: Identifier<N>,
+        message: Plaintext<N>,
+    ) -> Result<Self> {
+        let typed_data = Self { domain, types, primary_type, message };
+        typed_data.hash()?;
+        Ok(typed_data)
+    }
+
+    /// Returns the domain of the message.
+    pub fn domain(&self) -> &TypedDataDomain<N> {
+        &self.domain
+    }
+
+    /// Returns the structs declaring the members of the message.
+    pub fn types(&self) -> &[Struct<N>] {
+        &self.types
+    }
+
+    /// Returns the name of the struct of the message.
+    pub fn primary_type(&self) -> &Identifier<N> {
+        &self.primary_type
+    }
+
+    /// Returns the message.
+    pub fn message(&self) -> &Plaintext<N> {
+        &self.message
+    }
+
+    /// Returns the literals of the message with their paths, e.g. `order.maker`, in the order of their declaration,
+    /// for a wallet to render field by field.
+    pub fn fields(&self) -> Result<Vec<(String, Literal<N>)>> {
+        let mut fields = Vec::new();
+        let types = self.type_map()?;
+        let primary_type = PlaintextType::Struct(self.primary_type);
+        self.visit(&types, &primary_type, &self.message, &self.primary_type.to_string(), &mut |path, literal| {
+            fields.push((path.to_string(), literal.clone()))
+        })?;
+        Ok(fields)
+    }
+
+    /// Returns the canonical form of the types of the message, with the primary type first and the structs it
+    /// refers to in order of their names, e.g. `order(maker:address,item:item)item(id:field,amount:u64)`.
+    pub fn encode_type(&self) -> Result<String> {
+        let types = self.type_map()?;
+        let mut referenced = BTreeMap::new();
+        Self::collect_structs(&types, &self.primary_type, &mut referenced, &mut vec![])?;
+        let primary = referenced.remove(&self.primary_type.to_string());
+        let encode = |definition: &Struct<N>| {
+            let members = definition.members().iter().map(|(name, member_type)| format!("{name}:{member_type}"));
+            format!("{}({})", definition.name(), members.collect::<Vec<_>>().join(","))
+        };
+        Ok(primary.into_iter().chain(referenced.into_values()).map(encode).collect())
+    }
+
+    /// Hash the message to the field element that is signed.
+    pub fn hash(&self) -> Result<Field<N>> {
+        let mut preimage = vec![Field::new_domain_separator("AleoTypedData0")];
+        Self::encode_bytes(self.domain.program_id.to_string().as_bytes(), &mut preimage)?;
+        Self::encode_bytes(self.domain.chain.as_bytes(), &mut preimage)?;
+        preimage.push(Field::from_u32(self.domain.version));
+        Self::encode_bytes(self.encode_type()?.as_bytes(), &mut preimage)?;
+
+        let types = self.type_map()?;
+        let primary_type = PlaintextType::Struct(self.primary_type);
+        let mut values = Vec::new();
+        self.visit(&types, &primary_type, &self.message, &self.primary_type.to_string(), &mut |_, literal| {
+            values.push(literal.to_bits_le())
+        })?;
+        for bits in values {
+            Self::encode_bits(&bits, &mut preimage)?;
+        }
+        N::hash_psd8(&preimage)
+    }
+
+    // Returns the structs of the message by name, rejecting duplicated names
+    fn type_map(&self) -> Result<BTreeMap<String, &Struct<N>>> {
+        let mut types = BTreeMap::new();
+        for definition in &self.types {
+            let name = definition.name().to_string();
+            ensure!(types.insert(name, definition).is_none(), "Struct '{}' is declared twice", definition.name());
+        }
+        Ok(types)
+    }
+
+    // Collect the struct of the given name and the structs it refers to, rejecting undeclared and recursive structs
+    fn collect_structs<'a>(
+        types: &BTreeMap<String, &'a Struct<N>>,
+        name: &Identifier<N>,
+        collected: &mut BTreeMap<String, &'a Struct<N>>,
+        stack: &mut Vec<String>,
+    ) -> Result<()> {
+        let key = name.to_string();
+        ensure!(!stack.contains(&key), "Struct '{name}' refers to itself");
+        if collected.contains_key(&key) {
+            return Ok(());
+        }
+        let definition = types.get(&key).copied().ok_or_else(|| anyhow!("Struct '{name}' is not declared"))?;
+        collected.insert(key.clone(), definition);
+        stack.push(key);
+        for member_type in definition.members().values() {
+            if let PlaintextType::Struct(member) = member_type {
+                Self::collect_structs(types, member, collected, stack)?;
+            }
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    // Pass the literals of a plaintext of the given type to `f` with their paths, in the order of the declaration of
+    // the type, checking that the plaintext has the type
+    fn visit(
+        &self,
+        types: &BTreeMap<String, &Struct<N>>,
+        plaintext_type: &PlaintextType<N>,
+        plaintext: &Plaintext<N>,
+        path: &str,
+        f: &mut impl FnMut(&str, &Literal<N>),
+    ) -> Result<()> {
+        match (plaintext_type, plaintext) {
+            (PlaintextType::Literal(literal_type), Plaintext::Literal(literal, _)) => {
+                let found = literal.to_type();
+                ensure!(found == *literal_type, "'{path}' is a {found}, not a {literal_type}");
+                f(path, literal);
+            }
+            (PlaintextType::Struct(name), Plaintext::Struct(members, _)) => {
+                let definition =
+                    types.get(&name.to_string()).ok_or_else(|| anyhow!("Struct '{name}' is not declared"))?;
+                ensure!(
+                    members.len() == definition.members().len(),
+                    "'{path}' has {} members, but struct '{name}' declares {}",
+                    members.len(),
+                    definition.members().len()
+                );
+                for (member, member_type) in definition.members() {
+                    let value = members.get(member).ok_or_else(|| anyhow!("'{path}' has no member '{member}'"))?;
+                    self.visit(types, member_type, value, &format!("{path}.{member}"), f)?;
+                }
+            }
+            (PlaintextType::Literal(literal_type), Plaintext::Struct(..)) => {
+                bail!("'{path}' is a struct, not a {literal_type}")
+            }
+            (PlaintextType::Struct(name), Plaintext::Literal(..)) => {
+                bail!("'{path}' is a literal, not a '{name}' struct")
+            }
+        }
+        Ok(())
+    }
+
+    // Append the bytes to the preimage, prefixed with their length
+    fn encode_bytes(bytes: &[u8], preimage: &mut Vec<Field<N>>) -> Result<()> {
+        Self::encode_bits(&bits_from_bytes_le(bytes).collect::<Vec<_>>(), preimage)
+    }
+
+    // Append the bits to the preimage as field elements, prefixed with their length
+    fn encode_bits(bits: &[bool], preimage: &mut Vec<Field<N>>) -> Result<()> {
+        preimage.push(Field::from_u64(bits.len() as u64));
+        for chunk in bits.chunks(Field::<N>::size_in_data_bits()) {
+            preimage.push(Field::from_bits_le(chunk)?);
+        }
+        Ok(())
+    }
+}
+
+impl<N: Network> FromStr for AleoTypedData<N> {
+    type Err = anyhow::Error;
+
+    /// Parse a typed message from JSON, checking that the message is a plaintext of the primary type
+    fn from_str(typed_data: &str) -> Result<Self, Self::Err> {
+        let Self { domain, types, primary_type, message } = serde_json::from_str(typed_data)?;
+        Self::new(domain, types, primary_type, message)
+    }
+}
+
+impl<N: Network> fmt::Display for AleoTypedData<N> {
+    /// Serialize a typed message to JSON
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
+
+/// Sign a typed message with the private key
+pub fn sign_typed_data<N: Network>(private_key: &PrivateKey<N>, typed_data: &AleoTypedData<N>) -> Result<Signature<N>> {
+    Signature::sign(private_key, &[typed_data.hash()?], &mut rand::thread_rng())
+}
+
+/// Verify that the typed message was signed by the address
+pub fn verify_typed_data<N: Network>(
+    address: &Address<N>,
+    typed_data: &AleoTypedData<N>,
+    signature: &Signature<N>,
+) -> Result<()> {
+    ensure!(signature.verify(address, &[typed_data.hash()?]), "Invalid signature for the typed message");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::CurrentNetwork;
+
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    const ORDER: &str = "struct order:
+    maker as address;
+    item as item;
+    expires as u32;";
+
+    const ITEM: &str = "struct item:
+    id as field;
+    amount as u64;";
+
+    const MAKER: &str = "aleo1rhgdu77hgyqd3xjj8ucu3jj9r2krwz6mnzyd80gncr5fxcwlh5rsvzp9px";
+
+    const TYPED_DATA_HASH: &str = "1054559277040525797780930192051596575073373611121980054552092890491885385285field";
+
+    fn sample_typed_data(message: &str) -> Result<AleoTypedData<N>> {
+        let domain = TypedDataDomain {
+            program_id: ProgramID::from_str("order_book.aleo")?,
+            chain: "testnet3".to_string(),
+            version: 1,
+        };
+        let types = vec![Struct::from_str(ITEM)?, Struct::from_str(ORDER)?];
+        AleoTypedData::new(domain, types, Identifier::from_str("order")?, Plaintext::from_str(message)?)
+    }
+
+    fn sample_message(amount: u64) -> String {
+        format!("{{ maker: {MAKER}, item: {{ id: 7field, amount: {amount}u64 }}, expires: 1000u32 }}")
+    }
+
+    #[test]
+    fn test_typed_data_encoding() {
+        let typed_data = sample_typed_data(&sample_message(25)).unwrap();
+        let encoded_type = "order(maker:address,item:item,expires:u32)item(id:field,amount:u64)";
+        assert_eq!(typed_data.encode_type().unwrap(), encoded_type);
+        let fields = typed_data.fields().unwrap();
+        let fields = fields.iter().map(|(path, literal)| format!("{path}={literal}")).collect::<Vec<_>>();
+        assert_eq!(fields, [
+            format!("order.maker={MAKER}"),
+            "order.item.id=7field".to_string(),
+            "order.item.amount=25u64".to_string(),
+            "order.expires=1000u32".to_string(),
+        ]);
+
+        // The hash is pinned, so that verifiers in other languages can check their encoding against it.
+        let hash = typed_data.hash().unwrap();
+        assert_eq!(hash.to_string(), TYPED_DATA_HASH);
+        assert_eq!(AleoTypedData::<N>::from_str(&typed_data.to_string()).unwrap().hash().unwrap(), hash);
+
+        // The order of the members of the message does not change its meaning, so it does not change the hash.
+        let reordered = format!("{{ expires: 1000u32, item: {{ amount: 25u64, id: 7field }}, maker: {MAKER} }}");
+        assert_eq!(sample_typed_data(&reordered).unwrap().hash().unwrap(), hash);
+
+        // The order of the members of a type does.
+        let mut swapped = typed_data.clone();
+        swapped.types[0] = Struct::from_str("struct item:\n    amount as u64;\n    id as field;").unwrap();
+        assert_ne!(swapped.hash().unwrap(), hash);
+
+        // So do the domain and every value.
+        let mut other_chain = typed_data.clone();
+        other_chain.domain.chain = "testnet3x".to_string();
+        assert_ne!(other_chain.hash().unwrap(), hash);
+        assert_ne!(sample_typed_data(&sample_message(26)).unwrap().hash().unwrap(), hash);
+    }
+
+    #[test]
+    fn test_typed_data_rejects_invalid_messages() {
+        // A member of the wrong type, a missing member, and an extra member are rejected.
+        let wrong_type = format!("{{ maker: {MAKER}, item: {{ id: 7field, amount: 25u32 }}, expires: 1000u32 }}");
+        assert!(sample_typed_data(&wrong_type).is_err());
+        let missing = format!("{{ maker: {MAKER}, item: {{ id: 7field, amount: 25u64 }} }}");
+        assert!(sample_typed_data(&missing).is_err());
+        let extra = format!("{{ maker: {MAKER}, item: {{ id: 7field, amount: 25u64, fee: 1u64 }}, expires: 1000u32 }}");
+        assert!(sample_typed_data(&extra).is_err());
+
+        // Undeclared and recursive structs are rejected.
+        let mut typed_data = sample_typed_data(&sample_message(25)).unwrap();
+        typed_data.types.remove(0);
+        assert!(typed_data.hash().is_err());
+        typed_data.types.push(Struct::from_str("struct item:\n    next as order;").unwrap());
+        assert!(typed_data.encode_type().is_err());
+    }
+
+    #[test]
+    fn test_sign_typed_data() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let address = Address::try_from(&private_key).unwrap();
+        let typed_data = sample_typed_data(&sample_message(25)).unwrap();
+        let signature = sign_typed_data(&private_key, &typed_data).unwrap();
+        verify_typed_data(&address, &typed_data, &signature).unwrap();
+
+        // Altering any field of the message, or its domain, invalidates the signature.
+        let altered = sample_typed_data(&sample_message(24)).unwrap();
+        assert!(verify_typed_data(&address, &altered, &signature).is_err());
+        let mut altered = typed_data.clone();
+        altered.domain.version = 2;
+        assert!(verify_typed_data(&address, &altered, &signature).is_err());
+
+        let other = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        assert!(verify_typed_data(&other, &typed_data, &signature).is_err());
+    }
+}
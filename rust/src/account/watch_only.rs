@@ -0,0 +1,91 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm_console::{
+    account::{Address, PrivateKey, ViewKey},
+    network::Network,
+};
+
+use std::{error::Error, fmt};
+
+/// An account known only by its view key, e.g. to watch the deposits of an exchange from an online machine
+/// while the private key stays offline
+///
+/// A watch-only account finds and decrypts the records of the account, but cannot sign. Program managers and
+/// wallets built from one fail every operation that signs with [`SigningUnavailable`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WatchOnlyAccount<N: Network> {
+    view_key: ViewKey<N>,
+    address: Address<N>,
+}
+
+impl<N: Network> WatchOnlyAccount<N> {
+    /// Create a watch-only account from its view key.
+    pub fn new(view_key: ViewKey<N>) -> Self {
+        Self { view_key, address: view_key.to_address() }
+    }
+
+    /// Returns the view key of the account.
+    pub fn view_key(&self) -> &ViewKey<N> {
+        &self.view_key
+    }
+
+    /// Returns the address of the account.
+    pub fn address(&self) -> Address<N> {
+        self.address
+    }
+
+    /// Returns `true` if the private key belongs to the account.
+    pub fn is_owned_by(&self, private_key: &PrivateKey<N>) -> bool {
+        ViewKey::try_from(private_key).map_or(false, |view_key| view_key == self.view_key)
+    }
+}
+
+impl<N: Network> From<ViewKey<N>> for WatchOnlyAccount<N> {
+    fn from(view_key: ViewKey<N>) -> Self {
+        Self::new(view_key)
+    }
+}
+
+/// The error returned when an operation needs to sign, but the account is watch-only
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SigningUnavailable;
+
+impl fmt::Display for SigningUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The account is watch-only, so it cannot sign transactions")
+    }
+}
+
+impl Error for SigningUnavailable {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm_console::network::Testnet3 as CurrentNetwork;
+    use snarkvm_utilities::TestRng;
+
+    #[test]
+    fn test_watch_only_account() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+        let view_key = ViewKey::try_from(&private_key).unwrap();
+        let account = WatchOnlyAccount::from(view_key);
+        assert_eq!(account.address(), Address::try_from(&private_key).unwrap());
+        assert!(account.is_owned_by(&private_key));
+        assert!(!account.is_owned_by(&PrivateKey::new(rng).unwrap()));
+    }
+}
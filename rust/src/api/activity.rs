@@ -0,0 +1,109 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use snarkvm_console::program::{Identifier, Network, Plaintext, ProgramID, Value};
+use snarkvm_synthesizer::{Block, Input};
+
+/// A call to a program function found on chain
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ProgramCall<N: Network> {
+    height: u32,
+    transaction_id: N::TransactionID,
+    transition_id: N::TransitionID,
+    program_id: ProgramID<N>,
+    function_name: Identifier<N>,
+    public_inputs: Vec<Plaintext<N>>,
+    finalize: Option<Vec<Value<N>>>,
+}
+
+impl<N: Network> ProgramCall<N> {
+    // Returns the calls in the block to the given program, and to the given function if any, in order
+    pub(crate) fn find_in_block<'a>(
+        block: &'a Block<N>,
+        program_id: &'a ProgramID<N>,
+        function_name: Option<&'a Identifier<N>>,
+    ) -> impl 'a + Iterator<Item = Self> {
+        block.transactions().iter().flat_map(move |transaction| {
+            let transaction_id = transaction.id();
+            transaction
+                .transitions()
+                .filter(move |transition| {
+                    transition.program_id() == program_id
+                        && function_name.map_or(true, |function_name| transition.function_name() == function_name)
+                })
+                .map(move |transition| Self {
+                    height: block.height(),
+                    transaction_id,
+                    transition_id: *transition.id(),
+                    program_id: *transition.program_id(),
+                    function_name: *transition.function_name(),
+                    public_inputs: transition
+                        .inputs()
+                        .iter()
+                        .filter_map(|input| match input {
+                            Input::Constant(_, plaintext) | Input::Public(_, plaintext) => plaintext.clone(),
+                            _ => None,
+                        })
+                        .collect(),
+                    finalize: transition.finalize().cloned(),
+                })
+        })
+    }
+
+    /// Returns the height of the block containing the call.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the ID of the transaction containing the call.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the ID of the transition of the call.
+    pub fn transition_id(&self) -> N::TransitionID {
+        self.transition_id
+    }
+
+    /// Returns the ID of the called program.
+    pub fn program_id(&self) -> &ProgramID<N> {
+        &self.program_id
+    }
+
+    /// Returns the name of the called function.
+    pub fn function_name(&self) -> &Identifier<N> {
+        &self.function_name
+    }
+
+    /// Returns the constant and public inputs of the call, in order, which anyone can read.
+    pub fn public_inputs(&self) -> &[Plaintext<N>] {
+        &self.public_inputs
+    }
+
+    /// Returns the inputs passed to the finalize block of the function, if it has one.
+    ///
+    /// Blocks only hold accepted transactions, so every call with a finalize block was finalized.
+    pub fn finalize(&self) -> Option<&[Value<N>]> {
+        self.finalize.as_deref()
+    }
+
+    /// Returns `true` if the call ran the finalize block of the function.
+    pub fn is_finalized(&self) -> bool {
+        self.finalize.is_some()
+    }
+}
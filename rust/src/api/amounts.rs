@@ -0,0 +1,185 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::ApiError;
+
+use serde_json::Value as Json;
+use snarkvm_console::program::{Network, Value};
+use std::str::FromStr;
+
+/// The bound below which floating point numbers hold every integer exactly, 2^53
+///
+/// 2^53 itself is excluded, as 2^53 + 1 rounds to it.
+const MAX_EXACT_FLOAT: f64 = 9_007_199_254_740_992.0;
+
+/// The JSON path of the root of an item
+pub(crate) const ROOT_PATH: &str = "$";
+
+// Read an integer amount at `path` of the item from JSON. Integers, and strings of integers, are read as they are.
+// Gateways re-serializing amounts as floating point lose their precision, so floats are only read if they hold an
+// integer below 2^53, which they hold exactly. Floats with a fractional part or from 2^53 on, and amounts beyond the
+// range of `T`, fail with `ApiError::PrecisionLoss` rather than be truncated.
+pub(crate) fn parse_amount<T: TryFrom<i128>>(json: &Json, item: &str, path: &str) -> Result<T, ApiError> {
+    let amount = match json {
+        Json::Number(number) => match (number.as_i64(), number.as_u64(), number.as_f64()) {
+            (Some(amount), _, _) => Some(amount as i128),
+            (_, Some(amount), _) => Some(amount as i128),
+            (_, _, Some(amount)) => exact_integer(amount),
+            _ => None,
+        },
+        Json::String(string) => match (string.parse::<i128>(), string.parse::<f64>()) {
+            (Ok(amount), _) => Some(amount),
+            // Strings hold decimals exactly, so those with a zero fractional part are read at any size.
+            (_, Ok(_)) => string
+                .split_once('.')
+                .filter(|(_, fraction)| fraction.bytes().all(|digit| digit == b'0'))
+                .and_then(|(whole, _)| whole.parse().ok()),
+            _ => return Err(ApiError::parse(format!("the {item} at {path}"), format!("'{string}' is not an amount"))),
+        },
+        _ => return Err(ApiError::parse(format!("the {item} at {path}"), format!("expected an amount, found {json}"))),
+    };
+    amount.and_then(|amount| T::try_from(amount).ok()).ok_or_else(|| ApiError::PrecisionLoss {
+        item: item.to_string(),
+        path: path.to_string(),
+        value: json.to_string(),
+    })
+}
+
+// Returns the integer a float holds exactly, if it holds one
+fn exact_integer(amount: f64) -> Option<i128> {
+    (amount.fract() == 0.0 && amount.abs() < MAX_EXACT_FLOAT).then_some(amount as i128)
+}
+
+// Replace the amount at `path` of the item with the JSON integer it holds, see `parse_amount`
+fn normalize_amount<T: TryFrom<i128> + Into<Json>>(json: &mut Json, item: &str, path: &str) -> Result<(), ApiError> {
+    *json = parse_amount::<T>(json, item, path)?.into();
+    Ok(())
+}
+
+// Replace the fees of the transitions of a transaction at `path` of the item with the JSON integers they hold, so
+// that fees served as floats or strings are read exactly, or fail with `ApiError::PrecisionLoss`
+pub(crate) fn normalize_fees(transaction: &mut Json, item: &str, path: &str) -> Result<(), ApiError> {
+    if let Some(transitions) = transaction.pointer_mut("/execution/transitions").and_then(Json::as_array_mut) {
+        for (index, transition) in transitions.iter_mut().enumerate() {
+            if let Some(fee) = transition.get_mut("fee") {
+                normalize_amount::<i64>(fee, item, &format!("{path}.execution.transitions[{index}].fee"))?;
+            }
+        }
+    }
+    if let Some(fee) = transaction.pointer_mut("/additional_fee/transition/fee") {
+        normalize_amount::<i64>(fee, item, &format!("{path}.additional_fee.transition.fee"))?;
+    }
+    Ok(())
+}
+
+/// Parses a mapping value served by a node, or `None` if the key is unset.
+///
+/// Nodes serve mapping values as literals with their type, e.g. `"100u64"`. Gateways re-serializing them as JSON
+/// numbers, or as strings of bare integers, drop the type, so such values are read as `u64` amounts, the type of
+/// microcredit amounts, as long as they hold an integer exactly. Values whose precision was lost fail with
+/// [`ApiError::PrecisionLoss`] as the outer error. Other parse errors are returned as the inner error.
+pub(crate) fn from_mapping_json<N: Network>(
+    response: serde_json::Result<Json>,
+) -> Result<serde_json::Result<Option<Value<N>>>, ApiError> {
+    let json = match response {
+        Ok(json) => json,
+        Err(error) => return Ok(Err(error)),
+    };
+    let is_bare_amount = match &json {
+        Json::Number(_) => true,
+        Json::String(string) => Value::<N>::from_str(string).is_err() && string.parse::<f64>().is_ok(),
+        _ => false,
+    };
+    if !is_bare_amount {
+        return Ok(serde_json::from_value(json));
+    }
+    let amount = parse_amount::<u64>(&json, "mapping value", ROOT_PATH)?;
+    Ok(Ok(Some(Value::from_str(&format!("{amount}u64")).expect("a u64 literal is a value"))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::CurrentNetwork;
+
+    use serde_json::json;
+
+    type N = CurrentNetwork;
+
+    #[test]
+    fn test_parse_amount() {
+        let parse = |json: Json| parse_amount::<u64>(&json, "balance", ROOT_PATH);
+        assert_eq!(parse(json!(1_099_999_999_999_864u64)), Ok(1_099_999_999_999_864));
+        assert_eq!(parse(json!(u64::MAX)), Ok(u64::MAX));
+        assert_eq!(parse(json!("18446744073709551615")), Ok(u64::MAX));
+        assert_eq!(parse(json!(42000000.0)), Ok(42000000));
+        assert_eq!(parse(json!("9007199254740993.0")), Ok(9007199254740993));
+        assert_eq!(parse_amount::<i64>(&json!("-25"), "fee", ROOT_PATH), Ok(-25));
+
+        // Fractions, floats beyond 2^53 and amounts beyond the range of the type are lost precision, not truncated.
+        for lost in [json!(42000000.5), json!(1.8446744073709552e19), json!("42000000.5"), json!(-1)] {
+            let error = parse(lost.clone()).unwrap_err();
+            assert_eq!(error, ApiError::PrecisionLoss {
+                item: "balance".to_string(),
+                path: ROOT_PATH.to_string(),
+                value: lost.to_string()
+            });
+        }
+        assert_eq!(
+            parse(json!(42000000.5)).unwrap_err().to_string(),
+            "Precision loss in the balance at $: 42000000.5 cannot be read as an exact integer amount"
+        );
+        assert!(matches!(parse(json!("many")), Err(ApiError::Parse { .. })));
+        assert!(matches!(parse(json!(null)), Err(ApiError::Parse { .. })));
+    }
+
+    #[test]
+    fn test_parse_amount_float_bound() {
+        let parse = |json: Json| parse_amount::<u64>(&json, "balance", ROOT_PATH);
+        assert_eq!(parse(json!(9_007_199_254_740_991.0)), Ok(9_007_199_254_740_991));
+        assert_eq!(exact_integer(-9_007_199_254_740_991.0), Some(-9_007_199_254_740_991));
+        assert_eq!(exact_integer(-9_007_199_254_740_992.0), None);
+
+        // 2^53 + 1 rounds to 2^53 as a float, so neither is read as an exact integer.
+        for lost in [9_007_199_254_740_992u64, 9_007_199_254_740_993] {
+            assert!(matches!(parse(json!(lost as f64)), Err(ApiError::PrecisionLoss { .. })), "{lost}");
+        }
+
+        // As integers, or as strings, they are read exactly.
+        assert_eq!(parse(json!(9_007_199_254_740_992u64)), Ok(9_007_199_254_740_992));
+        assert_eq!(parse(json!(9_007_199_254_740_993u64)), Ok(9_007_199_254_740_993));
+        assert_eq!(parse(json!("9007199254740993.0")), Ok(9_007_199_254_740_993));
+    }
+
+    #[test]
+    fn test_from_mapping_json() {
+        let parse = |json: &str| from_mapping_json::<N>(serde_json::from_str(json));
+        let amount = |literal: &str| Some(Value::from_str(literal).unwrap());
+        assert_eq!(parse("\"42000000u64\"").unwrap().unwrap(), amount("42000000u64"));
+        assert_eq!(parse("\"true\"").unwrap().unwrap(), amount("true"));
+        assert_eq!(parse("null").unwrap().unwrap(), None);
+
+        // Amounts without their type are read as microcredits, if no precision was lost.
+        assert_eq!(parse("\"18446744073709551615\"").unwrap().unwrap(), amount("18446744073709551615u64"));
+        assert_eq!(parse("42000000").unwrap().unwrap(), amount("42000000u64"));
+        let error = parse("42000000.5").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Precision loss in the mapping value at $: 42000000.5 cannot be read as an exact integer amount"
+        );
+        assert!(parse("\"aleo1\"").unwrap().is_err());
+    }
+}
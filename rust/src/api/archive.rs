@@ -0,0 +1,506 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Archives of blocks, for analyzing a range of the ledger offline.
+//!
+//! An archive starts with a header of 20 bytes: the magic bytes `ALEOBLKS`, the version of the format and the ID
+//! of the network as little-endian `u16`, then the height of the first block and the number of blocks as
+//! little-endian `u32`. Each block follows in ascending order of height, as a little-endian `u32` length and the
+//! bytes of the block in the binary encoding of snarkVM.
+
+use crate::{
+    api::{search_source, to_height_range},
+    ids::{from_heights, heights},
+    AleoAPIClient,
+    BlockHeight,
+    RecordPrefilter,
+    ScanOptions,
+    ScannedRecord,
+    SearchHit,
+    SearchQuery,
+};
+
+use anyhow::{anyhow, bail, Result};
+use snarkvm_console::{
+    account::ViewKey,
+    prelude::{FromBytes, ToBytes},
+    program::{Ciphertext, Network, Record},
+    types::Field,
+};
+use snarkvm_synthesizer::Block;
+use std::{
+    error::Error,
+    fmt,
+    io::{Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    ops::{Range, RangeBounds},
+    sync::{Mutex, MutexGuard},
+};
+
+/// The magic bytes starting a block archive
+const ARCHIVE_MAGIC: &[u8; 8] = b"ALEOBLKS";
+/// The version of the archive format
+const ARCHIVE_VERSION: u16 = 1;
+/// The size of the header of an archive in bytes
+const HEADER_SIZE: u64 = 20;
+
+/// A source of blocks, which is either a node or an archive of blocks
+///
+/// Analyses written against a block source run the same against the network and offline.
+#[allow(clippy::type_complexity)]
+pub trait BlockSource<N: Network> {
+    /// Passes the blocks at the given heights to `f` in ascending order.
+    fn for_each_block(&self, block_heights: Range<BlockHeight>, f: &mut dyn FnMut(Block<N>)) -> Result<()>;
+
+    /// Scans the blocks at the given heights for records that match the given view key.
+    ///
+    /// The records are returned in the order of the blocks that created them, as by [`AleoAPIClient::scan`].
+    fn scan_records(
+        &self,
+        view_key: &ViewKey<N>,
+        block_heights: Range<BlockHeight>,
+    ) -> Result<Vec<(Field<N>, Record<N, Ciphertext<N>>)>> {
+        scan_decoded_blocks(self, view_key, block_heights)
+    }
+
+    /// Returns the fields of the transitions of the blocks at the given heights that match the query, in the order
+    /// of the blocks, then of their transactions and transitions.
+    fn search_blocks(&self, block_heights: Range<BlockHeight>, query: &SearchQuery) -> Result<Vec<SearchHit<N>>> {
+        search_source(self, block_heights, query)
+    }
+}
+
+// Scan the blocks of the source at the given heights for records that match the given view key, decoding every
+// record of the blocks
+#[allow(clippy::type_complexity)]
+fn scan_decoded_blocks<N: Network>(
+    source: &(impl BlockSource<N> + ?Sized),
+    view_key: &ViewKey<N>,
+    block_heights: Range<BlockHeight>,
+) -> Result<Vec<(Field<N>, Record<N, Ciphertext<N>>)>> {
+    let address_x_coordinate = view_key.to_address().to_x_coordinate();
+    let mut records = Vec::new();
+    source.for_each_block(block_heights, &mut |block| {
+        let owned = ScannedRecord::find_in_block(block, &[])
+            .filter(|scanned| scanned.record().is_owner_with_address_x_coordinate(view_key, &address_x_coordinate));
+        records.extend(owned.map(ScannedRecord::into_pair));
+    })?;
+    Ok(records)
+}
+
+#[allow(clippy::type_complexity)]
+impl<N: Network> BlockSource<N> for AleoAPIClient<N> {
+    fn for_each_block(&self, block_heights: Range<BlockHeight>, f: &mut dyn FnMut(Block<N>)) -> Result<()> {
+        AleoAPIClient::for_each_block(self, block_heights, f)
+    }
+
+    fn scan_records(
+        &self,
+        view_key: &ViewKey<N>,
+        block_heights: Range<BlockHeight>,
+    ) -> Result<Vec<(Field<N>, Record<N, Ciphertext<N>>)>> {
+        AleoAPIClient::scan(self, *view_key, block_heights)
+    }
+}
+
+impl<N: Network> AleoAPIClient<N> {
+    /// Write the blocks at the given heights to an archive, which [`ArchiveReader`] reads back.
+    ///
+    /// Blocks are fetched and written one at a time, so exports of long ranges do not hold the range in memory.
+    /// Writers to files should be buffered.
+    pub fn export_blocks(&self, block_heights: impl RangeBounds<BlockHeight>, mut writer: impl Write) -> Result<()> {
+        let block_heights = to_height_range(block_heights)?;
+        if block_heights.start >= block_heights.end {
+            bail!("Start height must be less than end height");
+        }
+        writer.write_all(ARCHIVE_MAGIC)?;
+        writer.write_all(&ARCHIVE_VERSION.to_le_bytes())?;
+        writer.write_all(&N::ID.to_le_bytes())?;
+        writer.write_all(&block_heights.start.to_le_bytes())?;
+        writer.write_all(&(block_heights.end - block_heights.start).to_le_bytes())?;
+
+        // Errors of the writer cannot leave the closure, so the first one is kept and the rest of the blocks skipped.
+        let mut result = Ok(());
+        AleoAPIClient::for_each_block(self, heights(block_heights), |block| {
+            if result.is_ok() {
+                result = write_block(&mut writer, &block);
+            }
+        })?;
+        result?;
+        Ok(writer.flush()?)
+    }
+}
+
+// Write a block to an archive, prefixed with its length
+fn write_block<N: Network>(writer: &mut impl Write, block: &Block<N>) -> Result<()> {
+    let bytes = block.to_bytes_le()?;
+    let length = u32::try_from(bytes.len()).map_err(|_| anyhow!("Block {} is too large to archive", block.height()))?;
+    writer.write_all(&length.to_le_bytes())?;
+    Ok(writer.write_all(&bytes)?)
+}
+
+/// The error returned when an archive of blocks is corrupted or truncated
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorruptArchive {
+    offset: u64,
+    reason: String,
+}
+
+impl CorruptArchive {
+    fn new(offset: u64, reason: impl ToString) -> Self {
+        Self { offset, reason: reason.to_string() }
+    }
+
+    /// Returns the offset in bytes of the corrupted part of the archive.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the reason the archive could not be read.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for CorruptArchive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Corrupted block archive at offset {}: {}", self.offset, self.reason)
+    }
+}
+
+impl Error for CorruptArchive {}
+
+/// Reads the blocks of an archive written by [`AleoAPIClient::export_blocks`]
+///
+/// The reader iterates over the blocks of the archive in ascending order of height. Readers over seekable
+/// sources, such as files, are also a [`BlockSource`], so the archive can be analyzed as many times as needed.
+///
+/// Scans of the archive check the ownership of records on their bytes, and only decode the blocks' records that
+/// pass, unless [`ScanOptions::fast_prefilter`] is off.
+pub struct ArchiveReader<N: Network, R: Read> {
+    state: Mutex<ArchiveState<R>>,
+    block_heights: Range<u32>,
+    scan_options: ScanOptions,
+    _network: PhantomData<N>,
+}
+
+// The position of a reader in its archive
+struct ArchiveState<R> {
+    reader: R,
+    offset: u64,
+    next_height: u32,
+    failed: bool,
+}
+
+impl<N: Network, R: Read> ArchiveReader<N, R> {
+    /// Open an archive, reading its header.
+    pub fn open(mut reader: R) -> Result<Self> {
+        let mut header = [0u8; HEADER_SIZE as usize];
+        read_exact(&mut reader, &mut header, 0, "the header is truncated")?;
+        if &header[..8] != ARCHIVE_MAGIC {
+            return Err(CorruptArchive::new(0, "this is not a block archive").into());
+        }
+        let version = u16::from_le_bytes([header[8], header[9]]);
+        if version != ARCHIVE_VERSION {
+            return Err(CorruptArchive::new(8, format!("unsupported version {version}")).into());
+        }
+        let network = u16::from_le_bytes([header[10], header[11]]);
+        if network != N::ID {
+            return Err(CorruptArchive::new(10, format!("the blocks are of network {network}, not {}", N::ID)).into());
+        }
+        let start_height = u32::from_le_bytes(header[12..16].try_into()?);
+        let count = u32::from_le_bytes(header[16..20].try_into()?);
+        let end_height = match start_height.checked_add(count) {
+            Some(end_height) => end_height,
+            None => return Err(CorruptArchive::new(16, format!("{count} blocks overflow the height")).into()),
+        };
+        let state = ArchiveState { reader, offset: HEADER_SIZE, next_height: start_height, failed: false };
+        let block_heights = start_height..end_height;
+        let scan_options = ScanOptions::default();
+        Ok(Self { state: Mutex::new(state), block_heights, scan_options, _network: PhantomData })
+    }
+
+    /// Set the options of scans of the archive.
+    pub fn with_scan_options(mut self, scan_options: ScanOptions) -> Self {
+        self.scan_options = scan_options;
+        self
+    }
+
+    /// Returns the heights of the blocks in the archive.
+    pub fn block_heights(&self) -> Range<BlockHeight> {
+        heights(self.block_heights.clone())
+    }
+
+    /// Returns the number of blocks in the archive.
+    pub fn len(&self) -> usize {
+        self.block_heights.len()
+    }
+
+    /// Returns `true` if the archive holds no blocks.
+    pub fn is_empty(&self) -> bool {
+        self.block_heights.is_empty()
+    }
+}
+
+impl<N: Network, R: Read> Iterator for ArchiveReader<N, R> {
+    type Item = Result<Block<N>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let state = self.state.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.failed || state.next_height >= self.block_heights.end {
+            return None;
+        }
+        let block = state.read_block();
+        // A corrupted block leaves the reader at an unknown position, so the iteration stops.
+        state.failed = block.is_err();
+        Some(block)
+    }
+}
+
+impl<N: Network, R: Read + Seek> ArchiveReader<N, R> {
+    // Lock the reader, positioned at the first of the given heights
+    fn seek_to(&self, block_heights: &Range<u32>) -> Result<MutexGuard<'_, ArchiveState<R>>> {
+        if block_heights.start < self.block_heights.start || block_heights.end > self.block_heights.end {
+            bail!(
+                "The archive holds blocks {} (inclusive) to {} (exclusive), not {} to {}",
+                self.block_heights.start,
+                self.block_heights.end,
+                block_heights.start,
+                block_heights.end
+            );
+        }
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Rewind to the first block if the range starts before the position of the reader.
+        if state.failed || block_heights.start < state.next_height {
+            state.reader.seek(SeekFrom::Start(HEADER_SIZE))?;
+            (state.offset, state.next_height, state.failed) = (HEADER_SIZE, self.block_heights.start, false);
+        }
+        while state.next_height < block_heights.start {
+            state.skip_block()?;
+        }
+        Ok(state)
+    }
+}
+
+#[allow(clippy::type_complexity)]
+impl<N: Network, R: Read + Seek> BlockSource<N> for ArchiveReader<N, R> {
+    fn for_each_block(&self, block_heights: Range<BlockHeight>, f: &mut dyn FnMut(Block<N>)) -> Result<()> {
+        let block_heights = from_heights(block_heights);
+        let mut state = self.seek_to(&block_heights)?;
+        while state.next_height < block_heights.end {
+            f(state.read_block()?);
+        }
+        Ok(())
+    }
+
+    fn scan_records(
+        &self,
+        view_key: &ViewKey<N>,
+        block_heights: Range<BlockHeight>,
+    ) -> Result<Vec<(Field<N>, Record<N, Ciphertext<N>>)>> {
+        if !self.scan_options.fast_prefilter {
+            return scan_decoded_blocks(self, view_key, block_heights);
+        }
+        let block_heights = from_heights(block_heights);
+        let prefilter = RecordPrefilter::new(view_key);
+        let mut state = self.seek_to(&block_heights)?;
+        let mut records = Vec::new();
+        while state.next_height < block_heights.end {
+            records.extend(state.scan_block(&prefilter)?.into_iter().map(ScannedRecord::into_pair));
+        }
+        Ok(records)
+    }
+}
+
+impl<R: Read> ArchiveState<R> {
+    // Read the length of the next block, moving past it
+    fn read_length(&mut self) -> Result<u32> {
+        let mut length = [0u8; 4];
+        read_exact(&mut self.reader, &mut length, self.offset, "the archive is truncated")?;
+        self.offset += 4;
+        Ok(u32::from_le_bytes(length))
+    }
+
+    // Read the bytes of the next block, returning them with the offset of the block
+    fn read_bytes(&mut self) -> Result<(u64, Vec<u8>)> {
+        let start = self.offset;
+        let length = self.read_length()?;
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(length as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < length as usize {
+            let reason = format!("block {} is truncated to {} of {length} bytes", self.next_height, bytes.len());
+            return Err(CorruptArchive::new(self.offset + bytes.len() as u64, reason).into());
+        }
+        Ok((start, bytes))
+    }
+
+    // Move past the bytes of the next block, after checking that they hold the block at the given height
+    fn advance(&mut self, start: u64, length: usize, height: u32) -> Result<()> {
+        if height != self.next_height {
+            let reason = format!("expected block {}, found block {height}", self.next_height);
+            return Err(CorruptArchive::new(start, reason).into());
+        }
+        self.offset += length as u64;
+        self.next_height += 1;
+        Ok(())
+    }
+
+    // Returns the error of a block that failed to decode
+    fn invalid_block(&self, start: u64, error: impl fmt::Display) -> anyhow::Error {
+        CorruptArchive::new(start, format!("block {} is invalid: {error}", self.next_height)).into()
+    }
+
+    // Read the next block, checking its height
+    fn read_block<N: Network>(&mut self) -> Result<Block<N>> {
+        let (start, bytes) = self.read_bytes()?;
+        let block = Block::<N>::from_bytes_le(&bytes).map_err(|error| self.invalid_block(start, error))?;
+        self.advance(start, bytes.len(), block.height())?;
+        Ok(block)
+    }
+
+    // Walk the next block, checking its height, and returns the records owned by the view key of the prefilter
+    fn scan_block<N: Network>(&mut self, prefilter: &RecordPrefilter<N>) -> Result<Vec<ScannedRecord<N>>> {
+        let (start, bytes) = self.read_bytes()?;
+        let (height, records) = prefilter.scan_block(&bytes).map_err(|error| self.invalid_block(start, error))?;
+        self.advance(start, bytes.len(), height)?;
+        Ok(records)
+    }
+}
+
+impl<R: Read + Seek> ArchiveState<R> {
+    // Move past the next block without reading it
+    fn skip_block(&mut self) -> Result<()> {
+        let length = self.read_length()?;
+        self.reader.seek(SeekFrom::Current(length as i64))?;
+        self.offset += length as u64;
+        self.next_height += 1;
+        Ok(())
+    }
+}
+
+// Fill the buffer from the reader, failing with a `CorruptArchive` at the offset if the reader ends first
+fn read_exact(reader: &mut impl Read, buffer: &mut [u8], offset: u64, reason: &str) -> Result<()> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Err(CorruptArchive::new(offset, reason).into())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_block_with_transactions,
+            sample_output,
+            sample_transaction,
+            sample_transition,
+            CurrentNetwork,
+            MockResponse,
+            MockServer,
+        },
+        testnet3,
+    };
+    use snarkvm_console::account::{Address, PrivateKey};
+    use snarkvm_utilities::TestRng;
+
+    use std::io::Cursor;
+
+    type N = CurrentNetwork;
+
+    // Start a mock node serving a chain of the given length, in which every other block holds a record of the
+    // view key, and the others a record of another account
+    fn mock_chain_server(length: u32, view_key: &ViewKey<N>) -> MockServer {
+        let rng = &mut TestRng::default();
+        let other = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let mut blocks = vec![genesis_block()];
+        for height in 1..length {
+            let owner = if height % 2 == 0 { view_key.to_address() } else { other };
+            let transaction = sample_transaction([sample_transition(&[], &[sample_output(owner, 100, rng)], rng)]);
+            let previous_hash = blocks.last().unwrap().hash();
+            let transactions = [transaction].into_iter().collect();
+            blocks.push(sample_block_with_transactions(height, previous_hash, transactions, rng));
+        }
+        let blocks = blocks.iter().map(ToString::to_string).collect::<Vec<_>>();
+        MockServer::start(move |request| {
+            let (start, end) = request.path.strip_prefix("/testnet3/blocks?start=")?.split_once("&end=")?;
+            let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+            let blocks = blocks.get(start..end.min(blocks.len()))?;
+            Some(MockResponse::json(format!("[{}]", blocks.join(","))))
+        })
+    }
+
+    // Returns the offset and reason of the corruption the archive fails with
+    fn corruption(archive: &[u8]) -> (u64, String) {
+        let error = match ArchiveReader::<N, _>::open(Cursor::new(archive)) {
+            Ok(reader) => reader.collect::<Result<Vec<_>>>().unwrap_err(),
+            Err(error) => error,
+        };
+        let corrupt = error.downcast::<CorruptArchive>().unwrap();
+        (corrupt.offset(), corrupt.reason().to_string())
+    }
+
+    #[test]
+    fn test_block_archive() {
+        let view_key = ViewKey::try_from(PrivateKey::<N>::new(&mut TestRng::default()).unwrap()).unwrap();
+        let server = mock_chain_server(12, &view_key);
+        let client = testnet3(server.base_url()).with_max_block_request(5);
+
+        // An exported range reads back block by block.
+        let mut archive = Vec::new();
+        client.export_blocks(heights(2..12), &mut archive).unwrap();
+        let reader = ArchiveReader::<N, _>::open(Cursor::new(&archive)).unwrap();
+        assert_eq!(reader.block_heights(), heights(2..12));
+        let blocks = reader.collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(blocks, client.get_block_range(heights(2..12)).unwrap());
+
+        // Scanning the archive finds the records the network does, repeatedly and over any part of it.
+        let reader = ArchiveReader::<N, _>::open(Cursor::new(&archive)).unwrap();
+        for range in [2..12, 5..9, 2..3].map(heights) {
+            let expected = client.scan(view_key, range.clone()).unwrap();
+            assert_eq!(reader.scan_records(&view_key, range.clone()).unwrap(), expected);
+            assert_eq!(client.scan_records(&view_key, range).unwrap(), expected);
+        }
+        assert!(reader.scan_records(&view_key, heights(1..5)).is_err());
+
+        // Scans that decode every record find the same records.
+        let scan_options = ScanOptions { fast_prefilter: false, ..Default::default() };
+        let decoding = ArchiveReader::<N, _>::open(Cursor::new(&archive)).unwrap().with_scan_options(scan_options);
+        let records = reader.scan_records(&view_key, heights(2..12)).unwrap();
+        assert_eq!(decoding.scan_records(&view_key, heights(2..12)).unwrap(), records);
+
+        // Truncated and corrupted archives fail at the offending offset.
+        let (second_block, end) = (HEADER_SIZE + 4 + blocks[0].to_bytes_le().unwrap().len() as u64, archive.len());
+        assert_eq!(corruption(&archive[..10]), (0, "the header is truncated".to_string()));
+        assert_eq!(corruption(b"NOTBLOCKSAAAAAAAAAAAAAAAA"), (0, "this is not a block archive".to_string()));
+        assert_eq!(
+            corruption(&archive[..second_block as usize + 2]),
+            (second_block, "the archive is truncated".to_string())
+        );
+        let (offset, reason) = corruption(&archive[..end - 1]);
+        assert_eq!(offset, end as u64 - 1);
+        assert!(reason.starts_with("block 11 is truncated to"), "{reason}");
+        let mut corrupted = archive.clone();
+        corrupted[second_block as usize + 4] ^= 0xff;
+        let (offset, reason) = corruption(&corrupted);
+        assert_eq!(offset, second_block);
+        assert!(reason.starts_with("block 3 is invalid") || reason.starts_with("expected block 3"), "{reason}");
+    }
+}
@@ -14,78 +14,243 @@
 // You should have received a copy of the GNU General Public License
 // along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::AleoAPIClient;
+use crate::{
+    api::{
+        amounts::from_mapping_json,
+        compat::{check_block_format, from_node_json},
+        continuity::{check_chunks, ChunkLinks, LinkedChunk},
+        error::{check_response, is_block_request_limit, is_not_found},
+        is_linked,
+        payment::{sleep, PaymentWatcher},
+        solution::to_solution_rejection,
+        to_height_range,
+        transactions::{BlockTransactions, TransactionSeed},
+    },
+    ids::heights,
+    program::{nearest_snapshot, replay_blocks},
+    AleoAPIClient,
+    ApiError,
+    BlockHeight,
+    BlockMetadata,
+    CancellationToken,
+    Cancelled,
+    ConfirmationTimeout,
+    MappingSnapshot,
+    NodeVersion,
+    PaymentCriteria,
+    PaymentEvent,
+    PaymentTimeout,
+    ProgramCall,
+    ScanDirection,
+    ScannedRecord,
+    TransactionAborted,
+    TransactionIndexOutOfRange,
+    TransactionStatus,
+    TxIndex,
+};
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::{
+    de::{DeserializeOwned, DeserializeSeed},
+    Serialize,
+};
 use snarkvm_console::{
-    program::{Network, ProgramID},
+    account::ViewKey,
+    program::{Ciphertext, Identifier, Network, Plaintext, ProgramID, Record, Value},
     types::Field,
 };
-use snarkvm_synthesizer::{Block, Program, Transaction};
-use std::convert::TryInto;
+use snarkvm_synthesizer::{Block, EpochChallenge, Program, ProverSolution, Transaction};
+use std::{
+    convert::TryInto,
+    ops::{Range, RangeBounds},
+    time::Duration,
+};
 
 impl<N: Network> AleoAPIClient<N> {
-    pub async fn latest_height(&self) -> Result<u32> {
-        let url = format!("{}/{}/latest/height", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
+    pub async fn latest_height(&self) -> Result<BlockHeight> {
+        let url = self.url()?.route("latest/height").build();
+        match serde_json::from_str(&self.get(&url).await?) {
             Ok(height) => Ok(height),
-            Err(error) => bail!("Failed to parse the latest block height: {error}"),
+            Err(error) => bail!(ApiError::parse("the latest block height", error)),
         }
     }
 
     pub async fn latest_hash(&self) -> Result<N::BlockHash> {
-        let url = format!("{}/{}/latest/hash", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
+        let url = self.url()?.route("latest/hash").build();
+        match serde_json::from_str(&self.get(&url).await?) {
             Ok(hash) => Ok(hash),
-            Err(error) => bail!("Failed to parse the latest block hash: {error}"),
+            Err(error) => bail!(ApiError::parse("the latest block hash", error)),
         }
     }
 
     pub async fn latest_block(&self) -> Result<Block<N>> {
-        let url = format!("{}/{}/latest/block", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
-            Ok(block) => Ok(block),
-            Err(error) => bail!("Failed to parse the latest block: {error}"),
-        }
+        let url = self.url()?.route("latest/block").build();
+        let block = match self.parse_node_json(serde_json::from_str(&self.get(&url).await?))? {
+            Ok(block) => block,
+            Err(error) => bail!(ApiError::parse("the latest block", error)),
+        };
+        self.verify_network(&block).await?;
+        Ok(block)
+    }
+
+    pub async fn get_block(&self, height: BlockHeight) -> Result<Block<N>> {
+        let url = self.url()?.route("block").segment(height).build();
+        let block: Block<N> = match self.parse_node_json(serde_json::from_str(&self.get(&url).await?))? {
+            Ok(block) => block,
+            Err(error) => bail!(ApiError::parse(format!("block {height}"), error)),
+        };
+        self.check_identifier("block height", height, BlockHeight(block.height()))?;
+        self.verify_network(&block).await?;
+        Ok(block)
+    }
+
+    /// Returns the header metadata of the block at the given height.
+    ///
+    /// Nodes serve no header-only endpoint, so the block is fetched in full, but only its metadata is kept.
+    pub async fn get_block_metadata(&self, height: BlockHeight) -> Result<BlockMetadata<N>> {
+        Ok(BlockMetadata::from(&self.get_block(height).await?))
     }
 
-    pub async fn get_block(&self, height: u32) -> Result<Block<N>> {
-        let url = format!("{}/{}/block/{height}", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
-            Ok(block) => Ok(block),
-            Err(error) => bail!("Failed to parse block {height}: {error}"),
+    /// Returns the header metadata of the latest block.
+    /// Check the format of the latest block of the node against the snarkVM version this SDK is pinned to, and
+    /// return the format, if the block tells it.
+    ///
+    /// A node in the format of a newer snarkVM fails with [`ApiError::SnarkvmVersion`], a warning, as its blocks
+    /// and transactions are still adapted where possible.
+    pub async fn check_snarkvm_version(&self) -> Result<Option<NodeVersion>> {
+        let url = self.url()?.route("latest/block").build();
+        match serde_json::from_str::<serde_json::Value>(&self.get(&url).await?) {
+            Ok(block) => Ok(check_block_format::<N>(&block)?),
+            Err(error) => bail!(ApiError::parse("the latest block", error)),
         }
     }
 
-    pub async fn get_blocks(&self, start_height: u32, end_height: u32) -> Result<Vec<Block<N>>> {
+    pub async fn latest_block_metadata(&self) -> Result<BlockMetadata<N>> {
+        Ok(BlockMetadata::from(&self.latest_block().await?))
+    }
+
+    /// Returns the block with the given hash.
+    pub async fn get_block_by_hash(&self, block_hash: N::BlockHash) -> Result<Block<N>> {
+        let url = self.url()?.route("block").segment(block_hash).build();
+        let block: Block<N> = match self.parse_node_json(serde_json::from_str(&self.get(&url).await?))? {
+            Ok(block) => block,
+            Err(error) => bail!(ApiError::parse(format!("block '{block_hash}'"), error)),
+        };
+        self.check_identifier("block hash", block_hash, block.hash())?;
+        self.verify_network(&block).await?;
+        Ok(block)
+    }
+
+    /// Returns the blocks from `start_height` (inclusive) to `end_height` (exclusive) in a single request.
+    pub async fn get_blocks(&self, start_height: BlockHeight, end_height: BlockHeight) -> Result<Vec<Block<N>>> {
+        let (start_height, end_height) = (start_height.0, end_height.0);
+        let max_block_request = self.max_block_request();
         if start_height >= end_height {
             bail!("Start height must be less than end height");
-        } else if end_height - start_height > 50 {
-            bail!("Cannot request more than 50 blocks at a time");
+        } else if end_height - start_height > max_block_request {
+            bail!("Cannot request more than {max_block_request} blocks at a time");
         }
 
-        let url = format!("{}/{}/blocks?start={start_height}&end={end_height}", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
-            Ok(blocks) => Ok(blocks),
+        let url = self.url()?.route("blocks").param("start", start_height).param("end", end_height).build();
+        let blocks: Vec<Block<N>> = match self.parse_node_json(serde_json::from_str(&self.get(&url).await?))? {
+            Ok(blocks) => blocks,
             Err(error) => {
-                bail!("Failed to parse blocks {start_height} (inclusive) to {end_height} (exclusive): {error}")
+                bail!(ApiError::parse(format!("blocks {start_height} (inclusive) to {end_height} (exclusive)"), error))
             }
+        };
+        let mut genesis_unverified = false;
+        for block in &blocks {
+            genesis_unverified |= self.check_network(block)?;
+        }
+        if genesis_unverified {
+            self.verify_genesis().await?;
         }
+        Ok(blocks)
+    }
+
+    /// Returns the blocks at the given heights, requested in chunks of at most
+    /// [`AleoAPIClient::max_block_request`] blocks.
+    ///
+    /// The range may be half-open or inclusive, so `10..20` and `10..=19` return the same blocks. Each block is
+    /// checked to build on the block before it, also across chunks, as caches in front of a node may serve chunks
+    /// of different forks. Chunks that fail the check are fetched again once, before the request fails with
+    /// [`ApiError::ForkedResponse`].
+    pub async fn get_block_range(&self, block_heights: impl RangeBounds<BlockHeight>) -> Result<Vec<Block<N>>> {
+        self.get_block_range_cancellable(block_heights, &CancellationToken::new()).await
+    }
+
+    /// Returns the blocks at the given heights, stopping between chunks once the token is cancelled.
+    ///
+    /// A cancelled request fails with [`Cancelled`] holding the blocks received so far.
+    pub async fn get_block_range_cancellable(
+        &self,
+        block_heights: impl RangeBounds<BlockHeight>,
+        token: &CancellationToken,
+    ) -> Result<Vec<Block<N>>> {
+        let block_heights = to_height_range(block_heights)?;
+        if block_heights.start >= block_heights.end {
+            bail!("Start height must be less than end height");
+        }
+        let mut blocks = Vec::with_capacity(block_heights.len());
+        let (mut remaining, mut passed, mut held) = (block_heights.clone(), None, None);
+        while !remaining.is_empty() {
+            if token.is_cancelled() {
+                blocks.extend(held.map(|chunk: LinkedChunk<N, Block<N>>| chunk.items).unwrap_or_default());
+                let next_height = block_heights.start + blocks.len() as u32;
+                return Err(Cancelled::new(blocks, Some(next_height)).into());
+            }
+            if let Some(chunk) = self.get_linked_chunk(&mut remaining, &mut passed, &mut held).await? {
+                blocks.extend(chunk.items);
+            }
+        }
+        blocks.extend(held.map(|chunk| chunk.items).unwrap_or_default());
+        Ok(blocks)
     }
 
     pub async fn get_transaction(&self, transaction_id: N::TransactionID) -> Result<Transaction<N>> {
-        let url = format!("{}/{}/transaction/{transaction_id}", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
+        let url = self.url()?.route("transaction").segment(transaction_id).build();
+        let transaction: Transaction<N> = match self.parse_node_json(serde_json::from_str(&self.get(&url).await?))? {
+            Ok(transaction) => transaction,
+            Err(error) => bail!(ApiError::parse(format!("transaction '{transaction_id}'"), error)),
+        };
+        self.check_identifier("transaction ID", transaction_id, transaction.id())?;
+        Ok(transaction)
+    }
+
+    /// Returns the number of transactions in the block at the given height.
+    ///
+    /// Nodes serve no route for the transactions of a block by index, so the block is fetched, but its
+    /// transactions are only counted, not parsed.
+    pub async fn get_block_transaction_count(&self, height: BlockHeight) -> Result<usize> {
+        Ok(self.read_block_transactions(height, None).await?.count)
+    }
+
+    /// Returns the transaction at the given index in the block at the given height.
+    ///
+    /// Only the transaction at the index is parsed. Indices past the last transaction fail with
+    /// [`TransactionIndexOutOfRange`], which holds the number of transactions in the block.
+    pub async fn get_block_transaction(&self, height: BlockHeight, index: TxIndex) -> Result<Transaction<N>> {
+        let BlockTransactions { count, transaction, .. } = self.read_block_transactions(height, Some(index)).await?;
+        let transaction = transaction.ok_or_else(|| TransactionIndexOutOfRange::new(height, index, count))?;
+        match from_node_json(transaction, self.node_version)? {
             Ok(transaction) => Ok(transaction),
-            Err(error) => bail!("Failed to parse transaction '{transaction_id}': {error}"),
+            Err(error) => bail!(ApiError::parse(format!("transaction {index} of block {height}"), error)),
         }
     }
 
+    /// Returns the IDs of the transactions the block at the given height includes as aborted rather than confirmed.
+    ///
+    /// Only newer nodes abort transactions, so the blocks of nodes running the snarkVM version of this SDK have
+    /// none. The transactions of the block are skipped as they are read, not parsed.
+    pub async fn get_block_aborted_transaction_ids(&self, height: BlockHeight) -> Result<Vec<N::TransactionID>> {
+        self.read_block_transactions(height, None).await?.aborted_transaction_ids::<N>()
+    }
+
     pub async fn get_memory_pool_transactions(&self) -> Result<Vec<Transaction<N>>> {
-        let url = format!("{}/{}/memoryPool/transactions", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
+        let url = self.url()?.route("memoryPool/transactions").build();
+        match self.parse_node_json(serde_json::from_str(&self.get(&url).await?))? {
             Ok(transactions) => Ok(transactions),
-            Err(error) => bail!("Failed to parse memory pool transactions: {error}"),
+            Err(error) => bail!(ApiError::parse("memory pool transactions", error)),
         }
     }
 
@@ -93,35 +258,905 @@ impl<N: Network> AleoAPIClient<N> {
         // Prepare the program ID.
         let program_id = program_id.try_into().map_err(|_| anyhow!("Invalid program ID"))?;
         // Perform the request.
-        let url = format!("{}/{}/program/{program_id}", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
-            Ok(program) => Ok(program),
-            Err(error) => bail!("Failed to parse program {program_id}: {error}"),
+        let url = self.url()?.route("program").identifier("program ID", program_id)?.build();
+        let program: Program<N> = match serde_json::from_str(&self.get(&url).await?) {
+            Ok(program) => program,
+            Err(error) => bail!(ApiError::parse(format!("program {program_id}"), error)),
+        };
+        self.check_identifier("program ID", &program_id, program.id())?;
+        Ok(program)
+    }
+
+    /// Returns a program and every program it imports, directly or not, each program following the programs it
+    /// imports, e.g. to decode its values with a [`crate::TypeRegistry`]. The result includes `credits.aleo` when it
+    /// is imported.
+    pub async fn get_program_with_imports(&self, program_id: impl TryInto<ProgramID<N>>) -> Result<Vec<Program<N>>> {
+        let mut programs: Vec<Program<N>> = Vec::new();
+        // The programs whose imports are being fetched, with the number of their imports visited so far
+        let mut pending = vec![(self.get_program(program_id).await?, 0)];
+        while let Some((program, visited)) = pending.pop() {
+            let next = program.imports().keys().skip(visited).find(|program_id| {
+                !programs.iter().any(|import| import.id() == *program_id)
+                    && !pending.iter().any(|(import, _)| import.id() == *program_id)
+            });
+            match next.copied() {
+                Some(program_id) => {
+                    let import = self.get_program(program_id).await?;
+                    let visited = program.imports().get_index_of(&program_id).map_or(visited, |index| index + 1);
+                    pending.push((program, visited));
+                    pending.push((import, 0));
+                }
+                None => programs.push(program),
+            }
+        }
+        Ok(programs)
+    }
+
+    /// Returns the value stored under `key` in a mapping of the given program, or `None` if the key is unset.
+    pub async fn get_mapping_value(
+        &self,
+        program_id: impl TryInto<ProgramID<N>>,
+        mapping_name: &Identifier<N>,
+        key: &Plaintext<N>,
+    ) -> Result<Option<Value<N>>> {
+        let program_id = program_id.try_into().map_err(|_| anyhow!("Invalid program ID"))?;
+        let url = self
+            .url()?
+            .route("program")
+            .identifier("program ID", program_id)?
+            .route("mapping")
+            .identifier("mapping name", mapping_name)?
+            .segment(key)
+            .build();
+        match from_mapping_json(serde_json::from_str(&self.get(&url).await?))? {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                bail!(ApiError::parse(format!("the value of '{key}' in mapping {program_id}/{mapping_name}"), error))
+            }
+        }
+    }
+
+    /// Fetch the values of the given keys of a mapping into a snapshot, against which finalize scopes can be
+    /// simulated with [`crate::ProgramManager::simulate_finalize`]. Unset keys are recorded as unset.
+    pub async fn snapshot_mapping(
+        &self,
+        program_id: impl TryInto<ProgramID<N>>,
+        mapping_name: &Identifier<N>,
+        keys: &[Plaintext<N>],
+    ) -> Result<MappingSnapshot<N>> {
+        let program_id = program_id.try_into().map_err(|_| anyhow!("Invalid program ID"))?;
+        let mut snapshot = MappingSnapshot::new();
+        for key in keys {
+            match self.get_mapping_value(program_id, mapping_name, key).await? {
+                Some(value) => snapshot.insert(program_id, *mapping_name, key.clone(), value),
+                None => snapshot.insert_unset(program_id, *mapping_name, key.clone()),
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Returns the value stored under `key` in a mapping of the given program after the block at the given height,
+    /// or `None` if the key was unset.
+    ///
+    /// Clients of nodes that serve historical mapping values, set with [`AleoAPIClient::with_historical_mappings`],
+    /// request the value from the node. Other clients replay the finalize scopes of the program from genesis, see
+    /// [`AleoAPIClient::replay_mapping_value`].
+    pub async fn get_mapping_value_at_height(
+        &self,
+        program_id: impl TryInto<ProgramID<N>>,
+        mapping_name: &Identifier<N>,
+        key: &Plaintext<N>,
+        height: BlockHeight,
+    ) -> Result<Option<Value<N>>> {
+        let program_id = program_id.try_into().map_err(|_| anyhow!("Invalid program ID"))?;
+        if !self.historical_mappings() {
+            return self.replay_mapping_value(&[], program_id, mapping_name, key, height).await;
+        }
+        let url = self
+            .url()?
+            .route("program")
+            .identifier("program ID", program_id)?
+            .route("mapping")
+            .identifier("mapping name", mapping_name)?
+            .segment(key)
+            .param("height", height)
+            .build();
+        match from_mapping_json(serde_json::from_str(&self.get(&url).await?))? {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                let item = format!("'{key}' in mapping {program_id}/{mapping_name} at height {height}");
+                bail!(ApiError::parse(item, error))
+            }
+        }
+    }
+
+    /// Reconstruct the value stored under `key` in a mapping of the given program after the block at the given
+    /// height, or `None` if the key was unset, by replaying the finalize scopes of the program.
+    ///
+    /// The replay starts from the snapshot with the highest height at or below `height` that covers the key, or from
+    /// genesis, and applies the finalize scopes of the transitions of each later block in the order of the chain.
+    /// Snapshots taken with [`AleoAPIClient::snapshot_mapping`] are pinned with [`MappingSnapshot::with_height`].
+    /// The replay fails rather than approximate the value if a block is unavailable, or a finalize scope on the key
+    /// cannot be evaluated.
+    pub async fn replay_mapping_value(
+        &self,
+        snapshots: &[MappingSnapshot<N>],
+        program_id: impl TryInto<ProgramID<N>>,
+        mapping_name: &Identifier<N>,
+        key: &Plaintext<N>,
+        height: BlockHeight,
+    ) -> Result<Option<Value<N>>> {
+        let program_id = program_id.try_into().map_err(|_| anyhow!("Invalid program ID"))?;
+        let program = self.get_program(program_id).await?;
+        ensure!(
+            program.mappings().contains_key(mapping_name),
+            "Mapping '{program_id}/{mapping_name}' does not exist in storage"
+        );
+        let height = height.0;
+        let mut snapshot = nearest_snapshot(snapshots, &program_id, mapping_name, key, height);
+        // The blocks are replayed a chunk at a time, so that only one chunk is held in memory.
+        let mut start = snapshot.height().unwrap_or_default();
+        while start < height {
+            let end = height.min(start.saturating_add(self.max_block_request()));
+            let blocks = self.get_block_range(heights(start + 1..end + 1)).await?;
+            replay_blocks(&mut snapshot, &program, &blocks, end)?;
+            start = end;
+        }
+        Ok(snapshot.get(&program_id, mapping_name, key).cloned())
+    }
+
+    /// Returns the height of the block with the given hash.
+    pub async fn get_height(&self, block_hash: N::BlockHash) -> Result<BlockHeight> {
+        let url = self.url()?.route("height").segment(block_hash).build();
+        match serde_json::from_str(&self.get(&url).await?) {
+            Ok(height) => Ok(height),
+            Err(error) => bail!(ApiError::parse(format!("the height of block '{block_hash}'"), error)),
         }
     }
 
     pub async fn find_block_hash(&self, transaction_id: N::TransactionID) -> Result<N::BlockHash> {
-        let url = format!("{}/{}/find/blockHash/{transaction_id}", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
+        let url = self.url()?.route("find/blockHash").segment(transaction_id).build();
+        match serde_json::from_str(&self.get(&url).await?) {
             Ok(hash) => Ok(hash),
-            Err(error) => bail!("Failed to parse block hash: {error}"),
+            Err(error) => bail!(ApiError::parse("block hash", error)),
+        }
+    }
+
+    /// Returns the status of the transaction on the chain of the node, against the confirmation depth of the
+    /// client.
+    ///
+    /// The status is derived from the current chain on each call, so a transaction whose block was orphaned by
+    /// a reorganization is [`TransactionStatus::Pending`] again until it is included in the new chain.
+    ///
+    /// Newer nodes also find the block of a transaction that was aborted, so the block is read to tell the aborted
+    /// transactions apart, unless the client is set to [`NodeVersion::Native`].
+    pub async fn transaction_status(&self, transaction_id: N::TransactionID) -> Result<TransactionStatus<N>> {
+        let block_hash = match self.find_block_hash(transaction_id).await {
+            Ok(block_hash) => block_hash,
+            Err(error) if is_not_found(&error) => return Ok(TransactionStatus::Pending),
+            Err(error) => return Err(error),
+        };
+        // The node may still index the transaction by an orphaned block, which has no height.
+        let height = match self.get_height(block_hash).await {
+            Ok(height) => height,
+            Err(error) if is_not_found(&error) => return Ok(TransactionStatus::Pending),
+            Err(error) => return Err(error),
+        };
+        if self.node_version != Some(NodeVersion::Native) {
+            match self.get_block_aborted_transaction_ids(height).await {
+                Ok(aborted) if aborted.contains(&transaction_id) => {
+                    return Ok(TransactionStatus::Aborted { height: height.0, block_hash });
+                }
+                Ok(_) => (),
+                // The block was orphaned since its height was found.
+                Err(error) if is_not_found(&error) => return Ok(TransactionStatus::Pending),
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(TransactionStatus::included(height.0, block_hash, self.latest_height().await?.0, self.confirmation_depth))
+    }
+
+    /// Poll the status of the transaction at the given interval until it is final, passing each change of
+    /// status to `f`, including demotions to [`TransactionStatus::Pending`] by reorganizations.
+    ///
+    /// Fails with [`ConfirmationTimeout`] if the transaction is not final before the timeout, and with
+    /// [`TransactionAborted`] as soon as it is aborted, as it can then never become final.
+    pub async fn wait_for_confirmation(
+        &self,
+        transaction_id: N::TransactionID,
+        timeout: Duration,
+        poll_interval: Duration,
+        mut f: impl FnMut(&TransactionStatus<N>),
+    ) -> Result<TransactionStatus<N>> {
+        let deadline = self.clock.now() + timeout;
+        let mut last_status = None;
+        loop {
+            let status = self.transaction_status(transaction_id).await?;
+            if last_status != Some(status) {
+                f(&status);
+                last_status = Some(status);
+            }
+            if status.is_final() {
+                return Ok(status);
+            }
+            if let TransactionStatus::Aborted { height, .. } = status {
+                return Err(TransactionAborted::<N>::new(transaction_id, height).into());
+            }
+            let remaining = deadline.saturating_duration_since(self.clock.now());
+            if remaining.is_zero() {
+                return Err(ConfirmationTimeout::new(transaction_id, timeout, status).into());
+            }
+            sleep(self.clock.clone(), poll_interval.min(remaining)).await;
         }
     }
 
     /// Returns the transition ID that contains the given `input ID` or `output ID`.
     pub async fn find_transition_id(&self, input_or_output_id: Field<N>) -> Result<N::TransitionID> {
-        let url = format!("{}/{}/find/transitionID/{input_or_output_id}", self.base_url, self.chain);
-        match self.client.get(url).send().await?.json().await {
+        let url = self.url()?.route("find/transitionID").segment(input_or_output_id).build();
+        match serde_json::from_str(&self.get(&url).await?) {
             Ok(transition_id) => Ok(transition_id),
-            Err(error) => bail!("Failed to parse transition ID: {error}"),
+            Err(error) => bail!(ApiError::parse("transition ID", error)),
+        }
+    }
+
+    /// Scans the blocks at the given heights for records that match the given view key.
+    ///
+    /// The range may be half-open or inclusive, and only records created in blocks within it are returned.
+    pub async fn scan(
+        &self,
+        view_key: impl TryInto<ViewKey<N>>,
+        block_heights: impl RangeBounds<BlockHeight>,
+    ) -> Result<Vec<(Field<N>, Record<N, Ciphertext<N>>)>> {
+        self.scan_cancellable(view_key, block_heights, &CancellationToken::new()).await
+    }
+
+    /// Scans the ledger for records that match the given view key, stopping between chunks once the token
+    /// is cancelled.
+    ///
+    /// A cancelled scan fails with [`Cancelled`] holding the records found so far and the height to resume
+    /// the scan from. Dropping the future instead also stops the scan, but discards the records found.
+    pub async fn scan_cancellable(
+        &self,
+        view_key: impl TryInto<ViewKey<N>>,
+        block_heights: impl RangeBounds<BlockHeight>,
+        token: &CancellationToken,
+    ) -> Result<Vec<(Field<N>, Record<N, Ciphertext<N>>)>> {
+        let mut records = Vec::new();
+        let f = |chunk: Vec<ScannedRecord<N>>| records.extend(chunk.into_iter().map(ScannedRecord::into_pair));
+        match self.scan_chunks(view_key, block_heights, &[], token, f).await? {
+            Some(resume_height) => Err(Cancelled::new(records, Some(resume_height)).into()),
+            None => Ok(records),
+        }
+    }
+
+    /// Scans the blocks at the given heights for records that match the given view key and were created by
+    /// transitions of the given programs, or of any program if none are given.
+    ///
+    /// Each record comes with the program and function that created it. Records of other programs are skipped
+    /// before their ownership is checked, so filtering by program also speeds up the scan.
+    pub async fn scan_filtered(
+        &self,
+        view_key: impl TryInto<ViewKey<N>>,
+        block_heights: impl RangeBounds<BlockHeight>,
+        program_ids: &[ProgramID<N>],
+    ) -> Result<Vec<ScannedRecord<N>>> {
+        let (mut records, token) = (Vec::new(), CancellationToken::new());
+        self.scan_chunks(view_key, block_heights, program_ids, &token, |chunk| records.extend(chunk)).await?;
+        Ok(records)
+    }
+
+    // Scan the blocks at the given heights for the records of the given programs, or of any program if none are
+    // given, passing the records of each chunk to `f`. Returns the height to resume from, if the token was
+    // cancelled.
+    async fn scan_chunks(
+        &self,
+        view_key: impl TryInto<ViewKey<N>>,
+        block_heights: impl RangeBounds<BlockHeight>,
+        program_ids: &[ProgramID<N>],
+        token: &CancellationToken,
+        mut f: impl FnMut(Vec<ScannedRecord<N>>),
+    ) -> Result<Option<u32>> {
+        // Prepare the view key.
+        let view_key = view_key.try_into().map_err(|_| anyhow!("Invalid view key"))?;
+        let block_heights = to_height_range(block_heights)?;
+        // Compute the x-coordinate of the address.
+        let address_x_coordinate = view_key.to_address().to_x_coordinate();
+
+        // Prepare the starting block height, by rounding down to a multiple of the chunk size. Blocks outside
+        // the requested range are fetched with their chunk, but their records are skipped.
+        let max_block_request = self.max_block_request();
+        let start_block_height = block_heights.start - (block_heights.start % max_block_request);
+        // Prepare the ending block height, by rounding up to a multiple of the chunk size.
+        let end_block_height = match block_heights.end % max_block_request {
+            0 => block_heights.end,
+            remainder => block_heights.end.saturating_add(max_block_request - remainder),
+        };
+
+        let mut scan_chunk = |chunk: LinkedChunk<N, Block<N>>| {
+            let blocks = chunk.items.into_iter().filter(|block| block_heights.contains(&block.height()));
+            let blocks = blocks.collect::<Vec<_>>();
+            let blocks_scanned = blocks.len();
+            let records = blocks.into_iter().flat_map(|block| ScannedRecord::find_in_block(block, program_ids));
+
+            // Filter the records by the view key.
+            let records = records
+                .filter(|scanned| scanned.record().is_owner_with_address_x_coordinate(&view_key, &address_x_coordinate))
+                .collect::<Vec<_>>();
+            self.count_scan(blocks_scanned, records.len());
+            f(records);
+        };
+
+        // Each chunk is scanned once the chunk after it is checked to build on it.
+        let (mut remaining, mut passed, mut held) = (start_block_height..end_block_height, None, None);
+        while !remaining.is_empty() {
+            if token.is_cancelled() {
+                if let Some(chunk) = held {
+                    scan_chunk(chunk);
+                }
+                return Ok(Some(remaining.start.max(block_heights.start)));
+            }
+            if let Some(chunk) = self.get_linked_chunk(&mut remaining, &mut passed, &mut held).await? {
+                scan_chunk(chunk);
+            }
+        }
+        if let Some(chunk) = held {
+            scan_chunk(chunk);
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the calls to the given program in the latest `lookback_blocks` blocks, in the order of the chain.
+    ///
+    /// When a function name is given, only calls to that function are returned. With a block cache set by
+    /// [`AleoAPIClient::with_block_cache`], repeated polls only fetch the blocks added since the last poll.
+    pub async fn get_recent_program_activity(
+        &self,
+        program_id: impl TryInto<ProgramID<N>>,
+        function: Option<Identifier<N>>,
+        lookback_blocks: u32,
+    ) -> Result<Vec<ProgramCall<N>>> {
+        let program_id = program_id.try_into().map_err(|_| anyhow!("Invalid program ID"))?;
+        if lookback_blocks == 0 {
+            return Ok(vec![]);
+        }
+        let blocks = self.get_recent_blocks(lookback_blocks).await?;
+        let calls = blocks.iter().flat_map(|block| ProgramCall::find_in_block(block, &program_id, function.as_ref()));
+        Ok(calls.collect())
+    }
+
+    /// Wait for a payment to the account of the view key that matches the criteria, returning it once it has
+    /// the confirmations the criteria require, or else the confirmation depth of the client.
+    ///
+    /// New blocks are polled at the interval of the criteria, and the records of each block are checked against
+    /// the view key. If a block holding a matching payment is orphaned before the payment is confirmed, the
+    /// payment is dropped and matched again if it is included in the new chain. Fails with [`PaymentTimeout`]
+    /// if no payment is confirmed before the timeout.
+    pub async fn await_payment(
+        &self,
+        view_key: impl TryInto<ViewKey<N>>,
+        criteria: PaymentCriteria<N>,
+        timeout: Duration,
+    ) -> Result<PaymentEvent<N>> {
+        let view_key = view_key.try_into().map_err(|_| anyhow!("Invalid view key"))?;
+        let deadline = self.clock.now() + timeout;
+        let start_height = match criteria.start_height() {
+            Some(start_height) => start_height,
+            None => self.latest_height().await?.0 + 1,
+        };
+        let mut watcher = PaymentWatcher::new(view_key, criteria, start_height, self.confirmation_depth);
+        loop {
+            // Watch the blocks up to the tip, then wait for the next one.
+            let tip = self.latest_height().await?;
+            while watcher.next_height() <= tip.0 {
+                if let Some(payment) = watcher.watch(&self.get_block(BlockHeight(watcher.next_height())).await?) {
+                    return Ok(payment);
+                }
+            }
+            let remaining = deadline.saturating_duration_since(self.clock.now());
+            if remaining.is_zero() {
+                return Err(PaymentTimeout::new(timeout).into());
+            }
+            sleep(self.clock.clone(), watcher.poll_interval().min(remaining)).await;
+        }
+    }
+
+    /// Returns the transaction ID that contains the given `transition ID`.
+    pub async fn find_transaction_id(&self, transition_id: N::TransitionID) -> Result<N::TransactionID> {
+        let url = self.url()?.route("find/transactionID").segment(transition_id).build();
+        match serde_json::from_str(&self.get(&url).await?) {
+            Ok(transaction_id) => Ok(transaction_id),
+            Err(error) => bail!(ApiError::parse("transaction ID", error)),
+        }
+    }
+
+    /// Returns the height of the block that created the record with the given commitment, or `None` if the
+    /// chain of the node holds no such record.
+    ///
+    /// The block is located through the `find` routes of the node. Nodes without those routes are searched chunk
+    /// by chunk from the latest block down, starting with the blocks cached by [`AleoAPIClient::with_block_cache`],
+    /// and the search stops at the first chunk holding the record.
+    pub async fn find_commitment_height(&self, commitment: Field<N>) -> Result<Option<BlockHeight>> {
+        self.find_height(commitment, |block| block.commitments().any(|candidate| *candidate == commitment)).await
+    }
+
+    /// Returns the height of the block that spent the record with the given serial number, or `None` if the
+    /// record is not spent on the chain of the node.
+    ///
+    /// The block is located as in [`AleoAPIClient::find_commitment_height`].
+    pub async fn find_serial_number_height(&self, serial_number: Field<N>) -> Result<Option<BlockHeight>> {
+        self.find_height(serial_number, |block| block.serial_numbers().any(|candidate| *candidate == serial_number))
+            .await
+    }
+
+    /// Send a GET request to a route of the node that the client does not wrap, and deserialize the JSON
+    /// response into `T`.
+    ///
+    /// The path is relative to the base URL and chain of the client, e.g. `latest/height`, and may carry a
+    /// query string. Absolute URLs and paths with `..` segments are rejected with [`ApiError::InvalidPath`].
+    /// Responses are checked as for the typed methods, so errors of the node are returned as [`ApiError`]s.
+    pub async fn query<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let url = self.query_url(path)?;
+        match serde_json::from_str(&self.get(&url).await?) {
+            Ok(value) => Ok(value),
+            Err(error) => bail!(ApiError::parse(format!("the response of '{path}'"), error)),
+        }
+    }
+
+    /// Send a POST request with a JSON body to a route of the node that the client does not wrap, and
+    /// deserialize the JSON response into `T`, as [`AleoAPIClient::query`] does.
+    pub async fn query_post<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T> {
+        let url = self.query_url(path)?;
+        match serde_json::from_str(&self.post(&url, body).await?) {
+            Ok(value) => Ok(value),
+            Err(error) => bail!(ApiError::parse(format!("the response of '{path}'"), error)),
         }
     }
 
     pub async fn transaction_broadcast(&self, transaction: Transaction<N>) -> Result<Block<N>> {
-        let url = format!("{}/{}/transaction/broadcast", self.base_url, self.chain);
-        match self.client.post(url).body(serde_json::to_string(&transaction)?).send().await?.json().await {
-            Ok(block) => Ok(block),
-            Err(error) => bail!("Failed to parse memory pool transactions: {error}"),
+        let url = self.url()?.route("transaction/broadcast").build();
+        let response = self.post(&url, &transaction).await.map_err(|error| {
+            self.count_broadcast("rejected");
+            error
+        })?;
+        match self.parse_node_json(serde_json::from_str(&response))? {
+            Ok(block) => {
+                self.count_broadcast("accepted");
+                Ok(block)
+            }
+            Err(error) => {
+                self.count_broadcast("rejected");
+                bail!(ApiError::parse("memory pool transactions", error))
+            }
+        }
+    }
+
+    /// Returns the hash of the block that seeds the challenge of the current epoch of the coinbase puzzle.
+    pub async fn latest_epoch_hash(&self) -> Result<N::BlockHash> {
+        Ok(self.latest_epoch().await?.1)
+    }
+
+    /// Returns the challenge of the current epoch of the coinbase puzzle, derived from the latest height and the
+    /// epoch hash.
+    pub async fn get_epoch_challenge(&self) -> Result<EpochChallenge<N>> {
+        let (epoch_number, epoch_hash) = self.latest_epoch().await?;
+        EpochChallenge::new(epoch_number, epoch_hash, N::COINBASE_PUZZLE_DEGREE)
+    }
+
+    /// Broadcast a solution of the coinbase puzzle to the node.
+    ///
+    /// Solutions the node rejects for a reason its message tells fail with [`crate::SolutionRejected`].
+    pub async fn broadcast_solution(&self, solution: ProverSolution<N>) -> Result<()> {
+        let url = self.url()?.route("solution/broadcast").build();
+        let response = self.post(&url, &solution).await.map_err(to_solution_rejection)?;
+        match serde_json::from_str::<serde_json::Value>(&response) {
+            Ok(_) => Ok(()),
+            Err(error) => bail!(ApiError::parse("the acknowledgement of the solution", error)),
+        }
+    }
+}
+
+// The integer overloads of the methods taking typed heights and indices, kept for one release
+#[allow(clippy::type_complexity)]
+impl<N: Network> AleoAPIClient<N> {
+    /// Returns the block at the given height.
+    #[deprecated(since = "0.3.6", note = "pass a `BlockHeight` to `get_block`; removed in the next release")]
+    pub async fn get_block_raw(&self, height: u32) -> Result<Block<N>> {
+        self.get_block(BlockHeight(height)).await
+    }
+
+    /// Returns the blocks from `start_height` (inclusive) to `end_height` (exclusive) in a single request.
+    #[deprecated(since = "0.3.6", note = "pass `BlockHeight`s to `get_blocks`; removed in the next release")]
+    pub async fn get_blocks_raw(&self, start_height: u32, end_height: u32) -> Result<Vec<Block<N>>> {
+        self.get_blocks(BlockHeight(start_height), BlockHeight(end_height)).await
+    }
+
+    /// Returns the blocks at the given heights.
+    #[deprecated(since = "0.3.6", note = "pass `BlockHeight`s to `get_block_range`; removed in the next release")]
+    pub async fn get_block_range_raw(&self, block_heights: impl RangeBounds<u32>) -> Result<Vec<Block<N>>> {
+        self.get_block_range(heights(to_height_range(block_heights)?)).await
+    }
+
+    /// Returns the transaction at the given index in the block at the given height.
+    #[deprecated(since = "0.3.6", note = "pass a `TxIndex` to `get_block_transaction`; removed in the next release")]
+    pub async fn get_block_transaction_raw(&self, height: u32, index: u32) -> Result<Transaction<N>> {
+        self.get_block_transaction(BlockHeight(height), TxIndex(index)).await
+    }
+
+    /// Scan the blocks at the given heights for the records of the view key.
+    #[deprecated(since = "0.3.6", note = "pass a range of `BlockHeight` to `scan`; removed in the next release")]
+    pub async fn scan_raw(
+        &self,
+        view_key: impl TryInto<ViewKey<N>>,
+        block_heights: impl RangeBounds<u32>,
+    ) -> Result<Vec<(Field<N>, Record<N, Ciphertext<N>>)>> {
+        self.scan(view_key, heights(to_height_range(block_heights)?)).await
+    }
+
+    /// Returns the value stored under `key` in a mapping of the given program after the block at the given height.
+    #[deprecated(
+        since = "0.3.6",
+        note = "pass a `BlockHeight` to `get_mapping_value_at_height`; removed in the next release"
+    )]
+    pub async fn get_mapping_value_at_height_raw(
+        &self,
+        program_id: impl TryInto<ProgramID<N>>,
+        mapping_name: &Identifier<N>,
+        key: &Plaintext<N>,
+        height: u32,
+    ) -> Result<Option<Value<N>>> {
+        self.get_mapping_value_at_height(program_id, mapping_name, key, BlockHeight(height)).await
+    }
+
+    /// Reconstruct the value stored under `key` in a mapping of the given program after the block at the given
+    /// height, by replaying the finalize scopes of the program.
+    #[deprecated(since = "0.3.6", note = "pass a `BlockHeight` to `replay_mapping_value`; removed in the next release")]
+    pub async fn replay_mapping_value_raw(
+        &self,
+        snapshots: &[MappingSnapshot<N>],
+        program_id: impl TryInto<ProgramID<N>>,
+        mapping_name: &Identifier<N>,
+        key: &Plaintext<N>,
+        height: u32,
+    ) -> Result<Option<Value<N>>> {
+        self.replay_mapping_value(snapshots, program_id, mapping_name, key, BlockHeight(height)).await
+    }
+
+    /// Returns the height of the block with the given hash.
+    #[deprecated(since = "0.3.6", note = "`get_height` returns a `BlockHeight`; removed in the next release")]
+    pub async fn get_height_raw(&self, block_hash: N::BlockHash) -> Result<u32> {
+        Ok(self.get_height(block_hash).await?.0)
+    }
+
+    /// Returns the height of the block that created the record with the given commitment, or `None` if the
+    /// chain of the node holds no such record.
+    #[deprecated(
+        since = "0.3.6",
+        note = "`find_commitment_height` returns a `BlockHeight`; removed in the next release"
+    )]
+    pub async fn find_commitment_height_raw(&self, commitment: Field<N>) -> Result<Option<u32>> {
+        Ok(self.find_commitment_height(commitment).await?.map(|height| height.0))
+    }
+
+    /// Returns the height of the block that spent the record with the given serial number, or `None` if the
+    /// record is not spent on the chain of the node.
+    #[deprecated(
+        since = "0.3.6",
+        note = "`find_serial_number_height` returns a `BlockHeight`; removed in the next release"
+    )]
+    pub async fn find_serial_number_height_raw(&self, serial_number: Field<N>) -> Result<Option<u32>> {
+        Ok(self.find_serial_number_height(serial_number).await?.map(|height| height.0))
+    }
+}
+
+impl<N: Network> AleoAPIClient<N> {
+    // Returns the number of the current epoch of the coinbase puzzle, and the hash that seeds its challenge
+    async fn latest_epoch(&self) -> Result<(u32, N::BlockHash)> {
+        let epoch_number = self.latest_height().await?.0 / N::NUM_BLOCKS_PER_EPOCH;
+        let epoch_height = BlockHeight(epoch_number * N::NUM_BLOCKS_PER_EPOCH);
+        let epoch_hash = self.get_block_metadata(epoch_height).await?.previous_hash;
+        Ok((epoch_number, epoch_hash))
+    }
+
+    // Returns the latest `lookback_blocks` blocks, taking the blocks below the tip from the cache when it holds them
+    async fn get_recent_blocks(&self, lookback_blocks: u32) -> Result<Vec<Block<N>>> {
+        let tip = self.latest_block().await?;
+        let block_heights = (tip.height() + 1).saturating_sub(lookback_blocks)..tip.height();
+        let (mut blocks, missing) = self.cached_blocks(block_heights.clone());
+        for range in missing {
+            blocks.extend(self.get_block_range(heights(range)).await?.into_iter().map(|block| (block.height(), block)));
+        }
+        let mut blocks = blocks.into_values().chain([tip]).collect::<Vec<_>>();
+        if !is_linked(&blocks) {
+            // A reorganization replaced cached blocks, so the window is fetched again.
+            let tip = blocks.pop();
+            blocks = match block_heights.is_empty() {
+                true => vec![],
+                false => self.get_block_range(heights(block_heights)).await?,
+            };
+            blocks.extend(tip);
+            if !is_linked(&blocks) {
+                bail!("The chain was reorganized while its latest blocks were fetched");
+            }
+        }
+        self.cache_blocks(&blocks);
+        Ok(blocks)
+    }
+
+    // Check a fetched block against the custom network, if the client is configured for one, fetching the genesis
+    // block of the node the first time
+    async fn verify_network(&self, block: &Block<N>) -> Result<()> {
+        match self.check_network(block)? {
+            true => self.verify_genesis().await,
+            false => Ok(()),
+        }
+    }
+
+    // Fetch the genesis block of the node and check it against the custom network of the client
+    async fn verify_genesis(&self) -> Result<()> {
+        let url = self.url()?.route("block").segment(0).build();
+        let genesis: Block<N> = match self.parse_node_json(serde_json::from_str(&self.get(&url).await?))? {
+            Ok(block) => block,
+            Err(error) => bail!(ApiError::parse("the genesis block", error)),
+        };
+        Ok(self.check_genesis(&genesis)?)
+    }
+
+    // Locate the block holding the given input or output ID through the `find` routes, falling back to a search
+    // of the chain when the node rejects them
+    async fn find_height(&self, id: Field<N>, holds: impl Fn(&Block<N>) -> bool) -> Result<Option<BlockHeight>> {
+        let block = async {
+            let transition_id = self.find_transition_id(id).await?;
+            let transaction_id = self.find_transaction_id(transition_id).await?;
+            let block_hash = self.find_block_hash(transaction_id).await?;
+            self.get_block_by_hash(block_hash).await
+        };
+        match block.await {
+            Ok(block) if holds(&block) => {
+                self.cache_blocks(std::slice::from_ref(&block));
+                Ok(Some(BlockHeight(block.height())))
+            }
+            Ok(block) => bail!("Block '{}' returned by the node does not hold '{id}'", block.hash()),
+            Err(error) if error.is::<ApiError>() => self.search_height(holds).await,
+            Err(error) => Err(error),
+        }
+    }
+
+    // Search the chain for a block satisfying `holds`, one chunk of blocks at a time
+    async fn search_height(&self, holds: impl Fn(&Block<N>) -> bool) -> Result<Option<BlockHeight>> {
+        let (cached, missing) = self.cached_blocks(0..self.latest_height().await?.0 + 1);
+        if let Some(block) = cached.values().find(|block| holds(block)) {
+            return Ok(Some(BlockHeight(block.height())));
+        }
+        // Recent blocks are searched first, as records are more often looked up soon after they are created.
+        let max_block_request = self.max_block_request().max(1);
+        let chunks = missing.into_iter().flat_map(|range| {
+            let end = range.end;
+            range.step_by(max_block_request as usize).map(move |start| start..(start + max_block_request).min(end))
+        });
+        for chunk in chunks.collect::<Vec<_>>().into_iter().rev() {
+            let blocks = self.get_block_range(heights(chunk)).await?;
+            if let Some(block) = blocks.into_iter().find(|block| holds(block)) {
+                self.cache_blocks(std::slice::from_ref(&block));
+                return Ok(Some(BlockHeight(block.height())));
+            }
+        }
+        Ok(None)
+    }
+
+    // Request the next chunk of blocks from `start_height`, up to `end_height` (exclusive), returning the end
+    // of the chunk with its blocks. The chunk size is halved for as long as the node rejects it.
+    async fn get_block_chunk(&self, start_height: u32, end_height: u32) -> Result<(u32, Vec<Block<N>>)> {
+        loop {
+            let max_block_request = self.max_block_request();
+            let chunk_end = end_height.min(start_height.saturating_add(max_block_request));
+            match self.get_blocks(BlockHeight(start_height), BlockHeight(chunk_end)).await {
+                Ok(blocks) => return Ok((chunk_end, blocks)),
+                Err(error) if chunk_end - start_height > 1 && is_block_request_limit(&error) => {
+                    self.reduce_max_block_request(chunk_end - start_height)
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    // Request the next chunk of the remaining heights, and check it against the chunk held back before it, as
+    // `check_chunks` does, fetching both again once if the check fails. The fetched chunk is held back in turn, and
+    // the chunk held before it is returned to be passed on.
+    async fn get_linked_chunk(
+        &self,
+        remaining: &mut Range<u32>,
+        passed: &mut Option<ChunkLinks<N>>,
+        held: &mut Option<LinkedChunk<N, Block<N>>>,
+    ) -> Result<Option<LinkedChunk<N, Block<N>>>> {
+        let (end_height, blocks) = self.get_block_chunk(remaining.start, remaining.end).await?;
+        let mut next = self.to_linked_chunk(remaining.start..end_height, blocks);
+        remaining.start = end_height;
+        let check = |held: &Option<LinkedChunk<N, Block<N>>>, next: &LinkedChunk<N, Block<N>>| match held {
+            Some(held) => check_chunks(passed.as_ref(), &held.links, &next.links, ScanDirection::Forward),
+            None => next.links.check(),
+        };
+        if check(held, &next).is_err() {
+            if let Some(chunk) = held {
+                let blocks = self.get_blocks(BlockHeight(chunk.heights.start), BlockHeight(chunk.heights.end)).await?;
+                *chunk = self.to_linked_chunk(chunk.heights.clone(), blocks);
+            }
+            let blocks = self.get_blocks(BlockHeight(next.heights.start), BlockHeight(end_height)).await?;
+            next = self.to_linked_chunk(next.heights.clone(), blocks);
+            check(held, &next)?;
+        }
+        let chunk = held.replace(next);
+        if let Some(chunk) = &chunk {
+            *passed = Some(chunk.links.clone());
+        }
+        Ok(chunk)
+    }
+
+    // Collect the blocks of a chunk, in ascending order
+    fn to_linked_chunk(&self, heights: Range<u32>, blocks: Vec<Block<N>>) -> LinkedChunk<N, Block<N>> {
+        let mut chunk = LinkedChunk::new(heights);
+        blocks.into_iter().for_each(|block| chunk.push(block, &mut |items, block| items.push(block)));
+        chunk
+    }
+
+    // Request the block at the given height, reading only its height, the number of its transactions, and the
+    // transaction at the given index, if any
+    async fn read_block_transactions(&self, height: BlockHeight, index: Option<TxIndex>) -> Result<BlockTransactions> {
+        let url = self.url()?.route("block").segment(height).build();
+        let body = self.get(&url).await?;
+        let mut deserializer = serde_json::Deserializer::from_str(&body);
+        let transactions = TransactionSeed::new(index.map(TxIndex::as_usize)).deserialize(&mut deserializer);
+        let transactions = match transactions.and_then(|transactions| deserializer.end().map(|()| transactions)) {
+            Ok(transactions) => transactions,
+            Err(error) => bail!(ApiError::parse(format!("block {height}"), error)),
+        };
+        self.check_identifier("block height", height, BlockHeight(transactions.height))?;
+        Ok(transactions)
+    }
+
+    // Send a GET request and return the body of the JSON response
+    async fn get(&self, url: &str) -> Result<String> {
+        let response = self.send(url, |client| client.get(url)).await.map_err(|error| {
+            self.count_request(url, None);
+            error
+        })?;
+        self.read_response(url, response).await
+    }
+
+    // Send a POST request with a JSON body and return the body of the JSON response
+    async fn post(&self, url: &str, body: &impl Serialize) -> Result<String> {
+        let body = serde_json::to_string(body)?;
+        let response = self.send(url, |client| client.post(url).body(body.clone())).await;
+        let response = response.map_err(|error| {
+            self.count_request(url, None);
+            error
+        })?;
+        self.read_response(url, response).await
+    }
+
+    // Send the request built by `build`. When the connection through the SOCKS proxy of the client fails, the
+    // request is sent again over a direct connection if the proxy allows it, and fails with [`ApiError::Proxy`]
+    // otherwise.
+    async fn send(
+        &self,
+        url: &str,
+        build: impl Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        let response = build(&self.client).send().await;
+        #[cfg(feature = "socks")]
+        if let Err(error) = &response {
+            if let Some(direct_client) = self.direct_client(url).filter(|_| error.is_connect()) {
+                return Ok(build(direct_client).send().await?);
+            }
+            // The error of reqwest quotes the URL, so only its cause is kept.
+            let reason = std::error::Error::source(error).map_or_else(|| error.to_string(), ToString::to_string);
+            if let Some(proxy_error) = self.proxy_error(reason).filter(|_| error.is_connect()) {
+                return Err(proxy_error.into());
+            }
+        }
+        response.map_err(|error| ApiError::connection(url, error.without_url()).into())
+    }
+
+    // Read the body of the response to a request to the URL, once its status and content type are checked.
+    // Reading fails as soon as the body exceeds the maximum response size.
+    async fn read_response(&self, url: &str, mut response: reqwest::Response) -> Result<String> {
+        self.count_request(url, Some(response.status().as_u16()));
+        let limit = self.max_response_size;
+        if response.content_length().map_or(false, |length| length > limit) {
+            return Err(ApiError::TooLarge { limit }.into());
+        }
+        let status = response.status().as_u16();
+        let content_type = response.headers().get(reqwest::header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+        let content_type = content_type.map(ToString::to_string);
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (body.len() + chunk.len()) as u64 > limit {
+                return Err(ApiError::TooLarge { limit }.into());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(check_response(status, content_type.as_deref(), String::from_utf8(body)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        test_helpers::{mock_find_server, sample_transaction, sample_transition, MockResponse, MockServer},
+        testnet3,
+        MockClock,
+    };
+    use snarkvm_console::{network::Testnet3, prelude::Uniform};
+    use snarkvm_utilities::TestRng;
+    use std::{future::Future, time::Instant};
+
+    type N = Testnet3;
+
+    // Run the future to completion on a runtime of the current thread
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn test_api_find_heights_by_routes() {
+        let (server, ids, requests) = mock_find_server(12, true);
+        let client = testnet3(server.base_url()).with_block_cache(16);
+
+        // The blocks creating and spending a record are located through the `find` routes, without a search.
+        let (commitment, serial_number) = ids[6];
+        assert_eq!(block_on(client.find_commitment_height(commitment)).unwrap(), Some(BlockHeight(7)));
+        assert_eq!(block_on(client.find_serial_number_height(serial_number)).unwrap(), Some(BlockHeight(7)));
+        assert!(requests.lock().unwrap().iter().all(|path| !path.contains("/blocks?")));
+        let (cached, _) = client.cached_blocks(7..8);
+        assert_eq!(cached.keys().copied().collect::<Vec<_>>(), [7]);
+
+        // IDs unknown to the node fall back to a search, which finds nothing.
+        let unknown = Field::rand(&mut TestRng::default());
+        assert_eq!(block_on(client.find_commitment_height(unknown)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_api_find_heights_by_search() {
+        let (server, ids, requests) = mock_find_server(30, false);
+        let client = testnet3(server.base_url()).with_max_block_request(4).with_block_cache(4);
+        let block_requests = || requests.lock().unwrap().iter().filter(|path| path.contains("/blocks?")).count();
+
+        // Without the `find` routes, the chain is searched in chunks until the record is found.
+        for (height, (commitment, serial_number)) in (1..).zip(&ids) {
+            assert_eq!(block_on(client.find_commitment_height(*commitment)).unwrap(), Some(BlockHeight(height)));
+            let found = block_on(client.find_serial_number_height(*serial_number)).unwrap();
+            assert_eq!(found, Some(BlockHeight(height)));
         }
+
+        // Blocks found before are cached, and are not fetched again.
+        let fetched = block_requests();
+        assert!(fetched > 0);
+        assert_eq!(block_on(client.find_commitment_height(ids[28].0)).unwrap(), Some(BlockHeight(29)));
+        assert_eq!(block_requests(), fetched);
+
+        // Records that are not on the chain are not found after all chunks are searched.
+        let rng = &mut TestRng::default();
+        assert_eq!(block_on(client.find_commitment_height(Field::rand(rng))).unwrap(), None);
+        assert_eq!(block_on(client.find_serial_number_height(Field::rand(rng))).unwrap(), None);
+    }
+
+    #[test]
+    fn test_api_wait_for_confirmation_timeout() {
+        let transaction_id = sample_transaction([sample_transition(&[], &[], &mut TestRng::default())]).id();
+        let server = MockServer::start(|_| Some(MockResponse::text(404, "Transaction not found")));
+        let clock = MockClock::new();
+        let client = testnet3(server.base_url()).with_clock(clock.clone());
+
+        // The polls sleep on the clock, so a transaction that is never included times out without waiting.
+        let started = Instant::now();
+        let wait =
+            client.wait_for_confirmation(transaction_id, Duration::from_secs(60), Duration::from_secs(25), |_| ());
+        let error = block_on(wait).unwrap_err();
+        let timeout = error.downcast_ref::<ConfirmationTimeout<N>>().unwrap();
+        assert_eq!((timeout.transaction_id(), timeout.status()), (transaction_id, TransactionStatus::Pending));
+        assert_eq!(clock.elapsed(), Duration::from_secs(60));
+        assert!(started.elapsed() < Duration::from_secs(25));
     }
 }
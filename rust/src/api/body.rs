@@ -0,0 +1,178 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{api::compat::from_node_json, ApiError, BudgetExhausted, NodeVersion};
+
+use anyhow::Result;
+use serde::{
+    de::{self, DeserializeSeed, SeqAccess, Visitor},
+    Deserializer,
+};
+use serde_json::de::IoRead;
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Block;
+use std::{
+    fmt,
+    io::{self, Read},
+    marker::PhantomData,
+};
+
+/// A reader that fails once more than `limit` bytes were read from the inner reader
+pub(crate) struct LimitedReader<R: Read> {
+    inner: R,
+    remaining: u64,
+    limit: u64,
+}
+
+impl<R: Read> LimitedReader<R> {
+    pub(crate) fn new(inner: R, limit: u64) -> Self {
+        Self { inner, remaining: limit, limit }
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Read one byte past the limit, to tell a body of exactly `limit` bytes from a longer one.
+        let max = buf.len().min(usize::try_from(self.remaining.saturating_add(1)).unwrap_or(usize::MAX));
+        let read = self.inner.read(&mut buf[..max])?;
+        if read as u64 > self.remaining {
+            return Err(io::Error::new(io::ErrorKind::Other, ApiError::TooLarge { limit: self.limit }));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+// Convert an error raised while reading a body, recovering the `ApiError` or `BudgetExhausted` it may carry
+pub(crate) fn read_error(error: io::Error) -> anyhow::Error {
+    if let Some(exhausted) = error.get_ref().and_then(|error| error.downcast_ref::<BudgetExhausted<()>>()) {
+        return exhausted.clone().into();
+    }
+    match error.get_ref().and_then(|error| error.downcast_ref::<ApiError>()) {
+        Some(api_error) => api_error.clone().into(),
+        None => error.into(),
+    }
+}
+
+// Deserialize a JSON body directly from a reader. Errors raised while reading the body are returned as the
+// outer error, and errors in the JSON itself as the inner error.
+pub(crate) fn deserialize_body<R: Read, T>(
+    reader: R,
+    deserialize: impl FnOnce(&mut serde_json::Deserializer<IoRead<R>>) -> serde_json::Result<T>,
+) -> Result<serde_json::Result<T>> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    match deserialize(&mut deserializer).and_then(|value| deserializer.end().map(|()| value)) {
+        Err(error) if error.is_io() => Err(read_error(error.into())),
+        result => Ok(result),
+    }
+}
+
+/// Deserializes a JSON array of blocks, passing each block to a callback as soon as it is parsed, so that
+/// only one block is held in memory at a time
+///
+/// Blocks are parsed in the given node format, or in the format their fields tell. A block that cannot be
+/// adapted to this SDK stops the deserialization, and its error is kept in the seed.
+pub(crate) struct BlockSeed<'a, N: Network, F: FnMut(Block<N>)> {
+    callback: &'a mut F,
+    version: Option<NodeVersion>,
+    mismatch: Option<ApiError>,
+    _network: PhantomData<N>,
+}
+
+impl<'a, N: Network, F: FnMut(Block<N>)> BlockSeed<'a, N, F> {
+    pub(crate) fn new(callback: &'a mut F, version: Option<NodeVersion>) -> Self {
+        Self { callback, version, mismatch: None, _network: PhantomData }
+    }
+
+    /// Returns the error of a block in the format of another node version, if the deserialization stopped at one
+    pub(crate) fn take_mismatch(&mut self) -> Option<ApiError> {
+        self.mismatch.take()
+    }
+}
+
+impl<'de, N: Network, F: FnMut(Block<N>)> DeserializeSeed<'de> for &mut BlockSeed<'_, N, F> {
+    type Value = u32;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, N: Network, F: FnMut(Block<N>)> Visitor<'de> for &mut BlockSeed<'_, N, F> {
+    type Value = u32;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of blocks")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut count = 0;
+        while let Some(value) = seq.next_element::<serde_json::Value>()? {
+            match from_node_json::<Block<N>>(value, self.version) {
+                Ok(Ok(block)) => (self.callback)(block),
+                Ok(Err(error)) => return Err(de::Error::custom(error)),
+                Err(mismatch) => {
+                    let error = de::Error::custom(&mismatch);
+                    self.mismatch = Some(mismatch);
+                    return Err(error);
+                }
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, CurrentNetwork};
+    use serde::Deserialize;
+
+    #[test]
+    fn test_limited_reader() {
+        // Bodies up to the limit are read in full.
+        let mut body = String::new();
+        LimitedReader::new("[1,2,3]".as_bytes(), 7).read_to_string(&mut body).unwrap();
+        assert_eq!(body, "[1,2,3]");
+
+        // Longer bodies fail with the limit.
+        let error = LimitedReader::new("[1,2,3]".as_bytes(), 6).read_to_string(&mut body).unwrap_err();
+        assert_eq!(read_error(error).downcast::<ApiError>().unwrap(), ApiError::TooLarge { limit: 6 });
+        let reader = LimitedReader::new("[1,2,3]".as_bytes(), 6);
+        let error = deserialize_body(reader, |deserializer| Vec::<u8>::deserialize(deserializer));
+        assert_eq!(error.unwrap_err().to_string(), "The response exceeds the maximum size of 6 bytes");
+    }
+
+    #[test]
+    fn test_block_seed() {
+        let block = genesis_block();
+        let body = format!("[{block},{block}]");
+        let mut blocks = Vec::new();
+        let mut callback = |block: Block<CurrentNetwork>| blocks.push(block);
+        let mut seed = BlockSeed::new(&mut callback, None);
+        let result = deserialize_body(body.as_bytes(), |deserializer| (&mut seed).deserialize(deserializer));
+        assert_eq!(result.unwrap().unwrap(), 2);
+        assert_eq!(blocks, [block.clone(), block]);
+
+        // Malformed JSON is reported as a parse error.
+        let mut ignore = |_: Block<CurrentNetwork>| ();
+        let mut seed = BlockSeed::new(&mut ignore, None);
+        let result = deserialize_body("[1]".as_bytes(), |deserializer| (&mut seed).deserialize(deserializer));
+        assert!(result.unwrap().is_err());
+        assert!(seed.take_mismatch().is_none());
+    }
+}
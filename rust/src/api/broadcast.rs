@@ -0,0 +1,116 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::Clock;
+use crate::mutex::lock;
+
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Block;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The transactions broadcast by a client, so that re-submitting one returns the acknowledgement of its first
+/// broadcast instead of posting it again
+pub(crate) struct BroadcastCache<N: Network> {
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+    broadcasts: Mutex<HashMap<N::TransactionID, Broadcast<N>>>,
+    acknowledged: Condvar,
+}
+
+// The state of the broadcast of a transaction
+enum Broadcast<N: Network> {
+    // The transaction is being posted
+    InFlight,
+    // The node acknowledged the transaction at the given time
+    Acknowledged(Block<N>, Instant),
+}
+
+/// The outcome of claiming the broadcast of a transaction
+pub(crate) enum Claim<'a, N: Network> {
+    /// The transaction was broadcast within the TTL, and the node acknowledged it with the block
+    Acknowledged(Block<N>),
+    /// The caller posts the transaction, and finishes the claim with the acknowledgement of the node
+    Broadcast(ClaimGuard<'a, N>),
+}
+
+/// The claim of a caller posting a transaction. Dropping the claim without finishing it, e.g. when the post
+/// fails, lets the next caller post the transaction.
+pub(crate) struct ClaimGuard<'a, N: Network> {
+    cache: &'a BroadcastCache<N>,
+    transaction_id: N::TransactionID,
+}
+
+impl<N: Network> BroadcastCache<N> {
+    /// The default time for which acknowledgements are kept
+    pub(crate) const DEFAULT_TTL: Duration = Duration::from_secs(600);
+
+    pub(crate) fn new(ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self { ttl, clock, broadcasts: Mutex::new(HashMap::new()), acknowledged: Condvar::new() }
+    }
+
+    /// Returns the time for which acknowledgements are kept.
+    pub(crate) fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the acknowledgement of a broadcast of the transaction within the TTL, waiting for a broadcast in
+    /// flight, or claims the broadcast for the caller if there is none.
+    pub(crate) fn claim(&self, transaction_id: N::TransactionID) -> Claim<'_, N> {
+        let mut broadcasts = lock(&self.broadcasts);
+        loop {
+            let now = self.clock.now();
+            broadcasts.retain(|_, broadcast| match broadcast {
+                Broadcast::InFlight => true,
+                Broadcast::Acknowledged(_, at) => now.duration_since(*at) < self.ttl,
+            });
+            match broadcasts.get(&transaction_id) {
+                Some(Broadcast::InFlight) => broadcasts = self.acknowledged.wait(broadcasts).unwrap(),
+                Some(Broadcast::Acknowledged(block, _)) => return Claim::Acknowledged(block.clone()),
+                None => {
+                    broadcasts.insert(transaction_id, Broadcast::InFlight);
+                    return Claim::Broadcast(ClaimGuard { cache: self, transaction_id });
+                }
+            }
+        }
+    }
+
+    /// Record the acknowledgement of a broadcast that was not claimed, e.g. a forced one.
+    pub(crate) fn acknowledge(&self, transaction_id: N::TransactionID, block: Block<N>) {
+        let acknowledged = Broadcast::Acknowledged(block, self.clock.now());
+        lock(&self.broadcasts).insert(transaction_id, acknowledged);
+        self.acknowledged.notify_all();
+    }
+}
+
+impl<N: Network> ClaimGuard<'_, N> {
+    /// Record the acknowledgement of the node, returning it to duplicate broadcasts within the TTL.
+    pub(crate) fn finish(self, block: Block<N>) {
+        self.cache.acknowledge(self.transaction_id, block);
+        // The claim is finished, so dropping it must not remove the acknowledgement.
+        std::mem::forget(self);
+    }
+}
+
+impl<N: Network> Drop for ClaimGuard<'_, N> {
+    fn drop(&mut self) {
+        lock(&self.cache.broadcasts).remove(&self.transaction_id);
+        self.cache.acknowledged.notify_all();
+    }
+}
@@ -0,0 +1,478 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Clock, ErrorCode, SystemClock};
+
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        Condvar,
+        Mutex,
+        MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+/// A cap on the requests a client sends and the response bytes it downloads, for metered environments
+///
+/// Every request debits the budget before it is sent, and every byte of a response body as it is read. Once a
+/// cap is reached, the operation that needed more fails with [`BudgetExhausted`], which holds the results of
+/// scans and walks over ranges of blocks gathered so far, and the height to resume them from. Clones of a budget
+/// share its consumption, so a budget attached to several clients caps them together. With a window, the
+/// consumption is reset at the start of every window, e.g. to allow a number of requests per minute.
+///
+/// Requests are debited with the [`RequestPriority`] of their client. While interactive work started with
+/// [`Budget::begin_interactive`] is in progress, background requests are shed with [`Preempted`], so that the
+/// requests a user waits for are not queued behind a backfill, and background requests never use the requests
+/// kept for interactive ones with [`Budget::with_interactive_reserve`].
+#[derive(Clone, Debug)]
+pub struct Budget {
+    max_requests: Option<u64>,
+    max_response_bytes: Option<u64>,
+    window: Option<Duration>,
+    interactive_reserve: u64,
+    consumption: Arc<Consumption>,
+}
+
+// The consumption shared by the clones of a budget, in the current window
+#[derive(Debug)]
+struct Consumption {
+    requests: AtomicU64,
+    response_bytes: AtomicU64,
+    clock: Arc<dyn Clock>,
+    started: Instant,
+    window_index: AtomicU64,
+    // The number of interactive works in progress, notified when it drops to zero
+    interactive: (Mutex<usize>, Condvar),
+}
+
+impl Consumption {
+    fn new(clock: Arc<dyn Clock>) -> Self {
+        let started = clock.now();
+        let (requests, response_bytes, window_index) = (AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0));
+        let interactive = (Mutex::new(0), Condvar::new());
+        Self { requests, response_bytes, clock, started, window_index, interactive }
+    }
+
+    fn interactive(&self) -> MutexGuard<'_, usize> {
+        self.interactive.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The priority of the requests of a client, as debited from its [`Budget`], or of the jobs of a
+/// [`crate::ExecutionPool`], whose interactive jobs are proven before its background ones
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RequestPriority {
+    /// Requests a user waits for, such as refreshing a balance or polling a confirmation
+    #[default]
+    Interactive,
+    /// Requests that may wait, such as the chunks of a backfill, which are shed while interactive work is in
+    /// progress
+    Background,
+}
+
+/// Interactive work in progress on a [`Budget`], from [`Budget::begin_interactive`] until it is dropped
+#[must_use = "the interactive work ends when it is dropped"]
+#[derive(Debug)]
+pub struct InteractiveWork {
+    consumption: Arc<Consumption>,
+}
+
+impl Drop for InteractiveWork {
+    fn drop(&mut self) {
+        let mut interactive = self.consumption.interactive();
+        *interactive -= 1;
+        if *interactive == 0 {
+            self.consumption.interactive.1.notify_all();
+        }
+    }
+}
+
+/// The requests and response bytes debited from a [`Budget`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BudgetUsage {
+    /// The number of requests sent
+    pub requests: u64,
+    /// The number of bytes of response bodies read
+    pub response_bytes: u64,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        let consumption = Arc::new(Consumption::new(Arc::new(SystemClock)));
+        Self { max_requests: None, max_response_bytes: None, window: None, interactive_reserve: 0, consumption }
+    }
+}
+
+impl Budget {
+    /// Create a budget without caps, which only measures the consumption of the clients it is attached to.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap the number of requests.
+    pub fn with_max_requests(mut self, max_requests: u64) -> Self {
+        self.max_requests = Some(max_requests);
+        self
+    }
+
+    /// Cap the number of bytes of response bodies.
+    pub fn with_max_response_bytes(mut self, max_response_bytes: u64) -> Self {
+        self.max_response_bytes = Some(max_response_bytes);
+        self
+    }
+
+    /// Reset the consumption at the start of every window of the given duration.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = Some(window).filter(|window| !window.is_zero());
+        self
+    }
+
+    /// Keep the last `requests` requests of the cap, in each window if there is one, for interactive requests.
+    pub fn with_interactive_reserve(mut self, requests: u64) -> Self {
+        self.interactive_reserve = requests;
+        self
+    }
+
+    /// Measure the windows with the given clock, instead of the clock of the operating system. The consumption is
+    /// reset, and no longer shared with earlier clones of the budget.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.consumption = Arc::new(Consumption::new(Arc::new(clock)));
+        self
+    }
+
+    /// Returns the maximum number of requests, if it is capped.
+    pub fn max_requests(&self) -> Option<u64> {
+        self.max_requests
+    }
+
+    /// Returns the maximum number of bytes of response bodies, if it is capped.
+    pub fn max_response_bytes(&self) -> Option<u64> {
+        self.max_response_bytes
+    }
+
+    /// Returns the duration of the windows after which the consumption is reset, if there is one.
+    pub fn window(&self) -> Option<Duration> {
+        self.window
+    }
+
+    /// Returns the number of requests of the cap kept for interactive requests.
+    pub fn interactive_reserve(&self) -> u64 {
+        self.interactive_reserve
+    }
+
+    /// Start interactive work, during which the background requests of the clients sharing the budget are shed
+    /// with [`Preempted`]. The work ends when the returned guard is dropped.
+    pub fn begin_interactive(&self) -> InteractiveWork {
+        *self.consumption.interactive() += 1;
+        InteractiveWork { consumption: self.consumption.clone() }
+    }
+
+    /// Returns `true` while interactive work is in progress.
+    pub fn is_interactive(&self) -> bool {
+        *self.consumption.interactive() > 0
+    }
+
+    /// Wait until no interactive work is in progress, or the timeout elapses, and return `true` in the first case.
+    pub fn wait_for_interactive(&self, timeout: Duration) -> bool {
+        let (_, condvar) = &self.consumption.interactive;
+        let interactive = self.consumption.interactive();
+        let wait = condvar.wait_timeout_while(interactive, timeout, |interactive| *interactive > 0);
+        let (interactive, _) = wait.unwrap_or_else(|poisoned| poisoned.into_inner());
+        *interactive == 0
+    }
+
+    /// Returns the consumption of the current window, or since the budget was created if it has no window.
+    pub fn usage(&self) -> BudgetUsage {
+        self.roll_window();
+        BudgetUsage {
+            requests: self.consumption.requests.load(Ordering::SeqCst),
+            response_bytes: self.consumption.response_bytes.load(Ordering::SeqCst),
+        }
+    }
+
+    // Debit an interactive request, unless the requests are exhausted
+    pub(crate) fn debit_request(&self) -> Result<(), BudgetExhausted<()>> {
+        self.debit(0)
+    }
+
+    // Debit a request of the given priority. Background requests are shed while interactive work is in progress,
+    // and exhaust the budget before the requests kept for interactive ones.
+    pub(crate) fn debit_request_with_priority(&self, priority: RequestPriority) -> anyhow::Result<()> {
+        match priority {
+            RequestPriority::Interactive => Ok(self.debit_request()?),
+            RequestPriority::Background if self.is_interactive() => Err(Preempted.into()),
+            RequestPriority::Background => Ok(self.debit(self.interactive_reserve)?),
+        }
+    }
+
+    // Debit a request, unless the requests are exhausted, leaving the given number of requests of the cap
+    fn debit(&self, reserved: u64) -> Result<(), BudgetExhausted<()>> {
+        self.roll_window();
+        let max_requests = self.max_requests.map_or(u64::MAX, |max_requests| max_requests.saturating_sub(reserved));
+        let debit = |requests: u64| (requests < max_requests).then_some(requests + 1);
+        match self.consumption.requests.fetch_update(Ordering::SeqCst, Ordering::SeqCst, debit) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(self.exhausted()),
+        }
+    }
+
+    // Returns the number of response bytes that may still be read
+    fn remaining_response_bytes(&self) -> u64 {
+        self.roll_window();
+        let read = self.consumption.response_bytes.load(Ordering::SeqCst);
+        self.max_response_bytes.map_or(u64::MAX, |max_response_bytes| max_response_bytes.saturating_sub(read))
+    }
+
+    // Debit the bytes of a response body, failing if they exceed the cap
+    fn debit_response_bytes(&self, bytes: u64) -> Result<(), BudgetExhausted<()>> {
+        let read = self.consumption.response_bytes.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        match self.max_response_bytes.map_or(false, |max_response_bytes| read > max_response_bytes) {
+            true => Err(self.exhausted()),
+            false => Ok(()),
+        }
+    }
+
+    // Reset the consumption if a new window started since it was last debited. Of the threads noticing the new
+    // window, only the one that advances the window index resets the consumption.
+    fn roll_window(&self) {
+        let Some(window) = self.window else {
+            return;
+        };
+        let consumption = &self.consumption;
+        let elapsed = consumption.clock.now().duration_since(consumption.started);
+        let window_index = (elapsed.as_nanos() / window.as_nanos()) as u64;
+        let current = consumption.window_index.load(Ordering::SeqCst);
+        if window_index > current
+            && consumption
+                .window_index
+                .compare_exchange(current, window_index, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        {
+            consumption.requests.store(0, Ordering::SeqCst);
+            consumption.response_bytes.store(0, Ordering::SeqCst);
+        }
+    }
+
+    fn exhausted(&self) -> BudgetExhausted<()> {
+        BudgetExhausted::new(self.usage(), (), None)
+    }
+}
+
+/// The error returned by an operation that stopped because the [`Budget`] of its client was exhausted
+///
+/// The error carries the consumption of the budget, the results gathered before the operation stopped, and for
+/// operations over a range of blocks, the height to resume from, e.g. with a fresh budget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BudgetExhausted<T> {
+    usage: BudgetUsage,
+    partial: T,
+    resume_height: Option<u32>,
+}
+
+impl<T> BudgetExhausted<T> {
+    pub(crate) fn new(usage: BudgetUsage, partial: T, resume_height: Option<u32>) -> Self {
+        Self { usage, partial, resume_height }
+    }
+
+    /// Returns the consumption of the budget when the operation stopped.
+    pub fn usage(&self) -> BudgetUsage {
+        self.usage
+    }
+
+    /// Returns the results gathered before the operation stopped.
+    pub fn partial(&self) -> &T {
+        &self.partial
+    }
+
+    /// Returns the results gathered before the operation stopped, discarding the error.
+    pub fn into_partial(self) -> T {
+        self.partial
+    }
+
+    /// Returns the height of the first block that was not processed, for operations over a range of blocks.
+    pub fn resume_height(&self) -> Option<u32> {
+        self.resume_height
+    }
+}
+
+impl<T> fmt::Display for BudgetExhausted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let BudgetUsage { requests, response_bytes } = self.usage;
+        write!(f, "The budget was exhausted after {requests} requests and {response_bytes} response bytes")?;
+        match self.resume_height {
+            Some(height) => write!(f, ", before block {height}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: fmt::Debug> Error for BudgetExhausted<T> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&ErrorCode::BudgetExhausted)
+    }
+}
+
+/// The error returned by a background request shed while interactive work was in progress on its [`Budget`]
+///
+/// Operations over ranges of blocks fail with it as they are, without the results gathered so far, as they are
+/// meant to be resumed from their own checkpoint once the interactive work completes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Preempted;
+
+impl fmt::Display for Preempted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The background request was shed while interactive work is in progress")
+    }
+}
+
+impl Error for Preempted {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&ErrorCode::Preempted)
+    }
+}
+
+// Attach the partial results of an operation, and the height to resume it from, to an error raised by an
+// exhausted budget. Other errors are returned as they are.
+pub(crate) fn checkpoint<T: fmt::Debug + Send + Sync + 'static>(
+    error: anyhow::Error,
+    partial: T,
+    resume_height: Option<u32>,
+) -> anyhow::Error {
+    match error.downcast::<BudgetExhausted<()>>() {
+        Ok(exhausted) => {
+            let resume_height = resume_height.or(exhausted.resume_height);
+            BudgetExhausted::new(exhausted.usage, partial, resume_height).into()
+        }
+        Err(error) => error,
+    }
+}
+
+/// A reader that debits the bytes read from the inner reader from a budget, and fails once it is exhausted
+pub(crate) struct BudgetReader<R: Read> {
+    inner: R,
+    budget: Budget,
+}
+
+impl<R: Read> BudgetReader<R> {
+    pub(crate) fn new(inner: R, budget: Budget) -> Self {
+        Self { inner, budget }
+    }
+}
+
+impl<R: Read> Read for BudgetReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Read one byte past the remaining budget, to tell a body that ends at the cap from a longer one.
+        let remaining = self.budget.remaining_response_bytes();
+        let max = buf.len().min(usize::try_from(remaining.saturating_add(1)).unwrap_or(usize::MAX));
+        let read = self.inner.read(&mut buf[..max])?;
+        self.budget.debit_response_bytes(read as u64).map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockClock;
+
+    use std::{io::Cursor, thread};
+
+    #[test]
+    fn test_budget_caps() {
+        let budget = Budget::new().with_max_requests(2).with_max_response_bytes(10);
+        assert!(budget.debit_request().is_ok());
+        assert!(budget.clone().debit_request().is_ok());
+        let exhausted = budget.debit_request().unwrap_err();
+        assert_eq!(exhausted.usage(), BudgetUsage { requests: 2, response_bytes: 0 });
+        assert_eq!(exhausted.to_string(), "The budget was exhausted after 2 requests and 0 response bytes");
+
+        // A body that ends at the cap is read in full, and a longer one fails.
+        let mut body = String::new();
+        BudgetReader::new(Cursor::new("0123456"), budget.clone()).read_to_string(&mut body).unwrap();
+        assert_eq!(body, "0123456");
+        let error = BudgetReader::new(Cursor::new("0123"), budget.clone()).read_to_string(&mut body).unwrap_err();
+        assert!(error.get_ref().unwrap().is::<BudgetExhausted<()>>());
+        assert_eq!(budget.usage().response_bytes, 11);
+        let mut body = Vec::new();
+        BudgetReader::new(Cursor::new(""), Budget::new().with_max_response_bytes(0)).read_to_end(&mut body).unwrap();
+    }
+
+    #[test]
+    fn test_budget_is_shared_across_threads() {
+        let budget = Budget::new().with_max_requests(500);
+        let debited = thread::scope(|scope| {
+            let workers = (0..8).map(|_| scope.spawn(|| (0..100).filter(|_| budget.debit_request().is_ok()).count()));
+            workers.collect::<Vec<_>>().into_iter().map(|worker| worker.join().unwrap()).sum::<usize>()
+        });
+        assert_eq!(debited, 500);
+        assert_eq!(budget.usage().requests, 500);
+    }
+
+    #[test]
+    fn test_budget_window() {
+        let clock = MockClock::new();
+        let budget = Budget::new().with_max_requests(1).with_window(Duration::from_secs(60)).with_clock(clock.clone());
+        assert!(budget.debit_request().is_ok());
+        assert!(budget.debit_request().is_err());
+        clock.advance(Duration::from_secs(59));
+        assert!(budget.debit_request().is_err());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(budget.usage(), BudgetUsage::default());
+        assert!(budget.debit_request().is_ok());
+    }
+
+    #[test]
+    fn test_budget_priorities() {
+        let budget = Budget::new().with_max_requests(3).with_interactive_reserve(1);
+        assert!(budget.debit_request_with_priority(RequestPriority::Background).is_ok());
+
+        // Background requests are shed while interactive work is in progress, and interactive requests go on.
+        let interactive = budget.clone().begin_interactive();
+        let error = budget.debit_request_with_priority(RequestPriority::Background).unwrap_err();
+        assert_eq!(crate::error_code(&error), ErrorCode::Preempted);
+        assert!(!budget.wait_for_interactive(Duration::from_millis(10)));
+        assert!(budget.debit_request_with_priority(RequestPriority::Interactive).is_ok());
+        let waiter = thread::spawn({
+            let budget = budget.clone();
+            move || budget.wait_for_interactive(Duration::from_secs(30))
+        });
+        drop(interactive);
+        assert!(waiter.join().unwrap() && !budget.is_interactive());
+
+        // Background requests leave the reserve to interactive requests.
+        let error = budget.debit_request_with_priority(RequestPriority::Background).unwrap_err();
+        assert_eq!(crate::error_code(&error), ErrorCode::BudgetExhausted);
+        assert!(budget.debit_request_with_priority(RequestPriority::Interactive).is_ok());
+        assert_eq!(budget.usage().requests, 3);
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let error = checkpoint(BudgetExhausted::new(BudgetUsage::default(), (), None).into(), vec![1u32], Some(7));
+        assert_eq!(error.to_string(), "The budget was exhausted after 0 requests and 0 response bytes, before block 7");
+        let exhausted = error.downcast::<BudgetExhausted<Vec<u32>>>().unwrap();
+        assert_eq!((exhausted.resume_height(), exhausted.into_partial()), (Some(7), vec![1]));
+
+        // Other errors are not changed.
+        let error = checkpoint(anyhow::anyhow!("Failed"), vec![1u32], Some(7));
+        assert_eq!(error.to_string(), "Failed");
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::ErrorCode;
+
+use std::{
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// A flag for cooperatively cancelling long running operations
+///
+/// Clones of a token share the flag, so a token can be handed to an operation and cancelled from another
+/// thread or task. Operations check the token between steps, e.g. between the chunks of a scan, and stop
+/// with a [`Cancelled`] error.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the operations holding this token, or a clone of it.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancellationToken {
+    /// Use an existing flag as a token, where setting the flag to `true` cancels the operation
+    fn from(cancelled: Arc<AtomicBool>) -> Self {
+        Self { cancelled }
+    }
+}
+
+/// The error returned by an operation that stopped because its [`CancellationToken`] was cancelled
+///
+/// The error carries the results gathered before the operation stopped, and for operations over a range
+/// of blocks, the height to resume from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cancelled<T> {
+    partial: T,
+    resume_height: Option<u32>,
+}
+
+impl<T> Cancelled<T> {
+    pub(crate) fn new(partial: T, resume_height: Option<u32>) -> Self {
+        Self { partial, resume_height }
+    }
+
+    /// Returns the results gathered before the operation stopped.
+    pub fn partial(&self) -> &T {
+        &self.partial
+    }
+
+    /// Returns the results gathered before the operation stopped, discarding the error.
+    pub fn into_partial(self) -> T {
+        self.partial
+    }
+
+    /// Returns the height of the first block that was not processed, for operations over a range of blocks.
+    pub fn resume_height(&self) -> Option<u32> {
+        self.resume_height
+    }
+}
+
+impl<T> fmt::Display for Cancelled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.resume_height {
+            Some(height) => write!(f, "The operation was cancelled before block {height}"),
+            None => write!(f, "The operation was cancelled"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> Error for Cancelled<T> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&ErrorCode::Cancelled)
+    }
+}
@@ -0,0 +1,412 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Recording the requests of a client and the responses of the node into cassettes, and replaying them
+//!
+//! A cassette reproduces the traffic of a user offline, e.g. to investigate a scan that returns wrong results
+//! against some provider, and becomes a regression test once the bug is fixed. It is a JSON file of the form
+//! `{"interactions": [{"method": "GET", "path": "/testnet3/latest/height", "status": 200, "body": "7", ...}]}`,
+//! which the mock node of the tests of this crate serves as well.
+
+use super::error::{is_secret_name, redact_url};
+use crate::{mutex::lock, store::write_atomic};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+// The headers of a response that describe its transfer rather than its content. Recorded bodies are decoded and
+// held in full, so these are left out and the length is set again on replay.
+const TRANSFER_HEADERS: &[&str] = &["connection", "content-encoding", "content-length", "transfer-encoding"];
+
+/// A request sent by a client and the response it received, as held by a [`Cassette`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The method of the request, e.g. `GET`
+    pub method: String,
+    /// The path and query of the request, without the scheme and host of the node, and with secrets redacted
+    pub path: String,
+    /// The headers set on the request, with the values of those carrying secrets redacted
+    #[serde(default)]
+    pub request_headers: Vec<(String, String)>,
+    /// The status of the response
+    pub status: u16,
+    /// The headers of the response
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// The body of the response
+    pub body: String,
+}
+
+impl Interaction {
+    // Returns the response to replay, which is an error for statuses of 400 and above as ureq returns them
+    #[allow(clippy::result_large_err)]
+    fn to_response(&self) -> Result<ureq::Response, ureq::Error> {
+        let mut response = format!("HTTP/1.1 {} Replayed\r\n", self.status);
+        for (name, value) in &self.headers {
+            response.push_str(&format!("{name}: {value}\r\n"));
+        }
+        response.push_str(&format!("Content-Length: {}\r\n\r\n{}", self.body.len(), self.body));
+        let response = response.parse::<ureq::Response>()?;
+        match response.status() >= 400 {
+            true => Err(ureq::Error::Status(response.status(), response)),
+            false => Ok(response),
+        }
+    }
+}
+
+/// The requests of a client and the responses of the node, in the order they were sent
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Create an empty cassette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an interaction to the cassette.
+    pub fn with_interaction(mut self, interaction: Interaction) -> Self {
+        self.interactions.push(interaction);
+        self
+    }
+
+    /// Returns the interactions of the cassette, in the order they were recorded.
+    pub fn interactions(&self) -> &[Interaction] {
+        &self.interactions
+    }
+
+    /// Read a cassette from a JSON file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|error| anyhow!("Failed to read the cassette {}: {error}", path.display()))?;
+        serde_json::from_str(&json).map_err(|error| anyhow!("Invalid cassette {}: {error}", path.display()))
+    }
+
+    /// Write the cassette to a JSON file, replacing it atomically.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_atomic(path.as_ref(), serde_json::to_string_pretty(self)?.as_bytes())
+    }
+}
+
+/// Records the requests of the clients it is set on with [`crate::AleoAPIClient::with_recorder`], and the responses
+/// of the node
+///
+/// The values of query parameters and headers carrying secrets, such as `api_key` or `Authorization`, are
+/// redacted from the recording, while the requests are sent with them. Clones of a recorder share its cassette.
+/// Response bodies are held in full while recording, so the maximum response size of the client is only checked
+/// once a body is recorded.
+#[derive(Clone, Debug, Default)]
+pub struct CassetteRecorder {
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl CassetteRecorder {
+    /// Create a recorder with an empty cassette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cassette recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        lock(&self.cassette).clone()
+    }
+
+    /// Write the cassette recorded so far to a JSON file, see [`Cassette::write`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.cassette().write(path)
+    }
+
+    // Record the response to the request, and return it to the client
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn record(
+        &self,
+        request: &ureq::Request,
+        response: Result<ureq::Response, ureq::Error>,
+    ) -> Result<ureq::Response, ureq::Error> {
+        // Failures to reach the node have no response to record.
+        let response = match response {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(error) => return Err(error),
+        };
+        let status = response.status();
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .filter(|name| !TRANSFER_HEADERS.contains(&name.as_str()))
+            .filter_map(|name| Some((name.clone(), response.header(&name)?.to_string())))
+            .collect();
+        let mut body = vec![];
+        response.into_reader().read_to_end(&mut body)?;
+        let request_headers = request
+            .header_names()
+            .into_iter()
+            .map(|name| {
+                let value = match is_secret_name(&name) {
+                    true => "***".to_string(),
+                    false => request.header(&name).unwrap_or_default().to_string(),
+                };
+                (name, value)
+            })
+            .collect();
+        let interaction = Interaction {
+            method: request.method().to_string(),
+            path: request_path(request.url()),
+            request_headers,
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        };
+        let response = interaction.to_response();
+        lock(&self.cassette).interactions.push(interaction);
+        response
+    }
+}
+
+/// Serves the responses of a cassette in place of the node, to the clients it is set on with
+/// [`crate::AleoAPIClient::with_replay`]
+///
+/// A request is answered with the first interaction of the same method and path that was not replayed yet. Once
+/// every such interaction was replayed, the last one is replayed again, e.g. for repeated polls of the latest
+/// height. Requests the cassette holds no interaction for are answered with `404 Not Found`, as a node without the
+/// requested item would answer. In strict mode, both fail instead, so that a test replaying a cassette notices any
+/// request that was not recorded. Clones of a player share the progress of the replay.
+#[derive(Clone, Debug)]
+pub struct CassettePlayer {
+    // The interactions of the cassette, with whether each was replayed
+    interactions: Arc<Mutex<Vec<(Interaction, bool)>>>,
+    strict: bool,
+}
+
+impl CassettePlayer {
+    /// Create a player replaying the cassette.
+    pub fn new(cassette: Cassette) -> Self {
+        let interactions = cassette.interactions.into_iter().map(|interaction| (interaction, false)).collect();
+        Self { interactions: Arc::new(Mutex::new(interactions)), strict: false }
+    }
+
+    /// Fail requests that were not recorded, or that were replayed as many times as they were recorded.
+    pub fn with_strict_mode(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Returns the interactions that were not replayed yet, in the order they were recorded.
+    pub fn remaining(&self) -> Vec<Interaction> {
+        let interactions = lock(&self.interactions);
+        interactions.iter().filter(|(_, replayed)| !replayed).map(|(interaction, _)| interaction.clone()).collect()
+    }
+
+    /// Returns the interaction answering a request of the method to the URL or path, or `None` if the cassette holds
+    /// none.
+    ///
+    /// The path of a URL is matched, without its scheme and host, so that a cassette recorded against one node is
+    /// replayed against any.
+    pub fn next_interaction(&self, method: &str, url: &str) -> Option<Interaction> {
+        let path = request_path(url);
+        let mut interactions = lock(&self.interactions);
+        let mut matching = interactions
+            .iter_mut()
+            .filter(|(interaction, _)| interaction.method.eq_ignore_ascii_case(method) && interaction.path == path)
+            .peekable();
+        matching.peek()?;
+        let mut last = None;
+        for (interaction, replayed) in matching {
+            if !*replayed {
+                *replayed = true;
+                return Some(interaction.clone());
+            }
+            last = Some(interaction.clone());
+        }
+        last.filter(|_| !self.strict)
+    }
+
+    // Answer the request from the cassette, without reaching the node
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn replay(&self, request: &ureq::Request) -> Result<ureq::Response, ureq::Error> {
+        match self.next_interaction(request.method(), request.url()) {
+            Some(interaction) => interaction.to_response(),
+            None if self.strict => {
+                let path = request_path(request.url());
+                let message = format!("The cassette holds no response for {} {path}", request.method());
+                Err(io::Error::new(io::ErrorKind::NotFound, message).into())
+            }
+            None => {
+                let not_found = Interaction {
+                    method: request.method().to_string(),
+                    path: request_path(request.url()),
+                    request_headers: vec![],
+                    status: 404,
+                    headers: vec![("content-type".to_string(), "text/plain".to_string())],
+                    body: String::new(),
+                };
+                not_found.to_response()
+            }
+        }
+    }
+}
+
+// How a client passes its requests through a cassette
+#[derive(Clone, Debug)]
+pub(crate) enum CassetteTransport {
+    Record(CassetteRecorder),
+    Replay(CassettePlayer),
+}
+
+// Returns the path and query of a URL, with secrets redacted. Paths are returned as they are.
+fn request_path(url: &str) -> String {
+    let url = redact_url(url);
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or_else(|| "/".to_string(), |start| rest[start..].to_string()),
+        None => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_block_with_transactions,
+            sample_output,
+            sample_transaction,
+            sample_transition,
+            CurrentNetwork,
+            MockResponse,
+            MockServer,
+        },
+        testnet3,
+        BlockHeight,
+        ErrorCode,
+    };
+    use snarkvm_console::account::{PrivateKey, ViewKey};
+    use snarkvm_utilities::TestRng;
+
+    use std::env;
+
+    type N = CurrentNetwork;
+
+    // Start a mock node serving a chain in which every block holds a record of the view key, and the chain's height
+    fn mock_chain_server(length: u32, view_key: &ViewKey<N>) -> MockServer {
+        let rng = &mut TestRng::default();
+        let mut blocks = vec![genesis_block()];
+        for height in 1..length {
+            let output = sample_output(view_key.to_address(), 100, rng);
+            let transaction = sample_transaction([sample_transition(&[], &[output], rng)]);
+            let previous_hash = blocks.last().unwrap().hash();
+            let transactions = [transaction].into_iter().collect();
+            blocks.push(sample_block_with_transactions(height, previous_hash, transactions, rng));
+        }
+        let blocks = blocks.iter().map(ToString::to_string).collect::<Vec<_>>();
+        MockServer::start(move |request| {
+            if request.path == "/testnet3/latest/height" {
+                return Some(MockResponse::json(blocks.len() - 1).with_header("ETag", "\"tip\""));
+            }
+            let (start, end) = request.path.strip_prefix("/testnet3/blocks?start=")?.split_once("&end=")?;
+            let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+            let blocks = blocks.get(start..end.min(blocks.len()))?;
+            Some(MockResponse::json(format!("[{}]", blocks.join(","))))
+        })
+    }
+
+    #[test]
+    fn test_cassette_record_and_replay_scan() {
+        let view_key = ViewKey::try_from(PrivateKey::<N>::new(&mut TestRng::default()).unwrap()).unwrap();
+        let server = mock_chain_server(10, &view_key);
+
+        // Record a scan against the node, and write the cassette.
+        let recorder = CassetteRecorder::new();
+        let client = testnet3(server.base_url()).with_max_block_request(3).with_recorder(&recorder);
+        let height = client.latest_height().unwrap();
+        let records = client.scan(view_key, BlockHeight::GENESIS..=height).unwrap();
+        assert_eq!(records.len(), 9);
+        let path = env::temp_dir().join(format!("aleo-cassette-{}.json", std::process::id()));
+        recorder.save(&path).unwrap();
+        let cassette = Cassette::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cassette, recorder.cassette());
+        let paths = cassette.interactions().iter().map(|interaction| interaction.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(paths, [
+            "/testnet3/latest/height",
+            "/testnet3/blocks?start=0&end=3",
+            "/testnet3/blocks?start=3&end=6",
+            "/testnet3/blocks?start=6&end=9",
+            "/testnet3/blocks?start=9&end=12",
+        ]);
+        assert!(cassette.interactions()[0].headers.contains(&("etag".to_string(), "\"tip\"".to_string())));
+
+        // The cassette is replayed offline with identical results, and every interaction is replayed.
+        let player = CassettePlayer::new(cassette.clone()).with_strict_mode(true);
+        let offline = testnet3("http://127.0.0.1:9").with_max_block_request(3).with_replay(player.clone());
+        assert_eq!(offline.latest_height().unwrap(), height);
+        assert_eq!(offline.scan(view_key, BlockHeight::GENESIS..=height).unwrap(), records);
+        assert!(player.remaining().is_empty());
+
+        // In strict mode, requests that were not recorded fail, and otherwise the node seems not to hold them.
+        let error = offline.get_block(BlockHeight(42)).unwrap_err();
+        assert_eq!(crate::error_code(&error), ErrorCode::Connection);
+        assert!(error.to_string().contains("The cassette holds no response for GET /testnet3/block/42"), "{error}");
+        let lenient = testnet3("http://127.0.0.1:9").with_replay(CassettePlayer::new(cassette.clone()));
+        assert_eq!(crate::error_code(&lenient.get_block(BlockHeight(42)).unwrap_err()), ErrorCode::NotFound);
+        assert_eq!(lenient.latest_height().unwrap(), height);
+        assert_eq!(lenient.latest_height().unwrap(), height);
+
+        // The mock node of the tests serves the same cassette.
+        let fixture = MockServer::replay(cassette);
+        let client = testnet3(fixture.base_url()).with_max_block_request(3);
+        assert_eq!(client.scan(view_key, BlockHeight(0)..BlockHeight(10)).unwrap(), records);
+    }
+
+    #[test]
+    fn test_cassette_redacts_secrets() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let headers = seen.clone();
+        let server = MockServer::start(move |request| {
+            headers.lock().unwrap().push(request.header("Authorization").map(ToString::to_string));
+            Some(MockResponse::json("7"))
+        });
+        let recorder = CassetteRecorder::new();
+        let client = testnet3(server.base_url())
+            .with_header("Authorization", "Bearer secret")
+            .with_header("X-Request-Source", "wallet")
+            .with_recorder(&recorder);
+        assert_eq!(client.latest_height().unwrap(), BlockHeight(7));
+
+        // The node receives the header, but the cassette does not hold its value.
+        assert_eq!(seen.lock().unwrap().as_slice(), [Some("Bearer secret".to_string())]);
+        let cassette = recorder.cassette();
+        assert_eq!(cassette.interactions()[0].request_headers, [
+            ("authorization".to_string(), "***".to_string()),
+            ("x-request-source".to_string(), "wallet".to_string())
+        ]);
+        let json = serde_json::to_string(&cassette).unwrap();
+        assert!(!json.contains("secret"), "{json}");
+
+        // Keys in the query of a URL are redacted from the recorded path.
+        let path = request_path("https://node.io/testnet3/blocks?start=0&api_key=abc&end=2");
+        assert_eq!(path, "/testnet3/blocks?start=0&api_key=***&end=2");
+        assert_eq!(request_path("https://node.io"), "/");
+    }
+}
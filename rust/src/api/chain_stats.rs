@@ -0,0 +1,483 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{api::to_height_range, ids::heights, AleoAPIClient, BlockHeight};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::{Block, Transition};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::{Range, RangeBounds},
+};
+
+/// How the blocks of a range are grouped into the buckets of [`AleoAPIClient::aggregate_chain_stats`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatsGranularity {
+    /// Each block is a bucket of its own
+    PerBlock,
+    /// The blocks are grouped by the given number of heights, with buckets starting at its multiples so that the
+    /// buckets of overlapping ranges line up
+    PerNBlocks(u32),
+}
+
+impl StatsGranularity {
+    // Returns the number of heights a bucket spans
+    fn bucket_size(&self) -> Result<u32> {
+        match self {
+            Self::PerBlock => Ok(1),
+            Self::PerNBlocks(0) => bail!("A bucket of chain statistics must span at least one block"),
+            Self::PerNBlocks(blocks) => Ok(*blocks),
+        }
+    }
+}
+
+/// The statistics of the blocks of a range of heights
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChainStatsBucket {
+    /// The height of the first block of the bucket
+    pub start_height: u32,
+    /// The height after the last block of the bucket
+    pub end_height: u32,
+    /// The number of blocks in the bucket
+    pub blocks: u32,
+    /// The number of blocks holding no transactions besides `credits.aleo/mint` executions, as the genesis block
+    pub empty_blocks: u32,
+    /// The number of transactions in the blocks
+    pub transactions: u64,
+    /// The fees paid by the transactions, in gates. The negative fee of a `credits.aleo/mint` transition is the
+    /// amount it mints rather than a fee, and is left out.
+    pub total_fees: u64,
+    /// The programs called by the transitions of the blocks
+    pub programs: BTreeSet<String>,
+    /// The number of records created by the blocks
+    pub records_created: u64,
+    /// The mean number of seconds between the blocks and their parents, or `None` if no block of the bucket has a
+    /// parent, as the genesis block
+    pub average_block_time: Option<f64>,
+    /// The values of the metrics of the [`ChainMetrics`] the statistics were aggregated with, by name
+    pub custom: BTreeMap<String, f64>,
+}
+
+impl ChainStatsBucket {
+    /// Returns the mean number of transactions per block.
+    pub fn transactions_per_block(&self) -> f64 {
+        match self.blocks {
+            0 => 0.0,
+            blocks => self.transactions as f64 / blocks as f64,
+        }
+    }
+
+    /// Returns the number of distinct programs called by the transitions of the blocks.
+    pub fn unique_programs(&self) -> usize {
+        self.programs.len()
+    }
+}
+
+// Folds the blocks of a bucket into the value of a custom metric
+type MetricFold<N> = Box<dyn Fn(f64, &Block<N>) -> f64>;
+
+/// The custom metrics of [`AleoAPIClient::aggregate_chain_stats_with`], each folding the blocks of a bucket into a
+/// value
+pub struct ChainMetrics<N: Network> {
+    metrics: Vec<(String, f64, MetricFold<N>)>,
+}
+
+impl<N: Network> Default for ChainMetrics<N> {
+    fn default() -> Self {
+        Self { metrics: vec![] }
+    }
+}
+
+impl<N: Network> ChainMetrics<N> {
+    /// Create a set of custom metrics without any metric.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the metric with the given name, whose value starts at `initial` in each bucket and is folded with each
+    /// block of the bucket in order. A metric of the same name is replaced.
+    pub fn with_metric(
+        mut self,
+        name: impl Into<String>,
+        initial: f64,
+        fold: impl Fn(f64, &Block<N>) -> f64 + 'static,
+    ) -> Self {
+        let name = name.into();
+        self.metrics.retain(|(metric, ..)| *metric != name);
+        self.metrics.push((name, initial, Box::new(fold)));
+        self
+    }
+
+    /// Returns the names of the metrics.
+    pub fn names(&self) -> impl '_ + Iterator<Item = &str> {
+        self.metrics.iter().map(|(name, ..)| name.as_str())
+    }
+}
+
+// Folds the blocks of a range, in order, into buckets of statistics
+struct StatsAggregator<'a, N: Network> {
+    block_heights: Range<u32>,
+    bucket_size: u32,
+    metrics: &'a ChainMetrics<N>,
+    buckets: Vec<ChainStatsBucket>,
+    current: Option<ChainStatsBucket>,
+    // The sum and the number of the times between the blocks of the current bucket and their parents
+    block_times: (i64, u32),
+    previous_timestamp: Option<i64>,
+}
+
+impl<'a, N: Network> StatsAggregator<'a, N> {
+    fn new(block_heights: Range<u32>, bucket_size: u32, metrics: &'a ChainMetrics<N>) -> Self {
+        Self {
+            block_heights,
+            bucket_size,
+            metrics,
+            buckets: vec![],
+            current: None,
+            block_times: (0, 0),
+            previous_timestamp: None,
+        }
+    }
+
+    fn add(&mut self, block: &Block<N>) {
+        let height = block.height();
+        let previous_timestamp = self.previous_timestamp.replace(block.timestamp());
+        // The parent of the first block is only read for the time between them.
+        if height < self.block_heights.start {
+            return;
+        }
+        if self.current.as_ref().map_or(true, |bucket| height >= bucket.end_height) {
+            self.close();
+            let start_height = height - height % self.bucket_size;
+            self.current = Some(ChainStatsBucket {
+                start_height: start_height.max(self.block_heights.start),
+                end_height: start_height.saturating_add(self.bucket_size).min(self.block_heights.end),
+                custom: self.metrics.metrics.iter().map(|(name, initial, _)| (name.clone(), *initial)).collect(),
+                ..Default::default()
+            });
+        }
+        let bucket = self.current.as_mut().expect("A bucket was just opened");
+
+        bucket.blocks += 1;
+        if block.transitions().all(is_mint) {
+            bucket.empty_blocks += 1;
+        }
+        bucket.transactions += block.transactions().len() as u64;
+        for transition in block.transitions() {
+            bucket.total_fees += (*transition.fee()).max(0) as u64;
+            bucket.programs.insert(transition.program_id().to_string());
+        }
+        bucket.records_created += block.records().count() as u64;
+        if let Some(previous_timestamp) = previous_timestamp {
+            self.block_times.0 += block.timestamp() - previous_timestamp;
+            self.block_times.1 += 1;
+        }
+        for (name, _, fold) in &self.metrics.metrics {
+            if let Some(value) = bucket.custom.get_mut(name) {
+                *value = fold(*value, block);
+            }
+        }
+    }
+
+    // Completes the current bucket, if any
+    fn close(&mut self) {
+        if let Some(mut bucket) = self.current.take() {
+            let (total, count) = std::mem::take(&mut self.block_times);
+            bucket.average_block_time = (count > 0).then(|| total as f64 / count as f64);
+            self.buckets.push(bucket);
+        }
+    }
+
+    fn finish(mut self) -> Vec<ChainStatsBucket> {
+        self.close();
+        self.buckets
+    }
+}
+
+// Returns `true` if the transition is a `credits.aleo/mint` call
+fn is_mint<N: Network>(transition: &Transition<N>) -> bool {
+    format!("{}/{}", transition.program_id(), transition.function_name()) == "credits.aleo/mint"
+}
+
+impl<N: Network> AleoAPIClient<N> {
+    /// Returns the statistics of the blocks at the given heights, grouped into buckets of the given granularity.
+    ///
+    /// The blocks are streamed in a single pass, so memory use does not grow with the range. A range that crosses
+    /// the latest height of the node is cut short at it, and a range past it has no buckets. The parent of the first
+    /// block is also read, for the time between them.
+    pub fn aggregate_chain_stats(
+        &self,
+        block_heights: impl RangeBounds<BlockHeight>,
+        granularity: StatsGranularity,
+    ) -> Result<Vec<ChainStatsBucket>> {
+        self.aggregate_chain_stats_with(block_heights, granularity, &ChainMetrics::new())
+    }
+
+    /// Returns the statistics of the blocks at the given heights as [`AleoAPIClient::aggregate_chain_stats`] does,
+    /// with the values of the given custom metrics.
+    pub fn aggregate_chain_stats_with(
+        &self,
+        block_heights: impl RangeBounds<BlockHeight>,
+        granularity: StatsGranularity,
+        metrics: &ChainMetrics<N>,
+    ) -> Result<Vec<ChainStatsBucket>> {
+        let block_heights = to_height_range(block_heights)?;
+        if block_heights.start >= block_heights.end {
+            bail!("Start height must be less than end height");
+        }
+        let bucket_size = granularity.bucket_size()?;
+        let end_height = block_heights.end.min(self.latest_height()?.0.saturating_add(1));
+        if block_heights.start >= end_height {
+            return Ok(vec![]);
+        }
+        let block_heights = block_heights.start..end_height;
+        let mut aggregator = StatsAggregator::new(block_heights.clone(), bucket_size, metrics);
+        let preceding_heights = block_heights.start.saturating_sub(1)..block_heights.end;
+        self.for_each_block(heights(preceding_heights), |block| aggregator.add(&block))?;
+        Ok(aggregator.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{genesis_block, sample_block_at, sample_output, CurrentNetwork, MockResponse, MockServer},
+        testnet3,
+    };
+
+    use rand::thread_rng;
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        prelude::Uniform,
+        program::{Field, Identifier, ProgramID},
+    };
+    use snarkvm_synthesizer::{Execution, Output, Transaction, Transactions};
+    use std::str::FromStr;
+
+    const PROGRAMS: [&str; 3] = ["credits.aleo", "token.aleo", "game.aleo"];
+
+    // The timestamp of the fixture block at the given height, 18 seconds after its parent at odd heights and 12
+    // seconds after it at even heights
+    fn timestamp(height: u32) -> i64 {
+        CurrentNetwork::GENESIS_TIMESTAMP + 15 * height as i64 + 3 * (height % 2) as i64
+    }
+
+    // Samples a transaction with a single transition of the given program, paying the given fee and creating the
+    // given number of records
+    fn sample_call(program: &str, fee: i64, records: usize) -> Transaction<CurrentNetwork> {
+        let rng = &mut thread_rng();
+        let template = genesis_block().transitions().next().unwrap().clone();
+        let owner = Address::try_from(PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+        let outputs = (0..records).map(|_| {
+            let (commitment, record) = sample_output(owner, 1, rng);
+            Output::Record(commitment, Field::rand(rng), Some(record))
+        });
+        let transition = Transition::new(
+            ProgramID::from_str(program).unwrap(),
+            Identifier::from_str("call").unwrap(),
+            vec![],
+            outputs.collect(),
+            None,
+            template.proof().clone(),
+            *template.tpk(),
+            *template.tcm(),
+            fee,
+        )
+        .unwrap();
+        let execution = Execution::from([transition].into_iter(), Default::default(), None).unwrap();
+        Transaction::from_execution(execution, None).unwrap()
+    }
+
+    // Samples a 20-block chain. Every fifth block holds the genesis transactions, so it is empty, and the other
+    // blocks hold `1 + height % 3` transactions, the i-th of which calls the `(height + i) % 3`-th program, pays a
+    // fee of `10 * height + i` gates, and creates `i` records.
+    fn sample_fixture_chain() -> Vec<Block<CurrentNetwork>> {
+        let rng = &mut thread_rng();
+        let mut chain = vec![genesis_block()];
+        for height in 1..20u32 {
+            let transactions = match height % 5 {
+                0 => chain[0].transactions().clone(),
+                _ => {
+                    let calls = (0..1 + height % 3).map(|i| {
+                        let program = PROGRAMS[((height + i) % 3) as usize];
+                        sample_call(program, (10 * height + i) as i64, i as usize)
+                    });
+                    Transactions::from(&calls.collect::<Vec<_>>())
+                }
+            };
+            let previous_hash = chain.last().unwrap().hash();
+            chain.push(sample_block_at(height, previous_hash, transactions, timestamp(height), rng));
+        }
+        chain
+    }
+
+    // Start a mock node serving the given chain
+    fn mock_chain_node(chain: &[Block<CurrentNetwork>]) -> MockServer {
+        let latest_height = chain.len() as u32 - 1;
+        let chain = chain.iter().map(ToString::to_string).collect::<Vec<_>>();
+        MockServer::start(move |request| {
+            if request.path == "/testnet3/latest/height" {
+                return Some(MockResponse::json(latest_height));
+            }
+            let (start, end) = request.path.strip_prefix("/testnet3/blocks?start=")?.split_once("&end=")?;
+            let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+            let blocks = chain.get(start..end)?;
+            Some(MockResponse::json(format!("[{}]", blocks.join(","))))
+        })
+    }
+
+    fn programs(programs: &[&str]) -> BTreeSet<String> {
+        programs.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_aggregate_chain_stats_per_block() {
+        let chain = sample_fixture_chain();
+        let genesis = &chain[0];
+        let (mint_transactions, mint_records) = (genesis.transactions().len() as u64, genesis.records().count() as u64);
+        assert_eq!(timestamp(0), genesis.timestamp());
+        let server = mock_chain_node(&chain);
+        let client = testnet3(server.base_url());
+        let buckets = client.aggregate_chain_stats(heights(0..20), StatsGranularity::PerBlock).unwrap();
+        assert_eq!(buckets.len(), 20);
+
+        // The height, transactions, fees, programs, and records of the blocks that are not empty
+        let expected: [(u32, u64, u64, &[&str], u64); 16] = [
+            (1, 2, 21, &["game.aleo", "token.aleo"], 1),
+            (2, 3, 63, &PROGRAMS, 3),
+            (3, 1, 30, &["credits.aleo"], 0),
+            (4, 2, 81, &["game.aleo", "token.aleo"], 1),
+            (6, 1, 60, &["credits.aleo"], 0),
+            (7, 2, 141, &["game.aleo", "token.aleo"], 1),
+            (8, 3, 243, &PROGRAMS, 3),
+            (9, 1, 90, &["credits.aleo"], 0),
+            (11, 3, 333, &PROGRAMS, 3),
+            (12, 1, 120, &["credits.aleo"], 0),
+            (13, 2, 261, &["game.aleo", "token.aleo"], 1),
+            (14, 3, 423, &PROGRAMS, 3),
+            (16, 2, 321, &["game.aleo", "token.aleo"], 1),
+            (17, 3, 513, &PROGRAMS, 3),
+            (18, 1, 180, &["credits.aleo"], 0),
+            (19, 2, 381, &["game.aleo", "token.aleo"], 1),
+        ];
+        let mut expected = expected.iter().copied();
+        for (height, bucket) in (0..20).zip(&buckets) {
+            assert_eq!((bucket.start_height, bucket.end_height, bucket.blocks), (height, height + 1, 1));
+            // The genesis block has no parent to measure its block time against.
+            let block_time = (height > 0).then_some(if height % 2 == 1 { 18.0 } else { 12.0 });
+            assert_eq!(bucket.average_block_time, block_time, "block time at height {height}");
+            assert!(bucket.custom.is_empty());
+            if height % 5 == 0 {
+                assert_eq!(bucket.empty_blocks, 1);
+                assert_eq!(bucket.transactions, mint_transactions);
+                assert_eq!(bucket.total_fees, 0);
+                assert_eq!(bucket.programs, programs(&["credits.aleo"]));
+                assert_eq!(bucket.records_created, mint_records);
+                continue;
+            }
+            let (_, transactions, total_fees, called, records_created) = match expected.next() {
+                Some(row) if row.0 == height => row,
+                row => panic!("unexpected row {row:?} at height {height}"),
+            };
+            assert_eq!(bucket.empty_blocks, 0);
+            assert_eq!(bucket.transactions, transactions, "transactions at height {height}");
+            assert_eq!(bucket.transactions_per_block(), transactions as f64);
+            assert_eq!(bucket.total_fees, total_fees, "fees at height {height}");
+            assert_eq!(bucket.programs, programs(called), "programs at height {height}");
+            assert_eq!(bucket.records_created, records_created, "records at height {height}");
+        }
+        assert!(expected.next().is_none());
+    }
+
+    #[test]
+    fn test_aggregate_chain_stats_per_n_blocks() {
+        let chain = sample_fixture_chain();
+        let genesis = &chain[0];
+        let (mint_transactions, mint_records) = (genesis.transactions().len() as u64, genesis.records().count() as u64);
+        let server = mock_chain_node(&chain);
+        let client = testnet3(server.base_url());
+        let metrics = ChainMetrics::new()
+            .with_metric("max_transactions", 0.0, |max: f64, block: &Block<CurrentNetwork>| {
+                max.max(block.transactions().len() as f64)
+            })
+            .with_metric("blocks", 0.0, |count, _| count + 1.0);
+        assert_eq!(metrics.names().collect::<Vec<_>>(), ["max_transactions", "blocks"]);
+
+        // Each bucket of five blocks holds one empty block, whose transactions are the mints of the genesis block.
+        let granularity = StatsGranularity::PerNBlocks(5);
+        let buckets = client.aggregate_chain_stats_with(heights(0..20), granularity, &metrics).unwrap();
+        let expected = [(0, 8, 195, 5, 15.0), (5, 7, 534, 4, 15.6), (10, 9, 1137, 7, 14.4), (15, 8, 1395, 5, 15.6)];
+        assert_eq!(buckets.len(), expected.len());
+        for (bucket, expected) in buckets.iter().zip(expected) {
+            let (start_height, transactions, total_fees, records_created, block_time) = expected;
+            assert_eq!((bucket.start_height, bucket.end_height), (start_height, start_height + 5));
+            assert_eq!((bucket.blocks, bucket.empty_blocks), (5, 1));
+            assert_eq!(bucket.transactions, transactions + mint_transactions);
+            assert_eq!(bucket.total_fees, total_fees);
+            assert_eq!(bucket.programs, programs(&PROGRAMS));
+            assert_eq!(bucket.unique_programs(), 3);
+            assert_eq!(bucket.records_created, records_created + mint_records);
+            assert!((bucket.average_block_time.unwrap() - block_time).abs() < 1e-9);
+            let max_transactions = mint_transactions.max(3) as f64;
+            let custom = BTreeMap::from([("blocks".into(), 5.0), ("max_transactions".into(), max_transactions)]);
+            assert_eq!(bucket.custom, custom);
+        }
+
+        // Buckets line up with multiples of their size, and the first block is timed against its parent.
+        let buckets = client.aggregate_chain_stats(heights(3..12), StatsGranularity::PerNBlocks(5)).unwrap();
+        let heights = buckets.iter().map(|bucket| (bucket.start_height, bucket.end_height, bucket.blocks));
+        assert_eq!(heights.collect::<Vec<_>>(), [(3, 5, 2), (5, 10, 5), (10, 12, 2)]);
+        assert_eq!(buckets[0].programs, programs(&PROGRAMS));
+        assert_eq!((buckets[0].transactions, buckets[0].total_fees, buckets[0].records_created), (3, 111, 1));
+        assert_eq!(buckets[0].average_block_time, Some(15.0));
+        let second_heights = BlockHeight(5)..BlockHeight(10);
+        let second = client.aggregate_chain_stats(second_heights, StatsGranularity::PerNBlocks(5)).unwrap();
+        assert_eq!(buckets[1], second[0]);
+        assert_eq!(buckets[2].transactions, 3 + mint_transactions);
+        assert_eq!((buckets[2].total_fees, buckets[2].records_created), (333, 3 + mint_records));
+        assert_eq!(buckets[2].average_block_time, Some(15.0));
+
+        // The buckets round trip through JSON.
+        let json = serde_json::to_string(&buckets).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<ChainStatsBucket>>(&json).unwrap(), buckets);
+    }
+
+    #[test]
+    fn test_aggregate_chain_stats_range() {
+        let chain = sample_fixture_chain();
+        let server = mock_chain_node(&chain);
+        let client = testnet3(server.base_url());
+
+        // A range crossing the tip is cut short at it, and a range past it has no buckets.
+        let buckets = client.aggregate_chain_stats(heights(15..40), StatsGranularity::PerNBlocks(10)).unwrap();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!((buckets[0].start_height, buckets[0].end_height, buckets[0].blocks), (15, 20, 5));
+        assert!(client.aggregate_chain_stats(heights(25..30), StatsGranularity::PerBlock).unwrap().is_empty());
+
+        // The genesis block alone has no block time.
+        let buckets = client.aggregate_chain_stats(heights(0..1), StatsGranularity::PerNBlocks(5)).unwrap();
+        assert_eq!((buckets[0].start_height, buckets[0].end_height), (0, 1));
+        assert_eq!(buckets[0].average_block_time, None);
+
+        let error = client.aggregate_chain_stats(heights(5..5), StatsGranularity::PerBlock).unwrap_err();
+        assert_eq!(error.to_string(), "Start height must be less than end height");
+        let error = client.aggregate_chain_stats(heights(0..5), StatsGranularity::PerNBlocks(0)).unwrap_err();
+        assert_eq!(error.to_string(), "A bucket of chain statistics must span at least one block");
+    }
+}
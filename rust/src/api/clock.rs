@@ -0,0 +1,136 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+#[cfg(any(test, feature = "test-utils"))]
+use crate::mutex::lock;
+#[cfg(any(test, feature = "test-utils"))]
+use std::sync::{Arc, Mutex};
+use std::{
+    fmt,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// The source of time of a client, read by its timeouts, TTLs, windows and staleness checks
+///
+/// Clients read the [`SystemClock`] by default. Tests inject a [`MockClock`], available with the `test-utils`
+/// feature, to control time without waiting for it.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Returns the current instant of a monotonic clock, to measure durations.
+    fn now(&self) -> Instant;
+
+    /// Returns the current time of the wall clock, to compare with timestamps.
+    fn system_now(&self) -> SystemTime;
+
+    /// Block the current thread for the given duration.
+    fn sleep(&self, duration: Duration);
+}
+
+/// The clock of the operating system
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration)
+    }
+}
+
+/// A clock that only moves when it is advanced, or when a thread sleeps on it
+///
+/// Sleeping advances the clock by the duration and returns at once, so that code polling at an interval runs
+/// through its timeouts without waiting. Clones of a clock share its time.
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    start: Instant,
+    system_start: SystemTime,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl Default for MockClock {
+    fn default() -> Self {
+        Self { start: Instant::now(), system_start: UNIX_EPOCH, elapsed: Arc::new(Mutex::new(Duration::ZERO)) }
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl MockClock {
+    /// Create a clock whose wall clock starts at the Unix epoch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the wall clock at the given number of seconds since the Unix epoch.
+    pub fn with_unix_time(mut self, seconds: u64) -> Self {
+        self.system_start = UNIX_EPOCH + Duration::from_secs(seconds);
+        self
+    }
+
+    /// Move the clock forward by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        *lock(&self.elapsed) += duration;
+    }
+
+    /// Returns the time the clock moved since it was created.
+    pub fn elapsed(&self) -> Duration {
+        *lock(&self.elapsed)
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        self.system_start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+// Returns the time of the wall clock in seconds since the Unix epoch
+pub(crate) fn unix_time(clock: &dyn Clock) -> u64 {
+    clock.system_now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new().with_unix_time(1_000);
+        let (start, shared) = (clock.now(), clock.clone());
+        shared.advance(Duration::from_secs(5));
+        clock.sleep(Duration::from_secs(10));
+        assert_eq!(clock.now() - start, Duration::from_secs(15));
+        assert_eq!(shared.elapsed(), Duration::from_secs(15));
+        assert_eq!(unix_time(&shared), 1_015);
+    }
+}
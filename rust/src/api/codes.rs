@@ -0,0 +1,251 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{ApiError, PaymentTimeout, TransactionIndexOutOfRange, WrongNetworkAddress};
+
+use std::{error::Error, fmt, io};
+
+/// A stable code identifying the kind of an error, to be quoted in support requests and looked up in documentation
+///
+/// Codes are grouped by where the error arose: reaching the node (`NET`), the responses of the node (`NODE`),
+/// the chain the node serves (`CHAIN`), the request itself (`REQ`), limits set on the client (`CLIENT`), and
+/// the lifecycle of transactions (`TX`). A code is never reused for another kind of error.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// The node could not be reached, or the connection broke
+    Connection,
+    /// The node responded with HTTP status 429, as it limits the rate of requests
+    RateLimited,
+    /// The request timed out, with HTTP status 408 or 504
+    Timeout,
+    /// The connection through the SOCKS proxy of the client failed
+    Proxy,
+    /// The node responded with a server error
+    ServerError,
+    /// The node responded with content that is not JSON, as gateways in front of a node do
+    NotJson,
+    /// The node does not hold the requested item
+    NotFound,
+    /// The node rejected the request
+    Rejected,
+    /// The node rejected a request for more blocks than it serves at a time
+    BlockRequestLimit,
+    /// The response of the node could not be parsed
+    Parse,
+    /// The node serves responses in the format of a version this SDK cannot read
+    NodeVersion,
+    /// The node runs another version of snarkVM than the one this SDK is pinned to
+    SnarkvmVersion,
+    /// An amount of the response was served as a float that lost its precision
+    PrecisionLoss,
+    /// The node served another item than the one requested, or blocks of different forks
+    ResponseMismatch,
+    /// The node, or an address, key, or file, is for another network than the one the client is configured for
+    WrongNetwork,
+    /// The path of a raw query is invalid
+    InvalidPath,
+    /// An identifier of the request holds characters that identifiers may not hold
+    InvalidIdentifier,
+    /// A block has no transaction at the requested index
+    IndexOutOfRange,
+    /// A range of the request is empty, or exceeds the limit on ranges
+    InvalidRange,
+    /// An argument of the request, such as an address or a transaction ID, does not parse
+    InvalidArgument,
+    /// The budget of the client is exhausted
+    BudgetExhausted,
+    /// The operation was cancelled
+    Cancelled,
+    /// The response exceeds the maximum size accepted by the client
+    TooLarge,
+    /// A background request was shed while interactive work was in progress
+    Preempted,
+    /// The transaction was not confirmed before the timeout
+    ConfirmationTimeout,
+    /// The transaction was aborted by the network
+    TransactionAborted,
+    /// No payment was confirmed before the timeout
+    PaymentTimeout,
+    /// The error is not one of the kinds above
+    Unknown,
+}
+
+impl ErrorCode {
+    /// Returns the code as quoted in support requests, e.g. `ALEO-NET-002`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Connection => "ALEO-NET-001",
+            Self::RateLimited => "ALEO-NET-002",
+            Self::Timeout => "ALEO-NET-003",
+            Self::Proxy => "ALEO-NET-004",
+            Self::ServerError => "ALEO-NODE-001",
+            Self::NotJson => "ALEO-NODE-002",
+            Self::NotFound => "ALEO-NODE-003",
+            Self::Rejected => "ALEO-NODE-004",
+            Self::BlockRequestLimit => "ALEO-NODE-005",
+            Self::Parse => "ALEO-NODE-006",
+            Self::NodeVersion => "ALEO-NODE-007",
+            Self::SnarkvmVersion => "ALEO-NODE-008",
+            Self::PrecisionLoss => "ALEO-NODE-009",
+            Self::ResponseMismatch => "ALEO-CHAIN-001",
+            Self::WrongNetwork => "ALEO-CHAIN-002",
+            Self::InvalidPath => "ALEO-REQ-001",
+            Self::InvalidIdentifier => "ALEO-REQ-002",
+            Self::IndexOutOfRange => "ALEO-REQ-003",
+            Self::InvalidRange => "ALEO-REQ-004",
+            Self::InvalidArgument => "ALEO-REQ-005",
+            Self::BudgetExhausted => "ALEO-CLIENT-001",
+            Self::Cancelled => "ALEO-CLIENT-002",
+            Self::TooLarge => "ALEO-CLIENT-003",
+            Self::Preempted => "ALEO-CLIENT-004",
+            Self::ConfirmationTimeout => "ALEO-TX-001",
+            Self::TransactionAborted => "ALEO-TX-002",
+            Self::PaymentTimeout => "ALEO-TX-003",
+            Self::Unknown => "ALEO-UNKNOWN",
+        }
+    }
+
+    /// Returns a short hint on what to do about the error.
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            Self::Connection => "the node could not be reached; check its URL and the network connection, or \
+                                 configure another endpoint",
+            Self::RateLimited => "the endpoint may be rate limiting you; reduce scan concurrency or configure a \
+                                  rate limit",
+            Self::Timeout => "the node took too long to respond; retry later, or request fewer blocks at a time \
+                              with `with_max_block_request`",
+            Self::Proxy => "the SOCKS proxy could not be reached; check that it is running, or allow a direct \
+                            connection with `SocksProxy::with_direct_fallback`",
+            Self::ServerError => "the node failed to serve the request; retry later, or configure another endpoint",
+            Self::NotJson => "a gateway answered instead of the node, e.g. during maintenance; retry later, or \
+                              configure another endpoint",
+            Self::NotFound => "the node does not hold the item; check its identifier, or wait for the node to sync",
+            Self::Rejected => "the node rejected the request as invalid; check its inputs against the node API",
+            Self::BlockRequestLimit => "the node serves fewer blocks at a time; lower `with_max_block_request`",
+            Self::Parse => "the node may run a version this SDK does not support; check the node version, or set \
+                            it with `with_node_version`",
+            Self::NodeVersion => "the node runs a version whose responses this SDK cannot read; upgrade the SDK, or \
+                                  use a node of a supported version",
+            Self::SnarkvmVersion => "responses of the node are adapted where possible; upgrade the SDK to the \
+                                     snarkVM version of the node",
+            Self::PrecisionLoss => "a gateway in front of the node re-serialized amounts as floating point; \
+                                    configure an endpoint that serves them as integers",
+            Self::ResponseMismatch => "the node or a cache in front of it served the wrong item; configure \
+                                       another endpoint",
+            Self::WrongNetwork => "the endpoint, address, or file is for another network; check it against the \
+                                   network of the client",
+            Self::InvalidPath => "raw queries must stay within the chain of the client; remove '..' and absolute \
+                                  URLs from the path",
+            Self::InvalidIdentifier => "identifiers may only hold ASCII letters, digits, '_' and '.'; check the \
+                                        program ID or name",
+            Self::IndexOutOfRange => "the block holds fewer transactions; check the index against the block",
+            Self::InvalidRange => "request a non-empty range within the limit, split into several requests",
+            Self::InvalidArgument => "check the argument against the format of the network, e.g. `aleo1...` for \
+                                      addresses",
+            Self::BudgetExhausted => "the budget of the client is spent; resume from the returned height with a \
+                                      fresh budget",
+            Self::Cancelled => "the operation was cancelled; resume it from the returned height",
+            Self::TooLarge => "raise the limit with `with_max_response_size`, or request fewer blocks at a time",
+            Self::Preempted => "background work gives way to interactive requests; resume it once they complete, \
+                                e.g. after `Budget::wait_for_interactive`",
+            Self::ConfirmationTimeout => "the transaction may still be confirmed; wait longer, or check it with \
+                                          `transaction_status`",
+            Self::TransactionAborted => "the transaction can no longer be confirmed; check its inputs and fee, \
+                                         and build a new transaction",
+            Self::PaymentTimeout => "no matching payment was confirmed yet; wait longer, or check the criteria",
+            Self::Unknown => "see the error message for details",
+        }
+    }
+
+    /// Returns `true` if sending the same request again, after a delay, may succeed.
+    ///
+    /// Failures to reach the node, rate limits, server errors, and responses of gateways in front of a node that is
+    /// down may not last. Errors of unknown kind are not retried, as most of them, such as invalid keys or inputs
+    /// that fail to parse, fail the same way every time.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Connection
+                | Self::RateLimited
+                | Self::Timeout
+                | Self::Proxy
+                | Self::ServerError
+                | Self::NotJson
+                | Self::BlockRequestLimit
+                | Self::ConfirmationTimeout
+                | Self::PaymentTimeout
+        )
+    }
+
+    // Returns `true` if the error arose from the request or the client, so that every endpoint fails with it
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn is_local(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidPath
+                | Self::InvalidIdentifier
+                | Self::IndexOutOfRange
+                | Self::InvalidRange
+                | Self::InvalidArgument
+                | Self::BudgetExhausted
+                | Self::Cancelled
+                | Self::TooLarge
+                | Self::Preempted
+        )
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Typed errors of generic types report their code as their source, so that it is found in the chain of an error.
+impl Error for ErrorCode {}
+
+/// Returns the code of the first typed error of the SDK in the chain of the error, or [`ErrorCode::Unknown`].
+///
+/// Errors raised while reading the body of a response are [`ErrorCode::Connection`].
+pub fn error_code(error: &anyhow::Error) -> ErrorCode {
+    for cause in error.chain() {
+        if let Some(code) = cause.downcast_ref::<ErrorCode>() {
+            return *code;
+        } else if let Some(error) = cause.downcast_ref::<ApiError>() {
+            return error.code();
+        } else if cause.is::<TransactionIndexOutOfRange>() {
+            return ErrorCode::IndexOutOfRange;
+        } else if cause.is::<PaymentTimeout>() {
+            return ErrorCode::PaymentTimeout;
+        } else if cause.is::<WrongNetworkAddress>() {
+            return ErrorCode::WrongNetwork;
+        } else if cause.is::<io::Error>() {
+            return ErrorCode::Connection;
+        }
+    }
+    ErrorCode::Unknown
+}
+
+/// Returns `true` if the request that failed with the error may succeed when sent again, see
+/// [`ErrorCode::is_retryable`].
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    error_code(error).is_retryable()
+}
+
+/// Returns a short hint on what to do about the error, see [`ErrorCode::remediation`].
+pub fn remediation(error: &anyhow::Error) -> &'static str {
+    error_code(error).remediation()
+}
@@ -0,0 +1,331 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    api::amounts::{normalize_fees, ROOT_PATH},
+    ApiError,
+    SNARKVM_VERSION,
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::{Block, Transaction};
+use std::fmt;
+
+/// The format of the blocks and transactions served by a node
+///
+/// Unknown fields are always ignored, so nodes that only add fields are read in either format. Newer nodes
+/// also renamed a few fields, which the client renames back before parsing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NodeVersion {
+    /// Nodes running the snarkVM version of this SDK, which serve the `coinbase` of blocks and the
+    /// `additional_fee` of transactions
+    Native,
+    /// Newer nodes, which serve the `solutions` of blocks and the `fee` of transactions
+    Newer,
+}
+
+impl fmt::Display for NodeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Native => write!(f, "native"),
+            Self::Newer => write!(f, "newer"),
+        }
+    }
+}
+
+/// An item served by a node, whose JSON depends on the version of the node
+pub(crate) trait NodeJson: DeserializeOwned {
+    /// The name of the item in errors
+    const ITEM: &'static str;
+
+    /// Returns the format of the item, if its fields tell it
+    fn detect(value: &Value) -> Option<NodeVersion>;
+
+    /// Renames the fields of newer nodes to the ones this SDK reads
+    fn rename_fields(value: &mut Value);
+
+    /// Adds the fields of the item this SDK does not read to `unknown`
+    fn unknown_fields(value: &Value, unknown: &mut Vec<String>);
+
+    /// Replaces the amounts at `path` of the named item with the JSON integers they hold, failing with
+    /// [`ApiError::PrecisionLoss`] on amounts whose precision was lost
+    fn normalize_amounts(value: &mut Value, item: &str, path: &str) -> Result<(), ApiError>;
+}
+
+/// The fields of a block read by this SDK
+const BLOCK_FIELDS: &[&str] =
+    &["block_hash", "previous_hash", "header", "transactions", "aborted_transaction_ids", "coinbase", "signature"];
+/// The fields of a transaction read by this SDK
+const TRANSACTION_FIELDS: &[&str] = &["type", "id", "deployment", "execution", "additional_fee"];
+
+impl<N: Network> NodeJson for Block<N> {
+    const ITEM: &'static str = "block";
+
+    fn detect(value: &Value) -> Option<NodeVersion> {
+        if value.get("solutions").is_some() {
+            return Some(NodeVersion::Newer);
+        }
+        if value.get("coinbase").is_some() {
+            return Some(NodeVersion::Native);
+        }
+        value.get("transactions")?.as_array()?.iter().find_map(Transaction::<N>::detect)
+    }
+
+    fn rename_fields(value: &mut Value) {
+        if let Some(block) = value.as_object_mut() {
+            rename_field(block, "solutions", "coinbase");
+            if let Some(transactions) = block.get_mut("transactions").and_then(Value::as_array_mut) {
+                transactions.iter_mut().for_each(Transaction::<N>::rename_fields);
+            }
+        }
+    }
+
+    fn unknown_fields(value: &Value, unknown: &mut Vec<String>) {
+        unknown_keys(value, BLOCK_FIELDS, unknown);
+        if let Some(transactions) = value.get("transactions").and_then(Value::as_array) {
+            transactions.iter().for_each(|transaction| Transaction::<N>::unknown_fields(transaction, unknown));
+        }
+    }
+
+    fn normalize_amounts(value: &mut Value, item: &str, path: &str) -> Result<(), ApiError> {
+        if let Some(transactions) = value.get_mut("transactions").and_then(Value::as_array_mut) {
+            for (index, transaction) in transactions.iter_mut().enumerate() {
+                Transaction::<N>::normalize_amounts(transaction, item, &format!("{path}.transactions[{index}]"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<N: Network> NodeJson for Transaction<N> {
+    const ITEM: &'static str = "transaction";
+
+    fn detect(value: &Value) -> Option<NodeVersion> {
+        if value.get("fee").is_some() {
+            return Some(NodeVersion::Newer);
+        }
+        value.get("additional_fee").map(|_| NodeVersion::Native)
+    }
+
+    fn rename_fields(value: &mut Value) {
+        if let Some(transaction) = value.as_object_mut() {
+            rename_field(transaction, "fee", "additional_fee");
+        }
+    }
+
+    fn unknown_fields(value: &Value, unknown: &mut Vec<String>) {
+        unknown_keys(value, TRANSACTION_FIELDS, unknown);
+    }
+
+    fn normalize_amounts(value: &mut Value, item: &str, path: &str) -> Result<(), ApiError> {
+        normalize_fees(value, item, path)
+    }
+}
+
+impl<T: NodeJson> NodeJson for Vec<T> {
+    const ITEM: &'static str = T::ITEM;
+
+    fn detect(value: &Value) -> Option<NodeVersion> {
+        value.as_array()?.iter().find_map(T::detect)
+    }
+
+    fn rename_fields(value: &mut Value) {
+        if let Some(items) = value.as_array_mut() {
+            items.iter_mut().for_each(T::rename_fields);
+        }
+    }
+
+    fn unknown_fields(value: &Value, unknown: &mut Vec<String>) {
+        if let Some(items) = value.as_array() {
+            items.iter().for_each(|item| T::unknown_fields(item, unknown));
+        }
+    }
+
+    fn normalize_amounts(value: &mut Value, item: &str, path: &str) -> Result<(), ApiError> {
+        if let Some(items) = value.as_array_mut() {
+            for (index, element) in items.iter_mut().enumerate() {
+                T::normalize_amounts(element, item, &format!("{path}[{index}]"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Move the value of field `from` to field `to`, unless the object already has field `to`
+fn rename_field(object: &mut Map<String, Value>, from: &str, to: &str) {
+    if !object.contains_key(to) {
+        if let Some(value) = object.remove(from) {
+            object.insert(to.to_string(), value);
+        }
+    }
+}
+
+// Add the keys of an object that are not in `known` to `unknown`, once each
+fn unknown_keys(value: &Value, known: &[&str], unknown: &mut Vec<String>) {
+    if let Some(object) = value.as_object() {
+        for key in object.keys() {
+            if !known.contains(&key.as_str()) && !unknown.contains(key) {
+                unknown.push(key.clone());
+            }
+        }
+    }
+}
+
+/// Parses an item served by a node in the given format, or in the format its fields tell if there is none.
+///
+/// Items that fail to parse in the format of a newer node, or that have fields this SDK does not read, fail with
+/// [`ApiError::NodeVersionMismatch`] as the outer error, as do amounts whose precision was lost with
+/// [`ApiError::PrecisionLoss`]. Other parse errors are returned as the inner error.
+pub(crate) fn from_node_json<T: NodeJson>(
+    mut value: Value,
+    version: Option<NodeVersion>,
+) -> Result<serde_json::Result<T>, ApiError> {
+    let version = version.or_else(|| T::detect(&value)).unwrap_or(NodeVersion::Native);
+    if version == NodeVersion::Newer {
+        T::rename_fields(&mut value);
+    }
+    T::normalize_amounts(&mut value, T::ITEM, ROOT_PATH)?;
+    let mut unknown = Vec::new();
+    T::unknown_fields(&value, &mut unknown);
+    match serde_json::from_value(value) {
+        Ok(item) => Ok(Ok(item)),
+        Err(error) if version == NodeVersion::Newer || !unknown.is_empty() => {
+            let mut reason = error.to_string();
+            if !unknown.is_empty() {
+                reason = format!("{reason} (unrecognized fields: {})", unknown.join(", "));
+            }
+            Err(ApiError::NodeVersionMismatch { item: T::ITEM.to_string(), version, reason })
+        }
+        Err(error) => Ok(Err(error)),
+    }
+}
+
+/// Returns the format of a block served by a node, if its fields tell it.
+///
+/// A block in the format of a newer node fails with [`ApiError::SnarkvmVersion`], as the node runs another
+/// version of snarkVM than the one this SDK is pinned to.
+pub(crate) fn check_block_format<N: Network>(block: &Value) -> Result<Option<NodeVersion>, ApiError> {
+    match Block::<N>::detect(block) {
+        Some(version @ NodeVersion::Newer) => {
+            Err(ApiError::SnarkvmVersion { pinned: SNARKVM_VERSION.to_string(), version })
+        }
+        version => Ok(version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{
+        newer_node_json,
+        sample_block_with_fee,
+        sample_transaction,
+        sample_transition,
+        CurrentNetwork,
+    };
+    use rand::thread_rng;
+
+    type N = CurrentNetwork;
+
+    // Returns a block, serialized by a native node and by a newer node
+    fn block_fixtures() -> (Block<N>, Value, Value) {
+        let block = sample_block_with_fee(1, Default::default(), &mut thread_rng());
+        let native = serde_json::to_value(&block).unwrap();
+        let newer = newer_node_json(&block);
+        (block, native, newer)
+    }
+
+    #[test]
+    fn test_same_block_from_both_versions() {
+        let (block, native, newer) = block_fixtures();
+        assert_eq!(Block::<N>::detect(&native), Some(NodeVersion::Native));
+        assert_eq!(Block::<N>::detect(&newer), Some(NodeVersion::Newer));
+        for version in [None, Some(NodeVersion::Native)] {
+            let parsed: Block<N> = from_node_json(native.clone(), version).unwrap().unwrap();
+            assert_eq!(parsed.hash(), block.hash());
+        }
+        for version in [None, Some(NodeVersion::Newer)] {
+            let parsed: Block<N> = from_node_json(newer.clone(), version).unwrap().unwrap();
+            assert_eq!(parsed.hash(), block.hash());
+        }
+        let parsed: Vec<Block<N>> = from_node_json(Value::Array(vec![newer, native]), None).unwrap().unwrap();
+        assert_eq!(parsed, [block.clone(), block]);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_ignored() {
+        let transaction = sample_transaction([sample_transition(&[], &[], &mut thread_rng())]);
+        let mut value = serde_json::to_value(&transaction).unwrap();
+        value["owner"] = Value::Null;
+        assert_eq!(Transaction::<N>::detect(&value), None);
+        let parsed: Transaction<N> = from_node_json(value, None).unwrap().unwrap();
+        assert_eq!(parsed.id(), transaction.id());
+    }
+
+    #[test]
+    fn test_fees_served_as_floats_or_strings() {
+        let (block, mut native, _) = block_fixtures();
+        let fee = |transaction: &Value| transaction["execution"]["transitions"][0]["fee"].as_i64().unwrap();
+        let (execution_fee, additional_fee) =
+            (fee(&native["transactions"][0]), native["transactions"][0]["additional_fee"]["transition"]["fee"].clone());
+
+        // Fees served as strings, or as floats holding them exactly, are read as they were.
+        native["transactions"][0]["execution"]["transitions"][0]["fee"] = Value::from(execution_fee.to_string());
+        native["transactions"][0]["additional_fee"]["transition"]["fee"] = Value::from(additional_fee.as_f64());
+        let parsed: Block<N> = from_node_json(native.clone(), None).unwrap().unwrap();
+        assert_eq!(parsed.hash(), block.hash());
+
+        // Fees whose precision was lost fail, naming the path of the fee.
+        native["transactions"][0]["execution"]["transitions"][0]["fee"] = Value::from(execution_fee as f64 + 0.5);
+        let error = from_node_json::<Block<N>>(native.clone(), None).unwrap_err();
+        assert_eq!(error, ApiError::PrecisionLoss {
+            item: "block".to_string(),
+            path: "$.transactions[0].execution.transitions[0].fee".to_string(),
+            value: (execution_fee as f64 + 0.5).to_string(),
+        });
+        let transaction = native["transactions"][0].clone();
+        native["transactions"][0]["execution"]["transitions"][0]["fee"] = Value::from(execution_fee);
+        native["transactions"][0]["additional_fee"]["transition"]["fee"] = Value::from(1e20);
+        let error = from_node_json::<Vec<Transaction<N>>>(native["transactions"].clone(), None).unwrap_err();
+        let message = error.to_string();
+        assert!(message.starts_with("Precision loss in the transaction at $[0].additional_fee.transition.fee"));
+        assert!(matches!(from_node_json::<Transaction<N>>(transaction, None), Err(ApiError::PrecisionLoss { .. })));
+    }
+
+    #[test]
+    fn test_node_version_mismatch() {
+        // Blocks that cannot be adapted name the mismatch.
+        let (_, native, mut newer) = block_fixtures();
+        newer["transactions"][0]["fee"]["global_state_root"] = Value::String("unexpected".to_string());
+        let error = from_node_json::<Block<N>>(newer.clone(), None).unwrap_err();
+        let ApiError::NodeVersionMismatch { item, version, reason } = &error else { panic!("unexpected {error}") };
+        assert_eq!((item.as_str(), *version), ("block", NodeVersion::Newer));
+        assert!(reason.ends_with("(unrecognized fields: ratifications)"));
+        assert!(error.to_string().starts_with("Node version mismatch: the block served in the newer format"));
+
+        // So do blocks of a newer node read in the native format.
+        let (_, _, newer) = block_fixtures();
+        let error = from_node_json::<Block<N>>(newer, Some(NodeVersion::Native)).unwrap_err();
+        assert!(matches!(error, ApiError::NodeVersionMismatch { version: NodeVersion::Native, .. }));
+
+        // Malformed blocks in the native format remain parse errors.
+        let mut native = native;
+        native["block_hash"] = Value::Null;
+        assert!(from_node_json::<Block<N>>(native, None).unwrap().is_err());
+    }
+}
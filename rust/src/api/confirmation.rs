@@ -0,0 +1,186 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::ErrorCode;
+
+use snarkvm_console::program::Network;
+
+use std::{error::Error, fmt, time::Duration};
+
+/// The status of a transaction on the chain of a node, as returned by
+/// [`crate::AleoAPIClient::transaction_status`]
+///
+/// The depth of a block is the number of blocks on the chain from it to the tip, counting both, so the tip has
+/// depth 1. A transaction is final once its block reaches the confirmation depth of the client.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransactionStatus<N: Network> {
+    /// The transaction is not in a block of the chain. Either it was not included yet, or the block including it
+    /// was orphaned by a reorganization.
+    Pending,
+    /// The transaction is in a block that is not yet as deep as the confirmation depth
+    IncludedAtDepth { height: u32, block_hash: N::BlockHash, depth: u32 },
+    /// The transaction is in a block at least as deep as the confirmation depth
+    Final { height: u32, block_hash: N::BlockHash },
+    /// The transaction was included in a block as aborted rather than confirmed, so it will never be final
+    Aborted { height: u32, block_hash: N::BlockHash },
+}
+
+impl<N: Network> TransactionStatus<N> {
+    // Returns the status of a transaction in the block at the given height and hash, on a chain with the given tip
+    pub(crate) fn included(height: u32, block_hash: N::BlockHash, tip: u32, confirmation_depth: u32) -> Self {
+        // A node may report the block of a transaction before its tip, so the depth is at least 1.
+        let depth = tip.saturating_sub(height).saturating_add(1);
+        match depth >= confirmation_depth {
+            true => Self::Final { height, block_hash },
+            false => Self::IncludedAtDepth { height, block_hash, depth },
+        }
+    }
+
+    /// Returns `true` if the transaction is final.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Final { .. })
+    }
+
+    /// Returns `true` if the transaction was aborted.
+    pub fn is_aborted(&self) -> bool {
+        matches!(self, Self::Aborted { .. })
+    }
+
+    /// Returns the height of the block including the transaction, if any.
+    pub fn height(&self) -> Option<u32> {
+        match self {
+            Self::Pending => None,
+            Self::IncludedAtDepth { height, .. } | Self::Final { height, .. } | Self::Aborted { height, .. } => {
+                Some(*height)
+            }
+        }
+    }
+
+    /// Returns the hash of the block including the transaction, if any.
+    pub fn block_hash(&self) -> Option<N::BlockHash> {
+        match self {
+            Self::Pending => None,
+            Self::IncludedAtDepth { block_hash, .. }
+            | Self::Final { block_hash, .. }
+            | Self::Aborted { block_hash, .. } => Some(*block_hash),
+        }
+    }
+}
+
+impl<N: Network> fmt::Display for TransactionStatus<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::IncludedAtDepth { height, depth, .. } => write!(f, "included at height {height}, depth {depth}"),
+            Self::Final { height, .. } => write!(f, "final at height {height}"),
+            Self::Aborted { height, .. } => write!(f, "aborted at height {height}"),
+        }
+    }
+}
+
+/// The error returned when a transaction was not final before the timeout
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ConfirmationTimeout<N: Network> {
+    transaction_id: N::TransactionID,
+    timeout: Duration,
+    status: TransactionStatus<N>,
+}
+
+impl<N: Network> ConfirmationTimeout<N> {
+    pub(crate) fn new(transaction_id: N::TransactionID, timeout: Duration, status: TransactionStatus<N>) -> Self {
+        Self { transaction_id, timeout, status }
+    }
+
+    /// Returns the ID of the transaction that was awaited.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the timeout the wait exceeded.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the last status of the transaction.
+    pub fn status(&self) -> TransactionStatus<N> {
+        self.status
+    }
+}
+
+impl<N: Network> fmt::Display for ConfirmationTimeout<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transaction '{}' was not confirmed within {:?}", self.transaction_id, self.timeout)
+    }
+}
+
+impl<N: Network> Error for ConfirmationTimeout<N> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&ErrorCode::ConfirmationTimeout)
+    }
+}
+
+/// The error returned when an awaited transaction was aborted, as it can no longer become final
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TransactionAborted<N: Network> {
+    transaction_id: N::TransactionID,
+    height: u32,
+}
+
+impl<N: Network> TransactionAborted<N> {
+    pub(crate) fn new(transaction_id: N::TransactionID, height: u32) -> Self {
+        Self { transaction_id, height }
+    }
+
+    /// Returns the ID of the aborted transaction.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the height of the block that aborted the transaction.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl<N: Network> fmt::Display for TransactionAborted<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transaction '{}' was aborted in block {}", self.transaction_id, self.height)
+    }
+}
+
+impl<N: Network> Error for TransactionAborted<N> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&ErrorCode::TransactionAborted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, CurrentNetwork};
+
+    #[test]
+    fn test_included_depth() {
+        let hash = genesis_block().hash();
+        let status = TransactionStatus::<CurrentNetwork>::included(10, hash, 11, 3);
+        assert_eq!(status, TransactionStatus::IncludedAtDepth { height: 10, block_hash: hash, depth: 2 });
+        assert_eq!(TransactionStatus::<CurrentNetwork>::included(10, hash, 12, 3).to_string(), "final at height 10");
+        // A tip behind the block of the transaction counts as depth 1.
+        let status = TransactionStatus::<CurrentNetwork>::included(10, hash, 9, 2);
+        assert_eq!(status, TransactionStatus::IncludedAtDepth { height: 10, block_hash: hash, depth: 1 });
+        assert!(TransactionStatus::<CurrentNetwork>::included(10, hash, 9, 1).is_final());
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{api::to_height_range, error_code, ids::heights, redact_url, AleoAPIClient, BlockHeight};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use snarkvm_console::{
+    account::ViewKey,
+    program::{Ciphertext, Network, Record},
+    types::Field,
+};
+use std::{
+    convert::TryInto,
+    ops::RangeBounds,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+/// A query result together with the chain tip of the node that served it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Observed<N: Network, T> {
+    value: T,
+    height: u32,
+    hash: N::BlockHash,
+}
+
+impl<N: Network, T> Observed<N, T> {
+    /// Returns the query result.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the query result, discarding the chain tip.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Returns the latest block height of the serving node, i.e. the block the result is current as of.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the latest block hash of the serving node.
+    pub fn hash(&self) -> N::BlockHash {
+        self.hash
+    }
+}
+
+/// Reads from a set of endpoints while guarding against answers from nodes that lag behind.
+///
+/// Before each query, the reader fetches the latest block of the serving node. If that block is more
+/// than `max_lag` blocks behind the highest tip observed so far, the endpoint is skipped and the query
+/// is retried on the next one. Results are returned as [`Observed`] values, so callers can display the
+/// block height they are current as of.
+pub struct ConsistentReader<N: Network> {
+    endpoints: Vec<AleoAPIClient<N>>,
+    max_lag: u32,
+    max_attempts: usize,
+    observed_tip: AtomicU32,
+    current_endpoint: AtomicUsize,
+}
+
+impl<N: Network> ConsistentReader<N> {
+    /// The default number of blocks an endpoint may trail the observed tip by
+    pub const DEFAULT_MAX_LAG: u32 = 10;
+
+    /// Create a reader over the given endpoints, which are tried in order.
+    ///
+    /// By default every endpoint is tried twice before a read fails.
+    pub fn new(endpoints: Vec<AleoAPIClient<N>>) -> Result<Self> {
+        ensure!(!endpoints.is_empty(), "A consistent reader requires at least one endpoint");
+        let max_attempts = 2 * endpoints.len();
+        Ok(Self {
+            endpoints,
+            max_lag: Self::DEFAULT_MAX_LAG,
+            max_attempts,
+            observed_tip: AtomicU32::new(0),
+            current_endpoint: AtomicUsize::new(0),
+        })
+    }
+
+    /// Set the number of blocks an endpoint may trail the observed tip by before its answers are rejected.
+    pub fn with_max_lag(mut self, max_lag: u32) -> Self {
+        self.max_lag = max_lag;
+        self
+    }
+
+    /// Set the number of attempts made across all endpoints before a read fails.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Seed the observed tip, e.g. with the height of the data last shown to the user.
+    pub fn with_observed_tip(self, height: u32) -> Self {
+        self.observed_tip.store(height, Ordering::SeqCst);
+        self
+    }
+
+    /// Returns the endpoints of the reader.
+    pub fn endpoints(&self) -> &[AleoAPIClient<N>] {
+        &self.endpoints
+    }
+
+    /// Returns the highest block height observed so far, or zero before the first read.
+    pub fn observed_tip(&self) -> u32 {
+        self.observed_tip.load(Ordering::SeqCst)
+    }
+
+    /// Run a query against the current endpoint, switching endpoints when it fails or lags behind.
+    ///
+    /// The reader sticks with an endpoint for as long as it keeps serving consistent answers. Errors of the request
+    /// or the client itself, such as an invalid identifier or an exhausted budget, are returned at once, as every
+    /// endpoint would fail with them.
+    pub fn read<T>(&self, query: impl Fn(&AleoAPIClient<N>) -> Result<T>) -> Result<Observed<N, T>> {
+        let mut last_error = None;
+        for _ in 0..self.max_attempts {
+            let index = self.current_endpoint.load(Ordering::SeqCst) % self.endpoints.len();
+            match self.read_from(&self.endpoints[index], &query) {
+                Ok(observed) => return Ok(observed),
+                Err(error) if error_code(&error).is_local() => return Err(error),
+                Err(error) => {
+                    // Move on to the next endpoint for the following attempt.
+                    self.current_endpoint.store((index + 1) % self.endpoints.len(), Ordering::SeqCst);
+                    last_error = Some(error);
+                }
+            }
+        }
+        let attempts = self.max_attempts;
+        match last_error {
+            Some(error) => bail!("Failed to obtain a consistent response after {attempts} attempts: {error}"),
+            None => bail!("Failed to obtain a consistent response"),
+        }
+    }
+
+    /// Scans the blocks at the given heights for records that match the given view key.
+    ///
+    /// The range may be half-open or inclusive. Blocks are requested in chunks of the smallest
+    /// [`AleoAPIClient::max_block_request`] of the endpoints, and each chunk is read through the guard, so a
+    /// node that has not reached the end of a chunk yet is skipped. The result is observed as of the lowest
+    /// tip that served a chunk.
+    #[allow(clippy::type_complexity)]
+    pub fn scan(
+        &self,
+        view_key: impl TryInto<ViewKey<N>>,
+        block_heights: impl RangeBounds<BlockHeight>,
+    ) -> Result<Observed<N, Vec<(Field<N>, Record<N, Ciphertext<N>>)>>> {
+        // Prepare the view key.
+        let view_key = view_key.try_into().map_err(|_| anyhow!("Invalid view key"))?;
+        let block_heights = to_height_range(block_heights)?;
+        ensure!(block_heights.start < block_heights.end, "Start height must be less than end height");
+        // Compute the x-coordinate of the address.
+        let address_x_coordinate = view_key.to_address().to_x_coordinate();
+
+        let mut records = Vec::new();
+        let mut lowest_tip: Option<(u32, N::BlockHash)> = None;
+
+        let chunk_size = self.endpoints.iter().map(AleoAPIClient::max_block_request).min().unwrap_or(1);
+        for start_height in block_heights.clone().step_by(chunk_size as usize) {
+            let end_height = block_heights.end.min(start_height.saturating_add(chunk_size));
+
+            let blocks = self.read(|client| {
+                let blocks = client.get_block_range(heights(start_height..end_height))?;
+                let expected = (end_height - start_height) as usize;
+                if blocks.len() != expected {
+                    let (base_url, received) = (redact_url(client.base_url()), blocks.len());
+                    bail!("Endpoint {base_url} returned {received} of the {expected} requested blocks");
+                }
+                Ok(blocks)
+            })?;
+            if lowest_tip.map_or(true, |(height, _)| blocks.height() < height) {
+                lowest_tip = Some((blocks.height(), blocks.hash()));
+            }
+
+            // Filter the records by the view key.
+            records.extend(blocks.into_value().into_iter().flat_map(|block| block.into_records()).filter(
+                |(_, record)| record.is_owner_with_address_x_coordinate(&view_key, &address_x_coordinate),
+            ));
+        }
+
+        let (height, hash) = lowest_tip.ok_or_else(|| anyhow!("No blocks were scanned"))?;
+        Ok(Observed { value: records, height, hash })
+    }
+
+    // Run a query against a single endpoint, recording its tip
+    fn read_from<T>(
+        &self,
+        endpoint: &AleoAPIClient<N>,
+        query: &impl Fn(&AleoAPIClient<N>) -> Result<T>,
+    ) -> Result<Observed<N, T>> {
+        // Fetch the latest block, so that the height and hash belong to the same block.
+        let block = endpoint.latest_block()?;
+        let (height, hash) = (block.height(), block.hash());
+
+        // Reject endpoints that trail the observed tip by more than the allowed lag.
+        let tip = self.observed_tip();
+        if tip.saturating_sub(height) > self.max_lag {
+            bail!(
+                "Endpoint {} is at block {height}, which is {} blocks behind the observed tip {tip}",
+                redact_url(endpoint.base_url()),
+                tip - height
+            );
+        }
+
+        let value = query(endpoint)?;
+        self.observed_tip.fetch_max(height, Ordering::SeqCst);
+        Ok(Observed { value, height, hash })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{genesis_block, sample_block, CurrentNetwork, MockResponse, MockServer},
+        testnet3,
+    };
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_utilities::TestRng;
+
+    use std::{
+        collections::HashMap,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    // A mock node at the given tip, counting the queries it answers besides the latest block
+    struct MockNode {
+        server: MockServer,
+        online: Arc<AtomicBool>,
+        queries: Arc<AtomicUsize>,
+    }
+
+    impl MockNode {
+        fn start(tip: u32, rng: &mut TestRng) -> Self {
+            let mut previous_hash = genesis_block().hash();
+            let blocks = (tip.saturating_sub(10)..=tip)
+                .map(|height| {
+                    let block = sample_block(height, previous_hash, rng);
+                    previous_hash = block.hash();
+                    (height, block.to_string())
+                })
+                .collect::<HashMap<_, _>>();
+            let online = Arc::new(AtomicBool::new(true));
+            let queries = Arc::new(AtomicUsize::new(0));
+
+            let (is_online, query_count) = (online.clone(), queries.clone());
+            let server = MockServer::start(move |request| {
+                if !is_online.load(Ordering::SeqCst) {
+                    return None;
+                }
+                if request.path == "/testnet3/latest/block" {
+                    return blocks.get(&tip).map(MockResponse::json);
+                }
+                query_count.fetch_add(1, Ordering::SeqCst);
+                if request.path == "/testnet3/latest/height" {
+                    return Some(MockResponse::json(tip));
+                }
+                // Serve the requested range, truncated to the tip of the node.
+                let (start, end) = request.path.strip_prefix("/testnet3/blocks?start=")?.split_once("&end=")?;
+                let (start, end) = (start.parse::<u32>().ok()?, end.parse::<u32>().ok()?.min(tip + 1));
+                let blocks = (start..end).filter_map(|height| blocks.get(&height).cloned()).collect::<Vec<_>>();
+                Some(MockResponse::json(format!("[{}]", blocks.join(","))))
+            });
+            Self { server, online, queries }
+        }
+
+        fn client(&self) -> AleoAPIClient<CurrentNetwork> {
+            testnet3(self.server.base_url())
+        }
+    }
+
+    #[test]
+    fn test_reader_retries_lagging_endpoint() {
+        let rng = &mut TestRng::default();
+        let leading = MockNode::start(1000, rng);
+        let lagging = MockNode::start(900, rng);
+
+        // The lagging endpoint is tried first, but trails the known tip by 100 blocks.
+        let reader = ConsistentReader::new(vec![lagging.client(), leading.client()]).unwrap().with_observed_tip(1000);
+        let observed = reader.read(|client| client.latest_height()).unwrap();
+        assert_eq!(*observed.value(), BlockHeight(1000));
+        assert_eq!(observed.height(), 1000);
+        assert_eq!(observed.hash(), leading.client().latest_block().unwrap().hash());
+
+        // The lagging endpoint was never queried, and the reader sticks with the leading one.
+        assert_eq!(lagging.queries.load(Ordering::SeqCst), 0);
+        reader.read(|client| client.latest_height()).unwrap();
+        assert_eq!(leading.queries.load(Ordering::SeqCst), 2);
+        assert_eq!(reader.observed_tip(), 1000);
+    }
+
+    #[test]
+    fn test_reader_rejects_lagging_endpoint() {
+        let rng = &mut TestRng::default();
+        let leading = MockNode::start(1000, rng);
+        let lagging = MockNode::start(900, rng);
+
+        let reader = ConsistentReader::new(vec![leading.client(), lagging.client()]).unwrap();
+        assert_eq!(reader.read(|client| client.latest_height()).unwrap().height(), 1000);
+
+        // Once the leading endpoint goes down, only the lagging one answers, which is rejected.
+        leading.online.store(false, Ordering::SeqCst);
+        let error = reader.read(|client| client.latest_height()).unwrap_err();
+        assert!(error.to_string().contains("100 blocks behind the observed tip 1000"), "{error}");
+        assert_eq!(lagging.queries.load(Ordering::SeqCst), 0);
+
+        // A reader that tolerates the lag accepts the answer, without lowering the observed tip.
+        let reader = ConsistentReader::new(vec![lagging.client()]).unwrap().with_max_lag(100).with_observed_tip(1000);
+        let observed = reader.read(|client| client.latest_height()).unwrap();
+        assert_eq!(observed.height(), 900);
+        assert_eq!(reader.observed_tip(), 1000);
+    }
+
+    #[test]
+    fn test_reader_returns_local_errors() {
+        let rng = &mut TestRng::default();
+        let (first, second) = (MockNode::start(1000, rng), MockNode::start(1000, rng));
+        second.online.store(false, Ordering::SeqCst);
+
+        // Errors of the request fail on every endpoint, so they are returned without trying the next one.
+        let reader = ConsistentReader::new(vec![first.client(), second.client()]).unwrap();
+        let error = reader.read(|client| client.query::<u32>("../admin")).unwrap_err();
+        assert_eq!(error_code(&error), crate::ErrorCode::InvalidPath);
+        assert!(error.to_string().starts_with("Invalid query path"), "{error}");
+
+        // Errors of the endpoint move on to the next one.
+        let error = reader.read(|client| client.query::<u32>("missing")).unwrap_err();
+        assert!(error.to_string().starts_with("Failed to obtain a consistent response"), "{error}");
+    }
+
+    #[test]
+    fn test_reader_scan_skips_endpoints_missing_blocks() {
+        let rng = &mut TestRng::default();
+        let leading = MockNode::start(1000, rng);
+        let lagging = MockNode::start(900, rng);
+        let view_key = ViewKey::try_from(&PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+
+        // Without a known tip the lagging endpoint passes the lag check, but cannot serve the range.
+        let reader = ConsistentReader::new(vec![lagging.client(), leading.client()]).unwrap();
+        let observed = reader.scan(view_key, heights(995..1000)).unwrap();
+        assert!(observed.value().is_empty());
+        assert_eq!(observed.height(), 1000);
+        assert_eq!(lagging.queries.load(Ordering::SeqCst), 1);
+        assert_eq!(leading.queries.load(Ordering::SeqCst), 1);
+
+        // Empty ranges are rejected.
+        assert!(reader.scan(view_key, heights(1000..1000)).is_err());
+    }
+}
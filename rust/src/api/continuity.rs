@@ -0,0 +1,152 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{ApiError, ScanDirection};
+
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Block;
+use std::ops::Range;
+
+// The blocks at the ends of a chunk of blocks, and the first pair of its blocks that do not build on each other,
+// so that the chunk can be checked against the chunks next to it without holding its blocks
+#[derive(Clone, Debug)]
+pub(crate) struct ChunkLinks<N: Network> {
+    // The height and previous hash of the first block
+    first: Option<(u32, N::BlockHash)>,
+    // The height and hash of the last block
+    last: Option<(u32, N::BlockHash)>,
+    // The heights of the first pair of blocks that do not build on each other
+    fork: Option<(u32, u32)>,
+}
+
+impl<N: Network> Default for ChunkLinks<N> {
+    fn default() -> Self {
+        Self { first: None, last: None, fork: None }
+    }
+}
+
+impl<N: Network> ChunkLinks<N> {
+    // Returns the links of the blocks, which are in ascending order
+    pub(crate) fn of(blocks: &[Block<N>]) -> Self {
+        let mut links = Self::default();
+        blocks.iter().for_each(|block| links.push(block));
+        links
+    }
+
+    // Add the next block of the chunk
+    pub(crate) fn push(&mut self, block: &Block<N>) {
+        match self.last {
+            Some((height, hash)) if self.fork.is_none() && block.previous_hash() != hash => {
+                self.fork = Some((height, block.height()))
+            }
+            Some(_) => (),
+            None => self.first = Some((block.height(), block.previous_hash())),
+        }
+        self.last = Some((block.height(), block.hash()));
+    }
+
+    // Check that the blocks of the chunk build on each other
+    pub(crate) fn check(&self) -> Result<(), ApiError> {
+        match self.fork {
+            Some((below, above)) => Err(ApiError::ForkedResponse { below, above }),
+            None => Ok(()),
+        }
+    }
+
+    // Check that the first block of the chunk above builds on the last block of this chunk. Empty chunks pass.
+    pub(crate) fn check_below(&self, above: &Self) -> Result<(), ApiError> {
+        match (self.last, above.first) {
+            (Some((below, hash)), Some((above, previous_hash))) if previous_hash != hash => {
+                Err(ApiError::ForkedResponse { below, above })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// A chunk of blocks fetched by a walk over the chain, with what was collected from its blocks
+pub(crate) struct LinkedChunk<N: Network, T> {
+    pub(crate) heights: Range<u32>,
+    pub(crate) links: ChunkLinks<N>,
+    pub(crate) items: Vec<T>,
+}
+
+impl<N: Network, T> LinkedChunk<N, T> {
+    pub(crate) fn new(heights: Range<u32>) -> Self {
+        Self { heights, links: ChunkLinks::default(), items: Vec::new() }
+    }
+
+    // Add the next block of the chunk, collecting from it with `collect`
+    pub(crate) fn push(&mut self, block: Block<N>, collect: &mut impl FnMut(&mut Vec<T>, Block<N>)) {
+        self.links.push(&block);
+        collect(&mut self.items, block)
+    }
+}
+
+// Check, in height order, that the chunk held back by a walk builds on the chunk passed on before it, and that the
+// chunk fetched after it builds on it, with the blocks of each chunk building on each other
+pub(crate) fn check_chunks<N: Network>(
+    passed: Option<&ChunkLinks<N>>,
+    held: &ChunkLinks<N>,
+    next: &ChunkLinks<N>,
+    direction: ScanDirection,
+) -> Result<(), ApiError> {
+    held.check()?;
+    next.check()?;
+    match (direction, passed) {
+        (ScanDirection::Forward, Some(passed)) => passed.check_below(held)?,
+        (ScanDirection::Reverse, Some(passed)) => held.check_below(passed)?,
+        (_, None) => (),
+    }
+    match direction {
+        ScanDirection::Forward => held.check_below(next),
+        ScanDirection::Reverse => next.check_below(held),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{sample_block, sample_chain, CurrentNetwork};
+
+    use snarkvm_console::prelude::TestRng;
+
+    type N = CurrentNetwork;
+
+    #[test]
+    fn test_chunk_links() {
+        let chain = &sample_chain()[..6];
+        let fork = sample_block(3, chain[1].hash(), &mut TestRng::default());
+        let (lower, upper) = (ChunkLinks::of(&chain[..3]), ChunkLinks::of(&chain[3..]));
+        assert!(lower.check().is_ok() && lower.check_below(&upper).is_ok());
+        assert!(check_chunks(Some(&lower), &upper, &ChunkLinks::default(), ScanDirection::Forward).is_ok());
+        assert!(check_chunks(None, &upper, &lower, ScanDirection::Reverse).is_ok());
+
+        // A block of another fork breaks the chunk it is in, or the boundary to the chunk it starts.
+        let mixed = ChunkLinks::of(&[chain[2].clone(), fork.clone(), chain[4].clone()]);
+        assert_eq!(mixed.check(), Err(ApiError::ForkedResponse { below: 2, above: 3 }));
+        let forked = ChunkLinks::<N>::of(&[fork]);
+        assert!(forked.check().is_ok());
+        assert_eq!(lower.check_below(&forked), Err(ApiError::ForkedResponse { below: 2, above: 3 }));
+        let passed = ChunkLinks::of(&chain[..2]);
+        let error = check_chunks(Some(&passed), &ChunkLinks::of(&chain[2..3]), &forked, ScanDirection::Forward);
+        assert_eq!(error, Err(ApiError::ForkedResponse { below: 2, above: 3 }));
+
+        // Chunks are checked in height order, whichever the direction of the walk.
+        let error = check_chunks(Some(&forked), &lower, &ChunkLinks::default(), ScanDirection::Reverse).unwrap_err();
+        assert_eq!(error.to_string(), "The node served blocks of different forks: block 3 does not build on block 2");
+    }
+}
@@ -0,0 +1,451 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{mutex::lock, AleoAPIClient, Clock, NodeNotSynced};
+
+use anyhow::{bail, Result};
+use rand::Rng;
+use snarkvm_console::program::Network;
+use std::{
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+        Condvar,
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How a [`HeightWatcher`] polls the latest height
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// The interval between two polls
+    pub poll_interval: Duration,
+    /// The longest random delay added to each interval, so that many watchers started together do not poll a node
+    /// at the same instants
+    pub jitter: Duration,
+    /// Whether each poll checks the [`AleoAPIClient::node_sync_status`] of the endpoint first, and fails over from
+    /// an endpoint that is syncing or stale as from one that fails
+    pub require_synced: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self { poll_interval: Duration::from_secs(5), jitter: Duration::from_millis(500), require_synced: false }
+    }
+}
+
+/// The error returned when the latest height did not reach a height within a timeout
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeightTimeout {
+    height: u32,
+    timeout: Duration,
+    current: Option<u32>,
+}
+
+impl HeightTimeout {
+    /// Returns the height that was awaited.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the timeout the wait exceeded.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the latest height when the wait timed out, if any poll succeeded.
+    pub fn current(&self) -> Option<u32> {
+        self.current
+    }
+}
+
+impl fmt::Display for HeightTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The latest height did not reach {} within {:?}", self.height, self.timeout)?;
+        match self.current {
+            Some(current) => write!(f, ", it is {current}"),
+            None => write!(f, ", no node returned it"),
+        }
+    }
+}
+
+impl Error for HeightTimeout {}
+
+// The marker of a latest height that no poll returned yet
+const UNKNOWN_HEIGHT: u64 = u64::MAX;
+
+// The state shared by a watcher and its polling thread
+struct WatchState {
+    // The latest height, or `UNKNOWN_HEIGHT`
+    height: AtomicU64,
+    // The index of the endpoint polled last
+    endpoint: AtomicUsize,
+    stopped: AtomicBool,
+    // The last error of a poll of every endpoint, cleared by the next successful poll
+    last_error: Mutex<Option<String>>,
+    subscribers: Mutex<Vec<Sender<u32>>>,
+    // Notified when the height changes or the watcher stops, guarding nothing but the waits
+    changed: (Mutex<()>, Condvar),
+    // The clock of the first endpoint, measuring the deadlines of the waits
+    clock: Arc<dyn Clock>,
+}
+
+impl WatchState {
+    fn current(&self) -> Option<u32> {
+        match self.height.load(Ordering::SeqCst) {
+            UNKNOWN_HEIGHT => None,
+            height => Some(height as u32),
+        }
+    }
+
+    // Record a height returned by a node. The height never goes back, as the endpoint failed over to may lag
+    // behind the one polled before it.
+    fn update(&self, height: u32) {
+        *lock(&self.last_error) = None;
+        let previous = self.current();
+        if previous.map_or(false, |previous| previous >= height) {
+            return;
+        }
+        self.height.store(height as u64, Ordering::SeqCst);
+        // Receivers that were dropped unsubscribe.
+        lock(&self.subscribers).retain(|subscriber| subscriber.send(height).is_ok());
+        let _guard = lock(&self.changed.0);
+        self.changed.1.notify_all();
+    }
+
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        let _guard = lock(&self.changed.0);
+        self.changed.1.notify_all();
+    }
+
+    // Wait until `until` returns `true` for the latest height, the watcher stops, or the deadline passes. Returns
+    // whether `until` returned `true`.
+    fn wait(&self, deadline: Instant, until: impl Fn(Option<u32>) -> bool) -> bool {
+        let mut guard = lock(&self.changed.0);
+        loop {
+            if until(self.current()) {
+                return true;
+            }
+            let now = self.clock.now();
+            if now >= deadline || self.stopped.load(Ordering::SeqCst) {
+                return false;
+            }
+            let (next, waited) = match self.changed.1.wait_timeout(guard, deadline - now) {
+                Ok(waited) => waited,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            guard = next;
+            // The wait lasted until the deadline, even if the clock did not move meanwhile.
+            if waited.timed_out() {
+                return until(self.current());
+            }
+        }
+    }
+}
+
+/// A watcher of the latest height of the chain for the blocking client, polling it on a background thread
+///
+/// The thread polls the first endpoint, and fails over to the next endpoints, in order, when a poll fails. The
+/// endpoint that answered is polled first from then on. A round in which every endpoint failed is retried after the
+/// next interval, with the error kept for [`HeightWatcher::last_error`], so that the thread outlives any transient
+/// failure. Each poll is a request of its client, so a [`crate::Budget`] with a window attached to the client
+/// limits the rate of the polls, which fail until the next window once it is exhausted. With
+/// [`WatchOptions::require_synced`], an endpoint that is not synced fails its poll with [`NodeNotSynced`], so that
+/// the latest height is only read from a synced endpoint.
+///
+/// The latest height is read without locking with [`HeightWatcher::current`], awaited with
+/// [`HeightWatcher::wait_for_height`], and its changes are sent to the receivers of [`HeightWatcher::subscribe`].
+/// The height never goes back, as an endpoint failed over to may lag behind. Deadlines are measured on the clock of
+/// the first endpoint. Dropping the watcher stops the thread and waits for it, which returns once its request in
+/// flight, if any, does.
+pub struct HeightWatcher<N: Network> {
+    endpoints: Vec<AleoAPIClient<N>>,
+    options: WatchOptions,
+    state: Arc<WatchState>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<N: Network> HeightWatcher<N> {
+    /// Start watching the latest height of the given endpoints, in order of preference.
+    pub fn start(endpoints: Vec<AleoAPIClient<N>>, options: WatchOptions) -> Result<Self> {
+        if endpoints.is_empty() {
+            bail!("A height watcher needs at least one endpoint");
+        }
+        let state = Arc::new(WatchState {
+            height: AtomicU64::new(UNKNOWN_HEIGHT),
+            endpoint: AtomicUsize::new(0),
+            stopped: AtomicBool::new(false),
+            last_error: Mutex::new(None),
+            subscribers: Mutex::new(vec![]),
+            changed: (Mutex::new(()), Condvar::new()),
+            clock: endpoints[0].clock.clone(),
+        });
+        let (polled, shared) = (endpoints.clone(), state.clone());
+        let thread = thread::Builder::new()
+            .name("aleo-height-watcher".to_string())
+            .spawn(move || poll(&polled, options, &shared))?;
+        Ok(Self { endpoints, options, state, thread: Some(thread) })
+    }
+
+    /// Returns the latest height, or `None` until a poll succeeds.
+    pub fn current(&self) -> Option<u32> {
+        self.state.current()
+    }
+
+    /// Wait until the latest height reaches `height`, and return the latest height, or fail with [`HeightTimeout`]
+    /// once the timeout elapses.
+    pub fn wait_for_height(&self, height: u32, timeout: Duration) -> Result<u32, HeightTimeout> {
+        let now = self.state.clock.now();
+        let deadline = now.checked_add(timeout).unwrap_or_else(|| far_future(now));
+        match self.state.wait(deadline, |current| current.map_or(false, |current| current >= height)) {
+            true => Ok(self.current().unwrap_or(height)),
+            false => Err(HeightTimeout { height, timeout, current: self.current() }),
+        }
+    }
+
+    /// Returns a receiver of the latest height each time it changes, from the next change on.
+    ///
+    /// Receivers that are dropped unsubscribe. Once the watcher is dropped, the receivers are disconnected.
+    pub fn subscribe(&self) -> Receiver<u32> {
+        let (sender, receiver) = mpsc::channel();
+        lock(&self.state.subscribers).push(sender);
+        receiver
+    }
+
+    /// Returns the endpoints, in order of preference.
+    pub fn endpoints(&self) -> &[AleoAPIClient<N>] {
+        &self.endpoints
+    }
+
+    /// Returns the endpoint polled last, which is polled first at the next poll.
+    pub fn endpoint(&self) -> &AleoAPIClient<N> {
+        &self.endpoints[self.state.endpoint.load(Ordering::SeqCst) % self.endpoints.len()]
+    }
+
+    /// Returns how the latest height is polled.
+    pub fn options(&self) -> WatchOptions {
+        self.options
+    }
+
+    /// Returns the error of the last poll, if every endpoint failed it.
+    pub fn last_error(&self) -> Option<String> {
+        lock(&self.state.last_error).clone()
+    }
+}
+
+impl<N: Network> Drop for HeightWatcher<N> {
+    fn drop(&mut self) {
+        self.state.stop();
+        if let Some(thread) = self.thread.take() {
+            // The thread does not panic, and if it did, there would be nothing left to clean up.
+            let _ = thread.join();
+        }
+    }
+}
+
+// Poll the endpoints until the watcher stops, failing over from an endpoint whose poll fails
+fn poll<N: Network>(endpoints: &[AleoAPIClient<N>], options: WatchOptions, state: &WatchState) {
+    while !state.stopped.load(Ordering::SeqCst) {
+        let first = state.endpoint.load(Ordering::SeqCst);
+        let mut error = None;
+        for index in (first..first + endpoints.len()).map(|index| index % endpoints.len()) {
+            match poll_endpoint(&endpoints[index], options.require_synced) {
+                Ok(height) => {
+                    state.endpoint.store(index, Ordering::SeqCst);
+                    state.update(height);
+                    error = None;
+                    break;
+                }
+                Err(poll_error) => error = Some(poll_error.to_string()),
+            }
+            if state.stopped.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+        if error.is_some() {
+            *lock(&state.last_error) = error;
+        }
+        let jitter = match options.jitter.is_zero() {
+            true => Duration::ZERO,
+            false => rand::thread_rng().gen_range(Duration::ZERO..=options.jitter),
+        };
+        let now = state.clock.now();
+        let deadline = now.checked_add(options.poll_interval + jitter).unwrap_or_else(|| far_future(now));
+        // Sleep until the next poll, waking early once the watcher stops.
+        state.wait(deadline, |_| false);
+    }
+}
+
+// Returns the latest height of the endpoint, failing if the endpoint is required to be synced and is not
+fn poll_endpoint<N: Network>(endpoint: &AleoAPIClient<N>, require_synced: bool) -> Result<u32> {
+    if require_synced {
+        let status = endpoint.node_sync_status()?;
+        if !status.is_synced() {
+            bail!(NodeNotSynced::new(endpoint.base_url(), status));
+        }
+    }
+    Ok(endpoint.latest_height()?.0)
+}
+
+// Returns an instant far enough after `now` to stand for a wait without a deadline
+fn far_future(now: Instant) -> Instant {
+    now + Duration::from_secs(60 * 60 * 24 * 365)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{genesis_block, CurrentNetwork, MockResponse, MockServer},
+        testnet3,
+    };
+
+    use std::sync::{atomic::AtomicU32, Weak};
+
+    type N = CurrentNetwork;
+
+    const OPTIONS: WatchOptions =
+        WatchOptions { poll_interval: Duration::from_millis(10), jitter: Duration::ZERO, require_synced: false };
+
+    // Start a mock node serving the given height, which the test may advance, or failing every request while the
+    // height is `u32::MAX`
+    fn mock_height_server(height: Arc<AtomicU32>) -> MockServer {
+        MockServer::start(move |request| match height.load(Ordering::SeqCst) {
+            _ if request.path != "/testnet3/latest/height" => None,
+            u32::MAX => Some(MockResponse::text(503, "Service Unavailable")),
+            height => Some(MockResponse::json(height)),
+        })
+    }
+
+    #[test]
+    fn test_height_watcher_notifications() {
+        let height = Arc::new(AtomicU32::new(3));
+        let server = mock_height_server(height.clone());
+        let watcher = HeightWatcher::<N>::start(vec![testnet3(server.base_url())], OPTIONS).unwrap();
+        assert_eq!(watcher.wait_for_height(3, Duration::from_secs(5)), Ok(3));
+        assert_eq!(watcher.current(), Some(3));
+
+        // Each change is sent to every receiver, and the receivers that were dropped unsubscribe.
+        let (receiver, dropped) = (watcher.subscribe(), watcher.subscribe());
+        drop(dropped);
+        for next in [4, 7] {
+            height.store(next, Ordering::SeqCst);
+            assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(next));
+        }
+        assert_eq!(watcher.wait_for_height(5, Duration::ZERO), Ok(7));
+        assert_eq!(lock(&watcher.state.subscribers).len(), 1);
+
+        // A height that is not reached times out.
+        let error = watcher.wait_for_height(9, Duration::from_millis(50)).unwrap_err();
+        assert_eq!((error.height(), error.current()), (9, Some(7)));
+        assert_eq!(error.to_string(), "The latest height did not reach 9 within 50ms, it is 7");
+        height.store(9, Ordering::SeqCst);
+        assert_eq!(watcher.wait_for_height(9, Duration::from_secs(5)), Ok(9));
+        assert!(HeightWatcher::<N>::start(vec![], OPTIONS).is_err());
+    }
+
+    #[test]
+    fn test_height_watcher_failover() {
+        let (primary_height, fallback_height) = (Arc::new(AtomicU32::new(u32::MAX)), Arc::new(AtomicU32::new(10)));
+        let (primary, fallback) = (mock_height_server(primary_height.clone()), mock_height_server(fallback_height));
+        let endpoints = vec![testnet3(primary.base_url()), testnet3(fallback.base_url())];
+        let watcher = HeightWatcher::<N>::start(endpoints, OPTIONS).unwrap();
+
+        // The failing primary is failed over from, and the fallback answering is polled first from then on.
+        assert_eq!(watcher.wait_for_height(10, Duration::from_secs(5)), Ok(10));
+        assert_eq!(watcher.endpoint().base_url(), fallback.base_url());
+        assert_eq!(watcher.last_error(), None);
+
+        // A round in which every endpoint fails keeps the thread polling, with the last height.
+        let offline = HeightWatcher::<N>::start(vec![testnet3(primary.base_url())], OPTIONS).unwrap();
+        let error = offline.wait_for_height(0, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.to_string(), "The latest height did not reach 0 within 100ms, no node returned it");
+        assert!(offline.last_error().is_some());
+        primary_height.store(12, Ordering::SeqCst);
+        assert_eq!(offline.wait_for_height(12, Duration::from_secs(5)), Ok(12));
+        assert_eq!(offline.last_error(), None);
+    }
+
+    #[test]
+    fn test_height_watcher_prefers_synced_endpoint() {
+        // Start a mock node at the given height, serving the genesis block as its latest block, and the number of
+        // blocks it is behind its peers, if it serves its sync status
+        let mock_node = |height: u32, behind_blocks: Option<u32>| {
+            let latest_block = genesis_block().to_string();
+            MockServer::start(move |request| match (request.path.as_str(), behind_blocks) {
+                ("/testnet3/latest/height", _) => Some(MockResponse::json(height)),
+                ("/testnet3/latest/block", _) => Some(MockResponse::json(latest_block.clone())),
+                ("/testnet3/sync/status", Some(behind_blocks)) => Some(MockResponse::json(serde_json::json!({
+                    "ledger_height": height,
+                    "network_height": height + behind_blocks,
+                }))),
+                _ => Some(MockResponse::text(404, "Not Found")),
+            })
+        };
+        let (syncing, stale, synced) = (mock_node(8, Some(5)), mock_node(9, None), mock_node(10, Some(0)));
+        let endpoints = vec![
+            testnet3(syncing.base_url()).with_staleness_threshold(Duration::MAX),
+            testnet3(stale.base_url()).with_staleness_threshold(Duration::from_secs(60)),
+            testnet3(synced.base_url()).with_staleness_threshold(Duration::MAX),
+        ];
+
+        // The syncing and the stale endpoints are failed over from, as if they failed.
+        let options = WatchOptions { require_synced: true, ..OPTIONS };
+        let watcher = HeightWatcher::<N>::start(endpoints.clone(), options).unwrap();
+        assert_eq!(watcher.wait_for_height(10, Duration::from_secs(5)), Ok(10));
+        assert_eq!(watcher.endpoint().base_url(), synced.base_url());
+        assert_eq!(watcher.last_error(), None);
+
+        // Every endpoint that is not synced fails, and the polls keep the error of the last one.
+        let unsynced = HeightWatcher::<N>::start(endpoints[..2].to_vec(), options).unwrap();
+        assert!(unsynced.wait_for_height(0, Duration::from_millis(100)).is_err());
+        assert!(unsynced.last_error().unwrap().contains("is not synced: it is stale"));
+
+        // Without the check, the first endpoint is followed, although it lags behind.
+        let unchecked = HeightWatcher::<N>::start(endpoints, OPTIONS).unwrap();
+        assert_eq!(unchecked.wait_for_height(8, Duration::from_secs(5)), Ok(8));
+        assert_eq!(unchecked.endpoint().base_url(), syncing.base_url());
+    }
+
+    #[test]
+    fn test_height_watcher_shutdown() {
+        let height = Arc::new(AtomicU32::new(1));
+        let server = mock_height_server(height);
+        let options = WatchOptions {
+            poll_interval: Duration::from_secs(3600),
+            jitter: Duration::from_secs(60),
+            require_synced: false,
+        };
+        let watcher = HeightWatcher::<N>::start(vec![testnet3(server.base_url())], options).unwrap();
+        assert_eq!(watcher.wait_for_height(1, Duration::from_secs(5)), Ok(1));
+        let receiver = watcher.subscribe();
+        let state: Weak<WatchState> = Arc::downgrade(&watcher.state);
+
+        // Dropping the watcher wakes its thread from a long interval, and joins it, which releases the state it
+        // shared and disconnects the receivers.
+        let started = Instant::now();
+        drop(watcher);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(state.upgrade().is_none());
+        assert_eq!(receiver.recv(), Err(mpsc::RecvError));
+    }
+}
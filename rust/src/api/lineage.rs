@@ -0,0 +1,616 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{api::is_not_found, AleoAPIClient, BlockHeight};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use snarkvm_console::{
+    account::{PrivateKey, ViewKey},
+    program::{Ciphertext, Network, Plaintext, Record},
+    types::Field,
+};
+use snarkvm_synthesizer::Transition;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    fmt::Write,
+    str::FromStr,
+};
+
+/// The direction in which a record lineage is traced
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineageDirection {
+    /// Follow records to the transitions that spent them
+    Forward,
+    /// Follow records back to the transitions that created the records they spent
+    Backward,
+}
+
+/// A transition in a record lineage
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct LineageNode<N: Network> {
+    transition_id: N::TransitionID,
+    transaction_id: N::TransactionID,
+    height: u32,
+    depth: u32,
+}
+
+impl<N: Network> LineageNode<N> {
+    /// Returns the ID of the transition.
+    pub fn transition_id(&self) -> N::TransitionID {
+        self.transition_id
+    }
+
+    /// Returns the ID of the transaction containing the transition.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the height of the block containing the transition.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the number of records between the transition and the one that created the traced record.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+}
+
+/// A record in a record lineage, from the transition that created it to the one that spent it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct LineageEdge<N: Network> {
+    commitment: Field<N>,
+    from: N::TransitionID,
+    to: Option<N::TransitionID>,
+}
+
+impl<N: Network> LineageEdge<N> {
+    /// Returns the commitment of the record.
+    pub fn commitment(&self) -> Field<N> {
+        self.commitment
+    }
+
+    /// Returns the ID of the transition that created the record.
+    pub fn from(&self) -> N::TransitionID {
+        self.from
+    }
+
+    /// Returns the ID of the transition that spent the record, if it is spent and was looked up.
+    pub fn to(&self) -> Option<N::TransitionID> {
+        self.to
+    }
+}
+
+/// The graph of transitions and records reached from a record
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct LineageGraph<N: Network> {
+    origin: Field<N>,
+    direction: LineageDirection,
+    nodes: Vec<LineageNode<N>>,
+    edges: Vec<LineageEdge<N>>,
+    unresolved: Vec<Field<N>>,
+    truncated: bool,
+}
+
+impl<N: Network> LineageGraph<N> {
+    /// Returns the commitment of the record the lineage was traced from.
+    pub fn origin(&self) -> Field<N> {
+        self.origin
+    }
+
+    /// Returns the direction the lineage was traced in.
+    pub fn direction(&self) -> LineageDirection {
+        self.direction
+    }
+
+    /// Returns the transitions of the lineage, in the order they were reached.
+    pub fn nodes(&self) -> &[LineageNode<N>] {
+        &self.nodes
+    }
+
+    /// Returns the records of the lineage, in the order they were reached.
+    pub fn edges(&self) -> &[LineageEdge<N>] {
+        &self.edges
+    }
+
+    /// Returns the transition with the given ID, if it is part of the lineage.
+    pub fn node(&self, transition_id: &N::TransitionID) -> Option<&LineageNode<N>> {
+        self.nodes.iter().find(|node| node.transition_id == *transition_id)
+    }
+
+    /// Returns the records that could not be followed because none of the tracing keys own them.
+    ///
+    /// When tracing forward these are record commitments, and when tracing backward they are the
+    /// serial numbers of spent records.
+    pub fn unresolved(&self) -> &[Field<N>] {
+        &self.unresolved
+    }
+
+    /// Returns `true` if the walk stopped at the depth or node limit before the lineage was complete.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Render the lineage in the Graphviz DOT language.
+    ///
+    /// Transitions are labeled with their block height and transaction ID, and records with their
+    /// commitment. Records without a known spender end in a point.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph lineage {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            let LineageNode { transition_id, transaction_id, height, .. } = node;
+            let label = format!("{transition_id}\\nblock {height}\\n{transaction_id}");
+            let _ = writeln!(dot, "    \"{transition_id}\" [shape=box, label=\"{label}\"];");
+        }
+        for LineageEdge { commitment, from, to } in &self.edges {
+            let to = match to {
+                Some(to) => to.to_string(),
+                None => {
+                    let _ = writeln!(dot, "    \"{commitment}\" [shape=point];");
+                    commitment.to_string()
+                }
+            };
+            let _ = writeln!(dot, "    \"{from}\" -> \"{to}\" [label=\"{commitment}\"];");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // Add a transition to the graph, returning `false` if the node limit was reached
+    fn add_node(&mut self, node: LineageNode<N>, max_nodes: usize) -> bool {
+        if self.nodes.len() >= max_nodes {
+            self.truncated = true;
+            return false;
+        }
+        self.nodes.push(node);
+        true
+    }
+}
+
+impl<N: Network> FromStr for LineageGraph<N> {
+    type Err = anyhow::Error;
+
+    /// Parse a lineage from JSON
+    fn from_str(graph: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(graph)?)
+    }
+}
+
+impl<N: Network> fmt::Display for LineageGraph<N> {
+    /// Serialize a lineage to JSON
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
+
+/// Walks the record graph of the ledger on behalf of the owners of a set of accounts.
+///
+/// Transactions do not reveal which record a transition spends, so the tracer matches serial numbers
+/// with the private keys it is given. Tracing forward, a record is followed to its spender if one of
+/// the keys owns it. Tracing backward, the inputs of a transition are matched against the records of
+/// the keys, which are indexed by scanning the ledger up to the height of the transition.
+pub struct LineageTracer<N: Network> {
+    client: AleoAPIClient<N>,
+    accounts: Vec<(PrivateKey<N>, ViewKey<N>)>,
+    max_nodes: usize,
+}
+
+impl<N: Network> LineageTracer<N> {
+    /// The default maximum number of transitions in a lineage
+    pub const DEFAULT_MAX_NODES: usize = 100;
+
+    /// Create a tracer that follows the records owned by the given private keys.
+    pub fn new(client: AleoAPIClient<N>, private_keys: &[PrivateKey<N>]) -> Result<Self> {
+        ensure!(!private_keys.is_empty(), "A lineage tracer requires at least one private key");
+        let accounts = private_keys
+            .iter()
+            .map(|private_key| Ok((*private_key, ViewKey::try_from(private_key)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { client, accounts, max_nodes: Self::DEFAULT_MAX_NODES })
+    }
+
+    /// Set the maximum number of transitions in a lineage, after which the walk stops.
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes.max(1);
+        self
+    }
+
+    /// Returns the maximum number of transitions in a lineage.
+    pub fn max_nodes(&self) -> usize {
+        self.max_nodes
+    }
+
+    /// Trace the lineage of the record with the given commitment.
+    ///
+    /// The lineage starts at the transition that created the record and follows at most `max_depth`
+    /// records away from it in the given direction. Tracing backward does not look up the spender of
+    /// the traced record itself.
+    pub fn trace_record_lineage(
+        &self,
+        commitment: Field<N>,
+        direction: LineageDirection,
+        max_depth: u32,
+    ) -> Result<LineageGraph<N>> {
+        let creator_id = match self.find_transition_id(commitment)? {
+            Some(transition_id) => transition_id,
+            None => bail!("Record '{commitment}' was not found on-chain"),
+        };
+        let (origin, creator) = self.get_node(creator_id, 0)?;
+        let mut graph = LineageGraph {
+            origin: commitment,
+            direction,
+            nodes: vec![origin],
+            edges: vec![],
+            unresolved: vec![],
+            truncated: false,
+        };
+        match direction {
+            LineageDirection::Forward => {
+                let record = creator
+                    .find_record(&commitment)
+                    .ok_or_else(|| anyhow!("Transition '{creator_id}' does not contain record '{commitment}'"))?;
+                self.trace_forward(&mut graph, (commitment, record.clone(), creator_id, 0), max_depth)?;
+            }
+            LineageDirection::Backward => {
+                graph.edges.push(LineageEdge { commitment, from: creator_id, to: None });
+                self.trace_backward(&mut graph, (creator, 0), max_depth)?;
+            }
+        }
+        Ok(graph)
+    }
+
+    // Follow records to the transitions that spent them, breadth first
+    fn trace_forward(
+        &self,
+        graph: &mut LineageGraph<N>,
+        origin: (Field<N>, Record<N, Ciphertext<N>>, N::TransitionID, u32),
+        max_depth: u32,
+    ) -> Result<()> {
+        let mut queue = VecDeque::new();
+        match max_depth {
+            0 => graph.truncated = true,
+            _ => queue.push_back(origin),
+        }
+        while let Some((commitment, record, from, depth)) = queue.pop_front() {
+            let private_key = match self.accounts.iter().find(|(_, view_key)| record.is_owner(view_key)) {
+                Some((private_key, _)) => private_key,
+                None => {
+                    graph.edges.push(LineageEdge { commitment, from, to: None });
+                    graph.unresolved.push(commitment);
+                    continue;
+                }
+            };
+            let serial_number = Record::<N, Plaintext<N>>::serial_number(*private_key, commitment)?;
+            let to = self.find_transition_id(serial_number)?;
+            graph.edges.push(LineageEdge { commitment, from, to });
+
+            // Expand the spender, unless it was already reached through another record.
+            let spender_id = match to {
+                Some(spender_id) if graph.node(&spender_id).is_none() => spender_id,
+                _ => continue,
+            };
+            let (node, spender) = self.get_node(spender_id, depth + 1)?;
+            if !graph.add_node(node, self.max_nodes) {
+                break;
+            }
+            for (commitment, record) in spender.into_records() {
+                match depth + 1 < max_depth {
+                    true => queue.push_back((commitment, record, spender_id, depth + 1)),
+                    false => graph.truncated = true,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Follow the inputs of transitions back to the transitions that created them, breadth first
+    fn trace_backward(&self, graph: &mut LineageGraph<N>, origin: (Transition<N>, u32), max_depth: u32) -> Result<()> {
+        let mut index = SerialNumberIndex::default();
+        let mut queue = VecDeque::from([origin]);
+        while let Some((transition, depth)) = queue.pop_front() {
+            let to = *transition.id();
+            let height = graph.node(&to).map(LineageNode::height).unwrap_or_default();
+            for serial_number in transition.serial_numbers() {
+                if depth >= max_depth {
+                    graph.truncated = true;
+                    break;
+                }
+                self.index_records(&mut index, height)?;
+                let commitment = match index.commitments.get(serial_number) {
+                    Some(commitment) => *commitment,
+                    None => {
+                        graph.unresolved.push(*serial_number);
+                        continue;
+                    }
+                };
+                let from = self
+                    .find_transition_id(commitment)?
+                    .ok_or_else(|| anyhow!("Record '{commitment}' was not found on-chain"))?;
+                graph.edges.push(LineageEdge { commitment, from, to: Some(to) });
+
+                // Expand the creator, unless it was already reached through another record.
+                if graph.node(&from).is_some() {
+                    continue;
+                }
+                let (node, creator) = self.get_node(from, depth + 1)?;
+                if !graph.add_node(node, self.max_nodes) {
+                    return Ok(());
+                }
+                queue.push_back((creator, depth + 1));
+            }
+        }
+        Ok(())
+    }
+
+    // Index the serial numbers of the records owned by the tracing keys, up to the given height
+    fn index_records(&self, index: &mut SerialNumberIndex<N>, height: u32) -> Result<()> {
+        if height < index.end_height {
+            return Ok(());
+        }
+        for block in self.client.get_block_range(BlockHeight(index.end_height)..=BlockHeight(height))? {
+            for (commitment, record) in block.records() {
+                for (private_key, view_key) in &self.accounts {
+                    if record.is_owner(view_key) {
+                        let serial_number = Record::<N, Plaintext<N>>::serial_number(*private_key, *commitment)?;
+                        index.commitments.insert(serial_number, *commitment);
+                    }
+                }
+            }
+        }
+        index.end_height = height + 1;
+        Ok(())
+    }
+
+    // Find the transition that consumed or produced the given input or output ID, if it is on-chain
+    fn find_transition_id(&self, input_or_output_id: Field<N>) -> Result<Option<N::TransitionID>> {
+        match self.client.find_transition_id(input_or_output_id) {
+            Ok(transition_id) => Ok(Some(transition_id)),
+            Err(error) if is_not_found(&error) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    // Fetch a transition together with its location on the ledger
+    fn get_node(&self, transition_id: N::TransitionID, depth: u32) -> Result<(LineageNode<N>, Transition<N>)> {
+        let transaction_id = self.client.find_transaction_id(transition_id)?;
+        let transaction = self.client.get_transaction(transaction_id)?;
+        let transition = match transaction.find_transition(&transition_id) {
+            Some(transition) => transition.clone(),
+            None => bail!("Transaction '{transaction_id}' does not contain transition '{transition_id}'"),
+        };
+        let height = self.client.get_height(self.client.find_block_hash(transaction_id)?)?.0;
+        Ok((LineageNode { transition_id, transaction_id, height, depth }, transition))
+    }
+}
+
+// The commitments of the records owned by the tracing keys, by serial number
+struct SerialNumberIndex<N: Network> {
+    commitments: HashMap<Field<N>, Field<N>>,
+    end_height: u32,
+}
+
+impl<N: Network> Default for SerialNumberIndex<N> {
+    fn default() -> Self {
+        Self { commitments: HashMap::new(), end_height: 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_block_with_transactions,
+            sample_output,
+            sample_transaction,
+            sample_transition,
+            CurrentNetwork,
+            MockResponse,
+            MockServer,
+        },
+        testnet3,
+    };
+    use snarkvm_console::{account::Address, prelude::Uniform};
+    use snarkvm_synthesizer::Block;
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    // A synthetic chain of transfers between the records of one account
+    struct SampleChain {
+        private_key: PrivateKey<N>,
+        blocks: Vec<Block<N>>,
+        transitions: Vec<<N as Network>::TransitionID>,
+        commitments: Vec<Field<N>>,
+        foreign: Field<N>,
+        unknown: Field<N>,
+    }
+
+    // Sample a chain in which each of blocks 1 to 4 contains one transition:
+    // T0 creates r0, T1 spends r0 and creates r1 and a record of another account, T2 spends r1 and a record
+    // of another account and creates r2, and T3 spends r2 and creates r3, which is unspent.
+    fn sample_chain(rng: &mut TestRng) -> SampleChain {
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let address = Address::try_from(&private_key).unwrap();
+        let other = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+
+        let records = (0..4).map(|_| sample_output(address, 100, rng)).collect::<Vec<_>>();
+        let commitments = records.iter().map(|(commitment, _)| *commitment).collect::<Vec<_>>();
+        let serial_numbers = commitments
+            .iter()
+            .map(|commitment| Record::<N, Plaintext<N>>::serial_number(private_key, *commitment).unwrap())
+            .collect::<Vec<_>>();
+        let foreign = sample_output(other, 100, rng);
+        let unknown = Field::rand(rng);
+
+        let transitions = [
+            sample_transition(&[], &records[0..1], rng),
+            sample_transition(&serial_numbers[0..1], &[records[1].clone(), foreign.clone()], rng),
+            sample_transition(&[serial_numbers[1], unknown], &records[2..3], rng),
+            sample_transition(&serial_numbers[2..3], &records[3..4], rng),
+        ];
+        let mut blocks = vec![genesis_block()];
+        for (height, transition) in (1..).zip(transitions.iter()) {
+            let transaction = sample_transaction([transition.clone()]);
+            let previous_hash = blocks.last().unwrap().hash();
+            let block = sample_block_with_transactions(height, previous_hash, [transaction].into_iter().collect(), rng);
+            blocks.push(block);
+        }
+        let transitions = transitions.iter().map(|transition| *transition.id()).collect();
+        SampleChain { private_key, blocks, transitions, commitments, foreign: foreign.0, unknown }
+    }
+
+    // Start a mock node serving the given blocks
+    fn mock_node(blocks: &[Block<N>]) -> MockServer {
+        let mut responses = HashMap::new();
+        for (height, block) in blocks.iter().enumerate() {
+            let hash = block.hash();
+            responses.insert(format!("/testnet3/height/{hash}"), height.to_string());
+            for transaction in block.transactions().iter() {
+                let transaction_id = transaction.id();
+                responses.insert(format!("/testnet3/transaction/{transaction_id}"), transaction.to_string());
+                responses.insert(format!("/testnet3/find/blockHash/{transaction_id}"), format!("\"{hash}\""));
+                for transition in transaction.transitions() {
+                    let transition_id = transition.id();
+                    responses.insert(
+                        format!("/testnet3/find/transactionID/{transition_id}"),
+                        format!("\"{transaction_id}\""),
+                    );
+                    for id in transition.input_ids().chain(transition.output_ids()) {
+                        responses.insert(format!("/testnet3/find/transitionID/{id}"), format!("\"{transition_id}\""));
+                    }
+                }
+            }
+        }
+        let blocks = blocks.iter().map(|block| block.to_string()).collect::<Vec<_>>();
+        MockServer::start(move |request| {
+            if let Some(range) = request.path.strip_prefix("/testnet3/blocks?start=") {
+                let (start, end) = range.split_once("&end=")?;
+                let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+                return Some(MockResponse::json(format!("[{}]", blocks.get(start..end)?.join(","))));
+            }
+            responses.get(&request.path).map(MockResponse::json)
+        })
+    }
+
+    #[test]
+    fn test_trace_record_lineage_forward() {
+        let rng = &mut TestRng::default();
+        let chain = sample_chain(rng);
+        let node = mock_node(&chain.blocks);
+        let tracer = LineageTracer::new(testnet3(node.base_url()), &[chain.private_key]).unwrap();
+        let (transitions, commitments) = (&chain.transitions, &chain.commitments);
+
+        let graph = tracer.trace_record_lineage(commitments[0], LineageDirection::Forward, 10).unwrap();
+        let nodes = graph.nodes().iter().map(|node| (node.transition_id(), node.height(), node.depth()));
+        assert_eq!(
+            nodes.collect::<Vec<_>>(),
+            vec![(transitions[0], 1, 0), (transitions[1], 2, 1), (transitions[2], 3, 2), (transitions[3], 4, 3)]
+        );
+        let edges = graph.edges().iter().map(|edge| (edge.commitment(), edge.from(), edge.to()));
+        assert_eq!(
+            edges.collect::<Vec<_>>(),
+            vec![
+                (commitments[0], transitions[0], Some(transitions[1])),
+                (commitments[1], transitions[1], Some(transitions[2])),
+                (chain.foreign, transitions[1], None),
+                (commitments[2], transitions[2], Some(transitions[3])),
+                (commitments[3], transitions[3], None),
+            ]
+        );
+        assert_eq!(graph.unresolved(), &[chain.foreign]);
+        assert!(!graph.is_truncated());
+        assert_eq!(
+            graph.node(&transitions[2]).unwrap().transaction_id(),
+            *chain.blocks[3].transaction_ids().next().unwrap()
+        );
+
+        // The lineage survives a round trip through JSON and renders to DOT.
+        assert_eq!(LineageGraph::<N>::from_str(&graph.to_string()).unwrap(), graph);
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph lineage {"));
+        assert!(
+            dot.contains(&format!("\"{}\" -> \"{}\" [label=\"{}\"];", transitions[0], transitions[1], commitments[0]))
+        );
+        assert!(dot.contains(&format!("\"{}\" [shape=point];", commitments[3])));
+    }
+
+    #[test]
+    fn test_trace_record_lineage_backward() {
+        let rng = &mut TestRng::default();
+        let chain = sample_chain(rng);
+        let node = mock_node(&chain.blocks);
+        let tracer = LineageTracer::new(testnet3(node.base_url()), &[chain.private_key]).unwrap();
+        let (transitions, commitments) = (&chain.transitions, &chain.commitments);
+
+        let graph = tracer.trace_record_lineage(commitments[3], LineageDirection::Backward, 10).unwrap();
+        let nodes = graph.nodes().iter().map(|node| (node.transition_id(), node.height(), node.depth()));
+        assert_eq!(
+            nodes.collect::<Vec<_>>(),
+            vec![(transitions[3], 4, 0), (transitions[2], 3, 1), (transitions[1], 2, 2), (transitions[0], 1, 3)]
+        );
+        let edges = graph.edges().iter().map(|edge| (edge.commitment(), edge.from(), edge.to()));
+        assert_eq!(
+            edges.collect::<Vec<_>>(),
+            vec![
+                (commitments[3], transitions[3], None),
+                (commitments[2], transitions[2], Some(transitions[3])),
+                (commitments[1], transitions[1], Some(transitions[2])),
+                (commitments[0], transitions[0], Some(transitions[1])),
+            ]
+        );
+        assert_eq!(graph.unresolved(), &[chain.unknown]);
+        assert!(!graph.is_truncated());
+    }
+
+    #[test]
+    fn test_trace_record_lineage_limits() {
+        let rng = &mut TestRng::default();
+        let chain = sample_chain(rng);
+        let node = mock_node(&chain.blocks);
+        let tracer = LineageTracer::new(testnet3(node.base_url()), &[chain.private_key]).unwrap();
+        let (transitions, commitments) = (&chain.transitions, &chain.commitments);
+
+        // The walk stops after the given number of records.
+        let graph = tracer.trace_record_lineage(commitments[0], LineageDirection::Forward, 2).unwrap();
+        assert_eq!(graph.nodes().len(), 3);
+        assert!(graph.node(&transitions[2]).is_some());
+        assert!(graph.edges().iter().all(|edge| edge.commitment() != commitments[2]));
+        assert!(graph.is_truncated());
+        let graph = tracer.trace_record_lineage(commitments[3], LineageDirection::Backward, 1).unwrap();
+        assert_eq!(graph.nodes().len(), 2);
+        assert!(graph.is_truncated());
+
+        // The walk stops after the given number of transitions.
+        let tracer = tracer.with_max_nodes(2);
+        let graph = tracer.trace_record_lineage(commitments[3], LineageDirection::Backward, 10).unwrap();
+        let nodes = graph.nodes().iter().map(LineageNode::transition_id).collect::<Vec<_>>();
+        assert_eq!(nodes, vec![transitions[3], transitions[2]]);
+        assert!(graph.is_truncated());
+
+        // Records that are not on-chain are rejected.
+        let missing = Field::rand(rng);
+        let error = tracer.trace_record_lineage(missing, LineageDirection::Forward, 10).unwrap_err();
+        assert_eq!(error.to_string(), format!("Record '{missing}' was not found on-chain"));
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{BlockHeight, Round};
+
+use serde::{Deserialize, Serialize};
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Block;
+
+/// The header metadata of a block, as a flat structure that serializes directly into API responses
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BlockMetadata<N: Network> {
+    /// The height of the block
+    pub height: BlockHeight,
+    /// The hash of the block
+    pub hash: N::BlockHash,
+    /// The hash of the previous block
+    pub previous_hash: N::BlockHash,
+    /// The UNIX timestamp of the block, in seconds
+    pub timestamp: i64,
+    /// The round in which the block was produced
+    pub round: Round,
+    /// The ID of the network the block belongs to
+    pub network_id: u16,
+    /// The coinbase target of the block
+    pub coinbase_target: u64,
+    /// The proof target of the block
+    pub proof_target: u64,
+    /// The coinbase target of the last block with a coinbase
+    pub last_coinbase_target: u64,
+    /// The UNIX timestamp of the last block with a coinbase, in seconds
+    pub last_coinbase_timestamp: i64,
+    /// The number of transactions in the block
+    pub transaction_count: usize,
+}
+
+impl<N: Network> From<&Block<N>> for BlockMetadata<N> {
+    fn from(block: &Block<N>) -> Self {
+        let header = block.header();
+        Self {
+            height: BlockHeight(header.height()),
+            hash: block.hash(),
+            previous_hash: block.previous_hash(),
+            timestamp: header.timestamp(),
+            round: Round(header.round()),
+            network_id: header.network(),
+            coinbase_target: header.coinbase_target(),
+            proof_target: header.proof_target(),
+            last_coinbase_target: header.last_coinbase_target(),
+            last_coinbase_timestamp: header.last_coinbase_timestamp(),
+            transaction_count: block.transactions().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, CurrentNetwork};
+
+    // The metadata of the Testnet3 genesis block, recorded from its header
+    const GENESIS_METADATA: &str = r#"{
+  "height": 0,
+  "hash": "ab1pfhf6r4e2cv3v9scmkgs8nrp3gfk2rs38rgl409p3ma9wkaprvzqpwlgpz",
+  "previous_hash": "ab1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq5g436j",
+  "timestamp": 1663718400,
+  "round": 0,
+  "network_id": 3,
+  "coinbase_target": 1023,
+  "proof_target": 8,
+  "last_coinbase_target": 1023,
+  "last_coinbase_timestamp": 1663718400,
+  "transaction_count": 1
+}"#;
+
+    #[test]
+    fn test_block_metadata_fields() {
+        // Each field is taken from the accessor of the same meaning, so renames in snarkVM fail here.
+        let metadata = BlockMetadata::<CurrentNetwork>::from(&genesis_block());
+        assert_eq!(serde_json::to_string_pretty(&metadata).unwrap(), GENESIS_METADATA);
+        assert_eq!(serde_json::from_str::<BlockMetadata<CurrentNetwork>>(GENESIS_METADATA).unwrap(), metadata);
+    }
+}
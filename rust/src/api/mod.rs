@@ -16,34 +16,741 @@
 
 #[cfg(not(feature = "async"))]
 pub mod blocking;
-#[cfg(not(feature = "async"))]
-pub use blocking::*;
 
 #[cfg(feature = "async")]
 pub mod asynchronous;
-#[cfg(feature = "async")]
-pub use asynchronous::*;
 
+#[cfg(not(feature = "async"))]
+mod body;
+
+mod activity;
+pub use activity::*;
+
+mod amounts;
+
+#[cfg(not(feature = "async"))]
+mod archive;
+#[cfg(not(feature = "async"))]
+pub use archive::*;
+
+#[cfg(not(feature = "async"))]
+mod cassette;
+#[cfg(not(feature = "async"))]
+pub use cassette::*;
+
+#[cfg(not(feature = "async"))]
+mod budget;
+#[cfg(not(feature = "async"))]
+pub use budget::*;
+
+#[cfg(not(feature = "async"))]
+mod broadcast;
+#[cfg(not(feature = "async"))]
+pub(crate) use broadcast::*;
+
+mod cancellation;
+pub use cancellation::*;
+
+#[cfg(not(feature = "async"))]
+mod chain_stats;
+#[cfg(not(feature = "async"))]
+pub use chain_stats::*;
+
+mod clock;
+pub use clock::*;
+
+mod codes;
+pub use codes::*;
+
+mod compat;
+pub use compat::*;
+
+mod confirmation;
+pub use confirmation::*;
+
+mod continuity;
+pub(crate) use continuity::*;
+
+mod error;
+pub use error::*;
+
+#[cfg(not(feature = "async"))]
+mod consistency;
+#[cfg(not(feature = "async"))]
+pub use consistency::*;
+
+#[cfg(not(feature = "async"))]
+mod height_watcher;
+#[cfg(not(feature = "async"))]
+pub use height_watcher::*;
+
+#[cfg(not(feature = "async"))]
+mod lineage;
+#[cfg(not(feature = "async"))]
+pub use lineage::*;
+
+mod metadata;
+pub use metadata::*;
+
+mod network;
+pub use network::*;
+
+mod payment;
+pub use payment::*;
+
+#[cfg(not(feature = "async"))]
+mod node_status;
+#[cfg(not(feature = "async"))]
+pub use node_status::*;
+
+#[cfg(not(feature = "async"))]
+mod pagination;
+#[cfg(not(feature = "async"))]
+pub use pagination::*;
+
+#[cfg(not(feature = "async"))]
+mod prefetch;
+#[cfg(not(feature = "async"))]
+pub use prefetch::*;
+
+#[cfg(not(feature = "async"))]
+mod prefilter;
+#[cfg(not(feature = "async"))]
+pub(crate) use prefilter::*;
+
+#[cfg(not(feature = "async"))]
+mod prover_pool;
+#[cfg(not(feature = "async"))]
+pub use prover_pool::*;
+
+#[cfg(not(feature = "async"))]
+mod search;
+#[cfg(not(feature = "async"))]
+pub use search::*;
+
+#[cfg(feature = "socks")]
+mod proxy;
+#[cfg(feature = "socks")]
+pub use proxy::*;
+
+#[cfg(not(feature = "async"))]
+mod response_cache;
+#[cfg(not(feature = "async"))]
+pub(crate) use response_cache::*;
+
+mod scanned;
+pub use scanned::*;
+
+mod solution;
+pub use solution::*;
+
+mod telemetry;
+pub use telemetry::*;
+
+mod transactions;
+pub use transactions::*;
+
+mod url;
+pub(crate) use url::*;
+
+use crate::{mutex::lock, BlockCache};
+
+use anyhow::{bail, Result};
 use snarkvm_console::{network::Testnet3, program::Network};
-use std::marker::PhantomData;
+use snarkvm_synthesizer::Block;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    marker::PhantomData,
+    ops::{Bound, Range, RangeBounds},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+        Mutex,
+    },
+};
+#[cfg(not(feature = "async"))]
+use std::time::Duration;
 
+#[derive(Clone)]
 pub struct AleoAPIClient<N: Network> {
     #[cfg(feature = "async")]
     client: reqwest::Client,
     #[cfg(not(feature = "async"))]
     client: ureq::Agent,
+    #[cfg(all(feature = "socks", feature = "async"))]
+    direct_client: Option<reqwest::Client>,
+    #[cfg(all(feature = "socks", not(feature = "async")))]
+    direct_client: Option<ureq::Agent>,
+    #[cfg(feature = "socks")]
+    proxy: Option<SocksProxy>,
     base_url: String,
     chain: String,
+    max_block_request: Arc<AtomicU32>,
+    max_response_size: u64,
+    strict: bool,
+    confirmation_depth: u32,
+    historical_mappings: bool,
+    #[cfg(not(feature = "async"))]
+    scan_options: ScanOptions,
+    #[cfg(not(feature = "async"))]
+    broadcast_cache: Arc<BroadcastCache<N>>,
+    #[cfg(not(feature = "async"))]
+    response_cache: Option<Arc<ResponseCache>>,
+    #[cfg(not(feature = "async"))]
+    budget: Option<Budget>,
+    #[cfg(not(feature = "async"))]
+    priority: RequestPriority,
+    #[cfg(not(feature = "async"))]
+    headers: Vec<(String, String)>,
+    #[cfg(not(feature = "async"))]
+    cassette: Option<CassetteTransport>,
+    #[cfg(not(feature = "async"))]
+    staleness_threshold: Duration,
+    // Whether the node serves its sync status, once probed, shared by the clones of the client
+    #[cfg(not(feature = "async"))]
+    sync_status_route: Arc<Mutex<Option<bool>>>,
+    block_cache: Option<Arc<Mutex<BlockCache<N>>>>,
+    node_version: Option<NodeVersion>,
+    custom_network: Option<CustomNetwork<N>>,
+    genesis_verified: Arc<AtomicBool>,
+    clock: Arc<dyn Clock>,
     _network: PhantomData<N>,
 }
 
+/// Options for the pipeline that scans the ledger for records
+///
+/// Scans fetch chunks of blocks on one thread, and check the ownership of their records on a pool of
+/// `check_threads` threads. Up to `prefetch_chunks` fetched chunks wait for their checks, which bounds the
+/// memory held by a scan.
+#[cfg(not(feature = "async"))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// The number of threads checking the ownership of records, by default the available parallelism
+    pub check_threads: usize,
+    /// The number of fetched chunks that may wait for their checks
+    pub prefetch_chunks: usize,
+    /// Whether scans of blocks in the binary encoding of snarkVM, such as those of an [`ArchiveReader`], check the
+    /// ownership of records on their bytes and only decode the records that pass. Nodes serve blocks as JSON, so
+    /// scans of a node always decode every record.
+    pub fast_prefilter: bool,
+}
+
+#[cfg(not(feature = "async"))]
+impl Default for ScanOptions {
+    fn default() -> Self {
+        let check_threads = std::thread::available_parallelism().map_or(1, usize::from);
+        Self { check_threads, prefetch_chunks: 2, fast_prefilter: true }
+    }
+}
+
 impl<N: Network> AleoAPIClient<N> {
+    /// The default maximum number of blocks requested at a time
+    pub const DEFAULT_MAX_BLOCK_REQUEST: u32 = 50;
+    /// The default maximum size of a response body in bytes
+    pub const DEFAULT_MAX_RESPONSE_SIZE: u64 = 256 * 1024 * 1024;
+    /// The default age past which the latest block of the node is stale
+    #[cfg(not(feature = "async"))]
+    pub const DEFAULT_STALENESS_THRESHOLD: Duration = Duration::from_secs(5 * 60);
+
     pub fn new(base_url: &str, chain: &str) -> Self {
         #[cfg(feature = "async")]
         let client = reqwest::Client::new();
         #[cfg(not(feature = "async"))]
         let client = ureq::Agent::new();
-        AleoAPIClient { client, base_url: base_url.to_string(), chain: chain.to_string(), _network: PhantomData }
+        AleoAPIClient {
+            client,
+            #[cfg(feature = "socks")]
+            direct_client: None,
+            #[cfg(feature = "socks")]
+            proxy: None,
+            base_url: base_url.to_string(),
+            chain: chain.to_string(),
+            max_block_request: Arc::new(AtomicU32::new(Self::DEFAULT_MAX_BLOCK_REQUEST)),
+            max_response_size: Self::DEFAULT_MAX_RESPONSE_SIZE,
+            strict: false,
+            confirmation_depth: 1,
+            historical_mappings: false,
+            #[cfg(not(feature = "async"))]
+            scan_options: ScanOptions::default(),
+            #[cfg(not(feature = "async"))]
+            broadcast_cache: Arc::new(BroadcastCache::new(BroadcastCache::<N>::DEFAULT_TTL, Arc::new(SystemClock))),
+            #[cfg(not(feature = "async"))]
+            response_cache: None,
+            #[cfg(not(feature = "async"))]
+            budget: None,
+            #[cfg(not(feature = "async"))]
+            priority: RequestPriority::Interactive,
+            #[cfg(not(feature = "async"))]
+            headers: vec![],
+            #[cfg(not(feature = "async"))]
+            cassette: None,
+            #[cfg(not(feature = "async"))]
+            staleness_threshold: Self::DEFAULT_STALENESS_THRESHOLD,
+            #[cfg(not(feature = "async"))]
+            sync_status_route: Arc::new(Mutex::new(None)),
+            block_cache: None,
+            node_version: None,
+            custom_network: None,
+            genesis_verified: Arc::new(AtomicBool::new(false)),
+            clock: Arc::new(SystemClock),
+            _network: PhantomData,
+        }
+    }
+
+    /// Create a client of a private or custom chain, which checks every block it fetches against the network.
+    ///
+    /// The client queries the REST path of the network, and fails with [`ApiError::WrongNetwork`] on blocks of
+    /// another network ID, or once it finds that the genesis block of the node has another hash. The genesis block
+    /// is fetched with the first block the client fetches, and clones of the client share the result. Program
+    /// managers are created for the chain by passing them the client.
+    pub fn custom(base_url: &str, network: CustomNetwork<N>) -> Self {
+        let mut client = Self::new(base_url, network.chain());
+        client.custom_network = Some(network);
+        client
+    }
+
+    /// Connect to the node through the given SOCKS5 proxy, such as a local Tor daemon, which also makes `.onion`
+    /// endpoints reachable.
+    ///
+    /// Host names are resolved by the proxy, so that no DNS query for the node leaves the proxy. Requests that
+    /// cannot connect through the proxy fail with [`ApiError::Proxy`], unless the proxy allows a direct connection
+    /// with [`SocksProxy::with_direct_fallback`]. The proxy is set per client, so that the endpoints of a failover
+    /// list may mix clearnet nodes, reached directly or through the proxy, and onion services.
+    #[cfg(feature = "socks")]
+    pub fn with_socks_proxy(mut self, proxy: SocksProxy) -> Result<Self> {
+        #[cfg(feature = "async")]
+        {
+            self.client = reqwest::Client::builder().proxy(proxy.to_reqwest()?).build()?;
+            self.direct_client = proxy.allows_direct_fallback().then(reqwest::Client::new);
+        }
+        #[cfg(not(feature = "async"))]
+        {
+            self.client = ureq::AgentBuilder::new().proxy(proxy.to_ureq()?).build();
+            self.direct_client = proxy.allows_direct_fallback().then(ureq::Agent::new);
+        }
+        self.proxy = Some(proxy);
+        Ok(self)
+    }
+
+    /// Returns the SOCKS5 proxy the client connects through, if any.
+    #[cfg(feature = "socks")]
+    pub fn socks_proxy(&self) -> Option<&SocksProxy> {
+        self.proxy.as_ref()
+    }
+
+    // Returns the client for a direct connection to the URL, if the proxy of the client failed and allows falling
+    // back to a direct connection. Onion services are never reached directly.
+    #[cfg(all(feature = "socks", feature = "async"))]
+    pub(crate) fn direct_client(&self, url: &str) -> Option<&reqwest::Client> {
+        self.direct_client.as_ref().filter(|_| !is_onion(url))
+    }
+
+    // Returns the client for a direct connection to the URL, if the proxy of the client failed and allows falling
+    // back to a direct connection. Onion services are never reached directly.
+    #[cfg(all(feature = "socks", not(feature = "async")))]
+    pub(crate) fn direct_client(&self, url: &str) -> Option<&ureq::Agent> {
+        self.direct_client.as_ref().filter(|_| !is_onion(url))
+    }
+
+    // Returns an error for a connection through the proxy of the client that failed
+    #[cfg(feature = "socks")]
+    pub(crate) fn proxy_error(&self, error: impl Display) -> Option<ApiError> {
+        let proxy = self.proxy.as_ref()?;
+        let reason = format!("connection through the SOCKS proxy at '{}' failed: {error}", proxy.address());
+        Some(ApiError::Proxy { endpoint: redact_url(&self.base_url), reason })
+    }
+
+    /// Returns the custom network the client is configured for, if any.
+    pub fn custom_network(&self) -> Option<&CustomNetwork<N>> {
+        self.custom_network.as_ref()
+    }
+
+    /// Set the maximum number of blocks requested at a time.
+    ///
+    /// When the node rejects a request for exceeding its own limit, the client halves this value
+    /// and keeps the smaller size for subsequent requests. Clones of the client share the value.
+    pub fn with_max_block_request(self, max_block_request: u32) -> Self {
+        self.max_block_request.store(max_block_request.max(1), Ordering::SeqCst);
+        self
+    }
+
+    /// Returns the maximum number of blocks requested at a time.
+    pub fn max_block_request(&self) -> u32 {
+        self.max_block_request.load(Ordering::SeqCst)
+    }
+
+    // Halve the maximum number of blocks requested at a time, after a request of `rejected` blocks failed
+    pub(crate) fn reduce_max_block_request(&self, rejected: u32) {
+        self.max_block_request.fetch_min((rejected / 2).max(1), Ordering::SeqCst);
+    }
+
+    /// Set the maximum size of a response body in bytes.
+    ///
+    /// Requests whose response exceeds the size fail with [`ApiError::TooLarge`] once the limit is reached,
+    /// instead of buffering an unbounded body.
+    pub fn with_max_response_size(mut self, max_response_size: u64) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    /// Returns the maximum size of a response body in bytes.
+    pub fn max_response_size(&self) -> u64 {
+        self.max_response_size
+    }
+
+    /// Enable or disable strict mode, in which responses are checked against the identifiers they were
+    /// requested by.
+    ///
+    /// In strict mode, blocks must have the requested height or hash, and transactions and programs the
+    /// requested ID, or the request fails with [`ApiError::ResponseMismatch`]. This guards against gateways
+    /// that serve the wrong item, e.g. from a poisoned cache. The identifiers are computed and verified while
+    /// the response is parsed, so the checks cost one comparison per response.
+    pub fn with_strict_mode(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Returns `true` if responses are checked against the identifiers they were requested by.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Set the depth a block must reach before its transactions are final, by default 1, the tip.
+    ///
+    /// The depth counts the block and the blocks built on it. It is used by
+    /// [`AleoAPIClient::transaction_status`] and [`AleoAPIClient::wait_for_confirmation`], and by
+    /// [`AleoAPIClient::await_payment`] for criteria that do not set their own confirmations. A deeper
+    /// confirmation depth makes it less likely that a final transaction is orphaned by a reorganization.
+    pub fn with_confirmation_depth(mut self, confirmation_depth: u32) -> Self {
+        self.confirmation_depth = confirmation_depth.max(1);
+        self
+    }
+
+    /// Returns the depth a block must reach before its transactions are final.
+    pub fn confirmation_depth(&self) -> u32 {
+        self.confirmation_depth
+    }
+
+    /// Set whether the node serves the values of mappings after past blocks, by default `false`.
+    ///
+    /// Nodes that do are queried by [`AleoAPIClient::get_mapping_value_at_height`] with the height as a query
+    /// parameter of the mapping route. For other nodes, the value is reconstructed by replaying the blocks.
+    pub fn with_historical_mappings(mut self, historical_mappings: bool) -> Self {
+        self.historical_mappings = historical_mappings;
+        self
+    }
+
+    /// Returns `true` if the node is queried for the values of mappings after past blocks.
+    pub fn historical_mappings(&self) -> bool {
+        self.historical_mappings
+    }
+
+    /// Set the options of the pipeline that scans the ledger for records.
+    #[cfg(not(feature = "async"))]
+    pub fn with_scan_options(mut self, scan_options: ScanOptions) -> Self {
+        self.scan_options = scan_options;
+        self
+    }
+
+    /// Returns the options of the pipeline that scans the ledger for records.
+    #[cfg(not(feature = "async"))]
+    pub fn scan_options(&self) -> ScanOptions {
+        self.scan_options
+    }
+
+    /// Keep the acknowledgements of broadcast transactions for the given time, by default ten minutes.
+    ///
+    /// Re-submitting a transaction within this time returns the acknowledgement of its first broadcast without
+    /// posting it again. Clones of the client share the acknowledgements.
+    #[cfg(not(feature = "async"))]
+    pub fn with_broadcast_ttl(mut self, ttl: Duration) -> Self {
+        self.broadcast_cache = Arc::new(BroadcastCache::new(ttl, self.clock.clone()));
+        self
+    }
+
+    /// Returns the time for which the acknowledgements of broadcast transactions are kept.
+    #[cfg(not(feature = "async"))]
+    pub fn broadcast_ttl(&self) -> Duration {
+        self.broadcast_cache.ttl()
+    }
+
+    // Returns the acknowledgements of the transactions broadcast by the client and its clones
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn broadcast_cache(&self) -> &BroadcastCache<N> {
+        &self.broadcast_cache
+    }
+
+    /// Read the time from the given clock, instead of the clock of the operating system.
+    ///
+    /// The clock measures the timeouts of the client, the TTLs of its caches and its staleness threshold, and the
+    /// client sleeps on it between polls, so that tests can run through them with a [`MockClock`]. The async client
+    /// sleeps on the clock on timer threads. The caches of the client are emptied, so the clock is best set first.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        #[cfg(not(feature = "async"))]
+        {
+            self.broadcast_cache = Arc::new(BroadcastCache::new(self.broadcast_cache.ttl(), self.clock.clone()));
+            if let Some(cache) = &self.response_cache {
+                let cache = ResponseCache::new(cache.capacity(), cache.ttl(), self.clock.clone());
+                self.response_cache = Some(Arc::new(cache));
+            }
+        }
+        self
+    }
+
+    /// Returns the clock the client reads the time from.
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Set the age past which the latest block of the node is stale, by default five minutes.
+    ///
+    /// A node whose latest block is older is reported as [`NodeSyncStatus::Stale`] by
+    /// [`AleoAPIClient::node_sync_status`], as it most likely stopped receiving blocks from the network.
+    #[cfg(not(feature = "async"))]
+    pub fn with_staleness_threshold(mut self, staleness_threshold: Duration) -> Self {
+        self.staleness_threshold = staleness_threshold;
+        self
+    }
+
+    /// Returns the age past which the latest block of the node is stale.
+    #[cfg(not(feature = "async"))]
+    pub fn staleness_threshold(&self) -> Duration {
+        self.staleness_threshold
+    }
+
+    // Returns whether the node serves its sync status, or `None` until it is probed
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn sync_status_route(&self) -> &Mutex<Option<bool>> {
+        &self.sync_status_route
+    }
+
+    /// Cache the parsed responses of up to `capacity` recent requests, and revalidate them with the node instead of
+    /// downloading them again.
+    ///
+    /// Responses that never change, such as blocks by hash, transactions, and programs, are served from the cache
+    /// for as long as they are held. Responses that may change, such as the latest block, are served from the cache
+    /// for the given TTL, and then requested with the `ETag` and `Last-Modified` validators of their last response,
+    /// so that a node responding `304 Not Modified` sends no body, and the cached value is returned without being
+    /// parsed again. Clones of the client share the cache, which evicts the least recently used response when full.
+    #[cfg(not(feature = "async"))]
+    pub fn with_response_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.response_cache = Some(Arc::new(ResponseCache::new(capacity, ttl, self.clock.clone())));
+        self
+    }
+
+    // Returns the cache of parsed responses, if the client has one
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn response_cache(&self) -> Option<&ResponseCache> {
+        self.response_cache.as_deref()
+    }
+
+    /// Debit every request of the client, and every byte of its responses, from the budget.
+    ///
+    /// Once the budget is exhausted, requests fail with [`BudgetExhausted`]. Scans and walks over ranges of blocks
+    /// fail with the results gathered so far and the height to resume from, so that they can be resumed by a
+    /// client with a fresh budget. Clones of the client, and of the budget, share its consumption.
+    #[cfg(not(feature = "async"))]
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Returns the budget of the client, if it has one.
+    #[cfg(not(feature = "async"))]
+    pub fn budget(&self) -> Option<&Budget> {
+        self.budget.as_ref()
+    }
+
+    /// Debit the requests of the client from its budget with the given priority, by default
+    /// [`RequestPriority::Interactive`].
+    ///
+    /// The requests of a background client fail with [`Preempted`] while interactive work is in progress on the
+    /// budget, see [`Budget::begin_interactive`]. Clients without a budget send every request.
+    #[cfg(not(feature = "async"))]
+    pub fn with_priority(mut self, priority: RequestPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Returns the priority of the requests of the client.
+    #[cfg(not(feature = "async"))]
+    pub fn priority(&self) -> RequestPriority {
+        self.priority
+    }
+
+    /// Send the header with every request of the client, e.g. `Authorization` for providers that require an API
+    /// key.
+    #[cfg(not(feature = "async"))]
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Record every request of the client and the response of the node into the cassette of the recorder, e.g. to
+    /// attach the traffic of a failing scan to a bug report.
+    ///
+    /// Secrets are redacted from the recording, see [`CassetteRecorder`]. A client records or replays, so this
+    /// replaces a cassette set with [`AleoAPIClient::with_replay`].
+    #[cfg(not(feature = "async"))]
+    pub fn with_recorder(mut self, recorder: &CassetteRecorder) -> Self {
+        self.cassette = Some(CassetteTransport::Record(recorder.clone()));
+        self
+    }
+
+    /// Answer every request of the client from the cassette of the player, without reaching the node, to reproduce
+    /// recorded traffic offline.
+    ///
+    /// A client records or replays, so this replaces a recorder set with [`AleoAPIClient::with_recorder`].
+    #[cfg(not(feature = "async"))]
+    pub fn with_replay(mut self, player: CassettePlayer) -> Self {
+        self.cassette = Some(CassetteTransport::Replay(player));
+        self
+    }
+
+    /// Cache up to `capacity` recent blocks, so that queries of overlapping windows of recent blocks, such as
+    /// repeated polls of [`AleoAPIClient::get_recent_program_activity`], only fetch the blocks they have not seen.
+    ///
+    /// Clones of the client share the cache. Cached blocks that a reorganization replaced are fetched again.
+    pub fn with_block_cache(mut self, capacity: usize) -> Self {
+        self.block_cache = Some(Arc::new(Mutex::new(BlockCache::new(capacity))));
+        self
+    }
+
+    // Returns `true` if the client caches blocks
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn has_block_cache(&self) -> bool {
+        self.block_cache.is_some()
+    }
+
+    // Returns the cached blocks at the given heights, and the ranges of heights that are not cached
+    pub(crate) fn cached_blocks(&self, block_heights: Range<u32>) -> (BTreeMap<u32, Block<N>>, Vec<Range<u32>>) {
+        let (mut blocks, mut missing) = (BTreeMap::new(), Vec::<Range<u32>>::new());
+        let cache = self.block_cache.as_ref().map(|cache| lock(cache));
+        for height in block_heights {
+            match cache.as_ref().and_then(|cache| cache.get(height)) {
+                Some(block) => {
+                    blocks.insert(height, block.clone());
+                }
+                None => match missing.last_mut() {
+                    Some(range) if range.end == height => range.end += 1,
+                    _ => missing.push(height..height + 1),
+                },
+            }
+        }
+        if cache.is_some() {
+            self.count_cache_lookups("block", true, blocks.len());
+            self.count_cache_lookups("block", false, missing.iter().map(|range| range.len()).sum());
+        }
+        (blocks, missing)
+    }
+
+    // Add the given blocks to the cache, if there is one
+    pub(crate) fn cache_blocks(&self, blocks: &[Block<N>]) {
+        if let Some(cache) = &self.block_cache {
+            let mut cache = lock(cache);
+            blocks.iter().for_each(|block| cache.insert(block.clone()));
+        }
+    }
+
+    /// Set the format of the blocks and transactions served by the node.
+    ///
+    /// By default, the format of each response is detected from its fields. Fields that newer nodes renamed are
+    /// renamed back before parsing, and responses that still cannot be parsed fail with
+    /// [`ApiError::NodeVersionMismatch`].
+    pub fn with_node_version(mut self, node_version: NodeVersion) -> Self {
+        self.node_version = Some(node_version);
+        self
+    }
+
+    /// Returns the configured format of the node, or `None` if it is detected from each response.
+    pub fn node_version(&self) -> Option<NodeVersion> {
+        self.node_version
+    }
+
+    // Parse a block or transaction served by the node, in the configured or detected format. Responses that are
+    // not JSON are returned as the inner error.
+    pub(crate) fn parse_node_json<T: NodeJson>(
+        &self,
+        response: serde_json::Result<serde_json::Value>,
+    ) -> Result<serde_json::Result<T>, ApiError> {
+        match response {
+            Ok(value) => from_node_json(value, self.node_version),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    // In strict mode, check that the identifier of a response is the requested one
+    pub(crate) fn check_identifier<T: PartialEq + Display>(
+        &self,
+        item: &str,
+        expected: T,
+        received: T,
+    ) -> Result<(), ApiError> {
+        if !self.strict || expected == received {
+            return Ok(());
+        }
+        Err(ApiError::ResponseMismatch {
+            item: item.to_string(),
+            expected: expected.to_string(),
+            received: received.to_string(),
+        })
+    }
+
+    // Start the URL of a route of the node, at the base URL and chain of the client
+    pub(crate) fn url(&self) -> Result<UrlBuilder, ApiError> {
+        // Onion services are only reachable through a proxy, and resolving them would leak them to the DNS servers.
+        #[cfg(feature = "socks")]
+        let has_proxy = self.proxy.is_some();
+        #[cfg(not(feature = "socks"))]
+        let has_proxy = false;
+        if !has_proxy && is_onion(&self.base_url) {
+            let reason = "onion services can only be reached through a SOCKS proxy".to_string();
+            return Err(ApiError::Proxy { endpoint: self.base_url.clone(), reason });
+        }
+        UrlBuilder::new(&self.base_url, &self.chain)
+    }
+
+    // Join the path of a raw query to the base URL and chain, rejecting absolute URLs and paths that would leave
+    // the chain with `..` segments, even when percent-encoded
+    pub(crate) fn query_url(&self, path: &str) -> Result<String, ApiError> {
+        Ok(self.url()?.path(path)?.build())
+    }
+
+    // Check a fetched block against the custom network, if the client is configured for one, and return `true`
+    // if the genesis block of the node is yet to be checked
+    pub(crate) fn check_network(&self, block: &Block<N>) -> Result<bool, ApiError> {
+        let Some(network) = &self.custom_network else {
+            return Ok(false);
+        };
+        network.check_block(block)?;
+        if block.height() == 0 {
+            self.genesis_verified.store(true, Ordering::SeqCst);
+        }
+        Ok(!self.genesis_verified.load(Ordering::SeqCst))
+    }
+
+    // Check the genesis block served by the node against the custom network of the client
+    pub(crate) fn check_genesis(&self, genesis: &Block<N>) -> Result<(), ApiError> {
+        if genesis.height() != 0 {
+            return Err(ApiError::ResponseMismatch {
+                item: "block height".to_string(),
+                expected: "0".to_string(),
+                received: genesis.height().to_string(),
+            });
+        }
+        self.check_network(genesis).map(|_| ())
+    }
+
+    /// Returns the base URL of the node this client is connected to.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Returns the name of the chain this client queries.
+    pub fn chain(&self) -> &str {
+        &self.chain
     }
 }
 
@@ -56,3 +763,46 @@ impl Default for AleoAPIClient<Testnet3> {
 pub fn testnet3(base_url: &str) -> AleoAPIClient<Testnet3> {
     AleoAPIClient::new(base_url, "testnet3")
 }
+
+// Convert a range of block heights to a half-open range, so that `10..20` and `10..=19` are equivalent.
+// Ranges without an end are rejected, as the client does not know the height of the chain. The public API takes
+// ranges of `BlockHeight`, while its internals walk over ranges of `u32`.
+pub(crate) fn to_height_range<H: Copy + Into<u32>>(block_heights: impl RangeBounds<H>) -> Result<Range<u32>> {
+    let start = match block_heights.start_bound() {
+        Bound::Included(start) => Some((*start).into()),
+        Bound::Excluded(start) => (*start).into().checked_add(1),
+        Bound::Unbounded => Some(0),
+    };
+    let end = match block_heights.end_bound() {
+        Bound::Included(end) => (*end).into().checked_add(1),
+        Bound::Excluded(end) => Some((*end).into()),
+        Bound::Unbounded => bail!("The range of block heights must have an end"),
+    };
+    match (start, end) {
+        (Some(start), Some(end)) => Ok(start..end),
+        _ => bail!("The range of block heights exceeds the maximum block height"),
+    }
+}
+
+// Returns `true` if each of the consecutive blocks builds on the one before it
+pub(crate) fn is_linked<N: Network>(blocks: &[Block<N>]) -> bool {
+    ChunkLinks::of(blocks).check().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BlockHeight;
+
+    #[test]
+    fn test_to_height_range() {
+        assert_eq!(to_height_range(10u32..20).unwrap(), 10..20);
+        assert_eq!(to_height_range(BlockHeight(10)..=BlockHeight(19)).unwrap(), 10..20);
+        assert_eq!(to_height_range(10u32..=19).unwrap(), 10..20);
+        assert_eq!(to_height_range(..5u32).unwrap(), 0..5);
+        assert_eq!(to_height_range((Bound::Excluded(9u32), Bound::Included(9))).unwrap(), 10..10);
+        assert_eq!(to_height_range(10u32..).unwrap_err().to_string(), "The range of block heights must have an end");
+        let error = to_height_range(0..=u32::MAX).unwrap_err();
+        assert_eq!(error.to_string(), "The range of block heights exceeds the maximum block height");
+    }
+}
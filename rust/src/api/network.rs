@@ -0,0 +1,114 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ApiError;
+
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Block;
+
+/// The parameters of a private or custom chain, supplied at runtime to [`crate::AleoAPIClient::custom`]
+///
+/// snarkVM fixes the cryptographic parameters of a network in the `N: Network` type, so a custom chain is used
+/// with the type whose parameters it shares, e.g. `Testnet3`. What distinguishes chains of the same type is
+/// checked at runtime instead: every block the client fetches must carry the configured network ID, and the
+/// genesis block of the node must have the configured hash, or the request fails with
+/// [`ApiError::WrongNetwork`].
+///
+/// The REST path of the chain is used for the queries of the client. snarkVM resolves the state roots and paths
+/// needed for proving on the `testnet3` routes of the node, whatever the chain, so nodes of custom chains used to
+/// build transactions must serve those routes too.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CustomNetwork<N: Network> {
+    chain: String,
+    network_id: u16,
+    genesis_hash: N::BlockHash,
+}
+
+impl<N: Network> CustomNetwork<N> {
+    /// Configure a chain served under the given REST path, with the given network ID and genesis block hash
+    pub fn new(chain: &str, network_id: u16, genesis_hash: N::BlockHash) -> Self {
+        Self { chain: chain.to_string(), network_id, genesis_hash }
+    }
+
+    /// Returns the REST path of the chain.
+    pub fn chain(&self) -> &str {
+        &self.chain
+    }
+
+    /// Returns the network ID expected in the headers of the blocks of the chain.
+    pub fn network_id(&self) -> u16 {
+        self.network_id
+    }
+
+    /// Returns the hash of the genesis block of the chain.
+    pub fn genesis_hash(&self) -> N::BlockHash {
+        self.genesis_hash
+    }
+
+    // Check that the block carries the network ID of the chain, and that it has the genesis hash if it is the
+    // genesis block
+    pub(crate) fn check_block(&self, block: &Block<N>) -> Result<(), ApiError> {
+        if block.network() != self.network_id {
+            return Err(ApiError::WrongNetwork {
+                item: "network ID".to_string(),
+                expected: self.network_id.to_string(),
+                received: block.network().to_string(),
+            });
+        }
+        if block.height() == 0 && block.hash() != self.genesis_hash {
+            return Err(ApiError::WrongNetwork {
+                item: "genesis hash".to_string(),
+                expected: self.genesis_hash.to_string(),
+                received: block.hash().to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, sample_block, CurrentNetwork};
+
+    use snarkvm_console::prelude::TestRng;
+
+    type N = CurrentNetwork;
+
+    #[test]
+    fn test_custom_network_check_block() {
+        let rng = &mut TestRng::default();
+        let genesis = genesis_block();
+        let block = sample_block(1, genesis.hash(), rng);
+
+        let network = CustomNetwork::<N>::new("private", N::ID, genesis.hash());
+        assert!(network.check_block(&genesis).is_ok());
+        assert!(network.check_block(&block).is_ok());
+
+        // Only the genesis block is checked against the genesis hash.
+        let other = CustomNetwork::<N>::new("private", N::ID, block.hash());
+        let error = other.check_block(&genesis).unwrap_err();
+        assert!(matches!(error, ApiError::WrongNetwork { item, .. } if item == "genesis hash"));
+        assert!(other.check_block(&block).is_ok());
+
+        let other = CustomNetwork::<N>::new("private", N::ID + 1, genesis.hash());
+        assert_eq!(other.check_block(&block).unwrap_err(), ApiError::WrongNetwork {
+            item: "network ID".to_string(),
+            expected: (N::ID + 1).to_string(),
+            received: N::ID.to_string(),
+        });
+    }
+}
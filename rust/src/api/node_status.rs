@@ -0,0 +1,232 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    api::{is_not_found, unix_time},
+    mutex::lock,
+    redact_url,
+    AleoAPIClient,
+    ApiError,
+};
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use snarkvm_console::program::Network;
+use std::{error::Error, fmt, time::Duration};
+
+/// Whether a node is synced with the network, as classified by [`AleoAPIClient::node_sync_status`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NodeSyncStatus {
+    /// The node is synced, and its latest height is the latest height of the network
+    Synced,
+    /// The node reports that it is still syncing, the given number of blocks behind its peers
+    Syncing { behind_blocks: u32 },
+    /// The latest block of the node is older than the staleness threshold of the client
+    Stale { last_block_age: Duration },
+}
+
+impl NodeSyncStatus {
+    /// Returns `true` if the node is synced, so that its latest height may be followed.
+    pub fn is_synced(&self) -> bool {
+        *self == Self::Synced
+    }
+}
+
+impl fmt::Display for NodeSyncStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Synced => write!(f, "synced"),
+            Self::Syncing { behind_blocks } => write!(f, "syncing, {behind_blocks} blocks behind its peers"),
+            Self::Stale { last_block_age } => write!(f, "stale, its latest block is {}s old", last_block_age.as_secs()),
+        }
+    }
+}
+
+/// The error returned when the latest height of a node that is not synced would be followed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeNotSynced {
+    base_url: String,
+    status: NodeSyncStatus,
+}
+
+impl NodeNotSynced {
+    pub(crate) fn new(base_url: &str, status: NodeSyncStatus) -> Self {
+        Self { base_url: redact_url(base_url), status }
+    }
+
+    /// Returns the base URL of the node, with its credentials redacted.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Returns the status of the node.
+    pub fn status(&self) -> NodeSyncStatus {
+        self.status
+    }
+}
+
+impl fmt::Display for NodeNotSynced {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Node {} is not synced: it is {}", self.base_url, self.status)
+    }
+}
+
+impl Error for NodeNotSynced {}
+
+// The sync status served by nodes that expose it
+#[derive(Deserialize)]
+struct SyncStatusResponse {
+    // The height of the latest block of the node
+    ledger_height: u32,
+    // The greatest latest height of the peers of the node
+    network_height: u32,
+}
+
+impl<N: Network> AleoAPIClient<N> {
+    /// Returns whether the node is synced with the network.
+    ///
+    /// A node that serves its sync status is asked for it, and is [`NodeSyncStatus::Syncing`] while its peers are
+    /// ahead of it. Whether a node serves it is probed with the first call, and a node answering `404 Not Found` is
+    /// not asked again by the client or its clones. A node that is not syncing is [`NodeSyncStatus::Stale`] once
+    /// the timestamp of its latest block is older than [`AleoAPIClient::staleness_threshold`], as a node that stopped
+    /// receiving blocks keeps returning the same latest height.
+    ///
+    /// A node that is not synced still serves the blocks it holds, so it remains usable for historical queries.
+    pub fn node_sync_status(&self) -> Result<NodeSyncStatus> {
+        if let Some(behind_blocks) = self.blocks_behind_peers()? {
+            if behind_blocks > 0 {
+                return Ok(NodeSyncStatus::Syncing { behind_blocks });
+            }
+        }
+        let timestamp = self.latest_block_metadata()?.timestamp;
+        let now = unix_time(self.clock()) as i64;
+        // A block from the future, as timestamps are set by the producer, is as recent as it gets.
+        let last_block_age = Duration::from_secs(now.saturating_sub(timestamp).max(0) as u64);
+        match last_block_age > self.staleness_threshold() {
+            true => Ok(NodeSyncStatus::Stale { last_block_age }),
+            false => Ok(NodeSyncStatus::Synced),
+        }
+    }
+
+    // Returns the number of blocks the node is behind its peers, or `None` if it does not serve its sync status
+    fn blocks_behind_peers(&self) -> Result<Option<u32>> {
+        if *lock(self.sync_status_route()) == Some(false) {
+            return Ok(None);
+        }
+        let url = self.url()?.route("sync/status").build();
+        let response = match self.get_json::<SyncStatusResponse>(&url) {
+            Err(error) if is_not_found(&error) => {
+                *lock(self.sync_status_route()) = Some(false);
+                return Ok(None);
+            }
+            response => response?,
+        };
+        let status = match response {
+            Ok(status) => status,
+            Err(error) => bail!(ApiError::parse("the sync status", error)),
+        };
+        *lock(self.sync_status_route()) = Some(true);
+        Ok(Some(status.network_height.saturating_sub(status.ledger_height)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{genesis_block, MockResponse, MockServer},
+        testnet3,
+        MockClock,
+    };
+
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    // Start a mock node serving the genesis block as its latest block, and the given sync status, if any, counting
+    // the requests for it
+    fn mock_status_node(sync_status: Option<(u32, u32)>, probes: Arc<AtomicUsize>) -> MockServer {
+        let latest_block = genesis_block().to_string();
+        MockServer::start(move |request| match request.path.as_str() {
+            "/testnet3/latest/block" => Some(MockResponse::json(latest_block.clone())),
+            "/testnet3/sync/status" => {
+                probes.fetch_add(1, Ordering::SeqCst);
+                Some(match sync_status {
+                    Some((ledger_height, network_height)) => MockResponse::json(serde_json::json!({
+                        "ledger_height": ledger_height,
+                        "network_height": network_height,
+                    })),
+                    None => MockResponse::text(404, "Not Found"),
+                })
+            }
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_node_sync_status() {
+        // A node serving its sync status is syncing while its peers are ahead of it.
+        let probes = Arc::new(AtomicUsize::new(0));
+        let server = mock_status_node(Some((3, 10)), probes.clone());
+        let client = testnet3(server.base_url());
+        let status = client.node_sync_status().unwrap();
+        assert_eq!(status, NodeSyncStatus::Syncing { behind_blocks: 7 });
+        assert!(!status.is_synced());
+        assert_eq!(status.to_string(), "syncing, 7 blocks behind its peers");
+
+        // Once caught up, the node is synced as long as its latest block is recent enough.
+        let server = mock_status_node(Some((10, 10)), probes.clone());
+        let client = testnet3(server.base_url()).with_staleness_threshold(Duration::MAX);
+        assert_eq!(client.node_sync_status().unwrap(), NodeSyncStatus::Synced);
+
+        // A node whose latest block is older than the threshold is stale.
+        let clock = MockClock::new().with_unix_time(genesis_block().timestamp() as u64);
+        let client =
+            testnet3(server.base_url()).with_clock(clock.clone()).with_staleness_threshold(Duration::from_secs(60));
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(client.node_sync_status().unwrap(), NodeSyncStatus::Synced);
+        clock.advance(Duration::from_secs(1));
+        let NodeSyncStatus::Stale { last_block_age } = client.node_sync_status().unwrap() else {
+            panic!("The genesis block should be stale");
+        };
+        assert_eq!(last_block_age, Duration::from_secs(61));
+        let error = NodeNotSynced::new(client.base_url(), NodeSyncStatus::Stale { last_block_age });
+        assert!(error.to_string().starts_with(&format!("Node {} is not synced: it is stale", server.base_url())));
+    }
+
+    #[test]
+    fn test_node_sync_status_probe() {
+        // A node that does not serve its sync status is only classified by the age of its latest block, and is not
+        // asked for its sync status again by the client or its clones.
+        let probes = Arc::new(AtomicUsize::new(0));
+        let server = mock_status_node(None, probes.clone());
+        let client = testnet3(server.base_url()).with_staleness_threshold(Duration::from_secs(60));
+        assert!(matches!(client.node_sync_status().unwrap(), NodeSyncStatus::Stale { .. }));
+        let client = client.clone().with_staleness_threshold(Duration::MAX);
+        assert_eq!(client.node_sync_status().unwrap(), NodeSyncStatus::Synced);
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+
+        // A node that serves it is asked each time.
+        let probes = Arc::new(AtomicUsize::new(0));
+        let server = mock_status_node(Some((5, 5)), probes.clone());
+        let client = testnet3(server.base_url()).with_staleness_threshold(Duration::MAX);
+        for _ in 0..2 {
+            assert_eq!(client.node_sync_status().unwrap(), NodeSyncStatus::Synced);
+        }
+        assert_eq!(probes.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{api::compat::from_node_json, AleoAPIClient, ApiError, NodeVersion};
+
+use anyhow::Result;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Transaction;
+use std::{fmt, marker::PhantomData};
+
+/// The pages of the memory pool of a node, fetched lazily as the iterator is advanced
+///
+/// Each page is requested with the cursor of the previous one. A node that does not paginate serves its whole
+/// memory pool as the first page, which is then the only one.
+pub struct MemoryPoolPages<'a, N: Network> {
+    api_client: &'a AleoAPIClient<N>,
+    limit: u32,
+    cursor: Option<String>,
+    done: bool,
+}
+
+impl<'a, N: Network> MemoryPoolPages<'a, N> {
+    pub(crate) fn new(api_client: &'a AleoAPIClient<N>, limit: u32) -> Self {
+        Self { api_client, limit, cursor: None, done: false }
+    }
+}
+
+impl<N: Network> Iterator for MemoryPoolPages<'_, N> {
+    type Item = Result<Vec<Transaction<N>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.api_client.get_memory_pool_transactions_page(self.cursor.take(), self.limit) {
+            Ok((transactions, cursor)) => {
+                self.done = cursor.is_none();
+                self.cursor = cursor;
+                Some(Ok(transactions))
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Deserializes a page of the memory pool into the cursor of the next page, passing each transaction to a
+/// callback as soon as it is parsed
+///
+/// A node that paginates serves an object holding the `transactions` of the page and the `cursor` of the next
+/// one. Other nodes ignore the pagination parameters and serve an array of every transaction, which is read the
+/// same way. Once the callback returns `false`, the deserialization stops without reading the rest of the body.
+pub(crate) struct MemoryPoolSeed<'a, N: Network, F: FnMut(Transaction<N>) -> bool> {
+    callback: &'a mut F,
+    version: Option<NodeVersion>,
+    mismatch: Option<ApiError>,
+    stopped: bool,
+    _network: PhantomData<N>,
+}
+
+impl<'a, N: Network, F: FnMut(Transaction<N>) -> bool> MemoryPoolSeed<'a, N, F> {
+    pub(crate) fn new(callback: &'a mut F, version: Option<NodeVersion>) -> Self {
+        Self { callback, version, mismatch: None, stopped: false, _network: PhantomData }
+    }
+
+    /// Returns the error of a transaction in the format of another node version, if the deserialization stopped at
+    /// one
+    pub(crate) fn take_mismatch(&mut self) -> Option<ApiError> {
+        self.mismatch.take()
+    }
+
+    /// Returns `true` if the deserialization stopped because the callback returned `false`
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+}
+
+impl<'de, N: Network, F: FnMut(Transaction<N>) -> bool> DeserializeSeed<'de> for &mut MemoryPoolSeed<'_, N, F> {
+    type Value = Option<String>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, N: Network, F: FnMut(Transaction<N>) -> bool> Visitor<'de> for &mut MemoryPoolSeed<'_, N, F> {
+    type Value = Option<String>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a page of transactions or an array of transactions")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut transactions, mut cursor) = (None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "transactions" => transactions = Some(map.next_value_seed(TransactionsSeed(&mut *self))?),
+                "cursor" => cursor = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        transactions.ok_or_else(|| de::Error::missing_field("transactions"))?;
+        Ok(cursor)
+    }
+
+    // Nodes that do not paginate serve every transaction, with no next page.
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        TransactionsSeed(self).visit_seq(seq)?;
+        Ok(None)
+    }
+}
+
+// Deserializes the array of transactions of a page, passing each transaction to the callback of the page seed
+struct TransactionsSeed<'a, 'b, N: Network, F: FnMut(Transaction<N>) -> bool>(&'a mut MemoryPoolSeed<'b, N, F>);
+
+impl<'de, N: Network, F: FnMut(Transaction<N>) -> bool> DeserializeSeed<'de> for TransactionsSeed<'_, '_, N, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, N: Network, F: FnMut(Transaction<N>) -> bool> Visitor<'de> for TransactionsSeed<'_, '_, N, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of transactions")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let seed = self.0;
+        while let Some(value) = seq.next_element::<serde_json::Value>()? {
+            match from_node_json::<Transaction<N>>(value, seed.version) {
+                Ok(Ok(transaction)) => {
+                    if !(seed.callback)(transaction) {
+                        // The rest of the body is left unread, which fails the deserialization.
+                        seed.stopped = true;
+                        return Err(de::Error::custom("stopped by the callback"));
+                    }
+                }
+                Ok(Err(error)) => return Err(de::Error::custom(error)),
+                Err(mismatch) => {
+                    let error = de::Error::custom(&mismatch);
+                    seed.mismatch = Some(mismatch);
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, CurrentNetwork};
+
+    type N = CurrentNetwork;
+
+    // Read a body with a seed whose callback keeps transactions until it holds `stop_after` of them
+    fn read(body: &str, stop_after: usize) -> (serde_json::Result<Option<String>>, Vec<Transaction<N>>, bool) {
+        let mut transactions = vec![];
+        let mut callback = |transaction| {
+            transactions.push(transaction);
+            transactions.len() < stop_after
+        };
+        let mut seed = MemoryPoolSeed::new(&mut callback, None);
+        let result = (&mut seed).deserialize(&mut serde_json::Deserializer::from_str(body));
+        let stopped = seed.is_stopped();
+        (result, transactions, stopped)
+    }
+
+    #[test]
+    fn test_memory_pool_seed() {
+        let transaction = genesis_block().transactions().iter().next().unwrap().clone();
+
+        // Pages and plain arrays are both read.
+        let body = format!(r#"{{"cursor":"abc","transactions":[{transaction},{transaction}],"total":9}}"#);
+        let (cursor, transactions, stopped) = read(&body, usize::MAX);
+        assert_eq!(cursor.unwrap(), Some("abc".to_string()));
+        assert_eq!((transactions.len(), stopped), (2, false));
+        let body = format!(r#"{{"transactions":[{transaction}],"cursor":null}}"#);
+        let (cursor, transactions, _) = read(&body, usize::MAX);
+        assert_eq!((cursor.unwrap(), transactions.len()), (None, 1));
+        let (cursor, transactions, _) = read(&format!("[{transaction},{transaction},{transaction}]"), usize::MAX);
+        assert_eq!((cursor.unwrap(), transactions.len()), (None, 3));
+
+        // The callback stops the deserialization.
+        let (cursor, transactions, stopped) = read(&format!("[{transaction},{transaction},{transaction}]"), 1);
+        assert!(cursor.is_err());
+        assert_eq!((transactions, stopped), (vec![transaction], true));
+
+        // Pages without transactions are malformed.
+        let (cursor, _, stopped) = read(r#"{"cursor":"abc"}"#, usize::MAX);
+        assert!(cursor.unwrap_err().to_string().starts_with("missing field `transactions`"));
+        assert!(!stopped);
+    }
+}
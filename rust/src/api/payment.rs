@@ -0,0 +1,357 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+#[cfg(feature = "async")]
+use crate::{mutex::lock, Clock};
+
+use snarkvm_console::{
+    account::{Address, ViewKey},
+    program::{Entry, Identifier, Literal, Network, Plaintext, Record},
+    types::Field,
+};
+use snarkvm_synthesizer::{Block, Input};
+
+use std::{collections::BTreeMap, error::Error, fmt, time::Duration};
+#[cfg(feature = "async")]
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+/// The payment awaited by [`crate::AleoAPIClient::await_payment`], and how it is watched for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentCriteria<N: Network> {
+    min_amount: u64,
+    sender: Option<Address<N>>,
+    data: Vec<(Identifier<N>, Plaintext<N>)>,
+    confirmations: Option<u32>,
+    start_height: Option<u32>,
+    poll_interval: Duration,
+}
+
+impl<N: Network> PaymentCriteria<N> {
+    /// Await a record of at least `min_amount` gates.
+    ///
+    /// By default the payment is accepted once the block holding it reaches the confirmation depth of the
+    /// client, and only blocks added after the call are watched.
+    pub fn new(min_amount: u64) -> Self {
+        Self {
+            min_amount,
+            sender: None,
+            data: vec![],
+            confirmations: None,
+            start_height: None,
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+
+    /// Only accept payments from the given address.
+    ///
+    /// Records do not reveal who created them, so the sender is only known when the transition creating the
+    /// record takes it as a public or constant address input. Private `credits.aleo` transfers never match
+    /// criteria with a sender.
+    pub fn with_sender(mut self, sender: Address<N>) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// Only accept records whose data holds the given entry, e.g. the memo of an invoice.
+    pub fn with_data(mut self, name: Identifier<N>, value: Plaintext<N>) -> Self {
+        self.data.push((name, value));
+        self
+    }
+
+    /// Set the number of blocks, counting the block holding the payment, that must be on the chain before the
+    /// payment is accepted, instead of the confirmation depth of the client. A payment whose block is orphaned
+    /// before then is dropped, and awaited again.
+    pub fn with_confirmations(mut self, confirmations: u32) -> Self {
+        self.confirmations = Some(confirmations.max(1));
+        self
+    }
+
+    /// Watch the blocks from the given height, e.g. to catch a payment made before the call.
+    pub fn with_start_height(mut self, start_height: u32) -> Self {
+        self.start_height = Some(start_height);
+        self
+    }
+
+    /// Set the interval at which the node is polled for new blocks.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Returns the minimum amount of the payment, in gates.
+    pub fn min_amount(&self) -> u64 {
+        self.min_amount
+    }
+
+    /// Returns the expected sender of the payment, if any.
+    pub fn sender(&self) -> Option<&Address<N>> {
+        self.sender.as_ref()
+    }
+
+    /// Returns the entries the data of the record must hold.
+    pub fn data(&self) -> &[(Identifier<N>, Plaintext<N>)] {
+        &self.data
+    }
+
+    /// Returns the number of confirmations required to accept the payment, if set instead of the confirmation
+    /// depth of the client.
+    pub fn confirmations(&self) -> Option<u32> {
+        self.confirmations
+    }
+
+    /// Returns the height from which blocks are watched, if set.
+    pub fn start_height(&self) -> Option<u32> {
+        self.start_height
+    }
+
+    /// Returns the interval at which the node is polled for new blocks.
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    // Returns `true` if the record, created by a transition with the given inputs, is a matching payment
+    fn matches(&self, record: &Record<N, Plaintext<N>>, inputs: &[Input<N>]) -> bool {
+        let sender_matches = self.sender.map_or(true, |sender| {
+            inputs.iter().any(|input| match input {
+                Input::Constant(_, Some(Plaintext::Literal(Literal::Address(address), _)))
+                | Input::Public(_, Some(Plaintext::Literal(Literal::Address(address), _))) => *address == sender,
+                _ => false,
+            })
+        });
+        let data_matches = self.data.iter().all(|(name, value)| match record.data().get(name) {
+            Some(Entry::Constant(plaintext) | Entry::Public(plaintext) | Entry::Private(plaintext)) => {
+                plaintext == value
+            }
+            None => false,
+        });
+        ***record.gates() >= self.min_amount && sender_matches && data_matches
+    }
+}
+
+/// A payment found by [`crate::AleoAPIClient::await_payment`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentEvent<N: Network> {
+    height: u32,
+    block_hash: N::BlockHash,
+    transaction_id: N::TransactionID,
+    transition_id: N::TransitionID,
+    commitment: Field<N>,
+    record: Record<N, Plaintext<N>>,
+}
+
+impl<N: Network> PaymentEvent<N> {
+    /// Returns the height of the block holding the payment.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the hash of the block holding the payment.
+    pub fn block_hash(&self) -> N::BlockHash {
+        self.block_hash
+    }
+
+    /// Returns the ID of the transaction of the payment.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the ID of the transition that created the record.
+    pub fn transition_id(&self) -> N::TransitionID {
+        self.transition_id
+    }
+
+    /// Returns the commitment of the record.
+    pub fn commitment(&self) -> &Field<N> {
+        &self.commitment
+    }
+
+    /// Returns the decrypted record.
+    pub fn record(&self) -> &Record<N, Plaintext<N>> {
+        &self.record
+    }
+
+    /// Returns the amount of the payment, in gates.
+    pub fn amount(&self) -> u64 {
+        ***self.record.gates()
+    }
+}
+
+/// The error returned when no payment was confirmed before the timeout
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PaymentTimeout {
+    timeout: Duration,
+}
+
+impl PaymentTimeout {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+
+    /// Returns the timeout the wait exceeded.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl fmt::Display for PaymentTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No payment was confirmed within {:?}", self.timeout)
+    }
+}
+
+impl Error for PaymentTimeout {}
+
+/// Follows the chain block by block for a payment, shared by the blocking and async clients
+pub(crate) struct PaymentWatcher<N: Network> {
+    view_key: ViewKey<N>,
+    address_x_coordinate: Field<N>,
+    criteria: PaymentCriteria<N>,
+    start_height: u32,
+    confirmations: u32,
+    // The hashes of the watched blocks, by height
+    hashes: BTreeMap<u32, N::BlockHash>,
+    // The first matching payment, which is accepted once confirmed
+    payment: Option<PaymentEvent<N>>,
+}
+
+impl<N: Network> PaymentWatcher<N> {
+    /// Watch for a payment from the start height, which is accepted with the confirmations of the criteria, or
+    /// else the given confirmation depth.
+    pub(crate) fn new(
+        view_key: ViewKey<N>,
+        criteria: PaymentCriteria<N>,
+        start_height: u32,
+        confirmation_depth: u32,
+    ) -> Self {
+        let address_x_coordinate = view_key.to_address().to_x_coordinate();
+        let confirmations = criteria.confirmations.unwrap_or(confirmation_depth);
+        Self {
+            view_key,
+            address_x_coordinate,
+            criteria,
+            start_height,
+            confirmations,
+            hashes: BTreeMap::new(),
+            payment: None,
+        }
+    }
+
+    /// Returns the height of the next block to watch.
+    pub(crate) fn next_height(&self) -> u32 {
+        self.hashes.keys().next_back().map_or(self.start_height, |height| height + 1)
+    }
+
+    /// Returns the poll interval of the criteria.
+    pub(crate) fn poll_interval(&self) -> Duration {
+        self.criteria.poll_interval
+    }
+
+    /// Watch the block at the next height, returning the payment once it is confirmed.
+    ///
+    /// If the block does not build on the last watched block, the chain was reorganized, so the last watched
+    /// block is forgotten and watched again at its new hash. A payment in a forgotten block is dropped.
+    pub(crate) fn watch(&mut self, block: &Block<N>) -> Option<PaymentEvent<N>> {
+        let height = block.height();
+        if let Some(hash) = height.checked_sub(1).and_then(|previous| self.hashes.get(&previous)) {
+            if *hash != block.previous_hash() {
+                self.hashes.remove(&(height - 1));
+                if self.payment.as_ref().map_or(false, |payment| payment.height >= height - 1) {
+                    self.payment = None;
+                }
+                return None;
+            }
+        }
+        self.hashes.insert(height, block.hash());
+
+        if self.payment.is_none() {
+            self.payment = self.find_payment(block);
+        }
+        let payment = self.payment.as_ref()?;
+        (height - payment.height + 1 >= self.confirmations).then(|| payment.clone())
+    }
+
+    // Returns the first payment in the block that matches the criteria
+    fn find_payment(&self, block: &Block<N>) -> Option<PaymentEvent<N>> {
+        block.transactions().iter().find_map(|transaction| {
+            transaction.transitions().find_map(|transition| {
+                transition.records().find_map(|(commitment, record)| {
+                    if !record.is_owner_with_address_x_coordinate(&self.view_key, &self.address_x_coordinate) {
+                        return None;
+                    }
+                    let record = record.decrypt(&self.view_key).ok()?;
+                    self.criteria.matches(&record, transition.inputs()).then(|| PaymentEvent {
+                        height: block.height(),
+                        block_hash: block.hash(),
+                        transaction_id: transaction.id(),
+                        transition_id: *transition.id(),
+                        commitment: *commitment,
+                        record,
+                    })
+                })
+            })
+        })
+    }
+}
+
+/// Waits for the given duration of the clock without blocking the executor, on a timer thread, as the async client
+/// does not depend on a runtime
+#[cfg(feature = "async")]
+pub(crate) async fn sleep(clock: Arc<dyn Clock>, duration: Duration) {
+    Sleep::new(clock, duration).await
+}
+
+// A future that completes once its timer thread has slept on the clock for the duration
+#[cfg(feature = "async")]
+struct Sleep {
+    state: Arc<Mutex<(bool, Option<Waker>)>>,
+}
+
+#[cfg(feature = "async")]
+impl Sleep {
+    fn new(clock: Arc<dyn Clock>, duration: Duration) -> Self {
+        let state = Arc::new(Mutex::new((false, None::<Waker>)));
+        let timer = state.clone();
+        thread::spawn(move || {
+            clock.sleep(duration);
+            let mut state = lock(&timer);
+            state.0 = true;
+            if let Some(waker) = state.1.take() {
+                waker.wake();
+            }
+        });
+        Self { state }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = lock(&self.state);
+        if state.0 {
+            return Poll::Ready(());
+        }
+        state.1 = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
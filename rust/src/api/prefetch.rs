@@ -0,0 +1,421 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::is_linked;
+use crate::{
+    ids::{from_heights, heights},
+    mutex::lock,
+    AleoAPIClient,
+    BlockHeight,
+    ScanPlan,
+    ScanStrategy,
+};
+
+use anyhow::{bail, Result};
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Block;
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// How far a [`BlockPrefetcher`] reads ahead of its consumer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PrefetchOptions {
+    /// The number of chunks that may wait for the consumer, past which the prefetcher backs off
+    pub read_ahead_chunks: usize,
+    /// The interval at which a prefetcher that backed off checks again whether the consumer caught up, in case the
+    /// consumer does not read the chunks it waits with
+    pub backoff: Duration,
+}
+
+impl Default for PrefetchOptions {
+    fn default() -> Self {
+        Self { read_ahead_chunks: 4, backoff: Duration::from_millis(50) }
+    }
+}
+
+/// The statistics of the blocks read through a [`BlockPrefetcher`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanReport {
+    /// The chunks fetched ahead of the consumer
+    pub prefetched_chunks: u64,
+    /// The chunks that failed to be fetched ahead, which the consumer then fetched itself
+    pub failed_chunks: u64,
+    /// The blocks the consumer read from the block cache, warmed ahead of it
+    pub warm_hits: u64,
+    /// The blocks the consumer fetched itself, as they were not in the block cache
+    pub cold_blocks: u64,
+    /// The number of times the prefetcher backed off, as the consumer fell `read_ahead_chunks` behind
+    pub backoffs: u64,
+    /// The most chunks that waited for the consumer at once
+    pub max_read_ahead: usize,
+}
+
+impl ScanReport {
+    /// Returns the share of the blocks read by the consumer that were warmed ahead of it, or zero if it read none.
+    pub fn hit_rate(&self) -> f64 {
+        match self.warm_hits + self.cold_blocks {
+            0 => 0.0,
+            blocks => self.warm_hits as f64 / blocks as f64,
+        }
+    }
+}
+
+// The state shared by a prefetcher and its thread
+#[derive(Default)]
+struct PrefetchState {
+    // The chunks to fetch, in order
+    pending: VecDeque<Range<u32>>,
+    // The chunk being fetched
+    fetching: Option<Range<u32>>,
+    // The chunks fetched that the consumer has not read yet
+    ready: Vec<Range<u32>>,
+    report: ScanReport,
+    stopped: bool,
+}
+
+impl PrefetchState {
+    // Returns the chunks fetched, being fetched, or to fetch
+    fn chunks(&self) -> impl '_ + Iterator<Item = &Range<u32>> {
+        self.pending.iter().chain(&self.fetching).chain(&self.ready)
+    }
+}
+
+// Returns `true` if the ranges share a height
+fn overlaps(first: &Range<u32>, second: &Range<u32>) -> bool {
+    first.start < second.end && second.start < first.end
+}
+
+/// A prefetcher warming the block cache of a blocking client with the chunks of blocks a scan will read next, on a
+/// background thread
+///
+/// Ranges are planned with [`BlockPrefetcher::warm`], or from a [`ScanPlan`] with [`BlockPrefetcher::warm_plan`],
+/// and split into chunks of [`AleoAPIClient::max_block_request`] blocks, which the thread fetches in order into the
+/// [`crate::BlockCache`] of the client while the consumer reads earlier chunks with
+/// [`BlockPrefetcher::get_block_range`]. Once `read_ahead_chunks` chunks wait for the consumer, the thread backs off
+/// until the consumer reads one.
+///
+/// Each chunk is fetched once, by the thread or by the consumer: a consumer reading a chunk being fetched waits for
+/// it, and fetches the chunks not fetched yet itself. A scan through the prefetcher thus sends the same requests as
+/// one without it, ahead of time. The requests are those of the client, which counts them against its
+/// [`crate::Budget`] and retries them as it retries its own. A chunk that fails to be fetched ahead is fetched by the
+/// consumer, which then gets the error.
+///
+/// A client without a block cache is given one holding the chunks that may be read ahead and the chunk being read.
+/// A client with a smaller cache evicts chunks before they are read, which are then fetched again. Dropping the
+/// prefetcher stops the thread and waits for it, which returns once its request in flight, if any, does.
+pub struct BlockPrefetcher<N: Network> {
+    api_client: AleoAPIClient<N>,
+    options: PrefetchOptions,
+    state: Arc<(Mutex<PrefetchState>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<N: Network> BlockPrefetcher<N> {
+    /// Start a prefetcher warming the block cache of the client, which plans no range until it is asked to warm one.
+    pub fn start(api_client: AleoAPIClient<N>, options: PrefetchOptions) -> Result<Self> {
+        if options.read_ahead_chunks == 0 {
+            bail!("A prefetcher must read at least one chunk ahead");
+        }
+        let api_client = match api_client.has_block_cache() {
+            true => api_client,
+            false => {
+                let capacity = (options.read_ahead_chunks + 1) * api_client.max_block_request() as usize;
+                api_client.with_block_cache(capacity)
+            }
+        };
+        let state = Arc::new((Mutex::new(PrefetchState::default()), Condvar::new()));
+        let (fetching, shared) = (api_client.clone(), state.clone());
+        let thread = thread::Builder::new()
+            .name("aleo-block-prefetcher".to_string())
+            .spawn(move || prefetch(&fetching, options, &shared))?;
+        Ok(Self { api_client, options, state, thread: Some(thread) })
+    }
+
+    /// Plan the chunks of the given range for the thread to fetch, after the chunks planned before.
+    ///
+    /// Heights planned before are not planned again.
+    pub fn warm(&self, block_heights: Range<BlockHeight>) {
+        let block_heights = from_heights(block_heights);
+        let max_block_request = self.api_client.max_block_request().max(1);
+        let (mutex, condvar) = &*self.state;
+        let mut state = lock(mutex);
+        let mut start_height = block_heights.start;
+        while start_height < block_heights.end {
+            let mut chunk = start_height..block_heights.end.min(start_height.saturating_add(max_block_request));
+            // A chunk stops short of the next planned chunk, and starts after a planned chunk it falls in.
+            let planned = state
+                .chunks()
+                .filter(|planned| overlaps(planned, &chunk))
+                .min_by_key(|planned| planned.start)
+                .cloned();
+            if let Some(planned) = planned {
+                match planned.start > chunk.start {
+                    true => chunk.end = planned.start,
+                    false => {
+                        start_height = planned.end;
+                        continue;
+                    }
+                }
+            }
+            start_height = chunk.end;
+            state.pending.push_back(chunk);
+        }
+        condvar.notify_all();
+    }
+
+    /// Plan the range of a plan of the [`crate::ScanPlanner`], if its strategy fetches blocks.
+    pub fn warm_plan(&self, plan: &ScanPlan) {
+        if plan.strategy() != ScanStrategy::FindLookups {
+            self.warm(heights(plan.block_heights().clone()));
+        }
+    }
+
+    /// Returns the blocks at the given heights, from the block cache where they were warmed, and fetched otherwise.
+    ///
+    /// The chunks being fetched ahead are awaited, and the planned chunks the range covers are fetched by the
+    /// consumer instead. Blocks taken from the cache that do not build on each other, as a reorganization replaced
+    /// some of them, are fetched again.
+    pub fn get_block_range(&self, block_heights: Range<BlockHeight>) -> Result<Vec<Block<N>>> {
+        let block_heights = from_heights(block_heights);
+        if block_heights.start >= block_heights.end {
+            bail!("Start height must be less than end height");
+        }
+        let (mutex, condvar) = &*self.state;
+        {
+            let mut state = lock(mutex);
+            state.pending.retain(|chunk| !overlaps(chunk, &block_heights));
+            while state.fetching.as_ref().map_or(false, |chunk| overlaps(chunk, &block_heights)) {
+                state = condvar.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            // The chunks the consumer read or went past no longer wait for it, even if it skipped them.
+            state.ready.retain(|chunk| chunk.start >= block_heights.end);
+            condvar.notify_all();
+        }
+        let (mut blocks, missing) = self.api_client.cached_blocks(block_heights.clone());
+        let (mut warm_hits, mut cold_blocks) = (blocks.len() as u64, 0);
+        for range in missing {
+            let fetched = self.api_client.get_block_range(heights(range))?;
+            cold_blocks += fetched.len() as u64;
+            blocks.extend(fetched.into_iter().map(|block| (block.height(), block)));
+        }
+        let mut blocks = blocks.into_values().collect::<Vec<_>>();
+        if !is_linked(&blocks) {
+            blocks = self.api_client.get_block_range(heights(block_heights))?;
+            (warm_hits, cold_blocks) = (0, blocks.len() as u64);
+        }
+        self.api_client.cache_blocks(&blocks);
+        let mut state = lock(mutex);
+        state.report.warm_hits += warm_hits;
+        state.report.cold_blocks += cold_blocks;
+        Ok(blocks)
+    }
+
+    /// Returns the statistics of the blocks read through the prefetcher.
+    pub fn report(&self) -> ScanReport {
+        lock(&self.state.0).report
+    }
+
+    /// Returns the client whose block cache the prefetcher warms.
+    pub fn api_client(&self) -> &AleoAPIClient<N> {
+        &self.api_client
+    }
+
+    /// Returns how far the prefetcher reads ahead.
+    pub fn options(&self) -> PrefetchOptions {
+        self.options
+    }
+}
+
+// The integer overloads of the methods taking typed heights, kept for one release
+impl<N: Network> BlockPrefetcher<N> {
+    /// Plan the chunks of the given range for the thread to fetch, after the chunks planned before.
+    #[deprecated(since = "0.3.6", note = "pass a range of `BlockHeight` to `warm`; removed in the next release")]
+    pub fn warm_raw(&self, block_heights: Range<u32>) {
+        self.warm(heights(block_heights))
+    }
+
+    /// Returns the blocks at the given heights, from the block cache where they were warmed, and fetched otherwise.
+    #[deprecated(
+        since = "0.3.6",
+        note = "pass a range of `BlockHeight` to `get_block_range`; removed in the next release"
+    )]
+    pub fn get_block_range_raw(&self, block_heights: Range<u32>) -> Result<Vec<Block<N>>> {
+        self.get_block_range(heights(block_heights))
+    }
+}
+
+impl<N: Network> Drop for BlockPrefetcher<N> {
+    fn drop(&mut self) {
+        let (mutex, condvar) = &*self.state;
+        lock(mutex).stopped = true;
+        condvar.notify_all();
+        if let Some(thread) = self.thread.take() {
+            // The thread does not panic, and if it did, there would be nothing left to clean up.
+            let _ = thread.join();
+        }
+    }
+}
+
+// Fetch the planned chunks until the prefetcher stops, backing off while `read_ahead_chunks` chunks wait
+fn prefetch<N: Network>(
+    api_client: &AleoAPIClient<N>,
+    options: PrefetchOptions,
+    shared: &(Mutex<PrefetchState>, Condvar),
+) {
+    let (mutex, condvar) = shared;
+    let mut state = lock(mutex);
+    loop {
+        if state.stopped {
+            return;
+        }
+        let wait = match (state.pending.is_empty(), state.ready.len() >= options.read_ahead_chunks) {
+            (true, _) => Some(None),
+            (false, true) => Some(Some(options.backoff)),
+            (false, false) => None,
+        };
+        match wait {
+            Some(None) => state = condvar.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner()),
+            Some(Some(backoff)) => {
+                state.report.backoffs += 1;
+                state = match condvar.wait_timeout(state, backoff) {
+                    Ok((state, _)) => state,
+                    Err(poisoned) => poisoned.into_inner().0,
+                };
+            }
+            None => {
+                let chunk = state.pending.pop_front().expect("a chunk is pending");
+                state.fetching = Some(chunk.clone());
+                drop(state);
+                let fetched = api_client.get_blocks(BlockHeight(chunk.start), BlockHeight(chunk.end));
+                if let Ok(blocks) = &fetched {
+                    api_client.cache_blocks(blocks);
+                }
+                state = lock(mutex);
+                state.fetching = None;
+                match fetched {
+                    Ok(_) => {
+                        state.ready.push(chunk);
+                        state.report.prefetched_chunks += 1;
+                        state.report.max_read_ahead = state.report.max_read_ahead.max(state.ready.len());
+                    }
+                    Err(_) => state.report.failed_chunks += 1,
+                }
+                condvar.notify_all();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_chain, MockResponse, MockServer},
+        testnet3,
+    };
+
+    use std::{collections::BTreeSet, time::Instant};
+
+    type RequestLog = Arc<Mutex<Vec<(u32, u32)>>>;
+
+    // Start a mock node serving ranges of the sample chain, which records the ranges requested
+    fn mock_block_server() -> (MockServer, RequestLog) {
+        let chain = sample_chain().iter().map(ToString::to_string).collect::<Vec<_>>();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let server = MockServer::start(move |request| {
+            let (start, end) = request.path.strip_prefix("/testnet3/blocks?start=")?.split_once("&end=")?;
+            let (start, end) = (start.parse::<u32>().ok()?, end.parse::<u32>().ok()?);
+            recorded.lock().unwrap().push((start, end));
+            let blocks = chain.get(start as usize..end as usize)?;
+            Some(MockResponse::json(format!("[{}]", blocks.join(","))))
+        });
+        (server, requests)
+    }
+
+    // Wait until the prefetcher fetched the given number of chunks ahead
+    fn wait_for_chunks<N: Network>(prefetcher: &BlockPrefetcher<N>, chunks: u64) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while prefetcher.report().prefetched_chunks < chunks {
+            assert!(Instant::now() < deadline, "the prefetcher did not fetch {chunks} chunks");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_prefetcher_bounds_read_ahead_for_slow_consumer() {
+        let (server, requests) = mock_block_server();
+        let client = testnet3(server.base_url()).with_max_block_request(10);
+        let options = PrefetchOptions { read_ahead_chunks: 2, backoff: Duration::from_millis(5) };
+        let prefetcher = BlockPrefetcher::start(client, options).unwrap();
+        prefetcher.warm(heights(0..100));
+        wait_for_chunks(&prefetcher, 2);
+
+        let mut blocks = vec![];
+        for chunk in 0..10u32 {
+            // The thread gets no further than the read-ahead distance past the chunk read, and the one it fetches.
+            assert!(requests.lock().unwrap().len() <= chunk as usize + options.read_ahead_chunks + 1);
+            blocks.extend(prefetcher.get_block_range(heights(chunk * 10..(chunk + 1) * 10)).unwrap());
+            thread::sleep(Duration::from_millis(30));
+        }
+        assert_eq!(blocks, sample_chain()[..100]);
+
+        let report = prefetcher.report();
+        assert!(report.max_read_ahead <= options.read_ahead_chunks);
+        assert!(report.backoffs > 0);
+        assert_eq!(report.prefetched_chunks, 10);
+        assert_eq!((report.warm_hits, report.cold_blocks), (100, 0));
+        assert_eq!(report.hit_rate(), 1.0);
+
+        // The scan sends the same requests as one reading the range without prefetching.
+        let (plain_server, plain_requests) = mock_block_server();
+        let plain_client = testnet3(plain_server.base_url()).with_max_block_request(10);
+        assert_eq!(plain_client.get_block_range(heights(0..100)).unwrap(), blocks);
+        assert_eq!(*requests.lock().unwrap(), *plain_requests.lock().unwrap());
+    }
+
+    #[test]
+    fn test_prefetcher_fetches_each_chunk_once() {
+        let (server, requests) = mock_block_server();
+        let client = testnet3(server.base_url()).with_max_block_request(10);
+        let options = PrefetchOptions { read_ahead_chunks: 1, backoff: Duration::from_millis(5) };
+        let prefetcher = BlockPrefetcher::start(client, options).unwrap();
+
+        // A consumer reading past the planned chunks fetches those not fetched yet itself.
+        prefetcher.warm(heights(0..50));
+        prefetcher.warm(heights(20..60));
+        let blocks = prefetcher.get_block_range(heights(0..60)).unwrap();
+        assert_eq!(blocks, sample_chain()[..60]);
+
+        let requests = requests.lock().unwrap().clone();
+        let heights = requests.iter().flat_map(|(start, end)| *start..*end).collect::<Vec<_>>();
+        assert_eq!(heights.len(), 60);
+        assert_eq!(heights.iter().collect::<BTreeSet<_>>().len(), 60);
+        let report = prefetcher.report();
+        assert_eq!(report.warm_hits + report.cold_blocks, 60);
+
+        assert!(BlockPrefetcher::start(testnet3(server.base_url()), PrefetchOptions {
+            read_ahead_chunks: 0,
+            ..options
+        })
+        .is_err());
+    }
+}
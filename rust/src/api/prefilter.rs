@@ -0,0 +1,316 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Ownership checks on serialized records, for scans of blocks in the binary encoding of snarkVM.
+//!
+//! Decoding a record reads every field of its ciphertext and decompresses its nonce, while the ownership check
+//! only needs the first field of the owner and the nonce. Scans of binary blocks walk the transactions of a block
+//! in place, check the ownership of each record on its bytes, and only decode the records that pass.
+
+use crate::ScannedRecord;
+
+use snarkvm_console::{
+    account::ViewKey,
+    prelude::{FromBytes, SizeInBytes},
+    program::{Ciphertext, Identifier, Network, ProgramID, Record, Value},
+    types::{Field, Group},
+};
+use snarkvm_synthesizer::{Deployment, Header, Input, Output, Proof};
+use std::io::{Error, ErrorKind, Result};
+
+/// The variant of record outputs in the encoding of transition outputs
+const RECORD_OUTPUT: u16 = 3;
+
+/// Checks the ownership of serialized records by a view key
+pub(crate) struct RecordPrefilter<'a, N: Network> {
+    view_key: &'a ViewKey<N>,
+    address_x_coordinate: Field<N>,
+}
+
+impl<'a, N: Network> RecordPrefilter<'a, N> {
+    pub(crate) fn new(view_key: &'a ViewKey<N>) -> Self {
+        Self { view_key, address_x_coordinate: view_key.to_address().to_x_coordinate() }
+    }
+
+    // Returns `true` if the serialized record is owned by the view key, as
+    // `Record::is_owner_with_address_x_coordinate` would on the decoded record. Fails if the owner or nonce do
+    // not decode.
+    pub(crate) fn is_owner(&self, record: &[u8]) -> Result<bool> {
+        let field_size = Field::<N>::size_in_bytes();
+        let nonce = record.len().checked_sub(field_size).map(|start| &record[start..]);
+        match (record.first(), nonce) {
+            // The address of a public owner is written as its x-coordinate.
+            (Some(0), Some(_)) => Ok(Field::<N>::read_le(&record[1..])? == self.address_x_coordinate),
+            (Some(1), Some(nonce)) => {
+                let owner = Field::<N>::read_le(record.get(1 + 2..).ok_or_else(|| invalid("empty owner"))?)?;
+                let nonce = Group::<N>::from_x_coordinate(Field::read_le(nonce)?)
+                    .map_err(|error| invalid(&error.to_string()))?;
+                // Decrypt the first field of the owner, as the record does.
+                let record_view_key = (nonce * **self.view_key).to_x_coordinate();
+                let randomizer = N::hash_many_psd8(&[N::encryption_domain(), record_view_key], 1);
+                Ok(owner - randomizer[0] == self.address_x_coordinate)
+            }
+            _ => Err(invalid("record owner")),
+        }
+    }
+
+    // Walks the transactions of a serialized block, returning its height and the records owned by the view key in
+    // the order of the block, as `ScannedRecord::find_in_block` and the ownership check on the decoded block would.
+    //
+    // Unlike the decoding of a block, the walk does not recompute the hashes of the block and its transitions.
+    pub(crate) fn scan_block(&self, mut bytes: &[u8]) -> Result<(u32, Vec<ScannedRecord<N>>)> {
+        let bytes = &mut bytes;
+        expect_version(bytes, "block")?;
+        N::BlockHash::read_le(&mut *bytes)?;
+        N::BlockHash::read_le(&mut *bytes)?;
+        let header = Header::<N>::read_le(&mut *bytes)?;
+        expect_version(bytes, "transactions")?;
+        let mut records = Vec::new();
+        for _ in 0..u32::read_le(&mut *bytes)? {
+            expect_version(bytes, "transaction")?;
+            let variant = u8::read_le(&mut *bytes)?;
+            N::TransactionID::read_le(&mut *bytes)?;
+            match variant {
+                0 => {
+                    Deployment::<N>::read_le(&mut *bytes)?;
+                    self.scan_fee(bytes, &mut records)?;
+                }
+                1 => {
+                    expect_version(bytes, "execution")?;
+                    for _ in 0..u16::read_le(&mut *bytes)? {
+                        self.scan_transition(bytes, &mut records)?;
+                    }
+                    skip_inclusion::<N>(bytes)?;
+                    match u8::read_le(&mut *bytes)? {
+                        0 => (),
+                        1 => self.scan_fee(bytes, &mut records)?,
+                        _ => return Err(invalid("additional fee variant")),
+                    }
+                }
+                _ => return Err(invalid("transaction variant")),
+            }
+        }
+        Ok((header.height(), records))
+    }
+
+    // Walks a serialized fee, adding the records of its transition that are owned by the view key
+    fn scan_fee(&self, bytes: &mut &[u8], records: &mut Vec<ScannedRecord<N>>) -> Result<()> {
+        expect_version(bytes, "fee")?;
+        self.scan_transition(bytes, records)?;
+        skip_inclusion::<N>(bytes)
+    }
+
+    // Walks a serialized transition, adding its records that are owned by the view key
+    fn scan_transition(&self, bytes: &mut &[u8], records: &mut Vec<ScannedRecord<N>>) -> Result<()> {
+        expect_version(bytes, "transition")?;
+        N::TransitionID::read_le(&mut *bytes)?;
+        let program_id = ProgramID::<N>::read_le(&mut *bytes)?;
+        let function_name = Identifier::<N>::read_le(&mut *bytes)?;
+        for _ in 0..u16::read_le(&mut *bytes)? {
+            Input::<N>::read_le(&mut *bytes)?;
+        }
+        for _ in 0..u16::read_le(&mut *bytes)? {
+            // Outputs other than records are decoded as a whole, so the variant is only peeked.
+            if u16::read_le(*bytes)? != RECORD_OUTPUT {
+                Output::<N>::read_le(&mut *bytes)?;
+                continue;
+            }
+            u16::read_le(&mut *bytes)?;
+            let commitment = Field::<N>::read_le(&mut *bytes)?;
+            take(bytes, Field::<N>::size_in_bytes())?;
+            if bool::read_le(&mut *bytes)? {
+                let record = take_record::<N>(bytes)?;
+                if self.is_owner(record)? {
+                    let record = Record::<N, Ciphertext<N>>::read_le(record)?;
+                    records.push(ScannedRecord::new(commitment, record, program_id, function_name));
+                }
+            }
+        }
+        match u8::read_le(&mut *bytes)? {
+            0 => (),
+            1 => {
+                for _ in 0..u16::read_le(&mut *bytes)? {
+                    Value::<N>::read_le(&mut *bytes)?;
+                }
+            }
+            _ => return Err(invalid("finalize variant")),
+        }
+        Proof::<N>::read_le(&mut *bytes)?;
+        // Skip the transition public key and commitment, and the fee.
+        take(bytes, 2 * Field::<N>::size_in_bytes() + 8)?;
+        Ok(())
+    }
+}
+
+// Split the serialized record at the front of the bytes off them, reading only the lengths of its parts
+fn take_record<'a, N: Network>(bytes: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let field_size = Field::<N>::size_in_bytes();
+    let start = *bytes;
+    // The owner is an address or a ciphertext, and the gates a `u64` or a ciphertext.
+    for public_size in [field_size, 8] {
+        match u8::read_le(&mut *bytes)? {
+            0 => take(bytes, public_size)?,
+            1 => {
+                let num_fields = u16::read_le(&mut *bytes)? as usize;
+                take(bytes, num_fields * field_size)?
+            }
+            _ => return Err(invalid("record visibility")),
+        };
+    }
+    for _ in 0..u8::read_le(&mut *bytes)? {
+        // Skip the identifier and the entry, which are prefixed with their lengths.
+        let identifier_size = u8::read_le(&mut *bytes)? as usize;
+        take(bytes, identifier_size)?;
+        let entry_size = u16::read_le(&mut *bytes)? as usize;
+        take(bytes, entry_size)?;
+    }
+    take(bytes, field_size)?;
+    Ok(&start[..start.len() - bytes.len()])
+}
+
+// Split the given number of bytes off the front of the bytes
+fn take<'a>(bytes: &mut &'a [u8], length: usize) -> Result<&'a [u8]> {
+    if bytes.len() < length {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "failed to fill whole buffer"));
+    }
+    let (taken, rest) = bytes.split_at(length);
+    *bytes = rest;
+    Ok(taken)
+}
+
+// Read the version of an item, which is 0 for every item of a block
+fn expect_version(bytes: &mut &[u8], item: &str) -> Result<()> {
+    match u16::read_le(&mut *bytes)? {
+        0 => Ok(()),
+        _ => Err(invalid(&format!("{item} version"))),
+    }
+}
+
+// Move past the global state root and the optional inclusion proof of an execution or fee
+fn skip_inclusion<N: Network>(bytes: &mut &[u8]) -> Result<()> {
+    N::StateRoot::read_le(&mut *bytes)?;
+    match u8::read_le(&mut *bytes)? {
+        0 => Ok(()),
+        1 => Proof::<N>::read_le(&mut *bytes).map(drop),
+        _ => Err(invalid("inclusion proof variant")),
+    }
+}
+
+fn invalid(item: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Invalid {item}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{
+        genesis_block,
+        sample_block_with_fee,
+        sample_block_with_transactions,
+        sample_output,
+        sample_transaction,
+        sample_transition,
+        CurrentNetwork,
+        OutputRecord,
+    };
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        prelude::{ToBytes, Uniform},
+        program::{Balance, Entry, Literal, Owner, Plaintext},
+        types::{Scalar, U64},
+    };
+    use snarkvm_utilities::TestRng;
+
+    use indexmap::IndexMap;
+    use std::str::FromStr;
+
+    type N = CurrentNetwork;
+
+    // Samples a record of the given owner, which is public or private, with the given number of data entries
+    fn sample_record_output(owner: Address<N>, public: bool, entries: usize, rng: &mut TestRng) -> OutputRecord {
+        let randomizer = Scalar::rand(rng);
+        let owner = match public {
+            true => Owner::Public(owner),
+            false => Owner::Private(Plaintext::from(Literal::Address(owner))),
+        };
+        let data = (0..entries)
+            .map(|i| {
+                let value = Plaintext::from(Literal::U64(U64::new(i as u64)));
+                (Identifier::from_str(&format!("entry_{i}")).unwrap(), Entry::Private(value))
+            })
+            .collect::<IndexMap<_, _>>();
+        let gates = Balance::Private(Plaintext::from(Literal::U64(U64::new(100))));
+        let nonce = N::g_scalar_multiply(&randomizer);
+        let plaintext = Record::<N, Plaintext<N>>::from_plaintext(owner, gates, data, nonce).unwrap();
+        (Field::rand(rng), plaintext.encrypt(randomizer).unwrap())
+    }
+
+    #[test]
+    fn test_prefilter_matches_decoding() {
+        let rng = &mut TestRng::default();
+        let view_key = ViewKey::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let other = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let prefilter = RecordPrefilter::new(&view_key);
+        let address_x_coordinate = view_key.to_address().to_x_coordinate();
+
+        // Blocks mix records of both accounts with public and private owners and data, across transactions of
+        // several transitions, and fees.
+        let mut blocks = vec![genesis_block()];
+        for height in 1..40u32 {
+            let previous_hash = blocks.last().unwrap().hash();
+            if height % 7 == 0 {
+                blocks.push(sample_block_with_fee(height, previous_hash, rng));
+                continue;
+            }
+            let transactions = (0..1 + height % 3).map(|i| {
+                let transitions = (0..1 + (height + i) % 2).map(|j| {
+                    let records = (0..(height + i + j) % 4)
+                        .map(|k| {
+                            let seed = height + i + j + k;
+                            let owner = if seed % 3 == 0 { view_key.to_address() } else { other };
+                            match seed % 5 {
+                                0 => sample_output(owner, seed as u64, rng),
+                                _ => sample_record_output(owner, seed % 2 == 0, (seed % 4) as usize, rng),
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    sample_transition(&[Field::rand(rng)], &records, rng)
+                });
+                sample_transaction(transitions.collect::<Vec<_>>())
+            });
+            let transactions = transactions.collect();
+            blocks.push(sample_block_with_transactions(height, previous_hash, transactions, rng));
+        }
+
+        let (mut owned, mut total) = (0, 0);
+        for block in blocks {
+            // Every record is classified as the full check classifies it.
+            for (_, record) in block.clone().into_transitions().flat_map(|transition| transition.into_records()) {
+                let is_owner = record.is_owner_with_address_x_coordinate(&view_key, &address_x_coordinate);
+                assert_eq!(prefilter.is_owner(&record.to_bytes_le().unwrap()).unwrap(), is_owner);
+                (owned, total) = (owned + is_owner as usize, total + 1);
+            }
+            // Walking the block finds exactly the records the decoded block holds.
+            let bytes = block.to_bytes_le().unwrap();
+            let expected = ScannedRecord::find_in_block(block.clone(), &[])
+                .filter(|scanned| scanned.record().is_owner_with_address_x_coordinate(&view_key, &address_x_coordinate))
+                .collect::<Vec<_>>();
+            assert_eq!(prefilter.scan_block(&bytes).unwrap(), (block.height(), expected));
+            assert!(prefilter.scan_block(&bytes[..bytes.len() / 2]).is_err());
+        }
+        assert!(owned > 10 && total - owned > 10, "{owned} of {total} records are owned");
+    }
+}
@@ -0,0 +1,316 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Delegated proving through a pool of proving machines.
+//!
+//! A prover pool proves authorized executions on behalf of its clients. The pool speaks JSON over HTTP, below
+//! the base URL and chain of its [`AleoAPIClient`]:
+//!
+//! - `POST {base}/{chain}/jobs` submits a job. The body is
+//!   `{"authorization": [request, ...], "fee_authorization": [request, ...]}`, where each request is a signed
+//!   `Request` as serialized by snarkVM, in the order of the authorization. The pool responds with `{"id": "..."}`.
+//! - `GET {base}/{chain}/jobs/{id}` returns the status of a job, which is one of `{"status": "queued"}`,
+//!   `{"status": "proving", "progress": 0.5}` with a progress between 0 and 1, `{"status": "done", "transaction":
+//!   {...}}`, or `{"status": "failed", "reason": "..."}`.
+//!
+//! Errors are reported with the HTTP status codes and messages of nodes. The pool rebuilds each authorization
+//! from its requests, proves the execution and the fee, and returns the transaction without broadcasting it.
+
+use crate::{AleoAPIClient, ApiError};
+
+use anyhow::{bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use snarkvm_console::program::{Network, Request};
+use snarkvm_synthesizer::{Authorization, Transaction};
+use std::{
+    error::Error,
+    fmt,
+    time::Duration,
+};
+
+/// The ID a prover pool assigned to a job
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JobId(String);
+
+impl JobId {
+    /// Returns the ID as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The status of a job in a prover pool, as returned by [`ProverPoolClient::job_status`]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case", bound = "")]
+pub enum JobStatus<N: Network> {
+    /// The job waits for a proving machine
+    Queued,
+    /// The job is being proven, with a progress between 0 and 1
+    Proving { progress: f64 },
+    /// The job was proven into a transaction, which was not broadcast
+    Done { transaction: Box<Transaction<N>> },
+    /// The job could not be proven
+    Failed { reason: String },
+}
+
+impl<N: Network> JobStatus<N> {
+    /// Returns `true` if the job is done or failed.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Done { .. } | Self::Failed { .. })
+    }
+}
+
+impl<N: Network> fmt::Display for JobStatus<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Queued => write!(f, "queued"),
+            Self::Proving { progress } => write!(f, "proving ({:.0}%)", progress * 100.0),
+            Self::Done { transaction } => write!(f, "done (transaction '{}')", transaction.id()),
+            Self::Failed { reason } => write!(f, "failed: {reason}"),
+        }
+    }
+}
+
+/// The error returned when a job of a prover pool failed or was not finished before the timeout
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobError<N: Network> {
+    /// The pool could not prove the job
+    Failed { job_id: JobId, reason: String },
+    /// The job was not finished within the timeout, with its last status
+    Timeout { job_id: JobId, timeout: Duration, status: JobStatus<N> },
+}
+
+impl<N: Network> fmt::Display for JobError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Failed { job_id, reason } => write!(f, "Prover job '{job_id}' failed: {reason}"),
+            Self::Timeout { job_id, timeout, status } => {
+                write!(f, "Prover job '{job_id}' was not finished within {timeout:?} ({status})")
+            }
+        }
+    }
+}
+
+impl<N: Network> Error for JobError<N> {}
+
+#[derive(Serialize)]
+#[serde(bound = "")]
+struct JobRequest<N: Network> {
+    authorization: Vec<Request<N>>,
+    fee_authorization: Vec<Request<N>>,
+}
+
+#[derive(Deserialize)]
+struct JobCreated {
+    id: JobId,
+}
+
+/// A client of a pool of proving machines, which proves authorized executions
+///
+/// Authorizations are built without proving with [`crate::ProgramManager::authorize_execution`] and
+/// [`crate::ProgramManager::authorize_fee`]. The transactions of finished jobs should be checked with
+/// [`ProverPoolClient::check_transaction`] before they are broadcast.
+pub struct ProverPoolClient<N: Network> {
+    api_client: AleoAPIClient<N>,
+    poll_interval: Duration,
+}
+
+impl<N: Network> ProverPoolClient<N> {
+    /// The default interval between checks of the status of a job
+    pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Create a client of the prover pool at the base URL and chain of the given client
+    pub fn new(api_client: AleoAPIClient<N>) -> Self {
+        Self { api_client, poll_interval: Self::DEFAULT_POLL_INTERVAL }
+    }
+
+    /// Set the interval between checks of the status of a job
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Returns the interval between checks of the status of a job
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Submit an authorized execution and the authorization of its fee to the pool
+    pub fn submit_job(&self, authorization: &Authorization<N>, fee_authorization: &Authorization<N>) -> Result<JobId> {
+        let url = self.api_client.url()?.route("jobs").build();
+        let body = JobRequest {
+            authorization: authorization.to_vec_deque().into(),
+            fee_authorization: fee_authorization.to_vec_deque().into(),
+        };
+        match self.api_client.post_json::<JobCreated>(&url, &body)? {
+            Ok(created) => Ok(created.id),
+            Err(error) => bail!(ApiError::parse("the prover job ID", error)),
+        }
+    }
+
+    /// Returns the status of a job
+    pub fn job_status(&self, job_id: &JobId) -> Result<JobStatus<N>> {
+        let url = self.api_client.url()?.route("jobs").segment(job_id).build();
+        match self.api_client.get_json(&url)? {
+            Ok(status) => Ok(status),
+            Err(error) => bail!(ApiError::parse(format!("the status of prover job '{job_id}'"), error)),
+        }
+    }
+
+    /// Poll the status of a job until it is done, returning its transaction
+    ///
+    /// Fails with [`JobError::Failed`] if the pool could not prove the job, and with [`JobError::Timeout`] if the
+    /// job was not finished within the timeout.
+    pub fn await_job(&self, job_id: &JobId, timeout: Duration) -> Result<Transaction<N>> {
+        let clock = self.api_client.clock();
+        let start = clock.now();
+        loop {
+            match self.job_status(job_id)? {
+                JobStatus::Done { transaction } => return Ok(*transaction),
+                JobStatus::Failed { reason } => {
+                    return Err(JobError::<N>::Failed { job_id: job_id.clone(), reason }.into())
+                }
+                status if clock.now() - start >= timeout => {
+                    return Err(JobError::Timeout { job_id: job_id.clone(), timeout, status }.into());
+                }
+                _ => clock.sleep(self.poll_interval.min(timeout.saturating_sub(clock.now() - start))),
+            }
+        }
+    }
+
+    /// Check that the transaction of a job proves the given authorizations
+    ///
+    /// Each transition of the transaction must belong to a distinct request of the authorizations, with the same
+    /// program, function and transition public key, so that the pool cannot substitute another execution. The
+    /// proofs themselves are verified by the network when the transaction is broadcast.
+    pub fn check_transaction(
+        authorization: &Authorization<N>,
+        fee_authorization: &Authorization<N>,
+        transaction: &Transaction<N>,
+    ) -> Result<()> {
+        let mut requests: Vec<_> =
+            authorization.to_vec_deque().into_iter().chain(fee_authorization.to_vec_deque()).collect();
+        let transitions = transaction.transitions().count();
+        ensure!(
+            transitions == requests.len(),
+            "Transaction '{}' holds {transitions} transitions, but {} were authorized",
+            transaction.id(),
+            requests.len()
+        );
+        for transition in transaction.transitions() {
+            let position = requests.iter().position(|request| {
+                request.program_id() == transition.program_id()
+                    && request.function_name() == transition.function_name()
+                    && request.to_tpk() == *transition.tpk()
+            });
+            match position {
+                Some(position) => {
+                    requests.swap_remove(position);
+                }
+                None => bail!(
+                    "Transition '{}' of transaction '{}' ({}/{}) was not authorized",
+                    transition.id(),
+                    transaction.id(),
+                    transition.program_id(),
+                    transition.function_name()
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_transaction, sample_transition, CurrentNetwork, MockResponse, MockServer},
+        testnet3,
+        MockClock,
+    };
+    use snarkvm_console::types::Field;
+    use snarkvm_utilities::{TestRng, Uniform};
+
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    type N = CurrentNetwork;
+
+    // Start a mock pool, whose job `job-1` is queued, then proving, then done, and whose job `job-2` fails
+    fn mock_pool(transaction: &Transaction<N>) -> MockServer {
+        let done = serde_json::json!({ "status": "done", "transaction": transaction }).to_string();
+        let polls = Arc::new(AtomicUsize::new(0));
+        MockServer::start(move |request| match request.path.as_str() {
+            "/testnet3/jobs" => Some(MockResponse::json(r#"{"id":"job-1"}"#)),
+            "/testnet3/jobs/job-1" => match polls.fetch_add(1, Ordering::SeqCst) {
+                0 => Some(MockResponse::json(r#"{"status":"queued"}"#)),
+                1 => Some(MockResponse::json(r#"{"status":"proving","progress":0.5}"#)),
+                _ => Some(MockResponse::json(&done)),
+            },
+            "/testnet3/jobs/job-2" => Some(MockResponse::json(r#"{"status":"failed","reason":"out of memory"}"#)),
+            "/testnet3/jobs/job-3" => Some(MockResponse::json(r#"{"status":"queued"}"#)),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_prover_pool_jobs() {
+        let rng = &mut TestRng::default();
+        let transaction = sample_transaction([sample_transition(&[Field::rand(rng)], &[], rng)]);
+        let server = mock_pool(&transaction);
+        let pool = ProverPoolClient::new(testnet3(server.base_url())).with_poll_interval(Duration::from_millis(10));
+
+        // A submitted job walks through every status.
+        let empty = Authorization::<N>::new(&[]);
+        let job_id = pool.submit_job(&empty, &empty).unwrap();
+        assert_eq!(job_id.as_str(), "job-1");
+        assert_eq!(pool.job_status(&job_id).unwrap(), JobStatus::Queued);
+        assert_eq!(pool.job_status(&job_id).unwrap(), JobStatus::Proving { progress: 0.5 });
+        let status = pool.job_status(&job_id).unwrap();
+        assert!(status.is_finished());
+        assert_eq!(status, JobStatus::Done { transaction: Box::new(transaction.clone()) });
+        assert_eq!(pool.await_job(&job_id, Duration::from_secs(1)).unwrap(), transaction);
+
+        // Failed jobs return their reason, and unfinished jobs time out.
+        let failed = JobId("job-2".to_string());
+        let error = pool.await_job(&failed, Duration::from_secs(1)).unwrap_err();
+        assert_eq!(error.to_string(), "Prover job 'job-2' failed: out of memory");
+        let queued = JobId("job-3".to_string());
+        let clock = MockClock::new();
+        let pool = ProverPoolClient::new(testnet3(server.base_url()).with_clock(clock.clone()))
+            .with_poll_interval(Duration::from_secs(10));
+        let error = pool.await_job(&queued, Duration::from_secs(45)).unwrap_err();
+        assert_eq!(error.to_string(), "Prover job 'job-3' was not finished within 45s (queued)");
+        assert!(matches!(error.downcast_ref::<JobError<N>>(), Some(JobError::Timeout { .. })));
+        assert_eq!(clock.elapsed(), Duration::from_secs(45));
+
+        // Transactions that do not prove the authorizations are rejected.
+        let error = ProverPoolClient::check_transaction(&empty, &empty, &transaction).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Transaction '{}' holds 1 transitions, but 0 were authorized", transaction.id())
+        );
+    }
+}
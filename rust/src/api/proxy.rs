@@ -0,0 +1,180 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::{anyhow, ensure, Result};
+
+/// A SOCKS5 proxy through which a client connects to its node, such as a local Tor daemon
+///
+/// Host names are sent to the proxy unresolved, so that DNS queries for the node, including `.onion` addresses,
+/// never leave the proxy. Only the address of the proxy itself is resolved locally.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SocksProxy {
+    address: String,
+    credentials: Option<(String, String)>,
+    direct_fallback: bool,
+}
+
+impl SocksProxy {
+    /// The address of the SOCKS port of a local Tor daemon
+    pub const TOR_ADDRESS: &'static str = "127.0.0.1:9050";
+
+    /// Create a proxy at the given `host:port` address.
+    pub fn new(address: &str) -> Self {
+        Self { address: address.to_string(), credentials: None, direct_fallback: false }
+    }
+
+    /// Create a proxy at the SOCKS port of a local Tor daemon.
+    pub fn tor() -> Self {
+        Self::new(Self::TOR_ADDRESS)
+    }
+
+    /// Authenticate to the proxy with the given username and password.
+    ///
+    /// Tor accepts any credentials, and routes connections with different credentials through different circuits,
+    /// so that the requests of separate clients cannot be linked by their exit node.
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Allow or forbid requests to fall back to a direct connection when the proxy cannot be reached, which is
+    /// forbidden by default.
+    ///
+    /// A direct connection reveals the address of the client to the node, so it should only be allowed when the
+    /// proxy is used for reliability rather than privacy. Requests to `.onion` endpoints never fall back.
+    pub fn with_direct_fallback(mut self, direct_fallback: bool) -> Self {
+        self.direct_fallback = direct_fallback;
+        self
+    }
+
+    /// Returns the `host:port` address of the proxy.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Returns `true` if requests may fall back to a direct connection when the proxy cannot be reached.
+    pub fn allows_direct_fallback(&self) -> bool {
+        self.direct_fallback
+    }
+
+    // Check that the address of the proxy is a host and a port, and that the credentials can be placed in its URL
+    fn check(&self) -> Result<()> {
+        let (host, port) = self.address.rsplit_once(':').ok_or_else(|| anyhow!("Missing port"))?;
+        ensure!(!host.is_empty() && !host.contains(['/', '@']), "Invalid host '{host}'");
+        port.parse::<u16>().map_err(|_| anyhow!("Invalid port '{port}'"))?;
+        if let Some((username, password)) = &self.credentials {
+            ensure!(!username.contains([':', '@', '/']), "The username may not hold ':', '@' or '/'");
+            ensure!(!password.contains(['@', '/']), "The password may not hold '@' or '/'");
+        }
+        Ok(())
+    }
+
+    // Returns the URL of the proxy with the given scheme
+    fn url(&self, scheme: &str) -> Result<String> {
+        self.check().map_err(|error| anyhow!("Invalid SOCKS proxy '{}': {error}", self.address))?;
+        Ok(match &self.credentials {
+            Some((username, password)) => format!("{scheme}://{username}:{password}@{}", self.address),
+            None => format!("{scheme}://{}", self.address),
+        })
+    }
+
+    // Returns the proxy for the blocking transport, which resolves host names through the proxy
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn to_ureq(&self) -> Result<ureq::Proxy> {
+        Ok(ureq::Proxy::new(self.url("socks5")?)?)
+    }
+
+    // Returns the proxy for the asynchronous transport. The `socks5h` scheme resolves host names through the proxy.
+    #[cfg(feature = "async")]
+    pub(crate) fn to_reqwest(&self) -> Result<reqwest::Proxy> {
+        Ok(reqwest::Proxy::all(self.url("socks5h")?)?)
+    }
+}
+
+#[cfg(all(test, not(feature = "async")))]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{MockResponse, MockServer, MockSocksProxy},
+        testnet3,
+        ApiError,
+        BlockHeight,
+    };
+
+    use std::net::TcpListener;
+
+    // Start a mock node serving its latest height
+    fn mock_height_server() -> MockServer {
+        MockServer::start(|request| (request.path == "/testnet3/latest/height").then(|| MockResponse::json(7)))
+    }
+
+    // Returns the address of a local port that refuses connections
+    fn closed_address() -> String {
+        TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string()
+    }
+
+    fn is_proxy_error(error: anyhow::Error) -> bool {
+        matches!(error.downcast_ref::<ApiError>(), Some(ApiError::Proxy { .. }))
+    }
+
+    #[test]
+    fn test_socks_proxy_resolves_onion_endpoints() {
+        let server = mock_height_server();
+        let proxy = MockSocksProxy::start(server.base_url().trim_start_matches("http://"));
+        let endpoint = "http://aleonodeexample.onion:3030";
+
+        // Onion endpoints are refused without a proxy, before their host name is resolved.
+        assert!(is_proxy_error(testnet3(endpoint).latest_height().unwrap_err()));
+
+        // Through the proxy, the host name is passed to the proxy unresolved.
+        let client = testnet3(endpoint).with_socks_proxy(SocksProxy::new(proxy.address())).unwrap();
+        assert_eq!(client.latest_height().unwrap(), BlockHeight(7));
+        assert_eq!(proxy.targets(), [("aleonodeexample.onion".to_string(), 3030)]);
+
+        // Clearnet endpoints go through the proxy by name as well.
+        let client = testnet3("http://node.example:3030").with_socks_proxy(SocksProxy::new(proxy.address())).unwrap();
+        assert_eq!(client.latest_height().unwrap(), BlockHeight(7));
+        assert_eq!(proxy.targets()[1], ("node.example".to_string(), 3030));
+    }
+
+    #[test]
+    fn test_socks_proxy_direct_fallback() {
+        let server = mock_height_server();
+        let proxy = SocksProxy::new(&closed_address());
+
+        // Failed connections through the proxy are proxy errors, and do not fall back by default.
+        let client = testnet3(server.base_url()).with_socks_proxy(proxy.clone()).unwrap();
+        assert!(is_proxy_error(client.latest_height().unwrap_err()));
+
+        // Falling back to a direct connection must be allowed explicitly.
+        let client = testnet3(server.base_url()).with_socks_proxy(proxy.clone().with_direct_fallback(true)).unwrap();
+        assert_eq!(client.latest_height().unwrap(), BlockHeight(7));
+
+        // Onion services never fall back.
+        let client = testnet3("http://aleonodeexample.onion").with_socks_proxy(proxy.with_direct_fallback(true));
+        assert!(is_proxy_error(client.unwrap().latest_height().unwrap_err()));
+    }
+
+    #[test]
+    fn test_socks_proxy_address() {
+        let with_proxy = |proxy: SocksProxy| testnet3("http://node").with_socks_proxy(proxy);
+        assert!(with_proxy(SocksProxy::tor()).is_ok());
+        assert!(with_proxy(SocksProxy::new("localhost:9150").with_credentials("wallet", "p@ss")).is_err());
+        for address in ["localhost", "localhost:port", ":9050", "user@localhost:9050"] {
+            assert!(with_proxy(SocksProxy::new(address)).is_err());
+        }
+    }
+}
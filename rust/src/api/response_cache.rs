@@ -0,0 +1,162 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::Clock;
+use crate::mutex::lock;
+
+use indexmap::IndexMap;
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Whether the response of a route may change, which decides how long it is served from the cache without asking
+/// the node
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Mutability {
+    /// The response may change, e.g. the latest block, so it is fresh for the TTL of the cache
+    Mutable,
+    /// The response never changes, e.g. a block by hash, so it is fresh for as long as it is cached
+    Immutable,
+}
+
+/// The `ETag` and `Last-Modified` headers of a response, sent back to the node to ask whether it changed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Validators {
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+}
+
+/// The result of looking up a URL in a [`ResponseCache`]
+pub(crate) enum Lookup<T> {
+    /// The cached value is fresh, and is returned without a request
+    Fresh(T),
+    /// The cached value is stale, and is returned if the node responds that it did not change
+    Stale(T, Validators),
+    /// No value of the type is cached for the URL
+    Miss,
+}
+
+/// The parsed responses of recent GET requests, by URL, evicting the least recently used response when full
+pub(crate) struct ResponseCache {
+    capacity: usize,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+    responses: Mutex<IndexMap<String, CachedResponse>>,
+}
+
+// A parsed response, with the validators of its last response and the time it was last known to be current
+struct CachedResponse {
+    value: Arc<dyn Any + Send + Sync>,
+    validators: Validators,
+    mutability: Mutability,
+    validated_at: Instant,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(capacity: usize, ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self { capacity: capacity.max(1), ttl, clock, responses: Mutex::new(IndexMap::new()) }
+    }
+
+    /// Returns the number of responses the cache holds at most.
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the time for which responses that may change are fresh.
+    pub(crate) fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the value cached for the URL, marking it as the most recently used.
+    pub(crate) fn lookup<T: Clone + 'static>(&self, url: &str) -> Lookup<T> {
+        let mut responses = lock(&self.responses);
+        let Some(index) = responses.get_index_of(url) else {
+            return Lookup::Miss;
+        };
+        let last = responses.len() - 1;
+        responses.move_index(index, last);
+        let response = &responses[last];
+        let Some(value) = response.value.downcast_ref::<T>() else {
+            return Lookup::Miss;
+        };
+        let age = self.clock.now().duration_since(response.validated_at);
+        match response.mutability == Mutability::Immutable || age < self.ttl {
+            true => Lookup::Fresh(value.clone()),
+            false => Lookup::Stale(value.clone(), response.validators.clone()),
+        }
+    }
+
+    /// Cache the parsed response of the URL, evicting the least recently used response if the cache is full.
+    pub(crate) fn insert<T: Send + Sync + 'static>(
+        &self,
+        url: &str,
+        value: T,
+        validators: Validators,
+        mutability: Mutability,
+    ) {
+        let mut responses = lock(&self.responses);
+        let validated_at = self.clock.now();
+        let response = CachedResponse { value: Arc::new(value), validators, mutability, validated_at };
+        responses.shift_remove(url);
+        responses.insert(url.to_string(), response);
+        while responses.len() > self.capacity {
+            responses.shift_remove_index(0);
+        }
+    }
+
+    /// Mark the response of the URL as current, after the node responded that it did not change.
+    pub(crate) fn revalidate(&self, url: &str) {
+        if let Some(response) = lock(&self.responses).get_mut(url) {
+            response.validated_at = self.clock.now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockClock;
+
+    #[test]
+    fn test_response_cache() {
+        let clock = MockClock::new();
+        let cache = ResponseCache::new(2, Duration::from_secs(60), Arc::new(clock.clone()));
+        let validators = Validators { etag: Some("\"v1\"".to_string()), last_modified: None };
+        cache.insert("latest/height", 7u32, validators.clone(), Mutability::Mutable);
+        cache.insert("block/ab1", "block".to_string(), Validators::default(), Mutability::Immutable);
+        assert!(matches!(cache.lookup::<u32>("latest/height"), Lookup::Fresh(7)));
+
+        // Mutable responses are stale once the TTL elapsed, and immutable responses stay fresh.
+        clock.advance(Duration::from_secs(60));
+        assert!(matches!(cache.lookup::<u32>("latest/height"), Lookup::Stale(7, v) if v == validators));
+        assert!(matches!(cache.lookup::<String>("block/ab1"), Lookup::Fresh(block) if block == "block"));
+        assert!(matches!(cache.lookup::<String>("latest/height"), Lookup::Miss));
+
+        // The least recently used response is evicted first.
+        cache.lookup::<u32>("latest/height");
+        cache.insert("transaction/at1", 1u64, Validators::default(), Mutability::Immutable);
+        assert!(matches!(cache.lookup::<String>("block/ab1"), Lookup::Miss));
+        assert!(matches!(cache.lookup::<u32>("latest/height"), Lookup::Stale(7, _)));
+        assert!(matches!(cache.lookup::<u64>("transaction/at1"), Lookup::Fresh(1)));
+
+        // Revalidating a response makes it fresh for another TTL.
+        cache.revalidate("latest/height");
+        clock.advance(Duration::from_secs(59));
+        assert!(matches!(cache.lookup::<u32>("latest/height"), Lookup::Fresh(7)));
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use snarkvm_console::{
+    account::ViewKey,
+    program::{Ciphertext, Identifier, Network, ProgramID, Record},
+    types::Field,
+};
+use snarkvm_synthesizer::Block;
+
+/// A record found by a scan, with the program function whose transition created it
+///
+/// Records do not name their type, so wallets use the program and function to tell how to decrypt and
+/// render a record of a custom program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ScannedRecord<N: Network> {
+    commitment: Field<N>,
+    record: Record<N, Ciphertext<N>>,
+    program_id: ProgramID<N>,
+    function_name: Identifier<N>,
+}
+
+impl<N: Network> ScannedRecord<N> {
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn new(
+        commitment: Field<N>,
+        record: Record<N, Ciphertext<N>>,
+        program_id: ProgramID<N>,
+        function_name: Identifier<N>,
+    ) -> Self {
+        Self { commitment, record, program_id, function_name }
+    }
+
+    // Returns the records of the block created by transitions of the given programs, or of any program if none
+    // are given, in the order of the block
+    pub(crate) fn find_in_block<'a>(
+        block: Block<N>,
+        program_ids: &'a [ProgramID<N>],
+    ) -> impl 'a + Iterator<Item = Self> {
+        block
+            .into_transitions()
+            .filter(move |transition| program_ids.is_empty() || program_ids.contains(transition.program_id()))
+            .flat_map(|transition| {
+                let (program_id, function_name) = (*transition.program_id(), *transition.function_name());
+                transition.into_records().map(move |(commitment, record)| Self {
+                    commitment,
+                    record,
+                    program_id,
+                    function_name,
+                })
+            })
+    }
+
+    /// Returns the commitment of the record.
+    pub fn commitment(&self) -> &Field<N> {
+        &self.commitment
+    }
+
+    /// Returns the encrypted record.
+    pub fn record(&self) -> &Record<N, Ciphertext<N>> {
+        &self.record
+    }
+
+    /// Returns the ID of the program whose transition created the record.
+    pub fn program_id(&self) -> &ProgramID<N> {
+        &self.program_id
+    }
+
+    /// Returns the name of the function whose transition created the record.
+    pub fn function_name(&self) -> &Identifier<N> {
+        &self.function_name
+    }
+
+    /// Returns the memo of the record, if the view key owns it and it holds one, as read by [`crate::extract_memo`].
+    pub fn memo(&self, view_key: &ViewKey<N>) -> Result<Option<Vec<u8>>> {
+        if !self.record.is_owner(view_key) {
+            return Ok(None);
+        }
+        Ok(crate::extract_memo(&self.record.decrypt(view_key)?)?)
+    }
+
+    /// Returns the commitment and the encrypted record, as returned by [`crate::AleoAPIClient::scan`].
+    pub fn into_pair(self) -> (Field<N>, Record<N, Ciphertext<N>>) {
+        (self.commitment, self.record)
+    }
+}
@@ -0,0 +1,408 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Full-text search over the transitions of blocks, without an index.
+//!
+//! The text of each field of a transition is split into tokens at the boundaries of Aleo literals, which are the
+//! characters that cannot appear in an address, a literal, a program ID or an identifier, and a field matches a
+//! query if one of its tokens equals it. A query never matches a part of a token, so that `token.aleo` does not
+//! match `mytoken.aleo`, and a prefix of an address does not match the address.
+
+use crate::{api::BlockSource, BlockHeight};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use snarkvm_console::program::{Balance, Entry, Network, Owner, Plaintext, Record, Value, Visibility};
+use snarkvm_synthesizer::{Block, Input, Output, Transition};
+use std::{fmt, ops::Range, str::FromStr};
+
+/// A term searched for in the transitions of blocks, which is an address, a literal, a program ID, a function name,
+/// or the commitment, serial number or tag of a record
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SearchQuery {
+    term: String,
+}
+
+impl SearchQuery {
+    /// Create a query for the given term, which fails if the term is empty or spans several tokens, e.g.
+    /// `credits.aleo/transfer`, which is searched for as `credits.aleo` or `transfer`.
+    pub fn new(term: &str) -> Result<Self> {
+        let term = term.trim();
+        let mut tokens = tokens(term);
+        match (tokens.next(), tokens.next()) {
+            (Some(token), None) if token == term => Ok(Self { term: term.to_string() }),
+            _ => bail!("The search term '{term}' is not a single address, literal, program ID or identifier"),
+        }
+    }
+
+    /// Returns the term searched for.
+    pub fn term(&self) -> &str {
+        &self.term
+    }
+
+    // Returns `true` if a token of the text equals the term
+    fn matches(&self, text: &str) -> bool {
+        tokens(text).any(|token| token == self.term)
+    }
+}
+
+impl FromStr for SearchQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(term: &str) -> Result<Self> {
+        Self::new(term)
+    }
+}
+
+impl fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.term)
+    }
+}
+
+/// A field of a transition matching a [`SearchQuery`]
+///
+/// The field is named by its path in the transition: `program`, `function`, or the index of an input, an output or
+/// a finalize argument, as in `inputs[0]`, `outputs[1]` or `finalize[0]`, followed by the members of the structs
+/// and records it is nested in, as in `finalize[0].owner`, or by `commitment`, `serial_number` or `tag` for the
+/// identifiers of a record.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct SearchHit<N: Network> {
+    height: BlockHeight,
+    transaction_id: N::TransactionID,
+    transition_id: N::TransitionID,
+    transition_index: usize,
+    path: String,
+}
+
+impl<N: Network> SearchHit<N> {
+    /// Returns the height of the block containing the transition.
+    pub fn height(&self) -> BlockHeight {
+        self.height
+    }
+
+    /// Returns the ID of the transaction containing the transition.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the ID of the transition.
+    pub fn transition_id(&self) -> N::TransitionID {
+        self.transition_id
+    }
+
+    /// Returns the index of the transition in its transaction, in which the transition of the fee comes last.
+    pub fn transition_index(&self) -> usize {
+        self.transition_index
+    }
+
+    /// Returns the path of the matching field in the transition.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+// Split a text into tokens at the characters that cannot appear in an Aleo literal, keeping the `.` of program IDs
+// and the `-` of negative integers
+fn tokens(text: &str) -> impl '_ + Iterator<Item = &str> {
+    let is_boundary = |c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    text.split(is_boundary).filter(|token| !token.is_empty())
+}
+
+// Returns the hits of the query in the blocks of the source at the given heights, in the order of the blocks, then
+// of the transactions and transitions in each block
+pub(crate) fn search_source<N: Network>(
+    source: &(impl BlockSource<N> + ?Sized),
+    block_heights: Range<BlockHeight>,
+    query: &SearchQuery,
+) -> Result<Vec<SearchHit<N>>> {
+    let mut hits = Vec::new();
+    source.for_each_block(block_heights, &mut |block| search_block(&block, query, &mut hits))?;
+    Ok(hits)
+}
+
+fn search_block<N: Network>(block: &Block<N>, query: &SearchQuery, hits: &mut Vec<SearchHit<N>>) {
+    for transaction in block.transactions().iter() {
+        for (transition_index, transition) in transaction.transitions().enumerate() {
+            let mut paths = Vec::new();
+            search_transition(transition, query, &mut paths);
+            hits.extend(paths.into_iter().map(|path| SearchHit {
+                height: BlockHeight(block.height()),
+                transaction_id: transaction.id(),
+                transition_id: *transition.id(),
+                transition_index,
+                path,
+            }));
+        }
+    }
+}
+
+// Push the paths of the fields of the transition matching the query
+fn search_transition<N: Network>(transition: &Transition<N>, query: &SearchQuery, paths: &mut Vec<String>) {
+    let mut matches = |path: String, text: String| {
+        if query.matches(&text) {
+            paths.push(path);
+        }
+    };
+    matches("program".to_string(), transition.program_id().to_string());
+    matches("function".to_string(), transition.function_name().to_string());
+    for (index, input) in transition.inputs().iter().enumerate() {
+        let path = format!("inputs[{index}]");
+        match input {
+            Input::Constant(_, Some(plaintext)) | Input::Public(_, Some(plaintext)) => {
+                search_plaintext(plaintext, path, &mut matches)
+            }
+            Input::Record(serial_number, tag) => {
+                matches(format!("{path}.serial_number"), serial_number.to_string());
+                matches(format!("{path}.tag"), tag.to_string());
+            }
+            _ => {}
+        }
+    }
+    for (index, output) in transition.outputs().iter().enumerate() {
+        let path = format!("outputs[{index}]");
+        match output {
+            Output::Constant(_, Some(plaintext)) | Output::Public(_, Some(plaintext)) => {
+                search_plaintext(plaintext, path, &mut matches)
+            }
+            Output::Record(commitment, _, record) => {
+                matches(format!("{path}.commitment"), commitment.to_string());
+                if let Some(record) = record {
+                    // Only the public parts of a record on chain are searched, as the others are encrypted.
+                    search_record(record, path, |_| None, &mut matches);
+                }
+            }
+            _ => {}
+        }
+    }
+    for (index, value) in transition.finalize_iter().enumerate() {
+        let path = format!("finalize[{index}]");
+        match value {
+            Value::Plaintext(plaintext) => search_plaintext(plaintext, path, &mut matches),
+            Value::Record(record) => search_record(record, path, |plaintext| Some(plaintext), &mut matches),
+        }
+    }
+}
+
+// Match the literals of a plaintext, naming the members of its structs in their paths
+fn search_plaintext<N: Network>(plaintext: &Plaintext<N>, path: String, matches: &mut impl FnMut(String, String)) {
+    match plaintext {
+        Plaintext::Literal(literal, _) => matches(path, literal.to_string()),
+        Plaintext::Struct(members, _) => {
+            for (name, member) in members {
+                search_plaintext(member, format!("{path}.{name}"), matches);
+            }
+        }
+    }
+}
+
+// Match the owner, gates and entries of a record, with the plaintext of its private parts if it is known
+fn search_record<N: Network, P: Visibility>(
+    record: &Record<N, P>,
+    path: String,
+    private: impl Fn(&P) -> Option<&Plaintext<N>>,
+    matches: &mut impl FnMut(String, String),
+) {
+    match record.owner() {
+        Owner::Public(address) => matches(format!("{path}.owner"), address.to_string()),
+        Owner::Private(owner) => {
+            if let Some(plaintext) = private(owner) {
+                search_plaintext(plaintext, format!("{path}.owner"), matches);
+            }
+        }
+    }
+    match record.gates() {
+        Balance::Public(gates) => matches(format!("{path}.gates"), gates.to_string()),
+        Balance::Private(gates) => {
+            if let Some(plaintext) = private(gates) {
+                search_plaintext(plaintext, format!("{path}.gates"), matches);
+            }
+        }
+    }
+    for (name, entry) in record.data() {
+        let plaintext = match entry {
+            Entry::Constant(plaintext) | Entry::Public(plaintext) => Some(plaintext),
+            Entry::Private(plaintext) => private(plaintext),
+        };
+        if let Some(plaintext) = plaintext {
+            search_plaintext(plaintext, format!("{path}.{name}"), matches);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, sample_block_with_transactions, sample_output, CurrentNetwork};
+
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        prelude::Uniform,
+        program::{Identifier, ProgramID},
+        types::Field,
+    };
+    use snarkvm_synthesizer::{Execution, Transaction};
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    // A source of the fixture blocks
+    struct FixtureSource(Vec<Block<N>>);
+
+    impl BlockSource<N> for FixtureSource {
+        fn for_each_block(&self, block_heights: Range<BlockHeight>, f: &mut dyn FnMut(Block<N>)) -> Result<()> {
+            let blocks = self.0.iter().filter(|block| block_heights.contains(&BlockHeight(block.height())));
+            blocks.cloned().for_each(f);
+            Ok(())
+        }
+    }
+
+    // Samples a transaction with a single transition of the given program and function, with the given public
+    // inputs and finalize arguments, creating a record with the given owner
+    fn sample_call(
+        program: &str,
+        function: &str,
+        inputs: Vec<Plaintext<N>>,
+        finalize: Vec<Plaintext<N>>,
+        owner: Address<N>,
+        rng: &mut TestRng,
+    ) -> Transaction<N> {
+        let template = genesis_block().transitions().next().unwrap().clone();
+        let inputs = inputs.into_iter().map(|plaintext| Input::Public(Field::rand(rng), Some(plaintext))).collect();
+        let (commitment, record) = sample_output(owner, 1, rng);
+        let transition = Transition::new(
+            ProgramID::from_str(program).unwrap(),
+            Identifier::from_str(function).unwrap(),
+            inputs,
+            vec![Output::Record(commitment, Field::rand(rng), Some(record))],
+            Some(finalize.into_iter().map(Value::Plaintext).collect()),
+            template.proof().clone(),
+            *template.tpk(),
+            *template.tcm(),
+            0,
+        )
+        .unwrap();
+        let execution = Execution::from([transition].into_iter(), Default::default(), None).unwrap();
+        Transaction::from_execution(execution, None).unwrap()
+    }
+
+    fn address(rng: &mut TestRng) -> Address<N> {
+        Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap()
+    }
+
+    fn literal(string: &str) -> Plaintext<N> {
+        Plaintext::from_str(string).unwrap()
+    }
+
+    // Samples a chain of three blocks: a transfer to `recipient` in its finalize arguments at height 1, and a call
+    // of `mytoken.aleo/airdrop` with an amount at height 2
+    fn sample_fixture(recipient: Address<N>, rng: &mut TestRng) -> FixtureSource {
+        let sender = address(rng);
+        let transfer = sample_call(
+            "token.aleo",
+            "transfer_public",
+            vec![literal(&recipient.to_string()), literal("100u64")],
+            vec![literal(&sender.to_string()), literal(&recipient.to_string()), literal("100u64")],
+            sender,
+            rng,
+        );
+        let airdrop = sample_call("mytoken.aleo", "airdrop", vec![literal("1000u64")], vec![], sender, rng);
+        let mut blocks = vec![genesis_block()];
+        for transaction in [transfer, airdrop] {
+            let previous_hash = blocks.last().unwrap().hash();
+            let height = blocks.len() as u32;
+            let transactions = [transaction].into_iter().collect();
+            blocks.push(sample_block_with_transactions(height, previous_hash, transactions, rng));
+        }
+        FixtureSource(blocks)
+    }
+
+    fn search(source: &FixtureSource, term: &str) -> Vec<(u32, usize, String)> {
+        let hits = source.search_blocks(BlockHeight(0)..BlockHeight(3), &SearchQuery::new(term).unwrap()).unwrap();
+        hits.into_iter().map(|hit| (hit.height().0, hit.transition_index(), hit.path().to_string())).collect()
+    }
+
+    #[test]
+    fn test_search_address_in_finalize() {
+        let rng = &mut TestRng::default();
+        let recipient = address(rng);
+        let source = sample_fixture(recipient, rng);
+
+        // The address is found in the inputs and in the finalize arguments of the transfer.
+        let hits = search(&source, &recipient.to_string());
+        assert_eq!(hits, [(1, 0, "inputs[0]".to_string()), (1, 0, "finalize[1]".to_string())]);
+        let query = recipient.to_string().parse::<SearchQuery>().unwrap();
+        let hits = source.search_blocks(BlockHeight(1)..BlockHeight(2), &query).unwrap();
+        let transaction = source.0[1].transactions().iter().next().unwrap();
+        assert_eq!(hits[1].transaction_id(), transaction.id());
+        assert_eq!(hits[1].transition_id(), *transaction.transitions().next().unwrap().id());
+
+        // Out of the range searched, the transfer is not found.
+        assert!(source.search_blocks(BlockHeight(2)..BlockHeight(3), &query).unwrap().is_empty());
+
+        // The commitment of a record is found, as is a literal.
+        let commitment = source.0[2].transitions().next().unwrap().commitments().next().unwrap().to_string();
+        assert_eq!(search(&source, &commitment), [(2, 0, "outputs[0].commitment".to_string())]);
+        assert_eq!(search(&source, "1000u64"), [(2, 0, "inputs[0]".to_string())]);
+    }
+
+    #[test]
+    fn test_search_program_name() {
+        let rng = &mut TestRng::default();
+        let source = sample_fixture(address(rng), rng);
+
+        assert_eq!(search(&source, "token.aleo"), [(1, 0, "program".to_string())]);
+        assert_eq!(search(&source, "mytoken.aleo"), [(2, 0, "program".to_string())]);
+        assert_eq!(search(&source, "airdrop"), [(2, 0, "function".to_string())]);
+        // The genesis block holds the transactions of `credits.aleo`.
+        assert!(search(&source, "credits.aleo").iter().all(|(height, _, path)| *height == 0 && path == "program"));
+        assert!(!search(&source, "credits.aleo").is_empty());
+    }
+
+    #[test]
+    fn test_search_near_miss() {
+        let rng = &mut TestRng::default();
+        let recipient = address(rng);
+        let source = sample_fixture(recipient, rng);
+
+        // Parts of a program ID, an address or a literal do not match it.
+        let recipient = recipient.to_string();
+        for term in ["token", "aleo", "oken.aleo", &recipient[..recipient.len() - 1], &recipient[4..], "100", "u64"] {
+            assert!(search(&source, term).is_empty(), "'{term}' matched");
+        }
+        assert!(search(&source, "1000").is_empty());
+        assert!(search(&source, "transfer").is_empty());
+
+        // Terms spanning several tokens are refused.
+        assert!(SearchQuery::new("token.aleo/transfer_public").is_err());
+        assert!(SearchQuery::new("").is_err());
+        assert_eq!(SearchQuery::new(" token.aleo ").unwrap().term(), "token.aleo");
+    }
+
+    #[test]
+    fn test_search_struct_members() {
+        let rng = &mut TestRng::default();
+        let owner = address(rng);
+        let member = Plaintext::from_str(&format!("{{ owner: {owner}, amount: 5u64 }}")).unwrap();
+        let transaction = sample_call("token.aleo", "lock", vec![], vec![member], address(rng), rng);
+        let block = sample_block_with_transactions(1, genesis_block().hash(), [transaction].into_iter().collect(), rng);
+        let source = FixtureSource(vec![genesis_block(), block]);
+
+        assert_eq!(search(&source, &owner.to_string()), [(1, 0, "finalize[0].owner".to_string())]);
+        assert_eq!(search(&source, "5u64"), [(1, 0, "finalize[0].amount".to_string())]);
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::ApiError;
+
+use std::{error::Error, fmt};
+
+/// The reason a node rejected a prover solution, for the retry logic of provers
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SolutionRejection {
+    /// The solution was constructed for an epoch that has ended. The prover should fetch the new epoch
+    /// challenge before solving again.
+    StaleEpoch,
+    /// The target of the solution is below the proof target of the node. The solution can be discarded.
+    BelowTarget,
+    /// The node could not parse or verify the solution
+    Malformed,
+}
+
+impl fmt::Display for SolutionRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::StaleEpoch => write!(f, "stale epoch"),
+            Self::BelowTarget => write!(f, "below target"),
+            Self::Malformed => write!(f, "malformed"),
+        }
+    }
+}
+
+/// The error returned when a node rejects a prover solution
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolutionRejected {
+    reason: SolutionRejection,
+    status: u16,
+    message: String,
+}
+
+impl SolutionRejected {
+    /// Returns the reason the solution was rejected.
+    pub fn reason(&self) -> SolutionRejection {
+        self.reason
+    }
+
+    /// Returns the HTTP status of the rejection.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Returns the start of the message of the node.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for SolutionRejected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The node rejected the solution ({}): {}", self.reason, self.message)
+    }
+}
+
+impl Error for SolutionRejected {}
+
+// Convert the error of a solution broadcast into a `SolutionRejected`, if the node rejected the solution for a
+// reason its message tells. Other errors are returned unchanged.
+pub(crate) fn to_solution_rejection(error: anyhow::Error) -> anyhow::Error {
+    let Some(ApiError::Http { status, snippet }) = error.downcast_ref::<ApiError>() else { return error };
+    let message = snippet.to_lowercase();
+    // Nodes may name the epoch and target in other rejections, so the most specific reasons are checked first.
+    let reason = if message.contains("epoch") || message.contains("stale") {
+        SolutionRejection::StaleEpoch
+    } else if message.contains("target") {
+        SolutionRejection::BelowTarget
+    } else if ["malformed", "invalid", "deserialize", "parse", "verif"].iter().any(|word| message.contains(word)) {
+        SolutionRejection::Malformed
+    } else {
+        return error;
+    };
+    SolutionRejected { reason, status: *status, message: snippet.clone() }.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns the reason of the rejection the node responded with, if any
+    fn reason(status: u16, message: &str) -> Option<SolutionRejection> {
+        let error = ApiError::Http { status, snippet: message.to_string() }.into();
+        to_solution_rejection(error).downcast::<SolutionRejected>().ok().map(|rejected| rejected.reason())
+    }
+
+    #[test]
+    fn test_solution_rejection() {
+        let stale = "Prover solution is for epoch 3, but the current epoch is 4";
+        assert_eq!(reason(400, stale), Some(SolutionRejection::StaleEpoch));
+        assert_eq!(reason(500, "Invalid solution: stale epoch challenge"), Some(SolutionRejection::StaleEpoch));
+        let below = "Prover puzzle does not meet the proof target requirements.";
+        assert_eq!(reason(500, below), Some(SolutionRejection::BelowTarget));
+        assert_eq!(reason(400, "Failed to deserialize the prover solution"), Some(SolutionRejection::Malformed));
+
+        // Other errors are kept.
+        assert_eq!(reason(503, "Service unavailable"), None);
+        let error = to_solution_rejection(anyhow::anyhow!("Connection refused"));
+        assert_eq!(error.to_string(), "Connection refused");
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::AleoAPIClient;
+#[cfg(not(feature = "async"))]
+use crate::FeeDeltaKind;
+
+use snarkvm_console::program::Network;
+
+/// The counter of requests sent to nodes, labelled by `network`, `route` and `status`
+///
+/// The network is the chain of the client, e.g. `testnet3`. The route is the path of the request below the
+/// network, with each segment that is not a plain word replaced by `{}`, e.g. `block/{}`. The status is the HTTP
+/// status of the response, or `error` if no response was received.
+///
+/// Counters are only recorded with the `metrics` feature, into the recorder installed with the `metrics` crate.
+pub const METRIC_REQUESTS: &str = "aleo_requests_total";
+/// The counter of requests that received an error status or no response, with the labels of [`METRIC_REQUESTS`]
+pub const METRIC_REQUEST_ERRORS: &str = "aleo_request_errors_total";
+/// The counter of blocks scanned for records, labelled by `network`
+pub const METRIC_SCANNED_BLOCKS: &str = "aleo_scanned_blocks_total";
+/// The counter of records found by scans, labelled by `network`
+pub const METRIC_SCANNED_RECORDS: &str = "aleo_scanned_records_total";
+/// The counter of transaction broadcasts, labelled by `network` and `outcome`
+///
+/// The outcome is `accepted` or `rejected` by the node, or `deduplicated` for a transaction that was not posted
+/// again, as it was broadcast within the broadcast TTL of the client.
+pub const METRIC_BROADCASTS: &str = "aleo_broadcasts_total";
+/// The counter of cache lookups, labelled by `network`, `cache` and `result`
+///
+/// The cache is `block`, for the block cache of the client, or `broadcast`, for its acknowledgements of
+/// broadcast transactions. The result is `hit` or `miss`.
+pub const METRIC_CACHE_LOOKUPS: &str = "aleo_cache_lookups_total";
+/// The counter of fees charged otherwise than estimated, beyond the tolerance of the [`crate::FeeAudit`] of a
+/// program manager, labelled by `network` and `kind`
+///
+/// The kind is that of the difference, as named by [`crate::FeeDeltaKind::as_str`], e.g. `estimator_drift`.
+pub const METRIC_FEE_DEVIATIONS: &str = "aleo_fee_deviations_total";
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+impl<N: Network> AleoAPIClient<N> {
+    // Count a request to the URL, which received a response with the given status, if any
+    pub(crate) fn count_request(&self, url: &str, status: Option<u16>) {
+        #[cfg(feature = "metrics")]
+        {
+            let labels = [
+                ("network", self.chain.clone()),
+                ("route", self.route(url)),
+                ("status", status.map_or_else(|| "error".to_string(), |status| status.to_string())),
+            ];
+            metrics::counter!(METRIC_REQUESTS, &labels).increment(1);
+            if !status.is_some_and(|status| (200..300).contains(&status)) {
+                metrics::counter!(METRIC_REQUEST_ERRORS, &labels).increment(1);
+            }
+        }
+    }
+
+    // Count the blocks scanned for records, and the records found in them
+    pub(crate) fn count_scan(&self, blocks: usize, records: usize) {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!(METRIC_SCANNED_BLOCKS, "network" => self.chain.clone()).increment(blocks as u64);
+            metrics::counter!(METRIC_SCANNED_RECORDS, "network" => self.chain.clone()).increment(records as u64);
+        }
+    }
+
+    // Count a broadcast with the given outcome
+    pub(crate) fn count_broadcast(&self, outcome: &'static str) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!(METRIC_BROADCASTS, "network" => self.chain.clone(), "outcome" => outcome).increment(1);
+    }
+
+    // Count lookups of the given cache with the given result
+    pub(crate) fn count_cache_lookups(&self, cache: &'static str, hit: bool, lookups: usize) {
+        #[cfg(feature = "metrics")]
+        {
+            let result = if hit { "hit" } else { "miss" };
+            let labels =
+                [("network", self.chain.clone()), ("cache", cache.to_string()), ("result", result.to_string())];
+            metrics::counter!(METRIC_CACHE_LOOKUPS, &labels).increment(lookups as u64);
+        }
+    }
+
+    // Count a fee charged otherwise than estimated
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn count_fee_deviation(&self, kind: FeeDeltaKind) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!(METRIC_FEE_DEVIATIONS, "network" => self.chain.clone(), "kind" => kind.as_str()).increment(1);
+    }
+
+    // Returns the route of a request to the URL, whose segments that are not plain words, such as heights, IDs,
+    // and keys, are replaced by `{}` to bound the number of routes
+    #[cfg(feature = "metrics")]
+    fn route(&self, url: &str) -> String {
+        let path = url.strip_prefix(self.base_url.as_str()).unwrap_or(url).trim_start_matches('/');
+        let path = path.strip_prefix(self.chain.as_str()).unwrap_or(path);
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let segments = path.split('/').filter(|segment| !segment.is_empty()).map(|segment| {
+            match segment.chars().all(|character| character.is_ascii_alphabetic()) {
+                true => segment,
+                false => "{}",
+            }
+        });
+        segments.collect::<Vec<_>>().join("/")
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::test_helpers::CurrentNetwork;
+
+    #[test]
+    fn test_route() {
+        let client = AleoAPIClient::<CurrentNetwork>::new("http://localhost:3030/", "testnet3");
+        assert_eq!(client.route("http://localhost:3030//testnet3/latest/height"), "latest/height");
+        assert_eq!(client.route("http://localhost:3030//testnet3/block/42"), "block/{}");
+        assert_eq!(client.route("http://localhost:3030//testnet3/blocks?start=0&end=50"), "blocks");
+        assert_eq!(
+            client.route("http://localhost:3030//testnet3/program/credits.aleo/mapping/account/aleo1abc"),
+            "program/{}/mapping/account/{}"
+        );
+    }
+}
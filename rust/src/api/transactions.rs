@@ -0,0 +1,234 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{BlockHeight, TxIndex};
+
+use anyhow::{anyhow, Result};
+use serde::{
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize,
+    Deserializer,
+};
+use serde_json::Value;
+use snarkvm_console::program::Network;
+use std::{error::Error, fmt};
+
+/// The error returned when a block has no transaction at the requested index
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TransactionIndexOutOfRange {
+    height: BlockHeight,
+    index: TxIndex,
+    count: usize,
+}
+
+impl TransactionIndexOutOfRange {
+    pub(crate) fn new(height: BlockHeight, index: TxIndex, count: usize) -> Self {
+        Self { height, index, count }
+    }
+
+    /// Returns the height of the block.
+    pub fn height(&self) -> BlockHeight {
+        self.height
+    }
+
+    /// Returns the requested index.
+    pub fn index(&self) -> TxIndex {
+        self.index
+    }
+
+    /// Returns the number of transactions in the block.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl fmt::Display for TransactionIndexOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Transaction index {} is out of range for block {}, which has {} transactions",
+            self.index, self.height, self.count
+        )
+    }
+}
+
+impl Error for TransactionIndexOutOfRange {}
+
+/// The transactions of a block, as read by a [`TransactionSeed`]
+#[derive(Debug)]
+pub(crate) struct BlockTransactions {
+    /// The height in the header of the block
+    pub(crate) height: u32,
+    /// The number of transactions in the block
+    pub(crate) count: usize,
+    /// The JSON of the transaction at the requested index, if there is one
+    pub(crate) transaction: Option<Value>,
+    /// The IDs of the transactions the block includes as aborted, which only newer nodes serve
+    pub(crate) aborted_ids: Vec<String>,
+}
+
+impl BlockTransactions {
+    /// Returns the IDs of the transactions the block includes as aborted.
+    pub(crate) fn aborted_transaction_ids<N: Network>(&self) -> Result<Vec<N::TransactionID>> {
+        let parse = |id: &String| id.parse().map_err(|_| anyhow!("Invalid aborted transaction ID '{id}'"));
+        self.aborted_ids.iter().map(parse).collect()
+    }
+}
+
+/// Deserializes the JSON of a block, keeping only its height, the number of its transactions, and the JSON of
+/// the transaction at the given index, if any
+///
+/// The other transactions and fields are skipped as they are read, so no more than one transaction is held in
+/// memory, however large the block.
+pub(crate) struct TransactionSeed {
+    index: Option<usize>,
+}
+
+impl TransactionSeed {
+    pub(crate) fn new(index: Option<usize>) -> Self {
+        Self { index }
+    }
+}
+
+// The part of a block header holding its height
+#[derive(Deserialize)]
+struct HeightHeader {
+    metadata: HeightMetadata,
+}
+
+#[derive(Deserialize)]
+struct HeightMetadata {
+    height: u32,
+}
+
+impl<'de> DeserializeSeed<'de> for TransactionSeed {
+    type Value = BlockTransactions;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TransactionSeed {
+    type Value = BlockTransactions;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a block")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut height, mut transactions, mut aborted_ids) = (None, None, vec![]);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "header" => height = Some(map.next_value::<HeightHeader>()?.metadata.height),
+                "transactions" => transactions = Some(map.next_value_seed(IndexSeed { index: self.index })?),
+                "aborted_transaction_ids" => aborted_ids = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        let height = height.ok_or_else(|| de::Error::missing_field("header"))?;
+        let (count, transaction) = transactions.ok_or_else(|| de::Error::missing_field("transactions"))?;
+        Ok(BlockTransactions { height, count, transaction, aborted_ids })
+    }
+}
+
+// Counts the elements of a JSON array, keeping the element at the given index
+struct IndexSeed {
+    index: Option<usize>,
+}
+
+impl<'de> DeserializeSeed<'de> for IndexSeed {
+    type Value = (usize, Option<Value>);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for IndexSeed {
+    type Value = (usize, Option<Value>);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of transactions")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let (mut count, mut kept) = (0, None);
+        loop {
+            if self.index == Some(count) {
+                match seq.next_element::<Value>()? {
+                    Some(value) => kept = Some(value),
+                    None => break,
+                }
+            } else if seq.next_element::<IgnoredAny>()?.is_none() {
+                break;
+            }
+            count += 1;
+        }
+        Ok((count, kept))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, sample_transaction, sample_transition, CurrentNetwork};
+    use snarkvm_console::{prelude::Uniform, types::Field};
+    use snarkvm_utilities::TestRng;
+
+    #[test]
+    fn test_transaction_seed() {
+        let block = genesis_block();
+        let json = block.to_string();
+        let transaction = block.transactions().iter().next().unwrap();
+
+        let read = |index| TransactionSeed::new(index).deserialize(&mut serde_json::Deserializer::from_str(&json));
+        let BlockTransactions { height, count, transaction: kept, .. } = read(Some(0)).unwrap();
+        assert_eq!((height, count), (0, 1));
+        assert_eq!(kept.unwrap(), serde_json::to_value(transaction).unwrap());
+        let BlockTransactions { count, transaction: kept, .. } = read(Some(1)).unwrap();
+        assert_eq!((count, kept), (1, None));
+        assert!(read(None).unwrap().transaction.is_none());
+        assert!(read(None).unwrap().aborted_ids.is_empty());
+
+        // Blocks without transactions are malformed.
+        let json = r#"{"header":{"metadata":{"height":1}}}"#;
+        let error = TransactionSeed::new(None).deserialize(&mut serde_json::Deserializer::from_str(json)).unwrap_err();
+        assert!(error.to_string().starts_with("missing field `transactions`"));
+    }
+
+    #[test]
+    fn test_aborted_transaction_ids() {
+        let rng = &mut TestRng::default();
+        let aborted = [0, 1].map(|_| sample_transaction([sample_transition(&[Field::rand(rng)], &[], rng)]).id());
+        let mut block = serde_json::to_value(genesis_block()).unwrap();
+        block["aborted_transaction_ids"] = serde_json::json!(aborted);
+        let json = block.to_string();
+
+        // Newer nodes list the aborted transactions of a block by their IDs.
+        let read = || TransactionSeed::new(None).deserialize(&mut serde_json::Deserializer::from_str(&json)).unwrap();
+        assert_eq!(read().count, 1);
+        assert_eq!(read().aborted_transaction_ids::<CurrentNetwork>().unwrap(), aborted);
+
+        block["aborted_transaction_ids"] = serde_json::json!(["at1invalid"]);
+        let json = block.to_string();
+        let transactions = TransactionSeed::new(None).deserialize(&mut serde_json::Deserializer::from_str(&json));
+        let error = transactions.unwrap().aborted_transaction_ids::<CurrentNetwork>().unwrap_err();
+        assert_eq!(error.to_string(), "Invalid aborted transaction ID 'at1invalid'");
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::ApiError;
+
+use std::fmt::{Display, Write};
+
+/// Builds the URL of a route of the node
+///
+/// Every path segment and query parameter is percent-encoded, so that no value, however malformed, changes the
+/// route it is placed in. Identifiers, such as the chain name and program IDs, are checked against the
+/// characters they may hold before they are encoded.
+pub(crate) struct UrlBuilder {
+    url: String,
+    has_query: bool,
+}
+
+impl UrlBuilder {
+    // Start a URL at the base URL of the node, followed by the chain
+    pub(crate) fn new(base_url: &str, chain: &str) -> Result<Self, ApiError> {
+        Self { url: base_url.trim_end_matches('/').to_string(), has_query: false }.identifier("chain name", chain)
+    }
+
+    // Append the segments of a route of the node, e.g. `latest/height`
+    pub(crate) fn route(self, route: &str) -> Self {
+        route.split('/').fold(self, Self::segment)
+    }
+
+    // Append a segment, encoding every character that is not unreserved in URLs
+    pub(crate) fn segment(mut self, segment: impl Display) -> Self {
+        self.url.push('/');
+        encode_into(&mut self.url, &segment.to_string(), false);
+        self
+    }
+
+    // Append a segment holding an identifier, which may only hold ASCII letters, digits, `_` and `.`
+    pub(crate) fn identifier(self, item: &str, identifier: impl Display) -> Result<Self, ApiError> {
+        let identifier = identifier.to_string();
+        let is_allowed = |character: char| character.is_ascii_alphanumeric() || matches!(character, '_' | '.');
+        if identifier.is_empty() || !identifier.chars().all(is_allowed) || identifier.chars().all(|c| c == '.') {
+            return Err(ApiError::InvalidIdentifier { item: item.to_string(), value: identifier });
+        }
+        Ok(self.segment(identifier))
+    }
+
+    // Append a query parameter, encoding its value
+    pub(crate) fn param(mut self, name: &str, value: impl Display) -> Self {
+        self.url.push(if self.has_query { '&' } else { '?' });
+        self.has_query = true;
+        encode_into(&mut self.url, name, false);
+        self.url.push('=');
+        encode_into(&mut self.url, &value.to_string(), false);
+        self
+    }
+
+    // Append the path of a raw query, which may carry a query string. Absolute URLs and paths leaving the chain
+    // with `..` segments are rejected, even when percent-encoded. The segments, parameter names and values are
+    // encoded, except for the escapes the path already holds, so that encoded paths are kept.
+    pub(crate) fn path(mut self, path: &str) -> Result<Self, ApiError> {
+        let invalid = |reason: &str| ApiError::InvalidPath { path: path.to_string(), reason: reason.to_string() };
+        if path.contains("://") || path.starts_with("//") || path.contains('\\') {
+            return Err(invalid("absolute URLs are not allowed"));
+        }
+        let (route, query) = match path.split_once('?') {
+            Some((route, query)) => (route, Some(query)),
+            None => (path, None),
+        };
+        let is_traversal =
+            |segment: &str| matches!(segment.to_ascii_lowercase().replace("%2e", ".").as_str(), "." | "..");
+        if route.split(['#', '/']).any(is_traversal) {
+            return Err(invalid("path traversal is not allowed"));
+        }
+        for segment in route.trim_start_matches('/').split('/') {
+            self.url.push('/');
+            encode_into(&mut self.url, segment, true);
+        }
+        for parameter in query.into_iter().flat_map(|query| query.split('&')) {
+            self.url.push(if self.has_query { '&' } else { '?' });
+            self.has_query = true;
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            encode_into(&mut self.url, name, true);
+            if parameter.contains('=') {
+                self.url.push('=');
+                encode_into(&mut self.url, value, true);
+            }
+        }
+        Ok(self)
+    }
+
+    // Returns the URL
+    pub(crate) fn build(self) -> String {
+        self.url
+    }
+}
+
+// Percent-encode the UTF-8 bytes of the text that are not unreserved in URLs, keeping the escapes the text
+// already holds if `keep_escapes` is set
+fn encode_into(url: &mut String, text: &str, keep_escapes: bool) {
+    let bytes = text.as_bytes();
+    for (index, byte) in bytes.iter().enumerate() {
+        let is_escape = *byte == b'%'
+            && bytes.get(index + 1..index + 3).map_or(false, |digits| digits.iter().all(u8::is_ascii_hexdigit));
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => url.push(*byte as char),
+            b'%' if keep_escapes && is_escape => url.push('%'),
+            _ => {
+                let _ = write!(url, "%{byte:02X}");
+            }
+        }
+    }
+}
+
+// Returns `true` if the host of the URL is an onion service, which only a proxy into Tor can reach
+pub(crate) fn is_onion(url: &str) -> bool {
+    let authority = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = authority.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = host.split(':').next().unwrap_or_default();
+    host.trim_end_matches('.').to_ascii_lowercase().ends_with(".onion")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_builder() {
+        let url = |builder: UrlBuilder| builder.build();
+        let base = || UrlBuilder::new("http://node/api/", "testnet3").unwrap();
+        assert_eq!(url(base().route("latest/height")), "http://node/api/testnet3/latest/height");
+
+        // Segments and parameters cannot change the route.
+        assert_eq!(
+            url(base().route("block").segment("1/../../admin")),
+            "http://node/api/testnet3/block/1%2F..%2F..%2Fadmin"
+        );
+        assert_eq!(url(base().segment("a b?c=d#e")), "http://node/api/testnet3/a%20b%3Fc%3Dd%23e");
+        assert_eq!(url(base().segment("ünï")), "http://node/api/testnet3/%C3%BCn%C3%AF");
+        let blocks = base().route("blocks").param("start", 0).param("end", "2&admin=1");
+        assert_eq!(url(blocks), "http://node/api/testnet3/blocks?start=0&end=2%26admin%3D1");
+
+        // Identifiers are checked before they are encoded.
+        assert_eq!(
+            url(base().identifier("program ID", "credits.aleo").unwrap()),
+            "http://node/api/testnet3/credits.aleo"
+        );
+        for identifier in ["", "..", "credits.aleo/../x", "a b", "ünï", "x?y"] {
+            let expected =
+                ApiError::InvalidIdentifier { item: "program ID".to_string(), value: identifier.to_string() };
+            assert_eq!(base().identifier("program ID", identifier).err(), Some(expected));
+        }
+        let error = UrlBuilder::new("http://node", "testnet3/../mainnet").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Invalid chain name 'testnet3/../mainnet': only ASCII letters, digits, '_' and '.' are allowed"
+        );
+
+        // Raw paths keep their escapes and separators, and encode the rest.
+        let path = |path| base().path(path).map(url);
+        assert_eq!(path("/latest/height").unwrap(), "http://node/api/testnet3/latest/height");
+        assert_eq!(path("a b/%2F?x=1 2&y=ü&z").unwrap(), "http://node/api/testnet3/a%20b/%2F?x=1%202&y=%C3%BC&z");
+        assert_eq!(path("a#b").unwrap(), "http://node/api/testnet3/a%23b");
+        assert!(path("a/..#b").is_err());
+    }
+
+    #[test]
+    fn test_is_onion() {
+        assert!(is_onion("http://aleonodeexample.onion:3030"));
+        assert!(is_onion("https://user@AleoNode.Onion./testnet3"));
+        assert!(!is_onion("https://vm.aleo.org/api"));
+        assert!(!is_onion("http://node.example/onion.aleo"));
+    }
+}
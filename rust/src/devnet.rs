@@ -0,0 +1,570 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! A local ledger for hermetic end-to-end tests, which runs a snarkVM VM in memory and serves it as a node.
+//!
+//! A [`LocalLedgerClient`] validates broadcast transactions with the VM, proofs included, and includes each
+//! accepted transaction in a new block, whose finalize commands update the mappings of the ledger. The ledger
+//! is also served over a loopback port, so that a [`ProgramManager`](crate::ProgramManager), a
+//! [`SyncService`](crate::SyncService), or a [`Wallet`](crate::Wallet) run against it through the
+//! [`AleoAPIClient`] returned by [`LocalLedgerClient::api_client`], with no network.
+
+use crate::AleoAPIClient;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::Serialize;
+use snarkvm_console::{
+    account::{Address, PrivateKey, ViewKey},
+    prelude::Zero,
+    program::{Ciphertext, Identifier, Network, Plaintext, ProgramID, Record, Value},
+    types::Field,
+};
+use snarkvm_synthesizer::{
+    Block,
+    ConsensusMemory,
+    ConsensusStore,
+    Header,
+    Metadata,
+    Program,
+    Transaction,
+    Transactions,
+    VM,
+};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// A client of a ledger held in memory, which validates and includes transactions as a node would
+///
+/// The ledger starts with a genesis block minting the starting supply to the genesis account, which funds
+/// other accounts with [`LocalLedgerClient::fund`]. Blocks are only produced when a transaction is accepted, or
+/// when [`LocalLedgerClient::advance_block`] is called, so that tests control the timing of the chain.
+#[derive(Clone)]
+pub struct LocalLedgerClient<N: Network> {
+    ledger: Arc<LocalLedger<N>>,
+    api_client: AleoAPIClient<N>,
+}
+
+// The state of a local ledger, shared by its client and the thread serving it
+struct LocalLedger<N: Network> {
+    vm: VM<N, ConsensusMemory<N>>,
+    private_key: PrivateKey<N>,
+    // The unspent records of the genesis account. Holding the lock also serializes the production of blocks.
+    genesis_records: Mutex<Vec<Record<N, Plaintext<N>>>>,
+}
+
+impl<N: Network> LocalLedgerClient<N> {
+    /// Create a ledger whose genesis block mints the starting supply to the account of the given private key, and
+    /// serve it on a loopback port.
+    pub fn new(genesis_private_key: PrivateKey<N>) -> Result<Self> {
+        let vm = VM::from(ConsensusStore::<N, ConsensusMemory<N>>::open(None)?)?;
+        let genesis = Block::genesis(&vm, &genesis_private_key, &mut rand::thread_rng())?;
+        vm.add_next_block(&genesis)?;
+        let view_key = ViewKey::try_from(genesis_private_key)?;
+        let genesis_records = genesis
+            .transitions()
+            .flat_map(|transition| transition.records())
+            .map(|(_, record)| record.decrypt(&view_key))
+            .collect::<Result<Vec<_>>>()?;
+        let genesis_records = Mutex::new(genesis_records);
+        let ledger = Arc::new(LocalLedger { vm, private_key: genesis_private_key, genesis_records });
+
+        // snarkVM queries the state of the ledger on the `testnet3` routes, whatever the network.
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let api_client = AleoAPIClient::new(&format!("http://{}", listener.local_addr()?), "testnet3");
+        let served = ledger.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                served.respond(stream);
+            }
+        });
+        Ok(Self { ledger, api_client })
+    }
+
+    /// Returns a client of the node serving the ledger, to run a [`crate::ProgramManager`], a
+    /// [`crate::SyncService`], or a [`crate::Wallet`] against it.
+    pub fn api_client(&self) -> &AleoAPIClient<N> {
+        &self.api_client
+    }
+
+    /// Returns the private key of the genesis account.
+    pub fn genesis_private_key(&self) -> &PrivateKey<N> {
+        &self.ledger.private_key
+    }
+
+    /// Returns the height of the latest block.
+    pub fn latest_height(&self) -> u32 {
+        self.ledger.latest_height()
+    }
+
+    /// Returns the block at the given height.
+    pub fn get_block(&self, height: u32) -> Result<Block<N>> {
+        self.ledger.get_block(height)?.ok_or_else(|| anyhow!("Block {height} is not in the ledger"))
+    }
+
+    /// Returns the latest block.
+    pub fn latest_block(&self) -> Result<Block<N>> {
+        self.get_block(self.latest_height())
+    }
+
+    /// Returns the program with the given ID, if it is built in or was deployed.
+    pub fn get_program(&self, program_id: impl TryInto<ProgramID<N>>) -> Result<Program<N>> {
+        let program_id = program_id.try_into().map_err(|_| anyhow!("Invalid program ID"))?;
+        self.ledger.get_program(&program_id).ok_or_else(|| anyhow!("Program {program_id} is not in the ledger"))
+    }
+
+    /// Returns the value stored under `key` in a mapping of the given program, or `None` if the key is unset.
+    pub fn get_mapping_value(
+        &self,
+        program_id: impl TryInto<ProgramID<N>>,
+        mapping_name: &Identifier<N>,
+        key: &Plaintext<N>,
+    ) -> Result<Option<Value<N>>> {
+        let program_id = program_id.try_into().map_err(|_| anyhow!("Invalid program ID"))?;
+        self.ledger.vm.program_store().get_value(&program_id, mapping_name, key)
+    }
+
+    /// Validate the transaction as a node would, and include it in a new block, which is returned.
+    ///
+    /// Transactions with an invalid proof, spending a record that is already spent, or deploying a program that
+    /// already exists are rejected, and leave the ledger unchanged.
+    pub fn transaction_broadcast(&self, transaction: Transaction<N>) -> Result<Block<N>> {
+        self.ledger.broadcast(transaction)
+    }
+
+    /// Produce a block without transactions, e.g. to advance the chain past a confirmation depth.
+    pub fn advance_block(&self) -> Result<Block<N>> {
+        let _genesis_records = self.ledger.genesis_records.lock().map_err(|_| anyhow!("The ledger is poisoned"))?;
+        self.ledger.add_block(vec![])
+    }
+
+    /// Send `amount` gates from the genesis account to the address, in a transaction without a fee that is
+    /// included in a new block, and return the commitment of the record sent to the address and the record.
+    #[allow(clippy::type_complexity)]
+    pub fn fund(&self, address: Address<N>, amount: u64) -> Result<(Field<N>, Record<N, Ciphertext<N>>)> {
+        let ledger = &self.ledger;
+        let mut genesis_records = ledger.genesis_records.lock().map_err(|_| anyhow!("The ledger is poisoned"))?;
+        let index = match genesis_records.iter().position(|record| ***record.gates() >= amount) {
+            Some(index) => index,
+            None => bail!("The genesis account has no unspent record holding {amount} gates"),
+        };
+        let inputs = [
+            Value::Record(genesis_records[index].clone()),
+            Value::from_str(&address.to_string())?,
+            Value::from_str(&format!("{amount}u64"))?,
+        ];
+        let rng = &mut rand::thread_rng();
+        let authorization = ledger.vm.authorize(&ledger.private_key, "credits.aleo", "transfer", inputs.iter(), rng)?;
+        let transaction = Transaction::execute_authorization(&ledger.vm, authorization, None, rng)?;
+        ledger.check_transaction(&transaction)?;
+        ledger.add_block(vec![transaction.clone()])?;
+
+        // The transfer outputs the record of the recipient, then the change of the genesis account.
+        let view_key = ViewKey::try_from(ledger.private_key)?;
+        let mut records = transaction.transitions().flat_map(|transition| transition.records());
+        let (commitment, record) = records.next().ok_or_else(|| anyhow!("The transfer output no record"))?;
+        let change = records.next().ok_or_else(|| anyhow!("The transfer output no change"))?.1.decrypt(&view_key)?;
+        genesis_records[index] = change;
+        Ok((*commitment, record.clone()))
+    }
+}
+
+impl<N: Network> LocalLedger<N> {
+    // Returns the height of the latest block
+    fn latest_height(&self) -> u32 {
+        self.vm.block_store().heights().map(|height| *height).max().unwrap_or_default()
+    }
+
+    // Returns the block at the given height, if there is one
+    fn get_block(&self, height: u32) -> Result<Option<Block<N>>> {
+        match self.vm.block_store().get_block_hash(height)? {
+            Some(block_hash) => self.vm.block_store().get_block(&block_hash),
+            None => Ok(None),
+        }
+    }
+
+    // Returns the program with the given ID, if it is built in or was deployed
+    fn get_program(&self, program_id: &ProgramID<N>) -> Option<Program<N>> {
+        self.vm.process().read().get_program(program_id).ok().cloned()
+    }
+
+    // Validate a transaction against the VM and the state of the ledger, as a node does before accepting it
+    fn check_transaction(&self, transaction: &Transaction<N>) -> Result<()> {
+        let transaction_id = transaction.id();
+        ensure!(
+            self.vm.transaction_store().get_transaction(&transaction_id)?.is_none(),
+            "Transaction '{transaction_id}' already exists in the ledger"
+        );
+        for serial_number in transaction.serial_numbers() {
+            ensure!(
+                !self.vm.transition_store().contains_serial_number(serial_number)?,
+                "Transaction '{transaction_id}' spends a record that is already spent"
+            );
+        }
+        if let Transaction::Deploy(_, deployment, _) = transaction {
+            let program_id = deployment.program_id();
+            ensure!(!self.vm.contains_program(program_id), "Program {program_id} already exists in the ledger");
+        }
+        self.vm.check_transaction(transaction)
+    }
+
+    // Validate a transaction and include it in a new block
+    fn broadcast(&self, transaction: Transaction<N>) -> Result<Block<N>> {
+        let _genesis_records = self.genesis_records.lock().map_err(|_| anyhow!("The ledger is poisoned"))?;
+        self.check_transaction(&transaction)?;
+        self.add_block(vec![transaction])
+    }
+
+    // Produce a block on top of the latest block holding the given transactions, and add it to the ledger. The
+    // caller holds the lock on the genesis records.
+    fn add_block(&self, transactions: Vec<Transaction<N>>) -> Result<Block<N>> {
+        let latest = self.get_block(self.latest_height())?.ok_or_else(|| anyhow!("The ledger has no blocks"))?;
+        let transactions = Transactions::from(&transactions);
+        let metadata = Metadata::new(
+            N::ID,
+            latest.round() + 1,
+            latest.height() + 1,
+            latest.coinbase_target(),
+            latest.proof_target(),
+            latest.last_coinbase_target(),
+            latest.last_coinbase_timestamp(),
+            latest.timestamp() + 1,
+        )?;
+        let previous_state_root = *self.vm.block_store().current_state_root();
+        let header = Header::from(previous_state_root, transactions.to_root()?, Field::zero(), metadata)?;
+        let block = Block::new(&self.private_key, latest.hash(), header, transactions, None, &mut rand::thread_rng())?;
+        self.vm.add_next_block(&block)?;
+        Ok(block)
+    }
+
+    // Answer a request to the node, closing the connection after the response
+    fn respond(&self, mut stream: TcpStream) {
+        let request = match read_request(&stream) {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+        let (status, content_type, body) = match self.route(&request) {
+            Ok(Some(body)) => (200, "application/json", body),
+            Ok(None) => (404, "text/plain", format!("Not found: {}", request.path)),
+            Err(error) => (500, "text/plain", error.to_string()),
+        };
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n\
+             {body}",
+            if status == 200 { "OK" } else { "Error" },
+            body.len()
+        );
+    }
+
+    // Returns the JSON body of the response to a request, or `None` if the route or the item is not found
+    fn route(&self, request: &Request) -> Result<Option<String>> {
+        let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+        let segments = match path.strip_prefix("/testnet3/") {
+            Some(path) => path.split('/').map(decode).collect::<Result<Vec<_>>>()?,
+            None => return Ok(None),
+        };
+        let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+        let block_store = self.vm.block_store();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["transaction", "broadcast"]) => {
+                let transaction = Transaction::<N>::from_str(&request.body)?;
+                json(&self.broadcast(transaction)?)
+            }
+            ("GET", ["latest", "height"]) => json(&self.latest_height()),
+            ("GET", ["latest", "hash"]) => json(&block_store.get_block_hash(self.latest_height())?),
+            ("GET", ["latest", "block"]) => json_or_not_found(self.get_block(self.latest_height())?),
+            ("GET", ["latest", "stateRoot"]) => json(&block_store.current_state_root()),
+            ("GET", ["block", height]) if height.parse::<u32>().is_ok() => {
+                json_or_not_found(self.get_block(parse(height)?)?)
+            }
+            ("GET", ["block", block_hash]) => json_or_not_found(block_store.get_block(&parse(block_hash)?)?),
+            ("GET", ["blocks"]) => {
+                let param = |name: &str| -> Result<u32> {
+                    let value = query.split('&').find_map(|param| param.strip_prefix(name)?.strip_prefix('='));
+                    parse(value.ok_or_else(|| anyhow!("Missing parameter '{name}'"))?)
+                };
+                let (start, end) = (param("start")?, param("end")?.min(self.latest_height() + 1));
+                let blocks = (start..end).map(|height| self.get_block(height)).collect::<Result<Option<Vec<_>>>>()?;
+                json(&blocks.unwrap_or_default())
+            }
+            ("GET", ["height", block_hash]) => json_or_not_found(block_store.get_block_height(&parse(block_hash)?)?),
+            ("GET", ["transaction", transaction_id]) => {
+                json_or_not_found(self.vm.transaction_store().get_transaction(&parse(transaction_id)?)?)
+            }
+            ("GET", ["memoryPool", "transactions"]) => json(&Vec::<Transaction<N>>::new()),
+            ("GET", ["program", program_id]) => json_or_not_found(self.get_program(&parse(program_id)?)),
+            ("GET", ["program", program_id, "mapping", mapping_name, key]) => {
+                let (program_id, mapping_name) = (parse(program_id)?, parse(mapping_name)?);
+                json(&self.vm.program_store().get_value(&program_id, &mapping_name, &parse(key)?)?)
+            }
+            ("GET", ["statePath", commitment]) => {
+                json(&block_store.get_state_path_for_commitment(&parse(commitment)?)?)
+            }
+            ("GET", ["find", "blockHash", transaction_id]) => {
+                json_or_not_found(block_store.find_block_hash(&parse(transaction_id)?)?)
+            }
+            ("GET", ["find", "transitionID", input_or_output_id]) => {
+                json_or_not_found(self.vm.transition_store().find_transition_id(&parse(input_or_output_id)?).ok())
+            }
+            ("GET", ["find", "transactionID", transition_id]) => json_or_not_found(
+                self.vm.transaction_store().find_transaction_id_from_transition_id(&parse(transition_id)?)?,
+            ),
+            _ => Ok(None),
+        }
+    }
+}
+
+// A request to the node serving a local ledger
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+// Read the request line, the headers, and the body of a request
+fn read_request(stream: &TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => bail!("Malformed request line '{}'", request_line.trim_end()),
+    };
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+        }
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Request { method, path, body: String::from_utf8(body)? })
+}
+
+// Decode the percent-encoded segment of a path
+fn decode(segment: &str) -> Result<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match (byte, tail) {
+            (b'%', [high, low, tail @ ..]) => {
+                let hex = std::str::from_utf8(&[*high, *low])?.to_string();
+                bytes.push(u8::from_str_radix(&hex, 16)?);
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+// Parse a segment of a path into the item it identifies
+fn parse<T: FromStr>(segment: &str) -> Result<T> {
+    segment.parse().map_err(|_| anyhow!("Invalid path segment '{segment}'"))
+}
+
+fn json(value: &impl Serialize) -> Result<Option<String>> {
+    Ok(Some(serde_json::to_string(value)?))
+}
+
+fn json_or_not_found(value: Option<impl Serialize>) -> Result<Option<String>> {
+    value.map(|value| serde_json::to_string(&value)).transpose().map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::CurrentNetwork,
+        BlockHeight,
+        CancellationToken,
+        Microcredits,
+        ProgramManager,
+        RecordStore,
+        SyncEvent,
+        SyncService,
+    };
+
+    use snarkvm_console::prelude::TestRng;
+
+    type N = CurrentNetwork;
+
+    const COUNTER_PROGRAM: &str = "program devnet_counter.aleo;
+
+mapping counts:
+    key left as u8.public;
+    value right as u64.public;
+
+function bump:
+    input r0 as u64.public;
+    finalize r0;
+
+finalize bump:
+    input r0 as u64.public;
+    increment counts[0u8] by r0;
+";
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("credits.aleo").unwrap(), "credits.aleo");
+        assert_eq!(decode("aleo1%20key%3Dvalue").unwrap(), "aleo1 key=value");
+        assert!(decode("%zz").is_err());
+    }
+
+    #[test]
+    fn test_local_ledger_transfer() {
+        let rng = &mut TestRng::default();
+        let ledger = LocalLedgerClient::<N>::new(PrivateKey::new(rng).unwrap()).unwrap();
+        assert_eq!(ledger.latest_height(), 0);
+
+        let sender = PrivateKey::<N>::new(rng).unwrap();
+        let sender_view_key = ViewKey::try_from(sender).unwrap();
+        let (_, record) = ledger.fund(Address::try_from(sender).unwrap(), 100).unwrap();
+        let (_, fee_record) = ledger.fund(Address::try_from(sender).unwrap(), 10).unwrap();
+        assert_eq!(ledger.latest_height(), 2);
+
+        // The program manager broadcasts through the node serving the ledger
+        let recipient = PrivateKey::<N>::new(rng).unwrap();
+        let program_manager = ProgramManager::new(sender, ledger.api_client().clone());
+        let record = record.decrypt(&sender_view_key).unwrap();
+        let fee_record = fee_record.decrypt(&sender_view_key).unwrap();
+        let transaction_id = program_manager
+            .transfer(Microcredits(50), Microcredits(1), Address::try_from(recipient).unwrap(), record, fee_record)
+            .unwrap();
+        assert_eq!(ledger.latest_height(), 3);
+        let transaction = ledger.api_client().get_transaction(transaction_id).unwrap();
+        assert_eq!(ledger.latest_block().unwrap().transactions().get(&transaction_id), Some(&transaction));
+
+        let block_heights = BlockHeight(0)..=BlockHeight(3);
+        let records = ledger.api_client().scan(ViewKey::try_from(recipient).unwrap(), block_heights).unwrap();
+        assert_eq!(records.len(), 1);
+        let record = records[0].1.decrypt(&ViewKey::try_from(recipient).unwrap()).unwrap();
+        assert_eq!(***record.gates(), 50);
+
+        // A transaction spending the same records again is rejected, and leaves the ledger unchanged
+        assert!(ledger.transaction_broadcast(transaction).is_err());
+        assert_eq!(ledger.latest_height(), 3);
+        assert_eq!(ledger.advance_block().unwrap().height(), 4);
+    }
+
+    #[test]
+    fn test_local_ledger_pending_records() {
+        let rng = &mut TestRng::default();
+        let ledger = LocalLedgerClient::<N>::new(PrivateKey::new(rng).unwrap()).unwrap();
+        let sender = PrivateKey::<N>::new(rng).unwrap();
+        let (address, view_key) = (Address::try_from(sender).unwrap(), ViewKey::try_from(sender).unwrap());
+        let mut records = RecordStore::new();
+        let mut funded = vec![];
+        for amount in [100, 10] {
+            let (commitment, record) = ledger.fund(address, amount).unwrap();
+            let record = record.decrypt(&view_key).unwrap();
+            records.insert(commitment, record.clone(), ledger.latest_height());
+            funded.push(record);
+        }
+        let mut program_manager = ProgramManager::new(sender, ledger.api_client().clone()).with_record_store(records);
+        let recipient = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+
+        // The change of the transfer and of its fee count towards the balance as soon as it is broadcast.
+        let (input_record, fee_record) = (funded[0].clone(), funded[1].clone());
+        let expected = program_manager
+            .transfer_tracked(Microcredits(60), Microcredits(1), recipient, input_record, fee_record)
+            .unwrap();
+        assert_eq!((expected.gates(), expected.records().len(), expected.spent().len()), (49, 2, 2));
+        let records = program_manager.record_store_mut().unwrap();
+        assert_eq!(records.insert_pending(&expected), 2);
+        assert_eq!((records.balance_with_pending(true), records.balance()), (Microcredits(49), Microcredits(110)));
+
+        // The scan finds the change records and confirms the spends, leaving the balance as it was.
+        let mut service = SyncService::new(ledger.api_client().clone());
+        let id = service.add_account(view_key, BlockHeight(ledger.latest_height())).unwrap();
+        service.watch_transaction(id, expected.transaction_id());
+        let mut confirmed = vec![];
+        service
+            .sync(&CancellationToken::new(), |event| match event {
+                SyncEvent::Record { height, commitment, record, .. } => {
+                    records.insert(commitment, *record, height);
+                }
+                SyncEvent::Confirmed { height, transaction_id, .. } => {
+                    records.confirm_pending(&transaction_id, height);
+                    confirmed.push(transaction_id);
+                }
+                _ => (),
+            })
+            .unwrap();
+        assert_eq!(confirmed, [expected.transaction_id()]);
+        assert_eq!((records.balance_with_pending(true), records.balance()), (Microcredits(49), Microcredits(49)));
+        assert!(records.iter().all(|(_, stored)| stored.pending().is_none() && stored.pending_spend().is_none()));
+        assert_eq!(records.history().len(), 4);
+
+        // A transaction spending a spent record again is rejected, and its pending records are rolled back.
+        let fee_record = records.iter().map(|(_, stored)| stored.record()).find(|record| ***record.gates() == 9);
+        let fee_record = fee_record.unwrap().clone();
+        let transaction = program_manager
+            .build_transfer(Microcredits(10), Microcredits(1), recipient, funded[0].clone(), fee_record)
+            .unwrap();
+        let expected = program_manager.expected_records(&transaction).unwrap();
+        let records = program_manager.record_store_mut().unwrap();
+        assert_eq!(records.insert_pending(&expected), 2);
+        assert_eq!(records.balance_with_pending(true), Microcredits(49 - 9 + 90 + 8));
+        assert!(ledger.transaction_broadcast(transaction).is_err());
+        assert_eq!(records.rollback_pending(&expected.transaction_id()), 2);
+        assert_eq!((records.balance_with_pending(true), records.balance()), (Microcredits(49), Microcredits(49)));
+    }
+
+    #[test]
+    fn test_local_ledger_deploy() {
+        let rng = &mut TestRng::default();
+        let ledger = LocalLedgerClient::<N>::new(PrivateKey::new(rng).unwrap()).unwrap();
+        let owner = PrivateKey::<N>::new(rng).unwrap();
+        let view_key = ViewKey::try_from(owner).unwrap();
+        let (_, deploy_fee) = ledger.fund(Address::try_from(owner).unwrap(), 1_000_000).unwrap();
+        let (_, execute_fee) = ledger.fund(Address::try_from(owner).unwrap(), 10).unwrap();
+
+        let program = Program::<N>::from_str(COUNTER_PROGRAM).unwrap();
+        let program_manager = ProgramManager::new(owner, ledger.api_client().clone());
+        program_manager.deploy(&program, &[], 600_000, deploy_fee.decrypt(&view_key).unwrap()).unwrap();
+        assert_eq!(ledger.get_program("devnet_counter.aleo").unwrap(), program);
+        assert_eq!(ledger.api_client().get_program("devnet_counter.aleo").unwrap(), program);
+
+        // The finalize block of the function updates the mapping when the execution is included
+        let bump = Identifier::from_str("bump").unwrap();
+        let inputs = vec![Value::from_str("3u64").unwrap()];
+        program_manager
+            .execute(&program, &[], bump, inputs, Microcredits(1), execute_fee.decrypt(&view_key).unwrap())
+            .unwrap();
+        let (counts, key) = (Identifier::from_str("counts").unwrap(), Plaintext::from_str("0u8").unwrap());
+        let value = ledger.get_mapping_value("devnet_counter.aleo", &counts, &key).unwrap();
+        assert_eq!(value, Some(Value::from_str("3u64").unwrap()));
+
+        // Deploying the program again is rejected
+        assert!(program_manager.deploy(&program, &[], 600_000, deploy_fee.decrypt(&view_key).unwrap()).is_err());
+    }
+}
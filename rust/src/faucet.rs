@@ -0,0 +1,281 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Funding of accounts on test networks, for end-to-end tests and demos.
+
+use crate::{api::is_not_found, AleoAPIClient, ApiError, BlockHeight, Microcredits, ProgramManager};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use snarkvm_console::{
+    account::{Address, PrivateKey, ViewKey},
+    program::{Ciphertext, Network, Plaintext, Record},
+    types::Field,
+};
+use snarkvm_synthesizer::Transaction;
+use std::time::Duration;
+
+/// Where a [`Faucet`] takes its funds from
+#[derive(Clone, Debug)]
+pub enum FaucetSource<N: Network> {
+    /// A faucet service, which is sent `{"address": ..., "amount": ...}` as JSON and responds with the ID of
+    /// the funding transaction
+    Endpoint(String),
+    /// The private key of the account owning the genesis records of a local devnet, which funds accounts with
+    /// `credits.aleo/transfer` transactions
+    Genesis(PrivateKey<N>),
+}
+
+/// Funds accounts on a test network and waits for the funds to be confirmed
+///
+/// The faucet refuses to operate on mainnet.
+pub struct Faucet<N: Network> {
+    api_client: AleoAPIClient<N>,
+    source: FaucetSource<N>,
+    fee: u64,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+/// A record sent to an account by a [`Faucet`], once its transaction is confirmed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FundingRecord<N: Network> {
+    transaction_id: N::TransactionID,
+    commitment: Field<N>,
+    record: Record<N, Ciphertext<N>>,
+}
+
+impl<N: Network> FundingRecord<N> {
+    /// Returns the ID of the funding transaction
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the commitment of the record
+    pub fn commitment(&self) -> Field<N> {
+        self.commitment
+    }
+
+    /// Returns the record, which the owner of the funded address can decrypt with their view key
+    pub fn record(&self) -> &Record<N, Ciphertext<N>> {
+        &self.record
+    }
+}
+
+#[derive(Serialize)]
+struct FundingRequest<'a, N: Network> {
+    address: &'a Address<N>,
+    amount: u64,
+}
+
+impl<N: Network> Faucet<N> {
+    /// The default fee in gates paid by transfers from the genesis account
+    pub const DEFAULT_FEE: u64 = 1;
+    /// The default interval between checks for the confirmation of a funding transaction
+    pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+    /// The default time to wait for the confirmation of a funding transaction
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Create a faucet funding accounts on the network of the given client
+    pub fn new(api_client: AleoAPIClient<N>, source: FaucetSource<N>) -> Result<Self> {
+        if api_client.chain().eq_ignore_ascii_case("mainnet") {
+            bail!("The faucet cannot be used on mainnet");
+        }
+        Ok(Self {
+            api_client,
+            source,
+            fee: Self::DEFAULT_FEE,
+            poll_interval: Self::DEFAULT_POLL_INTERVAL,
+            timeout: Self::DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Set the fee in gates paid by transfers from the genesis account
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Set the interval between checks for the confirmation of a funding transaction
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Set the time to wait for the confirmation of a funding transaction
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send `amount` gates to the address, and wait until the funding transaction is confirmed
+    ///
+    /// Returns the funding record, which the owner of the address can decrypt and spend immediately.
+    pub fn request_funds(&self, address: Address<N>, amount: u64) -> Result<FundingRecord<N>> {
+        let transaction_id = match &self.source {
+            FaucetSource::Endpoint(url) => self.request_from_endpoint(url, address, amount)?,
+            FaucetSource::Genesis(private_key) => self.transfer_from_genesis(private_key, address, amount)?,
+        };
+        let transaction = self.wait_for_confirmation(transaction_id)?;
+        Self::funding_record(&transaction)
+    }
+
+    // Ask a faucet service to send `amount` gates to the address
+    fn request_from_endpoint(&self, url: &str, address: Address<N>, amount: u64) -> Result<N::TransactionID> {
+        match self.api_client.post_json(url, &FundingRequest { address: &address, amount })? {
+            Ok(transaction_id) => Ok(transaction_id),
+            Err(error) => bail!(ApiError::parse("the faucet transaction ID", error)),
+        }
+    }
+
+    // Transfer `amount` gates from an unspent record of the genesis account, paying the fee with another
+    fn transfer_from_genesis(
+        &self,
+        private_key: &PrivateKey<N>,
+        address: Address<N>,
+        amount: u64,
+    ) -> Result<N::TransactionID> {
+        let view_key = ViewKey::try_from(private_key)?;
+        let latest_height = self.api_client.latest_height()?;
+        let mut records = Vec::new();
+        for (commitment, record) in self.api_client.scan(view_key, BlockHeight::GENESIS..=latest_height)? {
+            let serial_number = Record::<N, Plaintext<N>>::serial_number(*private_key, commitment)?;
+            match self.api_client.find_transition_id(serial_number) {
+                Ok(_) => continue,
+                Err(error) if is_not_found(&error) => records.push(record.decrypt(&view_key)?),
+                Err(error) => return Err(error),
+            }
+        }
+
+        // Fund the transfer with the largest record, and pay the fee with the smallest record that covers it.
+        records.sort_by_key(|record| ***record.gates());
+        let input_record = match records.pop() {
+            Some(record) if ***record.gates() >= amount => record,
+            _ => bail!("The genesis account has no unspent record holding {amount} gates"),
+        };
+        let fee_record = match records.into_iter().find(|record| ***record.gates() >= self.fee) {
+            Some(record) => record,
+            None => bail!("The genesis account has no other unspent record to pay a fee of {} gates", self.fee),
+        };
+        let program_manager = ProgramManager::new(*private_key, self.api_client.clone());
+        program_manager.transfer(Microcredits(amount), Microcredits(self.fee), address, input_record, fee_record)
+    }
+
+    // Poll the network until the transaction is final at the confirmation depth of the client
+    fn wait_for_confirmation(&self, transaction_id: N::TransactionID) -> Result<Transaction<N>> {
+        self.api_client.wait_for_confirmation(transaction_id, self.timeout, self.poll_interval, |_| ())?;
+        self.api_client.get_transaction(transaction_id)
+    }
+
+    // Returns the record sent to the recipient, which is the first output of `credits.aleo/transfer`
+    fn funding_record(transaction: &Transaction<N>) -> Result<FundingRecord<N>> {
+        let transfer = transaction.transitions().find(|transition| {
+            transition.program_id().to_string() == "credits.aleo"
+                && transition.function_name().to_string() == "transfer"
+        });
+        match transfer.and_then(|transition| transition.records().next()) {
+            Some((commitment, record)) => {
+                Ok(FundingRecord { transaction_id: transaction.id(), commitment: *commitment, record: record.clone() })
+            }
+            None => bail!("Transaction '{}' does not transfer credits", transaction.id()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_output,
+            sample_transaction,
+            sample_transition,
+            CurrentNetwork,
+            MockResponse,
+            MockServer,
+        },
+        testnet3,
+    };
+    use snarkvm_utilities::TestRng;
+
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    type N = CurrentNetwork;
+
+    // Start a mock faucet and node, which confirms the funding transaction in the genesis block after `pending` polls
+    fn mock_faucet(transaction: &Transaction<N>, pending: usize) -> MockServer {
+        let transaction_id = transaction.id();
+        let transaction = transaction.to_string();
+        let genesis = genesis_block().to_string();
+        let polls = Arc::new(AtomicUsize::new(0));
+        MockServer::start(move |request| match request.path.as_str() {
+            "/faucet" => Some(MockResponse::json(format!("\"{transaction_id}\""))),
+            path if path == format!("/testnet3/find/blockHash/{transaction_id}") => {
+                match polls.fetch_add(1, Ordering::SeqCst) < pending {
+                    true => Some(MockResponse::text(404, "Transaction not found")),
+                    false => Some(MockResponse::json(format!("\"{}\"", genesis_block().hash()))),
+                }
+            }
+            path if path == format!("/testnet3/transaction/{transaction_id}") => Some(MockResponse::json(&transaction)),
+            // The block is read for its aborted transactions once the transaction is found.
+            "/testnet3/block/0" => Some(MockResponse::json(&genesis)),
+            path if path.starts_with("/testnet3/height/") || path == "/testnet3/latest/height" => {
+                Some(MockResponse::json(0))
+            }
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_faucet_request_funds() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let (view_key, address) = (ViewKey::try_from(&private_key).unwrap(), Address::try_from(&private_key).unwrap());
+        let change_owner = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let outputs = [sample_output(address, 500, rng), sample_output(change_owner, 1_000, rng)];
+        let transaction = sample_transaction([sample_transition(&[], &outputs, rng)]);
+
+        // The faucet waits for the confirmation, and returns the record sent to the fresh account.
+        let server = mock_faucet(&transaction, 2);
+        let source = FaucetSource::Endpoint(format!("{}/faucet", server.base_url()));
+        let faucet = Faucet::new(testnet3(server.base_url()), source).unwrap();
+        let faucet = faucet.with_poll_interval(Duration::from_millis(10));
+        let funding = faucet.request_funds(address, 500).unwrap();
+        assert_eq!(funding.transaction_id(), transaction.id());
+        assert_eq!(funding.commitment(), outputs[0].0);
+        assert!(funding.record().is_owner(&view_key));
+        assert_eq!(***funding.record().decrypt(&view_key).unwrap().gates(), 500);
+
+        // Transactions that are never confirmed time out.
+        let server = mock_faucet(&transaction, usize::MAX);
+        let source = FaucetSource::Endpoint(format!("{}/faucet", server.base_url()));
+        let faucet = Faucet::new(testnet3(server.base_url()), source).unwrap();
+        let faucet = faucet.with_poll_interval(Duration::from_millis(10)).with_timeout(Duration::from_millis(50));
+        let error = faucet.request_funds(address, 500).unwrap_err();
+        assert_eq!(error.to_string(), format!("Transaction '{}' was not confirmed within 50ms", transaction.id()));
+    }
+
+    #[test]
+    fn test_faucet_refuses_mainnet() {
+        let source = FaucetSource::<N>::Endpoint("http://127.0.0.1:9/faucet".to_string());
+        let error = Faucet::new(AleoAPIClient::new("http://127.0.0.1:9", "mainnet"), source).err().unwrap();
+        assert_eq!(error.to_string(), "The faucet cannot be used on mainnet");
+    }
+}
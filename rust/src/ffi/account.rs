@@ -0,0 +1,183 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use snarkvm_console::account::{Address, PrivateKey, ViewKey};
+
+use std::{os::raw::c_char, str::FromStr};
+
+/// An Aleo account: a private key with its derived view key and address
+pub struct AleoAccount {
+    pub(crate) private_key: PrivateKey<CurrentNetwork>,
+    pub(crate) view_key: ViewKey<CurrentNetwork>,
+    pub(crate) address: Address<CurrentNetwork>,
+}
+
+impl AleoAccount {
+    fn from_private_key(private_key: PrivateKey<CurrentNetwork>) -> Result<Self, FfiError> {
+        let view_key = ViewKey::try_from(&private_key).map_err(FfiError::failure)?;
+        let address = Address::try_from(&view_key).map_err(FfiError::failure)?;
+        Ok(Self { private_key, view_key, address })
+    }
+}
+
+/// Generate a new random account and write it to `out`.
+///
+/// # Safety
+///
+/// `out` must be null or point to writable memory for an `AleoAccount*`. The account must be
+/// released with `aleo_account_free`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_account_new(out: *mut *mut AleoAccount) -> AleoErrorCode {
+    ffi_guard(|| {
+        let private_key = PrivateKey::new(&mut rand::thread_rng()).map_err(FfiError::failure)?;
+        write_object(out, AleoAccount::from_private_key(private_key)?)
+    })
+}
+
+/// Import an account from a private key string and write it to `out`.
+///
+/// # Safety
+///
+/// `private_key` must be null or a NUL-terminated string. `out` must be null or point to writable
+/// memory for an `AleoAccount*`. The account must be released with `aleo_account_free`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_account_from_private_key(
+    private_key: *const c_char,
+    out: *mut *mut AleoAccount,
+) -> AleoErrorCode {
+    ffi_guard(|| {
+        let private_key = PrivateKey::from_str(read_str(private_key, "private_key")?)
+            .map_err(|_| FfiError::invalid_argument("Invalid private key"))?;
+        write_object(out, AleoAccount::from_private_key(private_key)?)
+    })
+}
+
+/// Write the private key of the account to `out` as a string.
+///
+/// # Safety
+///
+/// `account` must be null or a live account. `out` must be null or point to writable memory for a
+/// `char*`. The string must be released with `aleo_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_account_private_key(account: *const AleoAccount, out: *mut *mut c_char) -> AleoErrorCode {
+    ffi_guard(|| write_string(out, read_object(account, "account")?.private_key.to_string()))
+}
+
+/// Write the view key of the account to `out` as a string.
+///
+/// # Safety
+///
+/// `account` must be null or a live account. `out` must be null or point to writable memory for a
+/// `char*`. The string must be released with `aleo_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_account_view_key(account: *const AleoAccount, out: *mut *mut c_char) -> AleoErrorCode {
+    ffi_guard(|| write_string(out, read_object(account, "account")?.view_key.to_string()))
+}
+
+/// Write the address of the account to `out` as a string.
+///
+/// # Safety
+///
+/// `account` must be null or a live account. `out` must be null or point to writable memory for a
+/// `char*`. The string must be released with `aleo_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_account_address(account: *const AleoAccount, out: *mut *mut c_char) -> AleoErrorCode {
+    ffi_guard(|| write_string(out, read_object(account, "account")?.address.to_string()))
+}
+
+/// Release an account. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `account` must be null or an account returned by this library that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_account_free(account: *mut AleoAccount) {
+    free_object(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::tests::{last_error, take_string};
+
+    use std::{ffi::CString, ptr};
+
+    #[test]
+    fn test_ffi_account_lifecycle() {
+        let mut account = ptr::null_mut();
+        assert_eq!(unsafe { aleo_account_new(&mut account) }, AleoErrorCode::Ok);
+        assert!(!account.is_null());
+
+        let mut private_key = ptr::null_mut();
+        let mut view_key = ptr::null_mut();
+        let mut address = ptr::null_mut();
+        unsafe {
+            assert_eq!(aleo_account_private_key(account, &mut private_key), AleoErrorCode::Ok);
+            assert_eq!(aleo_account_view_key(account, &mut view_key), AleoErrorCode::Ok);
+            assert_eq!(aleo_account_address(account, &mut address), AleoErrorCode::Ok);
+            aleo_account_free(account);
+        }
+        let private_key = take_string(private_key);
+        let view_key = take_string(view_key);
+        let address = take_string(address);
+
+        // Re-importing the private key must yield the same view key and address.
+        let private_key = CString::new(private_key).unwrap();
+        let mut imported = ptr::null_mut();
+        let mut imported_view_key = ptr::null_mut();
+        let mut imported_address = ptr::null_mut();
+        unsafe {
+            assert_eq!(aleo_account_from_private_key(private_key.as_ptr(), &mut imported), AleoErrorCode::Ok);
+            assert_eq!(aleo_account_view_key(imported, &mut imported_view_key), AleoErrorCode::Ok);
+            assert_eq!(aleo_account_address(imported, &mut imported_address), AleoErrorCode::Ok);
+            aleo_account_free(imported);
+        }
+        assert_eq!(take_string(imported_view_key), view_key);
+        assert_eq!(take_string(imported_address), address);
+    }
+
+    #[test]
+    fn test_ffi_account_error_paths() {
+        // An invalid private key is rejected and the out parameter is left untouched.
+        let invalid = CString::new("APrivateKey1invalid").unwrap();
+        let mut account = ptr::null_mut();
+        assert_eq!(
+            unsafe { aleo_account_from_private_key(invalid.as_ptr(), &mut account) },
+            AleoErrorCode::InvalidArgument
+        );
+        assert!(account.is_null());
+        assert_eq!(last_error(), "Invalid private key");
+
+        // Null arguments are reported rather than dereferenced.
+        assert_eq!(unsafe { aleo_account_from_private_key(ptr::null(), &mut account) }, AleoErrorCode::NullPointer);
+        assert!(last_error().contains("private_key"));
+        let mut address = ptr::null_mut();
+        assert_eq!(unsafe { aleo_account_address(ptr::null(), &mut address) }, AleoErrorCode::NullPointer);
+        assert_eq!(unsafe { aleo_account_new(ptr::null_mut()) }, AleoErrorCode::NullPointer);
+
+        // Invalid UTF-8 is rejected.
+        let bytes = [0xffu8, 0xfe, 0x00];
+        assert_eq!(
+            unsafe { aleo_account_from_private_key(bytes.as_ptr() as *const c_char, &mut account) },
+            AleoErrorCode::InvalidUtf8
+        );
+
+        // Freeing null is a no-op.
+        unsafe { aleo_account_free(ptr::null_mut()) };
+    }
+}
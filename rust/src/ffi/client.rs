@@ -0,0 +1,193 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use crate::{AleoAPIClient, BlockHeight};
+
+use snarkvm_console::account::ViewKey;
+use snarkvm_synthesizer::Transaction;
+
+use std::{os::raw::c_char, str::FromStr};
+
+/// A client connected to an Aleo node
+pub struct AleoClient {
+    pub(crate) client: AleoAPIClient<CurrentNetwork>,
+}
+
+/// Create a client for the node at `base_url` and write it to `out`.
+///
+/// # Safety
+///
+/// `base_url` must be null or a NUL-terminated string. `out` must be null or point to writable memory
+/// for an `AleoClient*`. The client must be released with `aleo_client_free`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_client_new(base_url: *const c_char, out: *mut *mut AleoClient) -> AleoErrorCode {
+    ffi_guard(|| {
+        let base_url = read_str(base_url, "base_url")?;
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(FfiError::invalid_argument("Base URL must start with http:// or https://"));
+        }
+        write_object(out, AleoClient { client: crate::testnet3(base_url) })
+    })
+}
+
+/// Write the latest block height known to the node to `out`.
+///
+/// # Safety
+///
+/// `client` must be null or a live client. `out` must be null or point to a writable `uint32_t`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_client_latest_height(client: *const AleoClient, out: *mut u32) -> AleoErrorCode {
+    ffi_guard(|| {
+        let client = read_object(client, "client")?;
+        if out.is_null() {
+            return Err(FfiError::new(AleoErrorCode::NullPointer, "Output argument is null"));
+        }
+        *out = client.client.latest_height().map_err(FfiError::network)?.0;
+        Ok(())
+    })
+}
+
+/// Scan blocks `start_height` (inclusive) to `end_height` (exclusive) for records owned by the view key.
+///
+/// The result is written to `out` as a JSON array of `{"commitment": "...", "record": "..."}` objects,
+/// where each record is the ciphertext string.
+///
+/// # Safety
+///
+/// `client` must be null or a live client. `view_key` must be null or a NUL-terminated string. `out`
+/// must be null or point to writable memory for a `char*`. The string must be released with
+/// `aleo_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_client_scan(
+    client: *const AleoClient,
+    view_key: *const c_char,
+    start_height: u32,
+    end_height: u32,
+    out: *mut *mut c_char,
+) -> AleoErrorCode {
+    ffi_guard(|| {
+        let client = read_object(client, "client")?;
+        let view_key = ViewKey::<CurrentNetwork>::from_str(read_str(view_key, "view_key")?)
+            .map_err(|_| FfiError::invalid_argument("Invalid view key"))?;
+        if start_height >= end_height {
+            return Err(FfiError::invalid_argument("Start height must be less than end height"));
+        }
+
+        let block_heights = BlockHeight(start_height)..BlockHeight(end_height);
+        let records = client.client.scan(view_key, block_heights).map_err(FfiError::network)?;
+        let records = records
+            .into_iter()
+            .map(|(commitment, record)| {
+                serde_json::json!({ "commitment": commitment.to_string(), "record": record.to_string() })
+            })
+            .collect::<Vec<_>>();
+        write_string(out, serde_json::Value::Array(records).to_string())
+    })
+}
+
+/// Broadcast a JSON-encoded transaction and write its transaction ID to `out`.
+///
+/// # Safety
+///
+/// `client` must be null or a live client. `transaction` must be null or a NUL-terminated string.
+/// `out` must be null or point to writable memory for a `char*`. The string must be released with
+/// `aleo_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_client_broadcast(
+    client: *const AleoClient,
+    transaction: *const c_char,
+    out: *mut *mut c_char,
+) -> AleoErrorCode {
+    ffi_guard(|| {
+        let client = read_object(client, "client")?;
+        let transaction = Transaction::<CurrentNetwork>::from_str(read_str(transaction, "transaction")?)
+            .map_err(|_| FfiError::invalid_argument("Invalid transaction"))?;
+        let transaction_id = transaction.id();
+        client.client.transaction_broadcast(transaction).map_err(FfiError::network)?;
+        write_string(out, transaction_id.to_string())
+    })
+}
+
+/// Release a client. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `client` must be null or a client returned by this library that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_client_free(client: *mut AleoClient) {
+    free_object(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::{aleo_account_free, aleo_account_new, aleo_account_view_key, tests::last_error};
+
+    use std::{ffi::CString, ptr};
+
+    #[test]
+    fn test_ffi_client_lifecycle_and_errors() {
+        // Clients are only created for HTTP(S) endpoints.
+        let mut client = ptr::null_mut();
+        let invalid_url = CString::new("vm.aleo.org/api").unwrap();
+        assert_eq!(unsafe { aleo_client_new(invalid_url.as_ptr(), &mut client) }, AleoErrorCode::InvalidArgument);
+        assert!(client.is_null());
+
+        // Point the client at a closed local port so that no request can succeed.
+        let base_url = CString::new("http://127.0.0.1:9").unwrap();
+        assert_eq!(unsafe { aleo_client_new(base_url.as_ptr(), &mut client) }, AleoErrorCode::Ok);
+        assert!(!client.is_null());
+
+        // Network failures are reported with the network code.
+        let mut height = 0u32;
+        assert_eq!(unsafe { aleo_client_latest_height(client, &mut height) }, AleoErrorCode::Network);
+        assert!(!last_error().is_empty());
+
+        // Invalid view keys and ranges are rejected before any request is made.
+        let mut output = ptr::null_mut();
+        let invalid_view_key = CString::new("AViewKey1invalid").unwrap();
+        assert_eq!(
+            unsafe { aleo_client_scan(client, invalid_view_key.as_ptr(), 0, 50, &mut output) },
+            AleoErrorCode::InvalidArgument
+        );
+        assert_eq!(last_error(), "Invalid view key");
+
+        let mut account = ptr::null_mut();
+        let mut view_key = ptr::null_mut();
+        unsafe {
+            assert_eq!(aleo_account_new(&mut account), AleoErrorCode::Ok);
+            assert_eq!(aleo_account_view_key(account, &mut view_key), AleoErrorCode::Ok);
+            assert_eq!(aleo_client_scan(client, view_key, 10, 10, &mut output), AleoErrorCode::InvalidArgument);
+            assert_eq!(aleo_client_scan(ptr::null(), view_key, 0, 10, &mut output), AleoErrorCode::NullPointer);
+            crate::ffi::aleo_string_free(view_key);
+            aleo_account_free(account);
+        }
+        assert!(output.is_null());
+
+        // Malformed transactions are rejected before broadcasting.
+        let transaction = CString::new("{}").unwrap();
+        assert_eq!(
+            unsafe { aleo_client_broadcast(client, transaction.as_ptr(), &mut output) },
+            AleoErrorCode::InvalidArgument
+        );
+
+        unsafe {
+            aleo_client_free(client);
+            aleo_client_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,242 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! C ABI for using the Aleo SDK from other languages.
+//!
+//! Every function returns an [`AleoErrorCode`]. When the code is not `Ok`, a description of the
+//! failure can be retrieved with [`aleo_last_error_message`] on the same thread.
+//!
+//! Memory rules:
+//! - Objects are handed out as opaque pointers written to an `out` parameter. Each object must be
+//!   released exactly once with its matching free function (`aleo_account_free`, `aleo_client_free`).
+//! - Strings returned by the library are NUL-terminated UTF-8 and owned by the caller, who must
+//!   release them with `aleo_string_free`. Strings passed into the library remain owned by the caller
+//!   and are only read for the duration of the call.
+//! - `out` parameters are only written on success.
+//! - Passing a pointer that was not produced by this library, or one that was already freed, is
+//!   undefined behavior.
+//!
+//! A static library can be produced with `cargo rustc --release --features ffi --crate-type staticlib`,
+//! and the header checked in at `include/aleo.h` is regenerated with `scripts/generate-header.sh`.
+
+mod account;
+pub use account::*;
+
+mod client;
+pub use client::*;
+
+mod transfer;
+pub use transfer::*;
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+pub type CurrentNetwork = snarkvm_console::network::Testnet3;
+
+/// Status codes returned by every FFI function
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AleoErrorCode {
+    /// The call succeeded
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// A string argument was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// An argument could not be parsed or was rejected
+    InvalidArgument = 3,
+    /// The request to the Aleo node failed
+    Network = 4,
+    /// The operation failed for another reason
+    Failure = 5,
+    /// The library panicked; the panic was caught at the boundary
+    Panic = 6,
+}
+
+// An error raised inside an FFI call, carrying the code to return and the message to store
+pub(crate) struct FfiError {
+    code: AleoErrorCode,
+    message: String,
+}
+
+impl FfiError {
+    pub(crate) fn new(code: AleoErrorCode, message: impl ToString) -> Self {
+        Self { code, message: message.to_string() }
+    }
+
+    pub(crate) fn invalid_argument(message: impl ToString) -> Self {
+        Self::new(AleoErrorCode::InvalidArgument, message)
+    }
+
+    pub(crate) fn network(message: impl ToString) -> Self {
+        Self::new(AleoErrorCode::Network, message)
+    }
+
+    pub(crate) fn failure(message: impl ToString) -> Self {
+        Self::new(AleoErrorCode::Failure, message)
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+// Record the message of the most recent error on this thread
+fn set_last_error(message: &str) {
+    // Interior NUL bytes cannot be represented in a C string, so they are stripped.
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+// Run the body of an FFI function, converting errors and panics into error codes
+pub(crate) fn ffi_guard(body: impl FnOnce() -> Result<(), FfiError>) -> AleoErrorCode {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => AleoErrorCode::Ok,
+        Ok(Err(error)) => {
+            set_last_error(&error.message);
+            error.code
+        }
+        Err(panic) => {
+            let message = match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+                (Some(message), _) => message.to_string(),
+                (_, Some(message)) => message.clone(),
+                _ => "Unknown panic".to_string(),
+            };
+            set_last_error(&format!("Panic caught at the FFI boundary: {message}"));
+            AleoErrorCode::Panic
+        }
+    }
+}
+
+// Read a borrowed UTF-8 string argument.
+// The pointer must be null or refer to a NUL-terminated string that outlives the returned borrow.
+pub(crate) unsafe fn read_str<'a>(pointer: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    if pointer.is_null() {
+        return Err(FfiError::new(AleoErrorCode::NullPointer, format!("Argument '{name}' is null")));
+    }
+    CStr::from_ptr(pointer)
+        .to_str()
+        .map_err(|_| FfiError::new(AleoErrorCode::InvalidUtf8, format!("Argument '{name}' is not valid UTF-8")))
+}
+
+// Borrow an opaque object argument.
+// The pointer must be null or have been produced by `write_object` and not yet freed.
+pub(crate) unsafe fn read_object<'a, T>(pointer: *const T, name: &str) -> Result<&'a T, FfiError> {
+    pointer.as_ref().ok_or_else(|| FfiError::new(AleoErrorCode::NullPointer, format!("Argument '{name}' is null")))
+}
+
+// Write an owned string to an out parameter, transferring ownership to the caller.
+// The pointer must be null or writable.
+pub(crate) unsafe fn write_string(out: *mut *mut c_char, value: String) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError::new(AleoErrorCode::NullPointer, "Output argument is null"));
+    }
+    let value = CString::new(value).map_err(|_| FfiError::failure("Output contains an interior NUL byte"))?;
+    *out = value.into_raw();
+    Ok(())
+}
+
+// Write an owned object to an out parameter, transferring ownership to the caller.
+// The pointer must be null or writable.
+pub(crate) unsafe fn write_object<T>(out: *mut *mut T, value: T) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError::new(AleoErrorCode::NullPointer, "Output argument is null"));
+    }
+    *out = Box::into_raw(Box::new(value));
+    Ok(())
+}
+
+// Release an object previously handed out by `write_object`.
+// The pointer must be null or have been produced by `write_object` and not yet freed.
+pub(crate) unsafe fn free_object<T>(pointer: *mut T) {
+    if !pointer.is_null() {
+        drop(Box::from_raw(pointer));
+    }
+}
+
+/// Copy the message of the last error raised on this thread into `out`.
+///
+/// Returns `InvalidArgument` when no error has occurred. The string must be released with
+/// `aleo_string_free`.
+///
+/// # Safety
+///
+/// `out` must be null or point to writable memory for a `char*`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_last_error_message(out: *mut *mut c_char) -> AleoErrorCode {
+    if out.is_null() {
+        return AleoErrorCode::NullPointer;
+    }
+    match LAST_ERROR.with(|last_error| last_error.borrow().clone()) {
+        Some(message) => {
+            *out = message.into_raw();
+            AleoErrorCode::Ok
+        }
+        None => AleoErrorCode::InvalidArgument,
+    }
+}
+
+/// Release a string returned by this library. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by this library that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use std::ptr;
+
+    // Take ownership of a string returned through the C ABI
+    pub(crate) fn take_string(string: *mut c_char) -> String {
+        let value = unsafe { CStr::from_ptr(string) }.to_str().unwrap().to_string();
+        unsafe { aleo_string_free(string) };
+        value
+    }
+
+    // Fetch the last error message through the C ABI
+    pub(crate) fn last_error() -> String {
+        let mut message = ptr::null_mut();
+        assert_eq!(unsafe { aleo_last_error_message(&mut message) }, AleoErrorCode::Ok);
+        take_string(message)
+    }
+
+    #[test]
+    fn test_ffi_guard_catches_panics() {
+        let code = ffi_guard(|| panic!("boom"));
+        assert_eq!(code, AleoErrorCode::Panic);
+        assert!(last_error().contains("boom"));
+    }
+
+    #[test]
+    fn test_ffi_null_out_parameter() {
+        unsafe {
+            assert_eq!(aleo_last_error_message(ptr::null_mut()), AleoErrorCode::NullPointer);
+            aleo_string_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use crate::{Microcredits, ProgramManager};
+
+use snarkvm_console::{
+    account::Address,
+    program::{Ciphertext, Plaintext, Record},
+};
+
+use std::{os::raw::c_char, str::FromStr};
+
+// Parse a plaintext record argument
+unsafe fn read_record(
+    pointer: *const c_char,
+    name: &str,
+) -> Result<Record<CurrentNetwork, Plaintext<CurrentNetwork>>, FfiError> {
+    Record::from_str(read_str(pointer, name)?)
+        .map_err(|_| FfiError::invalid_argument(format!("Argument '{name}' is not a valid plaintext record")))
+}
+
+/// Decrypt a record ciphertext owned by the account and write the plaintext record to `out`.
+///
+/// # Safety
+///
+/// `account` must be null or a live account. `ciphertext` must be null or a NUL-terminated string.
+/// `out` must be null or point to writable memory for a `char*`. The string must be released with
+/// `aleo_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn aleo_record_decrypt(
+    account: *const AleoAccount,
+    ciphertext: *const c_char,
+    out: *mut *mut c_char,
+) -> AleoErrorCode {
+    ffi_guard(|| {
+        let account = read_object(account, "account")?;
+        let ciphertext = read_str(ciphertext, "ciphertext")?;
+        let ciphertext = Record::<CurrentNetwork, Ciphertext<CurrentNetwork>>::from_str(ciphertext)
+            .map_err(|_| FfiError::invalid_argument("Invalid record ciphertext"))?;
+        let plaintext = ciphertext
+            .decrypt(&account.view_key)
+            .map_err(|_| FfiError::invalid_argument("The record is not owned by the account"))?;
+        write_string(out, plaintext.to_string())
+    })
+}
+
+/// Build a transfer of `amount` gates from the account to `recipient` and write the JSON-encoded
+/// transaction to `out`.
+///
+/// The transfer is funded by the plaintext record `input_record`, and the fee of `fee` gates is paid
+/// by the plaintext record `fee_record`. The transaction is not broadcast; pass it to
+/// `aleo_client_broadcast` to submit it.
+///
+/// # Safety
+///
+/// `client` and `account` must be null or live objects. `recipient`, `input_record`, and `fee_record`
+/// must be null or NUL-terminated strings. `out` must be null or point to writable memory for a
+/// `char*`. The string must be released with `aleo_string_free`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn aleo_transfer_build(
+    client: *const AleoClient,
+    account: *const AleoAccount,
+    recipient: *const c_char,
+    amount: u64,
+    fee: u64,
+    input_record: *const c_char,
+    fee_record: *const c_char,
+    out: *mut *mut c_char,
+) -> AleoErrorCode {
+    ffi_guard(|| {
+        let client = read_object(client, "client")?;
+        let account = read_object(account, "account")?;
+        let recipient = Address::<CurrentNetwork>::from_str(read_str(recipient, "recipient")?)
+            .map_err(|_| FfiError::invalid_argument("Invalid recipient address"))?;
+        let input_record = read_record(input_record, "input_record")?;
+        let fee_record = read_record(fee_record, "fee_record")?;
+
+        let program_manager = ProgramManager::new(account.private_key, client.client.clone());
+        let transaction = program_manager
+            .build_transfer(Microcredits(amount), Microcredits(fee), recipient, input_record, fee_record)
+            .map_err(FfiError::failure)?;
+        write_string(out, transaction.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ffi::tests::{last_error, take_string},
+        test_helpers::sample_record,
+    };
+    use snarkvm_utilities::TestRng;
+
+    use std::{ffi::CString, ptr};
+
+    #[test]
+    fn test_ffi_record_decrypt() {
+        let mut account = ptr::null_mut();
+        assert_eq!(unsafe { aleo_account_new(&mut account) }, AleoErrorCode::Ok);
+        let owner = unsafe { &*account }.address;
+
+        // Decrypt a record owned by the account.
+        let (plaintext, ciphertext) = sample_record(owner, 100, &mut TestRng::default());
+        let ciphertext = CString::new(ciphertext.to_string()).unwrap();
+        let mut output = ptr::null_mut();
+        assert_eq!(unsafe { aleo_record_decrypt(account, ciphertext.as_ptr(), &mut output) }, AleoErrorCode::Ok);
+        assert_eq!(take_string(output), plaintext.to_string());
+
+        // A record owned by another account cannot be decrypted.
+        let mut other = ptr::null_mut();
+        assert_eq!(unsafe { aleo_account_new(&mut other) }, AleoErrorCode::Ok);
+        let mut output = ptr::null_mut();
+        assert_eq!(
+            unsafe { aleo_record_decrypt(other, ciphertext.as_ptr(), &mut output) },
+            AleoErrorCode::InvalidArgument
+        );
+        assert_eq!(last_error(), "The record is not owned by the account");
+
+        unsafe {
+            aleo_account_free(account);
+            aleo_account_free(other);
+        }
+    }
+
+    #[test]
+    fn test_ffi_transfer_rejects_invalid_arguments() {
+        let mut client = ptr::null_mut();
+        let mut account = ptr::null_mut();
+        let base_url = CString::new("http://127.0.0.1:9").unwrap();
+        unsafe {
+            assert_eq!(aleo_client_new(base_url.as_ptr(), &mut client), AleoErrorCode::Ok);
+            assert_eq!(aleo_account_new(&mut account), AleoErrorCode::Ok);
+        }
+
+        let mut address = ptr::null_mut();
+        assert_eq!(unsafe { aleo_account_address(account, &mut address) }, AleoErrorCode::Ok);
+        let record = CString::new("{ owner: aleo1.private }").unwrap();
+        let mut output = ptr::null_mut();
+
+        // A malformed recipient is rejected.
+        let invalid_recipient = CString::new("aleo1invalid").unwrap();
+        let code = unsafe {
+            aleo_transfer_build(
+                client,
+                account,
+                invalid_recipient.as_ptr(),
+                1,
+                1,
+                record.as_ptr(),
+                record.as_ptr(),
+                &mut output,
+            )
+        };
+        assert_eq!(code, AleoErrorCode::InvalidArgument);
+        assert_eq!(last_error(), "Invalid recipient address");
+
+        // A malformed record is rejected.
+        let code = unsafe {
+            aleo_transfer_build(client, account, address, 1, 1, record.as_ptr(), record.as_ptr(), &mut output)
+        };
+        assert_eq!(code, AleoErrorCode::InvalidArgument);
+        assert!(last_error().contains("input_record"));
+
+        // Missing objects are reported.
+        let code = unsafe {
+            aleo_transfer_build(ptr::null(), account, address, 1, 1, record.as_ptr(), record.as_ptr(), &mut output)
+        };
+        assert_eq!(code, AleoErrorCode::NullPointer);
+        assert!(output.is_null());
+
+        // Decrypting a malformed ciphertext is rejected.
+        assert_eq!(
+            unsafe { aleo_record_decrypt(account, record.as_ptr(), &mut output) },
+            AleoErrorCode::InvalidArgument
+        );
+
+        unsafe {
+            aleo_string_free(address);
+            aleo_account_free(account);
+            aleo_client_free(client);
+        }
+    }
+}
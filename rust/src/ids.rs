@@ -0,0 +1,319 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Typed identifiers and amounts of the API
+//!
+//! Heights, rounds, transaction indices and amounts are all plain integers on the wire, so a value of one kind
+//! passed where another is expected used to go unnoticed. The API takes them as distinct types instead, which
+//! convert from and into their integers, and serialize as the plain integers they hold, so that mixing them up
+//! fails to compile
+//!
+//! ```compile_fail
+//! use aleo_rust::{snarkvm::Testnet3, AleoAPIClient, BlockHeight, TxIndex};
+//!
+//! fn first_transaction(api_client: &AleoAPIClient<Testnet3>, height: BlockHeight, index: TxIndex) {
+//!     api_client.get_block_transaction(index, height).unwrap();
+//! }
+//! ```
+//!
+//! while each in its place compiles:
+//!
+//! ```no_run
+//! use aleo_rust::{snarkvm::Testnet3, AleoAPIClient, BlockHeight, TxIndex};
+//!
+//! fn first_transaction(api_client: &AleoAPIClient<Testnet3>, height: BlockHeight, index: TxIndex) {
+//!     api_client.get_block_transaction(height, index).unwrap();
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    str::FromStr,
+};
+#[cfg(not(feature = "wasm"))]
+use std::ops::Range;
+
+// Implement the conversions from and into the integer a type holds, and its formatting and parsing as that integer
+macro_rules! integer_newtype {
+    ($($name:ident($integer:ty)),* $(,)?) => {
+        $(
+            impl From<$integer> for $name {
+                fn from(value: $integer) -> Self {
+                    Self(value)
+                }
+            }
+
+            impl From<$name> for $integer {
+                fn from(value: $name) -> Self {
+                    value.0
+                }
+            }
+
+            impl fmt::Display for $name {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    self.0.fmt(f)
+                }
+            }
+
+            impl FromStr for $name {
+                type Err = <$integer as FromStr>::Err;
+
+                fn from_str(string: &str) -> Result<Self, Self::Err> {
+                    string.parse().map(Self)
+                }
+            }
+        )*
+    };
+}
+
+integer_newtype!(BlockHeight(u32), Round(u64), TxIndex(u32), Microcredits(u64));
+
+/// The height of a block, counted from the genesis block at height 0
+///
+/// Heights move by a number of blocks, and the difference of two heights is the number of blocks between them.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BlockHeight(pub u32);
+
+impl BlockHeight {
+    /// The height of the genesis block
+    pub const GENESIS: Self = Self(0);
+
+    /// Returns the height the given number of blocks above, or `None` if it exceeds the maximum height.
+    pub fn checked_add(self, blocks: u32) -> Option<Self> {
+        self.0.checked_add(blocks).map(Self)
+    }
+
+    /// Returns the height the given number of blocks above, or the maximum height if it exceeds it.
+    pub fn saturating_add(self, blocks: u32) -> Self {
+        Self(self.0.saturating_add(blocks))
+    }
+
+    /// Returns the height the given number of blocks below, or `None` if it is below the genesis block.
+    pub fn checked_sub(self, blocks: u32) -> Option<Self> {
+        self.0.checked_sub(blocks).map(Self)
+    }
+
+    /// Returns the height the given number of blocks below, or the genesis block if it is below it.
+    pub fn saturating_sub(self, blocks: u32) -> Self {
+        Self(self.0.saturating_sub(blocks))
+    }
+}
+
+impl Add<u32> for BlockHeight {
+    type Output = Self;
+
+    fn add(self, blocks: u32) -> Self {
+        Self(self.0 + blocks)
+    }
+}
+
+impl AddAssign<u32> for BlockHeight {
+    fn add_assign(&mut self, blocks: u32) {
+        self.0 += blocks;
+    }
+}
+
+impl Sub<u32> for BlockHeight {
+    type Output = Self;
+
+    fn sub(self, blocks: u32) -> Self {
+        Self(self.0 - blocks)
+    }
+}
+
+impl Sub for BlockHeight {
+    type Output = u32;
+
+    fn sub(self, other: Self) -> u32 {
+        self.0 - other.0
+    }
+}
+
+// Returns the range of heights of a range of integers, to pass the ranges the crate walks over to its public API
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn heights(range: Range<u32>) -> Range<BlockHeight> {
+    BlockHeight(range.start)..BlockHeight(range.end)
+}
+
+// Returns the range of integers of a range of heights taken by the public API
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+pub(crate) fn from_heights(range: Range<BlockHeight>) -> Range<u32> {
+    range.start.0..range.end.0
+}
+
+/// The round of consensus in which a block was produced
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Round(pub u64);
+
+impl Add<u64> for Round {
+    type Output = Self;
+
+    fn add(self, rounds: u64) -> Self {
+        Self(self.0 + rounds)
+    }
+}
+
+impl Sub for Round {
+    type Output = u64;
+
+    fn sub(self, other: Self) -> u64 {
+        self.0 - other.0
+    }
+}
+
+/// The index of a transaction in its block, counted from 0
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TxIndex(pub u32);
+
+impl TxIndex {
+    // Returns the index of the transaction in the transactions of its block
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// An amount of microcredits, the unit of fees and balances, of which a credit holds 1,000,000
+///
+/// Amounts add up and subtract from each other, and sum up over an iterator of amounts.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Microcredits(pub u64);
+
+impl Microcredits {
+    /// No microcredits
+    pub const ZERO: Self = Self(0);
+
+    /// The number of microcredits in a credit
+    pub const PER_CREDIT: u64 = 1_000_000;
+
+    /// Returns the amount of the given number of credits, or `None` if it exceeds the maximum amount.
+    pub fn from_credits(credits: u64) -> Option<Self> {
+        credits.checked_mul(Self::PER_CREDIT).map(Self)
+    }
+
+    /// Returns the sum of the amounts, or `None` if it exceeds the maximum amount.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    /// Returns the difference of the amounts, or `None` if it is negative.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    /// Returns the difference of the amounts, or zero if it is negative.
+    pub fn saturating_sub(self, other: Self) -> Self {
+        Self(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Add for Microcredits {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Microcredits {
+    fn add_assign(&mut self, other: Self) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Microcredits {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Microcredits {
+    fn sub_assign(&mut self, other: Self) {
+        self.0 -= other.0;
+    }
+}
+
+impl Sum for Microcredits {
+    fn sum<I: Iterator<Item = Self>>(amounts: I) -> Self {
+        Self(amounts.map(|amount| amount.0).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_serde() {
+        // The identifiers serialize as the plain integers they hold, so the wire format is unchanged.
+        assert_eq!(serde_json::to_string(&BlockHeight(7)).unwrap(), "7");
+        assert_eq!(serde_json::to_string(&Round(u64::MAX)).unwrap(), u64::MAX.to_string());
+        assert_eq!(serde_json::to_string(&TxIndex(0)).unwrap(), "0");
+        assert_eq!(serde_json::to_string(&Microcredits(1_500_000)).unwrap(), "1500000");
+
+        assert_eq!(serde_json::from_str::<BlockHeight>("7").unwrap(), BlockHeight(7));
+        assert_eq!(serde_json::from_str::<Round>(&u64::MAX.to_string()).unwrap(), Round(u64::MAX));
+        assert_eq!(serde_json::from_str::<TxIndex>("0").unwrap(), TxIndex(0));
+        assert_eq!(serde_json::from_str::<Microcredits>("1500000").unwrap(), Microcredits(1_500_000));
+        assert!(serde_json::from_str::<BlockHeight>("-1").is_err());
+        assert!(serde_json::from_str::<BlockHeight>("\"7\"").is_err());
+
+        // Structs holding them keep the fields they held as integers.
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Entry {
+            height: BlockHeight,
+            index: TxIndex,
+            fee: Microcredits,
+        }
+        let json = r#"{"height":12,"index":3,"fee":250000}"#;
+        let entry: Entry = serde_json::from_str(json).unwrap();
+        assert_eq!(entry, Entry { height: BlockHeight(12), index: TxIndex(3), fee: Microcredits(250_000) });
+        assert_eq!(serde_json::to_string(&entry).unwrap(), json);
+    }
+
+    #[test]
+    fn test_ids_arithmetic() {
+        let height = BlockHeight(10);
+        assert_eq!(height + 5, BlockHeight(15));
+        assert_eq!(height - 5, BlockHeight(5));
+        assert_eq!(BlockHeight(15) - height, 5);
+        assert_eq!(height.checked_add(u32::MAX), None);
+        assert_eq!(height.checked_sub(11), None);
+        assert_eq!(height.saturating_sub(11), BlockHeight::GENESIS);
+        assert_eq!(Round(9) + 1, Round(10));
+        assert_eq!(Round(10) - Round(4), 6);
+
+        let amounts = [Microcredits(1), Microcredits::from_credits(2).unwrap(), Microcredits::ZERO];
+        assert_eq!(amounts.into_iter().sum::<Microcredits>(), Microcredits(2_000_001));
+        assert_eq!(Microcredits(5) - Microcredits(3), Microcredits(2));
+        assert_eq!(Microcredits(3).checked_sub(Microcredits(5)), None);
+        assert_eq!(Microcredits(3).saturating_sub(Microcredits(5)), Microcredits::ZERO);
+        assert_eq!(Microcredits::from_credits(u64::MAX), None);
+
+        assert_eq!(u32::from(BlockHeight::from(7)), 7);
+        assert_eq!("42".parse::<TxIndex>().unwrap(), TxIndex(42));
+        assert_eq!(Microcredits(1_500_000).to_string(), "1500000");
+    }
+}
@@ -0,0 +1,302 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reading and writing the files of the `leo` and `snarkos` command line tools
+//!
+//! Accounts are read from the key listing printed by `snarkos account new`, from the `.env` file of a Leo
+//! project, or from a file holding only the private key, as passed to `snarkos --private-key-file`. Records are
+//! read from the ciphertexts and plaintexts printed by the tools, either bare or in a JSON envelope.
+
+use crate::{check_account_format, check_network_name, network_name, store::write_atomic};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use snarkvm_console::{
+    account::{Address, PrivateKey, ViewKey},
+    program::{Ciphertext, Network, Plaintext, Record},
+};
+use std::{fs, path::Path, str::FromStr};
+
+/// Read the private key of an account file written by the `leo` or `snarkos` command line tools.
+///
+/// Files naming their network, such as Leo `.env` files, must name the network of `N`, and the keys and address
+/// must be in its account format, or the file fails with [`crate::WrongNetworkAddress`]. View keys and addresses
+/// listed with the private key must belong to it.
+pub fn parse_cli_account_file<N: Network>(path: impl AsRef<Path>) -> Result<PrivateKey<N>> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)
+        .map_err(|error| anyhow!("Failed to read the account file {}: {error}", path.display()))?;
+    parse_cli_account(&contents).map_err(|error| anyhow!("Invalid account file {}: {error}", path.display()))
+}
+
+/// Parse the contents of an account file written by the `leo` or `snarkos` command line tools, as
+/// [`parse_cli_account_file`] does.
+pub fn parse_cli_account<N: Network>(contents: &str) -> Result<PrivateKey<N>> {
+    let (mut network, mut private_key, mut view_key, mut address) = (None, None, None, None);
+    for line in strip_ansi_escapes(contents).lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Leo `.env` files assign variables, and other variables, e.g. the endpoint, are skipped.
+        if let Some((name, value)) = line.split_once('=') {
+            let value = value.trim().trim_matches(|character| character == '"' || character == '\'').to_string();
+            match name.trim() {
+                "NETWORK" => network = Some(value),
+                "PRIVATE_KEY" => private_key = Some(value),
+                _ => {}
+            }
+            continue;
+        }
+        // `snarkos account new` lists the keys by name, after a banner that is skipped.
+        if let Some(value) = line.strip_prefix("Private Key") {
+            private_key = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("View Key") {
+            view_key = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Address") {
+            address = Some(value.trim().to_string());
+        } else if line.starts_with("APrivateKey1") {
+            private_key = Some(line.to_string());
+        } else if line.starts_with("AViewKey1") {
+            view_key = Some(line.to_string());
+        }
+    }
+
+    if let Some(network) = network {
+        check_network_name::<N>("file", &network)?;
+    }
+    for value in [&private_key, &view_key, &address].into_iter().flatten() {
+        check_account_format::<N>(value)?;
+    }
+    let Some(private_key) = private_key else {
+        match view_key.is_some() {
+            true => {
+                bail!("The file holds a view key but no private key, so it can only be used as a watch-only account")
+            }
+            false => bail!("The file holds no private key"),
+        }
+    };
+    // The private key is not quoted in errors, as they may be logged.
+    let private_key = PrivateKey::<N>::from_str(&private_key).map_err(|_| anyhow!("The private key is malformed"))?;
+    if let Some(view_key) = view_key {
+        let view_key = ViewKey::<N>::from_str(&view_key).map_err(|_| anyhow!("The view key is malformed"))?;
+        ensure!(view_key == ViewKey::try_from(&private_key)?, "The view key does not belong to the private key");
+    }
+    if let Some(address) = address {
+        let address = Address::<N>::from_str(&address).map_err(|_| anyhow!("The address '{address}' is malformed"))?;
+        ensure!(
+            address == Address::try_from(&private_key)?,
+            "The address '{address}' does not belong to the private key"
+        );
+    }
+    Ok(private_key)
+}
+
+/// Write an account file for the `leo` or `snarkos` command line tools.
+///
+/// A file named `.env` is written as the `.env` file of a Leo project, with the network of `N`. Other files list
+/// the private key, view key, and address as `snarkos account new` prints them, and can be passed to
+/// `snarkos --private-key-file`. The file is replaced atomically, and on Unix only its owner can read it.
+pub fn export_cli_account_file<N: Network>(private_key: &PrivateKey<N>, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let contents = match path.file_name().map_or(false, |name| name == ".env") {
+        true => format!("NETWORK={}\nPRIVATE_KEY={private_key}\n", network_name::<N>()),
+        false => {
+            let (view_key, address) = (ViewKey::try_from(private_key)?, Address::try_from(private_key)?);
+            format!("  Private Key  {private_key}\n     View Key  {view_key}\n      Address  {address}\n")
+        }
+    };
+    write_atomic(path, contents.as_bytes())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// Parse a record ciphertext exported by the command line tools or returned by a node.
+///
+/// The ciphertext may be bare, as in `record1...`, a JSON string, or a JSON object holding it in a `record` or
+/// `ciphertext` field. Objects naming their network in a `network` field must name the network of `N`, or the export
+/// fails with [`crate::WrongNetworkAddress`].
+pub fn parse_record_export<N: Network>(export: &str) -> Result<Record<N, Ciphertext<N>>> {
+    let ciphertext = unwrap_envelope::<N>(export)?;
+    if ciphertext.starts_with('{') {
+        bail!("The record is a plaintext, and must be encrypted to its owner to be used as a ciphertext")
+    }
+    ensure!(ciphertext.starts_with("record1"), "The record ciphertext must start with 'record1'");
+    Record::from_str(&ciphertext).map_err(|error| anyhow!("The record ciphertext is malformed: {error}"))
+}
+
+/// Parse a plaintext record, as printed by the command line tools, e.g. after decrypting a record.
+///
+/// The plaintext may be bare, spanning several lines, or held in a JSON envelope as in [`parse_record_export`].
+pub fn parse_plaintext_record<N: Network>(export: &str) -> Result<Record<N, Plaintext<N>>> {
+    let plaintext = unwrap_envelope::<N>(export)?;
+    if plaintext.starts_with("record1") {
+        bail!("The record is a ciphertext, and must be decrypted with the view key of its owner")
+    }
+    Record::from_str(&plaintext).map_err(|error| anyhow!("The plaintext record is malformed: {error}"))
+}
+
+// Returns the record of a bare or JSON export, checking the network named by an envelope
+fn unwrap_envelope<N: Network>(export: &str) -> Result<String> {
+    let export = export.trim();
+    // Bare plaintexts also start with a brace, but are not JSON.
+    let json = match serde_json::from_str::<serde_json::Value>(export) {
+        Ok(json) => json,
+        Err(_) => return Ok(export.to_string()),
+    };
+    match json {
+        serde_json::Value::String(record) => Ok(record.trim().to_string()),
+        serde_json::Value::Object(envelope) => {
+            if let Some(network) = envelope.get("network") {
+                let network = network.as_str().ok_or_else(|| anyhow!("The network of the export is not a string"))?;
+                check_network_name::<N>("file", network)?;
+            }
+            match envelope.get("record").or_else(|| envelope.get("ciphertext")) {
+                Some(serde_json::Value::String(record)) => Ok(record.trim().to_string()),
+                Some(_) => bail!("The record of the export is not a string"),
+                None => bail!("The export has no 'record' or 'ciphertext' field"),
+            }
+        }
+        _ => bail!("The export is neither a record nor a JSON object holding one"),
+    }
+}
+
+// Remove the color codes that the tools print to terminals
+fn strip_ansi_escapes(contents: &str) -> String {
+    let mut stripped = String::with_capacity(contents.len());
+    let mut characters = contents.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            // Skip the escape sequence up to its final letter.
+            '\u{1b}' => {
+                characters.by_ref().find(|character| character.is_ascii_alphabetic());
+            }
+            character => stripped.push(character),
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WrongNetworkAddress;
+    use snarkvm_console::network::Testnet3;
+    use std::env;
+
+    type N = Testnet3;
+
+    const PRIVATE_KEY: &str = "APrivateKey1zkp5fCUVzS9b7my34CdraHBF9XzB58xYiPzFJQvjhmvv7A8";
+
+    // The output of `snarkos account new`, with the colors it prints to terminals
+    const SNARKOS_ACCOUNT: &str = "
+ \u{1b}[1mAttention - Remember to store this account private key and view key.\u{1b}[0m
+
+  \u{1b}[1mPrivate Key\u{1b}[0m  APrivateKey1zkp5fCUVzS9b7my34CdraHBF9XzB58xYiPzFJQvjhmvv7A8
+     \u{1b}[1mView Key\u{1b}[0m  AViewKey1oRpmvXibMYHar5JcsLp4sSirWpc9SFgXZrTSte9ce5D3
+      \u{1b}[1mAddress\u{1b}[0m  aleo18x0yenrkceapvt85e6aqw2v8hq37hpt4ew6k6cgum6xlpmaxt5xqwnkuja
+
+";
+
+    // The `.env` file of a Leo project
+    const LEO_ENV: &str = "NETWORK=testnet3
+PRIVATE_KEY=APrivateKey1zkp5fCUVzS9b7my34CdraHBF9XzB58xYiPzFJQvjhmvv7A8
+";
+
+    // A record ciphertext, and its plaintext as decrypted by `snarkos developer decrypt`
+    const RECORD_CIPHERTEXT: &str = "record1qyqsq0pr2ha842m8tjmqw3x5d8vl98fnuaf6fkxq0fk5xtjl930lf3gzqyqspsvqwd96humvclrrww0er9yrp8hak6klkcp36wyscrxweyaxt6gqqpv0q02f5tz0qr0um0nn4urrfwc7flkal5ywd50h2pm9sd30vmaq2j6q95q";
+    const RECORD_PLAINTEXT: &str = "{
+  owner: aleo18x0yenrkceapvt85e6aqw2v8hq37hpt4ew6k6cgum6xlpmaxt5xqwnkuja.private,
+  gates: 1099999999999864u64.private,
+  _nonce: 3859911413360468505092363429199432421222291175370483298628506550397056121761group.public
+}";
+
+    #[test]
+    fn test_parse_cli_account() {
+        let private_key = PrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+        assert_eq!(parse_cli_account::<N>(SNARKOS_ACCOUNT).unwrap(), private_key);
+        assert_eq!(parse_cli_account::<N>(LEO_ENV).unwrap(), private_key);
+        assert_eq!(parse_cli_account::<N>(&format!("{PRIVATE_KEY}\n")).unwrap(), private_key);
+
+        // Files for other networks, and listings of keys that do not belong together, are rejected.
+        let mainnet = LEO_ENV.replace("testnet3", "mainnet");
+        let error = parse_cli_account::<N>(&mainnet).unwrap_err();
+        assert_eq!(error.to_string(), "The file is for the 'mainnet' network, but this client is for 'testnet3'");
+        assert_eq!(error.downcast_ref::<WrongNetworkAddress>().map(|error| error.found()), Some("mainnet"));
+        assert_eq!(crate::error_code(&error), crate::ErrorCode::WrongNetwork);
+        let other_address = Address::try_from(PrivateKey::<N>::new(&mut rand::thread_rng()).unwrap()).unwrap();
+        let mismatched = SNARKOS_ACCOUNT
+            .replace("aleo18x0yenrkceapvt85e6aqw2v8hq37hpt4ew6k6cgum6xlpmaxt5xqwnkuja", &other_address.to_string());
+        let error = parse_cli_account::<N>(&mismatched).unwrap_err();
+        assert_eq!(error.to_string(), format!("The address '{other_address}' does not belong to the private key"));
+        let error = parse_cli_account::<N>("AViewKey1oRpmvXibMYHar5JcsLp4sSirWpc9SFgXZrTSte9ce5D3").unwrap_err();
+        assert!(error.to_string().contains("watch-only"), "{error}");
+        let error = parse_cli_account::<N>(&LEO_ENV.replace(PRIVATE_KEY, &PRIVATE_KEY[..40])).unwrap_err();
+        assert_eq!(error.to_string(), "The private key is malformed");
+    }
+
+    #[test]
+    fn test_export_cli_account_file() {
+        let directory = env::temp_dir().join(format!("aleo-interop-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let private_key = PrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+
+        // Both formats are written as the tools write them, and read back.
+        let (account_path, env_path) = (directory.join("account.txt"), directory.join(".env"));
+        export_cli_account_file(&private_key, &account_path).unwrap();
+        export_cli_account_file(&private_key, &env_path).unwrap();
+        let listing = "  Private Key  APrivateKey1zkp5fCUVzS9b7my34CdraHBF9XzB58xYiPzFJQvjhmvv7A8
+     View Key  AViewKey1oRpmvXibMYHar5JcsLp4sSirWpc9SFgXZrTSte9ce5D3
+      Address  aleo18x0yenrkceapvt85e6aqw2v8hq37hpt4ew6k6cgum6xlpmaxt5xqwnkuja
+";
+        assert_eq!(fs::read_to_string(&account_path).unwrap(), listing);
+        assert_eq!(fs::read_to_string(&env_path).unwrap(), LEO_ENV);
+        assert_eq!(parse_cli_account_file::<N>(&account_path).unwrap(), private_key);
+        assert_eq!(parse_cli_account_file::<N>(&env_path).unwrap(), private_key);
+
+        let error = parse_cli_account_file::<N>(directory.join("missing")).unwrap_err();
+        assert!(error.to_string().starts_with("Failed to read the account file"), "{error}");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_parse_record_export() {
+        let record = Record::<N, Ciphertext<N>>::from_str(RECORD_CIPHERTEXT).unwrap();
+        assert_eq!(parse_record_export::<N>(RECORD_CIPHERTEXT).unwrap(), record);
+        assert_eq!(parse_record_export::<N>(&format!("\"{RECORD_CIPHERTEXT}\"\n")).unwrap(), record);
+        let envelope = format!("{{\"network\": \"testnet3\", \"record\": \"{RECORD_CIPHERTEXT}\"}}");
+        assert_eq!(parse_record_export::<N>(&envelope).unwrap(), record);
+        assert_eq!(parse_record_export::<N>(&record.to_string()).unwrap().to_string(), RECORD_CIPHERTEXT);
+
+        // Exports of other networks and plaintexts are rejected.
+        let error = parse_record_export::<N>(&envelope.replace("testnet3", "mainnet")).unwrap_err();
+        assert_eq!(error.to_string(), "The file is for the 'mainnet' network, but this client is for 'testnet3'");
+        let error = parse_record_export::<N>(RECORD_PLAINTEXT).unwrap_err();
+        assert!(error.to_string().starts_with("The record is a plaintext"), "{error}");
+        let error = parse_record_export::<N>("{\"ciphertext\": 5}").unwrap_err();
+        assert_eq!(error.to_string(), "The record of the export is not a string");
+
+        // Plaintexts are read bare or in an envelope, and ciphertexts are rejected.
+        let plaintext = parse_plaintext_record::<N>(RECORD_PLAINTEXT).unwrap();
+        assert_eq!(plaintext.to_string(), RECORD_PLAINTEXT);
+        let envelope = serde_json::json!({ "record": RECORD_PLAINTEXT }).to_string();
+        assert_eq!(parse_plaintext_record::<N>(&envelope).unwrap(), plaintext);
+        let error = parse_plaintext_record::<N>(RECORD_CIPHERTEXT).unwrap_err();
+        assert!(error.to_string().starts_with("The record is a ciphertext"), "{error}");
+    }
+}
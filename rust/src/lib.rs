@@ -17,7 +17,80 @@
 pub mod account;
 pub use account::*;
 
+pub mod snarkvm;
+
+mod version;
+pub use version::*;
+
+mod ids;
+pub use ids::*;
+
+#[cfg(not(feature = "wasm"))]
+mod mutex;
+
 #[cfg(not(feature = "wasm"))]
 pub mod api;
 #[cfg(not(feature = "wasm"))]
 pub use api::*;
+
+#[cfg(not(feature = "wasm"))]
+pub mod program;
+#[cfg(not(feature = "wasm"))]
+pub use program::*;
+
+#[cfg(not(feature = "wasm"))]
+pub mod store;
+#[cfg(not(feature = "wasm"))]
+pub use store::*;
+
+#[cfg(not(feature = "wasm"))]
+pub mod interop;
+#[cfg(not(feature = "wasm"))]
+pub use interop::*;
+
+#[cfg(all(feature = "faucet", not(any(feature = "async", feature = "wasm"))))]
+pub mod faucet;
+#[cfg(all(feature = "faucet", not(any(feature = "async", feature = "wasm"))))]
+pub use faucet::*;
+
+#[cfg(not(feature = "wasm"))]
+pub mod verifier;
+#[cfg(not(feature = "wasm"))]
+pub use verifier::*;
+
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+pub mod wallet;
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+pub use wallet::*;
+
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+pub mod outbox;
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+pub use outbox::*;
+
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+pub mod planner;
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+pub use planner::*;
+
+#[cfg(all(feature = "service", not(any(feature = "async", feature = "wasm"))))]
+pub mod service;
+#[cfg(all(feature = "service", not(any(feature = "async", feature = "wasm"))))]
+pub use service::*;
+
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+pub mod sync;
+#[cfg(not(any(feature = "async", feature = "wasm")))]
+pub use sync::*;
+
+#[cfg(all(feature = "devnet", not(any(feature = "async", feature = "wasm"))))]
+pub mod devnet;
+#[cfg(all(feature = "devnet", not(any(feature = "async", feature = "wasm"))))]
+pub use devnet::*;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(test)]
+#[cfg_attr(feature = "async", allow(dead_code))]
+pub(crate) mod test_helpers;
@@ -0,0 +1,476 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! A persistent queue of transactions to broadcast.
+//!
+//! Transactions built while offline are enqueued in an [`OutboxQueue`], which is saved to a file on every change,
+//! and broadcast in order by [`OutboxQueue::pump`] once the node can be reached, either by the caller or by a
+//! [`crate::SyncService`] following the tip. A transaction stays queued until a block includes it, and is dropped
+//! once the tip passes its expiry height, as its input records may have been spent by another transaction since.
+
+use crate::{is_retryable, AleoAPIClient, Clock, Json, Persist, TransactionStatus};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Transaction;
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// A change of an [`OutboxQueue`] made by [`OutboxQueue::pump`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutboxEvent<N: Network> {
+    /// The node accepted the transaction, which stays queued until a block includes it
+    Broadcast { transaction_id: N::TransactionID },
+    /// The transaction was included in the block at the given height, and left the queue
+    Confirmed { transaction_id: N::TransactionID, height: u32 },
+    /// The broadcast failed for a transient reason, and is retried after a backoff
+    Retrying { transaction_id: N::TransactionID, attempts: u32, error: String },
+    /// The node rejected the transaction, or aborted it, and it left the queue
+    Rejected { transaction_id: N::TransactionID, reason: String },
+    /// The tip passed the expiry height of the transaction before a block included it, and it left the queue
+    Expired { transaction_id: N::TransactionID, expiry_height: u32, height: u32 },
+}
+
+impl<N: Network> OutboxEvent<N> {
+    /// Returns the ID of the transaction the event is for.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        match self {
+            Self::Broadcast { transaction_id }
+            | Self::Confirmed { transaction_id, .. }
+            | Self::Retrying { transaction_id, .. }
+            | Self::Rejected { transaction_id, .. }
+            | Self::Expired { transaction_id, .. } => *transaction_id,
+        }
+    }
+}
+
+/// A transaction of an [`OutboxQueue`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct OutboxEntry<N: Network> {
+    transaction: Transaction<N>,
+    expiry_height: u32,
+    broadcast: bool,
+    attempts: u32,
+    // The UNIX time in milliseconds before which a failed broadcast is not retried
+    retry_at: u64,
+    last_error: Option<String>,
+}
+
+impl<N: Network> OutboxEntry<N> {
+    /// Returns the queued transaction.
+    pub fn transaction(&self) -> &Transaction<N> {
+        &self.transaction
+    }
+
+    /// Returns the height past which the transaction is dropped.
+    pub fn expiry_height(&self) -> u32 {
+        self.expiry_height
+    }
+
+    /// Returns `true` if the node accepted the transaction, which awaits a block.
+    pub fn is_broadcast(&self) -> bool {
+        self.broadcast
+    }
+
+    /// Returns the number of broadcasts that failed for a transient reason.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the error of the last failed broadcast, if any.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+}
+
+/// A transaction the node rejected, and the reason it gave
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct OutboxRejection<N: Network> {
+    transaction_id: N::TransactionID,
+    reason: String,
+}
+
+impl<N: Network> OutboxRejection<N> {
+    /// Returns the ID of the rejected transaction.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the reason of the rejection.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+// The contents of an outbox file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+struct Outbox<N: Network> {
+    entries: Vec<OutboxEntry<N>>,
+    rejections: Vec<OutboxRejection<N>>,
+}
+
+impl<N: Network> Persist for Outbox<N> {
+    const KIND: u8 = 7;
+    const NAME: &'static str = "outbox";
+    const VERSION: u16 = 1;
+}
+
+/// A queue of transactions broadcast in order once the node can be reached, kept in a file
+///
+/// Each transaction is broadcast with [`AleoAPIClient::transaction_broadcast`], so a transaction the node already
+/// confirmed is not rejected. Broadcasts that fail for a transient reason, e.g. while offline, are retried with an
+/// exponential backoff, and hold back the transactions queued after them, which may spend their outputs. A
+/// transaction the node rejects leaves the queue, and its rejection is kept with the reason the node gave.
+pub struct OutboxQueue<N: Network> {
+    outbox: Outbox<N>,
+    path: PathBuf,
+    base_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl<N: Network> OutboxQueue<N> {
+    /// The default backoff after the first failed broadcast of a transaction
+    pub const DEFAULT_BASE_BACKOFF: Duration = Duration::from_secs(5);
+    /// The default longest backoff between two broadcasts of a transaction
+    pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+    /// Open the queue kept in the given file, which is created on the first change if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let outbox = if path.exists() { Outbox::load(&path)? } else { Outbox { entries: vec![], rejections: vec![] } };
+        Ok(Self { outbox, path, base_backoff: Self::DEFAULT_BASE_BACKOFF, max_backoff: Self::DEFAULT_MAX_BACKOFF })
+    }
+
+    /// Set the backoff after the first failed broadcast of a transaction, which doubles with each failure up to
+    /// the given maximum.
+    pub fn with_backoff(mut self, base_backoff: Duration, max_backoff: Duration) -> Self {
+        self.base_backoff = base_backoff;
+        self.max_backoff = max_backoff.max(base_backoff);
+        self
+    }
+
+    /// Returns the path of the file of the queue.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the queued transactions, in the order they are broadcast.
+    pub fn entries(&self) -> &[OutboxEntry<N>] {
+        &self.outbox.entries
+    }
+
+    /// Returns the transactions the node rejected, in the order they were rejected.
+    pub fn rejections(&self) -> &[OutboxRejection<N>] {
+        &self.outbox.rejections
+    }
+
+    /// Returns the number of queued transactions.
+    pub fn len(&self) -> usize {
+        self.outbox.entries.len()
+    }
+
+    /// Returns `true` if no transaction is queued.
+    pub fn is_empty(&self) -> bool {
+        self.outbox.entries.is_empty()
+    }
+
+    /// Queue a transaction, to be dropped if no block includes it before the tip passes the given height.
+    ///
+    /// Fails if the transaction is already queued.
+    pub fn enqueue(&mut self, transaction: Transaction<N>, expiry_height: u32) -> Result<()> {
+        let transaction_id = transaction.id();
+        if self.outbox.entries.iter().any(|entry| entry.transaction.id() == transaction_id) {
+            bail!("Transaction '{transaction_id}' is already queued");
+        }
+        let entry =
+            OutboxEntry { transaction, expiry_height, broadcast: false, attempts: 0, retry_at: 0, last_error: None };
+        self.outbox.entries.push(entry);
+        self.save()
+    }
+
+    /// Broadcast the queued transactions in order, and drop those that were included, rejected or expired,
+    /// returning the changes.
+    ///
+    /// Fails without changing the queue if the latest height cannot be read, e.g. while offline. The pump stops
+    /// at the first transaction whose broadcast fails for a transient reason, or still backs off.
+    pub fn pump(&mut self, api_client: &AleoAPIClient<N>) -> Result<Vec<OutboxEvent<N>>> {
+        let height = api_client.latest_height()?.0;
+        let (mut events, mut entries) = (vec![], std::mem::take(&mut self.outbox.entries).into_iter());
+        let now = unix_millis(api_client.clock());
+        for mut entry in entries.by_ref() {
+            let transaction_id = entry.transaction.id();
+            if entry.broadcast {
+                let status = match api_client.transaction_status(transaction_id) {
+                    Ok(status) => status,
+                    Err(error) => {
+                        self.outbox.entries.push(entry);
+                        self.save()?;
+                        return Err(error);
+                    }
+                };
+                match status {
+                    TransactionStatus::Aborted { height, .. } => {
+                        let reason = format!("The transaction was aborted in block {height}");
+                        self.reject(transaction_id, reason, &mut events);
+                        continue;
+                    }
+                    status => match status.height() {
+                        Some(height) => {
+                            events.push(OutboxEvent::Confirmed { transaction_id, height });
+                            continue;
+                        }
+                        None if height <= entry.expiry_height => {
+                            self.outbox.entries.push(entry);
+                            continue;
+                        }
+                        None => (),
+                    },
+                }
+            }
+            if height > entry.expiry_height {
+                events.push(OutboxEvent::Expired { transaction_id, expiry_height: entry.expiry_height, height });
+                continue;
+            }
+            if entry.retry_at > now {
+                self.outbox.entries.push(entry);
+                break;
+            }
+            match api_client.transaction_broadcast(entry.transaction.clone()) {
+                Ok(_) => {
+                    entry.broadcast = true;
+                    entry.last_error = None;
+                    events.push(OutboxEvent::Broadcast { transaction_id });
+                    self.outbox.entries.push(entry);
+                }
+                Err(error) if is_retryable(&error) => {
+                    entry.attempts += 1;
+                    entry.retry_at = now.saturating_add(self.backoff(entry.attempts).as_millis() as u64);
+                    entry.last_error = Some(error.to_string());
+                    let (attempts, error) = (entry.attempts, error.to_string());
+                    events.push(OutboxEvent::Retrying { transaction_id, attempts, error });
+                    self.outbox.entries.push(entry);
+                    break;
+                }
+                Err(error) => self.reject(transaction_id, error.to_string(), &mut events),
+            }
+        }
+        // The transactions after a transient failure keep their place.
+        self.outbox.entries.extend(entries);
+        if !events.is_empty() {
+            self.save()?;
+        }
+        Ok(events)
+    }
+
+    // Record the rejection of a transaction that left the queue
+    fn reject(&mut self, transaction_id: N::TransactionID, reason: String, events: &mut Vec<OutboxEvent<N>>) {
+        self.outbox.rejections.push(OutboxRejection { transaction_id, reason: reason.clone() });
+        events.push(OutboxEvent::Rejected { transaction_id, reason });
+    }
+
+    // Returns the backoff after the given number of failed broadcasts
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempts.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.base_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    // Write the queue to its file
+    fn save(&self) -> Result<()> {
+        self.outbox.save(&self.path, &Json)
+    }
+}
+
+// Returns the time of the wall clock in milliseconds since the Unix epoch
+fn unix_millis(clock: &dyn Clock) -> u64 {
+    clock.system_now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_transaction,
+            sample_transition,
+            temp_path,
+            CurrentNetwork,
+            MockResponse,
+            MockServer,
+        },
+        testnet3,
+    };
+    use snarkvm_console::{prelude::Uniform, types::Field};
+    use snarkvm_utilities::TestRng;
+
+    use std::{
+        fs,
+        sync::{
+            atomic::{AtomicU16, AtomicU32, Ordering},
+            Arc,
+            Mutex,
+        },
+    };
+
+    type N = CurrentNetwork;
+
+    // The state of a mock node
+    #[derive(Default)]
+    struct MockNode {
+        // The node is offline while this is `0`
+        height: AtomicU32,
+        // The status of the responses to broadcasts, `200` accepting them
+        broadcast_status: AtomicU16,
+        // The transactions included in the genesis block, as far as the node tells
+        included: Mutex<Vec<String>>,
+    }
+
+    // Start a node serving the given state
+    fn mock_node(node: Arc<MockNode>) -> MockServer {
+        MockServer::start(move |request| {
+            let genesis = genesis_block();
+            match (node.height.load(Ordering::SeqCst), request.path.as_str()) {
+                (0, _) => Some(MockResponse::text(503, "Service unavailable")),
+                (height, "/testnet3/latest/height") => Some(MockResponse::json(height)),
+                (_, "/testnet3/transaction/broadcast") => match node.broadcast_status.load(Ordering::SeqCst) {
+                    200 => Some(MockResponse::json(serde_json::to_string(&genesis).unwrap())),
+                    400 => Some(MockResponse::text(400, "Invalid fee")),
+                    status => Some(MockResponse::text(status, "Service unavailable")),
+                },
+                (_, "/testnet3/block/0") => Some(MockResponse::json(serde_json::to_string(&genesis).unwrap())),
+                (_, path) if path.starts_with("/testnet3/height/") => Some(MockResponse::json(0)),
+                (_, path) => {
+                    let transaction_id = path.strip_prefix("/testnet3/find/blockHash/")?;
+                    match node.included.lock().unwrap().iter().any(|id| id == transaction_id) {
+                        true => Some(MockResponse::json(serde_json::json!(genesis.hash()))),
+                        false => Some(MockResponse::text(404, "Transaction not found")),
+                    }
+                }
+            }
+        })
+    }
+
+    // Returns a transaction with a distinct ID
+    fn sample_queued(rng: &mut TestRng) -> Transaction<N> {
+        sample_transaction([sample_transition(&[Field::rand(rng)], &[], rng)])
+    }
+
+    #[test]
+    fn test_outbox_offline_then_pump() {
+        let rng = &mut TestRng::default();
+        let path = temp_path("outbox-offline");
+        let node = Arc::new(MockNode::default());
+        let server = mock_node(node.clone());
+        let api_client = testnet3(server.base_url());
+
+        // Transactions queued while offline stay queued, and a duplicate is rejected.
+        let mut outbox = OutboxQueue::<N>::open(&path).unwrap();
+        let (first, second) = (sample_queued(rng), sample_queued(rng));
+        outbox.enqueue(first.clone(), 100).unwrap();
+        outbox.enqueue(second.clone(), 100).unwrap();
+        assert!(outbox.enqueue(first.clone(), 200).unwrap_err().to_string().contains("already queued"));
+        assert!(outbox.pump(&api_client).is_err());
+        assert_eq!(outbox.len(), 2);
+
+        // The queue survives a restart, and is broadcast in order once the node is online.
+        drop(outbox);
+        let mut outbox = OutboxQueue::<N>::open(&path).unwrap();
+        let queued = outbox.entries().iter().map(|entry| entry.transaction().id()).collect::<Vec<_>>();
+        assert_eq!(queued, [first.id(), second.id()]);
+        node.height.store(10, Ordering::SeqCst);
+        node.broadcast_status.store(200, Ordering::SeqCst);
+        let events = outbox.pump(&api_client).unwrap();
+        let broadcast = |transaction: &Transaction<N>| OutboxEvent::Broadcast { transaction_id: transaction.id() };
+        assert_eq!(events, [broadcast(&first), broadcast(&second)]);
+        assert!(outbox.entries().iter().all(OutboxEntry::is_broadcast));
+
+        // The transactions leave the queue once a block includes them.
+        node.included.lock().unwrap().extend([first.id().to_string(), second.id().to_string()]);
+        let events = outbox.pump(&api_client).unwrap();
+        let confirmed =
+            |transaction: &Transaction<N>| OutboxEvent::Confirmed { transaction_id: transaction.id(), height: 0 };
+        assert_eq!(events, [confirmed(&first), confirmed(&second)]);
+        assert!(outbox.is_empty() && OutboxQueue::<N>::open(&path).unwrap().is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_outbox_retry_and_expiry() {
+        let rng = &mut TestRng::default();
+        let path = temp_path("outbox-expiry");
+        let node = Arc::new(MockNode::default());
+        node.height.store(10, Ordering::SeqCst);
+        node.broadcast_status.store(503, Ordering::SeqCst);
+        let server = mock_node(node.clone());
+        let api_client = testnet3(server.base_url());
+
+        // A transient failure backs off, holding back the transactions queued after it.
+        let mut outbox = OutboxQueue::<N>::open(&path).unwrap();
+        let (first, second) = (sample_queued(rng), sample_queued(rng));
+        outbox.enqueue(first.clone(), 12).unwrap();
+        outbox.enqueue(second.clone(), 20).unwrap();
+        let events = outbox.pump(&api_client).unwrap();
+        assert!(matches!(&events[..], [OutboxEvent::Retrying { attempts: 1, .. }]));
+        assert_eq!((outbox.entries()[0].attempts(), outbox.entries()[1].attempts()), (1, 0));
+        assert!(outbox.entries()[0].last_error().unwrap().contains("503"));
+        assert!(outbox.pump(&api_client).unwrap().is_empty());
+        assert_eq!(outbox.backoff(3), OutboxQueue::<N>::DEFAULT_BASE_BACKOFF * 4);
+        assert_eq!(outbox.backoff(40), OutboxQueue::<N>::DEFAULT_MAX_BACKOFF);
+
+        // Once the tip passes its expiry height, the transaction is dropped, even while it backs off.
+        node.height.store(13, Ordering::SeqCst);
+        node.broadcast_status.store(200, Ordering::SeqCst);
+        let events = outbox.pump(&api_client).unwrap();
+        assert_eq!(events, [
+            OutboxEvent::Expired { transaction_id: first.id(), expiry_height: 12, height: 13 },
+            OutboxEvent::Broadcast { transaction_id: second.id() }
+        ]);
+        assert_eq!(outbox.len(), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_outbox_rejection() {
+        let rng = &mut TestRng::default();
+        let path = temp_path("outbox-rejection");
+        let node = Arc::new(MockNode::default());
+        node.height.store(10, Ordering::SeqCst);
+        node.broadcast_status.store(400, Ordering::SeqCst);
+        let server = mock_node(node);
+
+        // A permanent rejection drops the transaction, and the queue keeps the reason.
+        let mut outbox = OutboxQueue::<N>::open(&path).unwrap();
+        let transaction = sample_queued(rng);
+        outbox.enqueue(transaction.clone(), 100).unwrap();
+        let events = outbox.pump(&testnet3(server.base_url())).unwrap();
+        assert!(matches!(&events[..], [OutboxEvent::Rejected { reason, .. }] if reason.contains("Invalid fee")));
+        assert!(outbox.is_empty());
+        let outbox = OutboxQueue::<N>::open(&path).unwrap();
+        assert_eq!(outbox.rejections().len(), 1);
+        assert_eq!(outbox.rejections()[0].transaction_id(), transaction.id());
+        assert!(outbox.rejections()[0].reason().contains("Invalid fee"));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Choosing how to scan a range of blocks.
+//!
+//! Finding new records needs every block of a range, but learning what happened to records already known, e.g.
+//! which of them were spent, does not. A [`ScanPlanner`] weighs the blocks of a range against the records known,
+//! given the limits of the node, and returns a [`ScanPlan`] with the strategy it chose and the requests each
+//! strategy would cost. The strategy of a plan may be overridden by the caller.
+
+use crate::{AleoAPIClient, RecordStore};
+
+use snarkvm_console::program::Network;
+use std::{fmt, ops::Range};
+
+/// A way of scanning a range of blocks
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ScanStrategy {
+    /// Fetch every block of the range, and check the ownership of each of its records. This is the only strategy
+    /// that finds new records.
+    BlockScan,
+    /// Fetch every block of the range, and only match its tags and transaction IDs against those already known,
+    /// which finds spends of known records without decrypting any record
+    TagScan,
+    /// Look up each known record or transaction through the find routes of the node, without fetching blocks
+    FindLookups,
+}
+
+impl fmt::Display for ScanStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BlockScan => write!(f, "block scan"),
+            Self::TagScan => write!(f, "tag scan"),
+            Self::FindLookups => write!(f, "find lookups"),
+        }
+    }
+}
+
+/// The estimated cost of scanning a range of blocks with a strategy
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanEstimate {
+    /// The number of requests sent to the node
+    pub requests: u64,
+    /// The number of blocks fetched
+    pub blocks: u64,
+}
+
+/// The strategy chosen to scan a range of blocks, and the estimated cost of each strategy
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanPlan {
+    block_heights: Range<u32>,
+    strategy: ScanStrategy,
+    overridden: bool,
+    block_scan: ScanEstimate,
+    tag_scan: ScanEstimate,
+    find_lookups: Option<ScanEstimate>,
+}
+
+impl ScanPlan {
+    /// Returns the heights of the blocks the plan scans.
+    pub fn block_heights(&self) -> &Range<u32> {
+        &self.block_heights
+    }
+
+    /// Returns the strategy of the plan.
+    pub fn strategy(&self) -> ScanStrategy {
+        self.strategy
+    }
+
+    /// Returns `true` if the strategy was set by the caller rather than chosen by the planner.
+    pub fn is_overridden(&self) -> bool {
+        self.overridden
+    }
+
+    /// Returns the estimated cost of the given strategy, or `None` if the strategy is not available.
+    pub fn estimate(&self, strategy: ScanStrategy) -> Option<ScanEstimate> {
+        match strategy {
+            ScanStrategy::BlockScan => Some(self.block_scan),
+            ScanStrategy::TagScan => Some(self.tag_scan),
+            ScanStrategy::FindLookups => self.find_lookups,
+        }
+    }
+
+    /// Returns the estimated number of requests of the strategy of the plan.
+    pub fn estimated_requests(&self) -> u64 {
+        self.estimate(self.strategy).map_or(0, |estimate| estimate.requests)
+    }
+
+    /// Override the strategy of the plan.
+    ///
+    /// Note that only [`ScanStrategy::BlockScan`] finds new records, and that forcing
+    /// [`ScanStrategy::FindLookups`] where the planner found it unavailable leaves the plan without an estimate.
+    pub fn with_strategy(mut self, strategy: ScanStrategy) -> Self {
+        self.overridden = strategy != self.strategy || self.overridden;
+        self.strategy = strategy;
+        self
+    }
+}
+
+/// A planner choosing the cheapest strategy to scan a range of blocks, given the limits of the node
+///
+/// Finding new records always takes a [`ScanStrategy::BlockScan`]. Otherwise, the planner compares a
+/// [`ScanStrategy::TagScan`] of the range with a [`ScanStrategy::FindLookups`] of the known records or
+/// transactions, and chooses the lookups only if they take fewer requests, so that a small wallet catching up on
+/// a wide range looks up its few records, while a large wallet scans the few blocks of a narrow range.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanPlanner {
+    max_block_request: u32,
+    find_routes: bool,
+    lookup_requests: u32,
+}
+
+impl ScanPlanner {
+    /// The default number of requests a lookup through the find routes takes at most
+    ///
+    /// Finding the block of a serial number takes its transition, transaction, block hash and block height.
+    pub const DEFAULT_LOOKUP_REQUESTS: u32 = 4;
+
+    /// Create a planner for a node serving at most the given number of blocks per request.
+    pub fn new(max_block_request: u32) -> Self {
+        let lookup_requests = Self::DEFAULT_LOOKUP_REQUESTS;
+        Self { max_block_request: max_block_request.max(1), find_routes: true, lookup_requests }
+    }
+
+    /// Create a planner for the node of the given client, with its current maximum block request.
+    pub fn for_client<N: Network>(api_client: &AleoAPIClient<N>) -> Self {
+        Self::new(api_client.max_block_request())
+    }
+
+    /// Set the maximum number of blocks the node serves per request.
+    pub fn with_max_block_request(mut self, max_block_request: u32) -> Self {
+        self.max_block_request = max_block_request.max(1);
+        self
+    }
+
+    /// Set whether the node serves the find routes. Without them, lookups are never chosen.
+    pub fn with_find_routes(mut self, find_routes: bool) -> Self {
+        self.find_routes = find_routes;
+        self
+    }
+
+    /// Set the number of requests a lookup takes at most.
+    pub fn with_lookup_requests(mut self, lookup_requests: u32) -> Self {
+        self.lookup_requests = lookup_requests.max(1);
+        self
+    }
+
+    /// Returns the maximum number of blocks the node serves per request.
+    pub fn max_block_request(&self) -> u32 {
+        self.max_block_request
+    }
+
+    /// Returns `true` if the node serves the find routes.
+    pub fn find_routes(&self) -> bool {
+        self.find_routes
+    }
+
+    /// Returns the number of requests a lookup takes at most.
+    pub fn lookup_requests(&self) -> u32 {
+        self.lookup_requests
+    }
+
+    /// Plan a scan of the blocks at the given heights, for the given number of known records or transactions.
+    ///
+    /// With `discover`, the scan must find new records, so it is always a [`ScanStrategy::BlockScan`].
+    pub fn plan(&self, block_heights: Range<u32>, known: usize, discover: bool) -> ScanPlan {
+        self.plan_with(block_heights, known, discover, self.find_routes)
+    }
+
+    /// Plan a scan of the blocks at the given heights for the unspent records of the given store.
+    ///
+    /// Records found by a watch-only account have no serial number to look up, so a store holding any such
+    /// unspent record is never planned with lookups.
+    pub fn plan_store<N: Network>(
+        &self,
+        store: &RecordStore<N>,
+        block_heights: Range<u32>,
+        discover: bool,
+    ) -> ScanPlan {
+        let unspent = store.iter().map(|(_, stored)| stored).filter(|stored| stored.spent_height().is_none());
+        let (known, watch_only) = unspent.fold((0, false), |(known, watch_only), stored| {
+            (known + 1, watch_only || stored.is_watch_only())
+        });
+        self.plan_with(block_heights, known, discover, self.find_routes && !watch_only)
+    }
+
+    // Plan a scan of the blocks at the given heights, with lookups available or not
+    fn plan_with(&self, block_heights: Range<u32>, known: usize, discover: bool, lookups: bool) -> ScanPlan {
+        let blocks = block_heights.end.saturating_sub(block_heights.start) as u64;
+        let chunk = self.max_block_request as u64;
+        let block_scan = ScanEstimate { requests: (blocks + chunk - 1) / chunk, blocks };
+        let lookup_requests = known as u64 * self.lookup_requests as u64;
+        let find_lookups = lookups.then_some(ScanEstimate { requests: lookup_requests, blocks: 0 });
+        let strategy = match find_lookups {
+            _ if discover => ScanStrategy::BlockScan,
+            Some(lookups) if lookups.requests < block_scan.requests => ScanStrategy::FindLookups,
+            _ => ScanStrategy::TagScan,
+        };
+        ScanPlan { block_heights, strategy, overridden: false, block_scan, tag_scan: block_scan, find_lookups }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{sample_record, CurrentNetwork};
+
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        prelude::Uniform,
+        types::Field,
+    };
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    // Returns a store with the given numbers of unspent and spent records
+    fn sample_store(unspent: usize, spent: usize, watch_only: bool, rng: &mut TestRng) -> RecordStore<N> {
+        let owner = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let mut store = RecordStore::new();
+        for index in 0..unspent + spent {
+            let commitment = Field::rand(rng);
+            let (record, _) = sample_record(owner, 1, rng);
+            match watch_only {
+                true => store.insert_watch_only(commitment, record, 1),
+                false => store.insert(commitment, record, 1),
+            };
+            if index >= unspent {
+                store.mark_spent(&commitment, 2);
+            }
+        }
+        store
+    }
+
+    #[test]
+    fn test_plan_thresholds() {
+        let planner = ScanPlanner::new(50);
+
+        // Finding new records always scans the blocks, whatever the cost of the lookups.
+        let plan = planner.plan(0..1000, 1, true);
+        assert_eq!(plan.strategy(), ScanStrategy::BlockScan);
+        assert_eq!(plan.estimate(ScanStrategy::BlockScan), Some(ScanEstimate { requests: 20, blocks: 1000 }));
+        assert_eq!(plan.estimate(ScanStrategy::FindLookups), Some(ScanEstimate { requests: 4, blocks: 0 }));
+
+        // A wide range is looked up while the lookups take fewer requests than the blocks, up to 4 records for 20
+        // block requests.
+        for (known, strategy, requests) in
+            [(0, ScanStrategy::FindLookups, 0), (4, ScanStrategy::FindLookups, 16), (5, ScanStrategy::TagScan, 20)]
+        {
+            let plan = planner.plan(0..1000, known, false);
+            assert_eq!((plan.strategy(), plan.estimated_requests()), (strategy, requests), "{known} records");
+        }
+
+        // A narrow range is scanned, as a single request fetches its blocks.
+        let plan = planner.plan(100..150, 1, false);
+        assert_eq!((plan.strategy(), plan.estimated_requests()), (ScanStrategy::TagScan, 1));
+        let plan = planner.plan(100..151, 1, false);
+        assert_eq!((plan.strategy(), plan.estimated_requests()), (ScanStrategy::TagScan, 2));
+
+        // A node with smaller block requests favors the lookups, and one without find routes never takes them.
+        let plan = planner.clone().with_max_block_request(10).plan(100..150, 1, false);
+        assert_eq!((plan.strategy(), plan.estimated_requests()), (ScanStrategy::FindLookups, 4));
+        let plan = planner.with_find_routes(false).plan(0..1000, 1, false);
+        assert_eq!((plan.strategy(), plan.estimate(ScanStrategy::FindLookups)), (ScanStrategy::TagScan, None));
+    }
+
+    #[test]
+    fn test_plan_store() {
+        let rng = &mut TestRng::default();
+        let planner = ScanPlanner::new(50);
+
+        // Only unspent records are looked up.
+        let store = sample_store(3, 10, false, rng);
+        let plan = planner.plan_store(&store, 0..1000, false);
+        assert_eq!(plan.strategy(), ScanStrategy::FindLookups);
+        assert_eq!(plan.estimated_requests(), 12);
+
+        // A larger store is scanned instead, and the plan may be overridden.
+        let store = sample_store(30, 0, false, rng);
+        let plan = planner.plan_store(&store, 0..1000, false);
+        assert_eq!((plan.strategy(), plan.estimated_requests()), (ScanStrategy::TagScan, 20));
+        assert_eq!(plan.estimate(ScanStrategy::FindLookups).unwrap().requests, 120);
+        let plan = plan.with_strategy(ScanStrategy::FindLookups);
+        assert!(plan.is_overridden());
+        assert_eq!((plan.strategy(), plan.estimated_requests()), (ScanStrategy::FindLookups, 120));
+
+        // Watch-only records have no serial number to look up.
+        let store = sample_store(1, 0, true, rng);
+        let plan = planner.plan_store(&store, 0..1000, false);
+        assert_eq!((plan.strategy(), plan.estimate(ScanStrategy::FindLookups)), (ScanStrategy::TagScan, None));
+    }
+}
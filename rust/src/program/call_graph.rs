@@ -0,0 +1,352 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramInspector;
+#[cfg(not(feature = "async"))]
+use crate::AleoAPIClient;
+
+use snarkvm_console::program::{Identifier, Network, ProgramID};
+use snarkvm_synthesizer::{CallOperator, Instruction, Program};
+
+#[cfg(not(feature = "async"))]
+use anyhow::anyhow;
+use anyhow::Result;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Write,
+};
+
+/// What a node of a [`CallGraph`] is
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallNodeKind {
+    /// A function, which every program exposes to be called from outside of it
+    Function,
+    /// A closure, which only the functions and closures of its program can call
+    Closure,
+    /// A function of a program that was not given to the graph, so that its body is unknown
+    External,
+}
+
+/// A function or closure of a [`CallGraph`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallNode {
+    /// The locator of the function or closure, e.g. `token.aleo/transfer`
+    pub id: String,
+    /// What the node is
+    pub kind: CallNodeKind,
+    /// Whether a function of the inspected program calls the node, directly or not, or is the node
+    pub reachable: bool,
+}
+
+/// A `call` instruction of a [`CallGraph`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallEdge {
+    /// The locator of the calling function or closure
+    pub caller: String,
+    /// The locator of the called function or closure
+    pub callee: String,
+    /// The index of the `call` instruction among the instructions of the caller
+    pub instruction: usize,
+}
+
+/// The code of a program that none of its functions can run
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadCode {
+    /// The locators of the closures of the program that none of its functions calls, directly or not
+    pub closures: Vec<String>,
+    /// The imports of the program that none of its functions calls, directly or not
+    pub imports: Vec<String>,
+}
+
+impl DeadCode {
+    /// Returns `true` if every closure and import of the program is used.
+    pub fn is_empty(&self) -> bool {
+        self.closures.is_empty() && self.imports.is_empty()
+    }
+}
+
+/// The functions and closures of a program, the functions of other programs it calls, and the calls between them,
+/// built by [`ProgramInspector::call_graph`]
+///
+/// Nodes are listed in the order they were found: the functions of the program, its closures, then the functions
+/// and closures of other programs as they are called. Edges are listed by caller, in the order of their
+/// instructions, so the graph and its serializations are deterministic.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallGraph {
+    /// The ID of the inspected program
+    pub program: String,
+    /// The functions and closures of the graph
+    pub nodes: Vec<CallNode>,
+    /// The calls between the nodes
+    pub edges: Vec<CallEdge>,
+    /// The closures and imports of the inspected program that none of its functions uses
+    pub dead_code: DeadCode,
+}
+
+impl CallGraph {
+    /// Returns the graph in the DOT language of Graphviz.
+    ///
+    /// Nodes are boxes for functions, ellipses for closures, and dashed boxes for external functions, and nodes
+    /// that are not reachable are grey. Unused imports are grey folders, and edges are labeled with the index of the
+    /// `call` instruction of the caller.
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph \"{}\" {{\n", self.program);
+        for node in &self.nodes {
+            let shape = match node.kind {
+                CallNodeKind::Function => "shape=box",
+                CallNodeKind::Closure => "shape=ellipse",
+                CallNodeKind::External => "shape=box, style=dashed",
+            };
+            let color = if node.reachable { "" } else { ", color=grey, fontcolor=grey" };
+            let _ = writeln!(dot, "    \"{}\" [{shape}{color}];", node.id);
+        }
+        for import in &self.dead_code.imports {
+            let _ = writeln!(dot, "    \"{import}\" [shape=folder, color=grey, fontcolor=grey];");
+        }
+        for edge in &self.edges {
+            let _ = writeln!(dot, "    \"{}\" -> \"{}\" [label=\"{}\"];", edge.caller, edge.callee, edge.instruction);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Returns the graph as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl<N: Network> ProgramInspector<N> {
+    /// Returns the call graph of the program, following its calls into the given imports.
+    ///
+    /// The `imports` are the programs the program imports, directly or not, in any order, e.g. as fetched by
+    /// [`crate::AleoAPIClient::get_program_with_imports`]. Calls into programs that are not given are leaves of the
+    /// graph. Every function of a program can be called from outside of it, so only closures and imports can be
+    /// dead code.
+    pub fn call_graph(&self, imports: &[Program<N>]) -> CallGraph {
+        let program = self.program();
+        let find_program = |program_id: &ProgramID<N>| {
+            [program].into_iter().chain(imports).find(|candidate| candidate.id() == program_id)
+        };
+
+        let mut nodes = IndexMap::new();
+        for name in program.functions().keys() {
+            nodes.insert(locator(program.id(), name), (*program.id(), *name, CallNodeKind::Function));
+        }
+        for name in program.closures().keys() {
+            nodes.insert(locator(program.id(), name), (*program.id(), *name, CallNodeKind::Closure));
+        }
+
+        // Walk the bodies of the nodes, adding the nodes they call as they are found.
+        let mut edges = vec![];
+        let mut pending = nodes.values().copied().collect::<VecDeque<_>>();
+        while let Some((program_id, name, kind)) = pending.pop_front() {
+            let instructions = match (find_program(&program_id), kind) {
+                (Some(program), CallNodeKind::Function) => {
+                    program.get_function(&name).map(|function| function.instructions().to_vec())
+                }
+                (Some(program), CallNodeKind::Closure) => {
+                    program.get_closure(&name).map(|closure| closure.instructions().to_vec())
+                }
+                _ => continue,
+            };
+            let caller = locator(&program_id, &name);
+            for (index, instruction) in instructions.unwrap_or_default().iter().enumerate() {
+                let Instruction::Call(call) = instruction else { continue };
+                let (callee_program, callee_name) = match call.operator() {
+                    CallOperator::Locator(callee) => (*callee.program_id(), *callee.resource()),
+                    CallOperator::Resource(callee) => (program_id, *callee),
+                };
+                let callee = locator(&callee_program, &callee_name);
+                if !nodes.contains_key(&callee) {
+                    let kind = match find_program(&callee_program) {
+                        Some(program) if program.contains_closure(&callee_name) => CallNodeKind::Closure,
+                        Some(program) if program.contains_function(&callee_name) => CallNodeKind::Function,
+                        _ => CallNodeKind::External,
+                    };
+                    nodes.insert(callee.clone(), (callee_program, callee_name, kind));
+                    pending.push_back((callee_program, callee_name, kind));
+                }
+                edges.push(CallEdge { caller: caller.clone(), callee, instruction: index });
+            }
+        }
+
+        // Every function of the program is an entry point, from which the other nodes are reached.
+        let mut reachable = HashSet::new();
+        let mut pending =
+            program.functions().keys().map(|name| locator(program.id(), name)).collect::<VecDeque<_>>();
+        while let Some(node) = pending.pop_front() {
+            if reachable.insert(node.clone()) {
+                pending.extend(edges.iter().filter(|edge| edge.caller == node).map(|edge| edge.callee.clone()));
+            }
+        }
+
+        let closures = program.closures().keys().map(|name| locator(program.id(), name));
+        let closures = closures.filter(|closure| !reachable.contains(closure)).collect();
+        let used = reachable.iter().filter_map(|node| node.split_once('/')).map(|(program_id, _)| program_id);
+        let used = used.collect::<HashSet<_>>();
+        let imports = program.imports().keys().map(ToString::to_string);
+        let imports = imports.filter(|import| !used.contains(import.as_str())).collect();
+
+        let nodes = nodes.into_iter().map(|(id, (_, _, kind))| {
+            let reachable = reachable.contains(&id);
+            CallNode { id, kind, reachable }
+        });
+        CallGraph {
+            program: program.id().to_string(),
+            nodes: nodes.collect(),
+            edges,
+            dead_code: DeadCode { closures, imports },
+        }
+    }
+
+    /// Fetch the program with the given ID and every program it imports, directly or not, and return its call graph.
+    #[cfg(not(feature = "async"))]
+    pub fn fetch_call_graph(
+        api_client: &AleoAPIClient<N>,
+        program_id: impl TryInto<ProgramID<N>>,
+    ) -> Result<CallGraph> {
+        let mut programs = api_client.get_program_with_imports(program_id)?;
+        let program = programs.pop().ok_or_else(|| anyhow!("No program was fetched"))?;
+        Ok(Self::new(program).call_graph(&programs))
+    }
+}
+
+// Returns the locator of a function or closure, e.g. `token.aleo/transfer`
+fn locator<N: Network>(program_id: &ProgramID<N>, name: &Identifier<N>) -> String {
+    format!("{program_id}/{name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::CurrentNetwork;
+    #[cfg(not(feature = "async"))]
+    use crate::{
+        test_helpers::{MockResponse, MockServer},
+        testnet3,
+    };
+
+    use std::str::FromStr;
+
+    const TOKEN_LIB: &str = "program token_lib.aleo;
+
+closure clamp:
+    input r0 as u64;
+    lt r0 1000u64 into r1;
+    ternary r1 r0 1000u64 into r2;
+    output r2 as u64;
+
+function mint_token:
+    input r0 as u64.private;
+    call clamp r0 into r1;
+    output r1 as u64.private;
+";
+
+    const UNUSED_LIB: &str = "program unused_lib.aleo;
+
+function noop:
+    input r0 as u64.private;
+    output r0 as u64.private;
+";
+
+    // A program calling an imported function, with a closure called through another closure, a closure no function
+    // calls, and an import it never calls
+    const AUDITED: &str = "import token_lib.aleo;
+import unused_lib.aleo;
+
+program audited.aleo;
+
+closure twice:
+    input r0 as u64;
+    add r0 r0 into r1;
+    output r1 as u64;
+
+closure quadruple:
+    input r0 as u64;
+    call twice r0 into r1;
+    call twice r1 into r2;
+    output r2 as u64;
+
+closure orphan:
+    input r0 as u64;
+    mul r0 r0 into r1;
+    output r1 as u64;
+
+function scale:
+    input r0 as u64.private;
+    call quadruple r0 into r1;
+    output r1 as u64.private;
+
+function mint:
+    input r0 as u64.private;
+    add r0 1u64 into r1;
+    call token_lib.aleo/mint_token r1 into r2;
+    output r2 as u64.private;
+";
+
+    const CALL_GRAPH_DOT: &str = include_str!("../../tests/fixtures/call_graph.dot");
+    const CALL_GRAPH_JSON: &str = include_str!("../../tests/fixtures/call_graph.json");
+
+    fn program(source: &str) -> Program<CurrentNetwork> {
+        Program::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn test_call_graph_golden() {
+        let inspector = ProgramInspector::new(program(AUDITED));
+        let graph = inspector.call_graph(&[program(UNUSED_LIB), program(TOKEN_LIB)]);
+        assert_eq!(graph.dead_code.closures, ["audited.aleo/orphan"]);
+        assert_eq!(graph.dead_code.imports, ["unused_lib.aleo"]);
+        assert_eq!(graph.to_dot(), CALL_GRAPH_DOT);
+        assert_eq!(graph.to_json().unwrap(), CALL_GRAPH_JSON.trim_end());
+        assert_eq!(serde_json::from_str::<CallGraph>(CALL_GRAPH_JSON).unwrap(), graph);
+    }
+
+    #[test]
+    fn test_call_graph_without_imports() {
+        // Calls into programs that were not given are external leaves, and count as using their import.
+        let graph = ProgramInspector::new(program(AUDITED)).call_graph(&[]);
+        let external = graph.nodes.iter().find(|node| node.id == "token_lib.aleo/mint_token").unwrap();
+        assert_eq!((external.kind, external.reachable), (CallNodeKind::External, true));
+        assert!(!graph.nodes.iter().any(|node| node.id.starts_with("token_lib.aleo/clamp")));
+        assert_eq!(graph.dead_code.imports, ["unused_lib.aleo"]);
+
+        // A program without closures or imports has no dead code.
+        let graph = ProgramInspector::new(program(UNUSED_LIB)).call_graph(&[]);
+        assert!(graph.edges.is_empty());
+        assert!(graph.dead_code.is_empty());
+    }
+
+    #[cfg(not(feature = "async"))]
+    #[test]
+    fn test_fetch_call_graph() {
+        let server = MockServer::start(|request| {
+            let source = match request.path.as_str() {
+                "/testnet3/program/audited.aleo" => AUDITED,
+                "/testnet3/program/token_lib.aleo" => TOKEN_LIB,
+                "/testnet3/program/unused_lib.aleo" => UNUSED_LIB,
+                _ => return None,
+            };
+            Some(MockResponse::json(serde_json::to_string(source).unwrap()))
+        });
+        let graph = ProgramInspector::fetch_call_graph(&testnet3(server.base_url()), "audited.aleo").unwrap();
+        assert_eq!(graph.to_dot(), CALL_GRAPH_DOT);
+    }
+}
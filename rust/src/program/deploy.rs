@@ -0,0 +1,532 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+#[cfg(not(feature = "async"))]
+use crate::{ConfirmationTimeout, Microcredits};
+
+use snarkvm_console::program::{
+    Entry,
+    EntryType,
+    Identifier,
+    Network,
+    Plaintext,
+    PlaintextType,
+    Record,
+    Value,
+    ValueType,
+};
+use snarkvm_synthesizer::{Program, Transaction};
+
+#[cfg(not(feature = "async"))]
+use anyhow::bail;
+use anyhow::{anyhow, ensure, Result};
+use std::{error::Error, fmt, time::Duration};
+
+/// The fees paid by [`ProgramManager::deploy_and_initialize`], each from its own record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeploymentFees<N: Network> {
+    /// The fee in gates paid by the deployment
+    pub deployment_fee: u64,
+    /// The record paying the fee of the deployment
+    pub deployment_fee_record: Record<N, Plaintext<N>>,
+    /// The fee in gates paid by the initialization call
+    pub initialization_fee: u64,
+    /// The record paying the fee of the initialization call
+    pub initialization_fee_record: Record<N, Plaintext<N>>,
+}
+
+/// Options of [`ProgramManager::deploy_and_initialize`]
+///
+/// By default the initialization call is checked before anything is built, and the deployment is awaited for
+/// up to five minutes, checking every five seconds.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InitializationOptions {
+    preflight: bool,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl Default for InitializationOptions {
+    fn default() -> Self {
+        Self { preflight: true, poll_interval: Duration::from_secs(5), timeout: Duration::from_secs(300) }
+    }
+}
+
+impl InitializationOptions {
+    /// Create the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check that the initialization function exists and that its inputs type-check with
+    /// [`ProgramManager::check_initialization`] before building the deployment, which takes far longer.
+    pub fn with_preflight(mut self, preflight: bool) -> Self {
+        self.preflight = preflight;
+        self
+    }
+
+    /// Set the interval between checks for the confirmation of the deployment.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Set the time to wait for the confirmation of the deployment before giving up on the initialization.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns `true` if the initialization call is checked before anything is built.
+    pub fn preflight(&self) -> bool {
+        self.preflight
+    }
+
+    /// Returns the interval between checks for the confirmation of the deployment.
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Returns the time to wait for the confirmation of the deployment.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+/// The error returned when a program was deployed, but its initialization call failed, so the program is
+/// deployed but uninitialized
+///
+/// The caller must initialize the program before anyone else calls it.
+#[derive(Debug)]
+pub struct InitializationFailed<N: Network> {
+    deployment_id: N::TransactionID,
+    initialization_id: N::TransactionID,
+    error: anyhow::Error,
+}
+
+impl<N: Network> InitializationFailed<N> {
+    /// Returns the ID of the confirmed deployment.
+    pub fn deployment_id(&self) -> N::TransactionID {
+        self.deployment_id
+    }
+
+    /// Returns the ID of the initialization call that failed.
+    pub fn initialization_id(&self) -> N::TransactionID {
+        self.initialization_id
+    }
+
+    /// Returns the error of the initialization call.
+    pub fn error(&self) -> &anyhow::Error {
+        &self.error
+    }
+}
+
+impl<N: Network> fmt::Display for InitializationFailed<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Deployment '{}' was confirmed, but broadcasting the initialization '{}' failed: {}",
+            self.deployment_id, self.initialization_id, self.error
+        )
+    }
+}
+
+impl<N: Network> Error for InitializationFailed<N> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Build a transaction deploying the given program, whose fee of `fee` gates is paid by `fee_record`.
+    ///
+    /// The `imports` are the programs imported by `program`, in the order expected by
+    /// [`ProgramManager::build_execution`]. Computing the circuit keys of the program counts as a proof of the
+    /// program ID against the [`crate::ProvingLimits`] of the program manager.
+    pub fn build_deployment(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        fee: u64,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<Transaction<N>> {
+        ensure!(***fee_record.gates() >= fee, "Fee record does not hold enough gates to pay the fee");
+        self.signer()?;
+        let _lease = self.lock_records(&[&fee_record])?;
+
+        // Add the imports, apart from the programs built into the VM.
+        let vm = self.vm_with(&imports.iter().collect::<Vec<_>>())?;
+
+        // Compute the deployment, then prove the fee.
+        let (deployment_vm, deployed) = (vm.clone(), program.clone());
+        let deployment =
+            self.prove(program.id().to_string(), move || deployment_vm.deploy(&deployed, &mut rand::thread_rng()))?;
+        let fee = self.prove_fee(&vm, fee_record, fee)?;
+        self.log_built(Transaction::from_deployment(deployment, fee)?)
+    }
+
+    /// Build a transaction deploying the given program and broadcast it to the network.
+    #[cfg(not(feature = "async"))]
+    pub fn deploy(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        fee: u64,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        let transaction = self.build_deployment(program, imports, fee, fee_record)?;
+        let transaction_id = transaction.id();
+        self.broadcast(transaction)?;
+        Ok(transaction_id)
+    }
+
+    /// Check that the program has a function of the given name, and that the inputs type-check against its
+    /// inputs, without building anything.
+    ///
+    /// Records passed to inputs of external record types are not checked, as their programs are not at hand.
+    pub fn check_initialization(program: &Program<N>, function_name: Identifier<N>, inputs: &[Value<N>]) -> Result<()> {
+        let function = program
+            .get_function(&function_name)
+            .map_err(|_| anyhow!("Program '{}' has no function '{function_name}'", program.id()))?;
+        let input_types = function.input_types();
+        ensure!(
+            input_types.len() == inputs.len(),
+            "Function '{function_name}' expects {} inputs, but {} were given",
+            input_types.len(),
+            inputs.len()
+        );
+        for (index, (input, value_type)) in inputs.iter().zip(&input_types).enumerate() {
+            let matches = match (input, value_type) {
+                (Value::Plaintext(plaintext), ValueType::Constant(plaintext_type))
+                | (Value::Plaintext(plaintext), ValueType::Public(plaintext_type))
+                | (Value::Plaintext(plaintext), ValueType::Private(plaintext_type)) => {
+                    matches_plaintext(program, plaintext, plaintext_type)
+                }
+                (Value::Record(record), ValueType::Record(record_name)) => matches_record(program, record, record_name),
+                (Value::Record(_), ValueType::ExternalRecord(_)) => true,
+                _ => false,
+            };
+            ensure!(matches, "Input {index} of function '{function_name}' is not of type '{value_type}'");
+        }
+        Ok(())
+    }
+
+    /// Deploy a program and call its initialization function, e.g. to set its admin or mint its initial supply,
+    /// returning the IDs of the deployment and of the initialization call.
+    ///
+    /// Both transactions are built before anything is broadcast, so that the initialization call is broadcast
+    /// as soon as the deployment is confirmed, leaving as little room as possible for someone else to call the
+    /// program first. If the deployment fails, nothing is deployed. If the initialization call fails once the
+    /// deployment is confirmed, the error is an [`InitializationFailed`], telling that the program is deployed
+    /// but uninitialized.
+    #[cfg(not(feature = "async"))]
+    pub fn deploy_and_initialize(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        init_function: Identifier<N>,
+        init_inputs: Vec<Value<N>>,
+        fees: DeploymentFees<N>,
+        options: InitializationOptions,
+    ) -> Result<(N::TransactionID, N::TransactionID)> {
+        ensure!(
+            fees.deployment_fee_record != fees.initialization_fee_record,
+            "The deployment and the initialization must pay their fees with different records"
+        );
+        if options.preflight {
+            Self::check_initialization(program, init_function, &init_inputs)?;
+        }
+        let deployment = self.build_deployment(program, imports, fees.deployment_fee, fees.deployment_fee_record)?;
+        let initialization = self.build_execution(
+            program,
+            imports,
+            init_function,
+            init_inputs,
+            Microcredits(fees.initialization_fee),
+            fees.initialization_fee_record,
+        )?;
+        self.broadcast_and_initialize(deployment, initialization, options)
+    }
+
+    /// Broadcast a deployment, wait for its confirmation, then broadcast the initialization call of the deployed
+    /// program, as [`ProgramManager::deploy_and_initialize`] does with the transactions it builds.
+    #[cfg(not(feature = "async"))]
+    pub fn broadcast_and_initialize(
+        &self,
+        deployment: Transaction<N>,
+        initialization: Transaction<N>,
+        options: InitializationOptions,
+    ) -> Result<(N::TransactionID, N::TransactionID)> {
+        let (deployment_id, initialization_id) = (deployment.id(), initialization.id());
+        self.broadcast(deployment)?;
+        self.wait_for_confirmation(deployment_id, options)?;
+        match self.broadcast(initialization) {
+            Ok(_) => Ok((deployment_id, initialization_id)),
+            Err(error) => Err(InitializationFailed::<N> { deployment_id, initialization_id, error }.into()),
+        }
+    }
+
+    // Poll the network until the deployment is final at the confirmation depth of the client
+    #[cfg(not(feature = "async"))]
+    fn wait_for_confirmation(&self, deployment_id: N::TransactionID, options: InitializationOptions) -> Result<()> {
+        match self.api_client.wait_for_confirmation(deployment_id, options.timeout, options.poll_interval, |_| ()) {
+            Ok(_) => Ok(()),
+            Err(error) if error.is::<ConfirmationTimeout<N>>() => bail!(
+                "Deployment '{deployment_id}' was not confirmed within {:?}, so the initialization was not broadcast",
+                options.timeout
+            ),
+            Err(error) => Err(anyhow!("Failed to check the confirmation of deployment '{deployment_id}': {error}")),
+        }
+    }
+}
+
+// Returns `true` if the plaintext has the layout of the plaintext type, whose structs are defined by the program
+pub(super) fn matches_plaintext<N: Network>(
+    program: &Program<N>,
+    plaintext: &Plaintext<N>,
+    plaintext_type: &PlaintextType<N>,
+) -> bool {
+    match (plaintext, plaintext_type) {
+        (Plaintext::Literal(literal, _), PlaintextType::Literal(literal_type)) => literal.to_type() == *literal_type,
+        (Plaintext::Struct(members, _), PlaintextType::Struct(struct_name)) => {
+            program.get_struct(struct_name).map_or(false, |struct_| {
+                struct_.members().len() == members.len()
+                    && struct_.members().iter().zip(members).all(|((name, member_type), (member_name, member))| {
+                        name == member_name && matches_plaintext(program, member, member_type)
+                    })
+            })
+        }
+        _ => false,
+    }
+}
+
+// Returns `true` if the record has the entries of the record type of the program
+pub(super) fn matches_record<N: Network>(
+    program: &Program<N>,
+    record: &Record<N, Plaintext<N>>,
+    record_name: &Identifier<N>,
+) -> bool {
+    program.get_record(record_name).map_or(false, |record_type| {
+        record_type.entries().len() == record.data().len()
+            && record_type.entries().iter().zip(record.data()).all(|((name, entry_type), (entry_name, entry))| {
+                let plaintext_type = match entry_type {
+                    EntryType::Constant(plaintext_type)
+                    | EntryType::Public(plaintext_type)
+                    | EntryType::Private(plaintext_type) => plaintext_type,
+                };
+                let plaintext = match entry {
+                    Entry::Constant(plaintext) | Entry::Public(plaintext) | Entry::Private(plaintext) => plaintext,
+                };
+                name == entry_name && matches_plaintext(program, plaintext, plaintext_type)
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testnet3;
+    #[cfg(not(feature = "async"))]
+    use crate::test_helpers::{
+        genesis_block,
+        sample_output,
+        sample_record,
+        sample_transaction,
+        sample_transition,
+        MockResponse,
+        MockServer,
+    };
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        network::Testnet3,
+    };
+    use snarkvm_utilities::TestRng;
+
+    use std::str::FromStr;
+    #[cfg(not(feature = "async"))]
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    type N = Testnet3;
+
+    const INIT_PROGRAM: &str = r"program init_test.aleo;
+
+struct supply:
+    total as u64;
+    cap as u64;
+
+function initialize:
+    input r0 as address.public;
+    input r1 as supply.public;
+    lte r1.total r1.cap into r2;
+    assert.eq r2 true;
+";
+
+    // Start a mock node confirming the deployment after `pending` checks and rejecting the initialization if
+    // asked, counting the broadcasts
+    #[cfg(not(feature = "async"))]
+    fn mock_deployment_server(
+        deployment: &Transaction<N>,
+        pending: usize,
+        reject_initialization: bool,
+    ) -> (MockServer, Arc<AtomicUsize>) {
+        let deployment_id = deployment.id().to_string();
+        let block = genesis_block();
+        let (block_json, block_hash) = (block.to_string(), serde_json::to_string(&block.hash()).unwrap());
+        let broadcasts = Arc::new(AtomicUsize::new(0));
+        let checks = AtomicUsize::new(0);
+        let counted = broadcasts.clone();
+        let server = MockServer::start(move |request| {
+            if request.path == "/testnet3/transaction/broadcast" {
+                if counted.fetch_add(1, Ordering::SeqCst) == 1 && reject_initialization {
+                    return Some(MockResponse::text(500, "Program 'init_test.aleo' is being initialized"));
+                }
+                return Some(MockResponse::json(&block_json));
+            }
+            if request.path.starts_with("/testnet3/height/") || request.path == "/testnet3/latest/height" {
+                return Some(MockResponse::json(0));
+            }
+            if request.path == "/testnet3/block/0" {
+                return Some(MockResponse::json(&block_json));
+            }
+            if request.path.strip_prefix("/testnet3/find/blockHash/")? != deployment_id {
+                return None;
+            }
+            if checks.fetch_add(1, Ordering::SeqCst) < pending {
+                return None;
+            }
+            Some(MockResponse::json(&block_hash))
+        });
+        (server, broadcasts)
+    }
+
+    fn sample_manager(base_url: &str) -> ProgramManager<N> {
+        let private_key = PrivateKey::new(&mut TestRng::default()).unwrap();
+        ProgramManager::new(private_key, testnet3(base_url))
+    }
+
+    // Sample two transactions standing in for a deployment and its initialization
+    #[cfg(not(feature = "async"))]
+    fn sample_transactions() -> (Transaction<N>, Transaction<N>) {
+        let rng = &mut TestRng::default();
+        let owner = Address::try_from(PrivateKey::new(rng).unwrap()).unwrap();
+        let mut sample = || sample_transaction([sample_transition(&[], &[sample_output(owner, 1, rng)], rng)]);
+        (sample(), sample())
+    }
+
+    #[cfg(not(feature = "async"))]
+    fn fast_options() -> InitializationOptions {
+        InitializationOptions::new().with_poll_interval(Duration::from_millis(10)).with_timeout(Duration::from_secs(5))
+    }
+
+    #[cfg(not(feature = "async"))]
+    #[test]
+    fn test_broadcast_and_initialize() {
+        let (deployment, initialization) = sample_transactions();
+
+        // The initialization is broadcast once the deployment is confirmed.
+        let (server, broadcasts) = mock_deployment_server(&deployment, 2, false);
+        let manager = sample_manager(server.base_url());
+        let ids = manager.broadcast_and_initialize(deployment.clone(), initialization.clone(), fast_options()).unwrap();
+        assert_eq!(ids, (deployment.id(), initialization.id()));
+        assert_eq!(broadcasts.load(Ordering::SeqCst), 2);
+
+        // A deployment that is not confirmed in time is not initialized.
+        let (server, broadcasts) = mock_deployment_server(&deployment, usize::MAX, false);
+        let manager = sample_manager(server.base_url());
+        let options = fast_options().with_timeout(Duration::from_millis(50));
+        let error = manager.broadcast_and_initialize(deployment.clone(), initialization.clone(), options).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Deployment '{}' was not confirmed within 50ms, so the initialization was not broadcast",
+                deployment.id()
+            )
+        );
+        assert!(error.downcast_ref::<InitializationFailed<N>>().is_none());
+        assert_eq!(broadcasts.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(not(feature = "async"))]
+    #[test]
+    fn test_initialization_failed_after_deployment() {
+        let (deployment, initialization) = sample_transactions();
+        let (server, broadcasts) = mock_deployment_server(&deployment, 0, true);
+        let manager = sample_manager(server.base_url());
+
+        // The error tells that the program is deployed but uninitialized.
+        let error =
+            manager.broadcast_and_initialize(deployment.clone(), initialization.clone(), fast_options()).unwrap_err();
+        assert_eq!(broadcasts.load(Ordering::SeqCst), 2);
+        let error = error.downcast::<InitializationFailed<N>>().unwrap();
+        assert_eq!((error.deployment_id(), error.initialization_id()), (deployment.id(), initialization.id()));
+        assert!(error.error().to_string().contains("is being initialized"), "{}", error.error());
+        assert!(error.to_string().starts_with(&format!(
+            "Deployment '{}' was confirmed, but broadcasting the initialization '{}' failed: ",
+            deployment.id(),
+            initialization.id()
+        )));
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn test_check_initialization() {
+        let program = Program::<N>::from_str(INIT_PROGRAM).unwrap();
+        let manager = sample_manager("http://127.0.0.1:9");
+        let owner = Address::try_from(manager.private_key().unwrap()).unwrap();
+        let admin = Value::from_str(&owner.to_string()).unwrap();
+        let initialize = Identifier::from_str("initialize").unwrap();
+        let supply = Value::from_str("{ total: 5u64, cap: 10u64 }").unwrap();
+        let check = |function_name, inputs: &[Value<N>]| {
+            ProgramManager::check_initialization(&program, function_name, inputs).map_err(|error| error.to_string())
+        };
+
+        check(initialize, &[admin.clone(), supply.clone()]).unwrap();
+        let missing = Identifier::from_str("init").unwrap();
+        assert_eq!(check(missing, &[]).unwrap_err(), "Program 'init_test.aleo' has no function 'init'");
+        assert_eq!(
+            check(initialize, std::slice::from_ref(&admin)).unwrap_err(),
+            "Function 'initialize' expects 2 inputs, but 1 were given"
+        );
+        let mistyped = [admin.clone(), Value::from_str("{ total: 5u32, cap: 10u64 }").unwrap()];
+        assert_eq!(
+            check(initialize, &mistyped).unwrap_err(),
+            "Input 1 of function 'initialize' is not of type 'supply.public'"
+        );
+        let reordered = [admin.clone(), Value::from_str("{ cap: 10u64, total: 5u64 }").unwrap()];
+        assert!(check(initialize, &reordered).is_err());
+        assert!(check(initialize, &[supply.clone(), admin]).is_err());
+
+        // With the preflight check, a mistyped initialization fails before anything is built or broadcast.
+        #[cfg(not(feature = "async"))]
+        {
+            let rng = &mut TestRng::default();
+            let fees = DeploymentFees {
+                deployment_fee: 1,
+                deployment_fee_record: sample_record(owner, 10, rng).0,
+                initialization_fee: 1,
+                initialization_fee_record: sample_record(owner, 10, rng).0,
+            };
+            let options = InitializationOptions::new();
+            let error =
+                manager.deploy_and_initialize(&program, &[], initialize, mistyped.to_vec(), fees, options).unwrap_err();
+            assert_eq!(error.to_string(), check(initialize, &mistyped).unwrap_err());
+        }
+    }
+}
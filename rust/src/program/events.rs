@@ -0,0 +1,57 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+use crate::{EventLog, WalletEvent};
+
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Transaction;
+
+use anyhow::Result;
+
+impl<N: Network> ProgramManager<N> {
+    /// Write the transactions the program manager builds and broadcasts, and its spending policy decisions, to the
+    /// given event log.
+    pub fn with_event_log(mut self, event_log: EventLog<N>) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    /// Returns the event log the program manager writes to, if any.
+    pub fn event_log(&self) -> Option<&EventLog<N>> {
+        self.event_log.as_ref()
+    }
+
+    // Write an event to the event log, if there is one
+    pub(crate) fn log_event(&self, event: WalletEvent<N>) -> Result<()> {
+        if let Some(event_log) = &self.event_log {
+            event_log.append(event)?;
+        }
+        Ok(())
+    }
+
+    // Write a built transaction to the event log, if there is one, and return it
+    pub(crate) fn log_built(&self, transaction: Transaction<N>) -> Result<Transaction<N>> {
+        if self.event_log.is_some() {
+            let functions = transaction
+                .transitions()
+                .map(|transition| format!("{}/{}", transition.program_id(), transition.function_name()))
+                .collect();
+            self.log_event(WalletEvent::TransactionBuilt { transaction_id: transaction.id(), functions })?;
+        }
+        Ok(transaction)
+    }
+}
@@ -0,0 +1,185 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{PendingTransaction, ProgramManager};
+
+use crate::Microcredits;
+
+use snarkvm_console::{
+    program::{Identifier, Literal, Network, Plaintext, Record, Value},
+    types::U64,
+};
+use snarkvm_synthesizer::{Authorization, Program, Transaction};
+
+use anyhow::{ensure, Result};
+
+impl<N: Network> ProgramManager<N> {
+    /// Build a transaction executing a function of the given program with the given inputs.
+    ///
+    /// The `imports` are the programs imported by `program`, directly or indirectly, in the order they must be
+    /// added: each program after the programs it imports. The `fee_record` pays the network fee of `fee` gates.
+    ///
+    /// The proofs are built within the [`crate::ProvingLimits`] of the program manager. Watch-only program
+    /// managers fail with [`crate::SigningUnavailable`]. Executions refused by the [`crate::ExecutionPolicy`] of the
+    /// program manager fail with [`crate::ExecutionViolation`], and executions refused by its
+    /// [`crate::SpendingPolicy`] fail with [`crate::PolicyViolation`], counting the fee as their only spend. Inputs
+    /// that do not match the signature of the function fail with [`crate::InputMismatch`] before anything is proven,
+    /// and executions spending a record another thread is building with fail with [`crate::RecordInUse`].
+    pub fn build_execution(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+        fee: Microcredits,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<Transaction<N>> {
+        ensure!(***fee_record.gates() >= fee.0, "Fee record does not hold enough gates to pay the fee");
+        let private_key = self.signer()?;
+        self.check_execution_policy(program, imports, &function_name, &inputs)?;
+        Self::check_inputs(program, &function_name, &inputs)?;
+        let pending = PendingTransaction {
+            recipient: None,
+            amount: 0,
+            program: *program.id(),
+            function: function_name,
+            fee: fee.0,
+        };
+        self.enforce_spending_policy(&pending)?;
+        let records = inputs.iter().filter_map(|input| match input {
+            Value::Record(record) => Some(record),
+            _ => None,
+        });
+        let _lease = self.lock_records(&records.chain([&fee_record]).collect::<Vec<_>>())?;
+
+        // Add the program and its imports, apart from the programs built into the VM.
+        let vm = self.vm_with(&imports.iter().chain([program]).collect::<Vec<_>>())?;
+
+        // Authorize the function, then prove the execution and the fee.
+        let rng = &mut rand::thread_rng();
+        let authorization = vm.authorize(&private_key, program.id(), function_name, inputs, rng)?;
+        let execution = self.prove_execution(&vm, authorization)?;
+        let fee = self.prove_fee(&vm, fee_record, fee.0)?;
+        self.log_built(Transaction::from_execution(execution, Some(fee))?)
+    }
+
+    /// Authorize a function of the given program with the given inputs, without proving it.
+    ///
+    /// The authorization holds the signed requests of the function and of the functions it calls, which a
+    /// [`crate::ProverPoolClient`] proves on another machine. Watch-only program managers fail with
+    /// [`crate::SigningUnavailable`], and executions refused by the [`crate::ExecutionPolicy`] of the program manager
+    /// fail with [`crate::ExecutionViolation`]. Inputs that do not match the signature of the function fail with
+    /// [`crate::InputMismatch`].
+    pub fn authorize_execution(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+    ) -> Result<Authorization<N>> {
+        let private_key = self.signer()?;
+        self.check_execution_policy(program, imports, &function_name, &inputs)?;
+        Self::check_inputs(program, &function_name, &inputs)?;
+        let vm = self.vm_with(&imports.iter().chain([program]).collect::<Vec<_>>())?;
+        vm.authorize(&private_key, program.id(), function_name, inputs, &mut rand::thread_rng())
+    }
+
+    /// Authorize the payment of a network fee of `fee` gates from the fee record, without proving it.
+    pub fn authorize_fee(&self, fee_record: Record<N, Plaintext<N>>, fee: Microcredits) -> Result<Authorization<N>> {
+        ensure!(***fee_record.gates() >= fee.0, "Fee record does not hold enough gates to pay the fee");
+        let private_key = self.signer()?;
+        let inputs = vec![Value::Record(fee_record), Value::Plaintext(Plaintext::from(Literal::U64(U64::new(fee.0))))];
+        self.vm()?.authorize(&private_key, "credits.aleo", "fee", inputs, &mut rand::thread_rng())
+    }
+
+    /// Build a transaction executing a function of the given program and broadcast it to the network.
+    #[cfg(not(feature = "async"))]
+    pub fn execute(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+        fee: Microcredits,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        let transaction = self.build_execution(program, imports, function_name, inputs, fee, fee_record)?;
+        let transaction_id = transaction.id();
+        self.broadcast(transaction)?;
+        Ok(transaction_id)
+    }
+
+    /// Fetch the programs imported by `program`, directly or indirectly, in the order expected by
+    /// [`ProgramManager::build_execution`].
+    #[cfg(not(feature = "async"))]
+    pub fn fetch_imports(&self, program: &Program<N>) -> Result<Vec<Program<N>>> {
+        let mut imports = Vec::new();
+        self.fetch_imports_into(program, &mut imports)?;
+        Ok(imports)
+    }
+
+    // Fetch the imports of `program` depth first, so that each program follows the programs it imports
+    #[cfg(not(feature = "async"))]
+    fn fetch_imports_into(&self, program: &Program<N>, imports: &mut Vec<Program<N>>) -> Result<()> {
+        for program_id in program.imports().keys() {
+            if imports.iter().any(|import| import.id() == program_id) || program_id.to_string() == "credits.aleo" {
+                continue;
+            }
+            let import = self.api_client.get_program(*program_id)?;
+            self.fetch_imports_into(&import, imports)?;
+            imports.push(import);
+        }
+        Ok(())
+    }
+}
+
+// The integer overloads of the methods taking fees in `Microcredits`, kept for one release
+impl<N: Network> ProgramManager<N> {
+    /// Build a transaction executing a function of the given program with the given inputs.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `build_execution`; removed in the next release")]
+    pub fn build_execution_raw(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+        fee: u64,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<Transaction<N>> {
+        self.build_execution(program, imports, function_name, inputs, Microcredits(fee), fee_record)
+    }
+
+    /// Authorize the payment of a network fee of `fee` gates from the fee record, without proving it.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `authorize_fee`; removed in the next release")]
+    pub fn authorize_fee_raw(&self, fee_record: Record<N, Plaintext<N>>, fee: u64) -> Result<Authorization<N>> {
+        self.authorize_fee(fee_record, Microcredits(fee))
+    }
+
+    /// Build a transaction executing a function of the given program and broadcast it to the network.
+    #[cfg(not(feature = "async"))]
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `execute`; removed in the next release")]
+    pub fn execute_raw(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+        fee: u64,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        self.execute(program, imports, function_name, inputs, Microcredits(fee), fee_record)
+    }
+}
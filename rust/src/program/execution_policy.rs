@@ -0,0 +1,433 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+
+use snarkvm_console::program::{Identifier, Network, ProgramID, Value};
+use snarkvm_synthesizer::Program;
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    sync::Arc,
+};
+
+/// Inspects the inputs of the executions of an [`ExecutionPolicy`] before they are authorized, e.g. to refuse
+/// amounts or addresses a service does not expect
+///
+/// Closures taking the program, the function, and the inputs, and returning the reason the execution is refused,
+/// if it is, are inspectors.
+pub trait InputInspector<N: Network>: Send + Sync {
+    /// Called before the execution is authorized, returning the reason to refuse it, or `None` to allow it.
+    fn inspect(&self, program: &ProgramID<N>, function: &Identifier<N>, inputs: &[Value<N>]) -> Option<String>;
+}
+
+impl<N: Network, F: Fn(&ProgramID<N>, &Identifier<N>, &[Value<N>]) -> Option<String> + Send + Sync> InputInspector<N>
+    for F
+{
+    fn inspect(&self, program: &ProgramID<N>, function: &Identifier<N>, inputs: &[Value<N>]) -> Option<String> {
+        self(program, function, inputs)
+    }
+}
+
+/// The programs and functions the executions built by a [`ProgramManager`] may call, set by
+/// [`ProgramManager::with_execution_policy`]
+///
+/// The policy is checked before the execution is authorized, so that a refused execution never reaches the proving
+/// stage, and fails the build with an [`ExecutionViolation`] naming the rule it breaks. Denied programs are refused
+/// even if they are allowed, and cannot be reached through the imports of an allowed program either.
+#[derive(Clone)]
+pub struct ExecutionPolicy<N: Network> {
+    allowed_programs: Option<HashSet<ProgramID<N>>>,
+    denied_programs: HashSet<ProgramID<N>>,
+    allowed_functions: HashMap<ProgramID<N>, HashSet<Identifier<N>>>,
+    import_limit: Option<usize>,
+    inspector: Option<Arc<dyn InputInspector<N>>>,
+}
+
+impl<N: Network> ExecutionPolicy<N> {
+    /// Create a policy allowing every execution.
+    pub fn new() -> Self {
+        Self {
+            allowed_programs: None,
+            denied_programs: HashSet::new(),
+            allowed_functions: HashMap::new(),
+            import_limit: None,
+            inspector: None,
+        }
+    }
+
+    /// Allow executions of the given program. Once a program is allowed, executions of any other are refused.
+    pub fn allow_program(mut self, program: ProgramID<N>) -> Self {
+        self.allowed_programs.get_or_insert_with(HashSet::new).insert(program);
+        self
+    }
+
+    /// Refuse executions of the given program, and of programs importing it, whether or not it is allowed.
+    pub fn deny_program(mut self, program: ProgramID<N>) -> Self {
+        self.denied_programs.insert(program);
+        self
+    }
+
+    /// Allow executions of the given function of a program. Once a function of a program is allowed, executions of
+    /// its other functions are refused. The functions of other programs are not affected.
+    pub fn allow_function(mut self, program: ProgramID<N>, function: Identifier<N>) -> Self {
+        self.allowed_functions.entry(program).or_default().insert(function);
+        self
+    }
+
+    /// Refuse executions of programs importing more than `limit` programs, directly or indirectly.
+    pub fn with_import_limit(mut self, limit: usize) -> Self {
+        self.import_limit = Some(limit);
+        self
+    }
+
+    /// Ask the inspector to check the inputs of every execution that the other rules allow.
+    pub fn with_input_inspector(mut self, inspector: impl InputInspector<N> + 'static) -> Self {
+        self.inspector = Some(Arc::new(inspector));
+        self
+    }
+
+    /// Check an execution of a function of a program with the given inputs against the policy, e.g. to validate a
+    /// request before handing it to a program manager.
+    ///
+    /// The denied programs are checked first, then the allowed programs and functions, and the inspector is only
+    /// called for executions that every other rule allows. The imports are checked by
+    /// [`ExecutionPolicy::check_imports`].
+    pub fn check(
+        &self,
+        program: &ProgramID<N>,
+        function: &Identifier<N>,
+        inputs: &[Value<N>],
+    ) -> Result<(), ExecutionViolation> {
+        if self.denied_programs.contains(program) {
+            return Err(ExecutionViolation::ProgramDenied { program: program.to_string() });
+        }
+        if self.allowed_programs.as_ref().map_or(false, |allowed| !allowed.contains(program)) {
+            return Err(ExecutionViolation::ProgramNotAllowed { program: program.to_string() });
+        }
+        if self.allowed_functions.get(program).map_or(false, |allowed| !allowed.contains(function)) {
+            let (program, function) = (program.to_string(), function.to_string());
+            return Err(ExecutionViolation::FunctionNotAllowed { program, function });
+        }
+        let inspector = self.inspector.as_ref();
+        if let Some(reason) = inspector.and_then(|inspector| inspector.inspect(program, function, inputs)) {
+            let (program, function) = (program.to_string(), function.to_string());
+            return Err(ExecutionViolation::InputsRejected { program, function, reason });
+        }
+        Ok(())
+    }
+
+    /// Check the programs imported by an executed program, directly or indirectly, against the denied programs and
+    /// the import limit.
+    pub fn check_imports<'a>(
+        &self,
+        program: &ProgramID<N>,
+        imports: impl IntoIterator<Item = &'a ProgramID<N>>,
+    ) -> Result<(), ExecutionViolation> {
+        let mut count = 0;
+        for import in imports {
+            if self.denied_programs.contains(import) {
+                let (program, import) = (program.to_string(), import.to_string());
+                return Err(ExecutionViolation::ImportDenied { program, import });
+            }
+            count += 1;
+        }
+        match self.import_limit {
+            Some(limit) if count > limit => {
+                Err(ExecutionViolation::TooManyImports { program: program.to_string(), imports: count, limit })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<N: Network> Default for ExecutionPolicy<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Network> fmt::Debug for ExecutionPolicy<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExecutionPolicy")
+            .field("allowed_programs", &self.allowed_programs)
+            .field("denied_programs", &self.denied_programs)
+            .field("allowed_functions", &self.allowed_functions)
+            .field("import_limit", &self.import_limit)
+            .field("inspector", &self.inspector.is_some())
+            .finish()
+    }
+}
+
+/// The error returned when an execution breaks a rule of the [`ExecutionPolicy`] of a [`ProgramManager`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecutionViolation {
+    /// The program is denied
+    ProgramDenied { program: String },
+    /// The program is not allowed
+    ProgramNotAllowed { program: String },
+    /// The function is not among the allowed functions of its program
+    FunctionNotAllowed { program: String, function: String },
+    /// The program imports a denied program
+    ImportDenied { program: String, import: String },
+    /// The program imports more programs than the import limit
+    TooManyImports { program: String, imports: usize, limit: usize },
+    /// The input inspector refused the inputs of the execution
+    InputsRejected { program: String, function: String, reason: String },
+}
+
+impl ExecutionViolation {
+    /// Returns the name of the rule the execution breaks.
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::ProgramDenied { .. } => "denied programs",
+            Self::ProgramNotAllowed { .. } => "allowed programs",
+            Self::FunctionNotAllowed { .. } => "allowed functions",
+            Self::ImportDenied { .. } => "denied programs",
+            Self::TooManyImports { .. } => "import limit",
+            Self::InputsRejected { .. } => "input inspector",
+        }
+    }
+}
+
+impl fmt::Display for ExecutionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rule = self.rule();
+        match self {
+            Self::ProgramDenied { program } => write!(f, "The program {program} is refused by the {rule}"),
+            Self::ProgramNotAllowed { program } => write!(f, "The program {program} is not in the {rule}"),
+            Self::FunctionNotAllowed { program, function } => {
+                write!(f, "The function {program}/{function} is not in the {rule}")
+            }
+            Self::ImportDenied { program, import } => {
+                write!(f, "The program {program} imports {import}, which is refused by the {rule}")
+            }
+            Self::TooManyImports { program, imports, limit } => {
+                write!(f, "The program {program} has {imports} imports, over the {rule} of {limit}")
+            }
+            Self::InputsRejected { program, function, reason } => {
+                write!(f, "The inputs of {program}/{function} were refused by the {rule}: {reason}")
+            }
+        }
+    }
+}
+
+impl Error for ExecutionViolation {}
+
+impl<N: Network> ProgramManager<N> {
+    /// Check the executions built by the program manager against the given policy.
+    pub fn with_execution_policy(mut self, execution_policy: ExecutionPolicy<N>) -> Self {
+        self.execution_policy = Some(execution_policy);
+        self
+    }
+
+    /// Returns the policy the executions built by the program manager are checked against, if it was set.
+    pub fn execution_policy(&self) -> Option<&ExecutionPolicy<N>> {
+        self.execution_policy.as_ref()
+    }
+
+    /// Check an execution of a function of the given program, with its imports and inputs, against the execution
+    /// policy. Executions are allowed when no policy is set.
+    pub fn check_execution_policy(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function: &Identifier<N>,
+        inputs: &[Value<N>],
+    ) -> Result<(), ExecutionViolation> {
+        let policy = match &self.execution_policy {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+        policy.check_imports(program.id(), imports.iter().map(Program::id))?;
+        policy.check(program.id(), function, inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helpers::CurrentNetwork, testnet3, Microcredits};
+
+    use snarkvm_console::{
+        account::PrivateKey,
+        program::{Literal, Plaintext},
+        types::U64,
+    };
+    use snarkvm_utilities::TestRng;
+    use std::str::FromStr;
+
+    type N = CurrentNetwork;
+
+    const LEAF_PROGRAM: &str = r"program policy_leaf.aleo;
+
+function echo:
+    input r0 as u64.private;
+    output r0 as u64.private;
+";
+
+    const ROOT_PROGRAM: &str = r"import policy_leaf.aleo;
+
+program policy_root.aleo;
+
+function pay:
+    input r0 as u64.private;
+    call policy_leaf.aleo/echo r0 into r1;
+    output r1 as u64.private;
+
+function refund:
+    input r0 as u64.private;
+    output r0 as u64.private;
+";
+
+    fn program_id(id: &str) -> ProgramID<N> {
+        ProgramID::from_str(id).unwrap()
+    }
+
+    fn function(name: &str) -> Identifier<N> {
+        Identifier::from_str(name).unwrap()
+    }
+
+    fn amount(gates: u64) -> Value<N> {
+        Value::Plaintext(Plaintext::from(Literal::U64(U64::new(gates))))
+    }
+
+    #[test]
+    fn test_execution_policy_rules() {
+        let (root, leaf) = (program_id("policy_root.aleo"), program_id("policy_leaf.aleo"));
+        let other = program_id("x.aleo");
+        let (pay, refund) = (function("pay"), function("refund"));
+        assert_eq!(ExecutionPolicy::new().check(&other, &pay, &[]), Ok(()));
+
+        // Once a program is allowed, the others are refused.
+        let policy = ExecutionPolicy::<N>::new().allow_program(root);
+        assert_eq!(policy.check(&root, &refund, &[]), Ok(()));
+        let violation = policy.check(&other, &pay, &[]).unwrap_err();
+        assert_eq!(violation, ExecutionViolation::ProgramNotAllowed { program: "x.aleo".to_string() });
+        assert_eq!(violation.to_string(), "The program x.aleo is not in the allowed programs");
+
+        // Once a function of a program is allowed, the other functions of that program are refused.
+        let policy = ExecutionPolicy::<N>::new().allow_function(root, pay);
+        assert_eq!(policy.check(&root, &pay, &[]), Ok(()));
+        assert_eq!(policy.check(&leaf, &function("echo"), &[]), Ok(()));
+        let violation = policy.check(&root, &refund, &[]).unwrap_err();
+        assert_eq!(violation.rule(), "allowed functions");
+        assert_eq!(violation.to_string(), "The function policy_root.aleo/refund is not in the allowed functions");
+
+        // Imports are counted against the limit, and denied imports are refused.
+        let policy = ExecutionPolicy::<N>::new().with_import_limit(1);
+        assert_eq!(policy.check_imports(&root, [&leaf]), Ok(()));
+        let violation = policy.check_imports(&root, [&leaf, &other]).unwrap_err();
+        assert_eq!(violation, ExecutionViolation::TooManyImports {
+            program: "policy_root.aleo".to_string(),
+            imports: 2,
+            limit: 1
+        });
+        assert_eq!(violation.to_string(), "The program policy_root.aleo has 2 imports, over the import limit of 1");
+        let violation = ExecutionPolicy::<N>::new().deny_program(leaf).check_imports(&root, [&leaf]).unwrap_err();
+        assert_eq!(violation.rule(), "denied programs");
+        assert_eq!(
+            violation.to_string(),
+            "The program policy_root.aleo imports policy_leaf.aleo, which is refused by the denied programs"
+        );
+    }
+
+    #[test]
+    fn test_execution_policy_deny_overrides_allow() {
+        let (root, leaf) = (program_id("policy_root.aleo"), program_id("policy_leaf.aleo"));
+        let pay = function("pay");
+
+        // A program both allowed and denied is denied, whichever rule was added first.
+        for policy in [
+            ExecutionPolicy::<N>::new().allow_program(root).deny_program(root),
+            ExecutionPolicy::<N>::new().deny_program(root).allow_program(root).allow_function(root, pay),
+        ] {
+            let violation = policy.check(&root, &pay, &[]).unwrap_err();
+            assert_eq!(violation, ExecutionViolation::ProgramDenied { program: "policy_root.aleo".to_string() });
+            assert_eq!(violation.to_string(), "The program policy_root.aleo is refused by the denied programs");
+        }
+
+        // Denying one program leaves the others allowed, and the allowed functions only narrow allowed programs.
+        let policy = ExecutionPolicy::<N>::new().deny_program(leaf).allow_function(root, pay);
+        assert_eq!(policy.check(&root, &pay, &[]), Ok(()));
+        let policy = policy.allow_program(leaf);
+        assert!(matches!(policy.check(&leaf, &function("echo"), &[]), Err(ExecutionViolation::ProgramDenied { .. })));
+    }
+
+    #[test]
+    fn test_execution_policy_inspector_vetoes_inputs() {
+        let root = program_id("policy_root.aleo");
+        let pay = function("pay");
+        let inspected = Arc::new(std::sync::Mutex::new(0));
+        let counted = inspected.clone();
+        let policy = ExecutionPolicy::<N>::new().allow_function(root, pay).with_input_inspector(
+            move |_: &ProgramID<N>, _: &Identifier<N>, inputs: &[Value<N>]| {
+                *counted.lock().unwrap() += 1;
+                match inputs.first() {
+                    Some(input) if *input == amount(1_000_000) => Some(format!("{input} is over the limit")),
+                    _ => None,
+                }
+            },
+        );
+        assert_eq!(policy.check(&root, &pay, &[amount(10)]), Ok(()));
+        let violation = policy.check(&root, &pay, &[amount(1_000_000)]).unwrap_err();
+        assert_eq!(violation, ExecutionViolation::InputsRejected {
+            program: "policy_root.aleo".to_string(),
+            function: "pay".to_string(),
+            reason: "1000000u64 is over the limit".to_string(),
+        });
+        assert_eq!(
+            violation.to_string(),
+            "The inputs of policy_root.aleo/pay were refused by the input inspector: 1000000u64 is over the limit"
+        );
+
+        // The inspector is not asked about executions another rule refuses.
+        assert!(policy.check(&root, &function("refund"), &[amount(1_000_000)]).is_err());
+        assert_eq!(*inspected.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_execution_policy_fails_builds_before_proving() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let root = Program::<N>::from_str(ROOT_PROGRAM).unwrap();
+        let imports = [Program::from_str(LEAF_PROGRAM).unwrap()];
+        let policy = ExecutionPolicy::new().allow_function(*root.id(), function("pay")).with_import_limit(0);
+        let program_manager =
+            ProgramManager::new(private_key, testnet3("http://127.0.0.1:9")).with_execution_policy(policy);
+        let address = snarkvm_console::account::Address::try_from(private_key).unwrap();
+        let (fee_record, _) = crate::test_helpers::sample_record(address, 10, rng);
+
+        // Each build fails with the violation, without authorizing the execution.
+        let build = |program_manager: &ProgramManager<N>, function_name: &str| {
+            let inputs = vec![amount(5)];
+            let error = program_manager
+                .build_execution(&root, &imports, function(function_name), inputs, Microcredits(1), fee_record.clone())
+                .unwrap_err();
+            error.downcast::<ExecutionViolation>().unwrap()
+        };
+        assert!(matches!(build(&program_manager, "pay"), ExecutionViolation::TooManyImports { imports: 1, .. }));
+        let result = program_manager.authorize_execution(&root, &imports, function("pay"), vec![amount(5)]);
+        assert!(result.err().unwrap().downcast::<ExecutionViolation>().is_ok());
+
+        let policy = ExecutionPolicy::new().allow_function(*root.id(), function("pay"));
+        let program_manager = program_manager.with_execution_policy(policy);
+        assert_eq!(program_manager.check_execution_policy(&root, &imports, &function("pay"), &[]), Ok(()));
+        assert!(matches!(build(&program_manager, "refund"), ExecutionViolation::FunctionNotAllowed { .. }));
+    }
+}
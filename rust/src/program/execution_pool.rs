@@ -0,0 +1,616 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{ProgramManager, ProvingEvent};
+use crate::{mutex::lock, Cancelled, Microcredits, OutboxQueue, RequestPriority};
+
+use snarkvm_console::{
+    account::Address,
+    program::{Identifier, Network, Plaintext, Record, Value},
+};
+use snarkvm_synthesizer::{Program, Transaction};
+
+use anyhow::{anyhow, bail, Result};
+use std::{
+    collections::VecDeque,
+    fmt,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        Condvar,
+        Mutex,
+        MutexGuard,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// A transaction built by an [`ExecutionPool`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum ExecutionJob<N: Network> {
+    /// Execute a function, as [`ProgramManager::build_execution`] does
+    Execute {
+        program: Program<N>,
+        imports: Vec<Program<N>>,
+        function: Identifier<N>,
+        inputs: Vec<Value<N>>,
+        fee: Microcredits,
+        fee_record: Record<N, Plaintext<N>>,
+    },
+    /// Transfer gates, as [`ProgramManager::build_transfer`] does
+    Transfer {
+        amount: Microcredits,
+        fee: Microcredits,
+        recipient: Address<N>,
+        record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    },
+}
+
+/// The identifier of a job of an [`ExecutionPool`], numbered in the order the jobs were submitted
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExecutionJobId(pub u64);
+
+impl fmt::Display for ExecutionJobId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The state of a job of an [`ExecutionPool`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ExecutionJobState {
+    /// The job waits for a worker, and can still be cancelled
+    Queued,
+    /// A worker is building the transaction of the job
+    Proving,
+    /// The transaction of the job was built, and queued to the outbox of the pool if it has one
+    Done,
+    /// The transaction of the job could not be built
+    Failed,
+    /// The job was cancelled before a worker took it
+    Cancelled,
+}
+
+impl ExecutionJobState {
+    /// Returns `true` if the job will not change state anymore.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Done | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// How the workers of an [`ExecutionPool`] are run
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionPoolOptions {
+    /// The number of workers, each building one transaction at a time
+    pub workers: usize,
+}
+
+impl Default for ExecutionPoolOptions {
+    /// Proving takes several gigabytes of memory, so by default half of the physical cores build transactions.
+    fn default() -> Self {
+        Self { workers: (num_cpus::get_physical() / 2).max(1) }
+    }
+}
+
+// Builds the transaction of a job, replaced by a mock in tests
+type Prover<N> = dyn Fn(&ProgramManager<N>, ExecutionJob<N>) -> Result<Transaction<N>> + Send + Sync;
+
+// The outbox the transactions built are queued to, and the number of blocks after which they expire
+type Outbox<N> = (Arc<Mutex<OutboxQueue<N>>>, u32);
+
+// A job and its result, shared by its handle and the pool
+struct Job<N: Network> {
+    id: ExecutionJobId,
+    slot: Mutex<JobSlot<N>>,
+    // Notified when the job reaches a final state
+    finished: Condvar,
+}
+
+struct JobSlot<N: Network> {
+    state: ExecutionJobState,
+    // The job to build, until a worker takes it
+    work: Option<ExecutionJob<N>>,
+    // The result of the job, until its handle takes it
+    result: Option<Result<Transaction<N>>>,
+}
+
+impl<N: Network> Job<N> {
+    fn slot(&self) -> MutexGuard<'_, JobSlot<N>> {
+        lock(&self.slot)
+    }
+
+    // Set the result of the job, returning its final state
+    fn finish(&self, result: Result<Transaction<N>>) -> ExecutionJobState {
+        let mut slot = self.slot();
+        slot.state = match &result {
+            Ok(_) => ExecutionJobState::Done,
+            Err(_) => ExecutionJobState::Failed,
+        };
+        slot.result = Some(result);
+        self.finished.notify_all();
+        slot.state
+    }
+
+    // Cancel the job if no worker took it yet, returning whether it was cancelled
+    fn cancel(&self) -> bool {
+        let mut slot = self.slot();
+        if slot.state != ExecutionJobState::Queued {
+            return false;
+        }
+        slot.state = ExecutionJobState::Cancelled;
+        slot.work = None;
+        slot.result = Some(Err(Cancelled::new((), None).into()));
+        self.finished.notify_all();
+        true
+    }
+}
+
+// The jobs waiting for a worker, in order of submission within each priority
+struct Queue<N: Network> {
+    interactive: VecDeque<Arc<Job<N>>>,
+    background: VecDeque<Arc<Job<N>>>,
+    stopped: bool,
+}
+
+impl<N: Network> Queue<N> {
+    fn pop(&mut self) -> Option<Arc<Job<N>>> {
+        self.interactive.pop_front().or_else(|| self.background.pop_front())
+    }
+}
+
+// The state shared by a pool and its workers
+struct PoolState<N: Network> {
+    program_manager: Arc<ProgramManager<N>>,
+    prover: Box<Prover<N>>,
+    outbox: Mutex<Option<Outbox<N>>>,
+    queue: Mutex<Queue<N>>,
+    // Notified when a job is queued or the pool stops
+    available: Condvar,
+    next_id: AtomicU64,
+}
+
+impl<N: Network> PoolState<N> {
+    fn queue(&self) -> MutexGuard<'_, Queue<N>> {
+        lock(&self.queue)
+    }
+
+    fn report(&self, job: &Job<N>, state: ExecutionJobState) {
+        self.program_manager.report(ProvingEvent::Job { job: job.id, state });
+    }
+
+    // Cancel a job if no worker took it yet, returning whether it was cancelled
+    fn cancel(&self, job: &Job<N>) -> bool {
+        let cancelled = job.cancel();
+        if cancelled {
+            self.report(job, ExecutionJobState::Cancelled);
+        }
+        cancelled
+    }
+
+    // Wait for the next job that was not cancelled, or `None` once the pool stops
+    fn next(&self) -> Option<(Arc<Job<N>>, ExecutionJob<N>)> {
+        let mut queue = self.queue();
+        loop {
+            while let Some(job) = queue.pop() {
+                let mut slot = job.slot();
+                if let Some(work) = slot.work.take() {
+                    slot.state = ExecutionJobState::Proving;
+                    drop(slot);
+                    return Some((job, work));
+                }
+            }
+            if queue.stopped {
+                return None;
+            }
+            queue = self.available.wait(queue).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    // Queue a built transaction to the outbox, if the pool has one, to expire the given number of blocks after the
+    // latest height
+    fn enqueue(&self, transaction: Transaction<N>) -> Result<Transaction<N>> {
+        let outbox = lock(&self.outbox).clone();
+        if let Some((outbox, expiry_blocks)) = outbox {
+            let latest_height = self.program_manager.api_client().latest_height()?;
+            lock(&outbox).enqueue(transaction.clone(), latest_height.saturating_add(expiry_blocks).0)?;
+        }
+        Ok(transaction)
+    }
+}
+
+// Build the transaction of a job with the builders of the program manager
+fn build<N: Network>(program_manager: &ProgramManager<N>, job: ExecutionJob<N>) -> Result<Transaction<N>> {
+    match job {
+        ExecutionJob::Execute { program, imports, function, inputs, fee, fee_record } => {
+            program_manager.build_execution(&program, &imports, function, inputs, fee, fee_record)
+        }
+        ExecutionJob::Transfer { amount, fee, recipient, record, fee_record } => {
+            program_manager.build_transfer(amount, fee, recipient, record, fee_record)
+        }
+    }
+}
+
+/// A handle to a job submitted to an [`ExecutionPool`]
+pub struct JobHandle<N: Network> {
+    job: Arc<Job<N>>,
+    pool: Arc<PoolState<N>>,
+}
+
+impl<N: Network> JobHandle<N> {
+    /// Returns the identifier of the job.
+    pub fn id(&self) -> ExecutionJobId {
+        self.job.id
+    }
+
+    /// Returns the state of the job.
+    pub fn state(&self) -> ExecutionJobState {
+        self.job.slot().state
+    }
+
+    /// Cancel the job if no worker took it yet, returning whether it was cancelled.
+    ///
+    /// A job being proven is not interrupted, and cannot be cancelled.
+    pub fn cancel(&self) -> bool {
+        self.pool.cancel(&self.job)
+    }
+
+    /// Wait until the job reaches a final state, and return its transaction, or the error that failed it, which is
+    /// [`Cancelled`] for a cancelled job.
+    pub fn wait(self) -> Result<Transaction<N>> {
+        let mut slot = self.job.slot();
+        loop {
+            if let Some(result) = slot.result.take() {
+                return result;
+            }
+            slot = self.job.finished.wait(slot).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+}
+
+/// A bounded pool of workers building the transactions of a [`ProgramManager`] on background threads
+///
+/// Jobs are taken in the order they were submitted, the [`RequestPriority::Interactive`] ones before the
+/// [`RequestPriority::Background`] ones, by a fixed number of workers, so that a burst of jobs queues instead of
+/// starting more proofs than the memory holds. The changes of state of each job are reported to the progress
+/// reporter of the program manager as [`ProvingEvent::Job`], and the steps of its proofs are reported in between,
+/// on the thread of its worker. With [`ExecutionPool::with_outbox`], the transactions built are queued to an
+/// [`OutboxQueue`] to be broadcast.
+///
+/// Dropping the pool, or [`ExecutionPool::shutdown`], cancels the queued jobs and waits for the jobs being proven
+/// to finish.
+pub struct ExecutionPool<N: Network> {
+    options: ExecutionPoolOptions,
+    state: Arc<PoolState<N>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<N: Network> ExecutionPool<N> {
+    /// Start the workers of a pool building the transactions of the given program manager.
+    pub fn start(program_manager: Arc<ProgramManager<N>>, options: ExecutionPoolOptions) -> Result<Self> {
+        Self::start_with_prover(program_manager, options, Box::new(build))
+    }
+
+    // Start the workers of a pool building the transactions of its jobs with the given prover
+    fn start_with_prover(
+        program_manager: Arc<ProgramManager<N>>,
+        options: ExecutionPoolOptions,
+        prover: Box<Prover<N>>,
+    ) -> Result<Self> {
+        if options.workers == 0 {
+            bail!("An execution pool needs at least one worker");
+        }
+        let state = Arc::new(PoolState {
+            program_manager,
+            prover,
+            outbox: Mutex::new(None),
+            queue: Mutex::new(Queue { interactive: VecDeque::new(), background: VecDeque::new(), stopped: false }),
+            available: Condvar::new(),
+            next_id: AtomicU64::new(0),
+        });
+        let mut pool = Self { options, state, workers: Vec::with_capacity(options.workers) };
+        for index in 0..options.workers {
+            let state = pool.state.clone();
+            let worker = thread::Builder::new().name(format!("aleo-execution-{index}")).spawn(move || work(&state))?;
+            pool.workers.push(worker);
+        }
+        Ok(pool)
+    }
+
+    /// Queue the transactions built from then on to the given outbox, to expire `expiry_blocks` blocks after the
+    /// latest height when they are built.
+    ///
+    /// A job whose transaction cannot be queued, e.g. as the latest height cannot be read, fails, and its
+    /// transaction is not broadcast.
+    pub fn with_outbox(self, outbox: Arc<Mutex<OutboxQueue<N>>>, expiry_blocks: u32) -> Self {
+        *lock(&self.state.outbox) = Some((outbox, expiry_blocks));
+        self
+    }
+
+    /// Queue a job as [`RequestPriority::Interactive`].
+    pub fn submit(&self, job: ExecutionJob<N>) -> JobHandle<N> {
+        self.submit_with_priority(job, RequestPriority::Interactive)
+    }
+
+    /// Queue a job after the queued jobs of the same priority, and before the background jobs for an interactive
+    /// job.
+    pub fn submit_with_priority(&self, job: ExecutionJob<N>, priority: RequestPriority) -> JobHandle<N> {
+        let id = ExecutionJobId(self.state.next_id.fetch_add(1, Ordering::SeqCst));
+        let slot = JobSlot { state: ExecutionJobState::Queued, work: Some(job), result: None };
+        let job = Arc::new(Job { id, slot: Mutex::new(slot), finished: Condvar::new() });
+        self.state.report(&job, ExecutionJobState::Queued);
+        let mut queue = self.state.queue();
+        match priority {
+            RequestPriority::Interactive => queue.interactive.push_back(job.clone()),
+            RequestPriority::Background => queue.background.push_back(job.clone()),
+        }
+        self.state.available.notify_one();
+        JobHandle { job, pool: self.state.clone() }
+    }
+
+    /// Returns how the workers are run.
+    pub fn options(&self) -> ExecutionPoolOptions {
+        self.options
+    }
+
+    /// Returns the number of jobs waiting for a worker.
+    pub fn queued(&self) -> usize {
+        let queue = self.state.queue();
+        let jobs = queue.interactive.iter().chain(&queue.background);
+        jobs.filter(|job| job.slot().state == ExecutionJobState::Queued).count()
+    }
+
+    /// Cancel the queued jobs, and wait for the jobs being proven to finish, as dropping the pool does.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    // Stop the workers once they finish their jobs, cancelling the queued ones
+    fn stop(&mut self) {
+        let cancelled = {
+            let mut queue = self.state.queue();
+            queue.stopped = true;
+            self.state.available.notify_all();
+            let mut cancelled = queue.interactive.drain(..).collect::<Vec<_>>();
+            cancelled.extend(queue.background.drain(..));
+            cancelled
+        };
+        for job in cancelled {
+            self.state.cancel(&job);
+        }
+        for worker in self.workers.drain(..) {
+            // The panics of the prover are caught by the worker, so it only panics if the pool itself does, and
+            // then there would be nothing left to clean up.
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<N: Network> Drop for ExecutionPool<N> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Build the transactions of the queued jobs until the pool stops
+fn work<N: Network>(state: &PoolState<N>) {
+    while let Some((job, work)) = state.next() {
+        state.report(&job, ExecutionJobState::Proving);
+        // A panic of the prover fails the job, rather than the worker, so that the job can still be waited for.
+        let result = match catch_unwind(AssertUnwindSafe(|| (state.prover)(&state.program_manager, work))) {
+            Ok(result) => result.and_then(|transaction| state.enqueue(transaction)),
+            Err(panic) => {
+                let message = match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+                    (Some(message), _) => message.to_string(),
+                    (_, Some(message)) => message.clone(),
+                    _ => "Unknown panic".to_string(),
+                };
+                Err(anyhow!("The prover panicked: {message}"))
+            }
+        };
+        let final_state = job.finish(result);
+        state.report(&job, final_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{sample_record, sample_transaction, sample_transition, CurrentNetwork};
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_utilities::TestRng;
+
+    use std::{
+        sync::mpsc::{self, Receiver, Sender},
+        time::Duration,
+    };
+
+    // A mock of the prover, which reports the amount of each transfer it starts, and blocks until it is released
+    struct MockProver {
+        started: Mutex<Sender<u64>>,
+        release: Mutex<Receiver<()>>,
+    }
+
+    // Returns a pool of the given number of workers proving with a mock, the amounts of the transfers the mock
+    // starts, the sender releasing one proof each, and the events reported
+    #[allow(clippy::type_complexity)]
+    fn sample_pool(
+        workers: usize,
+    ) -> (ExecutionPool<CurrentNetwork>, Receiver<u64>, Sender<()>, Arc<Mutex<Vec<ProvingEvent>>>) {
+        let private_key = PrivateKey::<CurrentNetwork>::new(&mut TestRng::default()).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reported = events.clone();
+        let program_manager = ProgramManager::new(private_key, crate::testnet3("http://127.0.0.1:9"))
+            .with_progress_reporter(move |event| reported.lock().unwrap().push(event));
+        let ((started, starts), (releases, release)) = (mpsc::channel(), mpsc::channel());
+        let mock = MockProver { started: Mutex::new(started), release: Mutex::new(release) };
+        let prover = move |_: &ProgramManager<CurrentNetwork>, job: ExecutionJob<CurrentNetwork>| {
+            let ExecutionJob::Transfer { amount: Microcredits(amount), .. } = job else {
+                bail!("The mock only proves transfers")
+            };
+            mock.started.lock().unwrap().send(amount).unwrap();
+            mock.release.lock().unwrap().recv()?;
+            if amount == 0 {
+                bail!("Transfer amount must be greater than zero");
+            }
+            if amount == u64::MAX {
+                panic!("The mock prover panics on the largest amount");
+            }
+            Ok(sample_transaction([sample_transition(&[], &[], &mut TestRng::default())]))
+        };
+        let options = ExecutionPoolOptions { workers };
+        let pool = ExecutionPool::start_with_prover(Arc::new(program_manager), options, Box::new(prover)).unwrap();
+        (pool, starts, releases, events)
+    }
+
+    // Returns a transfer of the given amount
+    fn sample_job(amount: u64) -> ExecutionJob<CurrentNetwork> {
+        let rng = &mut TestRng::default();
+        let address = Address::try_from(PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+        let (record, _) = sample_record(address, 100, rng);
+        let (fee_record, _) = sample_record(address, 10, rng);
+        let (amount, fee) = (Microcredits(amount), Microcredits(1));
+        ExecutionJob::Transfer { amount, fee, recipient: address, record, fee_record }
+    }
+
+    fn recv(starts: &Receiver<u64>) -> u64 {
+        starts.recv_timeout(Duration::from_secs(10)).unwrap()
+    }
+
+    #[test]
+    fn test_execution_pool_queues_beyond_workers() {
+        let (pool, starts, releases, events) = sample_pool(2);
+        let handles = (1..=4).map(|amount| pool.submit(sample_job(amount))).collect::<Vec<_>>();
+        let background = pool.submit_with_priority(sample_job(5), RequestPriority::Background);
+        let interactive = pool.submit(sample_job(6));
+
+        // Only as many jobs as there are workers are proven at once, in the order they were submitted.
+        let mut first = [recv(&starts), recv(&starts)];
+        first.sort();
+        assert_eq!(first, [1, 2]);
+        assert!(starts.recv_timeout(Duration::from_millis(100)).is_err());
+        assert_eq!(handles.iter().map(JobHandle::state).collect::<Vec<_>>(), [
+            ExecutionJobState::Proving,
+            ExecutionJobState::Proving,
+            ExecutionJobState::Queued,
+            ExecutionJobState::Queued
+        ]);
+        assert_eq!(pool.queued(), 4);
+
+        // A worker takes the next job once it finishes one, and the interactive job is taken before the background
+        // job submitted before it.
+        let mut order = vec![];
+        for _ in 0..4 {
+            releases.send(()).unwrap();
+            order.push(recv(&starts));
+        }
+        assert_eq!(order, [3, 4, 6, 5]);
+        assert_eq!(pool.queued(), 0);
+        releases.send(()).unwrap();
+        releases.send(()).unwrap();
+
+        let handles = handles.into_iter().chain([background, interactive]).collect::<Vec<_>>();
+        let ids = handles.iter().map(JobHandle::id).collect::<Vec<_>>();
+        assert_eq!(ids, (0..6).map(ExecutionJobId).collect::<Vec<_>>());
+        for handle in handles {
+            assert!(handle.wait().is_ok());
+        }
+        pool.shutdown();
+        let events = events.lock().unwrap();
+        for id in ids {
+            let states = events.iter().filter_map(|event| match event {
+                ProvingEvent::Job { job, state } if *job == id => Some(*state),
+                _ => None,
+            });
+            assert_eq!(states.collect::<Vec<_>>(), [
+                ExecutionJobState::Queued,
+                ExecutionJobState::Proving,
+                ExecutionJobState::Done
+            ]);
+        }
+    }
+
+    #[test]
+    fn test_execution_pool_cancel_queued() {
+        let (pool, starts, releases, _) = sample_pool(1);
+        let proving = pool.submit(sample_job(1));
+        let queued = pool.submit(sample_job(2));
+        let failing = pool.submit(sample_job(0));
+        assert_eq!(recv(&starts), 1);
+
+        // A queued job is cancelled once, and a job being proven is not.
+        assert!(queued.cancel());
+        assert!(!queued.cancel());
+        assert!(!proving.cancel());
+        assert_eq!(queued.state(), ExecutionJobState::Cancelled);
+        assert_eq!(pool.queued(), 1);
+
+        releases.send(()).unwrap();
+        assert!(proving.wait().is_ok());
+        // The cancelled job is skipped, and never reaches the prover.
+        assert_eq!(recv(&starts), 0);
+        releases.send(()).unwrap();
+        assert_eq!(failing.wait().unwrap_err().to_string(), "Transfer amount must be greater than zero");
+        assert!(queued.wait().unwrap_err().is::<Cancelled<()>>());
+        assert!(starts.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_execution_pool_shutdown() {
+        let (pool, starts, releases, events) = sample_pool(1);
+        let proving = pool.submit(sample_job(1));
+        let queued = pool.submit(sample_job(2));
+        assert_eq!(recv(&starts), 1);
+
+        // The shutdown waits for the job being proven, and cancels the queued one.
+        let shutdown = thread::spawn(move || pool.shutdown());
+        while queued.state() != ExecutionJobState::Cancelled {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!shutdown.is_finished());
+        assert_eq!(proving.state(), ExecutionJobState::Proving);
+        releases.send(()).unwrap();
+        shutdown.join().unwrap();
+
+        assert_eq!(proving.state(), ExecutionJobState::Done);
+        assert!(proving.wait().is_ok());
+        assert!(queued.wait().unwrap_err().is::<Cancelled<()>>());
+        assert!(starts.try_recv().is_err());
+        let cancelled = ProvingEvent::Job { job: ExecutionJobId(1), state: ExecutionJobState::Cancelled };
+        assert!(events.lock().unwrap().contains(&cancelled));
+    }
+
+    #[test]
+    fn test_execution_pool_prover_panic() {
+        let (pool, starts, releases, events) = sample_pool(1);
+        let panicking = pool.submit(sample_job(u64::MAX));
+        let next = pool.submit(sample_job(1));
+
+        // The panic fails the job, and the worker goes on with the next one.
+        assert_eq!(recv(&starts), u64::MAX);
+        releases.send(()).unwrap();
+        assert_eq!(recv(&starts), 1);
+        assert_eq!(panicking.state(), ExecutionJobState::Failed);
+        let error = panicking.wait().unwrap_err().to_string();
+        assert_eq!(error, "The prover panicked: The mock prover panics on the largest amount");
+        releases.send(()).unwrap();
+        assert!(next.wait().is_ok());
+        pool.shutdown();
+        let failed = ProvingEvent::Job { job: ExecutionJobId(0), state: ExecutionJobState::Failed };
+        assert!(events.lock().unwrap().contains(&failed));
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+
+use snarkvm_console::{
+    account::ViewKey,
+    program::{Network, Plaintext, Record},
+    types::Field,
+};
+use snarkvm_synthesizer::Transaction;
+
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// The records a transaction returns to the account that built it, e.g. the change of a transfer and of its fee,
+/// and the records of the account it spends
+///
+/// The records are known as soon as the transaction is built, so that a [`crate::RecordStore`] holds them as
+/// pending with [`crate::RecordStore::insert_pending`] until a scan finds the transaction on chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedRecords<N: Network> {
+    transaction_id: N::TransactionID,
+    records: Vec<ExpectedRecord<N>>,
+    spent: Vec<Field<N>>,
+}
+
+// A record output to the account, and its commitment
+type ExpectedRecord<N> = (Field<N>, Record<N, Plaintext<N>>);
+
+impl<N: Network> ExpectedRecords<N> {
+    pub(crate) fn new(transaction_id: N::TransactionID, records: Vec<ExpectedRecord<N>>, spent: Vec<Field<N>>) -> Self {
+        Self { transaction_id, records, spent }
+    }
+
+    /// Returns the ID of the transaction.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the records output to the account and their commitments, in the order of the transaction.
+    pub fn records(&self) -> &[ExpectedRecord<N>] {
+        &self.records
+    }
+
+    /// Returns the commitments of the records of the account spent by the transaction.
+    pub fn spent(&self) -> &[Field<N>] {
+        &self.spent
+    }
+
+    /// Returns the gates held by the records output to the account.
+    pub fn gates(&self) -> u64 {
+        self.records.iter().map(|(_, record)| ***record.gates()).sum()
+    }
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Returns the records the transaction outputs to the account of the program manager, and the records of its
+    /// record store the transaction spends.
+    ///
+    /// Spent records are only found in the record store of the program manager, if one is set. Watch-only program
+    /// managers fail with [`crate::SigningUnavailable`], as spends cannot be told without the private key.
+    pub fn expected_records(&self, transaction: &Transaction<N>) -> Result<ExpectedRecords<N>> {
+        let private_key = self.signer()?;
+        let view_key = ViewKey::try_from(private_key)?;
+        let mut records = vec![];
+        for (commitment, record) in transaction.transitions().flat_map(|transition| transition.records()) {
+            if record.is_owner(&view_key) {
+                records.push((*commitment, record.decrypt(&view_key)?));
+            }
+        }
+        let serial_numbers = transaction.serial_numbers().collect::<HashSet<_>>();
+        let mut spent = vec![];
+        for (commitment, _) in self.record_store.iter().flat_map(|record_store| record_store.iter()) {
+            if serial_numbers.contains(&Record::<N, Plaintext<N>>::serial_number(private_key, *commitment)?) {
+                spent.push(*commitment);
+            }
+        }
+        Ok(ExpectedRecords::new(transaction.id(), records, spent))
+    }
+}
@@ -0,0 +1,351 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+
+use crate::Microcredits;
+
+use snarkvm_console::{
+    account::Address,
+    program::{Identifier, Network, Plaintext, Record, Value},
+    types::Field,
+};
+use snarkvm_synthesizer::{Program, Transaction};
+
+use anyhow::{bail, Result};
+
+/// The fee record chosen by a program manager, reported alongside the transaction it pays for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeeSelection<N: Network> {
+    commitment: Field<N>,
+    record: Record<N, Plaintext<N>>,
+    fee: Microcredits,
+    candidates: usize,
+}
+
+impl<N: Network> FeeSelection<N> {
+    /// Returns the commitment of the selected record
+    pub fn commitment(&self) -> Field<N> {
+        self.commitment
+    }
+
+    /// Returns the selected record
+    pub fn record(&self) -> &Record<N, Plaintext<N>> {
+        &self.record
+    }
+
+    /// Returns the fee the record pays
+    pub fn fee(&self) -> Microcredits {
+        self.fee
+    }
+
+    /// Returns the microcredits returned as change once the fee is paid
+    pub fn change(&self) -> Microcredits {
+        Microcredits(***self.record.gates() - self.fee.0)
+    }
+
+    /// Returns the number of records that could have paid the fee
+    pub fn candidates(&self) -> usize {
+        self.candidates
+    }
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Select an unspent record from the record store to pay a fee of `fee` gates, skipping the records in
+    /// `exclude`, e.g. the records spent by the transition the fee pays for.
+    ///
+    /// The smallest record covering the fee is selected, so records too small for anything else are used up
+    /// first, and larger records stay whole. Ties go to the oldest record. Pending records, and records spent by a
+    /// pending transaction, are never selected.
+    pub fn select_fee_record(
+        &self,
+        fee: Microcredits,
+        exclude: &[&Record<N, Plaintext<N>>],
+    ) -> Result<FeeSelection<N>> {
+        let record_store = match &self.record_store {
+            Some(record_store) => record_store,
+            None => bail!("No record store is set to pay fees from"),
+        };
+        let available = record_store.iter().filter(|(_, stored)| {
+            stored.pending().is_none() && stored.pending_spend().is_none() && !exclude.contains(&stored.record())
+        });
+        let available = available.collect::<Vec<_>>();
+        let gates = |record: &Record<N, Plaintext<N>>| ***record.gates();
+
+        let candidates = available.iter().filter(|(_, stored)| gates(stored.record()) >= fee.0);
+        let candidates = candidates.collect::<Vec<_>>();
+        let selected = candidates
+            .iter()
+            .min_by_key(|(commitment, stored)| (gates(stored.record()), stored.height(), commitment.to_string()));
+        if let Some((commitment, stored)) = selected {
+            return Ok(FeeSelection {
+                commitment: **commitment,
+                record: stored.record().clone(),
+                fee,
+                candidates: candidates.len(),
+            });
+        }
+
+        let largest = available.iter().map(|(_, stored)| gates(stored.record())).max();
+        let total = available.iter().map(|(_, stored)| gates(stored.record())).sum::<u64>();
+        match largest {
+            None => bail!("No unspent record is available to pay a fee of {fee} gates"),
+            Some(largest) if total >= fee.0 => bail!(
+                "No unspent record covers a fee of {fee} gates, as the largest of the {} available records holds \
+                 {largest} gates. Together they hold {total} gates, so joining them would cover the fee",
+                available.len()
+            ),
+            Some(largest) => bail!(
+                "No unspent record covers a fee of {fee} gates, as the largest of the {} available records holds \
+                 {largest} gates, and together they hold only {total} gates",
+                available.len()
+            ),
+        }
+    }
+
+    /// Build a `credits.aleo/transfer` transaction, paying the fee with a record selected by
+    /// [`ProgramManager::select_fee_record`].
+    pub fn build_transfer_auto_fee(
+        &self,
+        amount: Microcredits,
+        fee: Microcredits,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+    ) -> Result<(Transaction<N>, FeeSelection<N>)> {
+        let selection = self.select_fee_record(fee, &[&input_record])?;
+        let transaction = self.build_transfer(amount, fee, recipient, input_record, selection.record.clone())?;
+        Ok((transaction, selection))
+    }
+
+    /// Build a transaction executing a function of the given program, paying the fee with a record selected by
+    /// [`ProgramManager::select_fee_record`] among those not passed as inputs.
+    pub fn build_execution_auto_fee(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+        fee: Microcredits,
+    ) -> Result<(Transaction<N>, FeeSelection<N>)> {
+        let input_records = inputs.iter().filter_map(|input| match input {
+            Value::Record(record) => Some(record),
+            Value::Plaintext(_) => None,
+        });
+        let selection = self.select_fee_record(fee, &input_records.collect::<Vec<_>>())?;
+        let fee_record = selection.record.clone();
+        let transaction = self.build_execution(program, imports, function_name, inputs, fee, fee_record)?;
+        Ok((transaction, selection))
+    }
+
+    /// Build a `credits.aleo/transfer` transaction paying the fee with a selected record, and broadcast it.
+    #[cfg(not(feature = "async"))]
+    pub fn transfer_auto_fee(
+        &self,
+        amount: Microcredits,
+        fee: Microcredits,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+    ) -> Result<(N::TransactionID, FeeSelection<N>)> {
+        let (transaction, selection) = self.build_transfer_auto_fee(amount, fee, recipient, input_record)?;
+        let transaction_id = transaction.id();
+        self.broadcast(transaction)?;
+        Ok((transaction_id, selection))
+    }
+
+    /// Build a transaction executing a function of the given program paying the fee with a selected record, and
+    /// broadcast it.
+    #[cfg(not(feature = "async"))]
+    pub fn execute_auto_fee(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+        fee: Microcredits,
+    ) -> Result<(N::TransactionID, FeeSelection<N>)> {
+        let (transaction, selection) = self.build_execution_auto_fee(program, imports, function_name, inputs, fee)?;
+        let transaction_id = transaction.id();
+        self.broadcast(transaction)?;
+        Ok((transaction_id, selection))
+    }
+}
+
+// The integer overloads of the methods taking and returning fees in `Microcredits`, kept for one release
+impl<N: Network> FeeSelection<N> {
+    /// Returns the fee the record pays, in gates
+    #[deprecated(since = "0.3.6", note = "`FeeSelection::fee` returns `Microcredits`; removed in the next release")]
+    pub fn fee_raw(&self) -> u64 {
+        self.fee.0
+    }
+
+    /// Returns the gates returned as change once the fee is paid
+    #[deprecated(since = "0.3.6", note = "`FeeSelection::change` returns `Microcredits`; removed in the next release")]
+    pub fn change_raw(&self) -> u64 {
+        self.change().0
+    }
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Select an unspent record from the record store to pay a fee of `fee` gates.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `select_fee_record`; removed in the next release")]
+    pub fn select_fee_record_raw(&self, fee: u64, exclude: &[&Record<N, Plaintext<N>>]) -> Result<FeeSelection<N>> {
+        self.select_fee_record(Microcredits(fee), exclude)
+    }
+
+    /// Build a `credits.aleo/transfer` transaction, paying the fee with a selected record.
+    #[deprecated(
+        since = "0.3.6",
+        note = "pass `Microcredits` to `build_transfer_auto_fee`; removed in the next release"
+    )]
+    pub fn build_transfer_auto_fee_raw(
+        &self,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+    ) -> Result<(Transaction<N>, FeeSelection<N>)> {
+        self.build_transfer_auto_fee(Microcredits(amount), Microcredits(fee), recipient, input_record)
+    }
+
+    /// Build a transaction executing a function of the given program, paying the fee with a selected record.
+    #[deprecated(
+        since = "0.3.6",
+        note = "pass `Microcredits` to `build_execution_auto_fee`; removed in the next release"
+    )]
+    pub fn build_execution_auto_fee_raw(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+        fee: u64,
+    ) -> Result<(Transaction<N>, FeeSelection<N>)> {
+        self.build_execution_auto_fee(program, imports, function_name, inputs, Microcredits(fee))
+    }
+
+    /// Build a `credits.aleo/transfer` transaction paying the fee with a selected record, and broadcast it.
+    #[cfg(not(feature = "async"))]
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `transfer_auto_fee`; removed in the next release")]
+    pub fn transfer_auto_fee_raw(
+        &self,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+    ) -> Result<(N::TransactionID, FeeSelection<N>)> {
+        self.transfer_auto_fee(Microcredits(amount), Microcredits(fee), recipient, input_record)
+    }
+
+    /// Build a transaction executing a function of the given program paying the fee with a selected record, and
+    /// broadcast it.
+    #[cfg(not(feature = "async"))]
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `execute_auto_fee`; removed in the next release")]
+    pub fn execute_auto_fee_raw(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+        fee: u64,
+    ) -> Result<(N::TransactionID, FeeSelection<N>)> {
+        self.execute_auto_fee(program, imports, function_name, inputs, Microcredits(fee))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_record, CurrentNetwork},
+        testnet3,
+        RecordStore,
+    };
+    use snarkvm_console::{account::PrivateKey, prelude::Uniform};
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    // Create a program manager whose record store holds records of the given gates, created at increasing heights
+    fn sample_manager(gates: &[u64], rng: &mut TestRng) -> (ProgramManager<N>, Vec<Record<N, Plaintext<N>>>) {
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let address = Address::try_from(&private_key).unwrap();
+        let mut record_store = RecordStore::new();
+        let mut records = Vec::new();
+        for (height, gates) in (0..).zip(gates) {
+            let (record, _) = sample_record(address, *gates, rng);
+            record_store.insert(Field::rand(rng), record.clone(), height);
+            records.push(record);
+        }
+        let manager = ProgramManager::new(private_key, testnet3("http://127.0.0.1:9")).with_record_store(record_store);
+        (manager, records)
+    }
+
+    #[test]
+    fn test_select_fee_record_preference() {
+        let rng = &mut TestRng::default();
+        let (manager, records) = sample_manager(&[100, 3, 12, 5, 5], rng);
+
+        // The smallest record covering the fee is selected, and the oldest among equal records.
+        let selection = manager.select_fee_record(Microcredits(4), &[]).unwrap();
+        assert_eq!(selection.record(), &records[3]);
+        assert_eq!(
+            (selection.fee(), selection.change(), selection.candidates()),
+            (Microcredits(4), Microcredits(1), 4)
+        );
+        let stored = manager.record_store().unwrap().get(&selection.commitment()).unwrap();
+        assert_eq!(stored.record(), &records[3]);
+        assert_eq!(manager.select_fee_record(Microcredits(3), &[]).unwrap().record(), &records[1]);
+        assert_eq!(manager.select_fee_record(Microcredits(6), &[]).unwrap().record(), &records[2]);
+
+        // Records spent by the main transition are never selected.
+        assert_eq!(manager.select_fee_record(Microcredits(4), &[&records[3]]).unwrap().record(), &records[4]);
+        let selection = manager.select_fee_record(Microcredits(4), &[&records[3], &records[4], &records[2]]).unwrap();
+        assert_eq!((selection.record(), selection.candidates()), (&records[0], 1));
+    }
+
+    #[test]
+    fn test_select_fee_record_insufficient_funds() {
+        let rng = &mut TestRng::default();
+        let (manager, records) = sample_manager(&[3, 12, 5], rng);
+
+        // The error explains whether joining the records would cover the fee.
+        let error = manager.select_fee_record(Microcredits(15), &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No unspent record covers a fee of 15 gates, as the largest of the 3 available records holds 12 gates. \
+             Together they hold 20 gates, so joining them would cover the fee"
+        );
+        let error = manager.select_fee_record(Microcredits(10), &[&records[1]]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No unspent record covers a fee of 10 gates, as the largest of the 2 available records holds 5 gates, \
+             and together they hold only 8 gates"
+        );
+        let error = manager.select_fee_record(Microcredits(1), &[&records[0], &records[1], &records[2]]).unwrap_err();
+        assert_eq!(error.to_string(), "No unspent record is available to pay a fee of 1 gates");
+
+        // The transfer fails before proving when no record covers the fee.
+        let address = Address::try_from(manager.private_key().unwrap()).unwrap();
+        let error = manager
+            .build_transfer_auto_fee(Microcredits(1), Microcredits(15), address, records[1].clone())
+            .unwrap_err();
+        assert!(error.to_string().starts_with("No unspent record covers a fee of 15 gates"), "{error}");
+
+        let manager = ProgramManager::new(PrivateKey::<N>::new(rng).unwrap(), testnet3("http://127.0.0.1:9"));
+        let error = manager.select_fee_record(Microcredits(1), &[]).unwrap_err();
+        assert_eq!(error.to_string(), "No record store is set to pay fees from");
+    }
+}
@@ -0,0 +1,405 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+#[cfg(not(feature = "async"))]
+use super::ProvingEvent;
+use crate::mutex::lock;
+
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Transaction;
+
+use anyhow::{anyhow, Result};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// The fees of a transaction, by the transitions paying them
+///
+/// snarkVM charges the fee of a transaction as the sum of the fees of its transitions, which is the difference
+/// between the gates the transition spends and the gates it outputs. It charges no base, storage, or finalize fee,
+/// so the fee paid by the `credits.aleo/fee` transition is a priority fee chosen by the sender, and the other
+/// transitions only pay fees when they burn gates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeeBreakdown {
+    /// The fee paid by the `credits.aleo/fee` transition, if the transaction has one
+    pub priority: i64,
+    /// The fees paid by the other transitions
+    pub execution: i64,
+    /// The `program/function` of each transition, in order
+    pub transitions: Vec<String>,
+}
+
+impl FeeBreakdown {
+    /// Returns the fees of the transitions of a transaction.
+    pub fn of<N: Network>(transaction: &Transaction<N>) -> Self {
+        let (mut priority, mut execution, mut transitions) = (0i64, 0i64, vec![]);
+        for transition in transaction.transitions() {
+            let function = format!("{}/{}", transition.program_id(), transition.function_name());
+            match function == "credits.aleo/fee" {
+                true => priority = priority.saturating_add(*transition.fee()),
+                false => execution = execution.saturating_add(*transition.fee()),
+            }
+            transitions.push(function);
+        }
+        Self { priority, execution, transitions }
+    }
+
+    /// Returns the fee of the transaction, in gates.
+    pub fn total(&self) -> i64 {
+        self.priority.saturating_add(self.execution)
+    }
+}
+
+/// How the fee charged for a transaction differs from the fee estimated when it was broadcast
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FeeDeltaKind {
+    /// The charged fee matches the estimate
+    Exact,
+    /// Only the fee of the `credits.aleo/fee` transition differs
+    Priority,
+    /// Only the fees of the other transitions differ
+    Execution,
+    /// Both the fee of the `credits.aleo/fee` transition and the fees of the other transitions differ
+    PriorityAndExecution,
+    /// The confirmed transaction has other transitions than the broadcast one, so that the estimator no longer
+    /// models what the node charges, e.g. after a change of the protocol
+    EstimatorDrift,
+}
+
+impl FeeDeltaKind {
+    /// Returns the name of the kind, as used to label metrics, e.g. `estimator_drift`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::Priority => "priority",
+            Self::Execution => "execution",
+            Self::PriorityAndExecution => "priority_and_execution",
+            Self::EstimatorDrift => "estimator_drift",
+        }
+    }
+}
+
+impl fmt::Display for FeeDeltaKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The fee estimated for a broadcast transaction, reconciled with the fee charged for it once confirmed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeeReconciliation<N: Network> {
+    transaction_id: N::TransactionID,
+    estimated: FeeBreakdown,
+    actual: FeeBreakdown,
+    kind: FeeDeltaKind,
+}
+
+impl<N: Network> FeeReconciliation<N> {
+    /// Reconcile the estimated fees of a transaction with the fees charged for it.
+    pub fn new(transaction_id: N::TransactionID, estimated: FeeBreakdown, actual: FeeBreakdown) -> Self {
+        let kind = match (estimated.priority != actual.priority, estimated.execution != actual.execution) {
+            _ if estimated.transitions != actual.transitions => FeeDeltaKind::EstimatorDrift,
+            (false, false) => FeeDeltaKind::Exact,
+            (true, false) => FeeDeltaKind::Priority,
+            (false, true) => FeeDeltaKind::Execution,
+            (true, true) => FeeDeltaKind::PriorityAndExecution,
+        };
+        Self { transaction_id, estimated, actual, kind }
+    }
+
+    /// Returns the ID of the transaction.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the fees estimated when the transaction was broadcast.
+    pub fn estimated(&self) -> &FeeBreakdown {
+        &self.estimated
+    }
+
+    /// Returns the fees charged for the confirmed transaction.
+    pub fn actual(&self) -> &FeeBreakdown {
+        &self.actual
+    }
+
+    /// Returns how the charged fee differs from the estimate.
+    pub fn kind(&self) -> FeeDeltaKind {
+        self.kind
+    }
+
+    /// Returns the charged fee minus the estimated fee, in gates.
+    pub fn delta(&self) -> i64 {
+        self.actual.total().saturating_sub(self.estimated.total())
+    }
+
+    /// Returns `true` if the charged fee differs from the estimate by more than the tolerance, or if the estimator
+    /// drifted, whatever the fees.
+    pub fn exceeds(&self, tolerance: u64) -> bool {
+        self.kind == FeeDeltaKind::EstimatorDrift || self.delta().unsigned_abs() > tolerance
+    }
+}
+
+/// A record of the fees estimated for the transactions broadcast by a [`ProgramManager`], to reconcile them with
+/// the fees charged once the transactions are confirmed
+///
+/// Clones of an audit share its records, so that an audit attached to a program manager can be reconciled from
+/// elsewhere. Reconciliations whose fee differs from the estimate by more than the tolerance are reported to the
+/// progress reporter of the program manager, and counted with [`crate::METRIC_FEE_DEVIATIONS`].
+#[derive(Clone, Debug)]
+pub struct FeeAudit<N: Network> {
+    tolerance: u64,
+    estimates: Arc<Mutex<HashMap<N::TransactionID, FeeBreakdown>>>,
+}
+
+impl<N: Network> Default for FeeAudit<N> {
+    fn default() -> Self {
+        Self { tolerance: 0, estimates: Default::default() }
+    }
+}
+
+impl<N: Network> FeeAudit<N> {
+    /// Create an audit without records, flagging every fee that differs from its estimate.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only flag the fees that differ from their estimate by more than the given number of gates.
+    pub fn with_tolerance(mut self, tolerance: u64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Returns the number of gates by which a fee may differ from its estimate without being flagged.
+    pub fn tolerance(&self) -> u64 {
+        self.tolerance
+    }
+
+    /// Record the fees of a transaction about to be broadcast, as the estimate to reconcile.
+    pub fn record(&self, transaction: &Transaction<N>) {
+        lock(&self.estimates).insert(transaction.id(), FeeBreakdown::of(transaction));
+    }
+
+    /// Returns the fees estimated for a transaction, if they were recorded.
+    pub fn estimate(&self, transaction_id: &N::TransactionID) -> Option<FeeBreakdown> {
+        lock(&self.estimates).get(transaction_id).cloned()
+    }
+
+    /// Forget the fees estimated for a transaction, e.g. once it was reconciled. Returns `false` if none were
+    /// recorded.
+    pub fn forget(&self, transaction_id: &N::TransactionID) -> bool {
+        lock(&self.estimates).remove(transaction_id).is_some()
+    }
+
+    /// Returns the number of transactions whose fees are recorded.
+    pub fn len(&self) -> usize {
+        lock(&self.estimates).len()
+    }
+
+    /// Returns `true` if no fees are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reconcile the recorded estimate of a transaction with the fees of the confirmed transaction.
+    pub fn reconcile(&self, confirmed: &Transaction<N>) -> Result<FeeReconciliation<N>> {
+        let transaction_id = confirmed.id();
+        let estimated = self
+            .estimate(&transaction_id)
+            .ok_or_else(|| anyhow!("No fee estimate was recorded for transaction {transaction_id}"))?;
+        Ok(FeeReconciliation::new(transaction_id, estimated, FeeBreakdown::of(confirmed)))
+    }
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Record the estimated fees of the transactions the program manager broadcasts into the given audit, to
+    /// reconcile them with [`ProgramManager::audit_fee`].
+    pub fn with_fee_audit(mut self, fee_audit: FeeAudit<N>) -> Self {
+        self.fee_audit = Some(fee_audit);
+        self
+    }
+
+    /// Returns the audit of the fees of the transactions the program manager broadcasts, if any.
+    pub fn fee_audit(&self) -> Option<&FeeAudit<N>> {
+        self.fee_audit.as_ref()
+    }
+
+    /// Fetch a confirmed transaction broadcast by the program manager, and reconcile its fees with those estimated
+    /// when it was broadcast.
+    ///
+    /// A fee that differs from its estimate by more than the tolerance of the audit is reported to the progress
+    /// reporter as a [`ProvingEvent::FeeDeviation`], and counted with [`crate::METRIC_FEE_DEVIATIONS`].
+    #[cfg(not(feature = "async"))]
+    pub fn audit_fee(&self, transaction_id: N::TransactionID) -> Result<FeeReconciliation<N>> {
+        let fee_audit = self.fee_audit.as_ref().ok_or_else(|| anyhow!("No fee audit is set to reconcile fees with"))?;
+        let estimated = fee_audit
+            .estimate(&transaction_id)
+            .ok_or_else(|| anyhow!("No fee estimate was recorded for transaction {transaction_id}"))?;
+        let actual = FeeBreakdown::of(&self.api_client.get_transaction(transaction_id)?);
+        let reconciliation = FeeReconciliation::new(transaction_id, estimated, actual);
+        if reconciliation.exceeds(fee_audit.tolerance()) {
+            self.report(ProvingEvent::FeeDeviation {
+                transaction_id: transaction_id.to_string(),
+                estimated: reconciliation.estimated.total(),
+                actual: reconciliation.actual.total(),
+                kind: reconciliation.kind,
+            });
+            self.api_client.count_fee_deviation(reconciliation.kind);
+        }
+        Ok(reconciliation)
+    }
+
+    // Broadcast a transaction, recording its fees into the fee audit first, if there is one, and writing whether the
+    // node accepted it to the event log, if there is one
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn broadcast(&self, transaction: Transaction<N>) -> Result<()> {
+        if let Some(fee_audit) = &self.fee_audit {
+            fee_audit.record(&transaction);
+        }
+        let transaction_id = transaction.id();
+        let result = self.api_client.transaction_broadcast(transaction);
+        let error = result.as_ref().err().map(|error| error.to_string());
+        self.log_event(crate::WalletEvent::TransactionBroadcast { transaction_id, error })?;
+        result?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, sample_transaction, CurrentNetwork};
+    #[cfg(not(feature = "async"))]
+    use crate::{
+        test_helpers::{MockResponse, MockServer},
+        testnet3,
+    };
+
+    #[cfg(not(feature = "async"))]
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_console::{
+        prelude::Uniform,
+        program::{Identifier, ProgramID},
+        types::Field,
+    };
+    use snarkvm_synthesizer::{Input, Transition};
+    use snarkvm_utilities::TestRng;
+    use std::str::FromStr;
+
+    type N = CurrentNetwork;
+
+    // Returns a transition of the given `program/function` paying the given fee, with the proof of the genesis block
+    // and a random input, so that the IDs of the transitions differ
+    fn transition(function: &str, fee: i64) -> Transition<N> {
+        let rng = &mut TestRng::default();
+        let genesis = genesis_block();
+        let template = genesis.transitions().next().unwrap();
+        let (program_id, function_name) = function.split_once('/').unwrap();
+        Transition::new(
+            ProgramID::from_str(program_id).unwrap(),
+            Identifier::from_str(function_name).unwrap(),
+            vec![Input::Record(Field::rand(rng), Field::rand(rng))],
+            vec![],
+            None,
+            template.proof().clone(),
+            *template.tpk(),
+            *template.tcm(),
+            fee,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_fee_audit_reconciliation() {
+        let broadcast = sample_transaction([transition("token.aleo/mint", 0), transition("credits.aleo/fee", 300)]);
+        let audit = FeeAudit::<N>::new().with_tolerance(50);
+        audit.record(&broadcast);
+        let estimated = audit.estimate(&broadcast.id()).unwrap();
+        assert_eq!((estimated.priority, estimated.execution, estimated.total()), (300, 0, 300));
+        assert_eq!(estimated.transitions, ["token.aleo/mint", "credits.aleo/fee"]);
+
+        // The confirmed transaction charges what was estimated.
+        let reconciliation = audit.reconcile(&broadcast).unwrap();
+        assert_eq!((reconciliation.kind(), reconciliation.delta()), (FeeDeltaKind::Exact, 0));
+        assert!(!reconciliation.exceeds(audit.tolerance()));
+
+        // Only the fee of the fee transition differs, within the tolerance or beyond it.
+        let actual = FeeBreakdown { priority: 340, ..estimated.clone() };
+        let reconciliation = FeeReconciliation::<N>::new(broadcast.id(), estimated.clone(), actual);
+        assert_eq!((reconciliation.kind(), reconciliation.delta()), (FeeDeltaKind::Priority, 40));
+        assert!(!reconciliation.exceeds(50) && reconciliation.exceeds(39));
+        let actual = FeeBreakdown { priority: 200, execution: 20, ..estimated.clone() };
+        let reconciliation = FeeReconciliation::<N>::new(broadcast.id(), estimated.clone(), actual);
+        assert_eq!((reconciliation.kind(), reconciliation.delta()), (FeeDeltaKind::PriorityAndExecution, -80));
+
+        // Fees moved between the transitions are told apart, even when they add up.
+        let moved = sample_transaction([transition("token.aleo/mint", 100), transition("credits.aleo/fee", 200)]);
+        let reconciliation = FeeReconciliation::<N>::new(broadcast.id(), estimated, FeeBreakdown::of(&moved));
+        assert_eq!((reconciliation.kind(), reconciliation.delta()), (FeeDeltaKind::PriorityAndExecution, 0));
+
+        // A confirmed transaction with other transitions is flagged as drift, beyond any tolerance.
+        let structural = sample_transaction([transition("token.aleo/mint", 0), transition("token.aleo/burn", 300)]);
+        let actual = FeeBreakdown::of(&structural);
+        assert_eq!((actual.priority, actual.execution), (0, 300));
+        let estimated = audit.estimate(&broadcast.id()).unwrap();
+        let reconciliation = FeeReconciliation::<N>::new(broadcast.id(), estimated, actual);
+        assert_eq!((reconciliation.kind(), reconciliation.delta()), (FeeDeltaKind::EstimatorDrift, 0));
+        assert!(reconciliation.exceeds(u64::MAX));
+        assert_eq!(reconciliation.kind().to_string(), "estimator_drift");
+
+        // Transactions without a recorded estimate are not reconciled.
+        let error = audit.reconcile(&structural).unwrap_err();
+        assert_eq!(error.to_string(), format!("No fee estimate was recorded for transaction {}", structural.id()));
+        assert!(audit.clone().forget(&broadcast.id()) && audit.is_empty());
+    }
+
+    #[cfg(not(feature = "async"))]
+    #[test]
+    fn test_audit_fee() {
+        let rng = &mut TestRng::default();
+        let broadcast = sample_transaction([transition("token.aleo/mint", 0), transition("credits.aleo/fee", 300)]);
+        let drifted = sample_transaction([transition("token.aleo/mint", 0), transition("token.aleo/burn", 300)]);
+
+        // The node serves the drifted transaction under the ID of the broadcast one, as a node charging otherwise
+        // would.
+        let served = drifted.to_string();
+        let server = MockServer::start(move |request| {
+            request.path.starts_with("/testnet3/transaction/").then(|| MockResponse::json(&served))
+        });
+        let events = Arc::new(Mutex::new(vec![]));
+        let reported = events.clone();
+        let audit = FeeAudit::new().with_tolerance(1000);
+        let manager = ProgramManager::new(PrivateKey::<N>::new(rng).unwrap(), testnet3(server.base_url()))
+            .with_fee_audit(audit.clone())
+            .with_progress_reporter(move |event| reported.lock().unwrap().push(event));
+        assert!(manager.audit_fee(broadcast.id()).is_err());
+
+        audit.record(&broadcast);
+        let reconciliation = manager.audit_fee(broadcast.id()).unwrap();
+        assert_eq!(reconciliation.kind(), FeeDeltaKind::EstimatorDrift);
+        assert_eq!(*events.lock().unwrap(), [ProvingEvent::FeeDeviation {
+            transaction_id: broadcast.id().to_string(),
+            estimated: 300,
+            actual: 300,
+            kind: FeeDeltaKind::EstimatorDrift,
+        }]);
+        let unaudited = ProgramManager::new(PrivateKey::<N>::new(rng).unwrap(), testnet3(server.base_url()));
+        let error = unaudited.audit_fee(broadcast.id()).unwrap_err();
+        assert_eq!(error.to_string(), "No fee audit is set to reconcile fees with");
+    }
+}
@@ -0,0 +1,734 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{deploy::matches_plaintext, ProgramManager};
+
+use snarkvm_console::{
+    account::Address,
+    prelude::Zero,
+    program::{
+        Entry,
+        FinalizeType,
+        Identifier,
+        Literal,
+        Network,
+        Plaintext,
+        ProgramID,
+        Register,
+        Value,
+        I128,
+        I16,
+        I32,
+        I64,
+        I8,
+        U128,
+        U16,
+        U32,
+        U64,
+        U8,
+    },
+};
+use snarkvm_synthesizer::{
+    program::finalize::{Command, Finalize},
+    Block,
+    Operand,
+    Program,
+};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use indexmap::IndexMap;
+
+/// The values of mappings of deployed programs, against which [`ProgramManager::simulate_finalize`] runs
+///
+/// A snapshot holds the keys it was given, fetched with [`crate::AleoAPIClient::snapshot_mapping`] or inserted by
+/// the caller. Keys missing from the snapshot are treated as unset, as on a node, so a snapshot must hold every
+/// key that finalize reads.
+///
+/// A snapshot pinned to the height of the block after which it was taken, e.g. with [`MappingSnapshot::with_height`],
+/// can be replayed to a later height by [`crate::AleoAPIClient::replay_mapping_value`], for the keys it covers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MappingSnapshot<N: Network> {
+    mappings: IndexMap<(ProgramID<N>, Identifier<N>), MappingEntries<N>>,
+    // The keys known to be unset, as opposed to keys missing from the snapshot
+    unset: Vec<(ProgramID<N>, Identifier<N>, Plaintext<N>)>,
+    height: Option<u32>,
+    // Whether the snapshot holds every key of every mapping, as at genesis
+    complete: bool,
+}
+
+// The keys and values of a mapping, searched linearly as plaintexts cannot be hashed
+type MappingEntries<N> = Vec<(Plaintext<N>, Value<N>)>;
+
+/// A value set in a mapping by a finalize scope
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MappingMutation<N: Network> {
+    /// The program defining the mapping
+    pub program_id: ProgramID<N>,
+    /// The name of the mapping
+    pub mapping_name: Identifier<N>,
+    /// The key whose value is set
+    pub key: Plaintext<N>,
+    /// The new value of the key
+    pub value: Value<N>,
+}
+
+/// The outcome of [`ProgramManager::simulate_finalize`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FinalizeOutcome<N: Network> {
+    /// Finalize succeeds, setting the values in order
+    Success(Vec<MappingMutation<N>>),
+    /// Finalize fails, so the network would not accept the transaction
+    Failure(FinalizeFailure),
+}
+
+/// The command of a finalize scope that fails, and the reason it fails
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FinalizeFailure {
+    index: usize,
+    command: String,
+    reason: String,
+}
+
+impl FinalizeFailure {
+    /// Returns the position of the failing command in the finalize scope.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the failing command, as written in the program.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Returns the reason the command fails, as reported by snarkVM.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl<N: Network> MappingSnapshot<N> {
+    /// Create an empty snapshot.
+    pub fn new() -> Self {
+        Self { mappings: IndexMap::new(), unset: Vec::new(), height: None, complete: false }
+    }
+
+    /// Create the snapshot of the mappings after the genesis block, in which every key is unset.
+    pub fn at_genesis() -> Self {
+        Self { mappings: IndexMap::new(), unset: Vec::new(), height: Some(0), complete: true }
+    }
+
+    /// Pin the snapshot to the height of the block after which its values were read.
+    pub fn with_height(mut self, height: u32) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    /// Returns the height of the block after which the values of the snapshot were read, if it is pinned.
+    pub fn height(&self) -> Option<u32> {
+        self.height
+    }
+
+    /// Record that a key of a mapping is unset.
+    pub fn insert_unset(&mut self, program_id: ProgramID<N>, mapping_name: Identifier<N>, key: Plaintext<N>) {
+        if let Some(entries) = self.mappings.get_mut(&(program_id, mapping_name)) {
+            entries.retain(|(entry_key, _)| *entry_key != key);
+        }
+        if !self.covers(&program_id, &mapping_name, &key) {
+            self.unset.push((program_id, mapping_name, key));
+        }
+    }
+
+    /// Returns `true` if the snapshot knows the value of a key in a mapping, or knows the key is unset.
+    pub fn covers(&self, program_id: &ProgramID<N>, mapping_name: &Identifier<N>, key: &Plaintext<N>) -> bool {
+        self.complete
+            || self.get(program_id, mapping_name, key).is_some()
+            || self.unset.iter().any(|(id, name, unset)| id == program_id && name == mapping_name && unset == key)
+    }
+
+    /// Set the value of a key in a mapping.
+    pub fn insert(
+        &mut self,
+        program_id: ProgramID<N>,
+        mapping_name: Identifier<N>,
+        key: Plaintext<N>,
+        value: Value<N>,
+    ) {
+        self.unset.retain(|(id, name, unset)| *id != program_id || *name != mapping_name || *unset != key);
+        let entries = self.mappings.entry((program_id, mapping_name)).or_default();
+        match entries.iter_mut().find(|(entry_key, _)| *entry_key == key) {
+            Some((_, entry_value)) => *entry_value = value,
+            None => entries.push((key, value)),
+        }
+    }
+
+    /// Returns the value of a key in a mapping, or `None` if the key is unset.
+    pub fn get(
+        &self,
+        program_id: &ProgramID<N>,
+        mapping_name: &Identifier<N>,
+        key: &Plaintext<N>,
+    ) -> Option<&Value<N>> {
+        let entries = self.mappings.get(&(*program_id, *mapping_name))?;
+        entries.iter().find(|(entry_key, _)| entry_key == key).map(|(_, value)| value)
+    }
+
+    /// Apply the mutations of a successful finalize, e.g. to simulate the next transaction.
+    pub fn apply(&mut self, mutations: &[MappingMutation<N>]) {
+        for mutation in mutations {
+            self.insert(mutation.program_id, mutation.mapping_name, mutation.key.clone(), mutation.value.clone());
+        }
+    }
+}
+
+impl<N: Network> Default for MappingSnapshot<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Simulate the finalize scope of a function called with the given inputs against a snapshot of the mappings,
+    /// predicting whether the network accepts the call before a fee is paid for it.
+    ///
+    /// The finalize inputs are taken from the function inputs, the program ID and the address of the account as
+    /// `self.caller`, so functions passing registers computed by their instructions to finalize cannot be
+    /// simulated. As in snarkVM 0.9.13, finalize scopes support `increment` and `decrement`, and fail on any other
+    /// instruction. Integer overflows fail with the message of snarkVM.
+    pub fn simulate_finalize(
+        &self,
+        program: &Program<N>,
+        function_name: Identifier<N>,
+        inputs: &[Value<N>],
+        snapshot: &MappingSnapshot<N>,
+    ) -> Result<FinalizeOutcome<N>> {
+        let function = program.get_function(&function_name)?;
+        let Some((finalize_command, finalize)) = function.finalize() else {
+            bail!("Function '{}/{function_name}' has no finalize scope", program.id());
+        };
+        ensure!(
+            function.inputs().len() == inputs.len(),
+            "Function '{function_name}' expects {} inputs, but {} were given",
+            function.inputs().len(),
+            inputs.len()
+        );
+
+        // Compute the finalize inputs from the operands of the `finalize` command of the function.
+        let caller = Address::try_from(self.signer()?)?;
+        let function_registers = function.inputs().iter().map(|input| input.register()).zip(inputs);
+        let function_registers = function_registers.map(|(register, value)| (register.locator(), value.clone()));
+        let function_registers = function_registers.collect::<IndexMap<_, _>>();
+        let mut finalize_inputs = Vec::with_capacity(finalize.inputs().len());
+        for (operand, input) in finalize_command.operands().iter().zip(finalize.inputs()) {
+            let value = match operand {
+                Operand::Caller => Value::Plaintext(Plaintext::from(Literal::Address(caller))),
+                Operand::Register(register) if function_registers.contains_key(&register.locator()) => {
+                    load(&function_registers, operand)?
+                }
+                Operand::Register(register) => bail!(
+                    "Finalize input '{register}' of function '{function_name}' is computed by the function, so it \
+                     cannot be simulated"
+                ),
+                operand => load(&function_registers, operand)?,
+            };
+            if let (Value::Plaintext(plaintext), FinalizeType::Public(plaintext_type)) = (&value, input.finalize_type())
+            {
+                ensure!(
+                    matches_plaintext(program, plaintext, plaintext_type),
+                    "Finalize input '{}' of function '{function_name}' is not of type '{}'",
+                    input.register(),
+                    input.finalize_type()
+                );
+            }
+            finalize_inputs.push(value);
+        }
+        evaluate(program, finalize, &finalize_inputs, snapshot, false)
+    }
+}
+
+// Evaluate a finalize scope of the program with the given inputs against a copy of the snapshot, which holds the
+// values set by earlier commands. When replaying, commands on keys the snapshot does not cover are skipped, as their
+// starting values are unknown.
+fn evaluate<N: Network>(
+    program: &Program<N>,
+    finalize: &Finalize<N>,
+    finalize_inputs: &[Value<N>],
+    snapshot: &MappingSnapshot<N>,
+    replay: bool,
+) -> Result<FinalizeOutcome<N>> {
+    ensure!(
+        finalize_inputs.len() == finalize.inputs().len(),
+        "Expected {} inputs to 'finalize', found {}",
+        finalize.inputs().len(),
+        finalize_inputs.len()
+    );
+    let registers = finalize.inputs().iter().map(|input| input.register().locator());
+    let registers = registers.zip(finalize_inputs.iter().cloned()).collect::<IndexMap<_, _>>();
+    let mut state = snapshot.clone();
+    let mut mutations = Vec::new();
+    for (index, command) in finalize.commands().iter().enumerate() {
+        let failure = |reason: String| {
+            FinalizeOutcome::Failure(FinalizeFailure { index, command: command.to_string(), reason })
+        };
+        let (mapping_name, key, amount, increment) = match command {
+            Command::Increment(increment) => (increment.mapping_name(), increment.key(), increment.value(), true),
+            Command::Decrement(decrement) => (decrement.mapping_name(), decrement.key(), decrement.value(), false),
+            Command::Instruction(_) => {
+                return Ok(failure("Instructions in 'finalize' are not supported (yet).".to_string()));
+            }
+        };
+        if !program.mappings().contains_key(mapping_name) {
+            return Ok(failure(format!("Mapping '{}/{mapping_name}' does not exist in storage", program.id())));
+        }
+        let key = match load(&registers, key)? {
+            Value::Plaintext(key) => key,
+            Value::Record(..) => return Ok(failure("Operand must be a plaintext".to_string())),
+        };
+        if replay && !state.covers(program.id(), mapping_name, &key) {
+            continue;
+        }
+        let amount = match load(&registers, amount)? {
+            Value::Plaintext(Plaintext::Literal(amount, _)) => amount,
+            _ => return Ok(failure("Operand must be a literal".to_string())),
+        };
+        let start = state.get(program.id(), mapping_name, &key).cloned();
+        match update(start, amount, increment) {
+            Ok(literal) => {
+                let value = Value::Plaintext(Plaintext::from(literal));
+                state.insert(*program.id(), *mapping_name, key.clone(), value.clone());
+                mutations.push(MappingMutation {
+                    program_id: *program.id(),
+                    mapping_name: *mapping_name,
+                    key,
+                    value,
+                });
+            }
+            Err(reason) => return Ok(failure(reason)),
+        }
+    }
+    Ok(FinalizeOutcome::Success(mutations))
+}
+
+// Replay the finalize scopes the transitions of the program in the block ran, in the order of its transactions and
+// transitions, applying their mutations to the keys the snapshot covers. Fails rather than skip a transition that
+// cannot be replayed, so that the covered keys stay exact.
+pub(crate) fn replay_block<N: Network>(
+    snapshot: &mut MappingSnapshot<N>,
+    program: &Program<N>,
+    block: &Block<N>,
+) -> Result<()> {
+    for transition in block.transactions().transitions().filter(|transition| transition.program_id() == program.id()) {
+        let function = program.get_function(transition.function_name())?;
+        let Some((_, finalize)) = function.finalize() else {
+            continue;
+        };
+        let Some(finalize_inputs) = transition.finalize() else {
+            bail!("Transition '{}' in block {} has no finalize inputs", transition.id(), block.height());
+        };
+        match evaluate(program, finalize, finalize_inputs, snapshot, true)? {
+            FinalizeOutcome::Success(mutations) => snapshot.apply(&mutations),
+            FinalizeOutcome::Failure(failure) => bail!(
+                "Failed to replay the finalize of transition '{}' in block {}: {}",
+                transition.id(),
+                block.height(),
+                failure.reason()
+            ),
+        }
+    }
+    if snapshot.height.is_some() {
+        snapshot.height = Some(block.height());
+    }
+    Ok(())
+}
+
+// Returns the snapshot with the highest height at or below the given height that covers the key, among the given
+// snapshots and the snapshot at genesis
+pub(crate) fn nearest_snapshot<N: Network>(
+    snapshots: &[MappingSnapshot<N>],
+    program_id: &ProgramID<N>,
+    mapping_name: &Identifier<N>,
+    key: &Plaintext<N>,
+    height: u32,
+) -> MappingSnapshot<N> {
+    snapshots
+        .iter()
+        .filter(|snapshot| snapshot.height.map_or(false, |start| start <= height))
+        .filter(|snapshot| snapshot.covers(program_id, mapping_name, key))
+        .max_by_key(|snapshot| snapshot.height)
+        .cloned()
+        .unwrap_or_else(MappingSnapshot::at_genesis)
+}
+
+// Replay the blocks, which must follow the snapshot in order up to the given height, onto the snapshot
+pub(crate) fn replay_blocks<N: Network>(
+    snapshot: &mut MappingSnapshot<N>,
+    program: &Program<N>,
+    blocks: &[Block<N>],
+    height: u32,
+) -> Result<()> {
+    let start = snapshot.height.ok_or_else(|| anyhow!("The snapshot is not pinned to a height"))?;
+    for (expected, block) in (start + 1..=height).zip(blocks) {
+        ensure!(block.height() == expected, "Expected block {expected} to replay, found block {}", block.height());
+        replay_block(snapshot, program, block)?;
+    }
+    ensure!(
+        snapshot.height == Some(height),
+        "Blocks {}..={height} are unavailable to replay",
+        snapshot.height.map_or(start, |replayed| replayed + 1)
+    );
+    Ok(())
+}
+
+// Load the value of an operand from the registers, as a finalize scope does
+fn load<N: Network>(registers: &IndexMap<u64, Value<N>>, operand: &Operand<N>) -> Result<Value<N>> {
+    let register = match operand {
+        Operand::Literal(literal) => return Ok(Value::Plaintext(Plaintext::from(literal))),
+        Operand::ProgramID(program_id) => {
+            return Ok(Value::Plaintext(Plaintext::from(Literal::Address(program_id.to_address()?))));
+        }
+        Operand::Caller => bail!("Forbidden operation: Cannot use 'self.caller' in 'finalize'"),
+        Operand::Register(register) => register,
+    };
+    let value = registers.get(&register.locator()).ok_or_else(|| anyhow!("'{register}' does not exist"))?;
+    match (register, value) {
+        (Register::Locator(_), value) => Ok(value.clone()),
+        (Register::Member(_, path), Value::Plaintext(plaintext)) => Ok(Value::Plaintext(plaintext.find(path)?)),
+        (Register::Member(_, path), Value::Record(record)) => match record.find(path)? {
+            Entry::Constant(plaintext) | Entry::Public(plaintext) | Entry::Private(plaintext) => {
+                Ok(Value::Plaintext(plaintext))
+            }
+        },
+    }
+}
+
+// Add the amount to the value of a key, or subtract it, starting from zero if the key is unset. Failures are
+// returned with the message of snarkVM.
+fn update<N: Network>(start: Option<Value<N>>, amount: Literal<N>, increment: bool) -> Result<Literal<N>, String> {
+    let name = if increment { "increment" } else { "decrement" };
+    let start = match start {
+        Some(Value::Plaintext(Plaintext::Literal(literal, _))) => literal,
+        Some(Value::Plaintext(Plaintext::Struct(..))) => return Err(format!("Cannot '{name}' by an 'struct'")),
+        Some(Value::Record(..)) => return Err(format!("Cannot '{name}' by a 'record'")),
+        None => match amount {
+            Literal::Field(..) => Literal::Field(Zero::zero()),
+            Literal::Group(..) => Literal::Group(Zero::zero()),
+            Literal::I8(..) => Literal::I8(Zero::zero()),
+            Literal::I16(..) => Literal::I16(Zero::zero()),
+            Literal::I32(..) => Literal::I32(Zero::zero()),
+            Literal::I64(..) => Literal::I64(Zero::zero()),
+            Literal::I128(..) => Literal::I128(Zero::zero()),
+            Literal::U8(..) => Literal::U8(Zero::zero()),
+            Literal::U16(..) => Literal::U16(Zero::zero()),
+            Literal::U32(..) => Literal::U32(Zero::zero()),
+            Literal::U64(..) => Literal::U64(Zero::zero()),
+            Literal::U128(..) => Literal::U128(Zero::zero()),
+            Literal::Scalar(..) => Literal::Scalar(Zero::zero()),
+            ref amount => return Err(format!("Cannot '{name}' by a '{}'", amount.to_type())),
+        },
+    };
+
+    // Integers fail on overflow, where snarkVM halts.
+    macro_rules! integer {
+        ($variant:ident, $a:expr, $b:expr) => {{
+            let (a, b) = ($a, $b);
+            match (increment, (*a).checked_add(*b), (*a).checked_sub(*b)) {
+                (true, Some(sum), _) => Ok(Literal::$variant($variant::new(sum))),
+                (true, None, _) => Err(format!("Integer addition failed on: {a} and {b}")),
+                (false, _, Some(difference)) => Ok(Literal::$variant($variant::new(difference))),
+                (false, _, None) => Err(format!("Integer subtraction failed on: {a} and {b}")),
+            }
+        }};
+    }
+    match (start, amount) {
+        (Literal::Field(a), Literal::Field(b)) => Ok(Literal::Field(if increment { a + b } else { a - b })),
+        (Literal::Group(a), Literal::Group(b)) => Ok(Literal::Group(if increment { a + b } else { a - b })),
+        (Literal::Scalar(a), Literal::Scalar(b)) => Ok(Literal::Scalar(if increment { a + b } else { a - b })),
+        (Literal::I8(a), Literal::I8(b)) => integer!(I8, a, b),
+        (Literal::I16(a), Literal::I16(b)) => integer!(I16, a, b),
+        (Literal::I32(a), Literal::I32(b)) => integer!(I32, a, b),
+        (Literal::I64(a), Literal::I64(b)) => integer!(I64, a, b),
+        (Literal::I128(a), Literal::I128(b)) => integer!(I128, a, b),
+        (Literal::U8(a), Literal::U8(b)) => integer!(U8, a, b),
+        (Literal::U16(a), Literal::U16(b)) => integer!(U16, a, b),
+        (Literal::U32(a), Literal::U32(b)) => integer!(U32, a, b),
+        (Literal::U64(a), Literal::U64(b)) => integer!(U64, a, b),
+        (Literal::U128(a), Literal::U128(b)) => integer!(U128, a, b),
+        (a, b) => Err(format!("Cannot '{name}' '{a}' by '{b}'")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testnet3;
+    #[cfg(not(feature = "async"))]
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_block_with_transactions,
+            sample_transaction,
+            sample_transition,
+            MockResponse,
+            MockServer,
+        },
+        BlockHeight,
+    };
+    use snarkvm_console::{account::PrivateKey, network::Testnet3};
+    #[cfg(not(feature = "async"))]
+    use snarkvm_console::types::Field;
+    #[cfg(not(feature = "async"))]
+    use snarkvm_synthesizer::{Input, Transition};
+    use snarkvm_utilities::TestRng;
+    #[cfg(not(feature = "async"))]
+    use snarkvm_utilities::Uniform;
+
+    use std::{panic, str::FromStr};
+
+    type N = Testnet3;
+
+    const TOKEN_PROGRAM: &str = r"program public_token.aleo;
+
+mapping account:
+    key left as address.public;
+    value right as u64.public;
+
+function transfer_public:
+    input r0 as address.public;
+    input r1 as u64.public;
+    finalize self.caller r0 r1;
+
+finalize transfer_public:
+    input r0 as address.public;
+    input r1 as address.public;
+    input r2 as u64.public;
+    decrement account[r0] by r2;
+    increment account[r1] by r2;
+
+function mint_public:
+    input r0 as address.public;
+    input r1 as u64.public;
+    finalize r0 r1;
+
+finalize mint_public:
+    input r0 as address.public;
+    input r1 as u64.public;
+    increment account[r0] by r1;
+
+function mint_twice:
+    input r0 as address.public;
+    input r1 as u64.public;
+    finalize r0 r1;
+
+finalize mint_twice:
+    input r0 as address.public;
+    input r1 as u64.public;
+    add r1 r1 into r2;
+    increment account[r0] by r2;
+";
+
+    fn sample_manager(base_url: &str) -> (ProgramManager<N>, Address<N>) {
+        let private_key = PrivateKey::new(&mut TestRng::default()).unwrap();
+        (ProgramManager::new(private_key, testnet3(base_url)), Address::try_from(&private_key).unwrap())
+    }
+
+    #[cfg(not(feature = "async"))]
+    fn balance(address: Address<N>) -> Plaintext<N> {
+        Plaintext::from(Literal::Address(address))
+    }
+
+    // Sample a call to a function of the token program, with the inputs its finalize scope ran on
+    #[cfg(not(feature = "async"))]
+    fn sample_call(function_name: &str, finalize_inputs: &[String], rng: &mut TestRng) -> Transition<N> {
+        let genesis = genesis_block();
+        let template = genesis.transitions().next().unwrap();
+        let finalize_inputs = finalize_inputs.iter().map(|input| Value::from_str(input).unwrap()).collect();
+        Transition::new(
+            ProgramID::from_str("public_token.aleo").unwrap(),
+            Identifier::from_str(function_name).unwrap(),
+            vec![Input::Constant(Field::rand(rng), None)],
+            vec![],
+            Some(finalize_inputs),
+            template.proof().clone(),
+            *template.tpk(),
+            *template.tcm(),
+            0,
+        )
+        .unwrap()
+    }
+
+    #[cfg(not(feature = "async"))]
+    #[test]
+    fn test_simulate_transfer_public() {
+        let program = Program::<N>::from_str(TOKEN_PROGRAM).unwrap();
+        let recipient = Address::try_from(PrivateKey::<N>::new(&mut TestRng::default()).unwrap()).unwrap();
+        let account = Identifier::from_str("account").unwrap();
+        let transfer = Identifier::from_str("transfer_public").unwrap();
+
+        // The balance of the sender is fetched into the snapshot, and the recipient has no balance yet.
+        let (manager, sender) = sample_manager("http://127.0.0.1:9");
+        let sender_path = format!("/testnet3/program/public_token.aleo/mapping/account/{sender}");
+        let server = MockServer::start(move |request| match request.path == sender_path {
+            true => Some(MockResponse::json("\"10u64\"")),
+            false => Some(MockResponse::json("null")),
+        });
+        let client = testnet3(server.base_url());
+        let snapshot =
+            client.snapshot_mapping(*program.id(), &account, &[balance(sender), balance(recipient)]).unwrap();
+        assert_eq!(snapshot.get(program.id(), &account, &balance(sender)), Some(&Value::from_str("10u64").unwrap()));
+        assert_eq!(snapshot.get(program.id(), &account, &balance(recipient)), None);
+
+        // A transfer within the balance moves it to the recipient.
+        let inputs = [Value::from_str(&recipient.to_string()).unwrap(), Value::from_str("4u64").unwrap()];
+        let FinalizeOutcome::Success(mutations) =
+            manager.simulate_finalize(&program, transfer, &inputs, &snapshot).unwrap()
+        else {
+            panic!("The transfer fails")
+        };
+        let values = mutations.iter().map(|mutation| (mutation.key.clone(), mutation.value.to_string()));
+        assert_eq!(values.collect::<Vec<_>>(), vec![
+            (balance(sender), "6u64".to_string()),
+            (balance(recipient), "4u64".to_string())
+        ]);
+        let mut next = snapshot.clone();
+        next.apply(&mutations);
+        assert_eq!(next.get(program.id(), &account, &balance(recipient)), Some(&Value::from_str("4u64").unwrap()));
+
+        // A transfer exceeding the balance fails on the decrement, with the error of the node.
+        let inputs = [Value::from_str(&recipient.to_string()).unwrap(), Value::from_str("11u64").unwrap()];
+        let FinalizeOutcome::Failure(failure) =
+            manager.simulate_finalize(&program, transfer, &inputs, &snapshot).unwrap()
+        else {
+            panic!("The transfer succeeds")
+        };
+        assert_eq!((failure.index(), failure.command()), (0, "decrement account[r0] by r2;"));
+        let node_error = panic::catch_unwind(|| U64::<N>::new(10) - U64::new(11)).unwrap_err();
+        assert_eq!(failure.reason(), node_error.downcast_ref::<String>().unwrap());
+        assert_eq!(failure.reason(), "Integer subtraction failed on: 10u64 and 11u64");
+    }
+
+    #[test]
+    fn test_simulate_unsupported() {
+        let program = Program::<N>::from_str(TOKEN_PROGRAM).unwrap();
+        let (manager, sender) = sample_manager("http://127.0.0.1:9");
+        let snapshot = MappingSnapshot::new();
+
+        // Nodes reject instructions in finalize, so the simulation predicts the failure.
+        let inputs = [Value::from_str(&sender.to_string()).unwrap(), Value::from_str("1u64").unwrap()];
+        let mint = Identifier::from_str("mint_twice").unwrap();
+        let FinalizeOutcome::Failure(failure) = manager.simulate_finalize(&program, mint, &inputs, &snapshot).unwrap()
+        else {
+            panic!("The mint succeeds")
+        };
+        assert_eq!((failure.index(), failure.command()), (0, "add r1 r1 into r2;"));
+        assert_eq!(failure.reason(), "Instructions in 'finalize' are not supported (yet).");
+
+        // Mistyped inputs and functions without finalize cannot be simulated.
+        let transfer = Identifier::from_str("transfer_public").unwrap();
+        let mistyped = [Value::from_str(&sender.to_string()).unwrap(), Value::from_str("1u32").unwrap()];
+        let error = manager.simulate_finalize(&program, transfer, &mistyped, &snapshot).unwrap_err();
+        assert_eq!(error.to_string(), "Finalize input 'r2' of function 'transfer_public' is not of type 'u64.public'");
+        let program = Program::<N>::from_str(
+            "program plain.aleo;\n\nfunction main:\n    input r0 as u64.public;\n    add r0 r0 into r1;\n",
+        )
+        .unwrap();
+        let error = manager.simulate_finalize(&program, Identifier::from_str("main").unwrap(), &[], &snapshot);
+        assert_eq!(error.unwrap_err().to_string(), "Function 'plain.aleo/main' has no finalize scope");
+    }
+
+    #[cfg(not(feature = "async"))]
+    #[test]
+    fn test_replay_mapping_value() {
+        let rng = &mut TestRng::default();
+        let program = Program::<N>::from_str(TOKEN_PROGRAM).unwrap();
+        let account = Identifier::from_str("account").unwrap();
+        let alice = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let bob = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+
+        // Alice mints 10 tokens and sends 4 to Bob, and after a block of other programs, Bob mints 5 and sends 1 back
+        // in one transaction.
+        let calls = [
+            vec![vec![sample_call("mint_public", &[alice.to_string(), "10u64".to_string()], rng)]],
+            vec![vec![sample_call("transfer_public", &[alice.to_string(), bob.to_string(), "4u64".to_string()], rng)]],
+            vec![vec![sample_transition(&[Field::rand(rng)], &[], rng)]],
+            vec![vec![
+                sample_call("mint_public", &[bob.to_string(), "5u64".to_string()], rng),
+                sample_call("transfer_public", &[bob.to_string(), alice.to_string(), "1u64".to_string()], rng),
+            ]],
+        ];
+        let mut blocks = vec![genesis_block()];
+        for transactions in calls {
+            let transactions = transactions.into_iter().map(sample_transaction).collect();
+            let (height, previous_hash) = (blocks.len() as u32, blocks.last().unwrap().hash());
+            blocks.push(sample_block_with_transactions(height, previous_hash, transactions, rng));
+        }
+
+        // The node serves the values after each block directly, from the states it kept.
+        let states = [(1, alice, "10u64"), (2, alice, "6u64"), (2, bob, "4u64"), (4, alice, "7u64"), (4, bob, "8u64")];
+        let source = program.to_string();
+        let server = MockServer::start(move |request| {
+            if request.path == "/testnet3/program/public_token.aleo" {
+                return Some(MockResponse::json(serde_json::json!(source)));
+            }
+            if let Some(path) = request.path.strip_prefix("/testnet3/program/public_token.aleo/mapping/account/") {
+                let (key, height) = path.split_once("?height=")?;
+                let height = height.parse::<u32>().ok()?;
+                let mut state = states.iter().filter(|(at, address, _)| *at <= height && address.to_string() == key);
+                let value = state.next_back().map(|(.., value)| value.to_string());
+                return Some(MockResponse::json(serde_json::json!(value)));
+            }
+            let (start, end) = request.path.strip_prefix("/testnet3/blocks?start=")?.split_once("&end=")?;
+            let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+            let blocks = blocks.get(start..end.min(blocks.len()))?.iter().map(ToString::to_string);
+            Some(MockResponse::json(format!("[{}]", blocks.collect::<Vec<_>>().join(","))))
+        });
+
+        // The values replayed from genesis match the values the node serves, in chunks of any size.
+        let direct = testnet3(server.base_url()).with_historical_mappings(true);
+        let replayed = testnet3(server.base_url()).with_max_block_request(2);
+        for height in 0..=4 {
+            for address in [alice, bob] {
+                let key = balance(address);
+                let value =
+                    replayed.get_mapping_value_at_height(*program.id(), &account, &key, BlockHeight(height)).unwrap();
+                assert_eq!(
+                    value,
+                    direct.get_mapping_value_at_height(*program.id(), &account, &key, BlockHeight(height)).unwrap()
+                );
+            }
+        }
+        let value =
+            replayed.get_mapping_value_at_height(*program.id(), &account, &balance(alice), BlockHeight(4)).unwrap();
+        assert_eq!(value, Some(Value::from_str("7u64").unwrap()));
+
+        // A pinned snapshot is replayed from its height, for the keys it covers only.
+        let mut snapshot = MappingSnapshot::new().with_height(2);
+        snapshot.insert(*program.id(), account, balance(alice), Value::from_str("100u64").unwrap());
+        let value = replayed.replay_mapping_value(
+            &[snapshot.clone()],
+            *program.id(),
+            &account,
+            &balance(alice),
+            BlockHeight(4),
+        );
+        assert_eq!(value.unwrap(), Some(Value::from_str("101u64").unwrap()));
+        let value = replayed.replay_mapping_value(&[snapshot], *program.id(), &account, &balance(bob), BlockHeight(4));
+        assert_eq!(value.unwrap(), Some(Value::from_str("8u64").unwrap()));
+
+        // Blocks the node does not have cannot be replayed.
+        let error =
+            replayed.get_mapping_value_at_height(*program.id(), &account, &balance(alice), BlockHeight(5)).unwrap_err();
+        assert_eq!(error.to_string(), "Blocks 5..=5 are unavailable to replay");
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+
+use snarkvm_console::{
+    account::ViewKey,
+    prelude::ToBits,
+    program::{Network, Plaintext},
+    types::{Field, U16},
+};
+use snarkvm_synthesizer::{Input, Transition};
+
+use anyhow::{anyhow, Result};
+
+/// An input of a transition, as supplied by the account that signed it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransitionInput<N: Network> {
+    /// A constant input, which is public
+    Constant(Plaintext<N>),
+    /// A public input
+    Public(Plaintext<N>),
+    /// A private input, decrypted with the transition view key
+    Private(Plaintext<N>),
+    /// A record spent by the transition, of which only the serial number and tag are on-chain
+    Record { serial_number: Field<N>, tag: Field<N> },
+    /// A record of another program, of which only the input commitment is on-chain
+    ExternalRecord(Field<N>),
+}
+
+impl<N: Network> TransitionInput<N> {
+    /// Returns the plaintext of the input, unless it is a record.
+    pub fn plaintext(&self) -> Option<&Plaintext<N>> {
+        match self {
+            Self::Constant(plaintext) | Self::Public(plaintext) | Self::Private(plaintext) => Some(plaintext),
+            Self::Record { .. } | Self::ExternalRecord(_) => None,
+        }
+    }
+}
+
+/// The inputs of a transition, as recovered by [`ProgramManager::decrypt_own_transition_inputs`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OwnTransitionInputs<N: Network> {
+    /// The transition was signed by the account, and these are its inputs in order
+    Signed(Vec<TransitionInput<N>>),
+    /// The transition was signed by another account, so its private inputs cannot be decrypted
+    NotSender,
+}
+
+impl<N: Network> OwnTransitionInputs<N> {
+    /// Returns the inputs of the transition, or `None` if it was not signed by the account.
+    pub fn inputs(&self) -> Option<&[TransitionInput<N>]> {
+        match self {
+            Self::Signed(inputs) => Some(inputs),
+            Self::NotSender => None,
+        }
+    }
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Recover the inputs of a transition signed by the account of the view key, decrypting its private inputs
+    ///
+    /// The transition view key is derived from the transition public key and the view key, and matches the
+    /// transition commitment only if the account signed the transition. Transitions signed by other accounts,
+    /// such as the transitions that sent records to the account, return [`OwnTransitionInputs::NotSender`].
+    pub fn decrypt_own_transition_inputs(
+        &self,
+        transition: &Transition<N>,
+        view_key: &ViewKey<N>,
+    ) -> Result<OwnTransitionInputs<N>> {
+        // The signer computes `tvk` as `r * address`, which is `view_key * tpk` for `tpk = r * G`.
+        let tvk = (*transition.tpk() * **view_key).to_x_coordinate();
+        if N::hash_psd2(&[tvk])? != *transition.tcm() {
+            return Ok(OwnTransitionInputs::NotSender);
+        }
+
+        let program_id = transition.program_id();
+        let function_id = N::hash_bhp1024(
+            &(U16::<N>::new(N::ID), program_id.name(), program_id.network(), transition.function_name()).to_bits_le(),
+        )?;
+        let inputs = transition
+            .inputs()
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let missing = || anyhow!("Input {index} of transition '{}' has no value", transition.id());
+                Ok(match input {
+                    Input::Constant(_, plaintext) => TransitionInput::Constant(plaintext.clone().ok_or_else(missing)?),
+                    Input::Public(_, plaintext) => TransitionInput::Public(plaintext.clone().ok_or_else(missing)?),
+                    Input::Private(_, ciphertext) => {
+                        let ciphertext = ciphertext.as_ref().ok_or_else(missing)?;
+                        // The input view key is `Hash(function ID || tvk || index)`.
+                        let index = Field::from_u16(u16::try_from(index)?);
+                        let input_view_key = N::hash_psd4(&[function_id, tvk, index])?;
+                        TransitionInput::Private(ciphertext.decrypt_symmetric(input_view_key)?)
+                    }
+                    Input::Record(serial_number, tag) => {
+                        TransitionInput::Record { serial_number: *serial_number, tag: *tag }
+                    }
+                    Input::ExternalRecord(commitment) => TransitionInput::ExternalRecord(*commitment),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(OwnTransitionInputs::Signed(inputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{genesis_block, sample_record, CurrentNetwork},
+        testnet3,
+    };
+
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        prelude::TestRng,
+        program::{Identifier, InputID, ProgramID, Request, Value, ValueType},
+    };
+    use std::str::FromStr;
+
+    type N = CurrentNetwork;
+
+    // Sign a `credits.aleo/transfer` of `amount` gates to the recipient, and wrap the request in a transition
+    fn signed_transfer(
+        private_key: &PrivateKey<N>,
+        recipient: Address<N>,
+        amount: u64,
+        rng: &mut TestRng,
+    ) -> Transition<N> {
+        let (record, _) = sample_record(Address::try_from(private_key).unwrap(), 100, rng);
+        let (program_id, function_name) =
+            (ProgramID::from_str("credits.aleo").unwrap(), Identifier::from_str("transfer").unwrap());
+        let inputs = [
+            Value::Record(record),
+            Value::from_str(&recipient.to_string()).unwrap(),
+            Value::from_str(&format!("{amount}u64")).unwrap(),
+        ];
+        let input_types = ["credits.record", "address.private", "u64.private"].map(|t| ValueType::from_str(t).unwrap());
+        let request = Request::sign(private_key, program_id, function_name, inputs.into_iter(), &input_types, rng);
+        let request = request.unwrap();
+
+        // The private inputs are encrypted as a transition built by the VM encrypts them.
+        let function_id = N::hash_bhp1024(
+            &(U16::<N>::new(N::ID), program_id.name(), program_id.network(), function_name).to_bits_le(),
+        )
+        .unwrap();
+        let inputs = request.input_ids().iter().zip(request.inputs()).enumerate().map(|(index, (input_id, input))| {
+            match (input_id, input) {
+                (InputID::Private(hash), Value::Plaintext(plaintext)) => {
+                    let input_view_key =
+                        N::hash_psd4(&[function_id, *request.tvk(), Field::from_u16(index as u16)]).unwrap();
+                    Input::Private(*hash, Some(plaintext.encrypt_symmetric(input_view_key).unwrap()))
+                }
+                (InputID::Record(_, _, serial_number, tag), _) => Input::Record(*serial_number, *tag),
+                _ => unreachable!(),
+            }
+        });
+        let proof = genesis_block().transitions().next().unwrap().proof().clone();
+        let inputs = inputs.collect();
+        Transition::new(program_id, function_name, inputs, vec![], None, proof, request.to_tpk(), *request.tcm(), 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_decrypt_own_transition_inputs() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let recipient_key = PrivateKey::<N>::new(rng).unwrap();
+        let recipient = Address::try_from(recipient_key).unwrap();
+        let transition = signed_transfer(&private_key, recipient, 42, rng);
+
+        let manager = ProgramManager::new(private_key, testnet3("http://127.0.0.1:9"));
+        let view_key = ViewKey::try_from(private_key).unwrap();
+        let inputs = manager.decrypt_own_transition_inputs(&transition, &view_key).unwrap();
+        let inputs = inputs.inputs().unwrap();
+        assert_eq!(inputs.len(), 3);
+        assert!(matches!(inputs[0], TransitionInput::Record { .. }));
+        assert_eq!(inputs[1], TransitionInput::Private(Plaintext::from_str(&recipient.to_string()).unwrap()));
+        assert_eq!(inputs[2], TransitionInput::Private(Plaintext::from_str("42u64").unwrap()));
+
+        // The view key of the recipient, or of anyone else, does not recover the inputs
+        let watch_only = ProgramManager::watch_only(testnet3("http://127.0.0.1:9"));
+        for private_key in [recipient_key, PrivateKey::<N>::new(rng).unwrap()] {
+            let view_key = ViewKey::try_from(private_key).unwrap();
+            let inputs = watch_only.decrypt_own_transition_inputs(&transition, &view_key).unwrap();
+            assert_eq!(inputs, OwnTransitionInputs::NotSender);
+        }
+    }
+}
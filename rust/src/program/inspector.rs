@@ -0,0 +1,615 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm_console::{
+    prelude::{ToBytes, Zero},
+    program::{
+        Address,
+        Boolean,
+        EntryType,
+        Field,
+        Group,
+        Identifier,
+        Literal,
+        LiteralType,
+        Network,
+        Plaintext,
+        PlaintextType,
+        Scalar,
+        StringType,
+        Visibility,
+        I128,
+        I16,
+        I32,
+        I64,
+        I8,
+        U128,
+        U16,
+        U32,
+        U64,
+        U8,
+    },
+};
+use snarkvm_synthesizer::Program;
+
+use anyhow::{anyhow, bail, Result};
+use indexmap::IndexMap;
+use std::{convert::TryInto, fmt, str::FromStr};
+
+// The keywords that start the definitions of a program, in the order of the sections of the pretty-printed
+// program, which keeps every definition after the definitions it refers to
+const SECTIONS: [(&str, &str); 5] = [
+    ("struct", "Structs"),
+    ("record", "Records"),
+    ("mapping", "Mappings"),
+    ("closure", "Closures"),
+    ("function", "Functions"),
+];
+
+/// Pretty-prints programs and reports the layout of their structs and records, for debugging deployed programs
+pub struct ProgramInspector<N: Network> {
+    program: Program<N>,
+}
+
+impl<N: Network> ProgramInspector<N> {
+    /// Create an inspector of the given program
+    pub fn new(program: Program<N>) -> Self {
+        Self { program }
+    }
+
+    /// Returns the inspected program
+    pub fn program(&self) -> &Program<N> {
+        &self.program
+    }
+
+    /// Returns the program as Aleo instructions, with its definitions grouped under a comment header per kind,
+    /// each struct and record preceded by a comment with its size, and each function and closure by a comment
+    /// with its signature.
+    ///
+    /// Definitions keep their relative order within their section, and every line of a definition but its
+    /// header is indented by four spaces, so the output is deterministic and parses back to the same program.
+    pub fn pretty_print(&self) -> Result<String> {
+        let mut output = String::new();
+        for import in self.program.imports().values() {
+            output.push_str(&format!("{import}\n"));
+        }
+        if !self.program.imports().is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!("program {};\n", self.program.id()));
+
+        let definitions = self.definitions();
+        for (keyword, header) in SECTIONS {
+            let names = definitions.iter().filter(|(kind, _)| *kind == keyword).map(|(_, name)| name);
+            for (index, name) in names.enumerate() {
+                if index == 0 {
+                    output.push_str(&format!("\n// {header}\n"));
+                }
+                let (comment, definition) = match keyword {
+                    "struct" => (self.layout_of(*name)?.to_string(), self.program.get_struct(name)?.to_string()),
+                    "record" => (self.layout_of(*name)?.to_string(), self.program.get_record(name)?.to_string()),
+                    "mapping" => {
+                        let mapping = self.program.get_mapping(name)?;
+                        let (key, value) = (mapping.key().finalize_type(), mapping.value().finalize_type());
+                        (format!("mapping {name}: {key} => {value}"), mapping.to_string())
+                    }
+                    "closure" => {
+                        let closure = self.program.get_closure(name)?;
+                        let signature = signature(closure.inputs().len(), closure.outputs().len(), false);
+                        (format!("closure {name}: {signature}"), closure.to_string())
+                    }
+                    _ => {
+                        let function = self.program.get_function(name)?;
+                        let (inputs, outputs) = (function.inputs().len(), function.outputs().len());
+                        let signature = signature(inputs, outputs, function.finalize().is_some());
+                        (format!("function {name}: {signature}"), function.to_string())
+                    }
+                };
+                output.push_str(&format!("\n// {comment}\n{}", indent(&definition)));
+            }
+        }
+        Ok(output)
+    }
+
+    /// Returns the layout of the struct or record with the given name.
+    ///
+    /// Sizes are those of the plaintext encoding of each field, in bytes and in field elements. Strings are
+    /// counted as empty, so the sizes of types holding strings are lower bounds.
+    pub fn layout_of(&self, type_name: impl TryInto<Identifier<N>>) -> Result<TypeLayout<N>> {
+        let name = type_name.try_into().map_err(|_| anyhow!("Invalid type name"))?;
+        if self.program.contains_struct(&name) {
+            let struct_ = self.program.get_struct(&name)?;
+            let fields = struct_
+                .members()
+                .iter()
+                .map(|(member, plaintext_type)| self.field_layout(*member, *plaintext_type, None))
+                .collect::<Result<Vec<_>>>()?;
+            let plaintext = self.sample_plaintext(&PlaintextType::Struct(name))?;
+            let (size_in_bytes, size_in_fields) = (plaintext.to_bytes_le()?.len(), plaintext.size_in_fields()?);
+            return Ok(TypeLayout { name, kind: TypeKind::Struct, fields, size_in_bytes, size_in_fields });
+        }
+        if self.program.contains_record(&name) {
+            let record_type = self.program.get_record(&name)?;
+            let mut fields = vec![
+                self.field_layout(
+                    Identifier::from_str("owner")?,
+                    PlaintextType::Literal(LiteralType::Address),
+                    Some(FieldVisibility::public_if(record_type.owner().is_public())),
+                )?,
+                self.field_layout(
+                    Identifier::from_str("gates")?,
+                    PlaintextType::Literal(LiteralType::U64),
+                    Some(FieldVisibility::public_if(record_type.gates().is_public())),
+                )?,
+            ];
+            for (entry, entry_type) in record_type.entries() {
+                let (plaintext_type, visibility) = match entry_type {
+                    EntryType::Constant(plaintext_type) => (plaintext_type, FieldVisibility::Constant),
+                    EntryType::Public(plaintext_type) => (plaintext_type, FieldVisibility::Public),
+                    EntryType::Private(plaintext_type) => (plaintext_type, FieldVisibility::Private),
+                };
+                fields.push(self.field_layout(*entry, *plaintext_type, Some(visibility))?);
+            }
+            // The entries of a record are encoded one by one, so its size is the sum of theirs.
+            let size_in_bytes = fields.iter().map(FieldLayout::size_in_bytes).sum();
+            let size_in_fields = fields.iter().map(|field| field.size_in_fields as usize).sum::<usize>();
+            let size_in_fields = u16::try_from(size_in_fields)?;
+            return Ok(TypeLayout { name, kind: TypeKind::Record, fields, size_in_bytes, size_in_fields });
+        }
+        bail!("Program '{}' does not define a struct or record named '{name}'", self.program.id())
+    }
+
+    // Returns the kind and name of each definition of the program, in the order they are declared.
+    //
+    // Programs do not expose the order of their definitions, but print them in that order, with the header of
+    // each definition, e.g. `struct point:`, unindented.
+    fn definitions(&self) -> Vec<(&'static str, Identifier<N>)> {
+        let program = self.program.to_string();
+        let headers = program.lines().filter_map(|line| {
+            let (keyword, name) = line.strip_suffix(':')?.split_once(' ')?;
+            let (keyword, _) = SECTIONS.iter().find(|(section, _)| *section == keyword)?;
+            Some((*keyword, Identifier::from_str(name).ok()?))
+        });
+        headers.collect()
+    }
+
+    // Returns the layout of a field with the given name, type, and visibility
+    fn field_layout(
+        &self,
+        name: Identifier<N>,
+        plaintext_type: PlaintextType<N>,
+        visibility: Option<FieldVisibility>,
+    ) -> Result<FieldLayout<N>> {
+        let plaintext = self.sample_plaintext(&plaintext_type)?;
+        let (size_in_bytes, size_in_fields) = (plaintext.to_bytes_le()?.len(), plaintext.size_in_fields()?);
+        Ok(FieldLayout { name, plaintext_type, visibility, size_in_bytes, size_in_fields })
+    }
+
+    // Returns a plaintext of the given type, whose literals are zero and whose strings are empty
+    fn sample_plaintext(&self, plaintext_type: &PlaintextType<N>) -> Result<Plaintext<N>> {
+        match plaintext_type {
+            PlaintextType::Literal(literal_type) => Ok(Plaintext::from(zero_literal(*literal_type))),
+            PlaintextType::Struct(struct_name) => {
+                let struct_ = self.program.get_struct(struct_name)?;
+                let members = struct_
+                    .members()
+                    .iter()
+                    .map(|(member, member_type)| Ok((*member, self.sample_plaintext(member_type)?)))
+                    .collect::<Result<IndexMap<_, _>>>()?;
+                Ok(Plaintext::Struct(members, Default::default()))
+            }
+        }
+    }
+}
+
+/// Whether a type is a struct or a record
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TypeKind {
+    Struct,
+    Record,
+}
+
+/// The visibility of a field of a record
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FieldVisibility {
+    Constant,
+    Public,
+    Private,
+}
+
+impl FieldVisibility {
+    // Returns the visibility of the owner or gates of a record, which are public or private
+    fn public_if(is_public: bool) -> Self {
+        match is_public {
+            true => Self::Public,
+            false => Self::Private,
+        }
+    }
+}
+
+impl fmt::Display for FieldVisibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Constant => write!(f, "constant"),
+            Self::Public => write!(f, "public"),
+            Self::Private => write!(f, "private"),
+        }
+    }
+}
+
+/// The layout of a struct or record, as returned by [`ProgramInspector::layout_of`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeLayout<N: Network> {
+    name: Identifier<N>,
+    kind: TypeKind,
+    fields: Vec<FieldLayout<N>>,
+    size_in_bytes: usize,
+    size_in_fields: u16,
+}
+
+impl<N: Network> TypeLayout<N> {
+    /// Returns the name of the type.
+    pub fn name(&self) -> &Identifier<N> {
+        &self.name
+    }
+
+    /// Returns whether the type is a struct or a record.
+    pub fn kind(&self) -> TypeKind {
+        self.kind
+    }
+
+    /// Returns the layouts of the fields of the type, in order. The fields of a record start with its owner
+    /// and gates.
+    pub fn fields(&self) -> &[FieldLayout<N>] {
+        &self.fields
+    }
+
+    /// Returns the size of the type in bytes. A struct is encoded as a whole, while the fields of a record are
+    /// encoded one by one, so the size of a record is the sum of the sizes of its fields.
+    pub fn size_in_bytes(&self) -> usize {
+        self.size_in_bytes
+    }
+
+    /// Returns the size of the type in field elements, which is the size of a record once encrypted, without
+    /// its nonce.
+    pub fn size_in_fields(&self) -> u16 {
+        self.size_in_fields
+    }
+}
+
+impl<N: Network> fmt::Display for TypeLayout<N> {
+    /// Prints the layout on one line, e.g. `record credits: 2 fields, 75 bytes, 2 field elements`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            TypeKind::Struct => "struct",
+            TypeKind::Record => "record",
+        };
+        write!(
+            f,
+            "{kind} {}: {}, {} bytes, {}",
+            self.name,
+            plural(self.fields.len(), "field"),
+            self.size_in_bytes,
+            plural(self.size_in_fields as usize, "field element")
+        )
+    }
+}
+
+/// The layout of a field of a struct or record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldLayout<N: Network> {
+    name: Identifier<N>,
+    plaintext_type: PlaintextType<N>,
+    visibility: Option<FieldVisibility>,
+    size_in_bytes: usize,
+    size_in_fields: u16,
+}
+
+impl<N: Network> FieldLayout<N> {
+    /// Returns the name of the field.
+    pub fn name(&self) -> &Identifier<N> {
+        &self.name
+    }
+
+    /// Returns the type of the field.
+    pub fn plaintext_type(&self) -> &PlaintextType<N> {
+        &self.plaintext_type
+    }
+
+    /// Returns the visibility of the field, if it is the field of a record.
+    pub fn visibility(&self) -> Option<FieldVisibility> {
+        self.visibility
+    }
+
+    /// Returns the size of the field in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.size_in_bytes
+    }
+
+    /// Returns the size of the field in field elements.
+    pub fn size_in_fields(&self) -> u16 {
+        self.size_in_fields
+    }
+}
+
+// Returns the zero literal of the type, or the empty string
+fn zero_literal<N: Network>(literal_type: LiteralType) -> Literal<N> {
+    match literal_type {
+        LiteralType::Address => Literal::Address(Address::new(Group::zero())),
+        LiteralType::Boolean => Literal::Boolean(Boolean::new(false)),
+        LiteralType::Field => Literal::Field(Field::zero()),
+        LiteralType::Group => Literal::Group(Group::zero()),
+        LiteralType::I8 => Literal::I8(I8::zero()),
+        LiteralType::I16 => Literal::I16(I16::zero()),
+        LiteralType::I32 => Literal::I32(I32::zero()),
+        LiteralType::I64 => Literal::I64(I64::zero()),
+        LiteralType::I128 => Literal::I128(I128::zero()),
+        LiteralType::U8 => Literal::U8(U8::zero()),
+        LiteralType::U16 => Literal::U16(U16::zero()),
+        LiteralType::U32 => Literal::U32(U32::zero()),
+        LiteralType::U64 => Literal::U64(U64::zero()),
+        LiteralType::U128 => Literal::U128(U128::zero()),
+        LiteralType::Scalar => Literal::Scalar(Scalar::zero()),
+        LiteralType::String => Literal::String(StringType::new("")),
+    }
+}
+
+// Re-indent a printed definition, whose header is unindented, and whose other lines are indented by four spaces.
+// The finalize scope of a function is unindented too, after a blank line.
+fn indent(definition: &str) -> String {
+    let lines = definition.lines().map(str::trim).filter(|line| !line.is_empty()).enumerate();
+    let lines = lines.map(|(index, line)| match (index, line.starts_with("finalize ") && line.ends_with(':')) {
+        (0, _) => format!("{line}\n"),
+        (_, true) => format!("\n{line}\n"),
+        (_, false) => format!("    {line}\n"),
+    });
+    lines.collect()
+}
+
+// Returns the signature of a function or closure, e.g. `2 inputs, 1 output, finalize`
+fn signature(inputs: usize, outputs: usize, finalize: bool) -> String {
+    let signature = format!("{}, {}", plural(inputs, "input"), plural(outputs, "output"));
+    match finalize {
+        true => format!("{signature}, finalize"),
+        false => signature,
+    }
+}
+
+// Returns the count with the noun, pluralized unless the count is 1
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {noun}"),
+        _ => format!("{count} {noun}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::CurrentNetwork;
+
+    type N = CurrentNetwork;
+
+    const LAYOUT_PROGRAM: &str = r"
+program layout_test.aleo;
+
+struct point:
+    x as u64;
+    y as u64;
+
+record ticket:
+    owner as address.private;
+    gates as u64.private;
+    seat as point.private;
+    price as u64.public;
+    serial as field.constant;
+
+struct segment:
+    start as point;
+    end as point;
+
+mapping segments:
+    key left as field.public;
+    value right as segment.public;
+
+closure width:
+    input r0 as segment;
+    sub r0.end.x r0.start.x into r1;
+    output r1 as u64;
+
+function measure:
+    input r0 as segment.private;
+    call width r0 into r1;
+    output r1 as u64.public;
+    finalize r1;
+
+finalize measure:
+    input r0 as u64.public;
+    add r0 1u64 into r1;
+";
+
+    const LAYOUT_GOLDEN: &str = "program layout_test.aleo;
+
+// Structs
+
+// struct point: 2 fields, 32 bytes, 2 field elements
+struct point:
+    x as u64;
+    y as u64;
+
+// struct segment: 2 fields, 80 bytes, 3 field elements
+struct segment:
+    start as point;
+    end as point;
+
+// Records
+
+// record ticket: 5 fields, 124 bytes, 8 field elements
+record ticket:
+    owner as address.private;
+    gates as u64.private;
+    seat as point.private;
+    price as u64.public;
+    serial as field.constant;
+
+// Mappings
+
+// mapping segments: field.public => segment.public
+mapping segments:
+    key left as field.public;
+    value right as segment.public;
+
+// Closures
+
+// closure width: 1 input, 1 output
+closure width:
+    input r0 as segment;
+    sub r0.end.x r0.start.x into r1;
+    output r1 as u64;
+
+// Functions
+
+// function measure: 1 input, 1 output, finalize
+function measure:
+    input r0 as segment.private;
+    call width r0 into r1;
+    output r1 as u64.public;
+    finalize r1;
+
+finalize measure:
+    input r0 as u64.public;
+    add r0 1u64 into r1;
+";
+
+    const CREDITS_GOLDEN: &str = "program credits.aleo;
+
+// Records
+
+// record credits: 2 fields, 46 bytes, 3 field elements
+record credits:
+    owner as address.private;
+    gates as u64.private;
+
+// Functions
+
+// function mint: 2 inputs, 1 output
+function mint:
+    input r0 as address.private;
+    input r1 as u64.private;
+    cast r0 r1 into r2 as credits.record;
+    output r2 as credits.record;
+
+// function transfer: 3 inputs, 2 outputs
+function transfer:
+    input r0 as credits.record;
+    input r1 as address.private;
+    input r2 as u64.private;
+    sub r0.gates r2 into r3;
+    cast r1 r2 into r4 as credits.record;
+    cast r0.owner r3 into r5 as credits.record;
+    output r4 as credits.record;
+    output r5 as credits.record;
+
+// function join: 2 inputs, 1 output
+function join:
+    input r0 as credits.record;
+    input r1 as credits.record;
+    add r0.gates r1.gates into r2;
+    cast r0.owner r2 into r3 as credits.record;
+    output r3 as credits.record;
+
+// function split: 2 inputs, 2 outputs
+function split:
+    input r0 as credits.record;
+    input r1 as u64.private;
+    sub r0.gates r1 into r2;
+    cast r0.owner r1 into r3 as credits.record;
+    cast r0.owner r2 into r4 as credits.record;
+    output r3 as credits.record;
+    output r4 as credits.record;
+
+// function fee: 2 inputs, 1 output
+function fee:
+    input r0 as credits.record;
+    input r1 as u64.private;
+    sub r0.gates r1 into r2;
+    cast r0.owner r2 into r3 as credits.record;
+    output r3 as credits.record;
+";
+
+    #[test]
+    fn test_pretty_print_credits() {
+        let program = Program::<N>::credits().unwrap();
+        let printed = ProgramInspector::new(program.clone()).pretty_print().unwrap();
+        assert_eq!(printed, CREDITS_GOLDEN);
+        assert_eq!(Program::<N>::from_str(&printed).unwrap(), program);
+    }
+
+    #[test]
+    fn test_pretty_print_nested_structs() {
+        // The struct declared after the record is printed with the other structs, and the finalize scope is
+        // indented like the function.
+        let program = Program::<N>::from_str(LAYOUT_PROGRAM).unwrap();
+        let printed = ProgramInspector::new(program.clone()).pretty_print().unwrap();
+        assert_eq!(printed, LAYOUT_GOLDEN);
+        assert_eq!(Program::<N>::from_str(&printed).unwrap(), program);
+    }
+
+    #[test]
+    fn test_layout_of() {
+        let inspector = ProgramInspector::new(Program::<N>::from_str(LAYOUT_PROGRAM).unwrap());
+        let sizes = |layout: &TypeLayout<N>| {
+            let fields = layout.fields().iter().map(|field| {
+                let visibility = field.visibility().map(|visibility| visibility.to_string());
+                (field.name().to_string(), field.plaintext_type().to_string(), visibility, field.size_in_bytes())
+            });
+            (fields.collect::<Vec<_>>(), layout.size_in_bytes(), layout.size_in_fields())
+        };
+
+        let point = inspector.layout_of("point").unwrap();
+        assert_eq!(point.kind(), TypeKind::Struct);
+        let u64_field = |name: &str| (name.to_string(), "u64".to_string(), None, 11);
+        assert_eq!(sizes(&point), (vec![u64_field("x"), u64_field("y")], 32, 2));
+
+        // A nested struct is encoded with the members of its members.
+        let segment = inspector.layout_of("segment").unwrap();
+        let point_field = |name: &str| (name.to_string(), "point".to_string(), None, 32);
+        assert_eq!(sizes(&segment), (vec![point_field("start"), point_field("end")], 80, 3));
+        assert!(segment.fields().iter().all(|field| field.size_in_fields() == 2));
+
+        // The fields of a record are its owner and gates, then its entries, which are encoded one by one.
+        let ticket = inspector.layout_of("ticket").unwrap();
+        assert_eq!(ticket.kind(), TypeKind::Record);
+        let field = |name: &str, type_: &str, visibility: &str, size| {
+            (name.to_string(), type_.to_string(), Some(visibility.to_string()), size)
+        };
+        let fields = vec![
+            field("owner", "address", "private", 35),
+            field("gates", "u64", "private", 11),
+            field("seat", "point", "private", 32),
+            field("price", "u64", "public", 11),
+            field("serial", "field", "constant", 35),
+        ];
+        assert_eq!(sizes(&ticket), (fields, 124, 8));
+        assert_eq!(ticket.to_string(), "record ticket: 5 fields, 124 bytes, 8 field elements");
+
+        let credits = ProgramInspector::new(Program::<N>::credits().unwrap()).layout_of("credits").unwrap();
+        assert_eq!((credits.size_in_bytes(), credits.size_in_fields()), (46, 3));
+
+        let error = inspector.layout_of("segments").unwrap_err();
+        assert_eq!(error.to_string(), "Program 'layout_test.aleo' does not define a struct or record named 'segments'");
+    }
+}
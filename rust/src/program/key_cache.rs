@@ -0,0 +1,85 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::{ConsensusMemory, ConsensusStore, Program, VM};
+
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use std::sync::Arc;
+
+// The VM a program manager builds transactions with, kept across builds so that the circuit keys synthesized for a
+// function are synthesized once. The VM locks its process itself, so builds on other threads share it without a
+// lock of their own: proofs hold the process for reading, and only adding a program holds it for writing.
+#[derive(Clone)]
+pub(crate) struct KeyCache<N: Network> {
+    vm: Arc<OnceCell<VM<N, ConsensusMemory<N>>>>,
+}
+
+impl<N: Network> Default for KeyCache<N> {
+    fn default() -> Self {
+        Self { vm: Arc::new(OnceCell::new()) }
+    }
+}
+
+impl<N: Network> KeyCache<N> {
+    // Returns the cached VM holding the given programs, adding those it does not hold yet. A program whose ID the VM
+    // holds with other source, e.g. a local edit of a deployed program, is built with a VM of its own instead.
+    pub(crate) fn vm_with(&self, programs: &[&Program<N>]) -> Result<VM<N, ConsensusMemory<N>>> {
+        let vm = self.vm.get_or_try_init(new_vm)?.clone();
+        let process = vm.process();
+        // The process is checked for reading first, as writing waits for the proofs in progress to finish.
+        let missing = {
+            let process = process.read();
+            let mut missing = vec![];
+            for program in programs {
+                match process.get_program(program.id()) {
+                    Ok(cached) if cached == *program => (),
+                    Ok(_) => return fresh_vm(programs),
+                    Err(_) => missing.push(*program),
+                }
+            }
+            missing
+        };
+        if !missing.is_empty() {
+            let mut process = process.write();
+            for program in missing {
+                // Another build may have added the program since it was checked.
+                if !process.contains_program(program.id()) {
+                    process.add_program(program)?;
+                }
+            }
+        }
+        Ok(vm)
+    }
+}
+
+// Initialize a VM backed by in-memory storage
+fn new_vm<N: Network>() -> Result<VM<N, ConsensusMemory<N>>> {
+    let store = ConsensusStore::<N, ConsensusMemory<N>>::open(None)?;
+    VM::from(store)
+}
+
+// Initialize a VM holding the given programs, apart from the programs built into the VM, without synthesized keys
+pub(crate) fn fresh_vm<N: Network>(programs: &[&Program<N>]) -> Result<VM<N, ConsensusMemory<N>>> {
+    let vm = new_vm()?;
+    for program in programs {
+        if !vm.contains_program(program.id()) {
+            vm.process().write().add_program(program)?;
+        }
+    }
+    Ok(vm)
+}
@@ -0,0 +1,379 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{PendingTransaction, ProgramManager};
+
+use anyhow::{ensure, Result};
+use snarkvm_console::{
+    account::Address,
+    prelude::{FromBytes, ToBytes},
+    program::{Entry, Identifier, Literal, LiteralType, Network, Plaintext, PlaintextType, Record, Value, ValueType},
+    types::{Field, U64},
+};
+use snarkvm_synthesizer::{Program, Transaction};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The source of the memo program used by [`ProgramManager::transfer_with_memo`] unless another one is set
+///
+/// Its `transfer` function calls `credits.aleo/transfer`, and gives the recipient and the sender a `receipt`
+/// record of no gates holding the memo, which only they can decrypt. The memo holds up to 61 bytes.
+pub const MEMO_PROGRAM: &str = "import credits.aleo;
+
+program credits_memo.aleo;
+
+struct memo_data:
+    part0 as field;
+    part1 as field;
+
+record receipt:
+    owner as address.private;
+    gates as u64.private;
+    memo as memo_data.private;
+
+function transfer:
+    input r0 as credits.aleo/credits.record;
+    input r1 as address.private;
+    input r2 as u64.private;
+    input r3 as memo_data.private;
+    call credits.aleo/transfer r0 r1 r2 into r4 r5;
+    cast r1 0u64 r3 into r6 as receipt.record;
+    cast self.caller 0u64 r3 into r7 as receipt.record;
+    output r4 as credits.aleo/credits.record;
+    output r5 as credits.aleo/credits.record;
+    output r6 as receipt.record;
+    output r7 as receipt.record;
+";
+
+/// The name of the record entry holding a memo
+const MEMO_ENTRY: &str = "memo";
+/// The name of the function of a memo program transferring gates with a memo
+const MEMO_FUNCTION: &str = "transfer";
+/// The number of memo bytes packed in each field, which keeps every packed field below the modulus
+const BYTES_PER_FIELD: usize = 31;
+
+/// An error returned when a memo cannot be attached to a transfer, or read from a record
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum MemoError {
+    /// The program has no `transfer` function taking a memo
+    #[error("Program '{program_id}' has no memo capacity")]
+    NoCapacity { program_id: String },
+    /// The memo is longer than the program can hold
+    #[error("The memo is {length} bytes long, but program '{program_id}' holds at most {capacity} bytes")]
+    TooLong { program_id: String, length: usize, capacity: usize },
+    /// The memo entry of the record does not follow the memo convention
+    #[error("The memo of the record is malformed")]
+    Malformed,
+}
+
+/// Read the memo of a decrypted record, or `None` if the record has no `memo` entry.
+///
+/// By convention a memo is held by a `memo` entry that is a `field`, or a struct whose members are all fields.
+/// The first byte of the first field is the length of the memo, followed by the memo bytes, 31 bytes per field in
+/// little-endian order, and zero padding.
+pub fn extract_memo<N: Network>(record: &Record<N, Plaintext<N>>) -> Result<Option<Vec<u8>>, MemoError> {
+    let name = Identifier::from_str(MEMO_ENTRY).map_err(|_| MemoError::Malformed)?;
+    let plaintext = match record.data().get(&name) {
+        Some(Entry::Constant(plaintext) | Entry::Public(plaintext) | Entry::Private(plaintext)) => plaintext,
+        None => return Ok(None),
+    };
+    let fields = match plaintext {
+        Plaintext::Literal(Literal::Field(field), _) => vec![*field],
+        Plaintext::Struct(members, _) => members
+            .values()
+            .map(|member| match member {
+                Plaintext::Literal(Literal::Field(field), _) => Ok(*field),
+                _ => Err(MemoError::Malformed),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err(MemoError::Malformed),
+    };
+    decode_memo(&fields).map(Some)
+}
+
+// Pack the memo behind its length into the given number of fields
+fn encode_memo<N: Network>(memo: &[u8], num_fields: usize) -> Result<Vec<Field<N>>> {
+    ensure!(memo.len() < num_fields * BYTES_PER_FIELD && memo.len() <= u8::MAX as usize, "The memo is too long");
+    let mut bytes = vec![0u8; num_fields * BYTES_PER_FIELD];
+    bytes[0] = memo.len() as u8;
+    bytes[1..=memo.len()].copy_from_slice(memo);
+    bytes
+        .chunks(BYTES_PER_FIELD)
+        .map(|chunk| {
+            let mut field_bytes = [0u8; 32];
+            field_bytes[..BYTES_PER_FIELD].copy_from_slice(chunk);
+            Field::from_bytes_le(&field_bytes)
+        })
+        .collect()
+}
+
+// Unpack a memo, rejecting fields holding more than 31 bytes, lengths past the fields, and non-zero padding
+fn decode_memo<N: Network>(fields: &[Field<N>]) -> Result<Vec<u8>, MemoError> {
+    let mut bytes = Vec::with_capacity(fields.len() * BYTES_PER_FIELD);
+    for field in fields {
+        let field_bytes = field.to_bytes_le().map_err(|_| MemoError::Malformed)?;
+        if field_bytes[BYTES_PER_FIELD..].iter().any(|byte| *byte != 0) {
+            return Err(MemoError::Malformed);
+        }
+        bytes.extend_from_slice(&field_bytes[..BYTES_PER_FIELD]);
+    }
+    let length = *bytes.first().ok_or(MemoError::Malformed)? as usize;
+    if length >= bytes.len() || bytes[length + 1..].iter().any(|byte| *byte != 0) {
+        return Err(MemoError::Malformed);
+    }
+    Ok(bytes[1..=length].to_vec())
+}
+
+// The members of the memo input of a memo program, or `None` if the memo is a single field
+type MemoMembers<N> = Option<Vec<Identifier<N>>>;
+
+impl<N: Network> ProgramManager<N> {
+    /// Set the memo program used by [`ProgramManager::transfer_with_memo`], instead of [`MEMO_PROGRAM`].
+    ///
+    /// A memo program has a `transfer` function taking a `credits.aleo` record, the address of the recipient, the
+    /// amount in gates, and the memo, as a `field` or a struct of fields.
+    pub fn with_memo_program(mut self, memo_program: Program<N>) -> Self {
+        self.memo_program = Some(memo_program);
+        self
+    }
+
+    /// Returns the memo program used by [`ProgramManager::transfer_with_memo`].
+    pub fn memo_program(&self) -> Result<Program<N>> {
+        match &self.memo_program {
+            Some(memo_program) => Ok(memo_program.clone()),
+            None => Program::from_str(MEMO_PROGRAM),
+        }
+    }
+
+    /// Returns the number of memo bytes the `transfer` function of the program holds, failing with
+    /// [`MemoError::NoCapacity`] if the program has no such function taking a memo.
+    pub fn memo_capacity(program: &Program<N>) -> Result<usize, MemoError> {
+        let (_, members) = Self::memo_input(program)?;
+        let num_fields = members.map_or(1, |members| members.len());
+        Ok((num_fields * BYTES_PER_FIELD - 1).min(u8::MAX as usize))
+    }
+
+    /// Build a transaction sending `amount` gates to the recipient through the memo program, attaching the memo.
+    ///
+    /// The recipient and the sender each receive a record holding the memo, which [`extract_memo`] reads once it
+    /// is decrypted. Memos longer than the memo program holds are rejected with [`MemoError::TooLong`] before
+    /// anything is proven. The `input_record` funds the transfer, while the `fee_record` pays the network fee of
+    /// `fee` gates.
+    pub fn build_transfer_with_memo<const M: usize>(
+        &self,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        memo: &[u8; M],
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<Transaction<N>> {
+        let program = self.memo_program()?;
+        let capacity = Self::memo_capacity(&program)?;
+        if M > capacity {
+            return Err(MemoError::TooLong { program_id: program.id().to_string(), length: M, capacity }.into());
+        }
+        ensure!(amount > 0, "Transfer amount must be greater than zero");
+        ensure!(***input_record.gates() >= amount, "Input record does not hold enough gates for the transfer");
+        ensure!(***fee_record.gates() >= fee, "Fee record does not hold enough gates to pay the fee");
+        let private_key = self.signer()?;
+        let (function_name, members) = Self::memo_input(&program)?;
+        let (program_id, function) = (*program.id(), function_name);
+        let pending = PendingTransaction { recipient: Some(recipient), amount, program: program_id, function, fee };
+        self.enforce_spending_policy(&pending)?;
+        let _lease = self.lock_records(&[&input_record, &fee_record])?;
+
+        // Pack the memo into the type of the memo input.
+        let fields = encode_memo::<N>(memo, members.as_ref().map_or(1, Vec::len))?;
+        let memo = match members {
+            None => Plaintext::from(Literal::Field(fields[0])),
+            Some(members) => {
+                let members = members.into_iter().zip(fields);
+                let members = members.map(|(name, field)| (name, Plaintext::from(Literal::Field(field))));
+                Plaintext::Struct(members.collect(), Default::default())
+            }
+        };
+        let inputs = vec![
+            Value::Record(input_record),
+            Value::Plaintext(Plaintext::from(Literal::Address(recipient))),
+            Value::Plaintext(Plaintext::from(Literal::U64(U64::new(amount)))),
+            Value::Plaintext(memo),
+        ];
+
+        // Authorize the transfer, then prove the transfer and the fee.
+        let vm = self.vm_with(&[&program])?;
+        let authorization = vm.authorize(&private_key, program.id(), function_name, inputs, &mut rand::thread_rng())?;
+        let execution = self.prove_execution(&vm, authorization)?;
+        let fee = self.prove_fee(&vm, fee_record, fee)?;
+        self.log_built(Transaction::from_execution(execution, Some(fee))?)
+    }
+
+    /// Build a transaction sending `amount` gates with a memo, and broadcast it to the network.
+    ///
+    /// The memo program must be deployed on the network, e.g. by deploying [`MEMO_PROGRAM`] once.
+    #[cfg(not(feature = "async"))]
+    pub fn transfer_with_memo<const M: usize>(
+        &self,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        memo: &[u8; M],
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        let transaction = self.build_transfer_with_memo(amount, fee, recipient, memo, input_record, fee_record)?;
+        let transaction_id = transaction.id();
+        self.broadcast(transaction)?;
+        Ok(transaction_id)
+    }
+
+    // Returns the name of the memo function of the program, and the members of its memo input
+    fn memo_input(program: &Program<N>) -> Result<(Identifier<N>, MemoMembers<N>), MemoError> {
+        let no_capacity = || MemoError::NoCapacity { program_id: program.id().to_string() };
+        let function_name = Identifier::from_str(MEMO_FUNCTION).map_err(|_| no_capacity())?;
+        let function = program.get_function(&function_name).map_err(|_| no_capacity())?;
+        let field = PlaintextType::Literal(LiteralType::Field);
+        let memo_type = match function.input_types().as_slice() {
+            [ValueType::Record(_) | ValueType::ExternalRecord(_), recipient, amount, memo]
+                if Self::input_type(recipient) == Some(&PlaintextType::Literal(LiteralType::Address))
+                    && Self::input_type(amount) == Some(&PlaintextType::Literal(LiteralType::U64)) =>
+            {
+                Self::input_type(memo).cloned()
+            }
+            _ => None,
+        };
+        match memo_type {
+            Some(memo_type) if memo_type == field => Ok((function_name, None)),
+            Some(PlaintextType::Struct(name)) => {
+                let memo_struct = program.get_struct(&name).map_err(|_| no_capacity())?;
+                let members = memo_struct.members();
+                match !members.is_empty() && members.values().all(|member| *member == field) {
+                    true => Ok((function_name, Some(members.keys().copied().collect()))),
+                    false => Err(no_capacity()),
+                }
+            }
+            _ => Err(no_capacity()),
+        }
+    }
+
+    // Returns the type of a public or private plaintext input
+    fn input_type(value_type: &ValueType<N>) -> Option<&PlaintextType<N>> {
+        match value_type {
+            ValueType::Public(plaintext_type) | ValueType::Private(plaintext_type) => Some(plaintext_type),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_record, CurrentNetwork},
+        testnet3,
+    };
+
+    use snarkvm_console::{account::PrivateKey, prelude::One};
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    #[test]
+    fn test_memo_round_trip() {
+        for memo in [&b""[..], b"INV-2023-0042", &[0xff; 61]] {
+            let fields = encode_memo::<N>(memo, 2).unwrap();
+            assert_eq!(decode_memo(&fields).unwrap(), memo);
+        }
+        assert!(encode_memo::<N>(&[1; 62], 2).is_err());
+
+        // Lengths past the fields, and non-zero padding, are rejected.
+        let mut fields = encode_memo::<N>(b"abc", 1).unwrap();
+        assert_eq!(decode_memo(&fields).unwrap(), b"abc");
+        fields[0] += Field::from_u64(1 << 40);
+        assert_eq!(decode_memo(&fields).unwrap_err(), MemoError::Malformed);
+        assert_eq!(decode_memo(&[Field::<N>::from_u64(31)]).unwrap_err(), MemoError::Malformed);
+        assert_eq!(decode_memo(&[-Field::<N>::one()]).unwrap_err(), MemoError::Malformed);
+    }
+
+    #[test]
+    fn test_memo_capacity() {
+        let memo_program = Program::<N>::from_str(MEMO_PROGRAM).unwrap();
+        assert_eq!(ProgramManager::memo_capacity(&memo_program).unwrap(), 61);
+
+        // `credits.aleo` has a transfer function, but it takes no memo.
+        let error = ProgramManager::<N>::memo_capacity(&Program::credits().unwrap()).unwrap_err();
+        assert_eq!(error.to_string(), "Program 'credits.aleo' has no memo capacity");
+    }
+
+    #[test]
+    fn test_transfer_with_memo_rejects_long_memos() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let address = Address::try_from(&private_key).unwrap();
+        let program_manager = ProgramManager::new(private_key, testnet3("http://127.0.0.1:9"));
+        let (input_record, _) = sample_record(address, 100, rng);
+        let (fee_record, _) = sample_record(address, 10, rng);
+
+        // Memos are checked against the memo program before anything is proven.
+        let error = program_manager
+            .build_transfer_with_memo(10, 1, address, &[7; 62], input_record.clone(), fee_record.clone())
+            .unwrap_err();
+        assert_eq!(
+            error.downcast::<MemoError>().unwrap(),
+            MemoError::TooLong { program_id: "credits_memo.aleo".to_string(), length: 62, capacity: 61 }
+        );
+        let program_manager = program_manager.with_memo_program(Program::credits().unwrap());
+        let error =
+            program_manager.build_transfer_with_memo(10, 1, address, b"INV-1", input_record, fee_record).unwrap_err();
+        assert_eq!(error.to_string(), "Program 'credits.aleo' has no memo capacity");
+    }
+
+    #[cfg(feature = "devnet")]
+    #[test]
+    fn test_transfer_with_memo_local_ledger() {
+        use crate::ScannedRecord;
+        use snarkvm_console::account::ViewKey;
+
+        let rng = &mut TestRng::default();
+        let ledger = crate::LocalLedgerClient::<N>::new(PrivateKey::new(rng).unwrap()).unwrap();
+        let sender = PrivateKey::<N>::new(rng).unwrap();
+        let (address, view_key) = (Address::try_from(sender).unwrap(), ViewKey::try_from(sender).unwrap());
+        let recipient = PrivateKey::<N>::new(rng).unwrap();
+        let recipient_view_key = ViewKey::try_from(recipient).unwrap();
+        let (_, deploy_fee) = ledger.fund(address, 1_000_000).unwrap();
+        let (_, funds) = ledger.fund(address, 100).unwrap();
+        let program_manager = ProgramManager::new(sender, ledger.api_client().clone());
+
+        // Deploy the memo program, then pay the recipient with the change of the deployment fee.
+        let memo_program = program_manager.memo_program().unwrap();
+        program_manager.deploy(&memo_program, &[], 600_000, deploy_fee.decrypt(&view_key).unwrap()).unwrap();
+        let block = ledger.latest_block().unwrap();
+        let fee_record = block.into_transitions().flat_map(|transition| transition.into_records()).next().unwrap().1;
+        let memo = b"INV-2023-0042";
+        let (funds, fee_record) = (funds.decrypt(&view_key).unwrap(), fee_record.decrypt(&view_key).unwrap());
+        let recipient_address = Address::try_from(recipient).unwrap();
+        program_manager.transfer_with_memo(60, 1, recipient_address, memo, funds, fee_record).unwrap();
+
+        // The recipient and the sender each read the memo from their receipt, as the scan surfaces it.
+        let program_ids = [*memo_program.id()];
+        let records = ScannedRecord::find_in_block(ledger.latest_block().unwrap(), &program_ids).collect::<Vec<_>>();
+        let memos = |view_key: &ViewKey<N>| {
+            records.iter().filter_map(|record| record.memo(view_key).unwrap()).collect::<Vec<_>>()
+        };
+        assert_eq!(memos(&recipient_view_key), [memo.to_vec()]);
+        assert_eq!(memos(&view_key), [memo.to_vec()]);
+    }
+}
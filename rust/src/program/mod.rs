@@ -0,0 +1,219 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+mod events;
+mod execute;
+mod key_cache;
+mod transfer;
+
+mod call_graph;
+pub use call_graph::*;
+
+mod deploy;
+pub use deploy::*;
+
+#[cfg(not(feature = "async"))]
+mod execution_pool;
+#[cfg(not(feature = "async"))]
+pub use execution_pool::*;
+
+mod execution_policy;
+pub use execution_policy::*;
+
+mod expected;
+pub use expected::*;
+
+mod fee;
+pub use fee::*;
+
+mod fee_audit;
+pub use fee_audit::*;
+
+mod finalize;
+pub use finalize::*;
+
+mod inputs;
+pub use inputs::*;
+
+mod inspector;
+pub use inspector::*;
+
+mod memo;
+pub use memo::*;
+
+#[cfg(not(feature = "async"))]
+mod orchestrator;
+#[cfg(not(feature = "async"))]
+pub use orchestrator::*;
+
+mod multisig;
+pub use multisig::*;
+
+mod policy;
+pub use policy::*;
+
+mod privacy;
+pub use privacy::*;
+
+mod profile;
+pub use profile::*;
+
+mod proving;
+pub use proving::*;
+
+mod record_locker;
+pub use record_locker::*;
+
+#[cfg(not(feature = "async"))]
+mod token;
+#[cfg(not(feature = "async"))]
+pub use token::*;
+
+mod type_registry;
+pub use type_registry::*;
+
+mod typed_inputs;
+pub use typed_inputs::*;
+
+use crate::{AleoAPIClient, EventLog, RecordStore, SigningUnavailable};
+use key_cache::KeyCache;
+
+use snarkvm_console::{account::PrivateKey, program::Network};
+use snarkvm_synthesizer::{ConsensusMemory, Program, Query, VM};
+
+use anyhow::Result;
+use std::sync::Arc;
+
+/// Builds and submits transactions against the programs of an Aleo network
+///
+/// A program manager is `Send` and `Sync`, so a manager shared behind an `Arc` builds transactions on several threads
+/// at once. Its private key is only read once it is constructed, the circuit keys it synthesizes are shared by its
+/// builds, and the records a build spends are locked by its [`RecordLocker`] until the build finishes, while the
+/// proofs themselves run without holding a lock of the manager.
+pub struct ProgramManager<N: Network> {
+    private_key: Option<PrivateKey<N>>,
+    api_client: AleoAPIClient<N>,
+    record_store: Option<RecordStore<N>>,
+    proving_limits: ProvingLimits,
+    progress_reporter: Option<Arc<dyn ProgressReporter>>,
+    privacy_strategy: PrivacyStrategy,
+    spending_policy: Option<SpendingPolicy<N>>,
+    execution_policy: Option<ExecutionPolicy<N>>,
+    memo_program: Option<Program<N>>,
+    fee_audit: Option<FeeAudit<N>>,
+    event_log: Option<EventLog<N>>,
+    key_cache: KeyCache<N>,
+    record_locker: RecordLocker<N>,
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Create a program manager that signs with the given private key and queries the given client
+    pub fn new(private_key: PrivateKey<N>, api_client: AleoAPIClient<N>) -> Self {
+        Self {
+            private_key: Some(private_key),
+            api_client,
+            record_store: None,
+            proving_limits: ProvingLimits::default(),
+            progress_reporter: None,
+            privacy_strategy: PrivacyStrategy::Direct,
+            spending_policy: None,
+            execution_policy: None,
+            memo_program: None,
+            fee_audit: None,
+            event_log: None,
+            key_cache: KeyCache::default(),
+            record_locker: RecordLocker::new(),
+        }
+    }
+
+    /// Create a program manager for a [`crate::WatchOnlyAccount`], which queries the given client but fails every
+    /// operation that signs with [`SigningUnavailable`]
+    pub fn watch_only(api_client: AleoAPIClient<N>) -> Self {
+        Self {
+            private_key: None,
+            api_client,
+            record_store: None,
+            proving_limits: ProvingLimits::default(),
+            progress_reporter: None,
+            privacy_strategy: PrivacyStrategy::Direct,
+            spending_policy: None,
+            execution_policy: None,
+            memo_program: None,
+            fee_audit: None,
+            event_log: None,
+            key_cache: KeyCache::default(),
+            record_locker: RecordLocker::new(),
+        }
+    }
+
+    /// Set the unspent records of the account, from which fee records are selected when none is given
+    pub fn with_record_store(mut self, record_store: RecordStore<N>) -> Self {
+        self.record_store = Some(record_store);
+        self
+    }
+
+    /// Returns the unspent records of the account, if they were set
+    pub fn record_store(&self) -> Option<&RecordStore<N>> {
+        self.record_store.as_ref()
+    }
+
+    /// Returns the unspent records of the account mutably, e.g. to remove records once they are spent
+    pub fn record_store_mut(&mut self) -> Option<&mut RecordStore<N>> {
+        self.record_store.as_mut()
+    }
+
+    // Returns the unspent records of the account mutably, starting an empty record store if none was set
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn record_store_or_default(&mut self) -> &mut RecordStore<N> {
+        self.record_store.get_or_insert_with(RecordStore::new)
+    }
+
+    /// Returns the private key used to sign transactions, or `None` for a watch-only account
+    pub fn private_key(&self) -> Option<&PrivateKey<N>> {
+        self.private_key.as_ref()
+    }
+
+    // Set the private key of a watch-only account, once it is imported
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn set_private_key(&mut self, private_key: PrivateKey<N>) {
+        self.private_key = Some(private_key);
+    }
+
+    // Returns the private key to sign with, failing for a watch-only account
+    pub(crate) fn signer(&self) -> Result<PrivateKey<N>, SigningUnavailable> {
+        self.private_key.ok_or(SigningUnavailable)
+    }
+
+    /// Returns the API client used to query the network
+    pub fn api_client(&self) -> &AleoAPIClient<N> {
+        &self.api_client
+    }
+
+    // Returns the VM for building transactions, which keeps the circuit keys it synthesizes across builds
+    fn vm(&self) -> Result<VM<N, ConsensusMemory<N>>> {
+        self.key_cache.vm_with(&[])
+    }
+
+    // Returns the VM for building transactions, holding the given programs apart from those built into the VM
+    fn vm_with(&self, programs: &[&Program<N>]) -> Result<VM<N, ConsensusMemory<N>>> {
+        self.key_cache.vm_with(programs)
+    }
+
+    // Prepare a query that resolves state roots and paths from the connected node
+    fn query(&self) -> Query<N, <ConsensusMemory<N> as snarkvm_synthesizer::ConsensusStorage<N>>::BlockStorage> {
+        Query::from(self.api_client.base_url().trim_end_matches('/'))
+    }
+}
@@ -0,0 +1,299 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Experimental two-party custody, in which every call of the account is approved on one machine before another
+//! authorizes it.
+//!
+//! The protocol runs between an approver and the custody machine holding the private key of the account:
+//!
+//! 1. The custody machine is asked to make a call, and assigns it a request ID it never assigns again.
+//! 2. The approver reviews the call and signs its digest, which binds the request ID, with
+//!    [`PartialAuthorization::approve`], then sends the serialized partial authorization to the custody machine.
+//! 3. The custody machine completes it with [`ProgramManager::complete_authorization`], passing the request ID and
+//!    the call it was asked to make. The call must hash to the approved digest, and the approval must be signed by
+//!    the expected co-signer, or the completion fails with a [`MultisigError`] before anything is signed. An
+//!    approval of an earlier request cannot be replayed, as its digest binds another request ID.
+//! 4. Either machine proves the completed authorization, e.g. through a [`crate::ProverPoolClient`], and
+//!    broadcasts the transaction.
+//!
+//! The private key is not split between the machines. A request of snarkVM 0.9 carries the transition secret
+//! key of its signature, from which the signing key follows, so a split key would be recovered by whoever proves
+//! the first completed authorization. The protocol guarantees instead that the custody machine signs no call the
+//! approver did not approve.
+
+use super::ProgramManager;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use snarkvm_console::{
+    account::{Address, PrivateKey, Signature},
+    prelude::{ToBits, ToFields},
+    program::{Identifier, Network, ProgramID, Value},
+    types::{Field, U16},
+};
+use snarkvm_synthesizer::{Authorization, Program};
+use thiserror::Error;
+
+/// A call of a program function approved by a co-signer, to be completed by the custody machine of the account
+///
+/// The approval signs a digest of the network, the request ID, the account, the function and its inputs. Partial
+/// authorizations are transported as JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PartialAuthorization<N: Network> {
+    request_id: u64,
+    account: Address<N>,
+    program_id: ProgramID<N>,
+    function_name: Identifier<N>,
+    inputs: Vec<Value<N>>,
+    digest: Field<N>,
+    approval: Signature<N>,
+}
+
+/// An error returned when a partial authorization cannot be completed
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum MultisigError {
+    /// The approval was signed by another account than the expected co-signer
+    #[error("The call was approved by '{found}', not by the co-signer '{expected}'")]
+    WrongCoSigner { expected: String, found: String },
+    /// The call was approved for another account than the one completing it
+    #[error("The call was approved for account '{approved}', not for '{account}'")]
+    WrongAccount { approved: String, account: String },
+    /// The call, or the call held by the partial authorization, does not hash to the approved digest
+    #[error("The call does not match the digest approved by the co-signer")]
+    DigestMismatch,
+    /// The signature of the approval does not verify
+    #[error("The approval of the co-signer is invalid")]
+    InvalidApproval,
+}
+
+impl<N: Network> PartialAuthorization<N> {
+    /// Approve the call of `program_id/function_name` with the given inputs from `account`, which the custody
+    /// machine assigned `request_id`, signing its digest with the private key of the approver.
+    pub fn approve(
+        approver: &PrivateKey<N>,
+        request_id: u64,
+        account: Address<N>,
+        program_id: ProgramID<N>,
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+    ) -> Result<Self> {
+        let digest = call_digest(request_id, &account, &program_id, &function_name, &inputs)?;
+        let approval = Signature::sign(approver, &[digest], &mut rand::thread_rng())?;
+        Ok(Self { request_id, account, program_id, function_name, inputs, digest, approval })
+    }
+
+    /// Returns the ID the custody machine assigned to the request of the call.
+    pub fn request_id(&self) -> u64 {
+        self.request_id
+    }
+
+    /// Returns the account the call is made from.
+    pub fn account(&self) -> &Address<N> {
+        &self.account
+    }
+
+    /// Returns the ID of the program of the call.
+    pub fn program_id(&self) -> &ProgramID<N> {
+        &self.program_id
+    }
+
+    /// Returns the name of the function of the call.
+    pub fn function_name(&self) -> &Identifier<N> {
+        &self.function_name
+    }
+
+    /// Returns the inputs of the call.
+    pub fn inputs(&self) -> &[Value<N>] {
+        &self.inputs
+    }
+
+    /// Returns the approved digest of the call.
+    pub fn digest(&self) -> &Field<N> {
+        &self.digest
+    }
+
+    /// Returns the address of the approver.
+    pub fn approver(&self) -> Address<N> {
+        self.approval.to_address()
+    }
+
+    /// Check that the approval was signed by the co-signer over the call the partial authorization holds.
+    pub fn verify(&self, co_signer: &Address<N>) -> Result<(), MultisigError> {
+        let approver = self.approver();
+        if approver != *co_signer {
+            return Err(MultisigError::WrongCoSigner { expected: co_signer.to_string(), found: approver.to_string() });
+        }
+        match call_digest(self.request_id, &self.account, &self.program_id, &self.function_name, &self.inputs) {
+            Ok(digest) if digest == self.digest => (),
+            _ => return Err(MultisigError::DigestMismatch),
+        }
+        match self.approval.verify(co_signer, &[self.digest]) {
+            true => Ok(()),
+            false => Err(MultisigError::InvalidApproval),
+        }
+    }
+
+    /// Check that the co-signer approved the given call of `program_id/function_name` from `account`, for the
+    /// request with the given ID.
+    pub fn check_call(
+        &self,
+        co_signer: &Address<N>,
+        request_id: u64,
+        account: &Address<N>,
+        program_id: &ProgramID<N>,
+        function_name: &Identifier<N>,
+        inputs: &[Value<N>],
+    ) -> Result<(), MultisigError> {
+        self.verify(co_signer)?;
+        if self.account != *account {
+            let (approved, account) = (self.account.to_string(), account.to_string());
+            return Err(MultisigError::WrongAccount { approved, account });
+        }
+        match call_digest(request_id, account, program_id, function_name, inputs) {
+            Ok(digest) if digest == self.digest => Ok(()),
+            _ => Err(MultisigError::DigestMismatch),
+        }
+    }
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Complete a partial authorization approved by the co-signer, authorizing the given call of the program
+    /// without proving it.
+    ///
+    /// The call is the one the custody machine was asked to make under `request_id`, and must be the call the
+    /// co-signer approved for that request and the account of the program manager, or the completion fails with a
+    /// [`MultisigError`]. The `imports` are as for [`ProgramManager::authorize_execution`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn complete_authorization(
+        &self,
+        partial: &PartialAuthorization<N>,
+        co_signer: &Address<N>,
+        request_id: u64,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+    ) -> Result<Authorization<N>> {
+        let account = Address::try_from(self.signer()?)?;
+        partial.check_call(co_signer, request_id, &account, program.id(), &function_name, &inputs)?;
+        self.authorize_execution(program, imports, function_name, inputs)
+    }
+}
+
+// Hash the network, the request ID, the account, the function and the inputs of a call, prefixing each input with
+// its length
+fn call_digest<N: Network>(
+    request_id: u64,
+    account: &Address<N>,
+    program_id: &ProgramID<N>,
+    function_name: &Identifier<N>,
+    inputs: &[Value<N>],
+) -> Result<Field<N>> {
+    let function_id =
+        N::hash_bhp1024(&(U16::<N>::new(N::ID), program_id.name(), program_id.network(), function_name).to_bits_le())?;
+    let mut preimage = vec![function_id, Field::from_u64(request_id), account.to_x_coordinate()];
+    preimage.push(Field::from_u64(inputs.len() as u64));
+    for input in inputs {
+        let fields = input.to_fields()?;
+        preimage.push(Field::from_u64(fields.len() as u64));
+        preimage.extend(fields);
+    }
+    N::hash_psd8(&preimage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_record, CurrentNetwork},
+        testnet3,
+    };
+    use snarkvm_console::program::Request;
+    use snarkvm_utilities::TestRng;
+
+    use std::str::FromStr;
+
+    type N = CurrentNetwork;
+
+    #[test]
+    fn test_two_party_authorization() {
+        let rng = &mut TestRng::default();
+        let (approver, custody) = (PrivateKey::<N>::new(rng).unwrap(), PrivateKey::<N>::new(rng).unwrap());
+        let (co_signer, account) = (Address::try_from(&approver).unwrap(), Address::try_from(&custody).unwrap());
+        let program_manager = ProgramManager::new(custody, testnet3("http://127.0.0.1:9"));
+        let credits = Program::<N>::credits().unwrap();
+        let transfer = Identifier::from_str("transfer").unwrap();
+        let (record, _) = sample_record(account, 100, rng);
+        let call = |amount: &str| {
+            let recipient = Value::from_str(&co_signer.to_string()).unwrap();
+            vec![Value::Record(record.clone()), recipient, Value::from_str(amount).unwrap()]
+        };
+
+        // The approver signs the call, which reaches the custody machine as JSON.
+        let partial =
+            PartialAuthorization::approve(&approver, 1, account, *credits.id(), transfer, call("10u64")).unwrap();
+        let json = serde_json::to_string(&partial).unwrap();
+        let partial = serde_json::from_str::<PartialAuthorization<N>>(&json).unwrap();
+        assert_eq!(partial.approver(), co_signer);
+        partial.verify(&co_signer).unwrap();
+
+        // The custody machine signs exactly the approved call, as `ProgramManager::complete_authorization` does
+        // with the VM, which loads proving keys.
+        partial.check_call(&co_signer, 1, &account, credits.id(), &transfer, &call("10u64")).unwrap();
+        let input_types = credits.get_function(&transfer).unwrap().input_types();
+        let request = Request::sign(&custody, *credits.id(), transfer, call("10u64").into_iter(), &input_types, rng);
+        let authorization = Authorization::new(&[request.unwrap()]);
+        let request = authorization.peek_next().unwrap();
+        assert!(request.verify(&input_types));
+        assert_eq!((request.caller(), request.inputs()), (&account, &call("10u64")[..]));
+
+        // Another call, or a tampered partial authorization, is rejected at completion.
+        let complete_request =
+            |partial: &PartialAuthorization<N>, co_signer: &Address<N>, id: u64, amount: &str| match program_manager
+                .complete_authorization(partial, co_signer, id, &credits, &[], transfer, call(amount))
+            {
+                Ok(_) => panic!("The call of {amount} was authorized"),
+                Err(error) => error.downcast::<MultisigError>().unwrap(),
+            };
+        let complete = |partial: &PartialAuthorization<N>, co_signer: &Address<N>, amount: &str| {
+            complete_request(partial, co_signer, 1, amount)
+        };
+        assert_eq!(complete(&partial, &co_signer, "11u64"), MultisigError::DigestMismatch);
+        let tampered = PartialAuthorization { inputs: call("11u64"), ..partial.clone() };
+        assert_eq!(complete(&tampered, &co_signer, "11u64"), MultisigError::DigestMismatch);
+        let digest = call_digest(1, &account, credits.id(), &transfer, &call("11u64")).unwrap();
+        let tampered = PartialAuthorization { inputs: call("11u64"), digest, ..partial.clone() };
+        assert_eq!(complete(&tampered, &co_signer, "11u64"), MultisigError::InvalidApproval);
+        let expected = MultisigError::WrongCoSigner { expected: account.to_string(), found: co_signer.to_string() };
+        assert_eq!(complete(&partial, &account, "10u64"), expected);
+
+        // Approvals for another account are not completed.
+        let other =
+            PartialAuthorization::approve(&approver, 1, co_signer, *credits.id(), transfer, call("10u64")).unwrap();
+        let expected = MultisigError::WrongAccount { approved: co_signer.to_string(), account: account.to_string() };
+        assert_eq!(complete(&other, &co_signer, "10u64"), expected);
+
+        // The approval of a request cannot be replayed for a later request of the same call.
+        partial.check_call(&co_signer, 2, &account, credits.id(), &transfer, &call("10u64")).unwrap_err();
+        assert_eq!(complete_request(&partial, &co_signer, 2, "10u64"), MultisigError::DigestMismatch);
+        let replayed = PartialAuthorization { request_id: 2, ..partial.clone() };
+        assert_eq!(replayed.verify(&co_signer), Err(MultisigError::DigestMismatch));
+        let digest = call_digest(2, &account, credits.id(), &transfer, &call("10u64")).unwrap();
+        let replayed = PartialAuthorization { request_id: 2, digest, ..partial.clone() };
+        assert_eq!(complete_request(&replayed, &co_signer, 2, "10u64"), MultisigError::InvalidApproval);
+    }
+}
@@ -0,0 +1,555 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+use crate::{Json, Microcredits, Persist, TransactionStatus, WalletEvent};
+
+use snarkvm_console::{
+    account::{Address, ViewKey},
+    program::{Identifier, Network, Plaintext, ProgramID, Record, Value},
+};
+use snarkvm_synthesizer::{Program, Transaction};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// An input of a [`Step`]: a value given up front, or a record output by an earlier step
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum StepInput<N: Network> {
+    /// A value known when the flow is declared
+    Value(Value<N>),
+    /// The record at `index` among the records that the transaction of the earlier `step` output to the account,
+    /// in the order of its transitions, so that the change of its fee comes last
+    Output { step: usize, index: usize },
+}
+
+impl<N: Network> From<Record<N, Plaintext<N>>> for StepInput<N> {
+    fn from(record: Record<N, Plaintext<N>>) -> Self {
+        Self::Value(Value::Record(record))
+    }
+}
+
+/// The program executed by a [`Step::Execute`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StepProgram<N: Network> {
+    /// A program on the network, fetched with its imports when the step is built
+    Id(ProgramID<N>),
+    /// The program deployed by the earlier `step`, with the imports of its deployment
+    Deployed { step: usize },
+}
+
+/// A transaction of an [`Orchestrator`] flow, built with the builders of [`ProgramManager`] once the steps it
+/// depends on are confirmed
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum Step<N: Network> {
+    /// Deploy a program, as [`ProgramManager::build_deployment`] does
+    Deploy { program: Program<N>, imports: Vec<Program<N>>, fee: u64, fee_record: StepInput<N> },
+    /// Execute a function, as [`ProgramManager::build_execution`] does
+    Execute {
+        program: StepProgram<N>,
+        function: Identifier<N>,
+        inputs: Vec<StepInput<N>>,
+        fee: u64,
+        fee_record: StepInput<N>,
+    },
+    /// Transfer gates, as [`ProgramManager::build_transfer`] does
+    Transfer { amount: u64, fee: u64, recipient: Address<N>, record: StepInput<N>, fee_record: StepInput<N> },
+}
+
+/// The state of an [`Orchestrator`] flow
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FlowState<N: Network> {
+    /// No step was started
+    Pending,
+    /// The steps before `step` are confirmed, and `step` runs next
+    InProgress { step: usize },
+    /// The step failed, and runs again when the flow is resumed
+    Failed { step: usize, reason: String },
+    /// Every step is confirmed, with the IDs of their transactions in order
+    Complete { tx_ids: Vec<N::TransactionID> },
+}
+
+/// The progress of an [`Orchestrator`] flow, saved after each confirmation so that a flow resumes where it stopped
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct FlowProgress<N: Network> {
+    completed: Vec<CompletedStep<N>>,
+    // The transaction of the next step, saved before it is broadcast, so that a flow resumed after a crash awaits
+    // it instead of spending its records in another transaction
+    pending: Option<Transaction<N>>,
+}
+
+/// A confirmed step of a flow, with the records its transaction output to the account
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct CompletedStep<N: Network> {
+    transaction_id: N::TransactionID,
+    records: Vec<Record<N, Plaintext<N>>>,
+}
+
+impl<N: Network> FlowProgress<N> {
+    /// Returns the confirmed steps, in order.
+    pub fn completed(&self) -> &[CompletedStep<N>] {
+        &self.completed
+    }
+
+    /// Returns the transaction of the next step, if it was built but not confirmed.
+    pub fn pending(&self) -> Option<&Transaction<N>> {
+        self.pending.as_ref()
+    }
+}
+
+impl<N: Network> CompletedStep<N> {
+    /// Returns the ID of the transaction of the step.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the decrypted records the transaction of the step output to the account.
+    pub fn records(&self) -> &[Record<N, Plaintext<N>>] {
+        &self.records
+    }
+}
+
+impl<N: Network> Persist for FlowProgress<N> {
+    const KIND: u8 = 6;
+    const NAME: &'static str = "flow progress";
+    const VERSION: u16 = 1;
+}
+
+/// Runs a flow of dependent transactions, such as deploying a program, initializing it, and funding it, one step
+/// at a time
+///
+/// Each step is built once the steps before it are confirmed, so that it can spend their output records. With a
+/// progress file, the transaction of each step is saved before it is broadcast, and the step is saved once the
+/// transaction is confirmed, so a flow run by a process that crashed resumes from the last confirmed step,
+/// awaiting the transaction that was in flight instead of building another.
+pub struct Orchestrator<N: Network> {
+    program_manager: ProgramManager<N>,
+    steps: Vec<Step<N>>,
+    progress: FlowProgress<N>,
+    progress_path: Option<PathBuf>,
+    failure: Option<(usize, String)>,
+    timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl<N: Network> Orchestrator<N> {
+    /// Create an orchestrator running the steps in order with the given program manager
+    ///
+    /// Each transaction is awaited for up to five minutes, checking every five seconds.
+    pub fn new(program_manager: ProgramManager<N>, steps: Vec<Step<N>>) -> Self {
+        Self {
+            program_manager,
+            steps,
+            progress: FlowProgress { completed: vec![], pending: None },
+            progress_path: None,
+            failure: None,
+            timeout: Duration::from_secs(300),
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+
+    /// Save the progress of the flow to the given file, resuming from the progress it holds if it exists.
+    pub fn with_progress_file(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            let progress = FlowProgress::load(path)?;
+            ensure!(
+                progress.completed.len() <= self.steps.len(),
+                "The progress file holds {} steps, but the flow has {}",
+                progress.completed.len(),
+                self.steps.len()
+            );
+            self.progress = progress;
+        }
+        self.progress_path = Some(path.to_path_buf());
+        Ok(self)
+    }
+
+    /// Set the time for which the transaction of each step is awaited, and the interval at which it is polled.
+    pub fn with_confirmation_timeout(mut self, timeout: Duration, poll_interval: Duration) -> Self {
+        self.timeout = timeout;
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Returns the program manager building the transactions.
+    pub fn program_manager(&self) -> &ProgramManager<N> {
+        &self.program_manager
+    }
+
+    /// Returns the steps of the flow.
+    pub fn steps(&self) -> &[Step<N>] {
+        &self.steps
+    }
+
+    /// Returns the progress of the flow.
+    pub fn progress(&self) -> &FlowProgress<N> {
+        &self.progress
+    }
+
+    /// Returns the decrypted records output to the account by the given step, if it is confirmed.
+    pub fn outputs(&self, step: usize) -> Option<&[Record<N, Plaintext<N>>]> {
+        self.progress.completed.get(step).map(CompletedStep::records)
+    }
+
+    /// Returns the state of the flow.
+    pub fn state(&self) -> FlowState<N> {
+        let next = self.progress.completed.len();
+        match &self.failure {
+            Some((step, reason)) => FlowState::Failed { step: *step, reason: reason.clone() },
+            None if next == self.steps.len() => {
+                FlowState::Complete { tx_ids: self.progress.completed.iter().map(|step| step.transaction_id).collect() }
+            }
+            None if next == 0 && self.progress.pending.is_none() => FlowState::Pending,
+            None => FlowState::InProgress { step: next },
+        }
+    }
+
+    /// Run the remaining steps, returning the IDs of the transactions of every step once they are confirmed.
+    ///
+    /// A step that fails stops the flow in [`FlowState::Failed`], and the flow resumes from that step when it is
+    /// run again.
+    pub fn run(&mut self) -> Result<Vec<N::TransactionID>> {
+        while self.run_step()? {}
+        match self.state() {
+            FlowState::Complete { tx_ids } => Ok(tx_ids),
+            state => bail!("The flow stopped in state {state:?}"),
+        }
+    }
+
+    /// Run the next step, returning `false` if every step is already confirmed.
+    pub fn run_step(&mut self) -> Result<bool> {
+        let step = self.progress.completed.len();
+        if step == self.steps.len() {
+            return Ok(false);
+        }
+        match self.confirm_step(step) {
+            Ok(()) => {
+                self.failure = None;
+                Ok(true)
+            }
+            Err(error) => {
+                self.failure = Some((step, error.to_string()));
+                Err(error)
+            }
+        }
+    }
+
+    // Build the transaction of the step, or take the transaction built before a crash, and broadcast it until it
+    // is confirmed, saving the progress before the broadcast and after the confirmation
+    fn confirm_step(&mut self, step: usize) -> Result<()> {
+        let api_client = self.program_manager.api_client();
+        let transaction = match self.progress.pending.clone() {
+            Some(transaction) => transaction,
+            None => {
+                let transaction = self.build_step(step)?;
+                self.progress.pending = Some(transaction.clone());
+                self.save()?;
+                transaction
+            }
+        };
+        let transaction_id = transaction.id();
+        // A transaction that is not in the chain was not broadcast before a crash, or was dropped by the node.
+        if api_client.transaction_status(transaction_id)? == TransactionStatus::Pending {
+            self.program_manager.broadcast(transaction.clone())?;
+        }
+        api_client.wait_for_confirmation(transaction_id, self.timeout, self.poll_interval, |_| ())?;
+        self.program_manager.log_event(WalletEvent::FlowStep { step, transaction_id })?;
+
+        let view_key = ViewKey::try_from(self.program_manager.signer()?)?;
+        let records = transaction
+            .transitions()
+            .flat_map(|transition| transition.records())
+            .filter(|(_, record)| record.is_owner(&view_key))
+            .map(|(_, record)| record.decrypt(&view_key))
+            .collect::<Result<Vec<_>>>()?;
+        self.progress.completed.push(CompletedStep { transaction_id, records });
+        self.progress.pending = None;
+        self.save()
+    }
+
+    // Build the transaction of the step from its inputs and the outputs of the steps before it
+    fn build_step(&self, step: usize) -> Result<Transaction<N>> {
+        let program_manager = &self.program_manager;
+        match &self.steps[step] {
+            Step::Deploy { program, imports, fee, fee_record } => {
+                program_manager.build_deployment(program, imports, *fee, self.record(step, fee_record)?)
+            }
+            Step::Execute { program, function, inputs, fee, fee_record } => {
+                let (program, imports) = match program {
+                    StepProgram::Id(program_id) => {
+                        let program = program_manager.api_client().get_program(*program_id)?;
+                        let imports = program_manager.fetch_imports(&program)?;
+                        (program, imports)
+                    }
+                    StepProgram::Deployed { step: deployment } => match self.steps.get(*deployment) {
+                        Some(Step::Deploy { program, imports, .. }) if *deployment < step => {
+                            (program.clone(), imports.clone())
+                        }
+                        _ => bail!(
+                            "Step {step} executes the program of step {deployment}, which is not an earlier deployment"
+                        ),
+                    },
+                };
+                let inputs = inputs.iter().map(|input| self.value(step, input)).collect::<Result<Vec<_>>>()?;
+                let fee_record = self.record(step, fee_record)?;
+                program_manager.build_execution(&program, &imports, *function, inputs, Microcredits(*fee), fee_record)
+            }
+            Step::Transfer { amount, fee, recipient, record, fee_record } => {
+                let (record, fee_record) = (self.record(step, record)?, self.record(step, fee_record)?);
+                let (amount, fee) = (Microcredits(*amount), Microcredits(*fee));
+                program_manager.build_transfer(amount, fee, *recipient, record, fee_record)
+            }
+        }
+    }
+
+    // Resolve an input of the step to its value
+    fn value(&self, step: usize, input: &StepInput<N>) -> Result<Value<N>> {
+        match input {
+            StepInput::Value(value) => Ok(value.clone()),
+            StepInput::Output { step: output_step, index } => {
+                let message = format!("Step {step} spends an output of step {output_step}, which is not earlier");
+                ensure!(*output_step < step, message);
+                let records = self.outputs(*output_step).ok_or_else(|| anyhow!("Step {output_step} is not confirmed"))?;
+                let record = records
+                    .get(*index)
+                    .ok_or_else(|| anyhow!("Step {output_step} output {} records, not {}", records.len(), index + 1))?;
+                Ok(Value::Record(record.clone()))
+            }
+        }
+    }
+
+    // Resolve an input of the step that must be a record
+    fn record(&self, step: usize, input: &StepInput<N>) -> Result<Record<N, Plaintext<N>>> {
+        match self.value(step, input)? {
+            Value::Record(record) => Ok(record),
+            Value::Plaintext(_) => bail!("Step {step} expects a record, but was given a plaintext"),
+        }
+    }
+
+    // Save the progress to the progress file, if there is one
+    fn save(&self) -> Result<()> {
+        match &self.progress_path {
+            Some(path) => self.progress.save(path, &Json),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_record, CurrentNetwork},
+        testnet3,
+    };
+
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_utilities::TestRng;
+    use std::{env, str::FromStr};
+
+    type N = CurrentNetwork;
+
+    #[test]
+    fn test_orchestrator_resolves_outputs() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let address = Address::try_from(private_key).unwrap();
+        let (record, _) = sample_record(address, 100, rng);
+        let (change, _) = sample_record(address, 40, rng);
+        let transfer =
+            |record, fee_record| Step::Transfer { amount: 10, fee: 1, recipient: address, record, fee_record };
+        let steps = vec![
+            transfer(record.clone().into(), record.clone().into()),
+            transfer(StepInput::Output { step: 0, index: 0 }, StepInput::Output { step: 1, index: 0 }),
+        ];
+        let program_manager = ProgramManager::new(private_key, testnet3("http://127.0.0.1:9"));
+        let mut orchestrator = Orchestrator::new(program_manager, steps);
+        assert_eq!(orchestrator.state(), FlowState::Pending);
+
+        // Outputs of steps that are not confirmed, or not earlier, are rejected.
+        let error = orchestrator.record(1, &StepInput::Output { step: 0, index: 0 }).unwrap_err();
+        assert_eq!(error.to_string(), "Step 0 is not confirmed");
+        let error = orchestrator.record(1, &StepInput::Output { step: 1, index: 0 }).unwrap_err();
+        assert_eq!(error.to_string(), "Step 1 spends an output of step 1, which is not earlier");
+
+        let transaction_id = <N as Network>::TransactionID::default();
+        orchestrator.progress.completed.push(CompletedStep { transaction_id, records: vec![change.clone()] });
+        assert_eq!(orchestrator.state(), FlowState::InProgress { step: 1 });
+        assert_eq!(orchestrator.record(1, &StepInput::Output { step: 0, index: 0 }).unwrap(), change);
+        let error = orchestrator.record(1, &StepInput::Output { step: 0, index: 1 }).unwrap_err();
+        assert_eq!(error.to_string(), "Step 0 output 1 records, not 2");
+        let plaintext = StepInput::Value(Value::from_str("1u64").unwrap());
+        assert!(orchestrator.record(1, &plaintext).is_err());
+
+        // A failed step is reported until it runs again, and a deployment must come from an earlier step.
+        let execute = Step::Execute {
+            program: StepProgram::Deployed { step: 0 },
+            function: Identifier::from_str("bump").unwrap(),
+            inputs: vec![],
+            fee: 1,
+            fee_record: record.into(),
+        };
+        orchestrator.steps[1] = execute;
+        assert!(orchestrator.run_step().is_err());
+        let FlowState::Failed { step, reason } = orchestrator.state() else { panic!("The flow did not fail") };
+        assert_eq!(step, 1);
+        assert_eq!(reason, "Step 1 executes the program of step 0, which is not an earlier deployment");
+    }
+
+    #[cfg(feature = "devnet")]
+    const COUNTER_PROGRAM: &str = "program flow_counter.aleo;
+
+mapping counts:
+    key left as u8.public;
+    value right as u64.public;
+
+function bump:
+    input r0 as u64.public;
+    finalize r0;
+
+finalize bump:
+    input r0 as u64.public;
+    increment counts[0u8] by r0;
+";
+
+    // Fund an account on a local ledger, and declare a flow deploying the counter program, bumping it with the
+    // change of the deployment fee, then transferring gates to a recipient with the change of the bump fee
+    #[cfg(feature = "devnet")]
+    fn counter_flow(rng: &mut TestRng) -> (crate::LocalLedgerClient<N>, PrivateKey<N>, Vec<Step<N>>) {
+        let ledger = crate::LocalLedgerClient::<N>::new(PrivateKey::new(rng).unwrap()).unwrap();
+        let owner = PrivateKey::<N>::new(rng).unwrap();
+        let (address, view_key) = (Address::try_from(owner).unwrap(), ViewKey::try_from(owner).unwrap());
+        let (_, deploy_fee) = ledger.fund(address, 1_000_000).unwrap();
+        let (_, funds) = ledger.fund(address, 100).unwrap();
+        let recipient = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let steps = vec![
+            Step::Deploy {
+                program: Program::from_str(COUNTER_PROGRAM).unwrap(),
+                imports: vec![],
+                fee: 600_000,
+                fee_record: deploy_fee.decrypt(&view_key).unwrap().into(),
+            },
+            Step::Execute {
+                program: StepProgram::Deployed { step: 0 },
+                function: Identifier::from_str("bump").unwrap(),
+                inputs: vec![StepInput::Value(Value::from_str("3u64").unwrap())],
+                fee: 1,
+                fee_record: StepInput::Output { step: 0, index: 0 },
+            },
+            Step::Transfer {
+                amount: 60,
+                fee: 1,
+                recipient,
+                record: funds.decrypt(&view_key).unwrap().into(),
+                fee_record: StepInput::Output { step: 1, index: 0 },
+            },
+        ];
+        (ledger, owner, steps)
+    }
+
+    #[cfg(feature = "devnet")]
+    #[test]
+    fn test_orchestrator_local_ledger() {
+        let rng = &mut TestRng::default();
+        let (ledger, owner, steps) = counter_flow(rng);
+        let program_manager = ProgramManager::new(owner, ledger.api_client().clone());
+        let mut orchestrator =
+            Orchestrator::new(program_manager, steps).with_confirmation_timeout(Duration::from_secs(5), Duration::ZERO);
+        let tx_ids = orchestrator.run().unwrap();
+        assert_eq!(tx_ids.len(), 3);
+        assert_eq!(orchestrator.state(), FlowState::Complete { tx_ids });
+
+        // The bump was paid with the change of the deployment fee, and the transfer with the change of the bump fee.
+        assert_eq!(***orchestrator.outputs(0).unwrap()[0].gates(), 400_000);
+        assert_eq!(***orchestrator.outputs(1).unwrap()[0].gates(), 399_999);
+        let (counts, key) = (Identifier::from_str("counts").unwrap(), Plaintext::from_str("0u8").unwrap());
+        let value = ledger.get_mapping_value("flow_counter.aleo", &counts, &key).unwrap();
+        assert_eq!(value, Some(Value::from_str("3u64").unwrap()));
+        let gates = orchestrator.outputs(2).unwrap().iter().map(|record| ***record.gates()).collect::<Vec<_>>();
+        assert_eq!(gates, [40, 399_998]);
+    }
+
+    #[cfg(feature = "devnet")]
+    #[test]
+    fn test_orchestrator_resumes_after_crash() {
+        let rng = &mut TestRng::default();
+        let (ledger, owner, steps) = counter_flow(rng);
+        let path = env::temp_dir().join(format!("aleo-flow-crash-{}", std::process::id()));
+        let orchestrator = |steps| {
+            let program_manager = ProgramManager::new(owner, ledger.api_client().clone());
+            Orchestrator::new(program_manager, steps)
+                .with_confirmation_timeout(Duration::from_secs(5), Duration::ZERO)
+                .with_progress_file(&path)
+                .unwrap()
+        };
+
+        // The process crashes once the second step is confirmed.
+        let mut crashed = orchestrator(steps.clone());
+        assert!(crashed.run_step().unwrap() && crashed.run_step().unwrap());
+        let confirmed = crashed.progress().completed().iter().map(CompletedStep::transaction_id).collect::<Vec<_>>();
+        drop(crashed);
+
+        // The flow resumes from the third step, and completes without building the first two again.
+        let mut resumed = orchestrator(steps);
+        assert_eq!(resumed.state(), FlowState::InProgress { step: 2 });
+        let height = ledger.latest_height();
+        let tx_ids = resumed.run().unwrap();
+        assert_eq!(tx_ids[..2], confirmed);
+        assert_eq!(ledger.latest_height(), height + 1);
+        let gates = resumed.outputs(2).unwrap().iter().map(|record| ***record.gates()).collect::<Vec<_>>();
+        assert_eq!(gates, [40, 399_998]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_flow_progress_round_trip() {
+        let rng = &mut TestRng::default();
+        let address = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let (record, _) = sample_record(address, 100, rng);
+        let progress = FlowProgress::<N> {
+            completed: vec![CompletedStep { transaction_id: Default::default(), records: vec![record] }],
+            pending: None,
+        };
+        let path = env::temp_dir().join(format!("aleo-flow-progress-{}", std::process::id()));
+        progress.save(&path, &Json).unwrap();
+
+        // A flow resumes from the progress file, which must not hold more steps than the flow.
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let program_manager = || ProgramManager::new(private_key, testnet3("http://127.0.0.1:9"));
+        let orchestrator = Orchestrator::new(program_manager(), vec![]).with_progress_file(&path);
+        assert!(orchestrator.is_err());
+        let transfer = Step::Transfer {
+            amount: 10,
+            fee: 1,
+            recipient: address,
+            record: StepInput::Output { step: 0, index: 0 },
+            fee_record: StepInput::Output { step: 0, index: 0 },
+        };
+        let orchestrator = Orchestrator::new(program_manager(), vec![transfer]).with_progress_file(&path).unwrap();
+        assert_eq!(orchestrator.progress(), &progress);
+        assert_eq!(orchestrator.state(), FlowState::Complete { tx_ids: vec![Default::default()] });
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,520 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+use crate::{is_shared_address_format, unix_time, SpendLog, WalletEvent};
+
+use snarkvm_console::{
+    account::Address,
+    program::{Identifier, Network, ProgramID},
+};
+
+use anyhow::Result;
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt,
+    str::FromStr,
+    sync::Arc,
+};
+
+/// A transaction about to be built, as checked by a [`SpendingPolicy`] and shown to its [`ApprovalCallback`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingTransaction<N: Network> {
+    /// The recipient of the gates, or `None` for executions of other functions than `credits.aleo/transfer`
+    pub recipient: Option<Address<N>>,
+    /// The gates sent to the recipient
+    pub amount: u64,
+    /// The program executed by the transaction
+    pub program: ProgramID<N>,
+    /// The function executed by the transaction
+    pub function: Identifier<N>,
+    /// The fee of the transaction in gates
+    pub fee: u64,
+}
+
+impl<N: Network> PendingTransaction<N> {
+    /// Describe a `credits.aleo/transfer` of `amount` gates to the recipient.
+    pub fn transfer(recipient: Address<N>, amount: u64, fee: u64) -> Result<Self> {
+        let (program, function) = (ProgramID::from_str("credits.aleo")?, Identifier::from_str("transfer")?);
+        Ok(Self { recipient: Some(recipient), amount, program, function, fee })
+    }
+
+    /// Returns the gates the transaction spends, which are the amount and the fee.
+    pub fn spend(&self) -> u64 {
+        self.amount.saturating_add(self.fee)
+    }
+}
+
+/// Approves the transactions of a [`SpendingPolicy`] that spend more than its approval threshold, e.g. by asking
+/// an operator
+///
+/// Closures taking a [`PendingTransaction`] and returning whether it is approved are callbacks.
+pub trait ApprovalCallback<N: Network>: Send + Sync {
+    /// Called before the transaction is built, returning `false` to refuse it.
+    fn approve(&self, transaction: &PendingTransaction<N>) -> bool;
+}
+
+impl<N: Network, F: Fn(&PendingTransaction<N>) -> bool + Send + Sync> ApprovalCallback<N> for F {
+    fn approve(&self, transaction: &PendingTransaction<N>) -> bool {
+        self(transaction)
+    }
+}
+
+/// Confirms the network of the transfers of a [`SpendingPolicy`], e.g. by asking the user, as addresses in a format
+/// shared by several networks do not tell which network they are for
+///
+/// Closures taking the chain of the client and a [`PendingTransaction`], and returning whether the transfer is
+/// confirmed, are hooks.
+pub trait NetworkConfirmation<N: Network>: Send + Sync {
+    /// Called before a transfer is built, with the REST path of the chain of the client, e.g. `testnet3`, returning
+    /// `false` to refuse it.
+    fn confirm(&self, chain: &str, transaction: &PendingTransaction<N>) -> bool;
+}
+
+impl<N: Network, F: Fn(&str, &PendingTransaction<N>) -> bool + Send + Sync> NetworkConfirmation<N> for F {
+    fn confirm(&self, chain: &str, transaction: &PendingTransaction<N>) -> bool {
+        self(chain, transaction)
+    }
+}
+
+/// The limits on the transactions built by a [`ProgramManager`], set by [`ProgramManager::with_spending_policy`]
+///
+/// A transaction spends its amount and its fee. The policy is checked before any proving starts, and fails the
+/// build with a [`PolicyViolation`]. The daily limit counts the spends over the last 24 hours, which are kept
+/// in the [`crate::RecordStore`] of the program manager, so that they survive a restart once the store is saved.
+#[derive(Clone)]
+pub struct SpendingPolicy<N: Network> {
+    transaction_limit: Option<u64>,
+    daily_limit: Option<u64>,
+    allowed_recipients: Option<HashSet<Address<N>>>,
+    allowed_programs: Option<HashSet<ProgramID<N>>>,
+    approval: Option<(u64, Arc<dyn ApprovalCallback<N>>)>,
+    network_confirmation: Option<Arc<dyn NetworkConfirmation<N>>>,
+}
+
+impl<N: Network> SpendingPolicy<N> {
+    /// Create a policy allowing every transaction.
+    pub fn new() -> Self {
+        Self {
+            transaction_limit: None,
+            daily_limit: None,
+            allowed_recipients: None,
+            allowed_programs: None,
+            approval: None,
+            network_confirmation: None,
+        }
+    }
+
+    /// Refuse transactions spending more than `limit` gates.
+    pub fn with_transaction_limit(mut self, limit: u64) -> Self {
+        self.transaction_limit = Some(limit);
+        self
+    }
+
+    /// Refuse transactions that would bring the gates spent over the last 24 hours above `limit`.
+    pub fn with_daily_limit(mut self, limit: u64) -> Self {
+        self.daily_limit = Some(limit);
+        self
+    }
+
+    /// Allow transfers to the given recipient. Once a recipient is allowed, transfers to any other are refused.
+    pub fn allow_recipient(mut self, recipient: Address<N>) -> Self {
+        self.allowed_recipients.get_or_insert_with(HashSet::new).insert(recipient);
+        self
+    }
+
+    /// Allow executions of the given program. Once a program is allowed, executions of any other are refused,
+    /// including `credits.aleo` unless it is allowed too.
+    pub fn allow_program(mut self, program: ProgramID<N>) -> Self {
+        self.allowed_programs.get_or_insert_with(HashSet::new).insert(program);
+        self
+    }
+
+    /// Ask the callback to approve the transactions spending more than `threshold` gates.
+    pub fn with_approval(mut self, threshold: u64, callback: impl ApprovalCallback<N> + 'static) -> Self {
+        self.approval = Some((threshold, Arc::new(callback)));
+        self
+    }
+
+    /// Ask the hook to confirm the network of every transfer, when other networks share the address format of `N`.
+    ///
+    /// The hook is asked by [`ProgramManager::check_spending_policy`] once every other rule allows the transfer.
+    pub fn with_network_confirmation(mut self, hook: impl NetworkConfirmation<N> + 'static) -> Self {
+        self.network_confirmation = Some(Arc::new(hook));
+        self
+    }
+
+    /// Check a transaction against the policy, given the spends of the account and the time in seconds since
+    /// the Unix epoch.
+    ///
+    /// The rules are checked in the order the policy lists them, and the approval callback is only called for
+    /// transactions that every other rule allows.
+    pub fn check(
+        &self,
+        transaction: &PendingTransaction<N>,
+        spends: &SpendLog,
+        now: u64,
+    ) -> Result<(), PolicyViolation> {
+        let spend = transaction.spend();
+        if let Some(limit) = self.transaction_limit.filter(|limit| spend > *limit) {
+            return Err(PolicyViolation::TransactionLimit { spend, limit });
+        }
+        if let Some(limit) = self.daily_limit {
+            let spent = spends.spent_within_window(now);
+            if spent.saturating_add(spend) > limit {
+                return Err(PolicyViolation::DailyLimit { spend, spent, limit });
+            }
+        }
+        if let (Some(allowed), Some(recipient)) = (&self.allowed_recipients, transaction.recipient) {
+            if !allowed.contains(&recipient) {
+                return Err(PolicyViolation::RecipientNotAllowed { recipient: recipient.to_string() });
+            }
+        }
+        if let Some(allowed) = &self.allowed_programs {
+            if !allowed.contains(&transaction.program) {
+                return Err(PolicyViolation::ProgramNotAllowed { program: transaction.program.to_string() });
+            }
+        }
+        if let Some((threshold, callback)) = self.approval.as_ref().filter(|(threshold, _)| spend > *threshold) {
+            if !callback.approve(transaction) {
+                return Err(PolicyViolation::ApprovalDenied { spend, threshold: *threshold });
+            }
+        }
+        Ok(())
+    }
+
+    /// Ask the network confirmation hook, if one is set, to confirm a transfer on the given chain.
+    ///
+    /// Executions without a recipient are not shown to the hook, nor are transfers when the address format of `N`
+    /// belongs to no other network, as an address of another network would then fail to parse.
+    pub fn confirm_network(&self, chain: &str, transaction: &PendingTransaction<N>) -> Result<(), PolicyViolation> {
+        let (Some(hook), Some(recipient)) = (&self.network_confirmation, transaction.recipient) else {
+            return Ok(());
+        };
+        if is_shared_address_format::<N>() && !hook.confirm(chain, transaction) {
+            return Err(PolicyViolation::NetworkNotConfirmed {
+                recipient: recipient.to_string(),
+                chain: chain.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<N: Network> Default for SpendingPolicy<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Network> fmt::Debug for SpendingPolicy<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SpendingPolicy")
+            .field("transaction_limit", &self.transaction_limit)
+            .field("daily_limit", &self.daily_limit)
+            .field("allowed_recipients", &self.allowed_recipients)
+            .field("allowed_programs", &self.allowed_programs)
+            .field("approval_threshold", &self.approval.as_ref().map(|(threshold, _)| threshold))
+            .field("network_confirmation", &self.network_confirmation.is_some())
+            .finish()
+    }
+}
+
+/// The error returned when a transaction breaks a rule of the [`SpendingPolicy`] of a [`ProgramManager`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The transaction spends more gates than a single transaction may
+    TransactionLimit { spend: u64, limit: u64 },
+    /// The transaction would bring the gates spent over the last 24 hours above the daily limit
+    DailyLimit { spend: u64, spent: u64, limit: u64 },
+    /// The recipient of the transfer is not allowed
+    RecipientNotAllowed { recipient: String },
+    /// The program of the transaction is not allowed
+    ProgramNotAllowed { program: String },
+    /// The approval callback refused the transaction
+    ApprovalDenied { spend: u64, threshold: u64 },
+    /// The network confirmation hook refused the transfer
+    NetworkNotConfirmed { recipient: String, chain: String },
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TransactionLimit { spend, limit } => {
+                write!(f, "The transaction spends {spend} gates, over the limit of {limit} gates per transaction")
+            }
+            Self::DailyLimit { spend, spent, limit } => write!(
+                f,
+                "The transaction spends {spend} gates, but {spent} of the daily limit of {limit} gates were spent \
+                 over the last 24 hours"
+            ),
+            Self::RecipientNotAllowed { recipient } => {
+                write!(f, "The recipient {recipient} is not in the allowed recipients")
+            }
+            Self::ProgramNotAllowed { program } => write!(f, "The program {program} is not in the allowed programs"),
+            Self::ApprovalDenied { spend, threshold } => write!(
+                f,
+                "The transaction spends {spend} gates, over the approval threshold of {threshold} gates, and was \
+                 not approved"
+            ),
+            Self::NetworkNotConfirmed { recipient, chain } => {
+                write!(f, "The transfer to {recipient} on the '{chain}' network was not confirmed")
+            }
+        }
+    }
+}
+
+impl Error for PolicyViolation {}
+
+impl<N: Network> ProgramManager<N> {
+    /// Check the transactions built by the program manager against the given policy.
+    pub fn with_spending_policy(mut self, spending_policy: SpendingPolicy<N>) -> Self {
+        self.spending_policy = Some(spending_policy);
+        self
+    }
+
+    /// Returns the policy the transactions built by the program manager are checked against, if it was set.
+    pub fn spending_policy(&self) -> Option<&SpendingPolicy<N>> {
+        self.spending_policy.as_ref()
+    }
+
+    // Set the spending policy of a program manager owned by a wallet
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn set_spending_policy(&mut self, spending_policy: SpendingPolicy<N>) {
+        self.spending_policy = Some(spending_policy);
+    }
+
+    /// Check a transaction against the spending policy, counting the spends recorded in the record store.
+    ///
+    /// Transactions are allowed when no policy is set. The transactions built by the program manager are
+    /// checked before they are proven, and transfers the policy allows are then confirmed on the chain of the API
+    /// client, see [`SpendingPolicy::confirm_network`].
+    pub fn check_spending_policy(&self, transaction: &PendingTransaction<N>) -> Result<(), PolicyViolation> {
+        let policy = match &self.spending_policy {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+        let empty = SpendLog::new();
+        let spends = self.record_store.as_ref().map_or(&empty, |records| records.spend_log());
+        policy.check(transaction, spends, unix_time(self.api_client.clock()))?;
+        policy.confirm_network(self.api_client.chain(), transaction)
+    }
+
+    // Check a transaction against the spending policy, writing the decision to the event log if a policy is set and
+    // there is an event log, and fail with the `PolicyViolation` if the policy refuses the transaction
+    pub(crate) fn enforce_spending_policy(&self, transaction: &PendingTransaction<N>) -> Result<()> {
+        let decision = self.check_spending_policy(transaction);
+        if self.spending_policy.is_some() {
+            self.log_event(WalletEvent::PolicyDecision {
+                program: transaction.program,
+                function: transaction.function,
+                recipient: transaction.recipient,
+                amount: transaction.amount,
+                fee: transaction.fee,
+                violation: decision.as_ref().err().map(|violation| violation.to_string()),
+            })?;
+        }
+        Ok(decision?)
+    }
+
+    /// Count the gates spent by a broadcast transaction towards the daily limit of the spending policy, starting
+    /// an empty record store if none was set.
+    ///
+    /// The program manager cannot tell when a transaction it built is broadcast, so callers broadcasting its
+    /// transactions record them here, then save the record store for the count to survive a restart.
+    pub fn record_spend(&mut self, transaction: &PendingTransaction<N>) {
+        let records = self.record_store.get_or_insert_with(crate::RecordStore::new);
+        records.spend_log_mut().record(unix_time(self.api_client.clock()), transaction.spend());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_record, CurrentNetwork},
+        testnet3,
+        AleoAPIClient,
+        Json,
+        Microcredits,
+        Persist,
+        RecordStore,
+        SPEND_WINDOW_SECS,
+    };
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_utilities::TestRng;
+
+    use std::sync::Mutex;
+
+    type N = CurrentNetwork;
+
+    // Sample a program manager with an empty record store, and an address
+    fn sample_manager(rng: &mut TestRng) -> (ProgramManager<N>, Address<N>) {
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let program_manager =
+            ProgramManager::new(private_key, testnet3("http://127.0.0.1:9")).with_record_store(RecordStore::new());
+        (program_manager, Address::try_from(private_key).unwrap())
+    }
+
+    #[test]
+    fn test_spending_policy_exhausts_daily_limit() {
+        let rng = &mut TestRng::default();
+        let (program_manager, _) = sample_manager(rng);
+        let recipient = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let policy = SpendingPolicy::new().with_transaction_limit(150).with_daily_limit(250);
+        let mut program_manager = program_manager.with_spending_policy(policy.clone());
+
+        // Two transfers of 100 gates and a fee of 1 fit the daily limit, but a third does not.
+        let transfer = PendingTransaction::transfer(recipient, 100, 1).unwrap();
+        for _ in 0..2 {
+            program_manager.check_spending_policy(&transfer).unwrap();
+            program_manager.record_spend(&transfer);
+        }
+        let violation = program_manager.check_spending_policy(&transfer).unwrap_err();
+        assert_eq!(violation, PolicyViolation::DailyLimit { spend: 101, spent: 202, limit: 250 });
+        assert_eq!(
+            violation.to_string(),
+            "The transaction spends 101 gates, but 202 of the daily limit of 250 gates were spent over the last 24 hours"
+        );
+        let smaller = PendingTransaction::transfer(recipient, 47, 1).unwrap();
+        program_manager.check_spending_policy(&smaller).unwrap();
+
+        // Transfers over the limit per transaction are refused whatever was spent.
+        let larger = PendingTransaction::transfer(recipient, 150, 1).unwrap();
+        let violation = program_manager.check_spending_policy(&larger).unwrap_err();
+        assert_eq!(violation, PolicyViolation::TransactionLimit { spend: 151, limit: 150 });
+
+        // The spends are kept by the record store, so a restarted program manager still counts them.
+        let bytes = program_manager.record_store().unwrap().encode(&Json).unwrap();
+        let records = RecordStore::<N>::decode(&bytes).unwrap();
+        assert_eq!(records.spend_log().spends().len(), 2);
+        let (restarted, _) = sample_manager(rng);
+        let restarted = restarted.with_record_store(records.clone()).with_spending_policy(policy.clone());
+        assert!(matches!(restarted.check_spending_policy(&transfer), Err(PolicyViolation::DailyLimit { .. })));
+
+        // Spends leave the limit a day after they were made.
+        let spent_at = records.spend_log().spends()[1].0;
+        assert!(policy.check(&transfer, records.spend_log(), spent_at + SPEND_WINDOW_SECS).is_ok());
+    }
+
+    #[test]
+    fn test_spending_policy_rejects_unlisted_recipients_and_programs() {
+        let rng = &mut TestRng::default();
+        let (program_manager, address) = sample_manager(rng);
+        let recipient = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let credits = ProgramID::from_str("credits.aleo").unwrap();
+        let policy = SpendingPolicy::new().allow_recipient(address).allow_program(credits);
+        let program_manager = program_manager.with_spending_policy(policy);
+        let (input_record, _) = sample_record(address, 100, rng);
+        let (fee_record, _) = sample_record(address, 10, rng);
+
+        // Transfers to other recipients fail before any proving work.
+        let error = program_manager
+            .build_transfer(Microcredits(10), Microcredits(1), recipient, input_record.clone(), fee_record.clone())
+            .unwrap_err();
+        let violation = error.downcast::<PolicyViolation>().unwrap();
+        assert_eq!(violation, PolicyViolation::RecipientNotAllowed { recipient: recipient.to_string() });
+        assert_eq!(violation.to_string(), format!("The recipient {recipient} is not in the allowed recipients"));
+        program_manager.check_spending_policy(&PendingTransaction::transfer(address, 10, 1).unwrap()).unwrap();
+
+        // Once a program is allowed, transfers are refused unless `credits.aleo` is allowed too.
+        let (program_manager, address) = sample_manager(rng);
+        let token = ProgramID::from_str("token.aleo").unwrap();
+        let program_manager = program_manager.with_spending_policy(SpendingPolicy::new().allow_program(token));
+        let error = program_manager
+            .build_transfer(Microcredits(10), Microcredits(1), address, input_record, fee_record)
+            .unwrap_err();
+        let violation = error.downcast::<PolicyViolation>().unwrap();
+        assert_eq!(violation, PolicyViolation::ProgramNotAllowed { program: "credits.aleo".to_string() });
+    }
+
+    #[test]
+    fn test_spending_policy_approval_veto() {
+        let rng = &mut TestRng::default();
+        let (program_manager, address) = sample_manager(rng);
+        let recipient = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let asked = Arc::new(Mutex::new(vec![]));
+        let seen = asked.clone();
+        let policy = SpendingPolicy::new().with_approval(50, move |transaction: &PendingTransaction<N>| {
+            seen.lock().unwrap().push(transaction.clone());
+            false
+        });
+        let program_manager = program_manager.with_spending_policy(policy);
+        let (input_record, _) = sample_record(address, 100, rng);
+        let (fee_record, _) = sample_record(address, 10, rng);
+
+        // Transfers up to the threshold are not shown to the callback.
+        program_manager.check_spending_policy(&PendingTransaction::transfer(recipient, 49, 1).unwrap()).unwrap();
+        assert!(asked.lock().unwrap().is_empty());
+
+        // The callback sees the transfer over the threshold, and its refusal fails the build.
+        let error = program_manager
+            .build_transfer(Microcredits(60), Microcredits(2), recipient, input_record, fee_record)
+            .unwrap_err();
+        let violation = error.downcast::<PolicyViolation>().unwrap();
+        assert_eq!(violation, PolicyViolation::ApprovalDenied { spend: 62, threshold: 50 });
+        assert_eq!(
+            violation.to_string(),
+            "The transaction spends 62 gates, over the approval threshold of 50 gates, and was not approved"
+        );
+        let asked = asked.lock().unwrap();
+        assert_eq!(asked.as_slice(), [PendingTransaction::transfer(recipient, 60, 2).unwrap()]);
+    }
+
+    #[test]
+    fn test_spending_policy_network_confirmation() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let address = Address::try_from(private_key).unwrap();
+        let recipient = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let asked = Arc::new(Mutex::new(vec![]));
+        let seen = asked.clone();
+        let policy = SpendingPolicy::new().with_network_confirmation(move |chain: &str, _: &PendingTransaction<N>| {
+            seen.lock().unwrap().push(chain.to_string());
+            chain == "testnet3"
+        });
+        let (input_record, _) = sample_record(address, 100, rng);
+        let (fee_record, _) = sample_record(address, 10, rng);
+
+        // The hook confirms transfers on the chain it expects.
+        let transfer = PendingTransaction::transfer(recipient, 10, 1).unwrap();
+        let program_manager = ProgramManager::new(private_key, testnet3("http://127.0.0.1:9"));
+        program_manager.with_spending_policy(policy.clone()).check_spending_policy(&transfer).unwrap();
+
+        // Its refusal on another chain fails the build before any proving work.
+        let program_manager = ProgramManager::new(private_key, AleoAPIClient::new("http://127.0.0.1:9", "private"))
+            .with_spending_policy(policy.clone());
+        let error = program_manager
+            .build_transfer(Microcredits(10), Microcredits(1), recipient, input_record, fee_record)
+            .unwrap_err();
+        let violation = error.downcast::<PolicyViolation>().unwrap();
+        assert_eq!(violation, PolicyViolation::NetworkNotConfirmed {
+            recipient: recipient.to_string(),
+            chain: "private".to_string()
+        });
+        assert_eq!(
+            violation.to_string(),
+            format!("The transfer to {recipient} on the 'private' network was not confirmed")
+        );
+        assert_eq!(asked.lock().unwrap().as_slice(), ["testnet3", "private"]);
+
+        // Executions without a recipient are not shown to the hook.
+        let execution = PendingTransaction { recipient: None, ..transfer };
+        program_manager.check_spending_policy(&execution).unwrap();
+        assert_eq!(asked.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,356 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+#[cfg(not(feature = "async"))]
+use super::PendingTransaction;
+#[cfg(not(feature = "async"))]
+use crate::Microcredits;
+
+use snarkvm_console::{
+    account::Address,
+    program::{Network, Plaintext, Record},
+};
+#[cfg(not(feature = "async"))]
+use snarkvm_console::{
+    account::ViewKey,
+    program::{Literal, Value},
+    types::U64,
+};
+#[cfg(not(feature = "async"))]
+use snarkvm_synthesizer::Transaction;
+
+#[cfg(not(feature = "async"))]
+use anyhow::anyhow;
+use anyhow::{bail, ensure, Result};
+use rand::{CryptoRng, Rng};
+#[cfg(not(feature = "async"))]
+use std::time::Duration;
+
+/// How a transfer funds its payment, as set by [`ProgramManager::with_privacy_strategy`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PrivacyStrategy {
+    /// Pay from the funding record, returning the remainder as change in the same transaction
+    #[default]
+    Direct,
+    /// Split the funding record into a record of the payment amount and `parts` change records of random sizes,
+    /// in one preparatory `credits.aleo/split` transaction per change record, then pay with the record of the
+    /// amount. Observers of the payment see neither the size of the funding record nor a change record tied
+    /// to the amount.
+    SplitBeforeSend { parts: usize },
+}
+
+impl PrivacyStrategy {
+    /// The most preparatory transactions a transfer may need
+    pub const MAX_PREPARATORY_TRANSACTIONS: usize = 4;
+    /// The fewest gates a change record is split into, so that splitting creates no dust
+    pub const MIN_CHANGE_GATES: u64 = 10_000;
+}
+
+/// A transaction of a [`TransferPlan`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlannedTransaction {
+    /// Split a record of `input` gates into a change record of `change` gates and a record holding the rest
+    Split { input: u64, change: u64, fee: u64 },
+    /// Send `amount` gates from a record of `input` gates to the recipient
+    Transfer { input: u64, amount: u64, fee: u64 },
+}
+
+impl PlannedTransaction {
+    /// Returns the fee of the transaction, in gates.
+    pub fn fee(&self) -> u64 {
+        match self {
+            Self::Split { fee, .. } | Self::Transfer { fee, .. } => *fee,
+        }
+    }
+}
+
+/// The transactions a transfer takes under a [`PrivacyStrategy`], returned by [`ProgramManager::plan_transfer`]
+/// to be approved before [`ProgramManager::execute_transfer_plan`] builds and broadcasts them
+///
+/// Every transaction pays its fee from the fee record, whose change pays the fee of the next transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferPlan<N: Network> {
+    recipient: Address<N>,
+    input_record: Record<N, Plaintext<N>>,
+    fee_record: Record<N, Plaintext<N>>,
+    transactions: Vec<PlannedTransaction>,
+}
+
+impl<N: Network> TransferPlan<N> {
+    // Plan a transfer of `amount` gates under the given strategy, drawing the sizes of the change records from
+    // the RNG
+    fn new<R: Rng + CryptoRng>(
+        strategy: PrivacyStrategy,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+        rng: &mut R,
+    ) -> Result<Self> {
+        ensure!(amount > 0, "Transfer amount must be greater than zero");
+        let funding = ***input_record.gates();
+        ensure!(funding >= amount, "Input record does not hold enough gates for the transfer");
+
+        let mut transactions = vec![];
+        let mut input = funding;
+        if let PrivacyStrategy::SplitBeforeSend { parts } = strategy {
+            let max = PrivacyStrategy::MAX_PREPARATORY_TRANSACTIONS;
+            if parts == 0 || parts > max {
+                bail!("A transfer may split its funding record into 1 to {max} change records, not {parts}");
+            }
+            let (change, min) = (funding - amount, PrivacyStrategy::MIN_CHANGE_GATES);
+            if change < parts as u64 * min {
+                bail!(
+                    "The {change} gates left over by the transfer cannot be split into {parts} change records of at \
+                     least {min} gates"
+                );
+            }
+            for change in random_parts(change, parts, min, rng) {
+                transactions.push(PlannedTransaction::Split { input, change, fee });
+                input -= change;
+            }
+        }
+        transactions.push(PlannedTransaction::Transfer { input, amount, fee });
+
+        let total_fees = transactions.len() as u64 * fee;
+        ensure!(
+            ***fee_record.gates() >= total_fees,
+            "Fee record does not hold enough gates to pay the fees of {} transactions, which total {total_fees} gates",
+            transactions.len()
+        );
+        Ok(Self { recipient, input_record, fee_record, transactions })
+    }
+
+    /// Returns the recipient of the payment.
+    pub fn recipient(&self) -> Address<N> {
+        self.recipient
+    }
+
+    /// Returns the gates sent to the recipient.
+    pub fn amount(&self) -> u64 {
+        match self.transactions.last() {
+            Some(PlannedTransaction::Transfer { amount, .. }) => *amount,
+            _ => unreachable!("A transfer plan ends with the transfer"),
+        }
+    }
+
+    /// Returns the transactions of the plan, in the order they are broadcast. The last one is the payment.
+    pub fn transactions(&self) -> &[PlannedTransaction] {
+        &self.transactions
+    }
+
+    /// Returns the preparatory transactions that split the funding record before the payment.
+    pub fn preparatory(&self) -> &[PlannedTransaction] {
+        &self.transactions[..self.transactions.len() - 1]
+    }
+
+    /// Returns the gates of the change records split from the funding record.
+    pub fn change(&self) -> Vec<u64> {
+        self.preparatory()
+            .iter()
+            .filter_map(|transaction| match transaction {
+                PlannedTransaction::Split { change, .. } => Some(*change),
+                PlannedTransaction::Transfer { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Returns the fees of all transactions, in gates.
+    pub fn total_fees(&self) -> u64 {
+        self.transactions.iter().map(PlannedTransaction::fee).sum()
+    }
+
+    /// Returns the fees paid on top of those of a direct transfer, in gates.
+    pub fn extra_fees(&self) -> u64 {
+        self.preparatory().iter().map(PlannedTransaction::fee).sum()
+    }
+}
+
+// Draw `parts` random sizes of at least `min` gates, which sum to `total` gates
+fn random_parts<R: Rng>(total: u64, parts: usize, min: u64, rng: &mut R) -> Vec<u64> {
+    // Cut the gates beyond the minimums at random points, and give each part the gates between two cuts.
+    let extra = total - parts as u64 * min;
+    let mut cuts = (1..parts).map(|_| rng.gen_range(0..=extra)).collect::<Vec<_>>();
+    cuts.sort_unstable();
+    let bounds = [0].into_iter().chain(cuts).chain([extra]).collect::<Vec<_>>();
+    bounds.windows(2).map(|bounds| min + bounds[1] - bounds[0]).collect()
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Set the strategy with which [`ProgramManager::plan_transfer`] funds payments.
+    pub fn with_privacy_strategy(mut self, privacy_strategy: PrivacyStrategy) -> Self {
+        self.privacy_strategy = privacy_strategy;
+        self
+    }
+
+    /// Returns the strategy with which payments are funded.
+    pub fn privacy_strategy(&self) -> PrivacyStrategy {
+        self.privacy_strategy
+    }
+
+    /// Plan a transfer of `amount` gates to the recipient under the privacy strategy of the program manager,
+    /// funded by the `input_record`, with the `fee_record` paying `fee` gates per transaction.
+    ///
+    /// Nothing is built or broadcast, so the plan and its [`TransferPlan::extra_fees`] can be approved first.
+    pub fn plan_transfer(
+        &self,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<TransferPlan<N>> {
+        let rng = &mut rand::thread_rng();
+        TransferPlan::new(self.privacy_strategy, amount, fee, recipient, input_record, fee_record, rng)
+    }
+
+    /// Build and broadcast the transactions of the plan in order, and return their IDs.
+    ///
+    /// Each preparatory transaction must be final before the next one spends its records, so it is awaited
+    /// with [`AleoAPIClient::wait_for_confirmation`](crate::AleoAPIClient::wait_for_confirmation) for at most
+    /// `timeout`. A failed execution leaves the change records split so far with the account.
+    #[cfg(not(feature = "async"))]
+    pub fn execute_transfer_plan(
+        &self,
+        plan: TransferPlan<N>,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<Vec<N::TransactionID>> {
+        let view_key = ViewKey::try_from(self.signer()?)?;
+        // Check the payment against the spending policy before splitting any record for it.
+        let fee = plan.transactions().last().map_or(0, PlannedTransaction::fee);
+        self.enforce_spending_policy(&PendingTransaction::transfer(plan.recipient, plan.amount(), fee)?)?;
+        let TransferPlan { recipient, input_record, fee_record, transactions } = plan;
+        let (mut record, mut fee_record, mut transaction_ids) = (input_record, fee_record, vec![]);
+        for transaction in transactions {
+            match transaction {
+                PlannedTransaction::Split { change, fee, .. } => {
+                    let transaction = self.build_split(change, fee, record, fee_record)?;
+                    // The split outputs the change record and the rest, then the fee outputs its change.
+                    let outputs = transaction.transitions().flat_map(|transition| transition.records());
+                    let outputs = outputs.map(|(_, record)| record.decrypt(&view_key)).collect::<Result<Vec<_>>>()?;
+                    let [_, rest, fee_change] = <[_; 3]>::try_from(outputs)
+                        .map_err(|_| anyhow!("The split transaction does not output three records"))?;
+                    (record, fee_record) = (rest, fee_change);
+                    let transaction_id = transaction.id();
+                    self.broadcast(transaction)?;
+                    self.api_client.wait_for_confirmation(transaction_id, timeout, poll_interval, |_| ())?;
+                    transaction_ids.push(transaction_id);
+                }
+                PlannedTransaction::Transfer { amount, fee, .. } => {
+                    let (amount, fee) = (Microcredits(amount), Microcredits(fee));
+                    transaction_ids.push(self.transfer(amount, fee, recipient, record.clone(), fee_record.clone())?);
+                }
+            }
+        }
+        Ok(transaction_ids)
+    }
+
+    // Build a `credits.aleo/split` transaction splitting a record of `change` gates from the input record
+    #[cfg(not(feature = "async"))]
+    fn build_split(
+        &self,
+        change: u64,
+        fee: u64,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<Transaction<N>> {
+        let private_key = self.signer()?;
+        let _lease = self.lock_records(&[&input_record, &fee_record])?;
+        let inputs =
+            vec![Value::Record(input_record), Value::Plaintext(Plaintext::from(Literal::U64(U64::new(change))))];
+        let rng = &mut rand::thread_rng();
+        let vm = self.vm()?;
+        let authorization = vm.authorize(&private_key, "credits.aleo", "split", inputs, rng)?;
+        let execution = self.prove_execution(&vm, authorization)?;
+        let fee = self.prove_fee(&vm, fee_record, fee)?;
+        self.log_built(Transaction::from_execution(execution, Some(fee))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{sample_record, CurrentNetwork};
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    // Plan a transfer of `amount` gates from a record of `funding` gates, paying fees from a record of `fees` gates
+    fn plan(strategy: PrivacyStrategy, amount: u64, funding: u64, fees: u64, seed: u64) -> Result<TransferPlan<N>> {
+        let rng = &mut TestRng::fixed(seed);
+        let address = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let (input_record, _) = sample_record(address, funding, rng);
+        let (fee_record, _) = sample_record(address, fees, rng);
+        TransferPlan::new(strategy, amount, 100, address, input_record, fee_record, &mut TestRng::fixed(seed))
+    }
+
+    #[test]
+    fn test_plan_transfer() {
+        // A direct transfer is a single transaction.
+        let direct = plan(PrivacyStrategy::Direct, 500_000, 1_000_000, 100, 1).unwrap();
+        assert_eq!(direct.transactions(), [PlannedTransaction::Transfer {
+            input: 1_000_000,
+            amount: 500_000,
+            fee: 100
+        }]);
+        assert_eq!((direct.total_fees(), direct.extra_fees()), (100, 0));
+
+        // Splitting conserves the gates of the funding record, and pays a fee per preparatory transaction.
+        let strategy = PrivacyStrategy::SplitBeforeSend { parts: 3 };
+        let split = plan(strategy, 500_000, 1_000_000, 400, 1).unwrap();
+        assert_eq!(split.preparatory().len(), 3);
+        assert_eq!(split.change().iter().sum::<u64>() + split.amount(), 1_000_000);
+        assert_eq!((split.total_fees(), split.extra_fees()), (400, 300));
+        assert!(split.change().iter().all(|change| *change >= PrivacyStrategy::MIN_CHANGE_GATES));
+        let mut input = 1_000_000;
+        for transaction in split.preparatory() {
+            let PlannedTransaction::Split { input: split_input, change, .. } = *transaction else { unreachable!() };
+            assert_eq!(split_input, input);
+            input -= change;
+        }
+        assert_eq!(
+            split.transactions().last(),
+            Some(&PlannedTransaction::Transfer { input, amount: 500_000, fee: 100 })
+        );
+
+        // The change sizes are random, but follow the seed.
+        assert_eq!(plan(strategy, 500_000, 1_000_000, 400, 1).unwrap(), split);
+        assert_ne!(plan(strategy, 500_000, 1_000_000, 400, 2).unwrap().change(), split.change());
+
+        // The change must be large enough for every part, which is all it holds when just large enough.
+        let exact = plan(strategy, 500_000, 530_000, 400, 1).unwrap();
+        assert_eq!(exact.change(), [10_000, 10_000, 10_000]);
+        let error = plan(strategy, 500_000, 529_999, 400, 1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The 29999 gates left over by the transfer cannot be split into 3 change records of at least 10000 gates"
+        );
+
+        // The number of preparatory transactions is capped, and the fee record must cover every fee.
+        let error = plan(PrivacyStrategy::SplitBeforeSend { parts: 5 }, 500_000, 1_000_000, 600, 1).unwrap_err();
+        assert_eq!(error.to_string(), "A transfer may split its funding record into 1 to 4 change records, not 5");
+        assert!(plan(PrivacyStrategy::SplitBeforeSend { parts: 0 }, 500_000, 1_000_000, 600, 1).is_err());
+        let error = plan(strategy, 500_000, 1_000_000, 399, 1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Fee record does not hold enough gates to pay the fees of 4 transactions, which total 400 gates"
+        );
+    }
+}
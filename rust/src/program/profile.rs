@@ -0,0 +1,325 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{key_cache::fresh_vm, CircuitMetrics, ProgramManager};
+
+use snarkvm_console::program::{Identifier, Network, Value};
+use snarkvm_synthesizer::{
+    program::finalize::{Command, Finalize},
+    CallOperator,
+    Instruction,
+    Program,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+/// The circuits built by a function call, returned by [`ProgramManager::profile_function`]
+///
+/// The report lists the function and every function it calls, in the order they are called, with the size of the
+/// circuit of each. Closures are part of the circuit of the function calling them, so they are listed under it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileReport {
+    function: String,
+    calls: Vec<CallProfile>,
+}
+
+impl ProfileReport {
+    /// Returns the profiled function, as `program/function`.
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// Returns the profiles of the profiled function and of the functions it calls, in the order they are called.
+    pub fn calls(&self) -> &[CallProfile] {
+        &self.calls
+    }
+
+    /// Returns the constraints of every circuit of the call.
+    pub fn constraints(&self) -> u64 {
+        self.calls.iter().map(|call| call.metrics.constraints).sum()
+    }
+
+    /// Returns the variables of every circuit of the call.
+    pub fn variables(&self) -> u64 {
+        self.calls.iter().map(|call| call.metrics.variables).sum()
+    }
+
+    /// Returns the commands of every finalize scope of the call.
+    pub fn finalize_commands(&self) -> usize {
+        self.calls.iter().filter_map(|call| call.finalize.as_ref()).map(FinalizeProfile::commands).sum()
+    }
+}
+
+impl fmt::Display for ProfileReport {
+    /// Prints the report as a table with a row per function, closure and finalize scope, and a row of totals.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Profile of {}", self.function)?;
+        writeln!(f, "{:<40} {:>12} {:>12} {:>12}", "Call", "Instructions", "Constraints", "Variables")?;
+        for call in &self.calls {
+            let metrics = call.metrics;
+            writeln!(
+                f,
+                "{:<40} {:>12} {:>12} {:>12}",
+                call.function, call.instructions, metrics.constraints, metrics.variables
+            )?;
+            for closure in &call.closures {
+                writeln!(f, "{:<40} {:>12}", format!("  closure {}", closure.name), closure.instructions)?;
+            }
+            if let Some(finalize) = &call.finalize {
+                writeln!(f, "{:<40} {:>12}", format!("  finalize ({finalize})"), finalize.commands())?;
+            }
+        }
+        write!(f, "{:<40} {:>12} {:>12} {:>12}", "Total", "", self.constraints(), self.variables())
+    }
+}
+
+/// The circuit of a function of a [`ProfileReport`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallProfile {
+    function: String,
+    instructions: usize,
+    closures: Vec<ClosureProfile>,
+    metrics: CircuitMetrics,
+    finalize: Option<FinalizeProfile>,
+}
+
+impl CallProfile {
+    // Profile a function of the program, whose circuit has the given size
+    fn new<N: Network>(program: &Program<N>, function_name: &Identifier<N>, metrics: CircuitMetrics) -> Result<Self> {
+        let function = program.get_function(function_name)?;
+        Ok(Self {
+            function: format!("{}/{function_name}", program.id()),
+            instructions: function.instructions().len(),
+            closures: closures_called(program, function.instructions()),
+            metrics,
+            finalize: function.finalize().map(|(_, finalize)| FinalizeProfile::new(finalize)),
+        })
+    }
+
+    /// Returns the function, as `program/function`.
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// Returns the number of instructions of the function, without those of the closures it calls.
+    pub fn instructions(&self) -> usize {
+        self.instructions
+    }
+
+    /// Returns the closures called by the function, directly or through other closures, in the order they are
+    /// called.
+    pub fn closures(&self) -> &[ClosureProfile] {
+        &self.closures
+    }
+
+    /// Returns the size of the circuit of the function, closures included.
+    pub fn metrics(&self) -> CircuitMetrics {
+        self.metrics
+    }
+
+    /// Returns the commands of the finalize scope of the function, if it has one.
+    pub fn finalize(&self) -> Option<&FinalizeProfile> {
+        self.finalize.as_ref()
+    }
+}
+
+/// A call to a closure, whose instructions are part of the circuit of the calling function
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClosureProfile {
+    /// The name of the closure
+    pub name: String,
+    /// The number of instructions of the closure
+    pub instructions: usize,
+}
+
+/// The commands of a finalize scope, which run on the nodes rather than in a circuit
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalizeProfile {
+    // The number of commands of each kind, named by their opcode
+    commands: BTreeMap<String, usize>,
+}
+
+impl FinalizeProfile {
+    // Count the commands of a finalize scope by kind
+    fn new<N: Network>(finalize: &Finalize<N>) -> Self {
+        let mut commands = BTreeMap::new();
+        for command in finalize.commands() {
+            let kind = match command {
+                Command::Increment(_) => "increment".to_string(),
+                Command::Decrement(_) => "decrement".to_string(),
+                Command::Instruction(instruction) => instruction.opcode().to_string(),
+            };
+            *commands.entry(kind).or_default() += 1;
+        }
+        Self { commands }
+    }
+
+    /// Returns the number of commands.
+    pub fn commands(&self) -> usize {
+        self.commands.values().sum()
+    }
+
+    /// Returns the number of commands of each kind, by opcode.
+    pub fn commands_by_kind(&self) -> &BTreeMap<String, usize> {
+        &self.commands
+    }
+}
+
+impl fmt::Display for FinalizeProfile {
+    /// Prints the number of commands of each kind, e.g. `add 1, increment 2`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kinds = self.commands.iter().map(|(kind, count)| format!("{kind} {count}")).collect::<Vec<_>>();
+        write!(f, "{}", kinds.join(", "))
+    }
+}
+
+// Returns the closures called by the instructions, and by the closures they call, in the order they are called
+fn closures_called<N: Network>(program: &Program<N>, instructions: &[Instruction<N>]) -> Vec<ClosureProfile> {
+    let mut closures = vec![];
+    for instruction in instructions {
+        if let Instruction::Call(call) = instruction {
+            if let CallOperator::Resource(name) = call.operator() {
+                if let Ok(closure) = program.get_closure(name) {
+                    let instructions = closure.instructions().len();
+                    closures.push(ClosureProfile { name: name.to_string(), instructions });
+                    closures.extend(closures_called(program, closure.instructions()));
+                }
+            }
+        }
+    }
+    closures
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Profile the circuits built by calling a function of the given program with the given inputs.
+    ///
+    /// The `imports` are the programs imported by `program`, in the order expected by
+    /// [`ProgramManager::build_execution`]. The call is authorized to find the functions it calls, then the
+    /// circuit keys of each are synthesized, as they would be before proving, and the sizes of their circuits are
+    /// read from their verifying keys. Nothing is proven, but synthesizing a function takes about as long as
+    /// proving it the first time, and is reported to the [`crate::ProgressReporter`] of the program manager.
+    pub fn profile_function(
+        &self,
+        program: &Program<N>,
+        imports: &[Program<N>],
+        function_name: Identifier<N>,
+        inputs: Vec<Value<N>>,
+    ) -> Result<ProfileReport> {
+        let private_key = self.signer()?;
+        // The keys are synthesized by a VM of its own, so that they are profiled even if the manager built with them.
+        let vm = fresh_vm(&imports.iter().chain([program]).collect::<Vec<_>>())?;
+
+        let authorization = vm.authorize(&private_key, program.id(), function_name, inputs, &mut rand::thread_rng())?;
+        let mut calls = vec![];
+        for request in authorization.to_vec_deque() {
+            let (program_id, function_name) = (request.program_id(), request.function_name());
+            self.prepare_proof(&vm, program_id, function_name)?;
+            let process = vm.process();
+            let process = process.read();
+            let metrics = CircuitMetrics::from_verifying_key(&process.get_verifying_key(program_id, function_name)?);
+            calls.push(CallProfile::new(process.get_program(program_id)?, function_name, metrics)?);
+        }
+        Ok(ProfileReport { function: format!("{}/{function_name}", program.id()), calls })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helpers::CurrentNetwork, testnet3};
+    use std::str::FromStr;
+
+    type N = CurrentNetwork;
+
+    const COUNTER_PROGRAM: &str = r"program profile_counter.aleo;
+
+mapping counts:
+    key left as u8.public;
+    value right as u64.public;
+
+closure squared:
+    input r0 as u64;
+    mul r0 r0 into r1;
+    output r1 as u64;
+
+closure squared_plus_one:
+    input r0 as u64;
+    call squared r0 into r1;
+    add r1 1u64 into r2;
+    output r2 as u64;
+
+function bump:
+    input r0 as u64.public;
+    call squared_plus_one r0 into r1;
+    finalize r1;
+
+finalize bump:
+    input r0 as u64.public;
+    add r0 r0 into r1;
+    increment counts[0u8] by r1;
+";
+
+    const PROFILE_TABLE: &str = "Profile of profile_counter.aleo/bump
+Call                                     Instructions  Constraints    Variables
+profile_counter.aleo/bump                           1        21000        20500
+  closure squared_plus_one                          2
+  closure squared                                   1
+  finalize (add 1, increment 1)                     2
+credits.aleo/transfer                               3        40000        39000
+Total                                                        61000        59500";
+
+    #[test]
+    fn test_profile_report() {
+        let program = Program::<N>::from_str(COUNTER_PROGRAM).unwrap();
+        let bump = Identifier::from_str("bump").unwrap();
+        let metrics = CircuitMetrics { constraints: 21_000, variables: 20_500, non_zero_entries: 64_000 };
+        let call = CallProfile::new(&program, &bump, metrics).unwrap();
+
+        // Closures are listed under the function, in the order they are called, and finalize commands by kind.
+        assert_eq!((call.function(), call.instructions(), call.metrics()), ("profile_counter.aleo/bump", 1, metrics));
+        let closures = call.closures().iter().map(|closure| (closure.name.as_str(), closure.instructions));
+        assert_eq!(closures.collect::<Vec<_>>(), [("squared_plus_one", 2), ("squared", 1)]);
+        let finalize = call.finalize().unwrap();
+        assert_eq!(finalize.commands(), 2);
+        assert_eq!(finalize.to_string(), "add 1, increment 1");
+
+        // Functions in other programs are calls of their own, and count towards the totals.
+        let transfer = Identifier::<N>::from_str("transfer").unwrap();
+        let credits = CircuitMetrics { constraints: 40_000, variables: 39_000, non_zero_entries: 120_000 };
+        let credits = CallProfile::new(&Program::credits().unwrap(), &transfer, credits).unwrap();
+        assert!(credits.closures().is_empty() && credits.finalize().is_none());
+        let report = ProfileReport { function: call.function().to_string(), calls: vec![call, credits] };
+        assert_eq!((report.constraints(), report.variables(), report.finalize_commands()), (61_000, 59_500, 2));
+
+        // The report is printed as a table, and survives a round trip through JSON.
+        assert_eq!(report.to_string(), PROFILE_TABLE);
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<ProfileReport>(&json).unwrap(), report);
+    }
+
+    #[test]
+    fn test_profile_function_watch_only() {
+        let program = Program::<N>::from_str(COUNTER_PROGRAM).unwrap();
+        let program_manager = ProgramManager::<N>::watch_only(testnet3("http://127.0.0.1:9"));
+        let bump = Identifier::from_str("bump").unwrap();
+
+        // Profiling authorizes the call, so watch-only program managers cannot profile.
+        let error = program_manager.profile_function(&program, &[], bump, vec![]).unwrap_err();
+        assert!(error.is::<crate::SigningUnavailable>());
+    }
+}
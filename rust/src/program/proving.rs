@@ -0,0 +1,455 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{FeeDeltaKind, ProgramManager};
+#[cfg(not(feature = "async"))]
+use super::{ExecutionJobId, ExecutionJobState};
+
+use snarkvm_circuit::AleoV0;
+use snarkvm_console::{
+    network::Testnet3,
+    program::{Identifier, Network, Plaintext, ProgramID, Record},
+};
+use snarkvm_synthesizer::{Authorization, ConsensusMemory, Execution, Fee, Process, VerifyingKey, VM};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    any::Any,
+    error::Error,
+    fmt,
+    str::FromStr,
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The size in bytes of a field element of the proving system
+const FIELD_ELEMENT_SIZE: u64 = 32;
+/// The number of polynomials the prover holds over the domain of the constraints
+const CONSTRAINT_DOMAIN_POLYNOMIALS: u64 = 16;
+/// The number of polynomials the prover holds over the domain of the non-zero entries, for each matrix
+const MATRIX_DOMAIN_POLYNOMIALS: u64 = 12;
+
+/// Limits on the proofs built by a [`ProgramManager`]
+///
+/// By default proofs have no timeout and no memory cap.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProvingLimits {
+    timeout: Option<Duration>,
+    memory_cap: Option<u64>,
+    low_memory: bool,
+}
+
+impl ProvingLimits {
+    /// Create limits without a timeout or a memory cap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail proofs that do not finish within the timeout with [`ProvingTimeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Flag proofs estimated to need more than `memory_cap` bytes of memory to the progress reporter.
+    pub fn with_memory_cap(mut self, memory_cap: u64) -> Self {
+        self.memory_cap = Some(memory_cap);
+        self
+    }
+
+    /// Refuse proofs estimated to need more memory than the memory cap with [`MemoryCapExceeded`], instead of
+    /// only flagging them.
+    pub fn with_low_memory(mut self, low_memory: bool) -> Self {
+        self.low_memory = low_memory;
+        self
+    }
+
+    /// Returns the timeout of each proof, if there is one.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Returns the memory cap in bytes, if there is one.
+    pub fn memory_cap(&self) -> Option<u64> {
+        self.memory_cap
+    }
+
+    /// Returns `true` if proofs over the memory cap are refused.
+    pub fn low_memory(&self) -> bool {
+        self.low_memory
+    }
+}
+
+/// The size of the circuit of a function, as recorded in its verifying key
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitMetrics {
+    /// The number of constraints
+    pub constraints: u64,
+    /// The number of variables
+    pub variables: u64,
+    /// The largest number of non-zero entries of the three constraint matrices
+    pub non_zero_entries: u64,
+}
+
+impl CircuitMetrics {
+    /// Read the size of a circuit from its verifying key.
+    pub fn from_verifying_key<N: Network>(verifying_key: &VerifyingKey<N>) -> Self {
+        let info = &verifying_key.circuit_info;
+        Self {
+            constraints: info.num_constraints as u64,
+            variables: info.num_variables as u64,
+            non_zero_entries: info.num_non_zero_a.max(info.num_non_zero_b).max(info.num_non_zero_c) as u64,
+        }
+    }
+
+    /// Returns a rough estimate of the memory needed to prove the circuit, in bytes.
+    ///
+    /// The prover holds a number of polynomials over the domain of the constraints and over the domain of the
+    /// non-zero entries of each matrix, both rounded up to a power of two. The estimate is within a small
+    /// factor of the peak use, and grows with the circuit as the peak does.
+    pub fn estimated_memory(&self) -> u64 {
+        let constraint_domain = self.constraints.max(self.variables).next_power_of_two();
+        let matrix_domain = self.non_zero_entries.next_power_of_two();
+        FIELD_ELEMENT_SIZE
+            * (CONSTRAINT_DOMAIN_POLYNOMIALS * constraint_domain + 3 * MATRIX_DOMAIN_POLYNOMIALS * matrix_domain)
+    }
+}
+
+/// A step in building the proofs of a transaction, named by the `program/function` being proven, or the audit of
+/// the fee charged for it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProvingEvent {
+    /// The circuit keys of a function are being synthesized, as it was not proven before
+    Synthesizing { function: String },
+    /// The memory needed to prove a function was estimated, flagging estimates over the memory cap
+    Estimated { function: String, metrics: CircuitMetrics, bytes: u64, exceeds_cap: bool },
+    /// A function is being proven, along with the functions it calls
+    Proving { function: String },
+    /// A function was proven
+    Proved { function: String, elapsed: Duration },
+    /// The fee charged for a confirmed transaction differs from its estimate by more than the tolerance of the
+    /// [`crate::FeeAudit`] of the program manager
+    FeeDeviation { transaction_id: String, estimated: i64, actual: i64, kind: FeeDeltaKind },
+    /// A job of an [`crate::ExecutionPool`] changed state, the steps of its proofs being reported between its
+    /// `Proving` state and its final state
+    #[cfg(not(feature = "async"))]
+    Job { job: ExecutionJobId, state: ExecutionJobState },
+}
+
+/// Receives the progress of the proofs built by a [`ProgramManager`], e.g. to show activity in a UI
+///
+/// Closures taking a [`ProvingEvent`] are reporters.
+pub trait ProgressReporter: Send + Sync {
+    /// Called on the thread building the transaction at each step of its proofs.
+    fn report(&self, event: ProvingEvent);
+}
+
+impl<F: Fn(ProvingEvent) + Send + Sync> ProgressReporter for F {
+    fn report(&self, event: ProvingEvent) {
+        self(event)
+    }
+}
+
+/// The error returned when a proof did not finish within the proving timeout
+///
+/// Proofs cannot be interrupted, so the proof finishes on a background thread and its result is discarded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProvingTimeout {
+    function: String,
+    timeout: Duration,
+}
+
+impl ProvingTimeout {
+    /// Returns the `program/function` whose proof timed out.
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// Returns the timeout the proof exceeded.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl fmt::Display for ProvingTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Proving {} did not finish within {:?}", self.function, self.timeout)
+    }
+}
+
+impl Error for ProvingTimeout {}
+
+/// The error returned in low-memory mode when a proof is estimated to need more memory than the cap
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryCapExceeded {
+    function: String,
+    estimated: u64,
+    cap: u64,
+}
+
+impl MemoryCapExceeded {
+    /// Returns the `program/function` whose proof was refused.
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// Returns the estimated memory of the proof in bytes.
+    pub fn estimated(&self) -> u64 {
+        self.estimated
+    }
+
+    /// Returns the memory cap in bytes.
+    pub fn cap(&self) -> u64 {
+        self.cap
+    }
+}
+
+impl fmt::Display for MemoryCapExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Proving {} is estimated to need {} bytes of memory, over the cap of {} bytes",
+            self.function, self.estimated, self.cap
+        )
+    }
+}
+
+impl Error for MemoryCapExceeded {}
+
+impl<N: Network> ProgramManager<N> {
+    /// Set the limits on the proofs built by the program manager.
+    pub fn with_proving_limits(mut self, proving_limits: ProvingLimits) -> Self {
+        self.proving_limits = proving_limits;
+        self
+    }
+
+    /// Returns the limits on the proofs built by the program manager.
+    pub fn proving_limits(&self) -> ProvingLimits {
+        self.proving_limits
+    }
+
+    /// Report the steps of the proofs built by the program manager to the given reporter.
+    pub fn with_progress_reporter(mut self, reporter: impl ProgressReporter + 'static) -> Self {
+        self.progress_reporter = Some(Arc::new(reporter));
+        self
+    }
+
+    // Report a step of a proof, if there is a progress reporter
+    pub(crate) fn report(&self, event: ProvingEvent) {
+        if let Some(reporter) = &self.progress_reporter {
+            reporter.report(event);
+        }
+    }
+
+    // Synthesize the circuit keys of a function if the VM has none, then check its estimated memory against the cap
+    pub(crate) fn prepare_proof(
+        &self,
+        vm: &VM<N, ConsensusMemory<N>>,
+        program_id: &ProgramID<N>,
+        function_name: &Identifier<N>,
+    ) -> Result<()> {
+        let function = format!("{program_id}/{function_name}");
+        let process = vm.process();
+        let process = process.read();
+        if !process.get_stack(program_id)?.contains_verifying_key(function_name) {
+            self.report(ProvingEvent::Synthesizing { function: function.clone() });
+            synthesize_key(&process, program_id, function_name)?;
+        }
+        let metrics = CircuitMetrics::from_verifying_key(&process.get_verifying_key(program_id, function_name)?);
+        self.check_memory(function, metrics)
+    }
+
+    // Report the estimated memory of a proof, failing in low-memory mode if it exceeds the cap
+    pub(crate) fn check_memory(&self, function: String, metrics: CircuitMetrics) -> Result<()> {
+        let bytes = metrics.estimated_memory();
+        let cap = self.proving_limits.memory_cap;
+        let exceeds_cap = cap.map_or(false, |cap| bytes > cap);
+        self.report(ProvingEvent::Estimated { function: function.clone(), metrics, bytes, exceeds_cap });
+        match cap {
+            Some(cap) if exceeds_cap && self.proving_limits.low_memory => {
+                Err(MemoryCapExceeded { function, estimated: bytes, cap }.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Prove an authorized execution within the proving limits, preparing each function it calls
+    pub(crate) fn prove_execution(
+        &self,
+        vm: &VM<N, ConsensusMemory<N>>,
+        authorization: Authorization<N>,
+    ) -> Result<Execution<N>> {
+        for request in authorization.to_vec_deque() {
+            self.prepare_proof(vm, request.program_id(), request.function_name())?;
+        }
+        let request = authorization.peek_next()?;
+        let function = format!("{}/{}", request.program_id(), request.function_name());
+        let (vm, query) = (vm.clone(), self.query());
+        self.prove(function, move || {
+            let (_, execution, _) = vm.execute(authorization, Some(query), &mut rand::thread_rng())?;
+            Ok(execution)
+        })
+    }
+
+    // Prove the fee of a transaction within the proving limits
+    pub(crate) fn prove_fee(
+        &self,
+        vm: &VM<N, ConsensusMemory<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+        fee: u64,
+    ) -> Result<Fee<N>> {
+        let (program_id, function_name) = (ProgramID::from_str("credits.aleo")?, Identifier::from_str("fee")?);
+        self.prepare_proof(vm, &program_id, &function_name)?;
+        let (vm, query, private_key) = (vm.clone(), self.query(), self.signer()?);
+        self.prove(format!("{program_id}/{function_name}"), move || {
+            let (_, fee, _) = vm.execute_fee(&private_key, fee_record, fee, Some(query), &mut rand::thread_rng())?;
+            Ok(fee)
+        })
+    }
+
+    // Run a proof, failing with `ProvingTimeout` if it does not finish within the proving timeout. Timed proofs run
+    // on their own thread, which is left to finish in the background when the timeout passes.
+    pub(crate) fn prove<T: Send + 'static>(
+        &self,
+        function: String,
+        prove: impl FnOnce() -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        self.report(ProvingEvent::Proving { function: function.clone() });
+        let start = Instant::now();
+        let result = match self.proving_limits.timeout {
+            Some(timeout) => {
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || sender.send(prove()));
+                match receiver.recv_timeout(timeout) {
+                    Ok(result) => result,
+                    Err(mpsc::RecvTimeoutError::Timeout) => return Err(ProvingTimeout { function, timeout }.into()),
+                    Err(mpsc::RecvTimeoutError::Disconnected) => Err(anyhow!("Proving {function} panicked")),
+                }
+            }
+            None => prove(),
+        }?;
+        self.report(ProvingEvent::Proved { function, elapsed: start.elapsed() });
+        Ok(result)
+    }
+}
+
+// Synthesize the circuit keys of a function. As in the VM, only Testnet3 has a circuit to synthesize with.
+pub(crate) fn synthesize_key<N: Network>(
+    process: &Process<N>,
+    program_id: &ProgramID<N>,
+    function_name: &Identifier<N>,
+) -> Result<()> {
+    match N::ID {
+        Testnet3::ID => {
+            let process = (process as &dyn Any).downcast_ref::<Process<Testnet3>>();
+            let program_id = (program_id as &dyn Any).downcast_ref::<ProgramID<Testnet3>>();
+            let function_name = (function_name as &dyn Any).downcast_ref::<Identifier<Testnet3>>();
+            match (process, program_id, function_name) {
+                (Some(process), Some(program_id), Some(function_name)) => {
+                    process.synthesize_key::<AleoV0, _>(program_id, function_name, &mut rand::thread_rng())
+                }
+                _ => bail!("Failed to downcast the process to Testnet3"),
+            }
+        }
+        _ => bail!("Unsupported VM configuration for network: {}", N::ID),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::CurrentNetwork;
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_utilities::TestRng;
+
+    use std::sync::Mutex;
+
+    // Returns a program manager with the given limits, and the events it reports
+    fn sample_program_manager(
+        limits: ProvingLimits,
+    ) -> (ProgramManager<CurrentNetwork>, Arc<Mutex<Vec<ProvingEvent>>>) {
+        let private_key = PrivateKey::<CurrentNetwork>::new(&mut TestRng::default()).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reported = events.clone();
+        let program_manager = ProgramManager::new(private_key, crate::testnet3("http://127.0.0.1:9"))
+            .with_proving_limits(limits)
+            .with_progress_reporter(move |event| reported.lock().unwrap().push(event));
+        (program_manager, events)
+    }
+
+    #[test]
+    fn test_estimated_memory() {
+        let small = CircuitMetrics { constraints: 1000, variables: 900, non_zero_entries: 3000 };
+        assert_eq!(small.estimated_memory(), 32 * (16 * 1024 + 36 * 4096));
+        let large = CircuitMetrics { constraints: 1 << 20, variables: 1 << 20, non_zero_entries: 1 << 22 };
+        assert!(large.estimated_memory() > 1000 * small.estimated_memory());
+    }
+
+    #[test]
+    fn test_memory_cap() {
+        let metrics = CircuitMetrics { constraints: 50_000, variables: 48_000, non_zero_entries: 200_000 };
+        let function = "hello.aleo/main".to_string();
+
+        // Over the cap, the estimate is only flagged by default.
+        let limits = ProvingLimits::new().with_memory_cap(1024);
+        let (program_manager, events) = sample_program_manager(limits);
+        program_manager.check_memory(function.clone(), metrics).unwrap();
+        let bytes = metrics.estimated_memory();
+        let estimated = ProvingEvent::Estimated { function: function.clone(), metrics, bytes, exceeds_cap: true };
+        assert_eq!(*events.lock().unwrap(), vec![estimated.clone()]);
+
+        // In low-memory mode, it is refused.
+        let (program_manager, events) = sample_program_manager(limits.with_low_memory(true));
+        let error = program_manager.check_memory(function.clone(), metrics).unwrap_err();
+        let error = error.downcast::<MemoryCapExceeded>().unwrap();
+        assert_eq!((error.function(), error.estimated(), error.cap()), (function.as_str(), bytes, 1024));
+        assert_eq!(events.lock().unwrap().as_slice(), [estimated]);
+
+        // Under the cap, it is allowed.
+        let (program_manager, _) = sample_program_manager(limits.with_low_memory(true).with_memory_cap(bytes));
+        program_manager.check_memory(function, metrics).unwrap();
+    }
+
+    #[test]
+    fn test_proving_timeout() {
+        let function = "hello.aleo/main".to_string();
+        let limits = ProvingLimits::new().with_timeout(Duration::from_millis(50));
+        let (program_manager, events) = sample_program_manager(limits);
+
+        // A prover that runs past the timeout fails without waiting for it.
+        let start = Instant::now();
+        let error = program_manager
+            .prove(function.clone(), || {
+                thread::sleep(Duration::from_secs(5));
+                Ok(())
+            })
+            .unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        let error = error.downcast::<ProvingTimeout>().unwrap();
+        assert_eq!((error.function(), error.timeout()), (function.as_str(), Duration::from_millis(50)));
+        assert_eq!(events.lock().unwrap().as_slice(), [ProvingEvent::Proving { function: function.clone() }]);
+
+        // A prover within the timeout returns its proof.
+        events.lock().unwrap().clear();
+        assert_eq!(program_manager.prove(function.clone(), || Ok(7)).unwrap(), 7);
+        let events = events.lock().unwrap();
+        assert!(
+            matches!(events.as_slice(), [ProvingEvent::Proving { .. }, ProvingEvent::Proved { function: f, .. }] if *f == function)
+        );
+    }
+}
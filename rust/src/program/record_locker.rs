@@ -0,0 +1,210 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+
+use snarkvm_console::{
+    program::{Network, Plaintext, Record},
+    types::Group,
+};
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use thiserror::Error;
+
+/// The error returned when a record is spent by a transaction that is still being built
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("Record with nonce {nonce} is already spent by a transaction being built")]
+pub struct RecordInUse {
+    nonce: String,
+}
+
+impl RecordInUse {
+    /// Returns the nonce of the record.
+    pub fn nonce(&self) -> &str {
+        &self.nonce
+    }
+}
+
+/// The records spent by the transactions a [`ProgramManager`] is building, so that builds running concurrently on
+/// other threads do not spend the same record
+///
+/// Records are told apart by their nonce. Clones of a locker share its records.
+#[derive(Clone, Debug)]
+pub struct RecordLocker<N: Network> {
+    locked: Arc<Mutex<HashSet<Group<N>>>>,
+}
+
+impl<N: Network> Default for RecordLocker<N> {
+    fn default() -> Self {
+        Self { locked: Arc::new(Mutex::new(HashSet::new())) }
+    }
+}
+
+impl<N: Network> RecordLocker<N> {
+    /// Create a locker without any locked record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lock the given records until the returned lease is dropped, failing with [`RecordInUse`] if any of them is
+    /// already locked, in which case none of them is locked.
+    pub fn lock(&self, records: &[&Record<N, Plaintext<N>>]) -> Result<RecordLease<N>, RecordInUse> {
+        let mut locked = self.locked();
+        let nonces = records.iter().map(|record| *record.nonce()).collect::<HashSet<_>>();
+        if let Some(nonce) = nonces.iter().find(|nonce| locked.contains(nonce)) {
+            return Err(RecordInUse { nonce: nonce.to_string() });
+        }
+        locked.extend(nonces.iter().copied());
+        Ok(RecordLease { locker: self.clone(), nonces })
+    }
+
+    /// Returns `true` if the record is locked.
+    pub fn is_locked(&self, record: &Record<N, Plaintext<N>>) -> bool {
+        self.locked().contains(record.nonce())
+    }
+
+    /// Returns the number of locked records.
+    pub fn len(&self) -> usize {
+        self.locked().len()
+    }
+
+    /// Returns `true` if no record is locked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The lock is only held to update the set, so a panic while holding it leaves the set consistent.
+    fn locked(&self) -> MutexGuard<'_, HashSet<Group<N>>> {
+        self.locked.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The records locked by [`RecordLocker::lock`], unlocked when the lease is dropped
+#[derive(Debug)]
+pub struct RecordLease<N: Network> {
+    locker: RecordLocker<N>,
+    nonces: HashSet<Group<N>>,
+}
+
+impl<N: Network> Drop for RecordLease<N> {
+    fn drop(&mut self) {
+        self.locker.locked().retain(|nonce| !self.nonces.contains(nonce));
+    }
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Returns the locker of the records spent by the transactions the program manager is building.
+    ///
+    /// The records a transaction spends are locked while it is built, so a build spending a record that another
+    /// thread is building with fails with [`RecordInUse`] instead of producing a second transaction spending it.
+    pub fn record_locker(&self) -> &RecordLocker<N> {
+        &self.record_locker
+    }
+
+    // Lock the records spent by a transaction for as long as it is built
+    pub(crate) fn lock_records(&self, records: &[&Record<N, Plaintext<N>>]) -> Result<RecordLease<N>, RecordInUse> {
+        self.record_locker.lock(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_record, CurrentNetwork},
+        testnet3,
+    };
+
+    use snarkvm_console::account::{Address, PrivateKey};
+    use snarkvm_utilities::TestRng;
+    use std::{sync::Barrier, thread, time::Duration};
+
+    type N = CurrentNetwork;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    // Returns a program manager and two records of its account
+    fn sample_manager(rng: &mut TestRng) -> (ProgramManager<N>, [Record<N, Plaintext<N>>; 2]) {
+        let private_key = PrivateKey::new(rng).unwrap();
+        let address = Address::try_from(private_key).unwrap();
+        let records = [sample_record(address, 100, rng).0, sample_record(address, 200, rng).0];
+        (ProgramManager::new(private_key, testnet3("http://127.0.0.1:9")), records)
+    }
+
+    #[test]
+    fn test_program_manager_is_send_sync() {
+        assert_send_sync::<ProgramManager<N>>();
+        assert_send_sync::<RecordLocker<N>>();
+    }
+
+    #[test]
+    fn test_record_locker() {
+        let (manager, [first, second]) = sample_manager(&mut TestRng::default());
+        let locker = manager.record_locker().clone();
+        let lease = manager.lock_records(&[&first]).unwrap();
+        assert!(locker.is_locked(&first));
+
+        // A lock overlapping a held lease fails without locking any record.
+        let error = locker.lock(&[&second, &first]).unwrap_err();
+        assert_eq!(error.nonce(), first.nonce().to_string());
+        assert!(!locker.is_locked(&second));
+        assert_eq!(locker.len(), 1);
+
+        // Dropping the lease unlocks its records.
+        drop(lease);
+        assert!(locker.is_empty());
+        let _lease = locker.lock(&[&first, &second]).unwrap();
+        assert!(manager.record_locker().is_locked(&second));
+    }
+
+    #[test]
+    fn test_concurrent_proving_overlaps() {
+        // Two threads sharing a program manager each lock their own record, then prove at the same time. No lock is
+        // held across the proving phase, so the proofs overlap, while a third build spending a locked record fails.
+        let (manager, records) = sample_manager(&mut TestRng::default());
+        let manager = Arc::new(manager);
+        let barrier = Arc::new(Barrier::new(3));
+        let handles = records.clone().map(|record| {
+            let (manager, barrier) = (manager.clone(), barrier.clone());
+            thread::spawn(move || {
+                let _lease = manager.lock_records(&[&record]).unwrap();
+                barrier.wait();
+                let client = manager.api_client().clone();
+                let proving = manager.prove("credits.aleo/transfer".to_string(), move || {
+                    let clock = client.clock();
+                    let start = clock.now();
+                    clock.sleep(Duration::from_millis(300));
+                    Ok((start, clock.now()))
+                });
+                barrier.wait();
+                proving
+            })
+        });
+        barrier.wait();
+        for record in &records {
+            assert!(matches!(manager.lock_records(&[record]), Err(RecordInUse { .. })));
+        }
+        barrier.wait();
+
+        let [(first_start, first_end), (second_start, second_end)] =
+            handles.map(|handle| handle.join().unwrap().unwrap());
+        assert!(first_start < second_end && second_start < first_end, "the proofs did not overlap");
+        assert!(manager.record_locker().is_empty());
+    }
+}
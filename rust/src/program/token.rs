@@ -0,0 +1,667 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+
+use crate::{BlockHeight, Microcredits};
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+use snarkvm_console::{
+    account::Address,
+    program::{
+        FinalizeType,
+        Identifier,
+        Literal,
+        LiteralType,
+        Network,
+        Plaintext,
+        PlaintextType,
+        ProgramID,
+        Record,
+        Value,
+        ValueType,
+    },
+    types::{U128, U16, U32, U64, U8},
+};
+use snarkvm_synthesizer::{Operand, Program};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The names accepted for each part of the token pattern, in order of preference
+const BALANCE_MAPPINGS: &[&str] = &["account", "balances"];
+const ALLOWANCE_MAPPINGS: &[&str] = &["allowances", "approvals"];
+const TRANSFER_FUNCTIONS: &[&str] = &["transfer_public", "transfer"];
+const MINT_FUNCTIONS: &[&str] = &["mint_public", "mint"];
+const APPROVE_FUNCTIONS: &[&str] = &["approve_public", "approve"];
+const DECIMALS_FUNCTION: &str = "decimals";
+
+/// An error returned when a program does not follow the part of the token pattern an operation needs
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum TokenError {
+    /// The program has no function or mapping matching the operation
+    #[error("Program '{program_id}' does not support {operation}")]
+    Unsupported { program_id: String, operation: &'static str },
+}
+
+/// A call to a function of a token program, with its inputs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenCall<N: Network> {
+    function_name: Identifier<N>,
+    inputs: Vec<Value<N>>,
+}
+
+impl<N: Network> TokenCall<N> {
+    /// Returns the name of the function to call
+    pub fn function_name(&self) -> &Identifier<N> {
+        &self.function_name
+    }
+
+    /// Returns the inputs to the function
+    pub fn inputs(&self) -> &[Value<N>] {
+        &self.inputs
+    }
+}
+
+// A function taking a recipient address and an amount
+#[derive(Clone, Debug)]
+struct AmountFunction<N: Network> {
+    name: Identifier<N>,
+    amount_type: LiteralType,
+}
+
+// A mapping from a key to an amount
+#[derive(Clone, Debug)]
+struct AmountMapping<N: Network> {
+    name: Identifier<N>,
+    // The members of the struct key of an allowance mapping, naming the owner and then the spender
+    key_members: Option<[Identifier<N>; 2]>,
+}
+
+/// A client for a deployed program following the token pattern
+///
+/// The pattern is introspected from the program: a public `transfer` function taking a recipient address
+/// and an amount, `mint` and `approve` functions of the same shape, an `account` mapping from addresses to
+/// balances, an `allowances` mapping keyed by a struct of the owner and spender addresses, and a `decimals`
+/// function returning a `u8` constant. Operations on parts the program does not have fail with
+/// [`TokenError::Unsupported`].
+///
+/// Amounts are given and formatted in whole tokens, scaled by the decimals of the program.
+pub struct TokenClient<N: Network> {
+    program_manager: ProgramManager<N>,
+    program: Program<N>,
+    balances: Option<AmountMapping<N>>,
+    allowances: Option<AmountMapping<N>>,
+    transfer: Option<AmountFunction<N>>,
+    mint: Option<AmountFunction<N>>,
+    approve: Option<AmountFunction<N>>,
+    decimals: u8,
+}
+
+impl<N: Network> TokenClient<N> {
+    /// Fetch a token program, and create a client that signs with the key of the program manager
+    pub fn new(program_manager: ProgramManager<N>, program_id: ProgramID<N>) -> Result<Self> {
+        let program = program_manager.api_client().get_program(program_id)?;
+        Self::from_program(program_manager, program)
+    }
+
+    /// Create a client for a program that was already fetched
+    pub fn from_program(program_manager: ProgramManager<N>, program: Program<N>) -> Result<Self> {
+        let balances = Self::find_mapping(&program, BALANCE_MAPPINGS, |key| Ok(Self::is_address(key).then_some(None)))?;
+        let allowances = Self::find_mapping(&program, ALLOWANCE_MAPPINGS, |key| match key {
+            PlaintextType::Struct(name) => {
+                let members = program.get_struct(name)?.members().clone();
+                match members.iter().collect::<Vec<_>>().as_slice() {
+                    [(owner, owner_type), (spender, spender_type)]
+                        if Self::is_address(owner_type) && Self::is_address(spender_type) =>
+                    {
+                        Ok(Some(Some([**owner, **spender])))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        })?;
+        Ok(Self {
+            balances,
+            allowances,
+            transfer: Self::find_function(&program, TRANSFER_FUNCTIONS),
+            mint: Self::find_function(&program, MINT_FUNCTIONS),
+            approve: Self::find_function(&program, APPROVE_FUNCTIONS),
+            decimals: Self::find_decimals(&program),
+            program_manager,
+            program,
+        })
+    }
+
+    /// Returns the token program
+    pub fn program(&self) -> &Program<N> {
+        &self.program
+    }
+
+    /// Returns the number of decimal places of the token, or 0 if the program does not expose them
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// Convert an amount in whole tokens, such as `"12.5"`, to the units stored by the program.
+    pub fn parse_amount(&self, amount: &str) -> Result<u128> {
+        let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        let is_digits = |digits: &str| digits.chars().all(|digit| digit.is_ascii_digit());
+        if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) || amount.ends_with('.') {
+            bail!("Invalid token amount '{amount}'");
+        }
+        if fraction.len() > self.decimals as usize {
+            bail!("Token amount '{amount}' has more than {} decimal places", self.decimals);
+        }
+        let digits = format!("{whole}{fraction:0<width$}", width = self.decimals as usize);
+        match digits.parse::<u128>() {
+            Ok(units) => Ok(units),
+            Err(_) => bail!("Token amount '{amount}' is too large"),
+        }
+    }
+
+    /// Format an amount in the units stored by the program as whole tokens.
+    pub fn format_amount(&self, units: u128) -> String {
+        let scale = 10u128.pow(self.decimals as u32);
+        let (whole, fraction) = (units / scale, units % scale);
+        match fraction {
+            0 => whole.to_string(),
+            _ => {
+                let fraction = format!("{fraction:0>width$}", width = self.decimals as usize);
+                format!("{whole}.{}", fraction.trim_end_matches('0'))
+            }
+        }
+    }
+
+    /// Returns the balance of the address in the units stored by the program.
+    pub fn balance_of(&self, address: Address<N>) -> Result<u128> {
+        let balances = self.require(&self.balances, "balances")?;
+        self.get_amount(&balances.name, Plaintext::from(Literal::Address(address)), None)
+    }
+
+    /// Returns the balance of the address after the block at the given height, in the units stored by the program.
+    ///
+    /// The balance is read with [`crate::AleoAPIClient::get_mapping_value_at_height`], so it is replayed from the
+    /// blocks unless the node serves historical mapping values.
+    pub fn balance_of_at_height(&self, address: Address<N>, height: BlockHeight) -> Result<u128> {
+        let balances = self.require(&self.balances, "balances")?;
+        self.get_amount(&balances.name, Plaintext::from(Literal::Address(address)), Some(height))
+    }
+
+    /// Returns the amount the spender may transfer on behalf of the owner, in the units stored by the program.
+    pub fn allowance(&self, owner: Address<N>, spender: Address<N>) -> Result<u128> {
+        let allowances = self.require(&self.allowances, "allowances")?;
+        let key = match &allowances.key_members {
+            Some([owner_member, spender_member]) => Plaintext::Struct(
+                IndexMap::from([
+                    (*owner_member, Plaintext::from(Literal::Address(owner))),
+                    (*spender_member, Plaintext::from(Literal::Address(spender))),
+                ]),
+                Default::default(),
+            ),
+            None => return Err(self.unsupported("allowances").into()),
+        };
+        self.get_amount(&allowances.name, key, None)
+    }
+
+    /// Prepare a call transferring an amount in whole tokens to the recipient.
+    pub fn transfer_call(&self, recipient: Address<N>, amount: &str) -> Result<TokenCall<N>> {
+        self.amount_call(&self.transfer, "transfers", recipient, amount)
+    }
+
+    /// Prepare a call minting an amount in whole tokens to the recipient.
+    pub fn mint_call(&self, recipient: Address<N>, amount: &str) -> Result<TokenCall<N>> {
+        self.amount_call(&self.mint, "minting", recipient, amount)
+    }
+
+    /// Prepare a call allowing the spender to transfer an amount in whole tokens on behalf of the signer.
+    pub fn approve_call(&self, spender: Address<N>, amount: &str) -> Result<TokenCall<N>> {
+        self.amount_call(&self.approve, "approvals", spender, amount)
+    }
+
+    /// Transfer an amount in whole tokens to the recipient, paying the network fee from the fee record.
+    pub fn transfer(
+        &self,
+        recipient: Address<N>,
+        amount: &str,
+        fee: Microcredits,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        self.execute(self.transfer_call(recipient, amount)?, fee, fee_record)
+    }
+
+    /// Mint an amount in whole tokens to the recipient, paying the network fee from the fee record.
+    pub fn mint(
+        &self,
+        recipient: Address<N>,
+        amount: &str,
+        fee: Microcredits,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        self.execute(self.mint_call(recipient, amount)?, fee, fee_record)
+    }
+
+    /// Allow the spender to transfer an amount in whole tokens, paying the network fee from the fee record.
+    pub fn approve(
+        &self,
+        spender: Address<N>,
+        amount: &str,
+        fee: Microcredits,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        self.execute(self.approve_call(spender, amount)?, fee, fee_record)
+    }
+
+    /// Build and broadcast a call to the token program, paying the network fee from the fee record.
+    pub fn execute(
+        &self,
+        call: TokenCall<N>,
+        fee: Microcredits,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        let imports = self.program_manager.fetch_imports(&self.program)?;
+        self.program_manager.execute(&self.program, &imports, call.function_name, call.inputs, fee, fee_record)
+    }
+
+    // Prepare a call to a function taking an address and an amount
+    fn amount_call(
+        &self,
+        function: &Option<AmountFunction<N>>,
+        operation: &'static str,
+        address: Address<N>,
+        amount: &str,
+    ) -> Result<TokenCall<N>> {
+        let function = self.require(function, operation)?;
+        let units = self.parse_amount(amount)?;
+        let inputs = vec![
+            Value::Plaintext(Plaintext::from(Literal::Address(address))),
+            Value::Plaintext(Plaintext::from(Self::amount_literal(function.amount_type, units)?)),
+        ];
+        Ok(TokenCall { function_name: function.name, inputs })
+    }
+
+    // Read an amount from a mapping, currently or after the block at a height, where a missing key holds zero
+    fn get_amount(&self, mapping_name: &Identifier<N>, key: Plaintext<N>, height: Option<BlockHeight>) -> Result<u128> {
+        let api_client = self.program_manager.api_client();
+        let value = match height {
+            Some(height) => api_client.get_mapping_value_at_height(*self.program.id(), mapping_name, &key, height)?,
+            None => api_client.get_mapping_value(*self.program.id(), mapping_name, &key)?,
+        };
+        match value {
+            None => Ok(0),
+            Some(Value::Plaintext(Plaintext::Literal(literal, _))) => match Self::literal_amount(&literal) {
+                Some(amount) => Ok(amount),
+                None => bail!("Mapping '{mapping_name}' holds '{literal}' instead of an amount"),
+            },
+            Some(value) => bail!("Mapping '{mapping_name}' holds '{value}' instead of an amount"),
+        }
+    }
+
+    fn require<'a, T>(&self, part: &'a Option<T>, operation: &'static str) -> Result<&'a T, TokenError> {
+        part.as_ref().ok_or_else(|| self.unsupported(operation))
+    }
+
+    fn unsupported(&self, operation: &'static str) -> TokenError {
+        TokenError::Unsupported { program_id: self.program.id().to_string(), operation }
+    }
+
+    // Find the first of the named functions whose inputs are an address and an unsigned amount
+    fn find_function(program: &Program<N>, names: &[&str]) -> Option<AmountFunction<N>> {
+        names.iter().find_map(|name| {
+            let function = program.get_function(&Identifier::from_str(name).ok()?).ok()?;
+            match function.input_types().as_slice() {
+                [address, amount] if Self::plaintext_type(address).map_or(false, Self::is_address) => {
+                    let amount_type = Self::unsigned_type(Self::plaintext_type(amount)?)?;
+                    Some(AmountFunction { name: *function.name(), amount_type })
+                }
+                _ => None,
+            }
+        })
+    }
+
+    // Find the first of the named mappings whose values are unsigned amounts, and whose key is accepted by
+    // `check_key`, which returns the members of a struct key
+    fn find_mapping(
+        program: &Program<N>,
+        names: &[&str],
+        check_key: impl Fn(&PlaintextType<N>) -> Result<Option<Option<[Identifier<N>; 2]>>>,
+    ) -> Result<Option<AmountMapping<N>>> {
+        for name in names {
+            let mapping = match program.get_mapping(&Identifier::from_str(name)?) {
+                Ok(mapping) => mapping,
+                Err(_) => continue,
+            };
+            let (FinalizeType::Public(key), FinalizeType::Public(value)) =
+                (mapping.key().finalize_type(), mapping.value().finalize_type())
+            else {
+                continue;
+            };
+            if Self::unsigned_type(value).is_none() {
+                continue;
+            }
+            if let Some(key_members) = check_key(key)? {
+                return Ok(Some(AmountMapping { name: *mapping.name(), key_members }));
+            }
+        }
+        Ok(None)
+    }
+
+    // Read the decimals from the first `u8` constant in the instructions of the `decimals` function
+    fn find_decimals(program: &Program<N>) -> u8 {
+        let function = Identifier::from_str(DECIMALS_FUNCTION).ok().and_then(|name| program.get_function(&name).ok());
+        let instructions = function.iter().flat_map(|function| function.instructions());
+        instructions
+            .flat_map(|instruction| instruction.operands())
+            .filter_map(|operand| match operand {
+                Operand::Literal(Literal::U8(decimals)) => Some(**decimals),
+                _ => None,
+            })
+            .next()
+            .unwrap_or(0)
+    }
+
+    // Returns the type of a public or private plaintext input
+    fn plaintext_type(value_type: &ValueType<N>) -> Option<&PlaintextType<N>> {
+        match value_type {
+            ValueType::Public(plaintext_type) | ValueType::Private(plaintext_type) => Some(plaintext_type),
+            _ => None,
+        }
+    }
+
+    fn is_address(plaintext_type: &PlaintextType<N>) -> bool {
+        *plaintext_type == PlaintextType::Literal(LiteralType::Address)
+    }
+
+    fn unsigned_type(plaintext_type: &PlaintextType<N>) -> Option<LiteralType> {
+        use LiteralType::{U128, U16, U32, U64, U8};
+        match plaintext_type {
+            PlaintextType::Literal(literal_type @ (U8 | U16 | U32 | U64 | U128)) => Some(*literal_type),
+            _ => None,
+        }
+    }
+
+    fn amount_literal(amount_type: LiteralType, units: u128) -> Result<Literal<N>> {
+        let literal = match amount_type {
+            LiteralType::U8 => u8::try_from(units).ok().map(|units| Literal::U8(U8::new(units))),
+            LiteralType::U16 => u16::try_from(units).ok().map(|units| Literal::U16(U16::new(units))),
+            LiteralType::U32 => u32::try_from(units).ok().map(|units| Literal::U32(U32::new(units))),
+            LiteralType::U64 => u64::try_from(units).ok().map(|units| Literal::U64(U64::new(units))),
+            _ => Some(Literal::U128(U128::new(units))),
+        };
+        match literal {
+            Some(literal) => Ok(literal),
+            None => bail!("Token amount of {units} units does not fit in a {amount_type}"),
+        }
+    }
+
+    fn literal_amount(literal: &Literal<N>) -> Option<u128> {
+        match literal {
+            Literal::U8(amount) => Some(**amount as u128),
+            Literal::U16(amount) => Some(**amount as u128),
+            Literal::U32(amount) => Some(**amount as u128),
+            Literal::U64(amount) => Some(**amount as u128),
+            Literal::U128(amount) => Some(**amount),
+            _ => None,
+        }
+    }
+}
+
+// The integer overloads of the methods taking typed heights and fees, kept for one release
+impl<N: Network> TokenClient<N> {
+    /// Returns the balance of the address after the block at the given height, in the units stored by the program.
+    #[deprecated(since = "0.3.6", note = "pass a `BlockHeight` to `balance_of_at_height`; removed in the next release")]
+    pub fn balance_of_at_height_raw(&self, address: Address<N>, height: u32) -> Result<u128> {
+        self.balance_of_at_height(address, BlockHeight(height))
+    }
+
+    /// Transfer an amount in whole tokens to the recipient, paying the network fee from the fee record.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `transfer`; removed in the next release")]
+    pub fn transfer_raw(
+        &self,
+        recipient: Address<N>,
+        amount: &str,
+        fee: u64,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        self.transfer(recipient, amount, Microcredits(fee), fee_record)
+    }
+
+    /// Mint an amount in whole tokens to the recipient, paying the network fee from the fee record.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `mint`; removed in the next release")]
+    pub fn mint_raw(
+        &self,
+        recipient: Address<N>,
+        amount: &str,
+        fee: u64,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        self.mint(recipient, amount, Microcredits(fee), fee_record)
+    }
+
+    /// Allow the spender to transfer an amount in whole tokens, paying the network fee from the fee record.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `approve`; removed in the next release")]
+    pub fn approve_raw(
+        &self,
+        spender: Address<N>,
+        amount: &str,
+        fee: u64,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        self.approve(spender, amount, Microcredits(fee), fee_record)
+    }
+
+    /// Build and broadcast a call to the token program, paying the network fee from the fee record.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `execute`; removed in the next release")]
+    pub fn execute_raw(
+        &self,
+        call: TokenCall<N>,
+        fee: u64,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        self.execute(call, Microcredits(fee), fee_record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error_code,
+        test_helpers::{CurrentNetwork, MockResponse, MockServer},
+        testnet3,
+        ApiError,
+        BlockHeight,
+        ErrorCode,
+    };
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    const TOKEN_PROGRAM: &str = r"
+program token.aleo;
+
+struct approval:
+    approver as address;
+    spender as address;
+
+mapping account:
+    key owner as address.public;
+    value amount as u64.public;
+
+mapping allowances:
+    key owner as approval.public;
+    value amount as u64.public;
+
+function decimals:
+    add 6u8 0u8 into r0;
+    output r0 as u8.public;
+
+function mint_public:
+    input r0 as address.public;
+    input r1 as u64.public;
+    finalize r0 r1;
+
+finalize mint_public:
+    input r0 as address.public;
+    input r1 as u64.public;
+    increment account[r0] by r1;
+
+function transfer_public:
+    input r0 as address.public;
+    input r1 as u64.public;
+    finalize self.caller r0 r1;
+
+finalize transfer_public:
+    input r0 as address.public;
+    input r1 as address.public;
+    input r2 as u64.public;
+    decrement account[r0] by r2;
+    increment account[r1] by r2;
+";
+
+    // Parse the fixture token program, or another program, into a client pointed at the given node
+    fn token_client(source: &str, base_url: &str) -> TokenClient<N> {
+        let private_key = PrivateKey::<N>::new(&mut TestRng::default()).unwrap();
+        let program_manager = ProgramManager::new(private_key, testnet3(base_url));
+        TokenClient::from_program(program_manager, Program::from_str(source).unwrap()).unwrap()
+    }
+
+    fn sample_address(rng: &mut TestRng) -> Address<N> {
+        Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_token_calls() {
+        let rng = &mut TestRng::default();
+        let recipient = sample_address(rng);
+        let client = token_client(TOKEN_PROGRAM, "http://127.0.0.1:9");
+        assert_eq!(client.decimals(), 6);
+
+        // Amounts are scaled by the decimals of the program.
+        let call = client.transfer_call(recipient, "12.5").unwrap();
+        assert_eq!(call.function_name().to_string(), "transfer_public");
+        assert_eq!(call.inputs(), [
+            Value::from_str(&recipient.to_string()).unwrap(),
+            Value::from_str("12500000u64").unwrap()
+        ]);
+        let call = client.mint_call(recipient, "3").unwrap();
+        assert_eq!(call.function_name().to_string(), "mint_public");
+        assert_eq!(call.inputs()[1], Value::from_str("3000000u64").unwrap());
+
+        // Parts of the pattern the program lacks are unsupported.
+        let error = client.approve_call(recipient, "1").unwrap_err();
+        assert_eq!(error.to_string(), "Program 'token.aleo' does not support approvals");
+        assert_eq!(
+            error.downcast_ref::<TokenError>(),
+            Some(&TokenError::Unsupported { program_id: "token.aleo".to_string(), operation: "approvals" })
+        );
+        let other = token_client("program other.aleo;\n\nfunction main:\n    input r0 as u8.public;\n", "");
+        assert_eq!(other.decimals(), 0);
+        assert!(other.transfer_call(recipient, "1").is_err());
+        assert_eq!(
+            other.balance_of(recipient).unwrap_err().to_string(),
+            "Program 'other.aleo' does not support balances"
+        );
+
+        // Amounts that do not fit the program are rejected.
+        let error = client.transfer_call(recipient, "18446744073709.551616").unwrap_err();
+        assert_eq!(error.to_string(), "Token amount of 18446744073709551616 units does not fit in a u64");
+    }
+
+    #[test]
+    fn test_token_amounts() {
+        let client = token_client(TOKEN_PROGRAM, "http://127.0.0.1:9");
+        assert_eq!(client.parse_amount("1").unwrap(), 1_000_000);
+        assert_eq!(client.parse_amount("0.000001").unwrap(), 1);
+        assert_eq!(client.parse_amount("12.50").unwrap(), 12_500_000);
+        for invalid in ["", ".5", "5.", "1.2.3", "-1", "1e6", " 1"] {
+            let error = client.parse_amount(invalid).unwrap_err();
+            assert_eq!(error.to_string(), format!("Invalid token amount '{invalid}'"));
+        }
+        let error = client.parse_amount("0.0000001").unwrap_err();
+        assert_eq!(error.to_string(), "Token amount '0.0000001' has more than 6 decimal places");
+
+        assert_eq!(client.format_amount(12_500_000), "12.5");
+        assert_eq!(client.format_amount(1), "0.000001");
+        assert_eq!(client.format_amount(7_000_000), "7");
+    }
+
+    #[test]
+    fn test_token_balances() {
+        let rng = &mut TestRng::default();
+        let (owner, spender, other) = (sample_address(rng), sample_address(rng), sample_address(rng));
+        let server = MockServer::start(move |request| {
+            let path = request.path.strip_prefix("/testnet3/program/token.aleo/mapping/")?;
+            match path.split_once('/')? {
+                ("account", key) if key == owner.to_string() => Some(MockResponse::json("\"42000000u64\"")),
+                ("account", key) if key == format!("{owner}?height=10") => Some(MockResponse::json("\"40000000u64\"")),
+                ("account", _) => Some(MockResponse::json("null")),
+                // The allowance key is a struct of the owner and the spender.
+                ("allowances", key) if key.contains(&owner.to_string()) && key.contains(&spender.to_string()) => {
+                    Some(MockResponse::json("\"1500000u64\""))
+                }
+                _ => None,
+            }
+        });
+        let client = token_client(TOKEN_PROGRAM, server.base_url());
+
+        // Balances are returned in units, and unset keys hold nothing.
+        assert_eq!(client.balance_of(owner).unwrap(), 42_000_000);
+        assert_eq!(client.format_amount(client.balance_of(owner).unwrap()), "42");
+        assert_eq!(client.balance_of(other).unwrap(), 0);
+        assert_eq!(client.allowance(owner, spender).unwrap(), 1_500_000);
+
+        // Past balances are read from nodes that serve historical mapping values.
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let api_client = testnet3(server.base_url()).with_historical_mappings(true);
+        let program_manager = ProgramManager::new(private_key, api_client);
+        let client = TokenClient::from_program(program_manager, client.program().clone()).unwrap();
+        assert_eq!(client.balance_of_at_height(owner, BlockHeight(10)).unwrap(), 40_000_000);
+    }
+
+    #[test]
+    fn test_token_balances_from_gateways() {
+        let rng = &mut TestRng::default();
+        let (owner, spender, other) = (sample_address(rng), sample_address(rng), sample_address(rng));
+        let server = MockServer::start(move |request| {
+            let path = request.path.strip_prefix("/testnet3/program/token.aleo/mapping/")?;
+            // The gateway re-serializes amounts as floats, or as strings without their type.
+            match path.split_once('/')? {
+                ("account", key) if key == owner.to_string() => Some(MockResponse::json("42000000.5")),
+                ("account", key) if key == other.to_string() => Some(MockResponse::json("\"18446744073709551615\"")),
+                ("account", _) => Some(MockResponse::json("1.8446744073709552e19")),
+                ("allowances", _) => Some(MockResponse::json("1500000.0")),
+                _ => None,
+            }
+        });
+        let client = token_client(TOKEN_PROGRAM, server.base_url());
+
+        // Amounts the gateway kept exact are read, and the others fail instead of returning a wrong balance.
+        assert_eq!(client.balance_of(other).unwrap(), u64::MAX as u128);
+        assert_eq!(client.allowance(owner, spender).unwrap(), 1_500_000);
+        let error = client.balance_of(owner).unwrap_err();
+        assert_eq!(error.downcast_ref::<ApiError>(), Some(&ApiError::PrecisionLoss {
+            item: "mapping value".to_string(),
+            path: "$".to_string(),
+            value: "42000000.5".to_string(),
+        }));
+        assert_eq!(error_code(&error), ErrorCode::PrecisionLoss);
+        let error = client.balance_of(spender).unwrap_err();
+        assert!(matches!(error.downcast_ref::<ApiError>(), Some(ApiError::PrecisionLoss { .. })));
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::ProgramManager;
+use crate::{CancellationToken, Cancelled, Microcredits, PendingTransaction};
+
+use snarkvm_console::{
+    account::Address,
+    program::{Literal, Network, Plaintext, Record, Value},
+    types::U64,
+};
+use snarkvm_synthesizer::Transaction;
+
+use anyhow::{ensure, Result};
+
+impl<N: Network> ProgramManager<N> {
+    /// Build a `credits.aleo/transfer` transaction sending `amount` gates to the recipient.
+    ///
+    /// The `input_record` funds the transfer and any remainder is returned to the sender as a new
+    /// record, while the `fee_record` pays the network fee of `fee` gates.
+    pub fn build_transfer(
+        &self,
+        amount: Microcredits,
+        fee: Microcredits,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<Transaction<N>> {
+        self.build_transfer_cancellable(amount, fee, recipient, input_record, fee_record, &CancellationToken::new())
+    }
+
+    /// Build a `credits.aleo/transfer` transaction, stopping between the proving phases once the token is
+    /// cancelled.
+    ///
+    /// The token is checked before authorizing the transfer, before proving it, and before proving the fee.
+    /// A cancelled build fails with [`Cancelled`]. The proofs are built within the [`crate::ProvingLimits`] of the
+    /// program manager, and watch-only program managers fail with [`crate::SigningUnavailable`]. Transfers refused
+    /// by the [`crate::SpendingPolicy`] of the program manager fail with [`crate::PolicyViolation`], and transfers
+    /// spending a record another thread is building with fail with [`crate::RecordInUse`].
+    pub fn build_transfer_cancellable(
+        &self,
+        amount: Microcredits,
+        fee: Microcredits,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+        token: &CancellationToken,
+    ) -> Result<Transaction<N>> {
+        ensure!(amount > Microcredits::ZERO, "Transfer amount must be greater than zero");
+        ensure!(***input_record.gates() >= amount.0, "Input record does not hold enough gates for the transfer");
+        ensure!(***fee_record.gates() >= fee.0, "Fee record does not hold enough gates to pay the fee");
+        let private_key = self.signer()?;
+        self.enforce_spending_policy(&PendingTransaction::transfer(recipient, amount.0, fee.0)?)?;
+        let _lease = self.lock_records(&[&input_record, &fee_record])?;
+        let check_cancelled = || match token.is_cancelled() {
+            true => Err(Cancelled::new((), None)),
+            false => Ok(()),
+        };
+        check_cancelled()?;
+
+        // Prepare the inputs to the transfer function.
+        let inputs = vec![
+            Value::Record(input_record),
+            Value::Plaintext(Plaintext::from(Literal::Address(recipient))),
+            Value::Plaintext(Plaintext::from(Literal::U64(U64::new(amount.0)))),
+        ];
+
+        // Authorize the transfer, then prove the transfer and the fee.
+        let rng = &mut rand::thread_rng();
+        let vm = self.vm()?;
+        let authorization = vm.authorize(&private_key, "credits.aleo", "transfer", inputs, rng)?;
+        check_cancelled()?;
+        let execution = self.prove_execution(&vm, authorization)?;
+        check_cancelled()?;
+        let fee = self.prove_fee(&vm, fee_record, fee.0)?;
+        self.log_built(Transaction::from_execution(execution, Some(fee))?)
+    }
+
+    /// Build a `credits.aleo/transfer` transaction and broadcast it to the network.
+    #[cfg(not(feature = "async"))]
+    pub fn transfer(
+        &self,
+        amount: Microcredits,
+        fee: Microcredits,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        let transaction = self.build_transfer(amount, fee, recipient, input_record, fee_record)?;
+        let transaction_id = transaction.id();
+        self.broadcast(transaction)?;
+        Ok(transaction_id)
+    }
+
+    /// Build a `credits.aleo/transfer` transaction and broadcast it, returning the change records it returns to the
+    /// sender and the records it spends, as found by [`ProgramManager::expected_records`].
+    ///
+    /// Inserting them with [`crate::RecordStore::insert_pending`] keeps the balance right until a scan finds the
+    /// transaction.
+    #[cfg(not(feature = "async"))]
+    pub fn transfer_tracked(
+        &self,
+        amount: Microcredits,
+        fee: Microcredits,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<super::ExpectedRecords<N>> {
+        let transaction = self.build_transfer(amount, fee, recipient, input_record, fee_record)?;
+        let expected = self.expected_records(&transaction)?;
+        self.broadcast(transaction)?;
+        Ok(expected)
+    }
+}
+
+// The integer overloads of the methods taking amounts and fees in `Microcredits`, kept for one release
+impl<N: Network> ProgramManager<N> {
+    /// Build a `credits.aleo/transfer` transaction sending `amount` gates to the recipient.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `build_transfer`; removed in the next release")]
+    pub fn build_transfer_raw(
+        &self,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<Transaction<N>> {
+        self.build_transfer(Microcredits(amount), Microcredits(fee), recipient, input_record, fee_record)
+    }
+
+    /// Build a `credits.aleo/transfer` transaction, stopping between the proving phases once the token is
+    /// cancelled.
+    #[deprecated(
+        since = "0.3.6",
+        note = "pass `Microcredits` to `build_transfer_cancellable`; removed in the next release"
+    )]
+    pub fn build_transfer_cancellable_raw(
+        &self,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+        token: &CancellationToken,
+    ) -> Result<Transaction<N>> {
+        let (amount, fee) = (Microcredits(amount), Microcredits(fee));
+        self.build_transfer_cancellable(amount, fee, recipient, input_record, fee_record, token)
+    }
+
+    /// Build a `credits.aleo/transfer` transaction and broadcast it to the network.
+    #[cfg(not(feature = "async"))]
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `transfer`; removed in the next release")]
+    pub fn transfer_raw(
+        &self,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<N::TransactionID> {
+        self.transfer(Microcredits(amount), Microcredits(fee), recipient, input_record, fee_record)
+    }
+
+    /// Build a `credits.aleo/transfer` transaction and broadcast it, returning the change records it returns to the
+    /// sender and the records it spends.
+    #[cfg(not(feature = "async"))]
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `transfer_tracked`; removed in the next release")]
+    pub fn transfer_tracked_raw(
+        &self,
+        amount: u64,
+        fee: u64,
+        recipient: Address<N>,
+        input_record: Record<N, Plaintext<N>>,
+        fee_record: Record<N, Plaintext<N>>,
+    ) -> Result<super::ExpectedRecords<N>> {
+        self.transfer_tracked(Microcredits(amount), Microcredits(fee), recipient, input_record, fee_record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{sample_record, CurrentNetwork};
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_utilities::TestRng;
+
+    #[test]
+    fn test_build_transfer_rejects_insufficient_records() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+        let address = Address::try_from(&private_key).unwrap();
+        let program_manager = ProgramManager::new(private_key, crate::testnet3("http://127.0.0.1:9"));
+
+        let (input_record, _) = sample_record(address, 100, rng);
+        let (fee_record, _) = sample_record(address, 10, rng);
+
+        // Zero-value transfers are rejected.
+        let error = program_manager
+            .build_transfer(Microcredits(0), Microcredits(1), address, input_record.clone(), fee_record.clone())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Transfer amount must be greater than zero");
+
+        // The input record must cover the amount.
+        let error = program_manager
+            .build_transfer(Microcredits(101), Microcredits(1), address, input_record.clone(), fee_record.clone())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Input record does not hold enough gates for the transfer");
+
+        // The fee record must cover the fee.
+        let error = program_manager
+            .build_transfer(Microcredits(100), Microcredits(11), address, input_record, fee_record)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Fee record does not hold enough gates to pay the fee");
+    }
+
+    #[test]
+    fn test_build_transfer_cancelled() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+        let address = Address::try_from(&private_key).unwrap();
+        let program_manager = ProgramManager::new(private_key, crate::testnet3("http://127.0.0.1:9"));
+
+        let (input_record, _) = sample_record(address, 100, rng);
+        let (fee_record, _) = sample_record(address, 10, rng);
+
+        // A cancelled token stops the build before any proving work.
+        let token = CancellationToken::new();
+        token.cancel();
+        let error = program_manager
+            .build_transfer_cancellable(Microcredits(10), Microcredits(1), address, input_record, fee_record, &token)
+            .unwrap_err();
+        assert!(error.downcast_ref::<Cancelled<()>>().is_some());
+        assert_eq!(error.to_string(), "The operation was cancelled");
+    }
+}
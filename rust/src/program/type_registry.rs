@@ -0,0 +1,542 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm_console::program::{
+    Entry,
+    EntryType,
+    Identifier,
+    Literal,
+    LiteralType,
+    Network,
+    Plaintext,
+    PlaintextType,
+    ProgramID,
+    Record,
+};
+use snarkvm_synthesizer::Program;
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// An error returned when a value cannot be decoded or encoded with the types of the registered programs
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum TypeError {
+    /// No registered program defines a type of the name
+    #[error("Type '{type_name}' is not defined by any registered program")]
+    UnknownType { type_name: String },
+    /// Several registered programs define a type of the name, so it must be qualified with its program
+    #[error("Type '{type_name}' is defined by several programs, qualify it with one of {}", .programs.join(", "))]
+    AmbiguousType { type_name: String, programs: Vec<String> },
+    /// The value at the path is of another kind or type than the one declared for it
+    #[error("'{path}' is a {found}, not a {expected}")]
+    TypeMismatch { path: String, expected: String, found: String },
+    /// The struct or record at the path has another number of fields than its type declares
+    #[error("'{path}' has {found} fields, but '{type_name}' declares {expected}")]
+    FieldCountMismatch { path: String, type_name: String, expected: usize, found: usize },
+    /// The struct or record at the path lacks a field its type declares
+    #[error("'{path}' has no field '{field}'")]
+    MissingField { path: String, field: String },
+}
+
+/// A plaintext value decoded with the types of the program defining it, as a tree of named fields and literals
+///
+/// The plaintexts of snarkVM hold literals and structs, so a decoded value is a literal with its Aleo type, or a
+/// struct or record with its fields in the order of their declaration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodedValue<N: Network> {
+    /// A literal, whose Aleo type is [`Literal::to_type`]
+    Literal(Literal<N>),
+    /// A struct or record of the program, with its fields in the order of their declaration
+    Struct { program_id: ProgramID<N>, name: Identifier<N>, fields: Vec<(Identifier<N>, DecodedValue<N>)> },
+}
+
+impl<N: Network> DecodedValue<N> {
+    /// Returns the Aleo type of the value, e.g. `u64`, or `token.aleo/point` for a struct of `token.aleo`.
+    pub fn type_name(&self) -> String {
+        match self {
+            Self::Literal(literal) => literal.to_type().to_string(),
+            Self::Struct { program_id, name, .. } => format!("{program_id}/{name}"),
+        }
+    }
+
+    /// Returns the field of the given name, if the value is a struct or record that has one.
+    pub fn field(&self, name: &str) -> Option<&DecodedValue<N>> {
+        match self {
+            Self::Literal(_) => None,
+            Self::Struct { fields, .. } => fields.iter().find(|(field, _)| field.to_string() == name).map(|(_, v)| v),
+        }
+    }
+
+    /// Returns the literal, if the value is one.
+    pub fn as_literal(&self) -> Option<&Literal<N>> {
+        match self {
+            Self::Literal(literal) => Some(literal),
+            Self::Struct { .. } => None,
+        }
+    }
+}
+
+// The members of a struct, by name, in the order of their declaration
+type Members<N> = IndexMap<Identifier<N>, PlaintextType<N>>;
+
+// A type name resolved against the registered programs
+enum ResolvedType<'a, N: Network> {
+    Literal(LiteralType),
+    Struct(&'a Program<N>, Identifier<N>),
+    Record(&'a Program<N>, Identifier<N>),
+}
+
+/// The structs and records of a set of programs, to decode the plaintext values of mapping reads and finalize
+/// arguments into typed trees, and to encode such trees back into plaintexts
+///
+/// Types are named as in Aleo instructions, e.g. `u64` or `point`, or qualified with their program, e.g.
+/// `token.aleo/point`, when several registered programs define a type of the name. The members of a struct refer to
+/// the structs of its own program.
+#[derive(Clone, Debug, Default)]
+pub struct TypeRegistry<N: Network> {
+    programs: IndexMap<ProgramID<N>, Program<N>>,
+}
+
+impl<N: Network> TypeRegistry<N> {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self { programs: IndexMap::new() }
+    }
+
+    /// Create a registry of the given programs, e.g. those returned by
+    /// [`AleoAPIClient::get_program_with_imports`](crate::AleoAPIClient::get_program_with_imports)
+    pub fn from_programs(programs: impl IntoIterator<Item = Program<N>>) -> Self {
+        let mut registry = Self::new();
+        programs.into_iter().for_each(|program| registry.add_program(program));
+        registry
+    }
+
+    /// Register the types of a program, replacing those of a program of the same ID.
+    pub fn add_program(&mut self, program: Program<N>) {
+        self.programs.insert(*program.id(), program);
+    }
+
+    /// Parse the source of a program in Aleo instructions, and register its types.
+    pub fn add_source(&mut self, source: &str) -> Result<()> {
+        self.add_program(Program::from_str(source)?);
+        Ok(())
+    }
+
+    /// Returns the registered programs, in the order they were added.
+    pub fn programs(&self) -> impl Iterator<Item = &Program<N>> {
+        self.programs.values()
+    }
+
+    /// Decode a plaintext of the given literal or struct type into a typed tree.
+    pub fn decode(&self, plaintext: &Plaintext<N>, type_name: &str) -> Result<DecodedValue<N>, TypeError> {
+        match self.resolve(type_name)? {
+            ResolvedType::Literal(literal_type) => {
+                decode_plaintext(None, &PlaintextType::Literal(literal_type), plaintext, type_name)
+            }
+            ResolvedType::Struct(program, name) => {
+                decode_plaintext(Some(program), &PlaintextType::Struct(name), plaintext, type_name)
+            }
+            ResolvedType::Record(..) => Err(TypeError::TypeMismatch {
+                path: type_name.to_string(),
+                expected: format!("'{type_name}' record, which is decoded with `decode_record`"),
+                found: "plaintext".to_string(),
+            }),
+        }
+    }
+
+    /// Decode a decrypted record of the given record type into a typed tree, whose fields start with the owner and
+    /// gates of the record.
+    pub fn decode_record(
+        &self,
+        record: &Record<N, Plaintext<N>>,
+        type_name: &str,
+    ) -> Result<DecodedValue<N>, TypeError> {
+        let (program, name) = match self.resolve(type_name)? {
+            ResolvedType::Record(program, name) => (program, name),
+            _ => {
+                return Err(TypeError::TypeMismatch {
+                    path: type_name.to_string(),
+                    expected: format!("'{type_name}'"),
+                    found: "record".to_string(),
+                });
+            }
+        };
+        let record_type = program.get_record(&name).map_err(|_| unknown(type_name))?;
+        let (expected, found) = (record_type.entries().len(), record.data().len());
+        if expected != found {
+            let type_name = type_name.to_string();
+            return Err(TypeError::FieldCountMismatch { path: type_name.clone(), type_name, expected, found });
+        }
+        let mut fields = vec![
+            (identifier("owner"), DecodedValue::Literal(Literal::Address(**record.owner()))),
+            (identifier("gates"), DecodedValue::Literal(Literal::U64(**record.gates()))),
+        ];
+        for (entry, entry_type) in record_type.entries() {
+            let path = format!("{type_name}.{entry}");
+            let plaintext = match record.data().get(entry) {
+                Some(Entry::Constant(plaintext) | Entry::Public(plaintext) | Entry::Private(plaintext)) => plaintext,
+                None => return Err(TypeError::MissingField { path: type_name.to_string(), field: entry.to_string() }),
+            };
+            let plaintext_type = match entry_type {
+                EntryType::Constant(plaintext_type)
+                | EntryType::Public(plaintext_type)
+                | EntryType::Private(plaintext_type) => plaintext_type,
+            };
+            fields.push((*entry, decode_plaintext(Some(program), plaintext_type, plaintext, &path)?));
+        }
+        Ok(DecodedValue::Struct { program_id: *program.id(), name, fields })
+    }
+
+    /// Encode a typed tree into a plaintext of the given literal or struct type, checking that the tree has the
+    /// type. The members of structs are encoded in the order of their declaration.
+    pub fn encode(&self, value: &DecodedValue<N>, type_name: &str) -> Result<Plaintext<N>, TypeError> {
+        match self.resolve(type_name)? {
+            ResolvedType::Literal(literal_type) => {
+                encode_value(None, &PlaintextType::Literal(literal_type), value, type_name)
+            }
+            ResolvedType::Struct(program, name) => {
+                encode_value(Some(program), &PlaintextType::Struct(name), value, type_name)
+            }
+            ResolvedType::Record(..) => Err(TypeError::TypeMismatch {
+                path: type_name.to_string(),
+                expected: "literal or struct".to_string(),
+                found: format!("'{type_name}' record"),
+            }),
+        }
+    }
+
+    // Resolve a type name to a literal type, or to a struct or record of the program defining it
+    fn resolve(&self, type_name: &str) -> Result<ResolvedType<'_, N>, TypeError> {
+        if let Some((program_id, name)) = type_name.split_once('/') {
+            let program = ProgramID::from_str(program_id).ok().and_then(|program_id| self.programs.get(&program_id));
+            let name = Identifier::from_str(name).ok();
+            return match (program, name) {
+                (Some(program), Some(name)) => resolve_in(program, name).ok_or_else(|| unknown(type_name)),
+                _ => Err(unknown(type_name)),
+            };
+        }
+        if let Ok(literal_type) = LiteralType::from_str(type_name) {
+            return Ok(ResolvedType::Literal(literal_type));
+        }
+        let name = Identifier::from_str(type_name).map_err(|_| unknown(type_name))?;
+        let mut resolved = self.programs.values().filter_map(|program| resolve_in(program, name)).collect::<Vec<_>>();
+        match resolved.len() {
+            0 => Err(unknown(type_name)),
+            1 => Ok(resolved.remove(0)),
+            _ => {
+                let programs = resolved.iter().map(|resolved| match resolved {
+                    ResolvedType::Struct(program, _) | ResolvedType::Record(program, _) => program.id().to_string(),
+                    ResolvedType::Literal(_) => unreachable!("literal types are resolved by their name"),
+                });
+                Err(TypeError::AmbiguousType { type_name: type_name.to_string(), programs: programs.collect() })
+            }
+        }
+    }
+}
+
+// Resolve the name to a struct or record of the program
+fn resolve_in<N: Network>(program: &Program<N>, name: Identifier<N>) -> Option<ResolvedType<'_, N>> {
+    if program.contains_struct(&name) {
+        Some(ResolvedType::Struct(program, name))
+    } else if program.contains_record(&name) {
+        Some(ResolvedType::Record(program, name))
+    } else {
+        None
+    }
+}
+
+// Decode a plaintext of the given type, whose structs are those of the program, at the given path
+fn decode_plaintext<N: Network>(
+    program: Option<&Program<N>>,
+    plaintext_type: &PlaintextType<N>,
+    plaintext: &Plaintext<N>,
+    path: &str,
+) -> Result<DecodedValue<N>, TypeError> {
+    match (plaintext_type, plaintext) {
+        (PlaintextType::Literal(literal_type), Plaintext::Literal(literal, _)) => {
+            check_literal(literal_type, literal, path)?;
+            Ok(DecodedValue::Literal(literal.clone()))
+        }
+        (PlaintextType::Struct(name), Plaintext::Struct(members, _)) => {
+            let (program, declared) = struct_members(program, name)?;
+            check_field_count(name, declared.len(), members.len(), path)?;
+            let mut fields = Vec::with_capacity(declared.len());
+            for (member, member_type) in &declared {
+                let value = members.get(member).ok_or_else(|| missing(path, member))?;
+                fields
+                    .push((*member, decode_plaintext(Some(program), member_type, value, &format!("{path}.{member}"))?));
+            }
+            Ok(DecodedValue::Struct { program_id: *program.id(), name: *name, fields })
+        }
+        (expected, Plaintext::Literal(..)) => Err(mismatch(path, expected, "literal")),
+        (expected, Plaintext::Struct(..)) => Err(mismatch(path, expected, "struct")),
+    }
+}
+
+// Encode a decoded value of the given type, whose structs are those of the program, at the given path
+fn encode_value<N: Network>(
+    program: Option<&Program<N>>,
+    plaintext_type: &PlaintextType<N>,
+    value: &DecodedValue<N>,
+    path: &str,
+) -> Result<Plaintext<N>, TypeError> {
+    match (plaintext_type, value) {
+        (PlaintextType::Literal(literal_type), DecodedValue::Literal(literal)) => {
+            check_literal(literal_type, literal, path)?;
+            Ok(Plaintext::from(literal.clone()))
+        }
+        (PlaintextType::Struct(name), DecodedValue::Struct { program_id, name: found, fields }) => {
+            let (program, declared) = struct_members(program, name)?;
+            if (program_id, found) != (program.id(), name) {
+                let expected = format!("'{}/{name}' struct", program.id());
+                return Err(TypeError::TypeMismatch { path: path.to_string(), expected, found: value.type_name() });
+            }
+            check_field_count(name, declared.len(), fields.len(), path)?;
+            let mut members = IndexMap::with_capacity(declared.len());
+            for (member, member_type) in &declared {
+                let (_, value) =
+                    fields.iter().find(|(field, _)| field == member).ok_or_else(|| missing(path, member))?;
+                members.insert(*member, encode_value(Some(program), member_type, value, &format!("{path}.{member}"))?);
+            }
+            Ok(Plaintext::Struct(members, Default::default()))
+        }
+        (expected, DecodedValue::Literal(..)) => Err(mismatch(path, expected, "literal")),
+        (expected, DecodedValue::Struct { .. }) => Err(mismatch(path, expected, "struct")),
+    }
+}
+
+// Returns the program defining the struct of the given name, and the members the struct declares
+fn struct_members<'a, N: Network>(
+    program: Option<&'a Program<N>>,
+    name: &Identifier<N>,
+) -> Result<(&'a Program<N>, Members<N>), TypeError> {
+    let program = program.ok_or_else(|| unknown(&name.to_string()))?;
+    let struct_ = program
+        .get_struct(name)
+        .map_err(|_| TypeError::UnknownType { type_name: format!("{}/{name}", program.id()) })?;
+    Ok((program, struct_.members().clone()))
+}
+
+// Check that a literal has the declared type
+fn check_literal<N: Network>(literal_type: &LiteralType, literal: &Literal<N>, path: &str) -> Result<(), TypeError> {
+    match literal.to_type() == *literal_type {
+        true => Ok(()),
+        false => Err(TypeError::TypeMismatch {
+            path: path.to_string(),
+            expected: literal_type.to_string(),
+            found: literal.to_type().to_string(),
+        }),
+    }
+}
+
+// Check that a struct has as many fields as its type declares
+fn check_field_count<N: Network>(
+    name: &Identifier<N>,
+    expected: usize,
+    found: usize,
+    path: &str,
+) -> Result<(), TypeError> {
+    match expected == found {
+        true => Ok(()),
+        false => {
+            Err(TypeError::FieldCountMismatch { path: path.to_string(), type_name: name.to_string(), expected, found })
+        }
+    }
+}
+
+fn unknown(type_name: &str) -> TypeError {
+    TypeError::UnknownType { type_name: type_name.to_string() }
+}
+
+fn missing<N: Network>(path: &str, field: &Identifier<N>) -> TypeError {
+    TypeError::MissingField { path: path.to_string(), field: field.to_string() }
+}
+
+fn mismatch<N: Network>(path: &str, expected: &PlaintextType<N>, found: &str) -> TypeError {
+    let expected = match expected {
+        PlaintextType::Literal(literal_type) => literal_type.to_string(),
+        PlaintextType::Struct(name) => format!("'{name}' struct"),
+    };
+    TypeError::TypeMismatch { path: path.to_string(), expected, found: found.to_string() }
+}
+
+// Returns the identifier of a field every record has
+fn identifier<N: Network>(name: &str) -> Identifier<N> {
+    Identifier::from_str(name).expect("the names of record fields are valid identifiers")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::CurrentNetwork;
+
+    use snarkvm_console::prelude::ToBytes;
+
+    type N = CurrentNetwork;
+
+    const REGISTRY_PROGRAM: &str = r"program registry_test.aleo;
+
+struct point:
+    x as u64;
+    y as i8;
+
+struct segment:
+    start as point;
+    end as point;
+    label as field;
+
+record ticket:
+    owner as address.private;
+    gates as u64.private;
+    seat as point.private;
+    price as u64.public;
+
+mapping segments:
+    key id as field.public;
+    value segment as segment.public;
+
+function draw:
+    input r0 as segment.public;
+    output r0.label as field.public;
+";
+
+    const OTHER_PROGRAM: &str = r"program other_test.aleo;
+
+struct point:
+    lat as i64;
+    lon as i64;
+
+function noop:
+    input r0 as point.private;
+    output r0.lat as i64.private;
+";
+
+    const SEGMENT: &str = "{ start: { x: 1u64, y: -2i8 }, end: { x: 3u64, y: 4i8 }, label: 5field }";
+
+    fn registry() -> TypeRegistry<N> {
+        let mut registry = TypeRegistry::new();
+        registry.add_source(REGISTRY_PROGRAM).unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_decode_encode_nested_struct() {
+        let registry = registry();
+        let plaintext = Plaintext::<N>::from_str(SEGMENT).unwrap();
+        let decoded = registry.decode(&plaintext, "segment").unwrap();
+
+        assert_eq!(decoded.type_name(), "registry_test.aleo/segment");
+        let start = decoded.field("start").unwrap();
+        assert_eq!(start.type_name(), "registry_test.aleo/point");
+        assert_eq!(start.field("y").and_then(DecodedValue::as_literal).unwrap().to_string(), "-2i8");
+        assert_eq!(start.field("y").unwrap().type_name(), "i8");
+        assert_eq!(decoded.field("label").unwrap().type_name(), "field");
+        assert!(decoded.field("middle").is_none());
+
+        // Re-encoding the tree gives back the plaintext, byte for byte.
+        let encoded = registry.encode(&decoded, "registry_test.aleo/segment").unwrap();
+        assert_eq!(encoded.to_bytes_le().unwrap(), plaintext.to_bytes_le().unwrap());
+        assert_eq!(encoded.to_string(), plaintext.to_string());
+
+        // Literals decode with their own type names.
+        let literal = Plaintext::<N>::from_str("7u64").unwrap();
+        assert_eq!(
+            registry.decode(&literal, "u64").unwrap(),
+            DecodedValue::Literal(Literal::from_str("7u64").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_decode_encode_errors() {
+        let mut registry = registry();
+        let plaintext = Plaintext::<N>::from_str(SEGMENT).unwrap();
+        let decoded = registry.decode(&plaintext, "segment").unwrap();
+
+        let unknown = |type_name: &str| TypeError::UnknownType { type_name: type_name.to_string() };
+        assert_eq!(registry.decode(&plaintext, "polygon"), Err(unknown("polygon")));
+        assert_eq!(registry.decode(&plaintext, "other_test.aleo/point"), Err(unknown("other_test.aleo/point")));
+        assert_eq!(registry.encode(&decoded, "registry_test.aleo/polygon"), Err(unknown("registry_test.aleo/polygon")));
+
+        // A struct with a field too many, or a field renamed, is rejected at its path.
+        let extra = Plaintext::<N>::from_str(
+            "{ start: { x: 1u64, y: 2i8, z: 0u64 }, end: { x: 3u64, y: 4i8 }, label: 5field }",
+        );
+        let error = registry.decode(&extra.unwrap(), "segment").unwrap_err();
+        assert_eq!(error, TypeError::FieldCountMismatch {
+            path: "segment.start".to_string(),
+            type_name: "point".to_string(),
+            expected: 2,
+            found: 3
+        });
+        assert_eq!(error.to_string(), "'segment.start' has 3 fields, but 'point' declares 2");
+        let renamed =
+            Plaintext::<N>::from_str("{ start: { x: 1u64, y: 2i8 }, end: { x: 3u64, z: 4i8 }, label: 5field }");
+        let error = registry.decode(&renamed.unwrap(), "segment").unwrap_err();
+        assert_eq!(error, TypeError::MissingField { path: "segment.end".to_string(), field: "y".to_string() });
+
+        // Literals must have the declared types, and structs must be where structs are declared.
+        let wrong = Plaintext::<N>::from_str("{ start: { x: 1u64, y: 2u8 }, end: { x: 3u64, y: 4i8 }, label: 5field }");
+        let error = registry.decode(&wrong.unwrap(), "segment").unwrap_err();
+        assert_eq!(error.to_string(), "'segment.start.y' is a u8, not a i8");
+        let error = registry.decode(&Plaintext::from_str("5field").unwrap(), "segment").unwrap_err();
+        assert_eq!(error.to_string(), "'segment' is a literal, not a 'segment' struct");
+
+        // Encoding checks the tree against the type it is encoded as.
+        let error = registry.encode(decoded.field("start").unwrap(), "segment").unwrap_err();
+        assert!(matches!(error, TypeError::TypeMismatch { ref found, .. } if found == "registry_test.aleo/point"));
+        let DecodedValue::Struct { program_id, name, mut fields } = decoded.clone() else { unreachable!() };
+        fields.pop();
+        let truncated = DecodedValue::Struct { program_id, name, fields };
+        assert!(matches!(registry.encode(&truncated, "segment"), Err(TypeError::FieldCountMismatch { found: 2, .. })));
+
+        // A name defined by several programs must be qualified.
+        registry.add_source(OTHER_PROGRAM).unwrap();
+        let point = Plaintext::from_str("{ lat: 1i64, lon: 2i64 }").unwrap();
+        let error = registry.decode(&point, "point").unwrap_err();
+        let programs = vec!["registry_test.aleo".to_string(), "other_test.aleo".to_string()];
+        assert_eq!(error, TypeError::AmbiguousType { type_name: "point".to_string(), programs });
+        assert!(error.to_string().ends_with("qualify it with one of registry_test.aleo, other_test.aleo"));
+        assert!(registry.decode(&point, "other_test.aleo/point").is_ok());
+        assert!(registry.decode(&plaintext, "segment").is_ok());
+        assert_eq!(registry.programs().count(), 2);
+    }
+
+    #[test]
+    fn test_decode_record() {
+        let registry = registry();
+        let record = Record::<N, Plaintext<N>>::from_str(concat!(
+            "{ owner: aleo10yse5aqawhnkrvcax89fch883mzlve46spr7vwap7y89x57j8g9s6rfrqr.private, gates: 5u64.private, ",
+            "seat: { x: 1u64.private, y: 2i8.private }, price: 9u64.public, _nonce: 0group.public }"
+        ))
+        .unwrap();
+        let decoded = registry.decode_record(&record, "ticket").unwrap();
+        let names = match &decoded {
+            DecodedValue::Struct { fields, .. } => fields.iter().map(|(name, _)| name.to_string()).collect::<Vec<_>>(),
+            DecodedValue::Literal(_) => unreachable!(),
+        };
+        assert_eq!(names, ["owner", "gates", "seat", "price"]);
+        assert_eq!(decoded.field("gates").unwrap().as_literal().unwrap().to_string(), "5u64");
+        assert_eq!(decoded.field("seat").unwrap().type_name(), "registry_test.aleo/point");
+
+        assert!(matches!(registry.decode_record(&record, "point"), Err(TypeError::TypeMismatch { .. })));
+        assert!(matches!(
+            registry.decode(&Plaintext::from_str("1u64").unwrap(), "ticket"),
+            Err(TypeError::TypeMismatch { .. })
+        ));
+    }
+}
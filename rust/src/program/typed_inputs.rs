@@ -0,0 +1,417 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{
+    deploy::{matches_plaintext, matches_record},
+    ProgramManager,
+    TypeRegistry,
+};
+
+use snarkvm_console::{
+    account::Address,
+    program::{Identifier, Literal, Network, Plaintext, Record, Value, ValueType},
+    types::{Boolean, Field, Group, Scalar, I128, I16, I32, I64, I8, U128, U16, U32, U64, U8},
+};
+use snarkvm_synthesizer::Program;
+
+use anyhow::{anyhow, bail, Result};
+use indexmap::IndexMap;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A native value that converts into an input of a program function, as a literal of the matching Aleo type
+///
+/// Integers convert into the integer literal of their width and sign, e.g. `1_000_000u64` into `1000000u64`, and
+/// booleans, addresses, fields, groups and scalars into their literals. Strings are parsed as Aleo values, e.g.
+/// `"5u32"` or a struct in Aleo syntax, and structs of a program are built with [`TypeRegistry::struct_builder`].
+/// The inputs of a call are converted together with [`crate::inputs!`].
+pub trait IntoAleoValue<N: Network> {
+    /// Convert the value into an input.
+    fn into_aleo_value(self) -> Result<Value<N>>;
+}
+
+// Convert native integers and booleans into the literal of the same Aleo type
+macro_rules! literal_inputs {
+    ($($native:ty => $variant:ident($aleo:ident)),* $(,)?) => {
+        $(
+            impl<N: Network> IntoAleoValue<N> for $native {
+                fn into_aleo_value(self) -> Result<Value<N>> {
+                    Ok(Value::Plaintext(Plaintext::from(Literal::$variant($aleo::new(self)))))
+                }
+            }
+        )*
+    };
+}
+
+literal_inputs!(
+    bool => Boolean(Boolean),
+    u8 => U8(U8),
+    u16 => U16(U16),
+    u32 => U32(U32),
+    u64 => U64(U64),
+    u128 => U128(U128),
+    i8 => I8(I8),
+    i16 => I16(I16),
+    i32 => I32(I32),
+    i64 => I64(I64),
+    i128 => I128(I128),
+);
+
+impl<N: Network> IntoAleoValue<N> for Address<N> {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        Ok(Value::Plaintext(Plaintext::from(Literal::Address(self))))
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for Field<N> {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        Ok(Value::Plaintext(Plaintext::from(Literal::Field(self))))
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for Group<N> {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        Ok(Value::Plaintext(Plaintext::from(Literal::Group(self))))
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for Scalar<N> {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        Ok(Value::Plaintext(Plaintext::from(Literal::Scalar(self))))
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for Literal<N> {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        Ok(Value::Plaintext(Plaintext::from(self)))
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for Plaintext<N> {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        Ok(Value::Plaintext(self))
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for Record<N, Plaintext<N>> {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        Ok(Value::Record(self))
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for Value<N> {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        Ok(self)
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for &str {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        Value::from_str(self).map_err(|error| anyhow!("'{self}' is not an Aleo value: {error}"))
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for String {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        self.as_str().into_aleo_value()
+    }
+}
+
+/// Convert native values into the inputs of a program function, failing on the first value that does not convert.
+///
+/// ```ignore
+/// let inputs = inputs![recipient, 1_000_000u64]?;
+/// let transaction = program_manager.build_execution(&program, &[], function, inputs, fee, fee_record)?;
+/// ```
+#[macro_export]
+macro_rules! inputs {
+    ($($input:expr),* $(,)?) => {
+        ::std::vec![$($crate::IntoAleoValue::into_aleo_value($input)),*]
+            .into_iter()
+            .collect::<::core::result::Result<::std::vec::Vec<_>, _>>()
+    };
+}
+
+/// A builder of a struct of a registered program, whose fields are native values
+///
+/// The struct is checked against the declaration of its type when it is built, so that a missing, unknown or
+/// mistyped field fails with the [`crate::TypeError`] naming it. Fields may be given in any order, and are encoded
+/// in the order of their declaration.
+pub struct StructBuilder<'a, N: Network> {
+    registry: &'a TypeRegistry<N>,
+    type_name: String,
+    fields: Vec<(String, Result<Value<N>>)>,
+}
+
+impl<N: Network> TypeRegistry<N> {
+    /// Start building a struct of the given type, named as in [`TypeRegistry::encode`].
+    pub fn struct_builder(&self, type_name: &str) -> StructBuilder<'_, N> {
+        StructBuilder { registry: self, type_name: type_name.to_string(), fields: vec![] }
+    }
+}
+
+impl<N: Network> StructBuilder<'_, N> {
+    /// Set a field of the struct, which may itself be built by a builder.
+    pub fn field(mut self, name: &str, value: impl IntoAleoValue<N>) -> Self {
+        self.fields.push((name.to_string(), value.into_aleo_value()));
+        self
+    }
+
+    /// Build the struct, checking it against its type.
+    pub fn build(self) -> Result<Value<N>> {
+        let mut members = IndexMap::with_capacity(self.fields.len());
+        for (name, value) in self.fields {
+            let path = format!("{}.{name}", self.type_name);
+            let member = Identifier::from_str(&name).map_err(|_| anyhow!("'{path}' is not a valid field name"))?;
+            match value.map_err(|error| anyhow!("Invalid value of '{path}': {error}"))? {
+                Value::Plaintext(plaintext) => members.insert(member, plaintext),
+                Value::Record(_) => bail!("'{path}' is a record, which a struct cannot hold"),
+            };
+        }
+        let decoded = self.registry.decode(&Plaintext::Struct(members, Default::default()), &self.type_name)?;
+        Ok(Value::Plaintext(self.registry.encode(&decoded, &self.type_name)?))
+    }
+}
+
+impl<N: Network> IntoAleoValue<N> for StructBuilder<'_, N> {
+    fn into_aleo_value(self) -> Result<Value<N>> {
+        self.build()
+    }
+}
+
+/// An error returned when the inputs of a call do not match the signature of the function
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum InputMismatch {
+    /// The program has no function of the name
+    #[error("Program '{program}' has no function '{function}'")]
+    UnknownFunction { program: String, function: String },
+    /// The call has another number of inputs than the function declares
+    #[error("Function '{function}' expects {expected} inputs, but {found} were given")]
+    InputCount { function: String, expected: usize, found: usize },
+    /// The input of the function at the register is of another type than the one declared for it
+    #[error("Input {register} of function '{function}' expects {expected}, but was given {found}")]
+    TypeMismatch { function: String, register: String, expected: String, found: String },
+}
+
+impl<N: Network> ProgramManager<N> {
+    /// Check that the program has a function of the given name, and that the inputs match its signature.
+    ///
+    /// This is checked before a call is authorized, so that an input of the wrong type fails at once, naming the
+    /// register of the input, rather than once the call is proven. Records passed to inputs of external record types
+    /// are not checked, as their programs are not at hand.
+    pub fn check_inputs(
+        program: &Program<N>,
+        function_name: &Identifier<N>,
+        inputs: &[Value<N>],
+    ) -> Result<(), InputMismatch> {
+        let function = program.get_function(function_name).map_err(|_| InputMismatch::UnknownFunction {
+            program: program.id().to_string(),
+            function: function_name.to_string(),
+        })?;
+        if function.inputs().len() != inputs.len() {
+            return Err(InputMismatch::InputCount {
+                function: function_name.to_string(),
+                expected: function.inputs().len(),
+                found: inputs.len(),
+            });
+        }
+        for (input, value) in function.inputs().iter().zip(inputs) {
+            let matches = match (value, input.value_type()) {
+                (Value::Plaintext(plaintext), ValueType::Constant(plaintext_type))
+                | (Value::Plaintext(plaintext), ValueType::Public(plaintext_type))
+                | (Value::Plaintext(plaintext), ValueType::Private(plaintext_type)) => {
+                    matches_plaintext(program, plaintext, plaintext_type)
+                }
+                (Value::Record(record), ValueType::Record(record_name)) => matches_record(program, record, record_name),
+                (Value::Record(_), ValueType::ExternalRecord(_)) => true,
+                _ => false,
+            };
+            if !matches {
+                return Err(InputMismatch::TypeMismatch {
+                    function: function_name.to_string(),
+                    register: input.register().to_string(),
+                    expected: input.value_type().to_string(),
+                    found: describe(value),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// Describe the type of a value, as far as it tells it
+fn describe<N: Network>(value: &Value<N>) -> String {
+    match value {
+        Value::Plaintext(Plaintext::Literal(literal, _)) => literal.to_type().to_string(),
+        Value::Plaintext(Plaintext::Struct(..)) => "a struct".to_string(),
+        Value::Record(_) => "a record".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{sample_record, CurrentNetwork},
+        testnet3,
+        TypeError,
+    };
+
+    use snarkvm_console::{account::PrivateKey, prelude::TestRng};
+
+    type N = CurrentNetwork;
+
+    const PROGRAM: &str = r"program typed_inputs.aleo;
+
+struct point:
+    x as u64;
+    y as i8;
+
+struct segment:
+    start as point;
+    end as point;
+
+function place:
+    input r0 as point.public;
+    input r1 as u64.private;
+    input r2 as address.private;
+    output r0.x as u64.private;
+";
+
+    // Returns the value of the given Aleo text
+    fn value(text: &str) -> Value<N> {
+        Value::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_primitive_inputs() {
+        let rng = &mut TestRng::default();
+        let address = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let inputs: Vec<Value<N>> = inputs![
+            true,
+            1u8,
+            2u16,
+            3u32,
+            1_000_000u64,
+            5u128,
+            -1i8,
+            -2i16,
+            -3i32,
+            -4i64,
+            -5i128,
+            address,
+            Field::<N>::from_u64(7),
+            Group::<N>::generator(),
+            Scalar::<N>::from_str("9scalar").unwrap(),
+            "10u32",
+            String::from("{ x: 1u64, y: 2i8 }"),
+        ]
+        .unwrap();
+        let expected = [
+            "true",
+            "1u8",
+            "2u16",
+            "3u32",
+            "1000000u64",
+            "5u128",
+            "-1i8",
+            "-2i16",
+            "-3i32",
+            "-4i64",
+            "-5i128",
+            &address.to_string(),
+            "7field",
+            &Group::<N>::generator().to_string(),
+            "9scalar",
+            "10u32",
+            "{ x: 1u64, y: 2i8 }",
+        ];
+        assert_eq!(inputs, expected.map(value));
+
+        // Values that are already inputs are passed as they are, and text that is not an Aleo value fails.
+        let (record, _) = sample_record(address, 5, rng);
+        let inputs: Vec<Value<N>> = inputs![value("1u8"), Value::Record(record.clone()), record.clone()].unwrap();
+        assert_eq!(inputs, [value("1u8"), Value::Record(record.clone()), Value::Record(record)]);
+        let error = inputs![1u8, "1u65"].map(|inputs: Vec<Value<N>>| inputs).unwrap_err();
+        assert!(error.to_string().starts_with("'1u65' is not an Aleo value"));
+    }
+
+    #[test]
+    fn test_struct_inputs() {
+        let program = Program::<N>::from_str(PROGRAM).unwrap();
+        let registry = TypeRegistry::from_programs([program.clone()]);
+
+        // Fields are encoded in the order of their declaration, and builders nest.
+        let point = registry.struct_builder("point").field("y", -2i8).field("x", 1u64).build().unwrap();
+        assert_eq!(point, value("{ x: 1u64, y: -2i8 }"));
+        let segment = registry
+            .struct_builder("segment")
+            .field("start", registry.struct_builder("point").field("x", 1u64).field("y", 2i8))
+            .field("end", "{ x: 3u64, y: 4i8 }")
+            .build()
+            .unwrap();
+        assert_eq!(segment, value("{ start: { x: 1u64, y: 2i8 }, end: { x: 3u64, y: 4i8 } }"));
+
+        // A mistyped or missing field fails, naming it.
+        let error = registry.struct_builder("point").field("x", 1u32).field("y", 2i8).build().unwrap_err();
+        let expected = TypeError::TypeMismatch { path: "point.x".into(), expected: "u64".into(), found: "u32".into() };
+        assert_eq!(error.downcast::<TypeError>().unwrap(), expected);
+        let error = registry.struct_builder("point").field("x", 1u64).build().unwrap_err();
+        assert_eq!(error.to_string(), "'point' has 1 fields, but 'point' declares 2");
+        let error = registry.struct_builder("point").field("x", 1u64).field("z", 2i8).build().unwrap_err();
+        assert_eq!(error.to_string(), "'point' has no field 'y'");
+    }
+
+    #[test]
+    fn test_check_inputs() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let recipient = Address::try_from(private_key).unwrap();
+        let program = Program::<N>::from_str(PROGRAM).unwrap();
+        let registry = TypeRegistry::from_programs([program.clone()]);
+        let place = Identifier::from_str("place").unwrap();
+        let point = || registry.struct_builder("point").field("x", 1u64).field("y", 2i8);
+
+        let inputs = inputs![point(), 1_000_000u64, recipient].unwrap();
+        assert_eq!(ProgramManager::check_inputs(&program, &place, &inputs), Ok(()));
+
+        // An input of another type than the function declares fails before the call is authorized, naming it.
+        let inputs = inputs![point(), 1_000_000u32, recipient].unwrap();
+        let error = ProgramManager::check_inputs(&program, &place, &inputs).unwrap_err();
+        assert_eq!(error.to_string(), "Input r1 of function 'place' expects u64.private, but was given u32");
+        let manager = ProgramManager::new(private_key, testnet3("http://127.0.0.1:9"));
+        let Err(error) = manager.authorize_execution(&program, &[], place, inputs) else {
+            panic!("The mistyped input should fail the authorization");
+        };
+        assert!(matches!(error.downcast::<InputMismatch>().unwrap(), InputMismatch::TypeMismatch { register, .. }
+            if register == "r1"));
+
+        let inputs = inputs![recipient, 1_000_000u64, recipient].unwrap();
+        let error = ProgramManager::check_inputs(&program, &place, &inputs).unwrap_err();
+        assert_eq!(error.to_string(), "Input r0 of function 'place' expects point.public, but was given address");
+        let (record, _) = sample_record(recipient, 5, rng);
+        let inputs = inputs![point(), 1_000_000u64, record].unwrap();
+        let error = ProgramManager::check_inputs(&program, &place, &inputs).unwrap_err();
+        assert_eq!(error.to_string(), "Input r2 of function 'place' expects address.private, but was given a record");
+
+        // Calls with too few inputs, or of a function the program does not have, fail too.
+        let inputs = inputs![point(), 1_000_000u64].unwrap();
+        let error = ProgramManager::check_inputs(&program, &place, &inputs).unwrap_err();
+        assert_eq!(error, InputMismatch::InputCount { function: "place".into(), expected: 3, found: 2 });
+        let error = ProgramManager::check_inputs(&program, &Identifier::from_str("draw").unwrap(), &[]).unwrap_err();
+        assert_eq!(error.to_string(), "Program 'typed_inputs.aleo' has no function 'draw'");
+    }
+}
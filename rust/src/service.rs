@@ -0,0 +1,433 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! A request and response facade over the read operations of [`AleoAPIClient`], for embedding in services.
+//!
+//! Requests are JSON objects naming a `method` and its `params`, and responses name their `result` and hold its
+//! `value`:
+//!
+//! ```json
+//! {"method": "block_range", "params": {"start": 10, "end": 20}}
+//! {"result": "blocks", "value": [...]}
+//! {"result": "error", "value": {"code": "ALEO-REQ-004", "message": "...", "remediation": "...", "retryable": false}}
+//! ```
+//!
+//! [`handle`] validates a request before any request reaches the node, so a web framework only has to decode the
+//! body into an [`ApiRequest`] and encode the returned [`ApiResponse`].
+
+use crate::{error_code, AleoAPIClient, BlockHeight, ErrorCode, ProgramCall, ProgramManager, TokenClient};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use snarkvm_console::{
+    account::Address,
+    program::{Identifier, Network, Plaintext, ProgramID, Value},
+};
+use snarkvm_synthesizer::{Block, Program, Transaction};
+use std::{fmt, str::FromStr};
+
+/// The maximum number of blocks a request of a block range may span
+pub const MAX_BLOCK_RANGE: u32 = 100;
+/// The maximum number of recent blocks a request of program history may look back on
+pub const MAX_LOOKBACK_BLOCKS: u32 = 1000;
+
+/// A read operation requested of a service
+///
+/// Identifiers, addresses and other values of the network are passed as strings, and validated by [`handle`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum ApiRequest {
+    /// The height of the latest block
+    LatestHeight,
+    /// The block at a height
+    Block { height: BlockHeight },
+    /// The blocks from `start` (inclusive) to `end` (exclusive), spanning at most [`MAX_BLOCK_RANGE`] blocks
+    BlockRange { start: BlockHeight, end: BlockHeight },
+    /// A transaction by its ID
+    Transaction { id: String },
+    /// A program by its ID
+    Program { id: String },
+    /// The value of a key in a mapping, after the block at `height` if one is given, which is only served by clients
+    /// of nodes that serve historical mapping values
+    MappingValue { program_id: String, mapping: String, key: String, height: Option<BlockHeight> },
+    /// The balance of an address in a token program, in the units stored by the program, after the block at
+    /// `height` if one is given, which is only served by clients of nodes that serve historical mapping values
+    Balance { program_id: String, address: String, height: Option<BlockHeight> },
+    /// The calls of a program, or of one of its functions, within the latest `lookback_blocks` blocks, at most
+    /// [`MAX_LOOKBACK_BLOCKS`]
+    History { program_id: String, function: Option<String>, lookback_blocks: u32 },
+}
+
+/// The response of a service to an [`ApiRequest`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "", tag = "result", content = "value", rename_all = "snake_case")]
+pub enum ApiResponse<N: Network> {
+    Height(BlockHeight),
+    Block(Box<Block<N>>),
+    Blocks(Vec<Block<N>>),
+    Transaction(Box<Transaction<N>>),
+    Program(Box<Program<N>>),
+    MappingValue(Option<Value<N>>),
+    Balance(u128),
+    History(Vec<ProgramCall<N>>),
+    Error(ServiceError),
+}
+
+/// An error of a request, with its stable code
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceError {
+    /// The code of the error, see [`ErrorCode::as_str`]
+    pub code: String,
+    /// The message of the error
+    pub message: String,
+    /// A short hint on what to do about the error, see [`ErrorCode::remediation`]
+    pub remediation: String,
+    /// Whether the request may succeed when sent again, see [`ErrorCode::is_retryable`]
+    pub retryable: bool,
+}
+
+impl ServiceError {
+    fn new(code: ErrorCode, message: impl fmt::Display) -> Self {
+        let (remediation, retryable) = (code.remediation().to_string(), code.is_retryable());
+        Self { code: code.as_str().to_string(), message: message.to_string(), remediation, retryable }
+    }
+}
+
+impl From<anyhow::Error> for ServiceError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::new(error_code(&error), format!("{error:#}"))
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+// A request whose arguments were parsed and checked
+enum Call<N: Network> {
+    LatestHeight,
+    Block(BlockHeight),
+    BlockRange(BlockHeight, BlockHeight),
+    Transaction(N::TransactionID),
+    Program(ProgramID<N>),
+    MappingValue(ProgramID<N>, Identifier<N>, Plaintext<N>, Option<BlockHeight>),
+    Balance(ProgramID<N>, Address<N>, Option<BlockHeight>),
+    History(ProgramID<N>, Option<Identifier<N>>, u32),
+}
+
+/// Handle a request with the client, returning its response, or an [`ApiResponse::Error`] with the code of the
+/// error.
+///
+/// Requests are validated before any request reaches the node. Values after a past block are only read from nodes
+/// that serve historical mapping values, see [`AleoAPIClient::with_historical_mappings`], as other clients would
+/// replay every block from genesis to answer a single request.
+pub fn handle<N: Network>(client: &AleoAPIClient<N>, request: ApiRequest) -> ApiResponse<N> {
+    match validate(client, request).and_then(|call| execute(client, call).map_err(ServiceError::from)) {
+        Ok(response) => response,
+        Err(error) => ApiResponse::Error(error),
+    }
+}
+
+// Parse and check the arguments of a request
+fn validate<N: Network>(client: &AleoAPIClient<N>, request: ApiRequest) -> Result<Call<N>, ServiceError> {
+    let historical = |height: Option<BlockHeight>| match height {
+        Some(height) if !client.historical_mappings() => {
+            let message = format!("Values at height {height} are only served by nodes with historical mappings");
+            Err(ServiceError::new(ErrorCode::InvalidArgument, message))
+        }
+        height => Ok(height),
+    };
+    Ok(match request {
+        ApiRequest::LatestHeight => Call::LatestHeight,
+        ApiRequest::Block { height } => Call::Block(height),
+        ApiRequest::BlockRange { start, end } => {
+            if start >= end || end - start > MAX_BLOCK_RANGE {
+                let message = format!("The range {start}..{end} must hold 1 to {MAX_BLOCK_RANGE} blocks");
+                return Err(ServiceError::new(ErrorCode::InvalidRange, message));
+            }
+            Call::BlockRange(start, end)
+        }
+        ApiRequest::Transaction { id } => Call::Transaction(argument("transaction ID", &id)?),
+        ApiRequest::Program { id } => Call::Program(identifier("program ID", &id)?),
+        ApiRequest::MappingValue { program_id, mapping, key, height } => Call::MappingValue(
+            identifier("program ID", &program_id)?,
+            identifier("mapping name", &mapping)?,
+            argument("mapping key", &key)?,
+            historical(height)?,
+        ),
+        ApiRequest::Balance { program_id, address, height } => {
+            Call::Balance(identifier("program ID", &program_id)?, argument("address", &address)?, historical(height)?)
+        }
+        ApiRequest::History { program_id, function, lookback_blocks } => {
+            if lookback_blocks == 0 || lookback_blocks > MAX_LOOKBACK_BLOCKS {
+                let message = format!("History must look back on 1 to {MAX_LOOKBACK_BLOCKS} blocks");
+                return Err(ServiceError::new(ErrorCode::InvalidRange, message));
+            }
+            let function = function.map(|function| identifier("function name", &function)).transpose()?;
+            Call::History(identifier("program ID", &program_id)?, function, lookback_blocks)
+        }
+    })
+}
+
+// Run a validated request against the node
+fn execute<N: Network>(client: &AleoAPIClient<N>, call: Call<N>) -> Result<ApiResponse<N>> {
+    Ok(match call {
+        Call::LatestHeight => ApiResponse::Height(client.latest_height()?),
+        Call::Block(height) => ApiResponse::Block(Box::new(client.get_block(height)?)),
+        Call::BlockRange(start, end) => ApiResponse::Blocks(client.get_block_range(start..end)?),
+        Call::Transaction(id) => ApiResponse::Transaction(Box::new(client.get_transaction(id)?)),
+        Call::Program(id) => ApiResponse::Program(Box::new(client.get_program(id)?)),
+        Call::MappingValue(program_id, mapping, key, None) => {
+            ApiResponse::MappingValue(client.get_mapping_value(program_id, &mapping, &key)?)
+        }
+        Call::MappingValue(program_id, mapping, key, Some(height)) => {
+            ApiResponse::MappingValue(client.get_mapping_value_at_height(program_id, &mapping, &key, height)?)
+        }
+        Call::Balance(program_id, address, height) => {
+            let token = TokenClient::new(ProgramManager::watch_only(client.clone()), program_id)?;
+            ApiResponse::Balance(match height {
+                Some(height) => token.balance_of_at_height(address, height)?,
+                None => token.balance_of(address)?,
+            })
+        }
+        Call::History(program_id, function, lookback_blocks) => {
+            ApiResponse::History(client.get_recent_program_activity(program_id, function, lookback_blocks)?)
+        }
+    })
+}
+
+// Parse an identifier of the request, such as a program ID
+fn identifier<T: FromStr>(item: &str, value: &str) -> Result<T, ServiceError> {
+    T::from_str(value).map_err(|_| ServiceError::new(ErrorCode::InvalidIdentifier, format!("Invalid {item} '{value}'")))
+}
+
+// Parse a value of the network passed by the request, such as an address
+fn argument<T: FromStr>(item: &str, value: &str) -> Result<T, ServiceError> {
+    T::from_str(value).map_err(|_| ServiceError::new(ErrorCode::InvalidArgument, format!("Invalid {item} '{value}'")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{genesis_block, sample_transaction, sample_transition, CurrentNetwork, MockResponse, MockServer},
+        testnet3,
+    };
+    use snarkvm_console::{account::PrivateKey, prelude::Uniform, types::Field};
+    use snarkvm_utilities::TestRng;
+
+    use serde::de::DeserializeOwned;
+    use std::{
+        fmt::Debug,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    type N = CurrentNetwork;
+
+    const TOKEN_PROGRAM: &str = r"
+program token.aleo;
+
+mapping account:
+    key owner as address.public;
+    value amount as u64.public;
+
+function burn:
+    input r0 as u64.public;
+";
+
+    fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: T) {
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(serde_json::from_str::<T>(&json).unwrap(), value, "{json}");
+    }
+
+    #[test]
+    fn test_service_serde() {
+        let rng = &mut TestRng::default();
+        let program_id = "token.aleo".to_string();
+        let requests = [
+            ApiRequest::LatestHeight,
+            ApiRequest::Block { height: BlockHeight(3) },
+            ApiRequest::BlockRange { start: BlockHeight(3), end: BlockHeight(9) },
+            ApiRequest::Transaction { id: "at1".to_string() },
+            ApiRequest::Program { id: program_id.clone() },
+            ApiRequest::MappingValue {
+                program_id: program_id.clone(),
+                mapping: "account".to_string(),
+                key: "1u8".to_string(),
+                height: Some(BlockHeight(4)),
+            },
+            ApiRequest::Balance { program_id: program_id.clone(), address: "aleo1".to_string(), height: None },
+            ApiRequest::History { program_id, function: Some("burn".to_string()), lookback_blocks: 10 },
+        ];
+        for request in requests {
+            round_trip(request);
+        }
+        let json = r#"{"method": "block_range", "params": {"start": 3, "end": 9}}"#;
+        let request = ApiRequest::BlockRange { start: BlockHeight(3), end: BlockHeight(9) };
+        assert_eq!(serde_json::from_str::<ApiRequest>(json).unwrap(), request);
+        assert_eq!(serde_json::to_string(&ApiRequest::LatestHeight).unwrap(), r#"{"method":"latest_height"}"#);
+
+        let genesis = genesis_block();
+        let credits = Program::<N>::credits().unwrap();
+        let calls = ProgramCall::find_in_block(&genesis, credits.id(), None).collect::<Vec<_>>();
+        assert!(!calls.is_empty());
+        let responses = [
+            ApiResponse::Height(BlockHeight(7)),
+            ApiResponse::Block(Box::new(genesis.clone())),
+            ApiResponse::Blocks(vec![genesis]),
+            ApiResponse::Transaction(Box::new(sample_transaction([sample_transition(&[Field::rand(rng)], &[], rng)]))),
+            ApiResponse::Program(Box::new(credits)),
+            ApiResponse::MappingValue(Some(Value::from_str("5u64").unwrap())),
+            ApiResponse::MappingValue(None),
+            ApiResponse::Balance(u64::MAX as u128 + 1),
+            ApiResponse::History(calls),
+            ApiResponse::Error(ServiceError::new(ErrorCode::InvalidRange, "The range is empty")),
+        ];
+        for response in responses {
+            round_trip::<ApiResponse<N>>(response);
+        }
+    }
+
+    #[test]
+    fn test_service_dispatch() {
+        let rng = &mut TestRng::default();
+        let owner = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let genesis = genesis_block();
+        let genesis_json = genesis.to_string();
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let server = MockServer::start(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            match request.path.strip_prefix("/testnet3/")? {
+                "latest/height" => Some(MockResponse::json("7")),
+                "block/0" => Some(MockResponse::json(genesis_json.clone())),
+                "program/token.aleo" => Some(MockResponse::json(serde_json::to_string(TOKEN_PROGRAM).unwrap())),
+                path if path == format!("program/token.aleo/mapping/account/{owner}") => {
+                    Some(MockResponse::json("\"42u64\""))
+                }
+                path if path == format!("program/token.aleo/mapping/account/{owner}?height=4") => {
+                    Some(MockResponse::json("\"40u64\""))
+                }
+                _ => None,
+            }
+        });
+        let client = testnet3(server.base_url());
+
+        // Valid requests are answered by the node.
+        assert_eq!(handle(&client, ApiRequest::LatestHeight), ApiResponse::Height(BlockHeight(7)));
+        let request = ApiRequest::Block { height: BlockHeight::GENESIS };
+        assert_eq!(handle(&client, request), ApiResponse::Block(Box::new(genesis)));
+        let (program_id, mapping, key) = ("token.aleo".to_string(), "account".to_string(), owner.to_string());
+        let request = ApiRequest::MappingValue { program_id: program_id.clone(), mapping, key, height: None };
+        assert_eq!(handle(&client, request), ApiResponse::MappingValue(Some(Value::from_str("42u64").unwrap())));
+        let request = ApiRequest::Balance { program_id: program_id.clone(), address: owner.to_string(), height: None };
+        assert_eq!(handle(&client, request), ApiResponse::<N>::Balance(42));
+
+        // Values after a past block are read from nodes that serve historical mapping values.
+        let historical = testnet3(server.base_url()).with_historical_mappings(true);
+        let (mapping, key) = ("account".to_string(), owner.to_string());
+        let request =
+            ApiRequest::MappingValue { program_id: program_id.clone(), mapping, key, height: Some(BlockHeight(4)) };
+        assert_eq!(handle(&historical, request), ApiResponse::MappingValue(Some(Value::from_str("40u64").unwrap())));
+        let address = owner.to_string();
+        let request = ApiRequest::Balance { program_id: program_id.clone(), address, height: Some(BlockHeight(4)) };
+        assert_eq!(handle(&historical, request), ApiResponse::<N>::Balance(40));
+
+        // Errors of the node carry their code.
+        let code = |response: ApiResponse<N>| match response {
+            ApiResponse::Error(error) => (error.code, error.retryable),
+            response => panic!("Expected an error, found {response:?}"),
+        };
+        let request = ApiRequest::Block { height: BlockHeight(1) };
+        assert_eq!(code(handle(&client, request)), ("ALEO-NODE-003".to_string(), false));
+
+        // Invalid requests fail before reaching the node.
+        let sent = count.load(Ordering::SeqCst);
+        let invalid = [
+            (ApiRequest::BlockRange { start: BlockHeight(5), end: BlockHeight(5) }, "ALEO-REQ-004"),
+            (ApiRequest::BlockRange { start: BlockHeight(0), end: BlockHeight(MAX_BLOCK_RANGE + 1) }, "ALEO-REQ-004"),
+            (ApiRequest::Transaction { id: "at1invalid".to_string() }, "ALEO-REQ-005"),
+            (ApiRequest::Program { id: "token".to_string() }, "ALEO-REQ-002"),
+            (
+                ApiRequest::MappingValue {
+                    program_id: program_id.clone(),
+                    mapping: "acc-ount".to_string(),
+                    key: owner.to_string(),
+                    height: None,
+                },
+                "ALEO-REQ-002",
+            ),
+            (
+                ApiRequest::MappingValue {
+                    program_id: program_id.clone(),
+                    mapping: "account".to_string(),
+                    key: "not a value".to_string(),
+                    height: None,
+                },
+                "ALEO-REQ-005",
+            ),
+            (
+                ApiRequest::Balance { program_id: program_id.clone(), address: "aleo1".to_string(), height: None },
+                "ALEO-REQ-005",
+            ),
+            // Other clients would replay every block from genesis to read a past value.
+            (
+                ApiRequest::MappingValue {
+                    program_id: program_id.clone(),
+                    mapping: "account".to_string(),
+                    key: owner.to_string(),
+                    height: Some(BlockHeight(4)),
+                },
+                "ALEO-REQ-005",
+            ),
+            (
+                ApiRequest::Balance {
+                    program_id: program_id.clone(),
+                    address: owner.to_string(),
+                    height: Some(BlockHeight(4)),
+                },
+                "ALEO-REQ-005",
+            ),
+            (
+                ApiRequest::History { program_id: program_id.clone(), function: None, lookback_blocks: 0 },
+                "ALEO-REQ-004",
+            ),
+            (
+                ApiRequest::History {
+                    program_id: program_id.clone(),
+                    function: None,
+                    lookback_blocks: MAX_LOOKBACK_BLOCKS + 1,
+                },
+                "ALEO-REQ-004",
+            ),
+            (
+                ApiRequest::History { program_id, function: Some("1burn".to_string()), lookback_blocks: 10 },
+                "ALEO-REQ-002",
+            ),
+        ];
+        for (request, expected) in invalid {
+            assert_eq!(code(handle(&client, request.clone())), (expected.to_string(), false), "{request:?}");
+        }
+        assert_eq!(count.load(Ordering::SeqCst), sent);
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! The snarkVM crates and types of the API of this crate, at the version it is pinned to.
+//!
+//! The types of two versions of snarkVM do not match, even where they look alike, so a crate depending on its own
+//! version of snarkVM fails to pass its types to this crate. Such crates should import snarkVM from here instead,
+//! e.g. `aleo_rust::snarkvm::PrivateKey` or `aleo_rust::snarkvm::console::program::Literal`. The pinned version
+//! is returned by [`crate::version_info`].
+
+pub use snarkvm_console as console;
+pub use snarkvm_utilities as utilities;
+
+#[cfg(feature = "snarkvm-circuit")]
+pub use snarkvm_circuit as circuit;
+
+#[cfg(feature = "snarkvm-synthesizer")]
+pub use snarkvm_synthesizer as synthesizer;
+
+pub use snarkvm_console::{
+    account::{Address, PrivateKey, Signature, ViewKey},
+    network::Testnet3,
+    program::{Ciphertext, Identifier, Network, Plaintext, ProgramID, Record, Value},
+    types::{Field, Group, Scalar},
+};
+
+#[cfg(feature = "snarkvm-synthesizer")]
+pub use snarkvm_synthesizer::{Block, Deployment, Execution, Program, Transaction, Transition};
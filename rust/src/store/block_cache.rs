@@ -0,0 +1,78 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::Persist;
+
+use serde::{Deserialize, Serialize};
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Block;
+use std::collections::BTreeMap;
+
+/// A bounded cache of blocks, by height
+///
+/// When the cache is full, the lowest blocks are evicted first, as scans revisit recent blocks more often.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BlockCache<N: Network> {
+    capacity: usize,
+    blocks: BTreeMap<u32, Block<N>>,
+}
+
+impl<N: Network> BlockCache<N> {
+    /// Create an empty cache holding at most `capacity` blocks.
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), blocks: BTreeMap::new() }
+    }
+
+    /// Add a block, evicting the lowest block if the cache is full.
+    pub fn insert(&mut self, block: Block<N>) {
+        self.blocks.insert(block.height(), block);
+        while self.blocks.len() > self.capacity {
+            self.blocks.pop_first();
+        }
+    }
+
+    /// Returns the block at the given height, if it is cached.
+    pub fn get(&self, height: u32) -> Option<&Block<N>> {
+        self.blocks.get(&height)
+    }
+
+    /// Returns the cached blocks in order of height.
+    pub fn iter(&self) -> impl '_ + Iterator<Item = &Block<N>> {
+        self.blocks.values()
+    }
+
+    /// Returns the maximum number of cached blocks.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of cached blocks.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns `true` if no blocks are cached.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+impl<N: Network> Persist for BlockCache<N> {
+    const KIND: u8 = 2;
+    const NAME: &'static str = "block cache";
+    const VERSION: u16 = 1;
+}
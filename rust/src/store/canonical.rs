@@ -0,0 +1,172 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use snarkvm_console::program::{Ciphertext, Network, Plaintext, Record};
+use snarkvm_synthesizer::{Block, Transaction};
+
+/// Values with a canonical JSON form and a stable content hash, e.g. to deduplicate them in an indexer
+///
+/// The content hash is the SHA-256 digest of the [`canonical_json`] of a value. It only identifies the JSON
+/// content within this library, and is unrelated to the transaction IDs and block hashes of the protocol.
+pub trait ContentHash: Serialize {
+    /// Returns the SHA-256 digest of the canonical JSON of the value.
+    fn content_hash(&self) -> Result<[u8; 32]> {
+        Ok(Sha256::digest(canonical_json(self)?).into())
+    }
+}
+
+impl<N: Network> ContentHash for Transaction<N> {}
+
+impl<N: Network> ContentHash for Block<N> {}
+
+impl<N: Network> ContentHash for Record<N, Plaintext<N>> {}
+
+impl<N: Network> ContentHash for Record<N, Ciphertext<N>> {}
+
+/// Serialize a value to its canonical JSON: the keys of every object sorted by their UTF-8 bytes, and no
+/// whitespace between tokens.
+///
+/// The field order of the serialized types is not part of the canonical form, so it holds across versions
+/// of serde_json and of the types.
+pub fn canonical_json<T: ContentHash + ?Sized>(value: &T) -> Result<String> {
+    let mut json = String::new();
+    write_canonical(&serde_json::to_value(value)?, &mut json);
+    Ok(json)
+}
+
+// Append the canonical JSON of a value
+fn write_canonical(value: &Value, json: &mut String) {
+    match value {
+        Value::Array(items) => {
+            json.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_canonical(item, json);
+            }
+            json.push(']');
+        }
+        Value::Object(object) => {
+            let mut entries = object.iter().collect::<Vec<_>>();
+            entries.sort_by_key(|&(key, _)| key);
+            json.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                json.push_str(&Value::from(key.as_str()).to_string());
+                json.push(':');
+                write_canonical(value, json);
+            }
+            json.push('}');
+        }
+        scalar => json.push_str(&scalar.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, CurrentNetwork};
+    use serde::de::DeserializeOwned;
+    use std::str::FromStr;
+
+    // A record of 5 gates, with a fixed owner and nonce
+    const RECORD: &str = "{ owner: aleo10yse5aqawhnkrvcax89fch883mzlve46spr7vwap7y89x57j8g9s6rfrqr.private, gates: \
+        5u64.private, _nonce: 2703981260079048995145876543848582468762875541275357422161389558975648624728group.public }";
+    // The record, encrypted to its owner
+    const RECORD_CIPHERTEXT: &str = "record1qyqsq0pr2ha842m8tjmqw3x5d8vl98fnuaf6fkxq0fk5xtjl930lf3gzqyqspsvqwd96humvclrrww0er9yrp8hak6klkcp36wyscrxweyaxt6gqqpv0q02f5tz0qr0um0nn4urrfwc7flkal5ywd50h2pm9sd30vmaq2j6q95q";
+
+    // Write the JSON of a value with the keys of every object in reverse order
+    fn reversed_json(value: &Value) -> String {
+        match value {
+            Value::Array(items) => format!("[{}]", items.iter().map(reversed_json).collect::<Vec<_>>().join(", ")),
+            Value::Object(object) => {
+                let entries = object.iter().rev().map(|(key, value)| format!("{key:?}: {}", reversed_json(value)));
+                format!("{{ {} }}", entries.collect::<Vec<_>>().join(", "))
+            }
+            scalar => scalar.to_string(),
+        }
+    }
+
+    // Parse a value from its JSON with reversed keys, checking that its canonical form and hash are unchanged
+    fn assert_order_independent<T: ContentHash + DeserializeOwned>(value: &T) {
+        let json = serde_json::to_string(value).unwrap();
+        let reversed = reversed_json(&serde_json::to_value(value).unwrap());
+        assert_ne!(json, reversed);
+        let parsed: T = serde_json::from_str(&reversed).unwrap();
+        assert_eq!(canonical_json(&parsed).unwrap(), canonical_json(value).unwrap());
+        assert_eq!(parsed.content_hash().unwrap(), value.content_hash().unwrap());
+    }
+
+    fn hex(bytes: [u8; 32]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let value = serde_json::json!({ "b": [1, { "d": null, "c": "\"x\"" }], "a": true, "é": -1.5 });
+        let mut json = String::new();
+        write_canonical(&value, &mut json);
+        assert_eq!(json, r#"{"a":true,"b":[1,{"c":"\"x\"","d":null}],"é":-1.5}"#);
+    }
+
+    #[test]
+    fn test_content_hash_vectors() {
+        // The canonical forms and hashes must never change, or indexers would store duplicates.
+        let record = Record::<CurrentNetwork, Plaintext<CurrentNetwork>>::from_str(RECORD).unwrap();
+        assert_eq!(
+            canonical_json(&record).unwrap(),
+            r#""{\n  owner: aleo10yse5aqawhnkrvcax89fch883mzlve46spr7vwap7y89x57j8g9s6rfrqr.private,\n  gates: 5u64.private,\n  _nonce: 2703981260079048995145876543848582468762875541275357422161389558975648624728group.public\n}""#
+        );
+        assert_eq!(
+            hex(record.content_hash().unwrap()),
+            "78c54edb854c1d57c0765fa97ba0d29e65b61b6b31155b9707ee25b34a1b17d2"
+        );
+
+        let ciphertext = Record::<CurrentNetwork, Ciphertext<CurrentNetwork>>::from_str(RECORD_CIPHERTEXT).unwrap();
+        assert_eq!(canonical_json(&ciphertext).unwrap(), format!("{RECORD_CIPHERTEXT:?}"));
+        assert_eq!(
+            hex(ciphertext.content_hash().unwrap()),
+            "2a222334e598fb74fc1a9d08ef4e78b9ca1666313af662864a997388c77e74f1"
+        );
+
+        let block = genesis_block();
+        let transaction = block.transactions().iter().next().unwrap();
+        assert!(canonical_json(transaction).unwrap().starts_with(r#"{"execution":{"global_state_root":"ar1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqgu0n07","transitions":[{"fee":-1100000000000000,"function":"mint","#));
+        assert_eq!(
+            hex(transaction.content_hash().unwrap()),
+            "6cc438c3f84b44ff0fd50cdb284dcba3043bef6b1cdf2f5917cc253e811bf763"
+        );
+        assert!(canonical_json(&block).unwrap().starts_with(r#"{"block_hash":"ab1pfhf6r4e2cv3v9scmkgs8nrp3gfk2rs38rgl409p3ma9wkaprvzqpwlgpz","header":{"coinbase_accumulator_point":"0field","#));
+        assert_eq!(
+            hex(block.content_hash().unwrap()),
+            "0efdb05735b7fe934c539d5785a1a864a04aeb1b96ff201f9d145a7eec3202d2"
+        );
+    }
+
+    #[test]
+    fn test_content_hash_ignores_key_order() {
+        let block = genesis_block();
+        assert_order_independent(block.transactions().iter().next().unwrap());
+        assert_order_independent(&block);
+    }
+}
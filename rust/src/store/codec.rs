@@ -0,0 +1,93 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// A serialization format for persisted stores
+pub trait Codec {
+    /// The tag identifying the codec in store headers
+    const TAG: u8;
+    /// The name of the codec, used in error messages
+    const NAME: &'static str;
+
+    /// Serialize a value.
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>>;
+
+    /// Deserialize a value.
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T>;
+}
+
+/// Human readable JSON, the default codec
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Json;
+
+impl Codec for Json {
+    const NAME: &'static str = "JSON";
+    const TAG: u8 = 0;
+
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Compact binary encoding, which stores keys, records, and blocks in their byte representation
+#[cfg(feature = "bincode")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl Codec for Bincode {
+    const NAME: &'static str = "bincode";
+    const TAG: u8 = 1;
+
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+/// Concise Binary Object Representation, a compact encoding that stays self-describing like JSON
+///
+/// Keys, records, and blocks are stored as the strings JSON holds, in the CBOR encoding of the JSON value, as the
+/// byte representations of snarkVM cannot be read back from CBOR byte strings.
+#[cfg(feature = "cbor")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Codec for Cbor {
+    const NAME: &'static str = "CBOR";
+    const TAG: u8 = 2;
+
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&serde_json::to_value(value)?, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        let value: serde_json::Value = ciborium::de::from_reader(bytes)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use snarkvm_console::{program::Network, types::Field};
+
+/// A record of a [`RecordStore`](super::RecordStore) reduced to what its history needs
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RecordSummary<N: Network> {
+    commitment: Field<N>,
+    height: u32,
+    spent_height: Option<u32>,
+    gates: u64,
+}
+
+impl<N: Network> RecordSummary<N> {
+    pub(super) fn new(commitment: Field<N>, height: u32, spent_height: Option<u32>, gates: u64) -> Self {
+        Self { commitment, height, spent_height, gates }
+    }
+
+    /// Returns the commitment of the record.
+    pub fn commitment(&self) -> Field<N> {
+        self.commitment
+    }
+
+    /// Returns the height of the block the record was created in.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the height of the block the record was spent in, if it is spent.
+    pub fn spent_height(&self) -> Option<u32> {
+        self.spent_height
+    }
+
+    /// Returns the gates held by the record.
+    pub fn gates(&self) -> u64 {
+        self.gates
+    }
+}
+
+/// What a compaction of a [`RecordStore`](super::RecordStore) may prune
+///
+/// Spent records are pruned only below the prune height, and only once the block they were spent in is at least
+/// the reorg depth below the tip, so that a reorganization can still bring them back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CompactionOptions {
+    tip_height: u32,
+    prune_below: u32,
+    reorg_depth: u32,
+}
+
+impl CompactionOptions {
+    /// The default number of blocks below the tip in which spent records are kept
+    pub const DEFAULT_REORG_DEPTH: u32 = 10;
+
+    /// Create options for a store synced up to the given tip, which prune every spent record outside the reorg
+    /// depth.
+    pub fn new(tip_height: u32) -> Self {
+        Self { tip_height, prune_below: u32::MAX, reorg_depth: Self::DEFAULT_REORG_DEPTH }
+    }
+
+    /// Prune only the records spent below the given height.
+    pub fn with_prune_below(mut self, prune_below: u32) -> Self {
+        self.prune_below = prune_below;
+        self
+    }
+
+    /// Set the number of blocks below the tip in which spent records are kept.
+    pub fn with_reorg_depth(mut self, reorg_depth: u32) -> Self {
+        self.reorg_depth = reorg_depth;
+        self
+    }
+
+    /// Returns the height of the tip the store is synced up to.
+    pub fn tip_height(&self) -> u32 {
+        self.tip_height
+    }
+
+    /// Returns the height below which spent records may be pruned.
+    pub fn prune_below(&self) -> u32 {
+        self.prune_below
+    }
+
+    /// Returns the number of blocks below the tip in which spent records are kept.
+    pub fn reorg_depth(&self) -> u32 {
+        self.reorg_depth
+    }
+
+    // Returns `true` if a record spent at the given height may be pruned.
+    pub(super) fn may_prune(&self, spent_height: u32) -> bool {
+        spent_height < self.prune_below && self.is_final(spent_height)
+    }
+
+    // Returns `true` if a block at the given height is outside the reorg depth.
+    pub(super) fn is_final(&self, height: u32) -> bool {
+        height.saturating_add(self.reorg_depth) <= self.tip_height
+    }
+}
+
+/// What a compaction of a [`RecordStore`](super::RecordStore) changed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    pub(super) pruned: usize,
+    pub(super) duplicates: usize,
+    pub(super) protected: usize,
+    pub(super) bytes_before: u64,
+    pub(super) bytes_after: u64,
+}
+
+impl CompactionReport {
+    /// Returns the number of spent records replaced by their summaries.
+    pub fn pruned(&self) -> usize {
+        self.pruned
+    }
+
+    /// Returns the number of records dropped because their commitment was already summarized.
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// Returns the number of spent records below the prune height that were kept, being within the reorg depth.
+    pub fn protected(&self) -> usize {
+        self.protected
+    }
+
+    /// Returns the size of the file of the store before the compaction.
+    ///
+    /// This is `0` for a store compacted in memory, with [`RecordStore::compact`](super::RecordStore::compact).
+    pub fn bytes_before(&self) -> u64 {
+        self.bytes_before
+    }
+
+    /// Returns the size of the file of the store after the compaction.
+    pub fn bytes_after(&self) -> u64 {
+        self.bytes_after
+    }
+
+    /// Returns the number of bytes the compaction removed from the file of the store.
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
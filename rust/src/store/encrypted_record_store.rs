@@ -0,0 +1,392 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{
+    read_store,
+    write_store,
+    Codec,
+    Persist,
+    RecordStore,
+    StoreLock,
+    StoredRecord,
+    DEFAULT_LOCK_TIMEOUT,
+    HEADER_SIZE,
+    MAGIC,
+};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use snarkvm_console::{
+    account::{Address, ViewKey},
+    prelude::{ToField, ToFields, Uniform},
+    program::{Ciphertext, Network, Plaintext, Record},
+    types::Field,
+};
+use std::{collections::HashMap, fs::File, io::Read, path::Path};
+
+/// A record held by an [`EncryptedRecordStore`]
+///
+/// The record is encrypted under a key derived from the view key of the store and the commitment of the record.
+/// Its height and watch-only flag are kept in cleartext for indexing, and are bound to the ciphertext by a tag.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct EncryptedRecord<N: Network> {
+    ciphertext: Record<N, Ciphertext<N>>,
+    height: u32,
+    watch_only: bool,
+    tag: Field<N>,
+}
+
+impl<N: Network> EncryptedRecord<N> {
+    /// Returns the height of the block the record was created in.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns `true` if the record was found by a watch-only account, and is not yet claimed by a private key.
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only
+    }
+}
+
+/// The records of an account, by commitment, with the plaintexts encrypted at rest
+///
+/// Where a [`RecordStore`] writes decrypted records to disk, this store keeps each record encrypted with a key
+/// derived from the view key of the account, in memory and on disk, and decrypts it only when it is read. The
+/// commitments, heights, and watch-only flags stay in cleartext, so records can be listed without the key. The
+/// public entries of a record are already public on the chain, and are not encrypted.
+///
+/// Opening a store requires its view key. A store opened with another view key is rejected, and a record whose
+/// ciphertext or cleartext fields were altered fails to decrypt.
+#[derive(Clone)]
+pub struct EncryptedRecordStore<N: Network> {
+    key: Field<N>,
+    records: EncryptedRecords<N>,
+}
+
+// The persisted form of an encrypted record store
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+struct EncryptedRecords<N: Network> {
+    // A salted hash of the store key, checked before decrypting, as records decrypted with the wrong key are garbage
+    salt: Field<N>,
+    key_hash: Field<N>,
+    records: HashMap<Field<N>, EncryptedRecord<N>>,
+}
+
+impl<N: Network> Persist for EncryptedRecords<N> {
+    const KIND: u8 = 5;
+    const NAME: &'static str = "encrypted record store";
+    const VERSION: u16 = 1;
+}
+
+impl<N: Network> EncryptedRecordStore<N> {
+    /// Create an empty store for the account of the given view key.
+    pub fn new(view_key: &ViewKey<N>) -> Result<Self> {
+        let key = store_key(view_key)?;
+        let salt = Field::rand(&mut rand::thread_rng());
+        let records = EncryptedRecords { salt, key_hash: key_hash(salt, key)?, records: HashMap::new() };
+        Ok(Self { key, records })
+    }
+
+    /// Encrypt the records of a plaintext store with the given view key.
+    pub fn from_record_store(store: &RecordStore<N>, view_key: &ViewKey<N>) -> Result<Self> {
+        let mut encrypted = Self::new(view_key)?;
+        for (commitment, stored) in store.iter() {
+            encrypted.insert_stored(*commitment, stored.record(), stored.height(), stored.is_watch_only())?;
+        }
+        Ok(encrypted)
+    }
+
+    /// Read a store from a file, checking that it was written with the given view key.
+    ///
+    /// The records stay encrypted until they are read.
+    pub fn open(path: impl AsRef<Path>, view_key: &ViewKey<N>) -> Result<Self> {
+        let records = EncryptedRecords::<N>::load(path)?;
+        let key = store_key(view_key)?;
+        if key_hash(records.salt, key)? != records.key_hash {
+            bail!("Failed to open the encrypted record store with the given view key");
+        }
+        Ok(Self { key, records })
+    }
+
+    /// Read a store from a file written either as an encrypted store or as a plaintext [`RecordStore`].
+    ///
+    /// A plaintext store is encrypted with the view key and rewritten in place in the given codec, so that its
+    /// decrypted records no longer remain on disk. An encrypted store is opened as with [`Self::open`].
+    pub fn migrate<C: Codec>(path: impl AsRef<Path>, view_key: &ViewKey<N>, codec: &C) -> Result<Self> {
+        let path = path.as_ref();
+        let mut header = [0u8; HEADER_SIZE];
+        File::open(path)?.read_exact(&mut header)?;
+        if !(header.starts_with(MAGIC) && header[4] == RecordStore::<N>::KIND) {
+            return Self::open(path, view_key);
+        }
+        let _lock = StoreLock::exclusive(path, DEFAULT_LOCK_TIMEOUT)?;
+        let store = Self::from_record_store(&read_store(path)?, view_key)?;
+        write_store(&store.records, path, codec)?;
+        Ok(store)
+    }
+
+    /// Write the store to a file atomically, using the given codec.
+    pub fn save<C: Codec>(&self, path: impl AsRef<Path>, codec: &C) -> Result<()> {
+        self.records.save(path, codec)
+    }
+
+    /// Decrypt every record into a plaintext store.
+    pub fn to_record_store(&self) -> Result<RecordStore<N>> {
+        let mut store = RecordStore::new();
+        for commitment in self.records.records.keys() {
+            if let Some(stored) = self.get(commitment)? {
+                match stored.is_watch_only() {
+                    true => store.insert_watch_only(*commitment, stored.record().clone(), stored.height()),
+                    false => store.insert(*commitment, stored.record().clone(), stored.height()),
+                };
+            }
+        }
+        Ok(store)
+    }
+
+    /// Add a record created at the given height, encrypting it.
+    pub fn insert(&mut self, commitment: Field<N>, record: &Record<N, Plaintext<N>>, height: u32) -> Result<()> {
+        self.insert_stored(commitment, record, height, false)
+    }
+
+    /// Add a record found by a watch-only account at the given height, encrypting it.
+    pub fn insert_watch_only(
+        &mut self,
+        commitment: Field<N>,
+        record: &Record<N, Plaintext<N>>,
+        height: u32,
+    ) -> Result<()> {
+        self.insert_stored(commitment, record, height, true)
+    }
+
+    /// Claim the records found by a watch-only account for the given address, and return their commitments.
+    ///
+    /// Only the watch-only records are decrypted, to check their owner.
+    pub fn claim(&mut self, address: Address<N>) -> Result<Vec<Field<N>>> {
+        let mut claimed = Vec::new();
+        for (commitment, encrypted) in self.records.records.iter() {
+            if encrypted.watch_only && **self.decrypt(commitment, encrypted)?.owner() == address {
+                claimed.push(*commitment);
+            }
+        }
+        for commitment in &claimed {
+            if let Some(stored) = self.get(commitment)? {
+                self.insert_stored(*commitment, stored.record(), stored.height(), false)?;
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// Returns the record with the given commitment, decrypting it.
+    ///
+    /// Records that fail to authenticate, e.g. because the file was altered, are rejected.
+    pub fn get(&self, commitment: &Field<N>) -> Result<Option<StoredRecord<N>>> {
+        match self.records.records.get(commitment) {
+            Some(encrypted) => {
+                let record = self.decrypt(commitment, encrypted)?;
+                Ok(Some(StoredRecord::new(record, encrypted.height, encrypted.watch_only)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Remove the record with the given commitment, e.g. once it is spent.
+    pub fn remove(&mut self, commitment: &Field<N>) -> Option<EncryptedRecord<N>> {
+        self.records.records.remove(commitment)
+    }
+
+    /// Returns the encrypted records and their commitments, in no particular order.
+    pub fn iter(&self) -> impl '_ + Iterator<Item = (&Field<N>, &EncryptedRecord<N>)> {
+        self.records.records.iter()
+    }
+
+    /// Returns the number of stored records.
+    pub fn len(&self) -> usize {
+        self.records.records.len()
+    }
+
+    /// Returns `true` if the store holds no records.
+    pub fn is_empty(&self) -> bool {
+        self.records.records.is_empty()
+    }
+
+    // Encrypt a record under its commitment, and store it with its cleartext fields
+    fn insert_stored(
+        &mut self,
+        commitment: Field<N>,
+        record: &Record<N, Plaintext<N>>,
+        height: u32,
+        watch_only: bool,
+    ) -> Result<()> {
+        let ciphertext = record.encrypt_symmetric(&record_key(self.key, commitment)?)?;
+        let tag = record_tag(self.key, commitment, &ciphertext, height, watch_only)?;
+        self.records.records.insert(commitment, EncryptedRecord { ciphertext, height, watch_only, tag });
+        Ok(())
+    }
+
+    // Authenticate and decrypt a stored record
+    fn decrypt(&self, commitment: &Field<N>, encrypted: &EncryptedRecord<N>) -> Result<Record<N, Plaintext<N>>> {
+        let EncryptedRecord { ciphertext, height, watch_only, tag } = encrypted;
+        if record_tag(self.key, *commitment, ciphertext, *height, *watch_only)? != *tag {
+            bail!("The record '{commitment}' in the encrypted record store failed authentication");
+        }
+        ciphertext.decrypt_symmetric(&record_key(self.key, *commitment)?)
+    }
+}
+
+// Derive the key of a store from the view key of its account
+fn store_key<N: Network>(view_key: &ViewKey<N>) -> Result<Field<N>> {
+    N::hash_psd2(&[Field::new_domain_separator("AleoEncryptedRecordStore0"), view_key.to_field()?])
+}
+
+// Hash the key of a store with the given salt
+fn key_hash<N: Network>(salt: Field<N>, key: Field<N>) -> Result<Field<N>> {
+    N::hash_psd2(&[Field::new_domain_separator("AleoEncryptedRecordStoreKey0"), salt, key])
+}
+
+// Derive the key of a record, so that no two records are encrypted under the same key
+fn record_key<N: Network>(key: Field<N>, commitment: Field<N>) -> Result<Field<N>> {
+    N::hash_psd2(&[Field::new_domain_separator("AleoEncryptedRecord0"), key, commitment])
+}
+
+// Compute the tag binding a ciphertext to the key of the store and to the cleartext fields of the record
+fn record_tag<N: Network>(
+    key: Field<N>,
+    commitment: Field<N>,
+    ciphertext: &Record<N, Ciphertext<N>>,
+    height: u32,
+    watch_only: bool,
+) -> Result<Field<N>> {
+    let domain = Field::new_domain_separator("AleoEncryptedRecordTag0");
+    let mut input = vec![domain, key, commitment, Field::from_u32(height), Field::from_u8(watch_only as u8)];
+    input.extend(ciphertext.to_fields()?);
+    N::hash_psd2(&input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        store::Json,
+        test_helpers::{sample_record, temp_path, CurrentNetwork},
+    };
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_utilities::TestRng;
+
+    use std::fs;
+
+    type N = CurrentNetwork;
+
+    // Sample a plaintext store with two records and a watch-only record of the given address
+    fn sample_store(address: Address<N>, rng: &mut TestRng) -> RecordStore<N> {
+        let mut store = RecordStore::new();
+        store.insert(Field::rand(rng), sample_record(address, 100, rng).0, 1);
+        store.insert(Field::rand(rng), sample_record(address, 200, rng).0, 2);
+        store.insert_watch_only(Field::rand(rng), sample_record(address, 300, rng).0, 3);
+        store
+    }
+
+    // Returns a view key and its address
+    fn sample_account(rng: &mut TestRng) -> (ViewKey<N>, Address<N>) {
+        let view_key = ViewKey::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        (view_key, view_key.to_address())
+    }
+
+    #[test]
+    fn test_encrypted_record_store_round_trip() {
+        let rng = &mut TestRng::default();
+        let (view_key, address) = sample_account(rng);
+        let records = sample_store(address, rng);
+        let store = EncryptedRecordStore::from_record_store(&records, &view_key).unwrap();
+        let path = temp_path("encrypted-store-round-trip");
+        store.save(&path, &Json).unwrap();
+
+        // The file holds no plaintext of the records.
+        let bytes = String::from_utf8_lossy(&fs::read(&path).unwrap()).to_string();
+        for (commitment, stored) in records.iter() {
+            assert!(bytes.contains(&commitment.to_string()));
+            assert!(!bytes.contains(&stored.record().to_string()));
+            assert!(!bytes.contains(&address.to_string()));
+        }
+
+        // The records are decrypted as they are read.
+        let opened = EncryptedRecordStore::open(&path, &view_key).unwrap();
+        assert_eq!(opened.len(), 3);
+        for (commitment, stored) in records.iter() {
+            assert_eq!(opened.get(commitment).unwrap().as_ref(), Some(stored));
+            let encrypted = opened.iter().find(|(other, _)| *other == commitment).unwrap().1;
+            assert_eq!((encrypted.height(), encrypted.is_watch_only()), (stored.height(), stored.is_watch_only()));
+        }
+        assert_eq!(opened.to_record_store().unwrap(), records);
+        assert_eq!(opened.get(&Field::rand(rng)).unwrap(), None);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_record_store_wrong_key() {
+        let rng = &mut TestRng::default();
+        let (view_key, address) = sample_account(rng);
+        let (other_view_key, _) = sample_account(rng);
+        let store = EncryptedRecordStore::from_record_store(&sample_store(address, rng), &view_key).unwrap();
+        let path = temp_path("encrypted-store-wrong-key");
+        store.save(&path, &Json).unwrap();
+
+        // Another view key fails to open the store.
+        let error = EncryptedRecordStore::open(&path, &other_view_key).err().unwrap();
+        assert_eq!(error.to_string(), "Failed to open the encrypted record store with the given view key");
+
+        // A record whose cleartext fields were altered fails to authenticate.
+        let mut altered = EncryptedRecordStore::open(&path, &view_key).unwrap();
+        let commitment = *altered.iter().next().unwrap().0;
+        altered.records.records.get_mut(&commitment).unwrap().height += 1;
+        let error = altered.get(&commitment).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("The record '{commitment}' in the encrypted record store failed authentication")
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_record_store_migrate() {
+        let rng = &mut TestRng::default();
+        let (view_key, address) = sample_account(rng);
+        let records = sample_store(address, rng);
+        let path = temp_path("encrypted-store-migrate");
+        records.save(&path, &Json).unwrap();
+
+        // A plaintext store is encrypted and rewritten in place.
+        let migrated = EncryptedRecordStore::migrate(&path, &view_key, &Json).unwrap();
+        assert_eq!(migrated.to_record_store().unwrap(), records);
+        assert!(RecordStore::<N>::load(&path).is_err());
+        assert_eq!(EncryptedRecordStore::open(&path, &view_key).unwrap().to_record_store().unwrap(), records);
+
+        // Migrating again opens the encrypted store, with its view key only.
+        assert_eq!(EncryptedRecordStore::migrate(&path, &view_key, &Json).unwrap().len(), 3);
+        let (other_view_key, _) = sample_account(rng);
+        assert!(EncryptedRecordStore::migrate(&path, &other_view_key, &Json).is_err());
+
+        // Watch-only records are claimed for their owner.
+        let mut migrated = migrated;
+        let claimed = migrated.claim(address).unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert!(!migrated.get(&claimed[0]).unwrap().unwrap().is_watch_only());
+        assert!(migrated.claim(address).unwrap().is_empty());
+        fs::remove_file(path).unwrap();
+    }
+}
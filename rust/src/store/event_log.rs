@@ -0,0 +1,786 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{ContentHash, Persist, StoreLock, DEFAULT_LOCK_TIMEOUT};
+use crate::{mutex::lock, unix_time, Clock, SystemClock};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use snarkvm_console::{
+    account::Address,
+    program::{Identifier, Network, ProgramID},
+    types::Field,
+};
+use std::{
+    ffi::OsString,
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use thiserror::Error;
+
+/// The hash the first entry of an event log chains from
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// The kind of a [`WalletEvent`], by which an [`EventLog`] is queried
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WalletEventKind {
+    /// See [`WalletEvent::Scan`]
+    Scan,
+    /// See [`WalletEvent::PolicyDecision`]
+    PolicyDecision,
+    /// See [`WalletEvent::TransactionBuilt`]
+    TransactionBuilt,
+    /// See [`WalletEvent::TransactionBroadcast`]
+    TransactionBroadcast,
+    /// See [`WalletEvent::RecordSpent`]
+    RecordSpent,
+    /// See [`WalletEvent::StoreMigrated`]
+    StoreMigrated,
+    /// See [`WalletEvent::FlowStep`]
+    FlowStep,
+}
+
+/// Something a wallet did, as written to its [`EventLog`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum WalletEvent<N: Network> {
+    /// The blocks at heights `start_height..end_height` were scanned for the given number of accounts, which found
+    /// the given number of records
+    Scan { start_height: u32, end_height: u32, accounts: usize, records: usize },
+    /// A transaction was checked against the spending policy before it was built, and refused if `violation` is set
+    PolicyDecision {
+        program: ProgramID<N>,
+        function: Identifier<N>,
+        recipient: Option<Address<N>>,
+        amount: u64,
+        fee: u64,
+        violation: Option<String>,
+    },
+    /// A transaction calling the given functions, as `program/function`, was built
+    TransactionBuilt { transaction_id: N::TransactionID, functions: Vec<String> },
+    /// A transaction was broadcast, and failed to reach the node or was rejected by it if `error` is set
+    TransactionBroadcast { transaction_id: N::TransactionID, error: Option<String> },
+    /// A record of the wallet was spent by a transaction in the block at the given height
+    RecordSpent { commitment: Field<N>, transaction_id: N::TransactionID, height: u32 },
+    /// A store file written at an older version was migrated to the current version
+    StoreMigrated { store: String, path: PathBuf, from_version: u16, to_version: u16 },
+    /// The transaction of a step of a flow run by a [`crate::Orchestrator`] was confirmed
+    FlowStep { step: usize, transaction_id: N::TransactionID },
+}
+
+impl<N: Network> WalletEvent<N> {
+    /// Returns the kind of the event.
+    pub fn kind(&self) -> WalletEventKind {
+        match self {
+            Self::Scan { .. } => WalletEventKind::Scan,
+            Self::PolicyDecision { .. } => WalletEventKind::PolicyDecision,
+            Self::TransactionBuilt { .. } => WalletEventKind::TransactionBuilt,
+            Self::TransactionBroadcast { .. } => WalletEventKind::TransactionBroadcast,
+            Self::RecordSpent { .. } => WalletEventKind::RecordSpent,
+            Self::StoreMigrated { .. } => WalletEventKind::StoreMigrated,
+            Self::FlowStep { .. } => WalletEventKind::FlowStep,
+        }
+    }
+
+    /// Returns the ID of the transaction the event is about, if any.
+    pub fn transaction_id(&self) -> Option<N::TransactionID> {
+        match self {
+            Self::TransactionBuilt { transaction_id, .. }
+            | Self::TransactionBroadcast { transaction_id, .. }
+            | Self::RecordSpent { transaction_id, .. }
+            | Self::FlowStep { transaction_id, .. } => Some(*transaction_id),
+            Self::Scan { .. } | Self::PolicyDecision { .. } | Self::StoreMigrated { .. } => None,
+        }
+    }
+
+    /// Returns the commitment of the record the event is about, if any.
+    pub fn commitment(&self) -> Option<Field<N>> {
+        match self {
+            Self::RecordSpent { commitment, .. } => Some(*commitment),
+            _ => None,
+        }
+    }
+}
+
+/// An event written to an [`EventLog`], chained to the entry before it by its hash
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct EventLogEntry<N: Network> {
+    sequence: u64,
+    timestamp: u64,
+    event: WalletEvent<N>,
+    previous_hash: String,
+    hash: String,
+}
+
+impl<N: Network> EventLogEntry<N> {
+    /// Returns the position of the entry in the log, counting from zero.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns the time the event was written, in seconds since the Unix epoch.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Returns the event.
+    pub fn event(&self) -> &WalletEvent<N> {
+        &self.event
+    }
+
+    /// Returns the hash of the entry before it, as hex, or zeros for the first entry.
+    pub fn previous_hash(&self) -> &str {
+        &self.previous_hash
+    }
+
+    /// Returns the hash of the entry, as hex, which covers its sequence, time, event, and the hash before it.
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    // Returns the hash of the contents of the entry, which its `hash` holds unless the entry was altered
+    fn content_hash(&self) -> Result<String> {
+        let chained = ChainedEvent {
+            sequence: self.sequence,
+            timestamp: self.timestamp,
+            event: &self.event,
+            previous_hash: &self.previous_hash,
+        };
+        Ok(chained.content_hash()?.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+}
+
+// The contents of an entry covered by its hash
+#[derive(Serialize)]
+#[serde(bound = "")]
+struct ChainedEvent<'a, N: Network> {
+    sequence: u64,
+    timestamp: u64,
+    event: &'a WalletEvent<N>,
+    previous_hash: &'a str,
+}
+
+impl<N: Network> ContentHash for ChainedEvent<'_, N> {}
+
+/// The entries of an [`EventLog`] to return from [`EventLog::query`]
+///
+/// An entry is returned if it matches every criterion that is set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventQuery<N: Network> {
+    /// The earliest time of the entries, in seconds since the Unix epoch
+    pub since: Option<u64>,
+    /// The time before which the entries were written, in seconds since the Unix epoch
+    pub until: Option<u64>,
+    /// The kinds of the events, or every kind if empty
+    pub kinds: Vec<WalletEventKind>,
+    /// The transaction the events are about
+    pub transaction_id: Option<N::TransactionID>,
+    /// The commitment of the record the events are about
+    pub commitment: Option<Field<N>>,
+}
+
+impl<N: Network> Default for EventQuery<N> {
+    fn default() -> Self {
+        Self { since: None, until: None, kinds: vec![], transaction_id: None, commitment: None }
+    }
+}
+
+impl<N: Network> EventQuery<N> {
+    /// Returns `true` if the entry matches the query.
+    pub fn matches(&self, entry: &EventLogEntry<N>) -> bool {
+        let event = entry.event();
+        self.since.map_or(true, |since| entry.timestamp() >= since)
+            && self.until.map_or(true, |until| entry.timestamp() < until)
+            && (self.kinds.is_empty() || self.kinds.contains(&event.kind()))
+            && self.transaction_id.map_or(true, |transaction_id| event.transaction_id() == Some(transaction_id))
+            && self.commitment.map_or(true, |commitment| event.commitment() == Some(commitment))
+    }
+}
+
+/// The error returned when an entry of an [`EventLog`] does not chain to the entries before it
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("The event log at '{}' was tampered with at entry {sequence}: {reason}", path.display())]
+pub struct TamperedEventLog {
+    path: PathBuf,
+    sequence: u64,
+    reason: String,
+}
+
+impl TamperedEventLog {
+    /// Returns the path of the file holding the entry.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the position of the entry in the log.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns why the entry was rejected.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+// The position of an event log writing its next entry
+struct LogHead {
+    path: PathBuf,
+    max_segment_bytes: u64,
+    segment: u32,
+    segment_bytes: u64,
+    next_sequence: u64,
+    last_hash: String,
+    clock: Arc<dyn Clock>,
+    _lock: StoreLock,
+}
+
+/// An append-only audit trail of what a wallet did, kept in files
+///
+/// Each entry is a line of JSON holding a [`WalletEvent`], the time it was written, and the hash of the entry
+/// before it, so that [`EventLog::verify_integrity`] detects entries that were altered, removed, or reordered.
+/// Once the file reaches [`EventLog::DEFAULT_MAX_SEGMENT_BYTES`], or the size set with
+/// [`EventLog::with_max_segment_bytes`], entries continue in a new file next to it, named after the log with a
+/// `.1`, `.2`, ... suffix, whose first entry chains from the last entry of the file before.
+///
+/// The log is shared by its clones, e.g. with a [`crate::ProgramManager`] and a [`crate::SyncService`], which write
+/// their events into it. It is written by one process at a time: the log holds a [`StoreLock`] on its first file
+/// while it is open, so that another process opening it fails with [`super::StoreLocked`].
+pub struct EventLog<N: Network> {
+    head: Arc<Mutex<LogHead>>,
+    _network: PhantomData<N>,
+}
+
+impl<N: Network> Clone for EventLog<N> {
+    fn clone(&self) -> Self {
+        Self { head: self.head.clone(), _network: PhantomData }
+    }
+}
+
+impl<N: Network> fmt::Debug for EventLog<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let head = lock(&self.head);
+        f.debug_struct("EventLog").field("path", &head.path).field("next_sequence", &head.next_sequence).finish()
+    }
+}
+
+impl<N: Network> EventLog<N> {
+    /// The default size of a file of the log, past which its entries continue in a new file
+    pub const DEFAULT_MAX_SEGMENT_BYTES: u64 = 16 * 1024 * 1024;
+
+    /// Open the log kept in the given file and its continuation files, which are created on the first entry if
+    /// they do not exist, waiting up to [`DEFAULT_LOCK_TIMEOUT`] for another process to close it. New entries chain
+    /// from the last entry of the log.
+    ///
+    /// An entry left partly written at the end of the log, by a process that crashed while appending it, was never
+    /// acknowledged, so it is removed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_timeout(path, DEFAULT_LOCK_TIMEOUT)
+    }
+
+    /// Open the log as [`EventLog::open`] does, waiting up to `timeout` for another process to close it.
+    pub fn open_with_timeout(path: impl AsRef<Path>, timeout: Duration) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let lock = StoreLock::exclusive(&path, timeout)?;
+        let mut segment = 0;
+        while segment_path(&path, segment + 1).exists() {
+            segment += 1;
+        }
+        let segment_path = segment_path(&path, segment);
+        let (segment_bytes, last) = match segment_path.exists() {
+            true => {
+                let last = recover_segment::<N>(&segment_path)?.pop();
+                (fs::metadata(&segment_path)?.len(), last)
+            }
+            false => (0, None),
+        };
+        let (next_sequence, last_hash) = match last {
+            Some(entry) => (entry.sequence + 1, entry.hash),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        let head = LogHead {
+            path,
+            max_segment_bytes: Self::DEFAULT_MAX_SEGMENT_BYTES,
+            segment,
+            segment_bytes,
+            next_sequence,
+            last_hash,
+            clock: Arc::new(SystemClock),
+            _lock: lock,
+        };
+        Ok(Self { head: Arc::new(Mutex::new(head)), _network: PhantomData })
+    }
+
+    /// Set the size of a file of the log, past which its entries continue in a new file.
+    pub fn with_max_segment_bytes(self, max_segment_bytes: u64) -> Self {
+        lock(&self.head).max_segment_bytes = max_segment_bytes;
+        self
+    }
+
+    /// Stamp the entries with the time of the given clock, instead of the clock of the operating system.
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        lock(&self.head).clock = Arc::new(clock);
+        self
+    }
+
+    /// Returns the path of the first file of the log.
+    pub fn path(&self) -> PathBuf {
+        lock(&self.head).path.clone()
+    }
+
+    /// Returns the paths of the files of the log, in the order of their entries.
+    pub fn segments(&self) -> Vec<PathBuf> {
+        let head = lock(&self.head);
+        (0..=head.segment).map(|segment| segment_path(&head.path, segment)).filter(|path| path.exists()).collect()
+    }
+
+    /// Returns the number of entries written to the log.
+    pub fn len(&self) -> u64 {
+        lock(&self.head).next_sequence
+    }
+
+    /// Returns `true` if no entry was written to the log.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write an event at the end of the log, synced to disk before it returns.
+    pub fn append(&self, event: WalletEvent<N>) -> Result<EventLogEntry<N>> {
+        let mut head = lock(&self.head);
+        let timestamp = unix_time(head.clock.as_ref());
+        let (sequence, previous_hash) = (head.next_sequence, head.last_hash.clone());
+        let mut entry = EventLogEntry { sequence, timestamp, event, previous_hash, hash: String::new() };
+        entry.hash = entry.content_hash()?;
+        let line = format!("{}\n", serde_json::to_string(&entry)?);
+        if head.segment_bytes > 0 && head.segment_bytes + line.len() as u64 > head.max_segment_bytes {
+            head.segment += 1;
+            head.segment_bytes = 0;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(segment_path(&head.path, head.segment))?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        head.segment_bytes += line.len() as u64;
+        head.next_sequence += 1;
+        head.last_hash = entry.hash.clone();
+        Ok(entry)
+    }
+
+    /// Returns the entries of the log, oldest first.
+    pub fn entries(&self) -> Result<Vec<EventLogEntry<N>>> {
+        let mut entries = vec![];
+        for path in self.segments() {
+            entries.extend(read_segment(&path)?);
+        }
+        Ok(entries)
+    }
+
+    /// Returns the entries of the log matching the query, oldest first.
+    pub fn query(&self, query: &EventQuery<N>) -> Result<Vec<EventLogEntry<N>>> {
+        Ok(self.entries()?.into_iter().filter(|entry| query.matches(entry)).collect())
+    }
+
+    /// Walk the hash chain of the log from its first entry, returning the number of entries.
+    ///
+    /// Fails with [`TamperedEventLog`] at the first entry that does not parse, whose hash does not match its
+    /// contents, or that does not chain to the entry before it, as well as if the log ends before the last entry
+    /// written through this log.
+    pub fn verify_integrity(&self) -> Result<u64> {
+        let (mut sequence, mut previous_hash) = (0, GENESIS_HASH.to_string());
+        let mut last_path = self.path();
+        for path in self.segments() {
+            let tampered =
+                |sequence, reason: &str| TamperedEventLog { path: path.clone(), sequence, reason: reason.into() };
+            for line in fs::read_to_string(&path)?.lines() {
+                let entry = serde_json::from_str::<EventLogEntry<N>>(line)
+                    .map_err(|error| tampered(sequence, &format!("the entry does not parse: {error}")))?;
+                if entry.sequence != sequence {
+                    return Err(tampered(sequence, &format!("found entry {} in its place", entry.sequence)).into());
+                }
+                if entry.previous_hash != previous_hash {
+                    return Err(tampered(sequence, "it does not chain to the entry before it").into());
+                }
+                if entry.content_hash()? != entry.hash {
+                    return Err(tampered(sequence, "its hash does not match its contents").into());
+                }
+                (sequence, previous_hash) = (sequence + 1, entry.hash);
+            }
+            last_path = path;
+        }
+        let head = lock(&self.head);
+        if sequence != head.next_sequence || previous_hash != head.last_hash {
+            let reason = match head.next_sequence.checked_sub(1) {
+                Some(last) if sequence < head.next_sequence => {
+                    format!("the log ends before entry {last} written to it")
+                }
+                _ => "the log does not end with the last entry written to it".to_string(),
+            };
+            return Err(TamperedEventLog { path: last_path, sequence, reason }.into());
+        }
+        Ok(sequence)
+    }
+
+    /// Migrate a store file written at an older version, as by [`Persist::migrate`], writing the migration to the
+    /// log. Returns the version the file was written at, or `None` if it was current.
+    pub fn migrate<T: Persist>(&self, path: impl AsRef<Path>) -> Result<Option<u16>> {
+        let from_version = T::migrate(path.as_ref())?;
+        if let Some(from_version) = from_version {
+            let (store, path) = (T::NAME.to_string(), path.as_ref().to_path_buf());
+            self.append(WalletEvent::StoreMigrated { store, path, from_version, to_version: T::VERSION })?;
+        }
+        Ok(from_version)
+    }
+}
+
+// Returns the path of the file holding the given segment of the log at `path`
+fn segment_path(path: &Path, segment: u32) -> PathBuf {
+    if segment == 0 {
+        return path.to_path_buf();
+    }
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(format!(".{segment}"));
+    path.with_file_name(file_name)
+}
+
+// Read the entries of the last file of the log, first truncating a partial line left at its end by an append that
+// was cut short
+fn recover_segment<N: Network>(path: &Path) -> Result<Vec<EventLogEntry<N>>> {
+    let contents = fs::read(path)?;
+    if contents.last().map_or(false, |byte| *byte != b'\n') {
+        let complete = contents.iter().rposition(|byte| *byte == b'\n').map_or(0, |end| end + 1);
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(complete as u64)?;
+        file.sync_data()?;
+    }
+    read_segment(path)
+}
+
+// Read the entries of a file of the log
+fn read_segment<N: Network>(path: &Path) -> Result<Vec<EventLogEntry<N>>> {
+    fs::read_to_string(path)?
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|error| anyhow!("Failed to parse an entry of the event log at '{}': {error}", path.display()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "async"))]
+    use crate::{
+        test_helpers::{genesis_block, sample_record, MockResponse, MockServer},
+        testnet3,
+        Microcredits,
+        PendingTransaction,
+        PolicyViolation,
+        ProgramManager,
+        RecordStore,
+        SpendingPolicy,
+    };
+    use crate::{
+        test_helpers::{sample_transaction, sample_transition, CurrentNetwork},
+        MockClock,
+        StoreLocked,
+    };
+
+    #[cfg(not(feature = "async"))]
+    use snarkvm_console::account::PrivateKey;
+    use snarkvm_console::prelude::Uniform;
+    use snarkvm_utilities::TestRng;
+    use std::env;
+    #[cfg(not(feature = "async"))]
+    use std::{
+        str::FromStr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    type N = CurrentNetwork;
+
+    // Returns the path of the log of a test in the temporary directory, removing the files of a previous run
+    fn log_path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("aleo-event-log-{name}-{}", std::process::id()));
+        for segment in 0..64 {
+            let _ = fs::remove_file(segment_path(&path, segment));
+        }
+        path
+    }
+
+    fn scan(start_height: u32) -> WalletEvent<N> {
+        WalletEvent::Scan { start_height, end_height: start_height + 50, accounts: 1, records: 0 }
+    }
+
+    // Returns the error of a log failing its integrity check
+    fn tampered(event_log: &EventLog<N>) -> TamperedEventLog {
+        event_log.verify_integrity().unwrap_err().downcast().unwrap()
+    }
+
+    #[cfg(not(feature = "async"))]
+    #[test]
+    fn test_event_log_records_transfer_flow() {
+        let rng = &mut TestRng::default();
+        let path = log_path("transfer");
+        let event_log = EventLog::<N>::open(&path).unwrap();
+
+        // The node rejects the first broadcast and accepts the others.
+        let block_json = genesis_block().to_string();
+        let broadcasts = AtomicUsize::new(0);
+        let server = MockServer::start(move |request| {
+            if request.path != "/testnet3/transaction/broadcast" {
+                return None;
+            }
+            match broadcasts.fetch_add(1, Ordering::SeqCst) {
+                0 => Some(MockResponse::text(400, "Invalid transaction")),
+                _ => Some(MockResponse::json(&block_json)),
+            }
+        });
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let address = Address::try_from(private_key).unwrap();
+        let recipient = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let program_manager = ProgramManager::new(private_key, testnet3(server.base_url()))
+            .with_record_store(RecordStore::new())
+            .with_spending_policy(SpendingPolicy::new().with_transaction_limit(50))
+            .with_event_log(event_log.clone());
+
+        // A transfer over the limit is refused before it is proven, and the refusal is logged.
+        let (input_record, _) = sample_record(address, 100, rng);
+        let (fee_record, _) = sample_record(address, 10, rng);
+        let error = program_manager
+            .build_transfer(Microcredits(100), Microcredits(1), recipient, input_record, fee_record)
+            .unwrap_err();
+        let violation = error.downcast::<PolicyViolation>().unwrap();
+        program_manager.enforce_spending_policy(&PendingTransaction::transfer(recipient, 40, 1).unwrap()).unwrap();
+
+        // The transfer built within the limit is logged with the outcome of each of its broadcasts.
+        let transaction = program_manager.log_built(sample_transaction([sample_transition(&[], &[], rng)])).unwrap();
+        let transaction_id = transaction.id();
+        assert!(program_manager.broadcast(transaction.clone()).is_err());
+        program_manager.broadcast(transaction).unwrap();
+
+        let kinds = event_log.entries().unwrap().iter().map(|entry| entry.event().kind()).collect::<Vec<_>>();
+        assert_eq!(kinds, [
+            WalletEventKind::PolicyDecision,
+            WalletEventKind::PolicyDecision,
+            WalletEventKind::TransactionBuilt,
+            WalletEventKind::TransactionBroadcast,
+            WalletEventKind::TransactionBroadcast,
+        ]);
+        let query = EventQuery { kinds: vec![WalletEventKind::PolicyDecision], ..Default::default() };
+        let decisions = event_log.query(&query).unwrap();
+        let violations = decisions.iter().map(|entry| match entry.event() {
+            WalletEvent::PolicyDecision { violation, .. } => violation.clone(),
+            event => panic!("Unexpected event {event:?}"),
+        });
+        assert_eq!(violations.collect::<Vec<_>>(), [Some(violation.to_string()), None]);
+        assert_eq!(decisions[0].event(), &WalletEvent::PolicyDecision {
+            program: ProgramID::from_str("credits.aleo").unwrap(),
+            function: Identifier::from_str("transfer").unwrap(),
+            recipient: Some(recipient),
+            amount: 100,
+            fee: 1,
+            violation: Some(violation.to_string()),
+        });
+
+        let query = EventQuery { transaction_id: Some(transaction_id), ..Default::default() };
+        let events = event_log.query(&query).unwrap().into_iter().map(|entry| entry.event().clone());
+        let events = events.collect::<Vec<_>>();
+        assert_eq!(events[0], WalletEvent::TransactionBuilt {
+            transaction_id,
+            functions: vec!["credits.aleo/transfer".to_string()]
+        });
+        assert!(matches!(&events[1], WalletEvent::TransactionBroadcast { error: Some(_), .. }));
+        assert_eq!(events[2], WalletEvent::TransactionBroadcast { transaction_id, error: None });
+        assert_eq!(events.len(), 3);
+        assert_eq!(event_log.verify_integrity().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_event_log_verifies_integrity() {
+        let rng = &mut TestRng::default();
+        let path = log_path("integrity");
+        let clock = MockClock::new().with_unix_time(1_000);
+        let event_log = EventLog::<N>::open(&path).unwrap().with_clock(clock.clone());
+        assert!(event_log.is_empty());
+        assert_eq!(event_log.verify_integrity().unwrap(), 0);
+
+        let transaction_id = sample_transaction([sample_transition(&[], &[], rng)]).id();
+        let commitment = Field::rand(rng);
+        let first = event_log.append(scan(0)).unwrap();
+        clock.advance(Duration::from_secs(10));
+        event_log.append(WalletEvent::RecordSpent { commitment, transaction_id, height: 7 }).unwrap();
+        assert_eq!(first.previous_hash(), GENESIS_HASH);
+        assert_eq!(event_log.verify_integrity().unwrap(), 2);
+
+        // A reopened log chains its entries from the last entry written before.
+        drop(event_log);
+        let reopened = EventLog::<N>::open(&path).unwrap().with_clock(clock);
+        assert_eq!(reopened.len(), 2);
+        let third = reopened.append(WalletEvent::FlowStep { step: 0, transaction_id }).unwrap();
+        let entries = reopened.entries().unwrap();
+        assert_eq!(third.sequence(), 2);
+        assert_eq!(third.previous_hash(), entries[1].hash());
+        assert_eq!(reopened.verify_integrity().unwrap(), 3);
+
+        // Queries filter the entries by time, kind, transaction, and record.
+        assert_eq!(first.timestamp(), 1_000);
+        let query =
+            |query: EventQuery<N>| reopened.query(&query).unwrap().iter().map(EventLogEntry::sequence).collect();
+        let all: Vec<u64> = query(EventQuery { since: Some(1_000), ..Default::default() });
+        assert_eq!(all, [0, 1, 2]);
+        assert_eq!(query(EventQuery { since: Some(1_010), ..Default::default() }), [1, 2]);
+        assert_eq!(query(EventQuery { until: Some(1_010), ..Default::default() }), [0]);
+        assert_eq!(query(EventQuery { kinds: vec![WalletEventKind::Scan], ..Default::default() }), [0]);
+        assert_eq!(query(EventQuery { transaction_id: Some(transaction_id), ..Default::default() }), [1, 2]);
+        let kinds = vec![WalletEventKind::RecordSpent];
+        assert_eq!(query(EventQuery { commitment: Some(commitment), kinds, ..Default::default() }), [1]);
+
+        // A log missing the entries written through it was truncated.
+        fs::write(&path, fs::read_to_string(&path).unwrap().lines().next().unwrap().to_string() + "\n").unwrap();
+        let error = tampered(&reopened);
+        assert_eq!(error.sequence(), 1);
+        assert_eq!(error.reason(), "the log ends before entry 2 written to it");
+
+        // A log holding entries that were not written through it, e.g. by a process ignoring its lock, does not end
+        // with its own last entry.
+        let foreign_path = log_path("foreign");
+        let foreign = EventLog::<N>::open(&foreign_path).unwrap();
+        fs::copy(&path, &foreign_path).unwrap();
+        let error = tampered(&foreign);
+        assert_eq!(error.sequence(), 1);
+        assert_eq!(error.reason(), "the log does not end with the last entry written to it");
+    }
+
+    #[test]
+    fn test_event_log_detects_tampered_entry() {
+        let path = log_path("tampered");
+        let event_log = EventLog::<N>::open(&path).unwrap();
+        for start_height in 0..5 {
+            event_log.append(scan(start_height * 50)).unwrap();
+        }
+        let lines = fs::read_to_string(&path).unwrap().lines().map(str::to_string).collect::<Vec<_>>();
+        let rewrite = |lines: &[String]| fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        // Altering the event of the middle entry breaks its hash.
+        let mut altered = lines.clone();
+        altered[2] = altered[2].replace("\"records\":0", "\"records\":3");
+        rewrite(&altered);
+        let error = tampered(&event_log);
+        assert_eq!((error.sequence(), error.reason()), (2, "its hash does not match its contents"));
+        assert_eq!(error.path(), path);
+        let message = "was tampered with at entry 2: its hash does not match its contents";
+        assert_eq!(error.to_string(), format!("The event log at '{}' {message}", path.display()));
+
+        // Rehashing the altered entry breaks the link of the entry after it.
+        let mut entry = serde_json::from_str::<EventLogEntry<N>>(&altered[2]).unwrap();
+        entry.hash = entry.content_hash().unwrap();
+        altered[2] = serde_json::to_string(&entry).unwrap();
+        rewrite(&altered);
+        let error = tampered(&event_log);
+        assert_eq!((error.sequence(), error.reason()), (3, "it does not chain to the entry before it"));
+
+        // Removing the middle entry leaves a gap in the sequence.
+        let mut removed = lines.clone();
+        removed.remove(2);
+        rewrite(&removed);
+        assert_eq!((tampered(&event_log).sequence(), tampered(&event_log).reason()), (2, "found entry 3 in its place"));
+
+        rewrite(&lines);
+        assert_eq!(event_log.verify_integrity().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_event_log_rotates_with_continuity() {
+        let path = log_path("rotation");
+        let event_log = EventLog::<N>::open(&path).unwrap().with_max_segment_bytes(600);
+        for start_height in 0..10 {
+            event_log.append(scan(start_height * 50)).unwrap();
+        }
+        let segments = event_log.segments();
+        assert!(segments.len() > 2);
+        assert_eq!(segments[0], path);
+        assert_eq!(segments[1], path.with_file_name(format!("{}.1", path.file_name().unwrap().to_string_lossy())));
+        for segment in &segments {
+            let size = fs::metadata(segment).unwrap().len();
+            assert!(size > 0 && size <= 600);
+        }
+
+        // The first entry of each continuation file chains from the last entry of the file before it.
+        for pair in segments.windows(2) {
+            let last = read_segment::<N>(&pair[0]).unwrap().pop().unwrap();
+            let first = &read_segment::<N>(&pair[1]).unwrap()[0];
+            assert_eq!(first.previous_hash(), last.hash());
+            assert_eq!(first.sequence(), last.sequence() + 1);
+        }
+
+        // A reopened log continues in its last file.
+        drop(event_log);
+        let reopened = EventLog::<N>::open(&path).unwrap().with_max_segment_bytes(600);
+        let entry = reopened.append(scan(500)).unwrap();
+        assert_eq!(entry.sequence(), 10);
+        assert_eq!(reopened.verify_integrity().unwrap(), 11);
+        let sequences = reopened.entries().unwrap().iter().map(EventLogEntry::sequence).collect::<Vec<_>>();
+        assert_eq!(sequences, (0..11).collect::<Vec<_>>());
+
+        // Removing a continuation file breaks the chain of the file after it.
+        fs::remove_file(&segments[1]).unwrap();
+        let error = tampered(&reopened);
+        assert_eq!(error.path(), segments[2]);
+        let found = read_segment::<N>(&segments[2]).unwrap()[0].sequence();
+        assert_eq!(error.reason(), format!("found entry {found} in its place"));
+    }
+
+    #[test]
+    fn test_event_log_recovers_torn_entry() {
+        let path = log_path("torn");
+        let event_log = EventLog::<N>::open(&path).unwrap();
+        event_log.append(scan(0)).unwrap();
+        event_log.append(scan(50)).unwrap();
+        drop(event_log);
+
+        // A crash while appending the third entry leaves part of its line at the end of the file.
+        let complete = fs::read_to_string(&path).unwrap();
+        let torn = serde_json::to_string(&scan(100)).unwrap();
+        fs::write(&path, format!("{complete}{}", &torn[..torn.len() / 2])).unwrap();
+
+        // The partial entry was never acknowledged, so the reopened log drops it and chains from the entry before.
+        let reopened = EventLog::<N>::open(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), complete);
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened.append(scan(100)).unwrap().sequence(), 2);
+        assert_eq!(reopened.verify_integrity().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_event_log_single_writer() {
+        let path = log_path("locked");
+        let event_log = EventLog::<N>::open(&path).unwrap();
+        event_log.append(scan(0)).unwrap();
+
+        // The log is locked while it is open, and can be opened again once it is closed.
+        let error = EventLog::<N>::open_with_timeout(&path, Duration::ZERO).unwrap_err();
+        assert_eq!(error.downcast_ref::<StoreLocked>().unwrap().pid(), Some(std::process::id()));
+        drop(event_log);
+        let reopened = EventLog::<N>::open_with_timeout(&path, Duration::ZERO).unwrap();
+        assert_eq!(reopened.len(), 1);
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::BlockHeight;
+
+use serde::{Deserialize, Serialize};
+use snarkvm_console::{program::Network, types::Field};
+
+/// Whether a record entered or left a wallet
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryKind {
+    /// The record was created for the wallet
+    Received,
+    /// The record was spent by the wallet
+    Spent,
+}
+
+/// A record that entered or left a wallet, in the transaction that created or spent it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct HistoryEntry<N: Network> {
+    kind: HistoryKind,
+    height: BlockHeight,
+    transaction_id: N::TransactionID,
+    commitment: Field<N>,
+    gates: u64,
+    // Entries written before memos were kept have none.
+    #[serde(default)]
+    memo: Option<Vec<u8>>,
+}
+
+impl<N: Network> HistoryEntry<N> {
+    /// Create an entry for a record created or spent in the given transaction.
+    pub fn new(
+        kind: HistoryKind,
+        height: BlockHeight,
+        transaction_id: N::TransactionID,
+        commitment: Field<N>,
+        gates: u64,
+    ) -> Self {
+        Self { kind, height, transaction_id, commitment, gates, memo: None }
+    }
+
+    /// Set the memo held by the record, as read by [`crate::extract_memo`].
+    pub fn with_memo(mut self, memo: Option<Vec<u8>>) -> Self {
+        self.memo = memo;
+        self
+    }
+
+    /// Returns whether the record was received or spent.
+    pub fn kind(&self) -> HistoryKind {
+        self.kind
+    }
+
+    /// Returns the height of the block holding the transaction.
+    pub fn height(&self) -> BlockHeight {
+        self.height
+    }
+
+    /// Returns the ID of the transaction that created or spent the record.
+    pub fn transaction_id(&self) -> N::TransactionID {
+        self.transaction_id
+    }
+
+    /// Returns the commitment of the record.
+    pub fn commitment(&self) -> Field<N> {
+        self.commitment
+    }
+
+    /// Returns the gates held by the record.
+    pub fn gates(&self) -> u64 {
+        self.gates
+    }
+
+    /// Returns the memo held by the record, if it follows the memo convention.
+    pub fn memo(&self) -> Option<&[u8]> {
+        self.memo.as_deref()
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{migrations::migrate_file, read_store, write_store, Codec, Persist};
+
+use anyhow::{bail, Result};
+use fs2::FileExt;
+use std::{
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// How long saving or loading a store waits for a lock held by another process, before failing with [`StoreLocked`]
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The interval at which a contended lock is tried again
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The error returned when a store file is locked by another process for longer than the lock timeout
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("The store at '{}' is locked by {}", path.display(), holder(*pid))]
+pub struct StoreLocked {
+    path: PathBuf,
+    pid: Option<u32>,
+}
+
+impl StoreLocked {
+    /// Returns the path of the locked store.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the ID of the process holding the lock, if it holds it exclusively.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+}
+
+fn holder(pid: Option<u32>) -> String {
+    match pid {
+        Some(pid) => format!("process {pid}"),
+        None => "another process".to_string(),
+    }
+}
+
+/// An advisory lock on a store file, released when it is dropped
+///
+/// The lock is taken on a `.lock` file next to the store, as saves replace the store file itself. Writers hold
+/// the lock exclusively, and write their process ID to the lock file so that contending processes can name them,
+/// while readers share it. Locks are advisory: they only exclude processes that lock the store as well. As the
+/// lock is released when the guard is dropped, it is also released when a panic unwinds past it, or when the
+/// process exits.
+#[derive(Debug)]
+pub struct StoreLock {
+    file: File,
+    path: PathBuf,
+    exclusive: bool,
+}
+
+impl StoreLock {
+    /// Lock the store at the given path exclusively, waiting up to `timeout` for other holders to release it.
+    pub fn exclusive(path: impl AsRef<Path>, timeout: Duration) -> Result<Self> {
+        Self::acquire(path.as_ref(), true, timeout)
+    }
+
+    /// Lock the store at the given path for reading, alongside other readers, waiting up to `timeout` for a writer
+    /// to release it.
+    pub fn shared(path: impl AsRef<Path>, timeout: Duration) -> Result<Self> {
+        Self::acquire(path.as_ref(), false, timeout)
+    }
+
+    /// Returns the path of the locked store.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true` if the lock is held exclusively.
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    fn acquire(path: &Path, exclusive: bool, timeout: Duration) -> Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(lock_path_of(path))?;
+        let start = Instant::now();
+        loop {
+            // The locks are taken through `FileExt`, as the methods of `File` of the same names need Rust 1.89.
+            let locked = match exclusive {
+                true => FileExt::try_lock_exclusive(&file),
+                false => FileExt::try_lock_shared(&file),
+            };
+            match locked {
+                Ok(()) => break,
+                Err(error) if error.kind() == fs2::lock_contended_error().kind() => {
+                    if start.elapsed() >= timeout {
+                        let pid = read_pid(&mut file);
+                        return Err(StoreLocked { path: path.to_path_buf(), pid }.into());
+                    }
+                    thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(error) => bail!("Failed to lock the store at '{}': {error}", path.display()),
+            }
+        }
+        if exclusive {
+            file.set_len(0)?;
+            file.rewind()?;
+            file.write_all(std::process::id().to_string().as_bytes())?;
+        }
+        Ok(Self { file, path: path.to_path_buf(), exclusive })
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        // Clear the process ID first, so that it never names a process that no longer holds the lock.
+        if self.exclusive {
+            let _ = self.file.set_len(0);
+        }
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+// Read the process ID written to a lock file by its exclusive holder, if any
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+// Returns the path of the lock file of the store at the given path
+fn lock_path_of(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(".lock");
+    path.with_file_name(file_name)
+}
+
+/// A store loaded from a file that stays locked while the store is open
+///
+/// A store opened for writing holds the lock exclusively, so that another process opening the same file fails
+/// with [`StoreLocked`] instead of overwriting its changes, while stores opened read-only share the lock. Saving a
+/// store opened read-only fails.
+#[derive(Debug)]
+pub struct LockedStore<T: Persist> {
+    store: T,
+    lock: StoreLock,
+}
+
+impl<T: Persist> LockedStore<T> {
+    /// Open the store in the given file for writing, waiting up to `timeout` for other processes to release it.
+    ///
+    /// A file written at an older version of the store is migrated in place, as by [`Persist::migrate`], while a
+    /// store opened for reading is only migrated in memory.
+    pub fn open(path: impl AsRef<Path>, timeout: Duration) -> Result<Self> {
+        let lock = StoreLock::exclusive(path.as_ref(), timeout)?;
+        migrate_file::<T>(path.as_ref())?;
+        Ok(Self { store: read_store(path.as_ref())?, lock })
+    }
+
+    /// Open the store in the given file for reading, waiting up to `timeout` for a writer to release it.
+    pub fn open_read_only(path: impl AsRef<Path>, timeout: Duration) -> Result<Self> {
+        let lock = StoreLock::shared(path.as_ref(), timeout)?;
+        Ok(Self { store: read_store(path.as_ref())?, lock })
+    }
+
+    /// Write a new store to the given file, and keep it open for writing.
+    pub fn create<C: Codec>(path: impl AsRef<Path>, store: T, codec: &C, timeout: Duration) -> Result<Self> {
+        let lock = StoreLock::exclusive(path.as_ref(), timeout)?;
+        write_store(&store, path.as_ref(), codec)?;
+        Ok(Self { store, lock })
+    }
+
+    /// Returns the store.
+    pub fn get(&self) -> &T {
+        &self.store
+    }
+
+    /// Returns the store mutably, e.g. to change it before saving it.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.store
+    }
+
+    /// Returns the lock held on the file.
+    pub fn lock(&self) -> &StoreLock {
+        &self.lock
+    }
+
+    /// Write the store back to its file atomically, using the given codec.
+    pub fn save<C: Codec>(&self, codec: &C) -> Result<()> {
+        if !self.lock.is_exclusive() {
+            bail!("The store at '{}' was opened read-only", self.lock.path().display());
+        }
+        write_store(&self.store, self.lock.path(), codec)
+    }
+
+    /// Returns the store, releasing the lock.
+    pub fn into_inner(self) -> T {
+        self.store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        store::Json,
+        test_helpers::{genesis_block, sample_record, temp_path, CurrentNetwork},
+        BlockCache,
+        RecordStore,
+    };
+
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        prelude::Uniform,
+        types::Field,
+    };
+    use snarkvm_utilities::TestRng;
+    use std::{fs, panic, sync::mpsc};
+
+    type N = CurrentNetwork;
+
+    fn remove(path: &Path) {
+        fs::remove_file(path).unwrap();
+        fs::remove_file(lock_path_of(path)).unwrap();
+    }
+
+    fn locked(error: anyhow::Error) -> StoreLocked {
+        error.downcast::<StoreLocked>().unwrap()
+    }
+
+    #[test]
+    fn test_store_lock_contention() {
+        let path = temp_path("lock-contention");
+
+        // Readers share the lock, and exclude writers.
+        let reader = StoreLock::shared(&path, Duration::ZERO).unwrap();
+        let other_reader = thread::scope(|scope| scope.spawn(|| StoreLock::shared(&path, Duration::ZERO)).join());
+        assert!(other_reader.unwrap().is_ok());
+        let error = locked(StoreLock::exclusive(&path, Duration::ZERO).unwrap_err());
+        assert_eq!(error.pid(), None);
+        assert_eq!(error.to_string(), format!("The store at '{}' is locked by another process", path.display()));
+        drop(reader);
+
+        // Writers exclude readers and writers, and are named by their process ID.
+        let writer = StoreLock::exclusive(&path, Duration::ZERO).unwrap();
+        let error = locked(StoreLock::shared(&path, Duration::from_millis(50)).unwrap_err());
+        let pid = std::process::id();
+        assert_eq!(error.pid(), Some(pid));
+        assert_eq!(error.to_string(), format!("The store at '{}' is locked by process {pid}", path.display()));
+
+        // A waiting writer takes the lock once it is released, within the timeout.
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            let waiter = scope.spawn(|| {
+                sender.send(()).unwrap();
+                StoreLock::exclusive(&path, Duration::from_secs(10)).map(|lock| lock.is_exclusive())
+            });
+            receiver.recv().unwrap();
+            thread::sleep(Duration::from_millis(50));
+            drop(writer);
+            assert!(waiter.join().unwrap().unwrap());
+        });
+        fs::remove_file(lock_path_of(&path)).unwrap();
+    }
+
+    #[test]
+    fn test_store_lock_released_on_panic() {
+        let path = temp_path("lock-panic");
+        let panicked = panic::catch_unwind(|| {
+            let _lock = StoreLock::exclusive(&path, Duration::ZERO).unwrap();
+            panic!("The writer crashed");
+        });
+        assert!(panicked.is_err());
+        assert!(StoreLock::exclusive(&path, Duration::ZERO).is_ok());
+        fs::remove_file(lock_path_of(&path)).unwrap();
+    }
+
+    #[test]
+    fn test_locked_record_store() {
+        let rng = &mut TestRng::default();
+        let address = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let path = temp_path("lock-records");
+        let mut records = LockedStore::create(&path, RecordStore::<N>::new(), &Json, Duration::ZERO).unwrap();
+
+        // Another open of the file fails while the store is open for writing, and saves and loads wait for it.
+        let open = || LockedStore::<RecordStore<N>>::open(&path, Duration::ZERO);
+        let error = thread::scope(|scope| scope.spawn(open).join().unwrap().unwrap_err());
+        assert_eq!(locked(error).pid(), Some(std::process::id()));
+        assert!(RecordStore::<N>::load(&path).is_err());
+        records.get_mut().insert(Field::rand(rng), sample_record(address, 100, rng).0, 1);
+        records.save(&Json).unwrap();
+        drop(records);
+
+        // Stores opened read-only share the file, and cannot be saved.
+        let reader = LockedStore::<RecordStore<N>>::open_read_only(&path, Duration::ZERO).unwrap();
+        let other_reader = LockedStore::<RecordStore<N>>::open_read_only(&path, Duration::ZERO).unwrap();
+        assert_eq!(reader.get().len(), 1);
+        assert_eq!(RecordStore::<N>::load(&path).unwrap().len(), 1);
+        assert!(LockedStore::<RecordStore<N>>::open(&path, Duration::ZERO).is_err());
+        let error = reader.save(&Json).unwrap_err();
+        assert_eq!(error.to_string(), format!("The store at '{}' was opened read-only", path.display()));
+        drop((reader, other_reader));
+        remove(&path);
+    }
+
+    #[test]
+    fn test_locked_block_cache() {
+        let path = temp_path("lock-blocks");
+        let mut blocks = BlockCache::<N>::new(2);
+        blocks.insert(genesis_block());
+        let blocks = LockedStore::create(&path, blocks, &Json, Duration::ZERO).unwrap();
+        let open = || LockedStore::<BlockCache<N>>::open_read_only(&path, Duration::ZERO);
+        let error = thread::scope(|scope| scope.spawn(open).join().unwrap().unwrap_err());
+        assert!(locked(error).pid().is_some());
+        assert!(BlockCache::<N>::new(2).save(&path, &Json).is_err());
+        drop(blocks);
+        assert_eq!(LockedStore::<BlockCache<N>>::open(&path, Duration::ZERO).unwrap().into_inner().len(), 1);
+        remove(&path);
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+#[cfg(feature = "bincode")]
+use super::Bincode;
+#[cfg(feature = "cbor")]
+use super::Cbor;
+#[cfg(any(feature = "bincode", feature = "cbor"))]
+use super::Codec;
+use super::{read_store, temp_path_of, write_store, Json, Persist, HEADER_SIZE, MAGIC};
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use snarkvm_console::program::{Network, Plaintext, ProgramID, Record};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// A step upgrading the serialized form of a store from one schema version to the next
+///
+/// Schema changes that only add fields with a default need no migration. A change that needs the stored data
+/// rewritten bumps the version of the store, and adds a migration from the previous version, along with a file in
+/// the previous format under `tests/fixtures` to test it against.
+#[derive(Copy, Clone)]
+pub struct Migration {
+    from: u16,
+    upgrade: fn(&mut Value) -> Result<()>,
+}
+
+impl Migration {
+    /// Create a migration upgrading the JSON body of a store from the given version to the next.
+    pub fn new(from: u16, upgrade: fn(&mut Value) -> Result<()>) -> Self {
+        Self { from, upgrade }
+    }
+
+    /// Returns the version the migration upgrades from.
+    pub fn from(&self) -> u16 {
+        self.from
+    }
+
+    /// Returns the version the migration upgrades to.
+    pub fn to(&self) -> u16 {
+        self.from + 1
+    }
+
+    /// Upgrade the JSON body of a store.
+    pub fn apply(&self, body: &mut Value) -> Result<()> {
+        (self.upgrade)(body)
+    }
+}
+
+// Upgrade the JSON body of a store written at the given version to the current version of the store
+pub(super) fn migrate_body<T: Persist>(body: &[u8], version: u16) -> Result<Vec<u8>> {
+    let mut migrations = T::migrations();
+    migrations.retain(|migration| migration.from() >= version && migration.from() < T::VERSION);
+    migrations.sort_by_key(Migration::from);
+    let mut value = serde_json::from_slice::<Value>(body)
+        .map_err(|error| anyhow!("Failed to decode the {} from JSON: {error}", T::NAME))?;
+    for migration in migrations {
+        let (from, to) = (migration.from(), migration.to());
+        migration
+            .apply(&mut value)
+            .map_err(|error| anyhow!("Failed to migrate the {} from version {from} to {to}: {error}", T::NAME))?;
+    }
+    Ok(serde_json::to_vec(&value)?)
+}
+
+// Returns `true` if a store written at the given version needs a migration to be read
+pub(super) fn needs_migration<T: Persist>(version: u16) -> bool {
+    T::migrations().iter().any(|migration| migration.from() >= version && migration.from() < T::VERSION)
+}
+
+// Returns `true` if the store file at the given path, or the temporary file of an interrupted save, was written
+// at an older version of the store
+pub(super) fn is_outdated<T: Persist>(path: &Path) -> Result<bool> {
+    for path in [temp_path_of(path), path.to_path_buf()] {
+        if let Some((_, version)) = read_header(&path)? {
+            if version < T::VERSION {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+// Upgrade a store file written at an older version in place, backing up the previous file, and return the version
+// it was written at. The file must be locked exclusively.
+pub(super) fn migrate_file<T: Persist>(path: &Path) -> Result<Option<u16>> {
+    // The store is read first, so that an interrupted save is recovered before the file is backed up.
+    let store = read_store::<T>(path)?;
+    let (tag, version) = match read_header(path)? {
+        Some((tag, version)) if version < T::VERSION => (tag, version),
+        _ => return Ok(None),
+    };
+    let backup_path = backup_path_of(path, version);
+    fs::copy(path, &backup_path)
+        .map_err(|error| anyhow!("Failed to back up the {} to '{}': {error}", T::NAME, backup_path.display()))?;
+    match tag {
+        #[cfg(feature = "bincode")]
+        Bincode::TAG => write_store(&store, path, &Bincode)?,
+        #[cfg(feature = "cbor")]
+        Cbor::TAG => write_store(&store, path, &Cbor)?,
+        _ => write_store(&store, path, &Json)?,
+    }
+    Ok(Some(version))
+}
+
+/// Returns the path of the backup kept when the store file at the given path is migrated from the given version.
+pub fn backup_path_of(path: &Path, version: u16) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(format!(".v{version}.bak"));
+    path.with_file_name(file_name)
+}
+
+// Returns the codec tag and schema version in the header of a store file, or `None` if there is no such file
+fn read_header(path: &Path) -> Result<Option<(u8, u16)>> {
+    let mut header = [0u8; HEADER_SIZE];
+    match File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+        Ok(()) if header.starts_with(MAGIC) => Ok(Some((header[5], u16::from_le_bytes([header[6], header[7]])))),
+        Ok(()) => bail!("The file at '{}' is not an Aleo store", path.display()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        // Files too short to hold a header are left for the load to reject.
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+// Record stores at version 4 do not attribute records to a program. Records with no entries besides their owner and
+// gates are attributed to `credits.aleo`, as credits records hold nothing else, and other records are left
+// unattributed.
+pub(super) fn record_store_v5<N: Network>(body: &mut Value) -> Result<()> {
+    let credits = ProgramID::<N>::from_str("credits.aleo")?.to_string();
+    let records = body
+        .get_mut("records")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| anyhow!("The records are missing"))?;
+    for (commitment, stored) in records {
+        let record = stored
+            .get("record")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("The record with commitment {commitment} is missing"))?;
+        let record = Record::<N, Plaintext<N>>::from_str(record)?;
+        let program_id = match record.data().is_empty() {
+            true => Value::String(credits.clone()),
+            false => Value::Null,
+        };
+        stored
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("The record with commitment {commitment} is not an object"))?
+            .insert("program_id".to_string(), program_id);
+    }
+    Ok(())
+}
+
+// Wallet snapshots at version 4 hold a record store at version 4.
+pub(super) fn wallet_snapshot_v5<N: Network>(body: &mut Value) -> Result<()> {
+    record_store_v5::<N>(body.get_mut("records").ok_or_else(|| anyhow!("The record store is missing"))?)
+}
+
+// Wallet snapshots at version 6 encrypt their records and history with the passphrase, which migrations do not
+// know. The records and history of earlier snapshots are read in plaintext until the snapshot is exported again.
+pub(super) fn wallet_snapshot_v6(_body: &mut Value) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helpers::CurrentNetwork, Microcredits, RecordStore, StoreLock, DEFAULT_LOCK_TIMEOUT};
+
+    use std::env;
+
+    type N = CurrentNetwork;
+
+    // The record store fixture at version 4 holds a spent credits record and an unspent token record.
+    const RECORD_STORE_V4: &[u8] = include_bytes!("../../tests/fixtures/record_store_v4.store");
+
+    fn fixture_path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("aleo-migration-{name}-{}", std::process::id()));
+        fs::write(&path, RECORD_STORE_V4).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_migrate_record_store_v4() {
+        let path = fixture_path("record-store");
+        let store = RecordStore::<N>::load(&path).unwrap();
+
+        // The records are kept, and attributed to a program where it is known.
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.balance(), Microcredits::ZERO);
+        let mut records = store.iter().map(|(_, stored)| stored).collect::<Vec<_>>();
+        records.sort_by_key(|stored| stored.height());
+        assert_eq!((records[0].height(), records[0].spent_height()), (3, Some(8)));
+        assert_eq!(records[0].program_id().map(ToString::to_string).as_deref(), Some("credits.aleo"));
+        assert_eq!(***records[0].record().gates(), 1500);
+        assert_eq!((records[1].height(), records[1].spent_height()), (5, None));
+        assert_eq!(records[1].program_id(), None);
+        assert_eq!(records[1].record().data().len(), 1);
+
+        // The file is rewritten at the current version, and the previous file is backed up.
+        assert_eq!(fs::read(&path).unwrap()[6..8], RecordStore::<N>::VERSION.to_le_bytes());
+        assert_eq!(fs::read(backup_path_of(&path, 4)).unwrap(), RECORD_STORE_V4);
+        assert_eq!(RecordStore::<N>::load(&path).unwrap(), store);
+        assert!(!is_outdated::<RecordStore<N>>(&path).unwrap());
+
+        fs::remove_file(backup_path_of(&path, 4)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_read_only() {
+        let path = fixture_path("read-only");
+
+        // A store opened while another process holds the lock is migrated in memory only.
+        let lock = StoreLock::shared(&path, DEFAULT_LOCK_TIMEOUT).unwrap();
+        let store = read_store::<RecordStore<N>>(&path).unwrap();
+        assert_eq!(store.iter().filter(|(_, stored)| stored.program_id().is_some()).count(), 1);
+        assert_eq!(fs::read(&path).unwrap(), RECORD_STORE_V4);
+        drop(lock);
+
+        // Migrating it explicitly reports the version it was written at, and migrating it again does nothing.
+        assert_eq!(RecordStore::<N>::migrate(&path).unwrap(), Some(4));
+        assert_eq!(RecordStore::<N>::migrate(&path).unwrap(), None);
+        assert_eq!(RecordStore::<N>::load(&path).unwrap(), store);
+
+        fs::remove_file(backup_path_of(&path, 4)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_migration_errors() {
+        let migration = Migration::new(4, record_store_v5::<N>);
+        assert_eq!((migration.from(), migration.to()), (4, 5));
+        assert!(migration.apply(&mut serde_json::json!({ "spends": [] })).is_err());
+        let error = migrate_body::<RecordStore<N>>(br#"{"records":{"1field":{"height":1}}}"#, 4).unwrap_err();
+        assert!(error.to_string().contains("Failed to migrate the record store from version 4 to 5"));
+    }
+}
@@ -0,0 +1,703 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Local persistence for records, blocks, and scan progress.
+//!
+//! Stores are written as an 8 byte header followed by the body in the chosen [`Codec`]. The header
+//! holds a magic string, the kind of store, the codec tag, and the schema version of the store, so
+//! that a file can be loaded without knowing how it was written, and files written by a newer
+//! version of the library are rejected instead of misread.
+//!
+//! Files are followed by a SHA-256 checksum of their contents, and are saved atomically: the store is
+//! written to a temporary file next to it, synced to disk, and renamed over the previous file. A crash
+//! during a save leaves either the previous store or the new one, and loading recovers from the temporary
+//! file a save left behind.
+//!
+//! Saves lock the file exclusively and loads share the lock with other loads, through a [`StoreLock`] on a
+//! `.lock` file next to the store, so that processes sharing a store do not interleave their writes. A
+//! [`LockedStore`] keeps the lock while the store is open, so that a second writer fails with [`StoreLocked`].
+//!
+//! Stores written by an older version of the library are upgraded by their [`Migration`]s when they are read.
+//! Loading such a file rewrites it at the current version, keeping the previous file next to it as a backup.
+//!
+//! An [`EventLog`] keeps an audit trail of what a wallet did, in files of hash-chained JSON lines rather than stores.
+
+mod block_cache;
+pub use block_cache::*;
+
+mod canonical;
+pub use canonical::*;
+
+mod codec;
+pub use codec::*;
+
+mod compaction;
+pub use compaction::*;
+
+mod encrypted_record_store;
+pub use encrypted_record_store::*;
+
+mod event_log;
+pub use event_log::*;
+
+mod history;
+pub use history::*;
+
+mod lock;
+pub use lock::*;
+
+mod migrations;
+pub use migrations::*;
+
+mod record_store;
+pub use record_store::*;
+
+mod scan_state;
+pub use scan_state::*;
+
+mod spend_log;
+pub use spend_log::*;
+
+mod wallet_snapshot;
+pub use wallet_snapshot::*;
+
+use crate::BlockHeight;
+
+use anyhow::{bail, ensure, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The magic string at the start of every store
+const MAGIC: &[u8; 4] = b"ALEO";
+
+/// The size of the store header in bytes
+const HEADER_SIZE: usize = 8;
+
+/// The size of the SHA-256 checksum at the end of a store file
+const CHECKSUM_SIZE: usize = 32;
+
+/// The error returned when a store file is truncated or otherwise damaged, e.g. by a crash during a save
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("The {store} at '{}' is corrupted: {reason}", path.display())]
+pub struct CorruptStore {
+    store: &'static str,
+    path: PathBuf,
+    reason: String,
+}
+
+impl CorruptStore {
+    /// Returns the name of the kind of store.
+    pub fn store(&self) -> &'static str {
+        self.store
+    }
+
+    /// Returns the path of the corrupted file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the reason the file was rejected.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+/// A store that can be persisted in any [`Codec`]
+pub trait Persist: Serialize + DeserializeOwned {
+    /// The name of the store, used in error messages
+    const NAME: &'static str;
+    /// The tag identifying the kind of store in headers
+    const KIND: u8;
+    /// The schema version of the store, incremented whenever its serialized form changes
+    const VERSION: u16;
+
+    /// Returns the migrations upgrading the stores written at older versions to the current version.
+    fn migrations() -> Vec<Migration> {
+        Vec::new()
+    }
+
+    /// Serialize the store with a header, using the given codec.
+    fn encode<C: Codec>(&self, codec: &C) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(Self::KIND);
+        bytes.push(C::TAG);
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+        bytes.extend(codec.encode(self)?);
+        Ok(bytes)
+    }
+
+    /// Deserialize a store, using the codec named in its header.
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() >= HEADER_SIZE && bytes.starts_with(MAGIC), "The data is not an Aleo store");
+        let (kind, tag, version) = (bytes[4], bytes[5], u16::from_le_bytes([bytes[6], bytes[7]]));
+        ensure!(kind == Self::KIND, "The data is not a {} (found store kind {kind})", Self::NAME);
+        if version > Self::VERSION {
+            let (name, supported) = (Self::NAME, Self::VERSION);
+            bail!("The {name} has version {version}, but this library only supports up to version {supported}")
+        }
+        let body = &bytes[HEADER_SIZE..];
+        if migrations::needs_migration::<Self>(version) {
+            match tag {
+                Json::TAG => return decode_body::<Self, _>(&Json, &migrations::migrate_body::<Self>(body, version)?),
+                // Migrations upgrade the JSON form of a store, as the binary form cannot be read without its schema.
+                _ => bail!("The {} has version {version}, and can only be migrated from JSON", Self::NAME),
+            }
+        }
+        match tag {
+            Json::TAG => decode_body::<Self, _>(&Json, body),
+            #[cfg(feature = "bincode")]
+            Bincode::TAG => decode_body::<Self, _>(&Bincode, body),
+            #[cfg(feature = "cbor")]
+            Cbor::TAG => decode_body::<Self, _>(&Cbor, body),
+            tag => bail!("The {} was written with an unsupported codec (tag {tag})", Self::NAME),
+        }
+    }
+
+    /// Write the store to a file atomically, using the given codec.
+    ///
+    /// The file holds either the previous store or this one, even if the process is killed during the save. The
+    /// file is locked exclusively during the save, waiting up to [`DEFAULT_LOCK_TIMEOUT`] for other processes.
+    fn save<C: Codec>(&self, path: impl AsRef<Path>, codec: &C) -> Result<()> {
+        let _lock = StoreLock::exclusive(path.as_ref(), DEFAULT_LOCK_TIMEOUT)?;
+        write_store(self, path.as_ref(), codec)
+    }
+
+    /// Read a store from a file written in any supported codec.
+    ///
+    /// If a save was interrupted after its temporary file was complete, the store is recovered from the
+    /// temporary file, which then replaces the previous store. An incomplete temporary file is discarded.
+    /// Files that are truncated or fail their checksum are rejected with [`CorruptStore`]. The lock of the file
+    /// is shared with other loads, waiting up to [`DEFAULT_LOCK_TIMEOUT`] for a process saving it.
+    ///
+    /// A file written at an older version of the store is migrated first, as by [`Persist::migrate`].
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        if migrations::is_outdated::<Self>(path.as_ref())? {
+            Self::migrate(path.as_ref())?;
+        }
+        let _lock = StoreLock::shared(path.as_ref(), DEFAULT_LOCK_TIMEOUT)?;
+        read_store(path.as_ref())
+    }
+
+    /// Upgrade a store file written at an older version of the store to the current version, in the codec it was
+    /// written in, and return the version it was written at, or `None` if it was current.
+    ///
+    /// The previous file is kept at [`backup_path_of`] its version, and the file is rewritten atomically. Files
+    /// written at a newer version are rejected rather than downgraded.
+    fn migrate(path: impl AsRef<Path>) -> Result<Option<u16>> {
+        let _lock = StoreLock::exclusive(path.as_ref(), DEFAULT_LOCK_TIMEOUT)?;
+        migrations::migrate_file::<Self>(path.as_ref())
+    }
+
+    /// Rewrite a store file in the given codec, from whichever codec it was written in.
+    fn convert_to<C: Codec>(path: impl AsRef<Path>, codec: &C) -> Result<()> {
+        let path = path.as_ref();
+        let _lock = StoreLock::exclusive(path, DEFAULT_LOCK_TIMEOUT)?;
+        write_store(&read_store::<Self>(path)?, path, codec)
+    }
+}
+
+/// A store kept in a file, whose changes are held in memory until they are flushed
+pub trait Flush {
+    /// Write the changes made since the last flush to the file, and return a summary of what the file holds.
+    ///
+    /// Flushing a store without changes writes nothing, so flushing again is harmless.
+    fn flush(&mut self) -> Result<FlushSummary>;
+}
+
+/// What a file-backed store holds after a [`Flush`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlushSummary {
+    path: PathBuf,
+    written: bool,
+    records: usize,
+    next_height: BlockHeight,
+}
+
+impl FlushSummary {
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn new(path: PathBuf, written: bool, records: usize, next_height: BlockHeight) -> Self {
+        Self { path, written, records, next_height }
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true` if the flush wrote the file, or `false` if it already held every change.
+    pub fn written(&self) -> bool {
+        self.written
+    }
+
+    /// Returns the number of records held by the file.
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    /// Returns the height from which the next sync resumes.
+    pub fn next_height(&self) -> BlockHeight {
+        self.next_height
+    }
+}
+
+// Write a store to a file atomically, with its header and checksum, without locking the file
+pub(crate) fn write_store<T: Persist, C: Codec>(store: &T, path: &Path, codec: &C) -> Result<()> {
+    let mut bytes = store.encode(codec)?;
+    let checksum = Sha256::digest(&bytes);
+    bytes.extend_from_slice(&checksum);
+    write_atomic(path, &bytes)
+}
+
+// Read a store from a file, recovering from an interrupted save, without locking the file
+pub(crate) fn read_store<T: Persist>(path: &Path) -> Result<T> {
+    let temp_path = temp_path_of(path);
+    if temp_path.exists() {
+        match read_checked::<T>(&temp_path) {
+            Ok(store) => {
+                fs::rename(&temp_path, path)?;
+                return Ok(store);
+            }
+            Err(error) if error.is::<CorruptStore>() => fs::remove_file(&temp_path)?,
+            Err(error) => return Err(error),
+        }
+    }
+    read_checked(path)
+}
+
+// Read a store file, checking its checksum before decoding it
+fn read_checked<T: Persist>(path: &Path) -> Result<T> {
+    let bytes = fs::read(path)?;
+    if let Some(length) = bytes.len().checked_sub(CHECKSUM_SIZE) {
+        if Sha256::digest(&bytes[..length]).as_slice() == &bytes[length..] {
+            return T::decode(&bytes[..length]);
+        }
+    }
+    // Files written before stores had checksums are accepted if they decode in full.
+    T::decode(&bytes).map_err(|_| {
+        let reason = "its checksum does not match its contents".to_string();
+        CorruptStore { store: T::NAME, path: path.to_path_buf(), reason }.into()
+    })
+}
+
+// Write a file by writing a temporary file in the same directory, syncing it to disk, and renaming it over
+// the file, so that the file is replaced in one step
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let temp_path = temp_path_of(path);
+    let mut file = File::create(&temp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp_path, path)?;
+    // Sync the directory, so that the rename itself survives a crash.
+    #[cfg(unix)]
+    if let Some(directory) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        File::open(directory)?.sync_all()?;
+    }
+    Ok(())
+}
+
+// Returns the path of the temporary file used while saving the file at the given path
+fn temp_path_of(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(".tmp");
+    path.with_file_name(file_name)
+}
+
+// Deserialize the body of a store, naming the store and codec on failure
+fn decode_body<T: Persist, C: Codec>(codec: &C, body: &[u8]) -> Result<T> {
+    match codec.decode(body) {
+        Ok(store) => Ok(store),
+        Err(error) => bail!("Failed to decode the {} from {}: {error}", T::NAME, C::NAME),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_block,
+            sample_record,
+            sample_transaction,
+            sample_transition,
+            temp_path,
+            CurrentNetwork,
+        },
+        ExpectedRecords,
+        Microcredits,
+    };
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        prelude::Uniform,
+        types::Field,
+    };
+    use snarkvm_utilities::TestRng;
+
+    use std::fmt::Debug;
+
+    type N = CurrentNetwork;
+
+    // Sample a store of each kind
+    fn sample_stores(rng: &mut TestRng) -> (RecordStore<N>, BlockCache<N>, ScanState<N>) {
+        let address = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let mut records = RecordStore::new();
+        for height in 0..3 {
+            records.insert(Field::rand(rng), sample_record(address, 100, rng).0, height);
+        }
+        let mut blocks = BlockCache::new(2);
+        let genesis = genesis_block();
+        let block = sample_block(1, genesis.hash(), rng);
+        let mut scan_state = ScanState::new(BlockHeight(0));
+        for block in [genesis, block] {
+            scan_state.advance(&block);
+            blocks.insert(block);
+        }
+        (records, blocks, scan_state)
+    }
+
+    // Check that a store survives a round trip through the given codec
+    fn assert_round_trip<T: Persist + Debug + PartialEq, C: Codec>(store: &T, codec: &C) {
+        let bytes = store.encode(codec).unwrap();
+        assert_eq!(bytes[5], C::TAG);
+        assert_eq!(&T::decode(&bytes).unwrap(), store);
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let (records, blocks, scan_state) = sample_stores(&mut TestRng::default());
+        assert_eq!(records.len(), 3);
+        assert_eq!(blocks.iter().map(|block| block.height()).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(scan_state.next_height(), BlockHeight(2));
+
+        assert_round_trip(&records, &Json);
+        assert_round_trip(&blocks, &Json);
+        assert_round_trip(&scan_state, &Json);
+        #[cfg(feature = "bincode")]
+        {
+            assert_round_trip(&records, &Bincode);
+            assert_round_trip(&blocks, &Bincode);
+            assert_round_trip(&scan_state, &Bincode);
+            assert!(blocks.encode(&Bincode).unwrap().len() < blocks.encode(&Json).unwrap().len());
+        }
+        #[cfg(feature = "cbor")]
+        {
+            assert_round_trip(&records, &Cbor);
+            assert_round_trip(&blocks, &Cbor);
+            assert_round_trip(&scan_state, &Cbor);
+            assert!(blocks.encode(&Cbor).unwrap().len() < blocks.encode(&Json).unwrap().len());
+        }
+    }
+
+    #[test]
+    fn test_store_convert_to() {
+        let (records, ..) = sample_stores(&mut TestRng::default());
+        let path = temp_path("store-convert");
+        records.save(&path, &Json).unwrap();
+        assert_eq!(fs::read(&path).unwrap()[5], Json::TAG);
+        #[cfg(feature = "bincode")]
+        {
+            RecordStore::<N>::convert_to(&path, &Bincode).unwrap();
+            assert_eq!(fs::read(&path).unwrap()[5], Bincode::TAG);
+            assert_eq!(RecordStore::<N>::load(&path).unwrap(), records);
+            RecordStore::<N>::convert_to(&path, &Json).unwrap();
+        }
+        #[cfg(feature = "cbor")]
+        {
+            RecordStore::<N>::convert_to(&path, &Cbor).unwrap();
+            assert_eq!(fs::read(&path).unwrap()[5], Cbor::TAG);
+            assert_eq!(RecordStore::<N>::load(&path).unwrap(), records);
+            RecordStore::<N>::convert_to(&path, &Json).unwrap();
+        }
+        assert_eq!(RecordStore::<N>::load(&path).unwrap(), records);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_store_rejects_corrupted_header() {
+        let (_, _, scan_state) = sample_stores(&mut TestRng::default());
+        let bytes = scan_state.encode(&Json).unwrap();
+        let decode = |bytes: &[u8]| ScanState::<N>::decode(bytes).unwrap_err().to_string();
+
+        // Data without the magic string, or too short to hold a header, is rejected.
+        let mut corrupted = bytes.clone();
+        corrupted[0] = b'X';
+        assert_eq!(decode(&corrupted), "The data is not an Aleo store");
+        assert_eq!(decode(&bytes[..6]), "The data is not an Aleo store");
+
+        // Stores of another kind are rejected.
+        let mut corrupted = bytes.clone();
+        corrupted[4] = RecordStore::<N>::KIND;
+        assert_eq!(decode(&corrupted), "The data is not a scan state (found store kind 1)");
+
+        // Unknown codecs and newer schema versions are rejected.
+        let mut corrupted = bytes.clone();
+        corrupted[5] = 200;
+        assert_eq!(decode(&corrupted), "The scan state was written with an unsupported codec (tag 200)");
+        let mut corrupted = bytes.clone();
+        corrupted[6..8].copy_from_slice(&3u16.to_le_bytes());
+        assert_eq!(decode(&corrupted), "The scan state has version 3, but this library only supports up to version 2");
+
+        // A body that does not match its codec is rejected.
+        let mut corrupted = bytes;
+        corrupted.truncate(HEADER_SIZE + 4);
+        assert!(decode(&corrupted).starts_with("Failed to decode the scan state from JSON"));
+        #[cfg(feature = "cbor")]
+        {
+            let mut corrupted = scan_state.encode(&Cbor).unwrap();
+            corrupted.truncate(HEADER_SIZE + 4);
+            assert!(decode(&corrupted).starts_with("Failed to decode the scan state from CBOR"));
+            let mut corrupted = scan_state.encode(&Cbor).unwrap();
+            corrupted[5] = Json::TAG;
+            assert!(decode(&corrupted).starts_with("Failed to decode the scan state from JSON"));
+        }
+    }
+
+    #[test]
+    fn test_store_save_is_atomic() {
+        let rng = &mut TestRng::default();
+        let (records, blocks, _) = sample_stores(rng);
+        let path = temp_path("store-atomic");
+        records.save(&path, &Json).unwrap();
+        assert!(!temp_path_of(&path).exists());
+
+        // The file holds the store followed by its checksum.
+        let bytes = fs::read(&path).unwrap();
+        let (store, checksum) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+        assert_eq!(Sha256::digest(store).as_slice(), checksum);
+        assert_eq!(RecordStore::<N>::decode(store).unwrap(), records);
+
+        // Files written before stores had checksums still load.
+        fs::write(&path, blocks.encode(&Json).unwrap()).unwrap();
+        assert_eq!(BlockCache::<N>::load(&path).unwrap(), blocks);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_store_rejects_truncated_file() {
+        let rng = &mut TestRng::default();
+        let (records, ..) = sample_stores(rng);
+        let path = temp_path("store-truncated");
+        records.save(&path, &Json).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        // A truncated file is reported as corrupted, with its path.
+        fs::write(&path, &bytes[..bytes.len() * 2 / 3]).unwrap();
+        let error = RecordStore::<N>::load(&path).unwrap_err().downcast::<CorruptStore>().unwrap();
+        assert_eq!((error.store(), error.path()), ("record store", path.as_path()));
+        assert_eq!(
+            error.to_string(),
+            format!("The record store at '{}' is corrupted: its checksum does not match its contents", path.display())
+        );
+
+        // The records stored in full before the truncation are recovered.
+        let recovered = RecordStore::<N>::recover_best_effort(&path).unwrap();
+        assert!(!recovered.is_empty() && recovered.len() < records.len());
+        for (commitment, record) in recovered.iter() {
+            assert_eq!(records.get(commitment), Some(record));
+        }
+
+        // Intact files are recovered in full, and files of other stores are refused.
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(RecordStore::<N>::recover_best_effort(&path).unwrap(), records);
+        let mut scan_state = ScanState::<N>::new(BlockHeight(0)).encode(&Json).unwrap();
+        scan_state.truncate(HEADER_SIZE + 4);
+        fs::write(&path, scan_state).unwrap();
+        let error = RecordStore::<N>::recover_best_effort(&path).unwrap_err().to_string();
+        assert_eq!(error, format!("The file at '{}' is not a record store", path.display()));
+        #[cfg(feature = "bincode")]
+        {
+            records.save(&path, &Bincode).unwrap();
+            let bytes = fs::read(&path).unwrap();
+            fs::write(&path, &bytes[..bytes.len() * 2 / 3]).unwrap();
+            let recovered = RecordStore::<N>::recover_best_effort(&path).unwrap();
+            assert!(!recovered.is_empty() && recovered.len() < records.len());
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_store_recovers_from_temp_file() {
+        let rng = &mut TestRng::default();
+        let (records, ..) = sample_stores(rng);
+        let path = temp_path("store-recovery");
+        records.save(&path, &Json).unwrap();
+        let mut newer = records.clone();
+        newer.insert(Field::rand(rng), records.iter().next().unwrap().1.record().clone(), 3);
+        let newer_path = temp_path("store-recovery-newer");
+        newer.save(&newer_path, &Json).unwrap();
+        let newer_bytes = fs::read(&newer_path).unwrap();
+        fs::remove_file(newer_path).unwrap();
+
+        // A save killed while writing its temporary file leaves the previous store, and the stale file is removed.
+        fs::write(temp_path_of(&path), &newer_bytes[..newer_bytes.len() / 2]).unwrap();
+        assert_eq!(RecordStore::<N>::load(&path).unwrap(), records);
+        assert!(!temp_path_of(&path).exists());
+
+        // A save killed after its temporary file was complete, but before the rename, is finished by the load.
+        fs::write(temp_path_of(&path), &newer_bytes).unwrap();
+        assert_eq!(RecordStore::<N>::load(&path).unwrap(), newer);
+        assert!(!temp_path_of(&path).exists());
+        assert_eq!(fs::read(&path).unwrap(), newer_bytes);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_scan_state_direction() {
+        let rng = &mut TestRng::default();
+        let genesis = genesis_block();
+        let block = sample_block(1, genesis.hash(), rng);
+
+        // A reverse scan resumes below the last scanned block.
+        let mut scan_state = ScanState::new_rev(BlockHeight(2));
+        scan_state.advance(&block);
+        assert_eq!(scan_state.next_height(), BlockHeight(1));
+        assert_eq!(scan_state.direction(), ScanDirection::Reverse);
+        scan_state.advance(&genesis);
+        assert_eq!(scan_state.next_height(), BlockHeight(0));
+        assert_round_trip(&scan_state, &Json);
+
+        // Scan states of the first version have no direction, and are forward scans.
+        let mut bytes = ScanState::<N>::new(BlockHeight(7)).encode(&Json).unwrap();
+        bytes[6..8].copy_from_slice(&1u16.to_le_bytes());
+        let body = format!(r#"{{"next_height":7,"last_hash":"{}"}}"#, genesis.hash());
+        bytes.splice(HEADER_SIZE.., body.into_bytes());
+        let scan_state = ScanState::<N>::decode(&bytes).unwrap();
+        assert_eq!((scan_state.next_height(), scan_state.direction()), (BlockHeight(7), ScanDirection::Forward));
+    }
+    #[test]
+    fn test_record_store_watch_only() {
+        let rng = &mut TestRng::default();
+        let (watched, other) = (PrivateKey::<N>::new(rng).unwrap(), PrivateKey::<N>::new(rng).unwrap());
+        let (watched, other) = (Address::try_from(watched).unwrap(), Address::try_from(other).unwrap());
+        let commitments = [Field::rand(rng), Field::rand(rng), Field::rand(rng)];
+        let mut records = RecordStore::<N>::new();
+        records.insert_watch_only(commitments[0], sample_record(watched, 100, rng).0, 1);
+        records.insert_watch_only(commitments[1], sample_record(other, 100, rng).0, 1);
+        records.insert(commitments[2], sample_record(watched, 100, rng).0, 2);
+        assert_round_trip(&records, &Json);
+
+        // Only the watch-only records of the address are claimed.
+        assert_eq!(records.claim(watched), [commitments[0]]);
+        assert!(records.iter().all(|(commitment, stored)| stored.is_watch_only() == (*commitment == commitments[1])));
+        assert!(records.claim(watched).is_empty());
+
+        // Records of the first version were found with a private key.
+        let mut bytes = records.encode(&Json).unwrap();
+        bytes[6..8].copy_from_slice(&1u16.to_le_bytes());
+        let record = serde_json::to_string(&sample_record(watched, 5, rng).0).unwrap();
+        let body = format!(r#"{{"records":{{"{}":{{"record":{record},"height":3}}}}}}"#, commitments[0]);
+        bytes.splice(HEADER_SIZE.., body.into_bytes());
+        let records = RecordStore::<N>::decode(&bytes).unwrap();
+        assert!(!records.get(&commitments[0]).unwrap().is_watch_only());
+    }
+
+    #[test]
+    fn test_record_store_pending() {
+        let rng = &mut TestRng::default();
+        let address = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let (input, fee, other) = (Field::rand(rng), Field::rand(rng), Field::rand(rng));
+        let mut records = RecordStore::<N>::new();
+        for (commitment, gates) in [(input, 100), (fee, 10), (other, 5)] {
+            records.insert(commitment, sample_record(address, gates, rng).0, 1);
+        }
+        let transaction_id = sample_transaction([sample_transition(&[Field::rand(rng)], &[], rng)]).id();
+        let (change, fee_change) = (Field::rand(rng), Field::rand(rng));
+        let outputs = vec![(change, sample_record(address, 40, rng).0), (fee_change, sample_record(address, 9, rng).0)];
+        let expected = ExpectedRecords::new(transaction_id, outputs.clone(), vec![input, fee]);
+
+        // The balance including pending records is the balance once the transaction is confirmed.
+        assert_eq!(records.insert_pending(&expected), 2);
+        assert_eq!((records.balance_with_pending(true), records.balance()), (Microcredits(54), Microcredits(115)));
+        assert_eq!(records.get(&change).unwrap().pending(), Some(transaction_id));
+        assert_eq!(records.get(&input).unwrap().pending_spend(), Some(transaction_id));
+        assert_eq!(records.history().len(), 3);
+        assert_round_trip(&records, &Json);
+        #[cfg(feature = "bincode")]
+        assert_round_trip(&records, &Bincode);
+
+        // A rolled back transaction leaves the store as it was.
+        let mut rolled_back = records.clone();
+        assert_eq!(rolled_back.rollback_pending(&transaction_id), 2);
+        let balances = (rolled_back.balance_with_pending(true), rolled_back.balance());
+        assert_eq!(balances, (Microcredits(115), Microcredits(115)));
+        assert!(rolled_back.iter().all(|(_, stored)| stored.pending_spend().is_none()));
+
+        // A scan finding one of the records confirms it, and confirming the transaction confirms the rest.
+        records.insert(change, outputs[0].1.clone(), 2);
+        assert_eq!(records.insert_pending(&expected), 0);
+        assert_eq!(records.get(&change).unwrap().pending(), None);
+        assert_eq!(records.confirm_pending(&transaction_id, 2), 3);
+        assert_eq!((records.balance_with_pending(true), records.balance()), (Microcredits(54), Microcredits(54)));
+        assert_eq!(records.get(&fee_change).unwrap().height(), 2);
+        assert_eq!(records.get(&input).unwrap().spent_height(), Some(2));
+        assert_eq!(records.history().len(), 5);
+    }
+
+    #[test]
+    fn test_record_store_compact() {
+        let rng = &mut TestRng::default();
+        let address = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let mut records = RecordStore::<N>::new();
+        let commitments = (0..20).map(|_| Field::rand(rng)).collect::<Vec<_>>();
+        for (height, commitment) in commitments.iter().enumerate() {
+            records.insert(*commitment, sample_record(address, 10 + height as u64, rng).0, height as u32);
+        }
+        // Every other record is spent in the next block.
+        for (height, commitment) in commitments.iter().enumerate().step_by(2) {
+            assert!(records.mark_spent(commitment, height as u32 + 1));
+        }
+        let (balance, history) = (records.balance(), records.history());
+        assert_eq!(history.len(), 20);
+
+        // The spends below the prune height are pruned, except those within the reorg depth of the tip.
+        let options = CompactionOptions::new(20).with_prune_below(18).with_reorg_depth(5);
+        let path = temp_path("store-compact");
+        records.save(&path, &Json).unwrap();
+        let report = RecordStore::<N>::compact_file(&path, &Json, &options).unwrap();
+        assert_eq!((report.pruned(), report.protected(), report.duplicates()), (8, 1, 0));
+        assert!(report.bytes_reclaimed() > 0);
+        assert_eq!(report.bytes_after(), fs::metadata(&path).unwrap().len());
+        let compacted = RecordStore::<N>::load(&path).unwrap();
+        assert_eq!(compacted.len(), 12);
+        assert_eq!((compacted.balance(), compacted.history()), (balance, history.clone()));
+        assert!(compacted.get(&commitments[16]).unwrap().spent_height().is_some());
+        assert!(compacted.pruned().iter().all(|summary| summary.spent_height() <= Some(15)));
+        assert_round_trip(&compacted, &Json);
+        #[cfg(feature = "bincode")]
+        assert_round_trip(&compacted, &Bincode);
+
+        // A pruned record found again by a rescan is dropped, and a second compaction changes nothing else.
+        let mut rescanned = compacted.clone();
+        rescanned.insert(commitments[0], sample_record(address, 10, rng).0, 0);
+        let report = rescanned.compact(&options);
+        assert_eq!((report.pruned(), report.duplicates(), report.bytes_reclaimed()), (0, 1, 0));
+        assert_eq!(rescanned, compacted);
+
+        // The spends within the reorg depth can still be undone.
+        assert_eq!(rescanned.unspend_from(16), 2);
+        assert_eq!(rescanned.balance(), balance + Microcredits(26 + 28));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,538 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+#[cfg(feature = "bincode")]
+use super::Bincode;
+#[cfg(feature = "cbor")]
+use super::Cbor;
+use super::{
+    read_store,
+    write_store,
+    Codec,
+    CompactionOptions,
+    CompactionReport,
+    CorruptStore,
+    Json,
+    Migration,
+    Persist,
+    RecordSummary,
+    SpendLog,
+    StoreLock,
+    DEFAULT_LOCK_TIMEOUT,
+    HEADER_SIZE,
+    MAGIC,
+};
+use crate::{ExpectedRecords, Microcredits, SpentStatus};
+
+use anyhow::{bail, ensure, Result};
+use serde::{
+    de::{DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+};
+use snarkvm_console::{
+    account::Address,
+    program::{Network, Plaintext, ProgramID, Record},
+    types::Field,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs,
+    path::Path,
+};
+
+/// A decrypted record together with the height of the block it was created in
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct StoredRecord<N: Network> {
+    record: Record<N, Plaintext<N>>,
+    height: u32,
+    // Stores written before watch-only accounts existed only hold records found with a private key.
+    #[serde(default)]
+    watch_only: bool,
+    // Stores written before compaction existed only hold unspent records.
+    #[serde(default)]
+    spent_height: Option<u32>,
+    // The program that created the record, which stores migrated from version 4 only know for credits records
+    #[serde(default)]
+    program_id: Option<ProgramID<N>>,
+    // The broadcast transaction that outputs the record, until a scan finds it
+    #[serde(default)]
+    pending: Option<N::TransactionID>,
+    // The broadcast transaction that spends the record, until a scan finds it
+    #[serde(default)]
+    pending_spend: Option<N::TransactionID>,
+}
+
+impl<N: Network> StoredRecord<N> {
+    pub(super) fn new(record: Record<N, Plaintext<N>>, height: u32, watch_only: bool) -> Self {
+        Self { record, height, watch_only, spent_height: None, program_id: None, pending: None, pending_spend: None }
+    }
+
+    /// Returns the decrypted record.
+    pub fn record(&self) -> &Record<N, Plaintext<N>> {
+        &self.record
+    }
+
+    /// Returns the height of the block the record was created in, or `0` for a pending record.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns `true` if the record was found by a watch-only account, and is not yet claimed by a private key.
+    ///
+    /// Without the private key, spends of the record cannot be detected, so it may already be spent.
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only
+    }
+
+    /// Returns the height of the block the record was spent in, if it was marked spent.
+    pub fn spent_height(&self) -> Option<u32> {
+        self.spent_height
+    }
+
+    /// Returns whether the record was spent. The spends of a record found by a watch-only account cannot be
+    /// detected, so its status is [`SpentStatus::Unknown`] until it is marked spent or claimed.
+    pub fn spent_status(&self) -> SpentStatus {
+        match (self.spent_height, self.watch_only) {
+            (Some(height), _) => SpentStatus::Spent { height },
+            (None, true) => SpentStatus::Unknown,
+            (None, false) => SpentStatus::Unspent,
+        }
+    }
+
+    /// Returns the ID of the program that created the record, if it is known.
+    pub fn program_id(&self) -> Option<&ProgramID<N>> {
+        self.program_id.as_ref()
+    }
+
+    /// Returns the ID of the broadcast transaction that outputs the record, if no scan found it yet.
+    pub fn pending(&self) -> Option<N::TransactionID> {
+        self.pending
+    }
+
+    /// Returns the ID of the broadcast transaction that spends the record, if no scan found it yet.
+    pub fn pending_spend(&self) -> Option<N::TransactionID> {
+        self.pending_spend
+    }
+
+    // Returns the summary of the record kept in the history of the store.
+    fn summary(&self, commitment: Field<N>) -> RecordSummary<N> {
+        RecordSummary::new(commitment, self.height, self.spent_height, ***self.record.gates())
+    }
+}
+
+/// The decrypted records of an account, by commitment
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RecordStore<N: Network> {
+    records: HashMap<Field<N>, StoredRecord<N>>,
+    // Stores written before spending policies existed hold no spends.
+    #[serde(default)]
+    spends: SpendLog,
+    // The summaries of the spent records pruned by a compaction
+    #[serde(default)]
+    pruned: Vec<RecordSummary<N>>,
+}
+
+impl<N: Network> RecordStore<N> {
+    /// Create an empty record store.
+    pub fn new() -> Self {
+        Self { records: HashMap::new(), spends: SpendLog::new(), pruned: vec![] }
+    }
+
+    /// Add a record created at the given height, returning the record previously stored under its commitment.
+    ///
+    /// A pending record with the same commitment is replaced, so that the record found by a scan is confirmed.
+    pub fn insert(
+        &mut self,
+        commitment: Field<N>,
+        record: Record<N, Plaintext<N>>,
+        height: u32,
+    ) -> Option<StoredRecord<N>> {
+        self.records.insert(commitment, StoredRecord::new(record, height, false))
+    }
+
+    /// Add a record found by a watch-only account at the given height, returning the record previously stored
+    /// under its commitment.
+    pub fn insert_watch_only(
+        &mut self,
+        commitment: Field<N>,
+        record: Record<N, Plaintext<N>>,
+        height: u32,
+    ) -> Option<StoredRecord<N>> {
+        self.records.insert(commitment, StoredRecord::new(record, height, true))
+    }
+
+    /// Add the records a broadcast transaction outputs to the account as pending, and mark the records it spends as
+    /// pending spends, then return the number of records added.
+    ///
+    /// Records the store already holds, e.g. as a scan found the transaction first, are left as they are. Pending
+    /// records count towards [`RecordStore::balance_with_pending`] until the transaction is confirmed with
+    /// [`RecordStore::confirm_pending`], or rolled back with [`RecordStore::rollback_pending`].
+    pub fn insert_pending(&mut self, expected: &ExpectedRecords<N>) -> usize {
+        let transaction_id = expected.transaction_id();
+        let mut count = 0;
+        for (commitment, record) in expected.records() {
+            if !self.records.contains_key(commitment) {
+                let mut stored = StoredRecord::new(record.clone(), 0, false);
+                stored.pending = Some(transaction_id);
+                self.records.insert(*commitment, stored);
+                count += 1;
+            }
+        }
+        for commitment in expected.spent() {
+            if let Some(stored) = self.records.get_mut(commitment).filter(|stored| stored.spent_height.is_none()) {
+                stored.pending_spend = Some(transaction_id);
+            }
+        }
+        count
+    }
+
+    /// Confirm the pending transaction once a block at the given height includes it, and return the number of
+    /// records it changed.
+    ///
+    /// Its pending records are kept as created at the height, unless a scan replaced them already, and the records it
+    /// spends are marked spent at the height.
+    pub fn confirm_pending(&mut self, transaction_id: &N::TransactionID, height: u32) -> usize {
+        let mut count = 0;
+        for stored in self.records.values_mut() {
+            if stored.pending.as_ref() == Some(transaction_id) {
+                stored.pending = None;
+                stored.height = height;
+                count += 1;
+            }
+            if stored.pending_spend.as_ref() == Some(transaction_id) {
+                stored.pending_spend = None;
+                stored.spent_height = Some(height);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Roll back the pending transaction once it is rejected or aborted, removing its pending records and
+    /// unmarking the records it spends, and return the number of records removed.
+    pub fn rollback_pending(&mut self, transaction_id: &N::TransactionID) -> usize {
+        let count = self.records.len();
+        self.records.retain(|_, stored| stored.pending.as_ref() != Some(transaction_id));
+        for stored in self.records.values_mut().filter(|stored| stored.pending_spend.as_ref() == Some(transaction_id)) {
+            stored.pending_spend = None;
+        }
+        count - self.records.len()
+    }
+
+    /// Claim the records found by a watch-only account for the given address, once its private key is imported,
+    /// and return their commitments.
+    ///
+    /// The claimed records are no longer watch-only. Records that were spent while watching should be removed
+    /// first, as the store cannot tell them apart.
+    pub fn claim(&mut self, address: Address<N>) -> Vec<Field<N>> {
+        self.records
+            .iter_mut()
+            .filter(|(_, stored)| stored.watch_only && **stored.record.owner() == address)
+            .map(|(commitment, stored)| {
+                stored.watch_only = false;
+                *commitment
+            })
+            .collect()
+    }
+
+    /// Returns the record with the given commitment.
+    pub fn get(&self, commitment: &Field<N>) -> Option<&StoredRecord<N>> {
+        self.records.get(commitment)
+    }
+
+    /// Remove the record with the given commitment, e.g. once it is spent.
+    pub fn remove(&mut self, commitment: &Field<N>) -> Option<StoredRecord<N>> {
+        self.records.remove(commitment)
+    }
+
+    /// Mark the record with the given commitment as spent in the block at the given height, keeping it for its
+    /// history until a [`RecordStore::compact`]. Returns `false` if the store does not hold the record.
+    pub fn mark_spent(&mut self, commitment: &Field<N>, height: u32) -> bool {
+        match self.records.get_mut(commitment) {
+            Some(stored) => {
+                stored.spent_height = Some(height);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Attribute the record with the given commitment to the program that created it. Returns `false` if the store
+    /// does not hold the record.
+    pub fn attribute(&mut self, commitment: &Field<N>, program_id: ProgramID<N>) -> bool {
+        match self.records.get_mut(commitment) {
+            Some(stored) => {
+                stored.program_id = Some(program_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mark the records spent at or above the given height as unspent again, e.g. once the blocks they were spent
+    /// in are orphaned by a reorganization, and return their number.
+    pub fn unspend_from(&mut self, height: u32) -> usize {
+        let spent = self.records.values_mut().filter(|stored| stored.spent_height.map_or(false, |spent| spent >= height));
+        spent.map(|stored| stored.spent_height = None).count()
+    }
+
+    /// Returns the gates held by the records that are not marked spent, leaving out pending records.
+    pub fn balance(&self) -> Microcredits {
+        self.balance_with_pending(false)
+    }
+
+    /// Returns the gates held by the records that are not marked spent.
+    ///
+    /// Including pending records counts the records output by the pending transactions, and leaves out the records
+    /// they spend, as the balance will be once they are confirmed. Excluding them counts the records found on chain.
+    pub fn balance_with_pending(&self, include_pending: bool) -> Microcredits {
+        self.records
+            .values()
+            .filter(|stored| stored.spent_height.is_none())
+            .filter(|stored| match include_pending {
+                true => stored.pending_spend.is_none(),
+                false => stored.pending.is_none(),
+            })
+            .map(|stored| Microcredits(***stored.record.gates()))
+            .sum()
+    }
+
+    /// Returns the summaries of every record the store held, including the records pruned by a compaction and
+    /// leaving out pending records, by height.
+    pub fn history(&self) -> Vec<RecordSummary<N>> {
+        let stored = self.records.iter().filter(|(_, stored)| stored.pending.is_none());
+        let stored = stored.map(|(commitment, stored)| stored.summary(*commitment));
+        let mut history = stored.chain(self.pruned.iter().cloned()).collect::<Vec<_>>();
+        history.sort_by_key(|summary| (summary.height(), summary.commitment()));
+        history
+    }
+
+    /// Returns the summaries of the spent records pruned by a compaction, by the height they were spent at.
+    pub fn pruned(&self) -> &[RecordSummary<N>] {
+        &self.pruned
+    }
+
+    /// Replace the spent records outside the reorg depth of the options with their summaries, and return what
+    /// changed.
+    ///
+    /// Records found again after being pruned, e.g. by a rescan, are dropped, as are duplicated summaries. A record
+    /// spent within the reorg depth is never pruned, so that a reorganization can still mark it unspent.
+    pub fn compact(&mut self, options: &CompactionOptions) -> CompactionReport {
+        let mut report = CompactionReport::default();
+        // Records are only pruned outside the reorg depth, so a record summarized already is spent for good.
+        let mut summarized = HashSet::with_capacity(self.pruned.len());
+        let count = self.records.len() + self.pruned.len();
+        self.pruned.retain(|summary| summarized.insert(summary.commitment()));
+        self.records.retain(|commitment, _| !summarized.contains(commitment));
+        report.duplicates = count - self.records.len() - self.pruned.len();
+
+        let mut prunable = vec![];
+        for (commitment, stored) in &self.records {
+            match stored.spent_height {
+                Some(spent_height) if options.may_prune(spent_height) => prunable.push(*commitment),
+                Some(spent_height) if spent_height < options.prune_below() => report.protected += 1,
+                _ => (),
+            }
+        }
+        report.pruned = prunable.len();
+        for commitment in prunable {
+            if let Some(stored) = self.records.remove(&commitment) {
+                self.pruned.push(stored.summary(commitment));
+            }
+        }
+
+        // The summaries are rebuilt in order, and the memory of the dropped records is released.
+        self.pruned.sort_by_key(|summary| (summary.spent_height(), summary.commitment()));
+        self.pruned.shrink_to_fit();
+        self.records.shrink_to_fit();
+        report
+    }
+
+    /// Compact the record store in the given file, and write it back atomically using the given codec.
+    ///
+    /// The file holds either the previous store or the compacted one, even if the process is killed during the
+    /// compaction.
+    pub fn compact_file<C: Codec>(
+        path: impl AsRef<Path>,
+        codec: &C,
+        options: &CompactionOptions,
+    ) -> Result<CompactionReport> {
+        let path = path.as_ref();
+        let _lock = StoreLock::exclusive(path, DEFAULT_LOCK_TIMEOUT)?;
+        let mut store = read_store::<Self>(path)?;
+        let bytes_before = fs::metadata(path)?.len();
+        let mut report = store.compact(options);
+        write_store(&store, path, codec)?;
+        report.bytes_before = bytes_before;
+        report.bytes_after = fs::metadata(path)?.len();
+        Ok(report)
+    }
+
+    /// Returns the stored records and their commitments, in no particular order.
+    pub fn iter(&self) -> impl '_ + Iterator<Item = (&Field<N>, &StoredRecord<N>)> {
+        self.records.iter()
+    }
+
+    /// Returns the number of stored records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns `true` if the store holds no records.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the gates spent by the account over the last day, counted by its [`crate::SpendingPolicy`].
+    pub fn spend_log(&self) -> &SpendLog {
+        &self.spends
+    }
+
+    /// Returns the gates spent by the account over the last day mutably, e.g. to record a spend.
+    pub fn spend_log_mut(&mut self) -> &mut SpendLog {
+        &mut self.spends
+    }
+
+    /// Read a record store from a file, salvaging the records of a corrupted file.
+    ///
+    /// A file that loads is returned in full. From a file rejected with [`CorruptStore`], e.g. one truncated
+    /// by a crash, the records stored in full before the damage are kept and the rest are dropped, so that
+    /// only the blocks holding the dropped records need to be scanned again.
+    pub fn recover_best_effort(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match Self::load(path) {
+            Err(error) if error.is::<CorruptStore>() => (),
+            result => return result,
+        }
+        let bytes = fs::read(path)?;
+        ensure!(
+            bytes.len() >= HEADER_SIZE && bytes.starts_with(MAGIC) && bytes[4] == Self::KIND,
+            "The file at '{}' is not a record store",
+            path.display()
+        );
+        // The records parsed before the body fails to parse are kept.
+        let mut store = Self::new();
+        let body = &bytes[HEADER_SIZE..];
+        match bytes[5] {
+            Json::TAG => {
+                let _ = RecordStoreSeed(&mut store).deserialize(&mut serde_json::Deserializer::from_slice(body));
+            }
+            #[cfg(feature = "bincode")]
+            Bincode::TAG => {
+                use bincode::Options;
+                let options = bincode::DefaultOptions::new().with_fixint_encoding().allow_trailing_bytes();
+                let _ = RecordStoreSeed(&mut store).deserialize(&mut bincode::Deserializer::from_slice(body, options));
+            }
+            // The CBOR decoder only reads whole values, so the records before the damage cannot be told apart.
+            #[cfg(feature = "cbor")]
+            Cbor::TAG => bail!("The records of a record store written in CBOR cannot be salvaged"),
+            tag => bail!("The record store was written with an unsupported codec (tag {tag})"),
+        }
+        Ok(store)
+    }
+}
+
+impl<N: Network> Default for RecordStore<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Network> Persist for RecordStore<N> {
+    const KIND: u8 = 1;
+    const NAME: &'static str = "record store";
+    const VERSION: u16 = 5;
+
+    fn migrations() -> Vec<Migration> {
+        vec![Migration::new(4, super::migrations::record_store_v5::<N>)]
+    }
+}
+
+/// Deserializes a record store into an existing store, inserting each record as soon as it is parsed, so that
+/// the records before a corruption are kept when the rest of the store fails to parse
+struct RecordStoreSeed<'a, N: Network>(&'a mut RecordStore<N>);
+
+impl<'de, N: Network> DeserializeSeed<'de> for RecordStoreSeed<'_, N> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("RecordStore", &["records", "spends", "pruned"], self)
+    }
+}
+
+impl<'de, N: Network> Visitor<'de> for RecordStoreSeed<'_, N> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a record store")
+    }
+
+    // Self-describing codecs write the store as a map of its fields.
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "records" => map.next_value_seed(RecordsSeed(&mut *self.0))?,
+                "spends" => self.0.spends = map.next_value()?,
+                "pruned" => self.0.pruned = map.next_value()?,
+                _ => map.next_value::<IgnoredAny>().map(|_| ())?,
+            }
+        }
+        Ok(())
+    }
+
+    // Compact codecs write the store as a sequence of its fields, and the records as a map.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        seq.next_element_seed(RecordsSeed(&mut *self.0))?;
+        if let Some(spends) = seq.next_element()? {
+            self.0.spends = spends;
+        }
+        if let Some(pruned) = seq.next_element()? {
+            self.0.pruned = pruned;
+        }
+        Ok(())
+    }
+}
+
+/// Deserializes the map of records of a store, inserting each record as soon as it is parsed
+struct RecordsSeed<'a, N: Network>(&'a mut RecordStore<N>);
+
+impl<'de, N: Network> DeserializeSeed<'de> for RecordsSeed<'_, N> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, N: Network> Visitor<'de> for RecordsSeed<'_, N> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of records by commitment")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        while let Some((commitment, record)) = map.next_entry::<Field<N>, StoredRecord<N>>()? {
+            self.0.records.insert(commitment, record);
+        }
+        Ok(())
+    }
+}
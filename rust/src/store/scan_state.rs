@@ -0,0 +1,89 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::Persist;
+use crate::BlockHeight;
+
+use serde::{Deserialize, Serialize};
+use snarkvm_console::program::Network;
+use snarkvm_synthesizer::Block;
+
+/// The direction in which a scan walks the chain
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScanDirection {
+    /// From the lowest block up to the tip
+    #[default]
+    Forward,
+    /// From the tip down to the lowest block, to find recent records first
+    Reverse,
+}
+
+/// The progress of a scan, so that it can resume where it stopped
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ScanState<N: Network> {
+    next_height: BlockHeight,
+    last_hash: Option<N::BlockHash>,
+    // Scan states written before reverse scans existed are forward scans.
+    #[serde(default)]
+    direction: ScanDirection,
+}
+
+impl<N: Network> ScanState<N> {
+    /// Create the state of a scan starting at the given height.
+    pub fn new(start_height: BlockHeight) -> Self {
+        Self { next_height: start_height, last_hash: None, direction: ScanDirection::Forward }
+    }
+
+    /// Create the state of a reverse scan of the blocks below the given height.
+    pub fn new_rev(end_height: BlockHeight) -> Self {
+        Self { next_height: end_height, last_hash: None, direction: ScanDirection::Reverse }
+    }
+
+    /// Record that the given block was scanned.
+    pub fn advance(&mut self, block: &Block<N>) {
+        self.next_height = match self.direction {
+            ScanDirection::Forward => BlockHeight(block.height() + 1),
+            ScanDirection::Reverse => BlockHeight(block.height()),
+        };
+        self.last_hash = Some(block.hash());
+    }
+
+    /// Returns the height of the next block to scan.
+    ///
+    /// A reverse scan has instead scanned every block from this height up, and resumes below it.
+    pub fn next_height(&self) -> BlockHeight {
+        self.next_height
+    }
+
+    /// Returns the hash of the last scanned block, which the next block must build on.
+    ///
+    /// In a reverse scan, the next block is instead the parent of the last scanned block.
+    pub fn last_hash(&self) -> Option<N::BlockHash> {
+        self.last_hash
+    }
+
+    /// Returns the direction of the scan.
+    pub fn direction(&self) -> ScanDirection {
+        self.direction
+    }
+}
+
+impl<N: Network> Persist for ScanState<N> {
+    const KIND: u8 = 3;
+    const NAME: &'static str = "scan state";
+    const VERSION: u16 = 2;
+}
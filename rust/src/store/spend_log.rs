@@ -0,0 +1,65 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+/// The seconds in the rolling window of the daily spending limit of a [`crate::SpendingPolicy`]
+pub const SPEND_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// The gates spent by an account, and when, over the last day
+///
+/// The log is kept in the [`crate::RecordStore`] of the account, so that the daily limits of a
+/// [`crate::SpendingPolicy`] hold across restarts.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendLog {
+    // The time in seconds since the Unix epoch and the gates of each spend, oldest first
+    spends: Vec<(u64, u64)>,
+}
+
+impl SpendLog {
+    /// Create an empty spend log.
+    pub fn new() -> Self {
+        Self { spends: vec![] }
+    }
+
+    /// Add a spend of `gates` at the given time in seconds since the Unix epoch, dropping the spends that left
+    /// the window.
+    pub fn record(&mut self, timestamp: u64, gates: u64) {
+        self.prune(timestamp);
+        let index = self.spends.partition_point(|(spent_at, _)| *spent_at <= timestamp);
+        self.spends.insert(index, (timestamp, gates));
+    }
+
+    /// Returns the gates spent within the day before the given time in seconds since the Unix epoch.
+    pub fn spent_within_window(&self, now: u64) -> u64 {
+        let start = now.saturating_sub(SPEND_WINDOW_SECS);
+        self.spends
+            .iter()
+            .filter(|(spent_at, _)| *spent_at > start)
+            .fold(0, |total, (_, gates)| total.saturating_add(*gates))
+    }
+
+    /// Returns the spends in the log, as times in seconds since the Unix epoch and gates, oldest first.
+    pub fn spends(&self) -> &[(u64, u64)] {
+        &self.spends
+    }
+
+    // Drop the spends made a day or more before the given time
+    fn prune(&mut self, now: u64) {
+        let start = now.saturating_sub(SPEND_WINDOW_SECS);
+        self.spends.retain(|(spent_at, _)| *spent_at > start);
+    }
+}
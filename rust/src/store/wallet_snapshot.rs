@@ -0,0 +1,469 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{
+    write_atomic,
+    HistoryEntry,
+    Json,
+    Migration,
+    Persist,
+    RecordStore,
+    ScanState,
+    StoreLock,
+    DEFAULT_LOCK_TIMEOUT,
+};
+#[cfg(not(feature = "async"))]
+use crate::AleoAPIClient;
+use crate::Encryptor;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use snarkvm_console::{
+    account::{PrivateKey, ViewKey},
+    program::{Ciphertext, Network},
+};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// The size of the SHA-256 checksum at the end of a snapshot file
+const CHECKSUM_SIZE: usize = 32;
+
+/// The error of an import with the wrong passphrase
+const WRONG_PASSPHRASE: &str = "Failed to decrypt the accounts of the wallet snapshot with the given passphrase";
+
+/// The domain separator of the keys encrypting the records and the history of a snapshot
+const CONTENTS_KEY_DOMAIN: &[u8] = b"AleoWalletSnapshotContents0";
+
+/// Everything a wallet needs to move to another device without rescanning the chain
+///
+/// A snapshot holds the private keys of the accounts, the records found for them, the progress of the scan
+/// that found them, the history of the records, and the settings of the application. It is exported as a single
+/// store file in which the private keys, records, and history are encrypted with a passphrase, followed by a
+/// checksum of the whole file.
+/// Watch-only accounts are held by their view keys, which are encrypted in the same way.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalletSnapshot<N: Network> {
+    accounts: Vec<PrivateKey<N>>,
+    watch_only: Vec<ViewKey<N>>,
+    records: RecordStore<N>,
+    scan_state: ScanState<N>,
+    history: Vec<HistoryEntry<N>>,
+    settings: BTreeMap<String, String>,
+}
+
+// The persisted form of a snapshot, with the private keys, view keys, records, and history encrypted
+//
+// No hash of the passphrase is stored, as it would only be as hard to guess from as the keys themselves; a wrong
+// passphrase fails to decrypt the keys instead. Snapshots written with a hash of the passphrase are read all the same.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct EncryptedSnapshot<N: Network> {
+    accounts: Vec<Ciphertext<N>>,
+    // Snapshots written before watch-only accounts existed have none.
+    #[serde(default)]
+    watch_only: Vec<Ciphertext<N>>,
+    // Snapshots written before version 6 hold their records and history in plaintext instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_records: Option<EncryptedContents>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_history: Option<EncryptedContents>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    records: Option<RecordStore<N>>,
+    scan_state: ScanState<N>,
+    // Snapshots written before wallets kept a history have none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    history: Vec<HistoryEntry<N>>,
+    settings: BTreeMap<String, String>,
+}
+
+impl<N: Network> Persist for EncryptedSnapshot<N> {
+    const KIND: u8 = 4;
+    const NAME: &'static str = "wallet snapshot";
+    const VERSION: u16 = 6;
+
+    fn migrations() -> Vec<Migration> {
+        vec![
+            Migration::new(4, super::migrations::wallet_snapshot_v5::<N>),
+            Migration::new(5, super::migrations::wallet_snapshot_v6),
+        ]
+    }
+}
+
+// Contents of a snapshot encrypted with a key derived from the passphrase, as hexadecimal strings
+//
+// The records are encrypted as a record store file, which keeps their version so that they are migrated when they
+// are decrypted, and the history as a JSON array.
+#[derive(Serialize, Deserialize)]
+struct EncryptedContents {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedContents {
+    // Encrypt the contents under a fresh salt and nonce
+    fn encrypt(contents: &[u8], passphrase: &str) -> Result<Self> {
+        let rng = &mut rand::thread_rng();
+        let (salt, nonce) = (rng.gen::<[u8; 16]>(), rng.gen::<[u8; 12]>());
+        let ciphertext = Self::cipher(&salt, passphrase)
+            .encrypt(Nonce::from_slice(&nonce), contents)
+            .map_err(|_| anyhow!("Failed to encrypt the contents of the wallet snapshot"))?;
+        Ok(Self { salt: hex::encode(salt), nonce: hex::encode(nonce), ciphertext: hex::encode(ciphertext) })
+    }
+
+    // Decrypt the contents, failing if the passphrase is wrong or the ciphertext was tampered with
+    fn decrypt(&self, passphrase: &str) -> Result<Vec<u8>> {
+        let nonce = hex::decode(&self.nonce)?;
+        ensure!(nonce.len() == 12, "The nonce of the contents of the wallet snapshot is malformed");
+        Self::cipher(&hex::decode(&self.salt)?, passphrase)
+            .decrypt(Nonce::from_slice(&nonce), hex::decode(&self.ciphertext)?.as_slice())
+            .map_err(|_| anyhow!(WRONG_PASSPHRASE))
+    }
+
+    // Derive the cipher of the contents from the passphrase and the salt
+    fn cipher(salt: &[u8], passphrase: &str) -> ChaCha20Poly1305 {
+        let key =
+            Sha256::new().chain_update(CONTENTS_KEY_DOMAIN).chain_update(salt).chain_update(passphrase).finalize();
+        ChaCha20Poly1305::new(&key)
+    }
+}
+
+impl<N: Network> WalletSnapshot<N> {
+    /// Create a snapshot of the given accounts, their records, and the progress of the scan that found them.
+    pub fn new(accounts: Vec<PrivateKey<N>>, records: RecordStore<N>, scan_state: ScanState<N>) -> Self {
+        Self { accounts, watch_only: vec![], records, scan_state, history: vec![], settings: BTreeMap::new() }
+    }
+
+    /// Set the view keys of the watch-only accounts.
+    pub fn with_watch_only(mut self, watch_only: Vec<ViewKey<N>>) -> Self {
+        self.watch_only = watch_only;
+        self
+    }
+
+    /// Set the history of the records, in the order of the chain.
+    pub fn with_history(mut self, history: Vec<HistoryEntry<N>>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Set the settings of the application, as key-value pairs.
+    pub fn with_settings(mut self, settings: BTreeMap<String, String>) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Returns the private keys of the accounts.
+    pub fn accounts(&self) -> &[PrivateKey<N>] {
+        &self.accounts
+    }
+
+    /// Returns the view keys of the watch-only accounts.
+    pub fn watch_only(&self) -> &[ViewKey<N>] {
+        &self.watch_only
+    }
+
+    /// Returns the records found for the accounts.
+    pub fn records(&self) -> &RecordStore<N> {
+        &self.records
+    }
+
+    /// Returns the progress of the scan that found the records.
+    pub fn scan_state(&self) -> &ScanState<N> {
+        &self.scan_state
+    }
+
+    /// Returns the history of the records, in the order of the chain.
+    pub fn history(&self) -> &[HistoryEntry<N>] {
+        &self.history
+    }
+
+    /// Returns the settings of the application.
+    pub fn settings(&self) -> &BTreeMap<String, String> {
+        &self.settings
+    }
+
+    /// Write the snapshot to a file, encrypting the private keys, view keys, records, and history with the passphrase.
+    ///
+    /// The file is locked exclusively during the export, waiting up to [`DEFAULT_LOCK_TIMEOUT`] for other processes,
+    /// such as a [`crate::Wallet`] holding the file open.
+    pub fn export(&self, path: impl AsRef<Path>, passphrase: &str) -> Result<()> {
+        let _lock = StoreLock::exclusive(path.as_ref(), DEFAULT_LOCK_TIMEOUT)?;
+        self.write(path.as_ref(), passphrase)
+    }
+
+    // Write the snapshot to a file without locking it, for callers already holding its lock
+    pub(crate) fn write(&self, path: &Path, passphrase: &str) -> Result<()> {
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|private_key| Encryptor::encrypt_private_key_with_secret(private_key, passphrase))
+            .collect::<Result<Vec<_>>>()?;
+        let watch_only = self
+            .watch_only
+            .iter()
+            .map(|view_key| Encryptor::encrypt_view_key_with_secret(view_key, passphrase))
+            .collect::<Result<Vec<_>>>()?;
+        let snapshot = EncryptedSnapshot {
+            accounts,
+            watch_only,
+            encrypted_records: Some(EncryptedContents::encrypt(&self.records.encode(&Json)?, passphrase)?),
+            encrypted_history: Some(EncryptedContents::encrypt(&serde_json::to_vec(&self.history)?, passphrase)?),
+            records: None,
+            scan_state: self.scan_state.clone(),
+            history: vec![],
+            settings: self.settings.clone(),
+        };
+        let mut bytes = snapshot.encode(&Json)?;
+        let checksum = Sha256::digest(&bytes);
+        bytes.extend_from_slice(&checksum);
+        write_atomic(path, &bytes)
+    }
+
+    /// Read a snapshot from a file, decrypting the private keys, view keys, records, and history with the passphrase.
+    ///
+    /// Files that fail their checksum, or that were written by a newer version of the library, are rejected. The
+    /// lock of the file is shared with other imports, waiting up to [`DEFAULT_LOCK_TIMEOUT`] for a process writing it.
+    pub fn import(path: impl AsRef<Path>, passphrase: &str) -> Result<Self> {
+        let _lock = StoreLock::shared(path.as_ref(), DEFAULT_LOCK_TIMEOUT)?;
+        Self::read(path.as_ref(), passphrase)
+    }
+
+    // Read a snapshot from a file without locking it, for callers already holding its lock
+    pub(crate) fn read(path: &Path, passphrase: &str) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.len() < CHECKSUM_SIZE {
+            bail!("The wallet snapshot is truncated");
+        }
+        let (bytes, checksum) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+        if Sha256::digest(bytes).as_slice() != checksum {
+            bail!("The wallet snapshot is corrupted: its checksum does not match its contents");
+        }
+        let snapshot = EncryptedSnapshot::<N>::decode(bytes)?;
+        let accounts = snapshot
+            .accounts
+            .iter()
+            .map(|ciphertext| Encryptor::decrypt_private_key_with_secret(ciphertext, passphrase))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|_| anyhow!(WRONG_PASSPHRASE))?;
+        let watch_only = snapshot
+            .watch_only
+            .iter()
+            .map(|ciphertext| Encryptor::decrypt_view_key_with_secret(ciphertext, passphrase))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|_| anyhow!(WRONG_PASSPHRASE))?;
+        let records = match (snapshot.encrypted_records, snapshot.records) {
+            (Some(encrypted), _) => RecordStore::decode(&encrypted.decrypt(passphrase)?)?,
+            (None, Some(records)) => records,
+            (None, None) => bail!("The wallet snapshot holds no records"),
+        };
+        let history = match snapshot.encrypted_history {
+            Some(encrypted) => serde_json::from_slice(&encrypted.decrypt(passphrase)?)?,
+            None => snapshot.history,
+        };
+        Ok(Self {
+            accounts,
+            watch_only,
+            records,
+            scan_state: snapshot.scan_state,
+            history,
+            settings: snapshot.settings,
+        })
+    }
+
+    /// Returns `true` if the last block scanned for the snapshot is still on the chain of the given node.
+    ///
+    /// If it is, the scan can resume from [`ScanState::next_height`] without rescanning the records. A snapshot
+    /// taken before any block was scanned always matches.
+    #[cfg(not(feature = "async"))]
+    pub fn matches_chain(&self, client: &AleoAPIClient<N>) -> Result<bool> {
+        match (self.scan_state.last_hash(), self.scan_state.next_height().checked_sub(1)) {
+            (Some(last_hash), Some(last_height)) => Ok(client.get_block(last_height)?.hash() == last_hash),
+            _ => Ok(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{genesis_block, sample_block, sample_record, temp_path, CurrentNetwork},
+        BlockHeight,
+        HistoryKind,
+    };
+    #[cfg(not(feature = "async"))]
+    use crate::{
+        test_helpers::{MockResponse, MockServer},
+        testnet3,
+    };
+    use snarkvm_console::{account::Address, prelude::Uniform, types::Field};
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    // Sample a snapshot of two accounts with a record each and a watch-only account, scanned up to block 1
+    fn sample_snapshot(rng: &mut TestRng) -> WalletSnapshot<N> {
+        let accounts = vec![PrivateKey::<N>::new(rng).unwrap(), PrivateKey::<N>::new(rng).unwrap()];
+        let transaction_id = genesis_block().transactions().iter().next().unwrap().id();
+        let (mut records, mut history) = (RecordStore::new(), vec![]);
+        for (height, private_key) in (0..).zip(&accounts) {
+            let address = Address::try_from(private_key).unwrap();
+            let commitment = Field::rand(rng);
+            records.insert(commitment, sample_record(address, 100, rng).0, height);
+            let height = BlockHeight(height);
+            history.push(HistoryEntry::new(HistoryKind::Received, height, transaction_id, commitment, 100));
+        }
+        let mut scan_state = ScanState::new(BlockHeight(0));
+        scan_state.advance(&genesis_block());
+        scan_state.advance(&sample_block(1, genesis_block().hash(), rng));
+        let settings = BTreeMap::from([("endpoint".to_string(), "http://127.0.0.1:3030".to_string())]);
+        let watch_only = vec![ViewKey::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap()];
+        WalletSnapshot::new(accounts, records, scan_state)
+            .with_watch_only(watch_only)
+            .with_history(history)
+            .with_settings(settings)
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let snapshot = sample_snapshot(&mut TestRng::default());
+        let path = temp_path("snapshot-round-trip");
+        snapshot.export(&path, "correct horse").unwrap();
+
+        // The private keys, view keys, records, and history are not written in the clear.
+        let contents = String::from_utf8_lossy(&fs::read(&path).unwrap()).to_string();
+        assert!(!contents.contains(&snapshot.accounts()[0].to_string()));
+        assert!(!contents.contains(&snapshot.watch_only()[0].to_string()));
+        for (commitment, stored) in snapshot.records().iter() {
+            assert!(!contents.contains(&commitment.to_string()));
+            assert!(!contents.contains(&stored.record().owner().to_string()));
+        }
+
+        let imported = WalletSnapshot::<N>::import(&path, "correct horse").unwrap();
+        assert_eq!(imported, snapshot);
+        assert_eq!(imported.scan_state().next_height(), BlockHeight(2));
+        assert_eq!(imported.history().len(), 2);
+        assert_eq!(imported.settings()["endpoint"], "http://127.0.0.1:3030");
+
+        let error = WalletSnapshot::<N>::import(&path, "wrong horse").unwrap_err().to_string();
+        assert_eq!(error, WRONG_PASSPHRASE);
+
+        // Snapshots written with a hash of the passphrase are read all the same.
+        let mut legacy = fs::read(&path).unwrap();
+        legacy.truncate(legacy.len() - CHECKSUM_SIZE);
+        let start = legacy.iter().position(|byte| *byte == b'{').unwrap() + 1;
+        legacy.splice(start..start, br#""salt":"0field","passphrase_hash":"0field","#.iter().copied());
+        let checksum = Sha256::digest(&legacy);
+        legacy.extend_from_slice(&checksum);
+        fs::write(&path, legacy).unwrap();
+        assert_eq!(WalletSnapshot::<N>::import(&path, "correct horse").unwrap(), snapshot);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_rejects_tampered_file() {
+        let snapshot = sample_snapshot(&mut TestRng::default());
+        let path = temp_path("snapshot-tampered");
+        snapshot.export(&path, "passphrase").unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let import = |bytes: &[u8]| {
+            fs::write(&path, bytes).unwrap();
+            WalletSnapshot::<N>::import(&path, "passphrase").unwrap_err().to_string()
+        };
+
+        // Any change to the contents fails the checksum.
+        let mut tampered = bytes.clone();
+        tampered[bytes.len() / 2] ^= 1;
+        let mismatch = "The wallet snapshot is corrupted: its checksum does not match its contents";
+        assert_eq!(import(&tampered), mismatch);
+        assert_eq!(import(&bytes[..bytes.len() - 1]), mismatch);
+        assert_eq!(import(&bytes[..4]), "The wallet snapshot is truncated");
+
+        // Snapshots written by a newer version are refused, even with a valid checksum.
+        let mut newer = bytes[..bytes.len() - CHECKSUM_SIZE].to_vec();
+        newer[6..8].copy_from_slice(&7u16.to_le_bytes());
+        let checksum = Sha256::digest(&newer);
+        newer.extend_from_slice(&checksum);
+        assert_eq!(import(&newer), "The wallet snapshot has version 7, but this library only supports up to version 6");
+
+        // Encrypted records changed along with the checksum fail to decrypt.
+        let mut body = serde_json::from_slice::<serde_json::Value>(&bytes[8..bytes.len() - CHECKSUM_SIZE]).unwrap();
+        let ciphertext = body["encrypted_records"]["ciphertext"].as_str().unwrap();
+        let flipped = if ciphertext.starts_with('0') { "1" } else { "0" };
+        body["encrypted_records"]["ciphertext"] = format!("{flipped}{}", &ciphertext[1..]).into();
+        let mut forged = bytes[..8].to_vec();
+        forged.extend(serde_json::to_vec(&body).unwrap());
+        let checksum = Sha256::digest(&forged);
+        forged.extend_from_slice(&checksum);
+        assert_eq!(import(&forged), WRONG_PASSPHRASE);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_without_history() {
+        let snapshot = sample_snapshot(&mut TestRng::default());
+        let path = temp_path("snapshot-without-history");
+        snapshot.export(&path, "passphrase").unwrap();
+
+        // Snapshots of the first version have no history, and hold their records in plaintext.
+        let bytes = fs::read(&path).unwrap();
+        let mut body = serde_json::from_slice::<serde_json::Value>(&bytes[8..bytes.len() - CHECKSUM_SIZE]).unwrap();
+        let object = body.as_object_mut().unwrap();
+        object.remove("history");
+        object.remove("encrypted_records");
+        object.remove("encrypted_history");
+        object.insert("records".to_string(), serde_json::to_value(snapshot.records()).unwrap());
+        let mut older = bytes[..6].to_vec();
+        older.extend_from_slice(&1u16.to_le_bytes());
+        older.extend(serde_json::to_vec(&body).unwrap());
+        let checksum = Sha256::digest(&older);
+        older.extend_from_slice(&checksum);
+        fs::write(&path, older).unwrap();
+
+        let imported = WalletSnapshot::<N>::import(&path, "passphrase").unwrap();
+        assert!(imported.history().is_empty());
+        assert_eq!(imported.records().len(), snapshot.records().len());
+        assert_eq!(imported.records().balance(), snapshot.records().balance());
+
+        // Their records are migrated, and the credits records are attributed to their program.
+        assert!(imported.records().iter().all(|(_, stored)| stored.program_id().is_some()));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(not(feature = "async"))]
+    #[test]
+    fn test_snapshot_matches_chain() {
+        let rng = &mut TestRng::default();
+        let snapshot = sample_snapshot(rng);
+        let scanned = sample_block(1, genesis_block().hash(), rng);
+        let reorged = sample_block(1, sample_block(2, genesis_block().hash(), rng).hash(), rng);
+        assert_eq!(Some(scanned.hash()), snapshot.scan_state().last_hash());
+
+        // The scan resumes when the node has the last scanned block, and must restart after a reorganization.
+        for (block, matches) in [(scanned, true), (reorged, false)] {
+            let block = block.to_string();
+            let server = MockServer::start(move |request| match request.path.as_str() {
+                "/testnet3/block/1" => Some(MockResponse::json(&block)),
+                _ => None,
+            });
+            assert_eq!(snapshot.matches_chain(&testnet3(server.base_url())).unwrap(), matches);
+        }
+        let unscanned = WalletSnapshot::<N>::new(vec![], RecordStore::new(), ScanState::new(BlockHeight(0)));
+        assert!(unscanned.matches_chain(&testnet3("http://127.0.0.1:9")).unwrap());
+    }
+}
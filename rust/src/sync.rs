@@ -0,0 +1,1407 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Syncing many accounts over a single stream of blocks.
+//!
+//! A [`SyncService`] fetches each block once and checks its records against the view key of every account it
+//! holds, so that syncing many accounts costs the requests of one. Each account keeps its own cursor, a
+//! [`ScanState`]. The accounts whose cursor reached the tip stream follow it together, while an account added
+//! behind it, e.g. from the height at which the account was created, is backfilled until it catches up.
+//!
+//! Backfill and tip chunks interleave at the ratio set by [`SyncService::with_backfill_ratio`], so a deep
+//! backfill of one account does not hold back the tip events of the others.
+//!
+//! The blocks of each chunk are fetched once for all the accounts, as they all discover records. How the
+//! transactions the accounts watch are resolved is planned each chunk by a [`ScanPlanner`], and the last plan is
+//! kept for the caller to inspect with [`SyncService::last_plan`]. With [`SyncService::with_prefetcher`], the
+//! chunks a backfill will scan are fetched ahead by a [`BlockPrefetcher`] while the service scans earlier ones.
+//!
+//! [`SyncService::follow`] keeps the accounts synced as the chain grows, waiting on a [`HeightWatcher`] for the
+//! blocks beyond the latest one instead of polling the node on the thread of the caller.
+//!
+//! A node that is still syncing returns a latest height that lags behind the network, past which the records of
+//! the accounts would silently go missing. With [`SyncService::with_require_synced`], the tip stream only follows
+//! the latest height of a synced node, while the backfills below it go on.
+//!
+//! The backfills fetch their chunks as [`RequestPriority::Background`] requests, so that the requests a user waits
+//! for go first under a shared [`Budget`]. A priority refresh, requested with [`SyncService::request_priority_refresh`]
+//! or from another thread with a [`PriorityHandle`], brings the accounts following the tip up to the latest block
+//! ahead of the backfills, which are shed meanwhile and resume from the cursors of their accounts.
+
+use crate::{
+    ids::heights,
+    AleoAPIClient,
+    AuditKey,
+    BlockHeight,
+    BlockPrefetcher,
+    Budget,
+    CancellationToken,
+    Cancelled,
+    EventLog,
+    HeightWatcher,
+    NodeNotSynced,
+    NodeSyncStatus,
+    OutboxEvent,
+    OutboxQueue,
+    Preempted,
+    PrefetchOptions,
+    RequestPriority,
+    ScanPlan,
+    ScanPlanner,
+    ScanReport,
+    ScanState,
+    ScanStrategy,
+    TransactionStatus,
+    WalletEvent,
+    WatchOnlyAccount,
+    WatchOptions,
+};
+
+use anyhow::{anyhow, bail, Result};
+use rayon::prelude::*;
+use snarkvm_console::{
+    account::ViewKey,
+    program::{Network, Plaintext, Record},
+    types::Field,
+};
+use snarkvm_synthesizer::Block;
+use std::{
+    fmt,
+    ops::Range,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+// How long a sync whose backfill was shed waits for the interactive work before stepping again
+const SHED_WAIT: Duration = Duration::from_millis(100);
+
+/// The identifier of an account of a [`SyncService`], assigned when the account is added
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AccountId(u32);
+
+impl fmt::Display for AccountId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// An event of a [`SyncService`], for one of its accounts
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyncEvent<N: Network> {
+    /// A record owned by the account was found in the block at the given height
+    Record { account: AccountId, height: u32, commitment: Field<N>, record: Box<Record<N, Plaintext<N>>> },
+    /// The account synced every block below the given height
+    Synced { account: AccountId, next_height: u32 },
+    /// The backfill of the account reached the tip stream, which the account follows from the given height
+    CaughtUp { account: AccountId, height: u32 },
+    /// The account synced every block below the given height, and its record store is due a compaction
+    CompactionDue { account: AccountId, next_height: u32 },
+    /// A transaction watched for the account was aborted by the block at the given height
+    Aborted { account: AccountId, height: u32, transaction_id: N::TransactionID },
+    /// A transaction watched for the account was confirmed by the block at the given height
+    Confirmed { account: AccountId, height: u32, transaction_id: N::TransactionID },
+}
+
+impl<N: Network> SyncEvent<N> {
+    /// Returns the account the event is for.
+    pub fn account(&self) -> AccountId {
+        match self {
+            Self::Record { account, .. }
+            | Self::Synced { account, .. }
+            | Self::CaughtUp { account, .. }
+            | Self::CompactionDue { account, .. }
+            | Self::Aborted { account, .. }
+            | Self::Confirmed { account, .. } => *account,
+        }
+    }
+}
+
+/// The work done by a call to [`SyncService::step`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyncStep {
+    /// The blocks at the given heights were scanned for the accounts following the tip
+    Tip(Range<u32>),
+    /// The blocks at the given heights were scanned for the accounts being backfilled
+    Backfill(Range<u32>),
+    /// Every account is synced up to the latest block
+    Idle,
+    /// The node is not synced, so the tip stream waits for it, and no account is being backfilled
+    NodeNotSynced(NodeSyncStatus),
+    /// The backfill chunk at the given heights was shed while interactive work was in progress, and is scanned again
+    /// once it completes
+    Shed(Range<u32>),
+}
+
+/// A handle to request a priority refresh of a [`SyncService`] from another thread, e.g. the one of a user interface
+///
+/// Clones of a handle request refreshes of the same service.
+#[derive(Clone)]
+pub struct PriorityHandle {
+    requests: Arc<PriorityRequests>,
+    budget: Option<Budget>,
+}
+
+impl PriorityHandle {
+    /// Request a priority refresh, and wait until the service has synced the accounts following the tip up to the
+    /// latest block, whose height is returned.
+    ///
+    /// Until then, interactive work is in progress on the budget of the service, if it has one, so that a backfill
+    /// chunk being fetched is shed. The refresh is served by the next [`SyncService::step`] of the service, so the
+    /// thread stepping it calls [`SyncService::request_priority_refresh`] instead. Fails with the error of the
+    /// refresh, or once the timeout elapses before the service served it.
+    pub fn request_priority_refresh(&self, timeout: Duration) -> Result<u32> {
+        let ticket = {
+            let mut state = self.requests.state();
+            state.requested += 1;
+            state.requested
+        };
+        let _interactive = self.budget.as_ref().map(Budget::begin_interactive);
+        let state = self.requests.state();
+        let wait = self.requests.served.wait_timeout_while(state, timeout, |state| state.served < ticket);
+        let (state, _) = wait.unwrap_or_else(|poisoned| poisoned.into_inner());
+        match &state.result {
+            _ if state.served < ticket => bail!("The priority refresh was not served within {timeout:?}"),
+            Some(Ok(height)) => Ok(*height),
+            Some(Err(error)) => bail!("The priority refresh failed: {error}"),
+            None => bail!("The priority refresh was not served"),
+        }
+    }
+}
+
+// The priority refreshes requested from the handles of a service, notified when one is served
+#[derive(Default)]
+struct PriorityRequests {
+    state: Mutex<PriorityState>,
+    served: Condvar,
+}
+
+#[derive(Default)]
+struct PriorityState {
+    // The number of refreshes requested, and of those served, as a refresh serves all those requested before it
+    requested: u64,
+    served: u64,
+    // The latest height reached by the last refresh served, or its error
+    result: Option<Result<u32, String>>,
+}
+
+impl PriorityRequests {
+    fn state(&self) -> MutexGuard<'_, PriorityState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Returns the last refresh requested, if it is not served yet
+    fn pending(&self) -> Option<u64> {
+        let state = self.state();
+        (state.requested > state.served).then_some(state.requested)
+    }
+
+    fn serve(&self, ticket: u64, result: Result<u32, String>) {
+        let mut state = self.state();
+        state.served = state.served.max(ticket);
+        state.result = Some(result);
+        self.served.notify_all();
+    }
+}
+
+// An account of a sync service, with its cursor
+struct SyncAccount<N: Network> {
+    id: AccountId,
+    view_key: ViewKey<N>,
+    address_x_coordinate: Field<N>,
+    scan_state: ScanState<N>,
+    // The transactions of the account that no block has confirmed or aborted yet
+    watched: Vec<N::TransactionID>,
+}
+
+impl<N: Network> SyncAccount<N> {
+    // Returns the cursor and watched transactions of the account after the blocks it has not synced yet, and the
+    // events of those blocks, given the IDs of the transactions each block aborted. The account itself is left as
+    // it is, so that a failed chunk changes no account.
+    fn scan(&self, blocks: &[Block<N>], aborted: &[Vec<N::TransactionID>]) -> Result<SyncScan<N>> {
+        let (mut scan_state, mut watched, mut events) = (self.scan_state.clone(), self.watched.clone(), vec![]);
+        for (index, block) in blocks.iter().enumerate() {
+            if block.height() < self.scan_state.next_height().0 {
+                continue;
+            }
+            if scan_state.last_hash().map_or(false, |last_hash| block.previous_hash() != last_hash) {
+                bail!("Block {} does not build on the last block synced for account {}", block.height(), self.id);
+            }
+            for (commitment, record) in block.records() {
+                if record.is_owner_with_address_x_coordinate(&self.view_key, &self.address_x_coordinate) {
+                    let record = Box::new(record.decrypt(&self.view_key)?);
+                    let (account, height, commitment) = (self.id, block.height(), *commitment);
+                    events.push(SyncEvent::Record { account, height, commitment, record });
+                }
+            }
+            // A watched transaction is no longer watched once a block confirms or aborts it.
+            watched.retain(|transaction_id| {
+                if aborted.get(index).map_or(false, |aborted| aborted.contains(transaction_id)) {
+                    let (account, height, transaction_id) = (self.id, block.height(), *transaction_id);
+                    events.push(SyncEvent::Aborted { account, height, transaction_id });
+                    return false;
+                }
+                if block.transactions().transaction_ids().any(|confirmed| confirmed == transaction_id) {
+                    let (account, height, transaction_id) = (self.id, block.height(), *transaction_id);
+                    events.push(SyncEvent::Confirmed { account, height, transaction_id });
+                    return false;
+                }
+                true
+            });
+            scan_state.advance(block);
+        }
+        if scan_state.next_height() != self.scan_state.next_height() {
+            events.push(SyncEvent::Synced { account: self.id, next_height: scan_state.next_height().0 });
+        }
+        Ok((scan_state, watched, events))
+    }
+}
+
+// The cursor and watched transactions of an account after a chunk, and the events of the chunk
+type SyncScan<N> = (ScanState<N>, Vec<<N as Network>::TransactionID>, Vec<SyncEvent<N>>);
+
+/// A sync of many accounts, watch-only or not, over a single stream of blocks
+///
+/// The service runs on the thread of the caller, one chunk of at most [`AleoAPIClient::max_block_request`]
+/// blocks per [`SyncService::step`]. The records of a chunk are checked against the accounts in parallel.
+pub struct SyncService<N: Network> {
+    api_client: AleoAPIClient<N>,
+    accounts: Vec<SyncAccount<N>>,
+    next_id: u32,
+    // The height of the next block of the tip stream. The accounts whose cursor is below it are backfilled.
+    tip_height: u32,
+    // The latest height the node returned, which is queried again once the tip stream reaches it
+    latest_height: Option<u32>,
+    backfill_ratio: u32,
+    // The number of backfill chunks that may still run before the next tip chunk
+    backfill_credit: u32,
+    // The number of blocks of the tip stream between two compactions, if they are scheduled
+    compaction_interval: Option<u32>,
+    planner: ScanPlanner,
+    // The strategy set by the caller, which overrides the plan of each chunk
+    strategy: Option<ScanStrategy>,
+    last_plan: Option<ScanPlan>,
+    outbox: Option<OutboxQueue<N>>,
+    // The events of the outbox pumped since they were last taken
+    outbox_events: Vec<OutboxEvent<N>>,
+    height_watcher: Option<HeightWatcher<N>>,
+    event_log: Option<EventLog<N>>,
+    prefetcher: Option<BlockPrefetcher<N>>,
+    require_synced: bool,
+    // The status of the node, if it was not synced when the tip stream last queried the latest height
+    unsynced: Option<NodeSyncStatus>,
+    priority_requests: Arc<PriorityRequests>,
+    // The last priority refresh requested when the one being served started
+    refreshing: Option<u64>,
+}
+
+impl<N: Network> SyncService<N> {
+    /// The default number of backfill chunks per tip chunk
+    pub const DEFAULT_BACKFILL_RATIO: u32 = 1;
+
+    /// Create a sync service without accounts.
+    pub fn new(api_client: AleoAPIClient<N>) -> Self {
+        Self {
+            api_client,
+            accounts: vec![],
+            next_id: 0,
+            tip_height: 0,
+            latest_height: None,
+            backfill_ratio: Self::DEFAULT_BACKFILL_RATIO,
+            backfill_credit: 0,
+            compaction_interval: None,
+            planner: ScanPlanner::new(1),
+            strategy: None,
+            last_plan: None,
+            outbox: None,
+            outbox_events: vec![],
+            height_watcher: None,
+            event_log: None,
+            prefetcher: None,
+            require_synced: false,
+            unsynced: None,
+            priority_requests: Arc::default(),
+            refreshing: None,
+        }
+    }
+
+    /// Set the number of backfill chunks that run between two tip chunks, while both have work.
+    ///
+    /// With a ratio of `0`, backfills only run once the tip stream has reached the latest block.
+    pub fn with_backfill_ratio(mut self, backfill_ratio: u32) -> Self {
+        self.backfill_ratio = backfill_ratio;
+        self
+    }
+
+    /// Returns the number of backfill chunks that run between two tip chunks.
+    pub fn backfill_ratio(&self) -> u32 {
+        self.backfill_ratio
+    }
+
+    /// Schedule a compaction every given number of blocks of the tip stream.
+    ///
+    /// Each time the tip stream crosses a multiple of the interval, a [`SyncEvent::CompactionDue`] is passed for
+    /// each account following it, whose record store can then be compacted with [`crate::RecordStore::compact`].
+    pub fn with_compaction_interval(mut self, compaction_interval: u32) -> Self {
+        self.compaction_interval = Some(compaction_interval.max(1));
+        self
+    }
+
+    /// Returns the number of blocks of the tip stream between two compactions, if they are scheduled.
+    pub fn compaction_interval(&self) -> Option<u32> {
+        self.compaction_interval
+    }
+
+    /// Set the planner that chooses how the watched transactions of each chunk are resolved.
+    ///
+    /// With a [`ScanStrategy::TagScan`], the aborted transactions of each block of the chunk are read, one block
+    /// per request, so the default planner plans with a maximum block request of `1`. With
+    /// [`ScanStrategy::FindLookups`], the status of each watched transaction is looked up instead.
+    pub fn with_planner(mut self, planner: ScanPlanner) -> Self {
+        self.planner = planner;
+        self
+    }
+
+    /// Returns the planner of the service.
+    pub fn planner(&self) -> &ScanPlanner {
+        &self.planner
+    }
+
+    /// Override the strategy the planner chooses for each chunk, or let it choose again with `None`.
+    ///
+    /// A [`ScanStrategy::BlockScan`] resolves the watched transactions as a [`ScanStrategy::TagScan`] does, as the
+    /// blocks of each chunk are fetched in any case.
+    pub fn with_strategy(mut self, strategy: Option<ScanStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Returns the plan of the last chunk scanned, if any.
+    pub fn last_plan(&self) -> Option<&ScanPlan> {
+        self.last_plan.as_ref()
+    }
+
+    /// Pump the given outbox after each chunk of the tip stream, so that its transactions are broadcast while the
+    /// service follows the tip.
+    ///
+    /// The events of the outbox are kept until they are taken with [`SyncService::take_outbox_events`]. A pump
+    /// that fails, e.g. because the node went offline, fails the step after its chunk was scanned.
+    pub fn with_outbox(mut self, outbox: OutboxQueue<N>) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    /// Returns the outbox pumped by the service, if any, e.g. to enqueue transactions.
+    pub fn outbox_mut(&mut self) -> Option<&mut OutboxQueue<N>> {
+        self.outbox.as_mut()
+    }
+
+    /// Returns the events of the outbox since they were last taken.
+    pub fn take_outbox_events(&mut self) -> Vec<OutboxEvent<N>> {
+        std::mem::take(&mut self.outbox_events)
+    }
+
+    /// Read the latest height from the given watcher, e.g. one failing over to other endpoints than the client of
+    /// the service, instead of querying the node once the tip stream reaches it.
+    pub fn with_height_watcher(mut self, height_watcher: HeightWatcher<N>) -> Self {
+        self.height_watcher = Some(height_watcher);
+        self
+    }
+
+    /// Returns the height watcher of the service, if any, e.g. to subscribe to the changes of the latest height.
+    pub fn height_watcher(&self) -> Option<&HeightWatcher<N>> {
+        self.height_watcher.as_ref()
+    }
+
+    /// Write the chunks of blocks the service scans, and the records they hold for its accounts, to the given event
+    /// log.
+    pub fn with_event_log(mut self, event_log: EventLog<N>) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    /// Returns the event log the service writes to, if any.
+    pub fn event_log(&self) -> Option<&EventLog<N>> {
+        self.event_log.as_ref()
+    }
+
+    /// Fetch the chunks of each backfill ahead of the one being scanned, up to the tip stream, with a
+    /// [`BlockPrefetcher`] warming the block cache of the client.
+    ///
+    /// The tip stream reads the blocks beyond the latest height as they are produced, so it is not read ahead.
+    pub fn with_prefetcher(mut self, options: PrefetchOptions) -> Result<Self> {
+        let prefetcher = BlockPrefetcher::start(self.background_client(), options)?;
+        self.api_client = prefetcher.api_client().clone().with_priority(self.api_client.priority());
+        self.prefetcher = Some(prefetcher);
+        Ok(self)
+    }
+
+    /// Returns the statistics of the blocks the backfills read through the prefetcher, if the service has one.
+    pub fn scan_report(&self) -> Option<ScanReport> {
+        self.prefetcher.as_ref().map(BlockPrefetcher::report)
+    }
+
+    /// Set whether the tip stream only follows the latest height of a synced node, by default `false`.
+    ///
+    /// Before the latest height is queried, the [`AleoAPIClient::node_sync_status`] of the client is checked. While
+    /// the node is syncing or stale, the tip stream waits, and the backfills below it go on, as the node still
+    /// serves the blocks it holds. A height watcher started by [`SyncService::follow`] only reads the latest height
+    /// of a synced endpoint too, while one set with [`SyncService::with_height_watcher`] follows its own
+    /// [`WatchOptions::require_synced`].
+    pub fn with_require_synced(mut self, require_synced: bool) -> Self {
+        self.require_synced = require_synced;
+        self
+    }
+
+    /// Returns `true` if the tip stream only follows the latest height of a synced node.
+    pub fn require_synced(&self) -> bool {
+        self.require_synced
+    }
+
+    /// Add an account to sync from the given height, e.g. the height at which it was created, and return its
+    /// identifier.
+    ///
+    /// An account starting below the tip stream is backfilled without holding back the other accounts.
+    pub fn add_account(&mut self, view_key: impl TryInto<ViewKey<N>>, start_height: BlockHeight) -> Result<AccountId> {
+        let view_key = view_key.try_into().map_err(|_| anyhow!("Invalid view key"))?;
+        Ok(self.push_account(view_key, start_height))
+    }
+
+    /// Add a watch-only account to sync from the given height, and return its identifier.
+    pub fn add_watch_only(&mut self, account: &WatchOnlyAccount<N>, start_height: BlockHeight) -> AccountId {
+        self.push_account(*account.view_key(), start_height)
+    }
+
+    /// Add the account of an audit key to sync from the given height, and return its identifier.
+    ///
+    /// The service finds records with view keys alone, and never looks up serial numbers, so the account receives
+    /// the same [`SyncEvent::Record`] events as any other. Whether its records were spent stays unknown.
+    pub fn add_audit_key(&mut self, audit_key: &AuditKey<N>, start_height: BlockHeight) -> AccountId {
+        self.push_account(*audit_key.view_key(), start_height)
+    }
+
+    /// Watch a transaction broadcast by the given account, so that a [`SyncEvent::Confirmed`] or a
+    /// [`SyncEvent::Aborted`] is passed for the account once a block confirms or aborts it. Returns `false` if the
+    /// service does not hold the account.
+    ///
+    /// The records of a transaction inserted as pending with [`crate::RecordStore::insert_pending`] are confirmed
+    /// on the first event with [`crate::RecordStore::confirm_pending`], and rolled back on the second with
+    /// [`crate::RecordStore::rollback_pending`].
+    ///
+    /// While an account watches transactions, the aborted transactions of each block it syncs are requested from
+    /// the node, which costs a request per block.
+    pub fn watch_transaction(&mut self, id: AccountId, transaction_id: N::TransactionID) -> bool {
+        match self.accounts.iter_mut().find(|account| account.id == id) {
+            Some(account) => {
+                account.watched.push(transaction_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop syncing the given account. Returns `false` if the service does not hold it.
+    pub fn remove_account(&mut self, id: AccountId) -> bool {
+        let count = self.accounts.len();
+        self.accounts.retain(|account| account.id != id);
+        self.accounts.len() != count
+    }
+
+    /// Returns the identifiers of the accounts, in the order they were added.
+    pub fn accounts(&self) -> impl '_ + Iterator<Item = AccountId> {
+        self.accounts.iter().map(|account| account.id)
+    }
+
+    /// Returns the cursor of the given account, if the service holds it.
+    pub fn scan_state(&self, id: AccountId) -> Option<&ScanState<N>> {
+        self.accounts.iter().find(|account| account.id == id).map(|account| &account.scan_state)
+    }
+
+    /// Returns `true` if the given account is behind the tip stream, and is being backfilled.
+    pub fn is_backfilling(&self, id: AccountId) -> bool {
+        self.scan_state(id).map_or(false, |scan_state| scan_state.next_height().0 < self.tip_height)
+    }
+
+    /// Returns a handle to request priority refreshes of the service from other threads.
+    pub fn priority_handle(&self) -> PriorityHandle {
+        let budget = self.api_client.budget().cloned();
+        PriorityHandle { requests: self.priority_requests.clone(), budget }
+    }
+
+    /// Sync the accounts following the tip up to the latest block ahead of any backfill, passing their events to
+    /// `f`, and return the height of the latest block, e.g. to show a fresh balance.
+    ///
+    /// The latest height is queried again, and interactive work is in progress on the budget of the service, if it
+    /// has one, until the refresh completes. The accounts being backfilled keep their cursors, from which the
+    /// backfill resumes.
+    pub fn request_priority_refresh(&mut self, mut f: impl FnMut(SyncEvent<N>)) -> Result<u32> {
+        let _interactive = self.api_client.budget().map(Budget::begin_interactive);
+        self.latest_height = None;
+        while let Some(tip) = self.tip_chunk()? {
+            let reached = self.reaches_latest_height(&tip);
+            self.follow_tip(tip, &mut f)?;
+            if reached {
+                break;
+            }
+        }
+        self.refreshed_height()
+    }
+
+    /// Scan the next chunk of blocks, passing its events to `f`, and return the work done.
+    ///
+    /// The events of each account are passed in the order of the chain. A chunk that fails, e.g. because a
+    /// block does not build on the last block an account synced, changes no account.
+    ///
+    /// While a priority refresh requested with a [`PriorityHandle`] is pending, only the tip stream is scanned, from
+    /// the latest height queried again, until it reaches the latest block and the refresh is served.
+    pub fn step(&mut self, f: impl FnMut(SyncEvent<N>)) -> Result<SyncStep> {
+        if self.refreshing.is_none() {
+            self.refreshing = self.priority_requests.pending();
+            if self.refreshing.is_some() {
+                self.latest_height = None;
+            }
+        }
+        let tip = match (self.tip_chunk(), self.refreshing) {
+            (Err(error), Some(ticket)) => {
+                self.serve_refresh(ticket, Err(error.to_string()));
+                return Err(error);
+            }
+            (tip, _) => tip?,
+        };
+        if let Some(ticket) = self.refreshing {
+            match tip {
+                Some(tip) => {
+                    let reached = self.reaches_latest_height(&tip);
+                    let step = self.follow_tip(tip, f);
+                    let result = match &step {
+                        Err(error) => Some(Err(error.to_string())),
+                        Ok(_) if reached => Some(self.refreshed_height().map_err(|error| error.to_string())),
+                        Ok(_) => None,
+                    };
+                    if let Some(result) = result {
+                        self.serve_refresh(ticket, result);
+                    }
+                    return step;
+                }
+                // A node that is not synced fails the refresh, while the backfills below the tip stream go on.
+                None => self.serve_refresh(ticket, self.refreshed_height().map_err(|error| error.to_string())),
+            }
+        }
+        let backfill = self.backfill_chunk();
+        match (tip, backfill) {
+            (Some(_), Some(backfill)) if self.backfill_credit > 0 => {
+                self.backfill_credit -= 1;
+                self.backfill(backfill, f)
+            }
+            (Some(tip), _) => {
+                self.backfill_credit = self.backfill_ratio;
+                self.follow_tip(tip, f)
+            }
+            (None, Some(backfill)) => self.backfill(backfill, f),
+            (None, None) => match self.unsynced {
+                Some(status) => Ok(SyncStep::NodeNotSynced(status)),
+                None => Ok(SyncStep::Idle),
+            },
+        }
+    }
+
+    /// Scan chunks until every account is synced up to the latest block, passing their events to `f`.
+    ///
+    /// Once the token is cancelled, the sync stops after the chunk it is scanning, and fails with [`Cancelled`]. A
+    /// service that requires a synced node fails with [`NodeNotSynced`] once only the tip stream is left to scan,
+    /// and the node is not synced.
+    pub fn sync(&mut self, token: &CancellationToken, mut f: impl FnMut(SyncEvent<N>)) -> Result<()> {
+        loop {
+            if token.is_cancelled() {
+                return Err(Cancelled::new((), None).into());
+            }
+            match self.step(&mut f)? {
+                SyncStep::Idle => return Ok(()),
+                SyncStep::NodeNotSynced(status) => bail!(NodeNotSynced::new(self.api_client.base_url(), status)),
+                SyncStep::Shed(_) => self.wait_for_interactive(),
+                _ => (),
+            }
+        }
+    }
+
+    /// Scan chunks as the chain grows, passing their events to `f`, until the token is cancelled.
+    ///
+    /// Once every account is synced up to the latest block, the service waits for its height watcher to see a new
+    /// block. Without a watcher set with [`SyncService::with_height_watcher`], one polling the client of the service
+    /// with the default [`WatchOptions`] is started. The token is checked between chunks, and at least once per poll
+    /// interval while waiting, after which the sync fails with [`Cancelled`]. A chunk that fails, e.g. because the
+    /// node went offline, fails the sync, which resumes from the cursors of the accounts when it is called again.
+    pub fn follow(&mut self, token: &CancellationToken, mut f: impl FnMut(SyncEvent<N>)) -> Result<()> {
+        if self.height_watcher.is_none() {
+            let options = WatchOptions { require_synced: self.require_synced, ..WatchOptions::default() };
+            self.height_watcher = Some(HeightWatcher::start(vec![self.api_client.clone()], options)?);
+        }
+        loop {
+            if token.is_cancelled() {
+                return Err(Cancelled::new((), None).into());
+            }
+            let step = self.step(&mut f)?;
+            if let SyncStep::Shed(_) = step {
+                self.wait_for_interactive();
+            } else if matches!(step, SyncStep::Idle | SyncStep::NodeNotSynced(_)) {
+                if let Some(watcher) = &self.height_watcher {
+                    // The wait times out after an interval, so that the token is checked again.
+                    let next_height = watcher.current().max(self.latest_height).map_or(0, |height| height + 1);
+                    let _ = watcher.wait_for_height(next_height, watcher.options().poll_interval);
+                }
+            }
+        }
+    }
+
+    // Returns a clone of the client whose requests are background requests
+    fn background_client(&self) -> AleoAPIClient<N> {
+        self.api_client.clone().with_priority(RequestPriority::Background)
+    }
+
+    // Wait for the interactive work for which a backfill chunk was shed, checking again after a while
+    fn wait_for_interactive(&self) {
+        if let Some(budget) = self.api_client.budget() {
+            budget.wait_for_interactive(SHED_WAIT);
+        }
+    }
+
+    // Returns the latest height the tip stream reached in a priority refresh, or fails if the node is not synced
+    fn refreshed_height(&self) -> Result<u32> {
+        match (self.unsynced, self.latest_height) {
+            (Some(status), _) => bail!(NodeNotSynced::new(self.api_client.base_url(), status)),
+            (None, Some(latest_height)) => Ok(latest_height),
+            // No account follows the tip, so the tip stream did not query the latest height.
+            (None, None) => Ok(self.api_client.latest_height()?.0),
+        }
+    }
+
+    // Returns `true` if the tip chunk reaches the latest height, so that a refresh does not query it again
+    fn reaches_latest_height(&self, tip: &Range<u32>) -> bool {
+        self.latest_height.map_or(false, |latest_height| tip.end > latest_height)
+    }
+
+    // Complete the priority refresh being served with its result
+    fn serve_refresh(&mut self, ticket: u64, result: Result<u32, String>) {
+        self.priority_requests.serve(ticket, result);
+        self.refreshing = None;
+    }
+
+    // Add an account with a new identifier
+    fn push_account(&mut self, view_key: ViewKey<N>, start_height: BlockHeight) -> AccountId {
+        let id = AccountId(self.next_id);
+        self.next_id += 1;
+        let address_x_coordinate = view_key.to_address().to_x_coordinate();
+        self.accounts.push(SyncAccount {
+            id,
+            view_key,
+            address_x_coordinate,
+            scan_state: ScanState::new(start_height),
+            watched: vec![],
+        });
+        id
+    }
+
+    // Returns the next chunk of the tip stream, if an account follows it and the node has blocks beyond it. A node
+    // that is required to be synced and is not has no blocks beyond the latest height queried before.
+    fn tip_chunk(&mut self) -> Result<Option<Range<u32>>> {
+        self.unsynced = None;
+        let following = self.accounts.iter().map(|account| account.scan_state.next_height().0);
+        // The tip stream starts at the lowest cursor of the accounts following it.
+        let start_height = match following.filter(|height| *height >= self.tip_height).min() {
+            Some(start_height) => start_height,
+            None => return Ok(None),
+        };
+        if self.latest_height.map_or(true, |latest_height| start_height > latest_height) {
+            // The watcher is queried once it saw a height, and the node until then.
+            let watched = self.height_watcher.as_ref().and_then(HeightWatcher::current);
+            match watched {
+                Some(latest_height) => self.latest_height = Some(latest_height),
+                None => match self.require_synced {
+                    true => match self.api_client.node_sync_status()? {
+                        NodeSyncStatus::Synced => self.latest_height = Some(self.api_client.latest_height()?.0),
+                        status => self.unsynced = Some(status),
+                    },
+                    false => self.latest_height = Some(self.api_client.latest_height()?.0),
+                },
+            }
+        }
+        match self.latest_height {
+            Some(latest_height) if start_height <= latest_height => {
+                let end_height =
+                    start_height.saturating_add(self.api_client.max_block_request()).min(latest_height + 1);
+                Ok(Some(start_height..end_height))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // Returns the next chunk of the backfill, which starts at the lowest cursor below the tip stream
+    fn backfill_chunk(&self) -> Option<Range<u32>> {
+        let backfilling = self.accounts.iter().map(|account| account.scan_state.next_height().0);
+        let start_height = backfilling.filter(|height| *height < self.tip_height).min()?;
+        Some(start_height..start_height.saturating_add(self.api_client.max_block_request()).min(self.tip_height))
+    }
+
+    // Scan a chunk of the tip stream for the accounts following it
+    fn follow_tip(&mut self, block_heights: Range<u32>, mut f: impl FnMut(SyncEvent<N>)) -> Result<SyncStep> {
+        let tip_height = self.tip_height;
+        let blocks = self.api_client.get_block_range(heights(block_heights.clone()))?;
+        self.scan_chunk(&block_heights, blocks, |account| account.scan_state.next_height().0 >= tip_height, &mut f)?;
+        self.tip_height = block_heights.end;
+        if let Some(interval) = self.compaction_interval {
+            if tip_height / interval != self.tip_height / interval {
+                for account in self.accounts.iter().filter(|account| account.scan_state.next_height().0 >= tip_height) {
+                    f(SyncEvent::CompactionDue { account: account.id, next_height: self.tip_height });
+                }
+            }
+        }
+        // A failed pump leaves the chunk scanned, and is retried after the next chunk.
+        if let Some(outbox) = &mut self.outbox {
+            self.outbox_events.extend(outbox.pump(&self.api_client)?);
+        }
+        Ok(SyncStep::Tip(block_heights))
+    }
+
+    // Scan a chunk below the tip stream for the accounts being backfilled, some of which may catch up with it
+    fn backfill(&mut self, block_heights: Range<u32>, mut f: impl FnMut(SyncEvent<N>)) -> Result<SyncStep> {
+        let end_height = block_heights.end;
+        let backfilling = self.accounts.iter().filter(|account| account.scan_state.next_height().0 < end_height);
+        let backfilling = backfilling.map(|account| account.id).collect::<Vec<_>>();
+        let blocks = match &self.prefetcher {
+            Some(prefetcher) => {
+                prefetcher.warm(heights(block_heights.start..self.tip_height));
+                prefetcher.get_block_range(heights(block_heights.clone()))
+            }
+            None => self.background_client().get_block_range(heights(block_heights.clone())),
+        };
+        // A shed chunk changes no account, so the backfill resumes from the same cursors.
+        let blocks = match blocks {
+            Err(error) if error.is::<Preempted>() => return Ok(SyncStep::Shed(block_heights)),
+            blocks => blocks?,
+        };
+        self.scan_chunk(&block_heights, blocks, |account| backfilling.contains(&account.id), &mut f)?;
+        for account in self.accounts.iter().filter(|account| backfilling.contains(&account.id)) {
+            if account.scan_state.next_height().0 >= self.tip_height {
+                f(SyncEvent::CaughtUp { account: account.id, height: self.tip_height });
+            }
+        }
+        Ok(SyncStep::Backfill(block_heights))
+    }
+
+    // Scan the blocks at the given heights, fetched once, for the accounts that match the filter
+    fn scan_chunk(
+        &mut self,
+        block_heights: &Range<u32>,
+        blocks: Vec<Block<N>>,
+        filter: impl Fn(&SyncAccount<N>) -> bool,
+        mut f: impl FnMut(SyncEvent<N>),
+    ) -> Result<()> {
+        let mut watched = vec![];
+        let accounts = self.accounts.iter().filter(|account| filter(account));
+        for transaction_id in accounts.flat_map(|account| &account.watched) {
+            if !watched.contains(transaction_id) {
+                watched.push(*transaction_id);
+            }
+        }
+        let plan = self.planner.plan(block_heights.clone(), watched.len(), false);
+        let plan = match self.strategy {
+            Some(strategy) => plan.with_strategy(strategy),
+            None => plan,
+        };
+        // Parsed blocks do not hold their aborted transactions, so these are requested separately, and only while a
+        // transaction is watched.
+        let aborted = match plan.strategy() {
+            _ if watched.is_empty() => vec![],
+            ScanStrategy::FindLookups => self.lookup_aborted(&blocks, &watched)?,
+            ScanStrategy::BlockScan | ScanStrategy::TagScan => blocks
+                .iter()
+                .map(|block| self.api_client.get_block_aborted_transaction_ids(BlockHeight(block.height())))
+                .collect::<Result<Vec<_>>>()?,
+        };
+        self.last_plan = Some(plan);
+        let mut accounts = self.accounts.iter_mut().filter(|account| filter(account)).collect::<Vec<_>>();
+        let scanned = accounts.par_iter().map(|account| account.scan(&blocks, &aborted)).collect::<Result<Vec<_>>>()?;
+        let records = scanned.iter().flat_map(|(_, _, events)| events);
+        let records = records.filter(|event| matches!(event, SyncEvent::Record { .. })).count();
+        let (start_height, end_height) = (block_heights.start, block_heights.end);
+        let scan = WalletEvent::Scan { start_height, end_height, accounts: accounts.len(), records };
+        for (account, (scan_state, watched, events)) in accounts.iter_mut().zip(scanned) {
+            account.scan_state = scan_state;
+            account.watched = watched;
+            events.into_iter().for_each(&mut f);
+        }
+        if let Some(event_log) = &self.event_log {
+            event_log.append(scan)?;
+        }
+        Ok(())
+    }
+
+    // Returns the IDs of the given transactions each of the given blocks aborted, looking up the status of each
+    fn lookup_aborted(
+        &self,
+        blocks: &[Block<N>],
+        transaction_ids: &[N::TransactionID],
+    ) -> Result<Vec<Vec<N::TransactionID>>> {
+        let mut aborted = vec![vec![]; blocks.len()];
+        for transaction_id in transaction_ids {
+            if let TransactionStatus::Aborted { height, .. } = self.api_client.transaction_status(*transaction_id)? {
+                if let Some(index) = blocks.iter().position(|block| block.height() == height) {
+                    aborted[index].push(*transaction_id);
+                }
+            }
+        }
+        Ok(aborted)
+    }
+}
+
+// The integer overloads of the methods taking typed heights, kept for one release
+impl<N: Network> SyncService<N> {
+    /// Add an account to sync from the given height, and return its identifier.
+    #[deprecated(since = "0.3.6", note = "pass a `BlockHeight` to `add_account`; removed in the next release")]
+    pub fn add_account_raw(&mut self, view_key: impl TryInto<ViewKey<N>>, start_height: u32) -> Result<AccountId> {
+        self.add_account(view_key, BlockHeight(start_height))
+    }
+
+    /// Add a watch-only account to sync from the given height, and return its identifier.
+    #[deprecated(since = "0.3.6", note = "pass a `BlockHeight` to `add_watch_only`; removed in the next release")]
+    pub fn add_watch_only_raw(&mut self, account: &WatchOnlyAccount<N>, start_height: u32) -> AccountId {
+        self.add_watch_only(account, BlockHeight(start_height))
+    }
+
+    /// Add the account of an audit key to sync from the given height, and return its identifier.
+    #[deprecated(since = "0.3.6", note = "pass a `BlockHeight` to `add_audit_key`; removed in the next release")]
+    pub fn add_audit_key_raw(&mut self, audit_key: &AuditKey<N>, start_height: u32) -> AccountId {
+        self.add_audit_key(audit_key, BlockHeight(start_height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_block_with_transactions,
+            sample_output,
+            sample_transaction,
+            sample_transition,
+            CurrentNetwork,
+            MockResponse,
+            MockServer,
+        },
+        testnet3,
+        MockClock,
+    };
+    use snarkvm_console::{
+        account::{Address, PrivateKey},
+        prelude::Uniform,
+    };
+    use snarkvm_utilities::TestRng;
+
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc,
+            Arc,
+            Mutex,
+        },
+        thread,
+        time::Duration,
+    };
+
+    type N = CurrentNetwork;
+
+    // Start a mock node serving the given chain, which may be extended while the node runs, with the given aborted
+    // transactions by height, and counting the requests for blocks. The node finds the blocks of the transactions
+    // it aborted, and does not serve its sync status.
+    fn mock_node(
+        chain: Arc<Mutex<Vec<Block<N>>>>,
+        aborted: Vec<(u32, <N as Network>::TransactionID)>,
+        block_requests: Arc<AtomicUsize>,
+    ) -> MockServer {
+        MockServer::start(move |request| {
+            let blocks = chain.lock().unwrap();
+            if request.path == "/testnet3/latest/height" {
+                return Some(MockResponse::json(blocks.len() - 1));
+            }
+            if request.path == "/testnet3/latest/block" {
+                return Some(MockResponse::json(blocks.last()?));
+            }
+            if let Some(transaction_id) = request.path.strip_prefix("/testnet3/find/blockHash/") {
+                let (height, _) = aborted.iter().find(|(_, id)| id.to_string() == transaction_id)?;
+                return Some(MockResponse::json(serde_json::json!(blocks.get(*height as usize)?.hash())));
+            }
+            if let Some(hash) = request.path.strip_prefix("/testnet3/height/") {
+                return Some(MockResponse::json(blocks.iter().position(|block| block.hash().to_string() == hash)?));
+            }
+            if let Some(height) = request.path.strip_prefix("/testnet3/block/") {
+                let height = height.parse::<u32>().ok()?;
+                block_requests.fetch_add(1, Ordering::SeqCst);
+                let mut block = serde_json::to_value(blocks.get(height as usize)?).unwrap();
+                let ids = aborted.iter().filter(|(aborted_height, _)| *aborted_height == height).map(|(_, id)| id);
+                block["aborted_transaction_ids"] = serde_json::json!(ids.collect::<Vec<_>>());
+                return Some(MockResponse::json(block));
+            }
+            let (start, end) = request.path.strip_prefix("/testnet3/blocks?start=")?.split_once("&end=")?;
+            let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+            block_requests.fetch_add(1, Ordering::SeqCst);
+            let blocks = blocks.get(start..end.min(blocks.len()))?.iter().map(ToString::to_string);
+            Some(MockResponse::json(format!("[{}]", blocks.collect::<Vec<_>>().join(","))))
+        })
+    }
+
+    // Append a block paying the given address to the chain
+    fn extend_chain(chain: &Mutex<Vec<Block<N>>>, owner: Address<N>, rng: &mut TestRng) {
+        let output = sample_output(owner, 1, rng);
+        let transaction = sample_transaction([sample_transition(&[Field::rand(rng)], &[output], rng)]);
+        let mut blocks = chain.lock().unwrap();
+        let (height, previous_hash) = (blocks.len() as u32, blocks.last().unwrap().hash());
+        blocks.push(sample_block_with_transactions(height, previous_hash, [transaction].into_iter().collect(), rng));
+    }
+
+    #[test]
+    fn test_sync_service() {
+        let rng = &mut TestRng::default();
+        let (first, second) = (PrivateKey::<N>::new(rng).unwrap(), PrivateKey::<N>::new(rng).unwrap());
+        let watched = WatchOnlyAccount::new(ViewKey::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap());
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        for owner in [Address::try_from(second).unwrap(), watched.address()].into_iter().cycle().take(10) {
+            extend_chain(&chain, owner, rng);
+        }
+        let block_requests = Arc::new(AtomicUsize::new(0));
+        let server = mock_node(chain.clone(), vec![], block_requests.clone());
+        let api_client = testnet3(server.base_url()).with_max_block_request(2);
+
+        // Both accounts are synced from a single stream of blocks.
+        let mut service = SyncService::new(api_client.clone());
+        let first_id = service.add_account(first, BlockHeight(0)).unwrap();
+        let watched_id = service.add_watch_only(&watched, BlockHeight(0));
+        let mut events = vec![];
+        service.sync(&CancellationToken::new(), |event| events.push(event)).unwrap();
+        assert_eq!(block_requests.load(Ordering::SeqCst), 6);
+        let records = |events: &[SyncEvent<N>], id| {
+            events.iter().filter(|event| matches!(event, SyncEvent::Record { account, .. } if *account == id)).count()
+        };
+        assert_eq!((records(&events, first_id), records(&events, watched_id)), (0, 5));
+        assert_eq!(service.scan_state(first_id).unwrap().next_height(), BlockHeight(11));
+
+        // A second account added mid-sync is backfilled, while the tip stream keeps growing and the first
+        // account keeps receiving its blocks.
+        let second_id = service.add_account(second, BlockHeight(0)).unwrap();
+        assert!(service.is_backfilling(second_id));
+        let mut events = vec![];
+        let mut steps = vec![];
+        while service.is_backfilling(second_id) {
+            if steps.len() % 2 == 0 {
+                extend_chain(&chain, Address::try_from(first).unwrap(), rng);
+            }
+            steps.push(service.step(|event| events.push(event)).unwrap());
+        }
+        let caught_up = events.iter().position(|event| matches!(event, SyncEvent::CaughtUp { .. })).unwrap();
+        assert_eq!(events[caught_up].account(), second_id);
+        let tip_steps = steps.iter().filter(|step| matches!(step, SyncStep::Tip(_))).count();
+        let synced = |event: &&SyncEvent<N>| matches!(event, SyncEvent::Synced { account, .. } if *account == first_id);
+        assert_eq!(events[..caught_up].iter().filter(synced).count(), tip_steps);
+        assert!(tip_steps >= (steps.len() - 1) / 2);
+        assert_eq!(records(&events, second_id), 5);
+        assert!(records(&events, first_id) >= tip_steps);
+
+        // With a ratio of one, backfill and tip chunks alternate.
+        let kinds = steps.iter().map(|step| matches!(step, SyncStep::Tip(_))).collect::<Vec<_>>();
+        assert!(kinds.windows(2).all(|pair| pair[0] != pair[1]));
+
+        // Once caught up, the second account follows the tip with the others.
+        service.sync(&CancellationToken::new(), |_| ()).unwrap();
+        let latest_height = chain.lock().unwrap().len() as u32;
+        for id in [first_id, watched_id, second_id] {
+            assert_eq!(service.scan_state(id).unwrap().next_height(), BlockHeight(latest_height));
+        }
+    }
+
+    #[test]
+    fn test_sync_service_audit_key() {
+        // An audit key account is synced from the view key alone, and receives the records sent to it.
+        let rng = &mut TestRng::default();
+        let audit_key = AuditKey::from_private_key(&PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        for _ in 0..3 {
+            extend_chain(&chain, audit_key.address(), rng);
+        }
+        let server = mock_node(chain, vec![], Arc::new(AtomicUsize::new(0)));
+        let mut service = SyncService::new(testnet3(server.base_url()));
+        let id = service.add_audit_key(&audit_key, BlockHeight(1));
+        let mut heights = vec![];
+        service
+            .sync(&CancellationToken::new(), |event| {
+                if let SyncEvent::Record { account, height, record, .. } = event {
+                    assert_eq!((account, **record.owner()), (id, audit_key.address()));
+                    heights.push(height);
+                }
+            })
+            .unwrap();
+        assert_eq!(heights, [1, 2, 3]);
+        assert_eq!(service.scan_state(id).unwrap().next_height(), BlockHeight(4));
+    }
+
+    #[test]
+    fn test_sync_service_compaction_interval() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        for _ in 0..10 {
+            extend_chain(&chain, Address::try_from(private_key).unwrap(), rng);
+        }
+        let server = mock_node(chain, vec![], Arc::new(AtomicUsize::new(0)));
+        let api_client = testnet3(server.base_url()).with_max_block_request(2);
+
+        // A compaction is due each time the tip stream crosses a multiple of the interval.
+        let mut service = SyncService::new(api_client).with_compaction_interval(4);
+        let id = service.add_account(private_key, BlockHeight(0)).unwrap();
+        let mut due = vec![];
+        service
+            .sync(&CancellationToken::new(), |event| {
+                if let SyncEvent::CompactionDue { account, next_height } = event {
+                    due.push((account, next_height));
+                }
+            })
+            .unwrap();
+        assert_eq!(due, [(id, 4), (id, 8)]);
+    }
+
+    #[test]
+    fn test_sync_service_aborted_transactions() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        for _ in 0..4 {
+            extend_chain(&chain, Address::try_from(private_key).unwrap(), rng);
+        }
+        let confirmed = *chain.lock().unwrap()[3].transactions().transaction_ids().next().unwrap();
+        let mut sample_id = || sample_transaction([sample_transition(&[Field::rand(rng)], &[], rng)]).id();
+        let (aborted, unseen) = (sample_id(), sample_id());
+        let block_requests = Arc::new(AtomicUsize::new(0));
+        let server = mock_node(chain, vec![(2, aborted), (4, confirmed)], block_requests.clone());
+        let api_client = testnet3(server.base_url()).with_max_block_request(2);
+
+        // An account that watches no transaction requests no aborted transactions.
+        let mut service = SyncService::new(api_client.clone());
+        service.add_account(private_key, BlockHeight(0)).unwrap();
+        service.sync(&CancellationToken::new(), |_| ()).unwrap();
+        assert_eq!(block_requests.load(Ordering::SeqCst), 3);
+
+        // Only the transaction aborted before it was confirmed is reported aborted, in the order of the chain.
+        let mut service = SyncService::new(api_client);
+        let id = service.add_account(private_key, BlockHeight(0)).unwrap();
+        assert!(!service.watch_transaction(AccountId(1), aborted));
+        for transaction_id in [aborted, confirmed, unseen] {
+            assert!(service.watch_transaction(id, transaction_id));
+        }
+        let mut events = vec![];
+        service.sync(&CancellationToken::new(), |event| events.push(event)).unwrap();
+        assert_eq!(block_requests.load(Ordering::SeqCst), 3 + 3 + 5);
+        let position = |height: u32| {
+            events.iter().position(|event| matches!(event, SyncEvent::Record { height: found, .. } if *found == height))
+        };
+        let aborted_event = SyncEvent::Aborted { account: id, height: 2, transaction_id: aborted };
+        let index = events.iter().position(|event| *event == aborted_event).unwrap();
+        assert!(position(2).unwrap() < index && index < position(3).unwrap());
+        assert_eq!(events.iter().filter(|event| matches!(event, SyncEvent::Aborted { .. })).count(), 1);
+        assert!(events.contains(&SyncEvent::Confirmed { account: id, height: 3, transaction_id: confirmed }));
+        assert_eq!(service.accounts[0].watched, [unseen]);
+    }
+
+    #[test]
+    fn test_sync_service_planner() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        for _ in 0..9 {
+            extend_chain(&chain, Address::try_from(private_key).unwrap(), rng);
+        }
+        let aborted = sample_transaction([sample_transition(&[Field::rand(rng)], &[], rng)]).id();
+        let block_requests = Arc::new(AtomicUsize::new(0));
+        let server = mock_node(chain, vec![(6, aborted)], block_requests.clone());
+        let api_client = testnet3(server.base_url()).with_max_block_request(10);
+
+        // Looking up a single transaction takes fewer requests than reading the aborted transactions of 10 blocks.
+        let mut service = SyncService::new(api_client.clone());
+        let id = service.add_account(private_key, BlockHeight(0)).unwrap();
+        service.watch_transaction(id, aborted);
+        let mut events = vec![];
+        service.sync(&CancellationToken::new(), |event| events.push(event)).unwrap();
+        let plan = service.last_plan().unwrap();
+        assert_eq!((plan.strategy(), plan.estimated_requests()), (ScanStrategy::FindLookups, 4));
+        assert!(!plan.is_overridden());
+        assert_eq!(plan.estimate(ScanStrategy::TagScan).unwrap().requests, 10);
+        assert!(events.contains(&SyncEvent::Aborted { account: id, height: 6, transaction_id: aborted }));
+        // The range of blocks, and the block read to check the transaction was aborted.
+        assert_eq!(block_requests.swap(0, Ordering::SeqCst), 2);
+
+        // Overriding the plan reads the aborted transactions of each block instead, with the same events.
+        let mut service = SyncService::new(api_client).with_strategy(Some(ScanStrategy::TagScan));
+        let id = service.add_account(private_key, BlockHeight(0)).unwrap();
+        service.watch_transaction(id, aborted);
+        let mut overridden_events = vec![];
+        service.sync(&CancellationToken::new(), |event| overridden_events.push(event)).unwrap();
+        assert!(service.last_plan().unwrap().is_overridden());
+        assert_eq!(overridden_events, events);
+        assert_eq!(block_requests.load(Ordering::SeqCst), 1 + 10);
+    }
+
+    #[test]
+    fn test_sync_service_follow() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let owner = Address::try_from(private_key).unwrap();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        extend_chain(&chain, owner, rng);
+        let server = mock_node(chain.clone(), vec![], Arc::new(AtomicUsize::new(0)));
+        let api_client = testnet3(server.base_url());
+        let options = WatchOptions { poll_interval: Duration::from_millis(10), ..WatchOptions::default() };
+        let watcher = HeightWatcher::start(vec![api_client.clone()], options).unwrap();
+        let heights = watcher.subscribe();
+        let mut service = SyncService::new(api_client).with_height_watcher(watcher);
+        let id = service.add_account(private_key, BlockHeight(0)).unwrap();
+
+        // The blocks added while the service waits are scanned once the watcher sees them, until the token is
+        // cancelled.
+        let (token, (sender, events)) = (CancellationToken::new(), mpsc::channel());
+        thread::scope(|scope| {
+            let follower = scope.spawn(|| service.follow(&token, |event| sender.send(event).unwrap()));
+            let synced = |next_height: u32| loop {
+                let event = events.recv_timeout(Duration::from_secs(10)).unwrap();
+                if event == (SyncEvent::Synced { account: id, next_height }) {
+                    break;
+                }
+            };
+            synced(2);
+            for next_height in 3..5 {
+                extend_chain(&chain, owner, rng);
+                synced(next_height);
+            }
+            token.cancel();
+            assert!(follower.join().unwrap().unwrap_err().is::<Cancelled<()>>());
+        });
+        assert_eq!(service.scan_state(id).unwrap().next_height(), BlockHeight(4));
+        assert_eq!(heights.try_iter().last(), Some(3));
+        assert!(service.height_watcher().is_some());
+    }
+
+    #[test]
+    fn test_sync_service_prefetcher() {
+        let rng = &mut TestRng::default();
+        let (first, second) = (PrivateKey::<N>::new(rng).unwrap(), PrivateKey::<N>::new(rng).unwrap());
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        for _ in 0..20 {
+            extend_chain(&chain, Address::try_from(second).unwrap(), rng);
+        }
+
+        // The second account is backfilled once the first follows the tip, reading ahead or not.
+        let backfill = |options: Option<PrefetchOptions>| {
+            let block_requests = Arc::new(AtomicUsize::new(0));
+            let server = mock_node(chain.clone(), vec![], block_requests.clone());
+            let api_client = testnet3(server.base_url()).with_max_block_request(2).with_block_cache(6);
+            let mut service = SyncService::new(api_client);
+            if let Some(options) = options {
+                service = service.with_prefetcher(options).unwrap();
+            }
+            service.add_account(first, BlockHeight(0)).unwrap();
+            service.sync(&CancellationToken::new(), |_| ()).unwrap();
+            let id = service.add_account(second, BlockHeight(0)).unwrap();
+            let mut records = 0;
+            while service.is_backfilling(id) {
+                service.step(|event| records += matches!(event, SyncEvent::Record { .. }) as usize).unwrap();
+                thread::sleep(Duration::from_millis(10));
+            }
+            (records, block_requests.load(Ordering::SeqCst), service.scan_report())
+        };
+        let (records, block_requests, report) = backfill(None);
+        assert_eq!((records, report), (20, None));
+
+        // The chunks read ahead are taken from the block cache, at the cost of the same requests.
+        let options = PrefetchOptions { read_ahead_chunks: 2, backoff: Duration::from_millis(5) };
+        let (prefetched_records, prefetched_requests, report) = backfill(Some(options));
+        assert_eq!((prefetched_records, prefetched_requests), (records, block_requests));
+        let report = report.unwrap();
+        assert!(report.warm_hits > 0 && report.max_read_ahead <= 2);
+        assert_eq!(report.warm_hits + report.cold_blocks, 21);
+    }
+
+    #[test]
+    fn test_sync_service_require_synced() {
+        let rng = &mut TestRng::default();
+        let (first, second) = (PrivateKey::<N>::new(rng).unwrap(), PrivateKey::<N>::new(rng).unwrap());
+        let owner = Address::try_from(second).unwrap();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        for _ in 0..10 {
+            extend_chain(&chain, owner, rng);
+        }
+        let server = mock_node(chain.clone(), vec![], Arc::new(AtomicUsize::new(0)));
+        // The blocks of the sample chain are old, so the node is synced or stale depending on the threshold.
+        let client = testnet3(server.base_url());
+        let (synced, stale) =
+            (client.clone().with_staleness_threshold(Duration::MAX), client.with_staleness_threshold(Duration::ZERO));
+        let mut service = SyncService::new(synced.clone()).with_require_synced(true);
+        assert!(service.require_synced());
+        let first_id = service.add_account(first, BlockHeight(0)).unwrap();
+        service.sync(&CancellationToken::new(), |_| ()).unwrap();
+        assert_eq!(service.scan_state(first_id).unwrap().next_height(), BlockHeight(11));
+
+        // Once the node is stale, the tip stream waits for it, while an account added behind it is backfilled.
+        for _ in 0..4 {
+            extend_chain(&chain, owner, rng);
+        }
+        service.api_client = stale;
+        let second_id = service.add_account(second, BlockHeight(0)).unwrap();
+        let mut records = 0;
+        let error = service
+            .sync(&CancellationToken::new(), |event| records += matches!(event, SyncEvent::Record { .. }) as usize)
+            .unwrap_err()
+            .downcast::<NodeNotSynced>()
+            .unwrap();
+        assert!(matches!(error.status(), NodeSyncStatus::Stale { .. }));
+        assert!(matches!(service.step(|_| ()).unwrap(), SyncStep::NodeNotSynced(NodeSyncStatus::Stale { .. })));
+        assert_eq!(records, 10);
+        for id in [first_id, second_id] {
+            assert_eq!(service.scan_state(id).unwrap().next_height(), BlockHeight(11));
+        }
+
+        // The tip stream goes on once the node is synced again.
+        service.api_client = synced;
+        service.sync(&CancellationToken::new(), |_| ()).unwrap();
+        for id in [first_id, second_id] {
+            assert_eq!(service.scan_state(id).unwrap().next_height(), BlockHeight(15));
+        }
+    }
+
+    #[test]
+    fn test_sync_service_outbox() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        extend_chain(&chain, Address::try_from(private_key).unwrap(), rng);
+        let server = mock_node(chain, vec![], Arc::new(AtomicUsize::new(0)));
+        let path = std::env::temp_dir().join(format!("aleo-sync-outbox-{}", std::process::id()));
+
+        // The outbox is pumped after each chunk of the tip stream, and the node, which serves no broadcasts,
+        // rejects the queued transaction.
+        let mut service = SyncService::new(testnet3(server.base_url())).with_outbox(OutboxQueue::open(&path).unwrap());
+        service.add_account(private_key, BlockHeight(0)).unwrap();
+        let transaction = sample_transaction([sample_transition(&[Field::rand(rng)], &[], rng)]);
+        service.outbox_mut().unwrap().enqueue(transaction.clone(), 10).unwrap();
+        service.sync(&CancellationToken::new(), |_| ()).unwrap();
+        let events = service.take_outbox_events();
+        let transaction_id = transaction.id();
+        assert!(matches!(&events[..], [OutboxEvent::Rejected { transaction_id: id, .. }] if *id == transaction_id));
+        assert!(service.outbox_mut().unwrap().is_empty() && service.take_outbox_events().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // Start a service whose first account followed the tip up to block 8, each block paying the second account,
+    // which is then added to be backfilled while two more blocks pay the first account
+    fn backfilling_service(budget: Budget, rng: &mut TestRng) -> (MockServer, SyncService<N>, AccountId, AccountId) {
+        let (following, backfilled) = (PrivateKey::<N>::new(rng).unwrap(), PrivateKey::<N>::new(rng).unwrap());
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        for _ in 0..8 {
+            extend_chain(&chain, Address::try_from(backfilled).unwrap(), rng);
+        }
+        let server = mock_node(chain.clone(), vec![], Arc::new(AtomicUsize::new(0)));
+        let api_client = testnet3(server.base_url()).with_max_block_request(3).with_budget(budget);
+        let mut service = SyncService::new(api_client).with_backfill_ratio(u32::MAX);
+        let following_id = service.add_account(following, BlockHeight(0)).unwrap();
+        service.sync(&CancellationToken::new(), |_| ()).unwrap();
+        let backfilled_id = service.add_account(backfilled, BlockHeight(0)).unwrap();
+        for _ in 0..2 {
+            extend_chain(&chain, Address::try_from(following).unwrap(), rng);
+        }
+        (server, service, following_id, backfilled_id)
+    }
+
+    // Returns the heights of the records of each account in the events
+    fn record_heights(events: &[SyncEvent<N>], id: AccountId) -> Vec<u32> {
+        let heights = events.iter().filter_map(|event| match event {
+            SyncEvent::Record { account, height, .. } if *account == id => Some(*height),
+            _ => None,
+        });
+        heights.collect()
+    }
+
+    #[test]
+    fn test_sync_service_priority_refresh() {
+        let rng = &mut TestRng::default();
+        let budget = Budget::new();
+        let (_server, mut service, following_id, backfilled_id) = backfilling_service(budget.clone(), rng);
+        let mut events = vec![];
+        assert_eq!(service.step(|event| events.push(event)).unwrap(), SyncStep::Backfill(0..3));
+
+        // While interactive work is in progress, the backfill chunk is shed, and changes no account.
+        let interactive = budget.begin_interactive();
+        assert_eq!(service.step(|event| events.push(event)).unwrap(), SyncStep::Shed(3..6));
+        assert_eq!(service.scan_state(backfilled_id).unwrap().next_height(), BlockHeight(3));
+        drop(interactive);
+
+        // A refresh requested from another thread runs the tip stream ahead of the queued backfill chunks.
+        let handle = service.priority_handle();
+        let requester = thread::spawn(move || handle.request_priority_refresh(Duration::from_secs(30)));
+        while service.priority_requests.pending().is_none() {
+            thread::yield_now();
+        }
+        assert_eq!(service.step(|event| events.push(event)).unwrap(), SyncStep::Tip(9..11));
+        assert_eq!(requester.join().unwrap().unwrap(), 10);
+        assert_eq!(record_heights(&events, following_id), [9, 10]);
+
+        // The backfill resumes from its cursor without losing records.
+        service.sync(&CancellationToken::new(), |event| events.push(event)).unwrap();
+        assert_eq!(record_heights(&events, backfilled_id), (1..=8).collect::<Vec<_>>());
+        assert_eq!(service.scan_state(backfilled_id).unwrap().next_height(), BlockHeight(11));
+
+        // A refresh that is not served in time fails.
+        let error = service.priority_handle().request_priority_refresh(Duration::from_millis(10)).unwrap_err();
+        assert_eq!(error.to_string(), "The priority refresh was not served within 10ms");
+    }
+
+    #[test]
+    fn test_sync_service_interactive_reserve() {
+        // Of the 5 requests per window, the backfill leaves 2 for interactive requests.
+        let rng = &mut TestRng::default();
+        let clock = MockClock::new();
+        let window = Duration::from_secs(60);
+        let budget = Budget::new().with_max_requests(5).with_window(window).with_interactive_reserve(2);
+        let budget = budget.with_clock(clock.clone());
+        let (_server, mut service, following_id, backfilled_id) = backfilling_service(budget.clone(), rng);
+        clock.advance(window);
+        let mut events = vec![];
+        assert_eq!(service.step(|event| events.push(event)).unwrap(), SyncStep::Backfill(0..3));
+        assert_eq!(service.step(|event| events.push(event)).unwrap(), SyncStep::Backfill(3..6));
+        let error = service.step(|event| events.push(event)).unwrap_err();
+        assert_eq!(crate::error_code(&error), crate::ErrorCode::BudgetExhausted);
+
+        // The refresh still gets the latest height and the tip chunk within the window.
+        assert_eq!(service.request_priority_refresh(|event| events.push(event)).unwrap(), 10);
+        assert_eq!(budget.usage().requests, 5);
+        assert_eq!(record_heights(&events, following_id), [9, 10]);
+
+        // In the next windows, the backfill resumes from its cursor without losing records.
+        loop {
+            clock.advance(window);
+            match service.sync(&CancellationToken::new(), |event| events.push(event)) {
+                Err(error) => assert_eq!(crate::error_code(&error), crate::ErrorCode::BudgetExhausted),
+                Ok(()) => break,
+            }
+        }
+        assert_eq!(record_heights(&events, backfilled_id), (1..=8).collect::<Vec<_>>());
+    }
+}
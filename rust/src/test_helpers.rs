@@ -0,0 +1,594 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm_console::{
+    account::{Address, PrivateKey},
+    network::Testnet3,
+    prelude::{FromBytes, Network, TestRng, ToBytes, Uniform, Zero},
+    program::{Balance, Ciphertext, Identifier, Literal, Owner, Plaintext, ProgramID, Record},
+    types::{Field, Scalar, U64},
+};
+use snarkvm_synthesizer::{
+    Block,
+    Execution,
+    Fee,
+    Header,
+    Input,
+    Metadata,
+    Output,
+    PartialSolution,
+    ProverSolution,
+    PuzzleCommitment,
+    PuzzleProof,
+    Transaction,
+    Transactions,
+    Transition,
+};
+
+use once_cell::sync::Lazy;
+use rand::{CryptoRng, Rng};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    env,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+pub(crate) type CurrentNetwork = Testnet3;
+
+/// The commitment and serial number of a record, by height
+pub(crate) type RecordIds = Vec<(Field<CurrentNetwork>, Field<CurrentNetwork>)>;
+/// The paths requested from a mock node
+pub(crate) type PathLog = Arc<Mutex<Vec<String>>>;
+
+/// A record created by a transition, with its commitment
+pub(crate) type OutputRecord = (Field<CurrentNetwork>, Record<CurrentNetwork, Ciphertext<CurrentNetwork>>);
+
+static GENESIS_BLOCK: Lazy<Block<CurrentNetwork>> =
+    Lazy::new(|| Block::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap());
+
+/// Returns the genesis block of the current network.
+pub(crate) fn genesis_block() -> Block<CurrentNetwork> {
+    GENESIS_BLOCK.clone()
+}
+
+/// The number of blocks of the chain returned by [`sample_chain`]
+pub(crate) const SAMPLE_CHAIN_LENGTH: u32 = 200;
+
+static SAMPLE_CHAIN: Lazy<Vec<Block<CurrentNetwork>>> = Lazy::new(|| {
+    let (rng, mut blocks) = (&mut TestRng::default(), vec![genesis_block()]);
+    for height in 1..SAMPLE_CHAIN_LENGTH {
+        blocks.push(sample_block(height, blocks.last().unwrap().hash(), rng));
+    }
+    blocks
+});
+
+/// Returns a chain of [`SAMPLE_CHAIN_LENGTH`] blocks on top of the genesis block, in which each block builds on
+/// the one before it.
+pub(crate) fn sample_chain() -> &'static [Block<CurrentNetwork>] {
+    &SAMPLE_CHAIN
+}
+
+/// Samples a block at the given height on top of `previous_hash`.
+///
+/// The block reuses the genesis transactions and is only signed, so it passes deserialization but
+/// would not be accepted by a ledger.
+pub(crate) fn sample_block<R: Rng + CryptoRng>(
+    height: u32,
+    previous_hash: <CurrentNetwork as Network>::BlockHash,
+    rng: &mut R,
+) -> Block<CurrentNetwork> {
+    if height == 0 {
+        return genesis_block();
+    }
+    sample_block_with_transactions(height, previous_hash, GENESIS_BLOCK.transactions().clone(), rng)
+}
+
+/// Samples a block at the given height on top of `previous_hash` that contains the given transactions.
+///
+/// The header commits to the transactions, so blocks at the same height and on the same parent have different
+/// hashes unless they hold the same transactions, as on a chain whose tip is replaced. The other roots of the
+/// header are taken from the genesis block.
+pub(crate) fn sample_block_with_transactions<R: Rng + CryptoRng>(
+    height: u32,
+    previous_hash: <CurrentNetwork as Network>::BlockHash,
+    transactions: Transactions<CurrentNetwork>,
+    rng: &mut R,
+) -> Block<CurrentNetwork> {
+    let timestamp = CurrentNetwork::GENESIS_TIMESTAMP + height as i64;
+    sample_block_at(height, previous_hash, transactions, timestamp, rng)
+}
+
+/// Samples a block at the given height on top of `previous_hash` that contains the given transactions, with the
+/// given timestamp.
+pub(crate) fn sample_block_at<R: Rng + CryptoRng>(
+    height: u32,
+    previous_hash: <CurrentNetwork as Network>::BlockHash,
+    transactions: Transactions<CurrentNetwork>,
+    timestamp: i64,
+    rng: &mut R,
+) -> Block<CurrentNetwork> {
+    let genesis = &*GENESIS_BLOCK;
+    let metadata = Metadata::new(
+        CurrentNetwork::ID,
+        height as u64,
+        height,
+        CurrentNetwork::GENESIS_COINBASE_TARGET,
+        CurrentNetwork::GENESIS_PROOF_TARGET,
+        CurrentNetwork::GENESIS_COINBASE_TARGET,
+        CurrentNetwork::GENESIS_TIMESTAMP,
+        timestamp,
+    )
+    .unwrap();
+    let previous_state_root = genesis.header().transactions_root();
+    let header = Header::from(previous_state_root, transactions.to_root().unwrap(), Field::zero(), metadata).unwrap();
+    let private_key = PrivateKey::new(rng).unwrap();
+    Block::new(&private_key, previous_hash, header, transactions, None, rng).unwrap()
+}
+
+/// An HTTP request received by a [`MockServer`]
+pub(crate) struct MockRequest {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl MockRequest {
+    /// Returns the value of the header with the given name, if the request has one.
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+}
+
+/// An HTTP response sent by a [`MockServer`]
+pub(crate) struct MockResponse {
+    status: u16,
+    content_type: String,
+    body: String,
+    content_length: bool,
+    headers: Vec<(String, String)>,
+}
+
+impl MockResponse {
+    /// A `200 OK` response with a JSON body.
+    pub(crate) fn json(body: impl ToString) -> Self {
+        let body = body.to_string();
+        Self { status: 200, content_type: "application/json".to_string(), body, content_length: true, headers: vec![] }
+    }
+
+    /// A `200 OK` response with a JSON body and no `Content-Length`, so that the body ends when the connection
+    /// is closed.
+    pub(crate) fn streamed_json(body: impl ToString) -> Self {
+        Self { content_length: false, ..Self::json(body) }
+    }
+
+    /// A response with the given status and a plain text body, as sent by nodes to reject a request.
+    pub(crate) fn text(status: u16, body: impl ToString) -> Self {
+        Self {
+            status,
+            content_type: "text/plain".to_string(),
+            body: body.to_string(),
+            content_length: true,
+            headers: vec![],
+        }
+    }
+
+    /// A response with the given status and an HTML body, as sent by gateways in front of a node.
+    pub(crate) fn html(status: u16, body: impl ToString) -> Self {
+        Self {
+            status,
+            content_type: "text/html".to_string(),
+            body: body.to_string(),
+            content_length: true,
+            headers: vec![],
+        }
+    }
+
+    /// A `304 Not Modified` response without a body, as sent to a request whose validators are current.
+    pub(crate) fn not_modified() -> Self {
+        Self { status: 304, ..Self::json("") }
+    }
+
+    /// Adds a header to the response.
+    pub(crate) fn with_header(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A minimal HTTP server standing in for an Aleo node in tests.
+///
+/// Each request is passed to the handler, which returns the response to send, or `None` to respond
+/// with `404 Not Found`.
+pub(crate) struct MockServer {
+    base_url: String,
+}
+
+impl MockServer {
+    /// Starts a server on a random local port. The server runs until the test process exits.
+    pub(crate) fn start(handler: impl Fn(&MockRequest) -> Option<MockResponse> + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                Self::respond(stream, &handler);
+            }
+        });
+        Self { base_url }
+    }
+
+    /// Starts a server answering requests from the interactions of the cassette, as
+    /// [`crate::CassettePlayer`] does, so that recorded traffic serves as the fixture of a test.
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn replay(cassette: crate::Cassette) -> Self {
+        let player = crate::CassettePlayer::new(cassette);
+        Self::start(move |request| {
+            let interaction = player.next_interaction(&request.method, &request.path)?;
+            let content_type = interaction.headers.iter().find(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+            let content_type = content_type.map_or("application/json".to_string(), |(_, value)| value.clone());
+            let headers =
+                interaction.headers.into_iter().filter(|(name, _)| !name.eq_ignore_ascii_case("content-type"));
+            Some(MockResponse {
+                status: interaction.status,
+                content_type,
+                body: interaction.body,
+                content_length: true,
+                headers: headers.collect(),
+            })
+        })
+    }
+
+    /// Returns the URL to point an API client at.
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn respond(mut stream: TcpStream, handler: &impl Fn(&MockRequest) -> Option<MockResponse>) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        // Parse the request line and headers.
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let method = request_line.split_whitespace().next().unwrap_or_default().to_string();
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        let request = MockRequest { method, path, headers };
+        // Drain the request body.
+        let content_length = request.header("Content-Length").and_then(|length| length.parse().ok()).unwrap_or(0);
+        reader.read_exact(&mut vec![0u8; content_length]).unwrap();
+
+        let not_found = || MockResponse::text(404, "");
+        let MockResponse { status, content_type, body, content_length, headers: extra_headers } =
+            handler(&request).unwrap_or_else(not_found);
+        let mut headers = format!("Content-Type: {content_type}\r\nConnection: close");
+        if content_length {
+            headers.push_str(&format!("\r\nContent-Length: {}", body.len()));
+        }
+        for (name, value) in extra_headers {
+            headers.push_str(&format!("\r\n{name}: {value}"));
+        }
+        let _ = write!(stream, "HTTP/1.1 {status} Mock\r\n{headers}\r\n\r\n{body}");
+    }
+}
+
+/// A minimal SOCKS5 proxy standing in for a Tor daemon in tests.
+///
+/// Every connection the proxy is asked for is made to the upstream address instead, so that host names only the
+/// proxy knows, such as onion services, are reachable through it. The requested hosts and ports are recorded.
+#[cfg(feature = "socks")]
+pub(crate) struct MockSocksProxy {
+    address: String,
+    targets: Arc<Mutex<Vec<(String, u16)>>>,
+}
+
+#[cfg(feature = "socks")]
+impl MockSocksProxy {
+    /// Starts a proxy on a random local port, relaying to the given `host:port` address. The proxy runs until
+    /// the test process exits.
+    pub(crate) fn start(upstream: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let (recorded, upstream) = (targets.clone(), upstream.to_string());
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (recorded, upstream) = (recorded.clone(), upstream.clone());
+                thread::spawn(move || Self::relay(stream, &upstream, &recorded));
+            }
+        });
+        Self { address, targets }
+    }
+
+    /// Returns the `host:port` address to point a client at.
+    pub(crate) fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Returns the hosts and ports the proxy was asked to connect to, in order.
+    pub(crate) fn targets(&self) -> Vec<(String, u16)> {
+        self.targets.lock().unwrap().clone()
+    }
+
+    fn relay(mut client: TcpStream, upstream: &str, targets: &Mutex<Vec<(String, u16)>>) -> std::io::Result<()> {
+        // Accept the client without authentication.
+        let mut header = [0u8; 2];
+        client.read_exact(&mut header)?;
+        client.read_exact(&mut vec![0u8; header[1] as usize])?;
+        client.write_all(&[5, 0])?;
+
+        // Read the CONNECT request, with the host as an IPv4 address, a domain name, or an IPv6 address.
+        let mut request = [0u8; 4];
+        client.read_exact(&mut request)?;
+        let host = match request[3] {
+            1 => {
+                let mut octets = [0u8; 4];
+                client.read_exact(&mut octets)?;
+                std::net::Ipv4Addr::from(octets).to_string()
+            }
+            3 => {
+                let mut length = [0u8; 1];
+                client.read_exact(&mut length)?;
+                let mut name = vec![0u8; length[0] as usize];
+                client.read_exact(&mut name)?;
+                String::from_utf8_lossy(&name).into_owned()
+            }
+            _ => {
+                let mut octets = [0u8; 16];
+                client.read_exact(&mut octets)?;
+                std::net::Ipv6Addr::from(octets).to_string()
+            }
+        };
+        let mut port = [0u8; 2];
+        client.read_exact(&mut port)?;
+        targets.lock().unwrap().push((host, u16::from_be_bytes(port)));
+
+        // Connect to the upstream address, and relay both directions until either side closes.
+        let mut upstream = TcpStream::connect(upstream)?;
+        client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])?;
+        let (mut client_reader, mut upstream_writer) = (client.try_clone()?, upstream.try_clone()?);
+        thread::spawn(move || std::io::copy(&mut client_reader, &mut upstream_writer));
+        std::io::copy(&mut upstream, &mut client)?;
+        client.shutdown(std::net::Shutdown::Both)
+    }
+}
+
+/// Samples a `credits` record owned by the given address, returning the plaintext and its ciphertext.
+pub(crate) fn sample_record<R: Rng + CryptoRng>(
+    owner: Address<CurrentNetwork>,
+    gates: u64,
+    rng: &mut R,
+) -> (Record<CurrentNetwork, Plaintext<CurrentNetwork>>, Record<CurrentNetwork, Ciphertext<CurrentNetwork>>) {
+    let randomizer = Scalar::rand(rng);
+    let nonce = CurrentNetwork::g_scalar_multiply(&randomizer);
+    let plaintext = Record::<CurrentNetwork, Plaintext<CurrentNetwork>>::from_plaintext(
+        Owner::Private(Plaintext::from(Literal::Address(owner))),
+        Balance::Private(Plaintext::from(Literal::U64(U64::new(gates)))),
+        Default::default(),
+        nonce,
+    )
+    .unwrap();
+    let ciphertext = plaintext.encrypt(randomizer).unwrap();
+    (plaintext, ciphertext)
+}
+
+/// Samples a `credits` record owned by the given address, returning its commitment and ciphertext.
+pub(crate) fn sample_output<R: Rng + CryptoRng>(
+    owner: Address<CurrentNetwork>,
+    gates: u64,
+    rng: &mut R,
+) -> OutputRecord {
+    let (plaintext, ciphertext) = sample_record(owner, gates, rng);
+    let program_id = ProgramID::from_str("credits.aleo").unwrap();
+    let commitment = plaintext.to_commitment(&program_id, &Identifier::from_str("credits").unwrap()).unwrap();
+    (commitment, ciphertext)
+}
+
+/// Samples a `credits.aleo/transfer` transition spending the given serial numbers and creating the given records.
+///
+/// The proof is taken from the genesis block, so the transition does not verify.
+pub(crate) fn sample_transition<R: Rng + CryptoRng>(
+    serial_numbers: &[Field<CurrentNetwork>],
+    records: &[OutputRecord],
+    rng: &mut R,
+) -> Transition<CurrentNetwork> {
+    let genesis = genesis_block();
+    let template = genesis.transitions().next().unwrap();
+    let inputs = serial_numbers.iter().map(|serial_number| Input::Record(*serial_number, Field::rand(rng)));
+    let inputs = inputs.collect::<Vec<_>>();
+    let outputs = records.iter().map(|(commitment, record)| {
+        Output::Record(*commitment, Field::rand(rng), Some(record.clone()))
+    });
+    Transition::new(
+        ProgramID::from_str("credits.aleo").unwrap(),
+        Identifier::from_str("transfer").unwrap(),
+        inputs,
+        outputs.collect(),
+        None,
+        template.proof().clone(),
+        *template.tpk(),
+        *template.tcm(),
+        0,
+    )
+    .unwrap()
+}
+
+/// Wraps the given transitions in an execution transaction without a fee.
+pub(crate) fn sample_transaction(
+    transitions: impl IntoIterator<Item = Transition<CurrentNetwork>>,
+) -> Transaction<CurrentNetwork> {
+    let execution = Execution::from(transitions.into_iter(), Default::default(), None).unwrap();
+    Transaction::from_execution(execution, None).unwrap()
+}
+
+/// Samples a block at the given height on top of `previous_hash`, with an execution transaction that pays a fee.
+pub(crate) fn sample_block_with_fee<R: Rng + CryptoRng>(
+    height: u32,
+    previous_hash: <CurrentNetwork as Network>::BlockHash,
+    rng: &mut R,
+) -> Block<CurrentNetwork> {
+    let execution = Execution::from([sample_transition(&[], &[], rng)].into_iter(), Default::default(), None).unwrap();
+    // Fees without an inclusion proof do not deserialize, so the proof of the genesis block is reused.
+    let proof = GENESIS_BLOCK.transitions().next().unwrap().proof().clone();
+    let fee = Fee::from(sample_transition(&[], &[], rng), Default::default(), Some(proof));
+    let transaction = Transaction::from_execution(execution, Some(fee)).unwrap();
+    sample_block_with_transactions(height, previous_hash, Transactions::from(&[transaction]), rng)
+}
+
+/// Starts a mock node serving a chain of `length` blocks, in which each block after genesis creates one record
+/// and spends another, serving the `find` routes only if asked. Returns the commitments and serial numbers by
+/// height, starting at height 1, and the paths of the requests.
+pub(crate) fn mock_find_server(length: u32, find_routes: bool) -> (MockServer, RecordIds, PathLog) {
+    let rng = &mut TestRng::default();
+    let owner = Address::try_from(PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+    let (mut blocks, mut ids, mut routes) = (vec![genesis_block()], vec![], vec![]);
+    for height in 1..length {
+        let (serial_number, output) = (Field::rand(rng), sample_output(owner, 100, rng));
+        let transition = sample_transition(&[serial_number], std::slice::from_ref(&output), rng);
+        let transaction = sample_transaction([transition.clone()]);
+        let previous_hash = blocks.last().unwrap().hash();
+        let transactions = [transaction.clone()].into_iter().collect();
+        let block = sample_block_with_transactions(height, previous_hash, transactions, rng);
+        for id in [serial_number, output.0] {
+            routes.push((format!("/testnet3/find/transitionID/{id}"), transition.id().to_string()));
+        }
+        routes.push((format!("/testnet3/find/transactionID/{}", transition.id()), transaction.id().to_string()));
+        routes.push((format!("/testnet3/find/blockHash/{}", transaction.id()), block.hash().to_string()));
+        routes.push((format!("/testnet3/block/{}", block.hash()), block.to_string()));
+        ids.push((output.0, serial_number));
+        blocks.push(block);
+    }
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let server = MockServer::start(move |request| {
+        recorded.lock().unwrap().push(request.path.clone());
+        if request.path == "/testnet3/latest/height" {
+            return Some(MockResponse::json(blocks.len() - 1));
+        }
+        if let Some(query) = request.path.strip_prefix("/testnet3/blocks?start=") {
+            let (start, end) = query.split_once("&end=")?;
+            let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+            let blocks = blocks.get(start..end.min(blocks.len()))?.iter().map(ToString::to_string);
+            return Some(MockResponse::json(format!("[{}]", blocks.collect::<Vec<_>>().join(","))));
+        }
+        if !find_routes && request.path.starts_with("/testnet3/find/") {
+            return Some(MockResponse::text(404, "Not Found"));
+        }
+        match routes.iter().find(|(path, _)| *path == request.path) {
+            Some((_, body)) if body.starts_with('{') => Some(MockResponse::json(body)),
+            Some((_, body)) => Some(MockResponse::json(format!("\"{body}\""))),
+            None => Some(MockResponse::text(404, "Not Found")),
+        }
+    });
+    (server, ids, requests)
+}
+
+/// Samples a prover solution of a random address, with random group elements in place of a valid proof.
+pub(crate) fn sample_prover_solution<R: Rng + CryptoRng>(rng: &mut R) -> ProverSolution<CurrentNetwork> {
+    let address = Address::try_from(PrivateKey::new(rng).unwrap()).unwrap();
+    let proof = PuzzleProof::<CurrentNetwork> { w: rng.gen(), random_v: None };
+    let commitment = PuzzleCommitment::from_bytes_le(&proof.w.to_bytes_le().unwrap()).unwrap();
+    ProverSolution::new(PartialSolution::new(address, rng.gen(), commitment), proof)
+}
+
+/// Serializes a block as newer nodes serve it, with the `solutions` of the block and the `fee` of its
+/// transactions renamed, and with fields this SDK does not read.
+pub(crate) fn newer_node_json(block: &Block<CurrentNetwork>) -> serde_json::Value {
+    let mut value = serde_json::to_value(block).unwrap();
+    let object = value.as_object_mut().unwrap();
+    if let Some(coinbase) = object.remove("coinbase") {
+        object.insert("solutions".to_string(), coinbase);
+    }
+    object.insert("ratifications".to_string(), serde_json::Value::Array(vec![]));
+    object.insert("aborted_transaction_ids".to_string(), serde_json::Value::Array(vec![]));
+    for transaction in object["transactions"].as_array_mut().unwrap() {
+        let transaction = transaction.as_object_mut().unwrap();
+        if let Some(fee) = transaction.remove("additional_fee") {
+            transaction.insert("fee".to_string(), fee);
+        }
+    }
+    value
+}
+
+/// An allocator counting the bytes held by each thread, to measure the peak memory use of code under test
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+impl CountingAllocator {
+    fn record(delta: isize) {
+        // The counters are unavailable while the thread is torn down.
+        let _ = ALLOCATED.try_with(|allocated| {
+            allocated.set(allocated.get() + delta);
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(allocated.get())));
+        });
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::record(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::record(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Runs `f`, returning its result with the peak number of bytes it held at once on the current thread.
+pub(crate) fn peak_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let start = ALLOCATED.with(Cell::get);
+    PEAK.with(|peak| peak.set(start));
+    let result = f();
+    (result, (PEAK.with(Cell::get) - start) as usize)
+}
+
+/// Returns a path in the temporary directory that is unique to the test of the given name and to the process.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("aleo-{name}-{}", std::process::id()))
+}
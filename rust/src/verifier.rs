@@ -0,0 +1,519 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Verification of transactions and record disclosures produced elsewhere, without any signing capability.
+//!
+//! Each verification runs a list of named checks and returns a [`VerificationReport`] of their outcomes, so that
+//! an audit shows which checks passed, rather than a single verdict. Checks that cannot run because an earlier
+//! check failed are reported as failed, with the reason they were skipped.
+
+use crate::{program::synthesize_key, RecordDisclosure};
+
+use snarkvm_console::{
+    account::Address,
+    prelude::ToBits,
+    program::{Ciphertext, Identifier, Network, ProgramID, Record, ValueType},
+    types::U16,
+};
+use snarkvm_synthesizer::{Input, Output, Process, Program, Transaction, Transition, VerifyingKey};
+
+use anyhow::{anyhow, ensure, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// The outcome of one named check of a verification
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckResult {
+    name: String,
+    failure: Option<String>,
+}
+
+impl CheckResult {
+    /// A check that passed
+    pub fn passed(name: impl Into<String>) -> Self {
+        Self { name: name.into(), failure: None }
+    }
+
+    /// A check that failed for the given reason
+    pub fn failed(name: impl Into<String>, reason: impl fmt::Display) -> Self {
+        Self { name: name.into(), failure: Some(reason.to_string()) }
+    }
+
+    /// Returns the name of the check.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns `true` if the check passed.
+    pub fn is_passed(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// Returns the reason the check failed, if it did.
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.failure {
+            None => write!(f, "[pass] {}", self.name),
+            Some(reason) => write!(f, "[fail] {}: {reason}", self.name),
+        }
+    }
+}
+
+/// The outcomes of the checks of a verification, in the order they ran
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    checks: Vec<CheckResult>,
+}
+
+impl VerificationReport {
+    /// Returns the outcomes of the checks, in the order they ran.
+    pub fn checks(&self) -> &[CheckResult] {
+        &self.checks
+    }
+
+    /// Returns the outcome of the check with the given name, if it ran.
+    pub fn check(&self, name: &str) -> Option<&CheckResult> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+
+    /// Returns the checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks.iter().filter(|check| !check.is_passed())
+    }
+
+    /// Returns `true` if every check passed.
+    pub fn is_valid(&self) -> bool {
+        self.checks.iter().all(CheckResult::is_passed)
+    }
+
+    // Record the outcome of a check, passing if `passed` is true
+    fn ensure(&mut self, name: impl Into<String>, passed: bool, reason: impl fmt::Display) -> bool {
+        self.checks.push(match passed {
+            true => CheckResult::passed(name),
+            false => CheckResult::failed(name, reason),
+        });
+        passed
+    }
+
+    // Record the outcome of a check that fails with an error
+    fn record(&mut self, name: impl Into<String>, result: Result<()>) -> bool {
+        match result {
+            Ok(()) => self.ensure(name, true, ""),
+            Err(error) => self.ensure(name, false, error),
+        }
+    }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{check}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Verify that the IDs of a transaction are consistent with its contents, and that its transitions are well-formed.
+///
+/// The transaction ID is recomputed from its transitions, or from its deployment and fee, and each transition is
+/// checked against its ID, and its inputs and outputs against the hashes and commitments of their values. No proof
+/// is verified, see [`verify_execution_proofs`].
+pub fn verify_transaction_structure<N: Network>(transaction: &Transaction<N>) -> VerificationReport {
+    let mut report = VerificationReport::default();
+    let recomputed = match transaction {
+        Transaction::Deploy(_, deployment, fee) => {
+            report.record("deployment", deployment.check_is_ordered());
+            Transaction::from_deployment((**deployment).clone(), fee.clone())
+        }
+        Transaction::Execute(_, execution, fee) => Transaction::from_execution(execution.clone(), fee.clone()),
+    };
+    match recomputed {
+        Ok(recomputed) => report.ensure(
+            "transaction ID",
+            recomputed.id() == transaction.id(),
+            format!("the contents of transaction '{}' hash to '{}'", transaction.id(), recomputed.id()),
+        ),
+        Err(error) => report.ensure("transaction ID", false, error),
+    };
+    for (index, transition) in transaction.transitions().enumerate() {
+        check_transition(&mut report, &format!("transition {index}"), transition);
+    }
+    report
+}
+
+// Check the ID, inputs, outputs, and fee of a transition
+fn check_transition<N: Network>(report: &mut VerificationReport, name: &str, transition: &Transition<N>) {
+    let id = transition.id();
+    match transition.to_root() {
+        Ok(root) => report.ensure(format!("{name} ID"), root == **id, format!("the transition hashes to '{root}'")),
+        Err(error) => report.ensure(format!("{name} ID"), false, error),
+    };
+    let function_id = N::hash_bhp1024(
+        &(
+            U16::<N>::new(N::ID),
+            transition.program_id().name(),
+            transition.program_id().network(),
+            transition.function_name(),
+        )
+            .to_bits_le(),
+    );
+    let function_id = match function_id {
+        Ok(function_id) => function_id,
+        Err(error) => {
+            report.ensure(format!("{name} inputs"), false, &error);
+            report.ensure(format!("{name} outputs"), false, error);
+            return;
+        }
+    };
+    let (tcm, offset) = (transition.tcm(), transition.inputs().len());
+    let mut inputs = transition.inputs().iter().enumerate();
+    let invalid = inputs.position(|(index, input)| !input.verify(function_id, tcm, index));
+    let reason = format!("input {} has an invalid ID", invalid.unwrap_or_default());
+    report.ensure(format!("{name} inputs"), invalid.is_none(), reason);
+    let mut outputs = transition.outputs().iter().enumerate();
+    let invalid = outputs.position(|(index, output)| !output.verify(function_id, tcm, offset + index));
+    let reason = format!("output {} has an invalid ID", invalid.unwrap_or_default());
+    report.ensure(format!("{name} outputs"), invalid.is_none(), reason);
+    let fee_is_valid = match Program::is_coinbase(transition.program_id(), transition.function_name()) {
+        true => *transition.fee() < 0,
+        false => *transition.fee() >= 0,
+    };
+    report.ensure(format!("{name} fee"), fee_is_valid, format!("the fee of {} is out of range", transition.fee()));
+}
+
+/// Verify the proofs of an execution against the given programs, synthesizing their verifying keys locally.
+///
+/// See [`verify_execution_proofs_with_keys`].
+pub fn verify_execution_proofs<N: Network>(
+    transaction: &Transaction<N>,
+    programs: &IndexMap<ProgramID<N>, Program<N>>,
+) -> Result<VerificationReport> {
+    verify_execution_proofs_with_keys(transaction, programs, &IndexMap::new())
+}
+
+/// Verify the proofs of an execution against the given programs, with the given verifying keys.
+///
+/// Each transition is first checked against the signature of its function in the given programs, so that a
+/// transaction produced with another version of a program fails a named check, rather than its proof. The
+/// programs must be ordered so that each program follows its imports, and `credits.aleo` is built in. Functions
+/// without a given verifying key have theirs synthesized, which takes a while per function. Fails if the process
+/// that verifies the proofs cannot be loaded.
+pub fn verify_execution_proofs_with_keys<N: Network>(
+    transaction: &Transaction<N>,
+    programs: &IndexMap<ProgramID<N>, Program<N>>,
+    verifying_keys: &IndexMap<(ProgramID<N>, Identifier<N>), VerifyingKey<N>>,
+) -> Result<VerificationReport> {
+    let mut report = VerificationReport::default();
+    let Transaction::Execute(_, execution, fee) = transaction else {
+        report.ensure("execution", false, format!("transaction '{}' is a deployment", transaction.id()));
+        return Ok(report);
+    };
+    let signatures_match = check_signatures(&mut report, transaction, programs);
+    if !signatures_match {
+        report.ensure("execution proof", false, "skipped, as the transaction does not match the programs");
+        return Ok(report);
+    }
+
+    let mut process = Process::<N>::load()?;
+    for program in programs.values().filter(|program| !is_credits(program.id())) {
+        process.add_program(program)?;
+    }
+    for transition in transaction.transitions() {
+        let (program_id, function_name) = (transition.program_id(), transition.function_name());
+        if process.get_stack(program_id)?.contains_verifying_key(function_name) {
+            continue;
+        }
+        match verifying_keys.get(&(*program_id, *function_name)) {
+            Some(verifying_key) => process.insert_verifying_key(program_id, function_name, verifying_key.clone())?,
+            None => synthesize_key(&process, program_id, function_name)?,
+        }
+    }
+    report.record("execution proof", process.verify_execution::<true>(execution));
+    if let Some(fee) = fee {
+        report.record("fee proof", process.verify_fee(fee));
+    }
+    Ok(report)
+}
+
+// Check each transition against the signature of its function in the given programs, returning `true` if all match
+fn check_signatures<N: Network>(
+    report: &mut VerificationReport,
+    transaction: &Transaction<N>,
+    programs: &IndexMap<ProgramID<N>, Program<N>>,
+) -> bool {
+    let credits = Program::credits().ok();
+    let mut all_match = true;
+    for (index, transition) in transaction.transitions().enumerate() {
+        let (program_id, function_name) = (transition.program_id(), transition.function_name());
+        let name = format!("transition {index} program");
+        let program = match (programs.get(program_id), &credits) {
+            (Some(program), _) => program,
+            (None, Some(credits)) if is_credits(program_id) => credits,
+            (None, _) => {
+                all_match &= report.ensure(name, false, format!("program '{program_id}' was not supplied"));
+                continue;
+            }
+        };
+        let result = match program.get_function(function_name) {
+            Ok(function) => {
+                let inputs = function.inputs().iter().map(|input| input.value_type());
+                let outputs = function.outputs().iter().map(|output| output.value_type());
+                check_signature(transition, inputs.collect(), outputs.collect())
+            }
+            Err(_) => Err(anyhow!("program '{program_id}' has no function '{function_name}'")),
+        };
+        let result = result.map_err(|error| anyhow!("the transition does not match '{program_id}': {error}"));
+        all_match &= report.record(name, result);
+    }
+    all_match
+}
+
+// Check the inputs and outputs of a transition against the value types of its function
+fn check_signature<N: Network>(
+    transition: &Transition<N>,
+    inputs: Vec<&ValueType<N>>,
+    outputs: Vec<&ValueType<N>>,
+) -> Result<()> {
+    let (function_name, found, expected) = (transition.function_name(), transition.inputs().len(), inputs.len());
+    ensure!(found == expected, "'{function_name}' takes {expected} inputs, but the transition has {found}");
+    let (found, expected) = (transition.outputs().len(), outputs.len());
+    ensure!(found == expected, "'{function_name}' has {expected} outputs, but the transition has {found}");
+    for (index, (input, value_type)) in transition.inputs().iter().zip(inputs).enumerate() {
+        let matches = matches!(
+            (input, value_type),
+            (Input::Constant(..), ValueType::Constant(..))
+                | (Input::Public(..), ValueType::Public(..))
+                | (Input::Private(..), ValueType::Private(..))
+                | (Input::Record(..), ValueType::Record(..))
+                | (Input::ExternalRecord(..), ValueType::ExternalRecord(..))
+        );
+        ensure!(matches, "input {index} of '{function_name}' is not {value_type}");
+    }
+    for (index, (output, value_type)) in transition.outputs().iter().zip(outputs).enumerate() {
+        let matches = matches!(
+            (output, value_type),
+            (Output::Constant(..), ValueType::Constant(..))
+                | (Output::Public(..), ValueType::Public(..))
+                | (Output::Private(..), ValueType::Private(..))
+                | (Output::Record(..), ValueType::Record(..))
+                | (Output::ExternalRecord(..), ValueType::ExternalRecord(..))
+        );
+        ensure!(matches, "output {index} of '{function_name}' is not {value_type}");
+    }
+    Ok(())
+}
+
+// Returns `true` if the program ID is `credits.aleo`, which every process holds
+fn is_credits<N: Network>(program_id: &ProgramID<N>) -> bool {
+    ProgramID::from_str("credits.aleo").map_or(false, |credits| *program_id == credits)
+}
+
+/// Verify that a disclosed record is the plaintext of the given ciphertext, and is owned by the claimed address.
+///
+/// The record view key of the disclosure proves that its nonce opens the ciphertext: the disclosed record must
+/// have the nonce of the ciphertext, match the disclosed commitment, and encrypt to the ciphertext under the record
+/// view key. Unlike [`RecordDisclosure::verify`], the chain is not queried.
+pub fn verify_record_ownership<N: Network>(
+    ciphertext: &Record<N, Ciphertext<N>>,
+    claimed_owner: &Address<N>,
+    disclosure: &RecordDisclosure<N>,
+) -> VerificationReport {
+    let mut report = VerificationReport::default();
+    let record = disclosure.record();
+    report.ensure(
+        "nonce",
+        *record.nonce() == disclosure.nonce() && ciphertext.nonce() == record.nonce(),
+        "the nonce of the disclosed record does not match the ciphertext",
+    );
+    let commitment = record.to_commitment(disclosure.program_id(), disclosure.record_name());
+    report.ensure(
+        "commitment",
+        commitment.as_ref().map_or(false, |commitment| *commitment == disclosure.commitment()),
+        format!("the disclosed record does not match commitment '{}'", disclosure.commitment()),
+    );
+    let encrypted = record.encrypt_symmetric(&disclosure.record_view_key());
+    report.ensure(
+        "encryption",
+        encrypted.map_or(false, |encrypted| encrypted == *ciphertext),
+        "the disclosed record does not encrypt to the ciphertext under the record view key",
+    );
+    let owner = **record.owner();
+    report.ensure("owner", owner == *claimed_owner, format!("the record is owned by '{owner}'"));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{genesis_block, sample_record, CurrentNetwork};
+
+    use snarkvm_console::{
+        account::{PrivateKey, ViewKey},
+        prelude::Uniform,
+        program::Plaintext,
+        types::Field,
+    };
+    use snarkvm_synthesizer::Execution;
+    use snarkvm_utilities::TestRng;
+
+    type N = CurrentNetwork;
+
+    // Returns the genesis transaction, a valid execution of `credits.aleo/mint`
+    fn genesis_transaction() -> Transaction<N> {
+        genesis_block().transactions().iter().next().unwrap().clone()
+    }
+
+    // Rebuild the transaction with the genesis transition changed by `f`, keeping the original transaction ID
+    fn tamper(f: impl FnOnce(&Transition<N>) -> (ProgramID<N>, Vec<Output<N>>)) -> Transaction<N> {
+        let Transaction::Execute(id, execution, fee) = genesis_transaction() else { unreachable!() };
+        let transition = execution.transitions().next().unwrap();
+        let (program_id, outputs) = f(transition);
+        let tampered = Transition::new(
+            program_id,
+            *transition.function_name(),
+            transition.inputs().to_vec(),
+            outputs,
+            transition.finalize().cloned(),
+            transition.proof().clone(),
+            *transition.tpk(),
+            *transition.tcm(),
+            *transition.fee(),
+        )
+        .unwrap();
+        let (state_root, inclusion_proof) = (execution.global_state_root(), execution.inclusion_proof().cloned());
+        Transaction::Execute(id, Execution::from([tampered].into_iter(), state_root, inclusion_proof).unwrap(), fee)
+    }
+
+    // Returns the names of the checks that failed
+    fn failures(report: &VerificationReport) -> Vec<&str> {
+        report.failures().map(CheckResult::name).collect()
+    }
+
+    #[test]
+    fn test_verify_valid_transaction() {
+        let transaction = genesis_transaction();
+        let report = verify_transaction_structure(&transaction);
+        assert!(report.is_valid(), "{report}");
+        for name in ["transaction ID", "transition 0 ID", "transition 0 inputs", "transition 0 outputs"] {
+            assert!(report.check(name).unwrap().is_passed());
+        }
+
+        // The genesis transition matches the built-in `credits.aleo`.
+        let mut report = VerificationReport::default();
+        assert!(check_signatures(&mut report, &transaction, &IndexMap::new()));
+        assert_eq!(report.checks(), [CheckResult::passed("transition 0 program")]);
+    }
+
+    #[test]
+    fn test_verify_tampered_output_id() {
+        let transaction = tamper(|transition| {
+            let outputs = transition.outputs().iter().map(|output| match output {
+                Output::Record(_, checksum, record) => {
+                    Output::Record(Field::rand(&mut TestRng::default()), *checksum, record.clone())
+                }
+                output => output.clone(),
+            });
+            (*transition.program_id(), outputs.collect())
+        });
+
+        // The transition is consistent with its new ID, which no longer hashes to the ID of the transaction.
+        let report = verify_transaction_structure(&transaction);
+        assert_eq!(failures(&report), ["transaction ID"]);
+        let failure = report.check("transaction ID").unwrap().failure().unwrap();
+        assert!(failure.starts_with(&format!("the contents of transaction '{}' hash to", transaction.id())));
+    }
+
+    #[test]
+    fn test_verify_program_version_mismatch() {
+        // A `credits.aleo` whose `mint` function takes one input instead of two.
+        let credits = Program::<N>::from_str(
+            r"program credits.aleo;
+
+record credits:
+    owner as address.private;
+    gates as u64.private;
+
+function mint:
+    input r0 as address.public;
+    cast r0 0u64 into r1 as credits.record;
+    output r1 as credits.record;
+",
+        )
+        .unwrap();
+        let programs = [(*credits.id(), credits)].into_iter().collect();
+        let report = verify_execution_proofs(&genesis_transaction(), &programs).unwrap();
+        assert_eq!(failures(&report), ["transition 0 program", "execution proof"]);
+        let failure = report.check("transition 0 program").unwrap().failure().unwrap();
+        assert!(failure.contains("'mint' takes 1 inputs, but the transition has 2"), "{failure}");
+
+        // Transitions of programs that were not supplied fail the same check.
+        let token = ProgramID::from_str("token.aleo").unwrap();
+        let transaction = tamper(|transition| (token, transition.outputs().to_vec()));
+        let report = verify_execution_proofs(&transaction, &IndexMap::new()).unwrap();
+        assert_eq!(failures(&report), ["transition 0 program", "execution proof"]);
+        let failure = report.check("transition 0 program").unwrap().failure().unwrap();
+        assert_eq!(failure, "program 'token.aleo' was not supplied");
+    }
+
+    #[test]
+    fn test_verify_record_ownership() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let (view_key, owner) = (ViewKey::try_from(&private_key).unwrap(), Address::try_from(&private_key).unwrap());
+        let disclose = |plaintext: &Record<N, Plaintext<N>>, ciphertext: &Record<N, Ciphertext<N>>| {
+            let (program_id, record_name) = (ProgramID::from_str("credits.aleo").unwrap(), "credits");
+            let commitment = plaintext.to_commitment(&program_id, &Identifier::from_str(record_name).unwrap());
+            let disclosure = serde_json::json!({
+                "program_id": program_id.to_string(),
+                "record_name": record_name,
+                "record": plaintext.to_string(),
+                "record_view_key": (*ciphertext.nonce() * *view_key).to_x_coordinate().to_string(),
+                "nonce": plaintext.nonce().to_string(),
+                "commitment": commitment.unwrap().to_string(),
+                "transition_id": <N as Network>::TransitionID::default().to_string(),
+                "transaction_id": <N as Network>::TransactionID::default().to_string(),
+                "height": 42,
+            });
+            RecordDisclosure::<N>::from_str(&disclosure.to_string()).unwrap()
+        };
+        let (plaintext, ciphertext) = sample_record(owner, 100, rng);
+        let disclosure = disclose(&plaintext, &ciphertext);
+
+        let report = verify_record_ownership(&ciphertext, &owner, &disclosure);
+        assert!(report.is_valid(), "{report}");
+        assert_eq!(report.checks().iter().map(CheckResult::name).collect::<Vec<_>>(), [
+            "nonce",
+            "commitment",
+            "encryption",
+            "owner"
+        ]);
+
+        // Another owner is rejected.
+        let other = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        assert_eq!(failures(&verify_record_ownership(&ciphertext, &other, &disclosure)), ["owner"]);
+
+        // The disclosure does not open the ciphertext of another record.
+        let (_, other_ciphertext) = sample_record(owner, 100, rng);
+        let report = verify_record_ownership(&other_ciphertext, &owner, &disclosure);
+        assert_eq!(failures(&report), ["nonce", "encryption"]);
+    }
+}
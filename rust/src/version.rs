@@ -0,0 +1,62 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+/// The version of this crate
+pub const SDK_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The exact version of snarkVM this crate is built against
+///
+/// The build fails unless every snarkVM crate is pinned to this version, so that the types re-exported under
+/// [`crate::snarkvm`] are the ones of the API of this crate.
+pub const SNARKVM_VERSION: &str = env!("ALEO_SNARKVM_VERSION");
+
+/// The versions this crate is built with
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VersionInfo {
+    /// The version of this crate
+    pub sdk: &'static str,
+    /// The exact version of snarkVM the types of this crate come from
+    pub snarkvm: &'static str,
+}
+
+impl fmt::Display for VersionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "aleo-rust {} (snarkVM {})", self.sdk, self.snarkvm)
+    }
+}
+
+/// Returns the versions this crate is built with.
+pub fn version_info() -> VersionInfo {
+    VersionInfo { sdk: SDK_VERSION, snarkvm: SNARKVM_VERSION }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_info() {
+        let info = version_info();
+        assert_eq!((info.sdk, info.snarkvm), ("0.3.5", "0.9.13"));
+        assert_eq!(info.to_string(), "aleo-rust 0.3.5 (snarkVM 0.9.13)");
+
+        // Every snarkVM crate is pinned to the reported version.
+        let manifest = include_str!("../Cargo.toml");
+        assert_eq!(manifest.matches("version = \"=0.9.13\"").count(), 4);
+    }
+}
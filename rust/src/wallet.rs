@@ -0,0 +1,880 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+//! A high-level wallet for a single account.
+//!
+//! [`Wallet`] is a thin layer over the components of the library: the account keys, an [`AleoAPIClient`], a
+//! [`ProgramManager`] holding the unspent records in a [`RecordStore`], and a [`ScanState`]. Its state is kept
+//! in a profile file, which is a [`WalletSnapshot`] with the private key encrypted by a passphrase. An open
+//! wallet holds an exclusive [`StoreLock`] on its profile, so that another process opening the same profile
+//! fails with [`crate::StoreLocked`] instead of overwriting the changes of the first.
+//!
+//! A wallet of a [`WatchOnlyAccount`] holds the view key in place of the private key. It finds the records
+//! received by the account, but cannot send, nor tell when its records are spent until the private key is
+//! imported with [`Wallet::import_private_key`].
+//!
+//! A daemon syncing a wallet shuts it down by cancelling its [`Wallet::shutdown_token`], e.g. from a signal
+//! handler, so that a sync in progress stops at the next chunk of blocks and saves the blocks it synced, then
+//! by calling [`Wallet::shutdown`] to flush what is left.
+
+use crate::{
+    is_not_found,
+    AleoAPIClient,
+    BlockHeight,
+    CancellationToken,
+    Cancelled,
+    Flush,
+    FlushSummary,
+    HistoryEntry,
+    HistoryKind,
+    Microcredits,
+    PendingTransaction,
+    PolicyViolation,
+    ProgramManager,
+    RecordStore,
+    ScanState,
+    SigningUnavailable,
+    SpendingPolicy,
+    StoreLock,
+    WalletEvent,
+    WalletSnapshot,
+    WatchOnlyAccount,
+    WrongNetworkAddress,
+};
+
+use anyhow::anyhow;
+use snarkvm_console::{
+    account::{Address, PrivateKey, ViewKey},
+    program::{Network, Plaintext, Record},
+    types::Field,
+};
+use snarkvm_synthesizer::Block;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeBounds,
+    path::Path,
+    time::Duration,
+};
+use thiserror::Error;
+
+// The setting of a profile holding the chain it was created for
+const NETWORK_SETTING: &str = "network";
+
+/// An error returned by a [`Wallet`]
+#[derive(Debug, Error)]
+pub enum WalletError {
+    /// The profile could not be created, read, or written
+    #[error("Failed to access the wallet profile: {0}")]
+    Profile(anyhow::Error),
+    /// A query to the node failed
+    #[error("Failed to query the node: {0}")]
+    Network(anyhow::Error),
+    /// The block at the given height does not build on the last synced block
+    #[error("Block {height} does not build on the last synced block, as the chain was reorganized")]
+    Reorganized { height: u32 },
+    /// The unspent records of the wallet hold less than the amount and the fee together
+    #[error("The wallet holds {balance} gates, which cannot pay {amount} gates and a fee of {fee} gates")]
+    InsufficientFunds { amount: Microcredits, fee: Microcredits, balance: Microcredits },
+    /// The transaction could not be built or broadcast
+    #[error("Failed to send the transaction: {0}")]
+    Transaction(anyhow::Error),
+    /// The wallet is watch-only, so it cannot sign transactions
+    #[error("{0}")]
+    SigningUnavailable(SigningUnavailable),
+    /// The spending policy of the wallet refused the transaction
+    #[error("{0}")]
+    PolicyViolation(PolicyViolation),
+    /// The sync stopped because the wallet was shut down, after saving the blocks synced before
+    #[error("{0}")]
+    Cancelled(Cancelled<()>),
+}
+
+/// A wallet for a single account, kept in a profile file
+///
+/// The wallet syncs the records of its account from the node, tracks its balance and history, and sends
+/// `credits.aleo` transfers. Each underlying component is reachable through an accessor for uses beyond the
+/// wallet, e.g. [`Wallet::program_manager`] to execute other programs.
+pub struct Wallet<N: Network> {
+    // The lock held on the profile while the wallet is open, which also holds its path
+    profile_lock: StoreLock,
+    passphrase: String,
+    view_key: ViewKey<N>,
+    program_manager: ProgramManager<N>,
+    scan_state: ScanState<N>,
+    history: Vec<HistoryEntry<N>>,
+    settings: BTreeMap<String, String>,
+    fee: Microcredits,
+    // Whether the wallet holds changes that are not saved to its profile
+    dirty: bool,
+    shutdown: CancellationToken,
+}
+
+impl<N: Network> Wallet<N> {
+    /// The default fee in gates paid by transfers
+    pub const DEFAULT_FEE: Microcredits = Microcredits(1);
+
+    /// Create a wallet for a new account, and write its profile to the given path, encrypting the private key
+    /// with the passphrase.
+    ///
+    /// An existing profile is never overwritten.
+    pub fn create(
+        profile_path: impl AsRef<Path>,
+        passphrase: &str,
+        api_client: AleoAPIClient<N>,
+    ) -> Result<Self, WalletError> {
+        let profile_path = profile_path.as_ref();
+        let profile_lock = StoreLock::exclusive(profile_path, Duration::ZERO).map_err(WalletError::Profile)?;
+        if profile_path.exists() {
+            let error = anyhow!("A wallet profile already exists at '{}'", profile_path.display());
+            return Err(WalletError::Profile(error));
+        }
+        let private_key = PrivateKey::new(&mut rand::thread_rng()).map_err(WalletError::Profile)?;
+        let snapshot = WalletSnapshot::new(vec![private_key], RecordStore::new(), ScanState::new(BlockHeight::GENESIS))
+            .with_settings(BTreeMap::from([(NETWORK_SETTING.to_string(), api_client.chain().to_string())]));
+        let wallet = Self::from_snapshot(profile_lock, passphrase, snapshot, api_client)?;
+        wallet.save()?;
+        Ok(wallet)
+    }
+
+    /// Create a wallet for a watch-only account, and write its profile to the given path, encrypting the view key
+    /// with the passphrase.
+    ///
+    /// The wallet syncs from the given height, e.g. the height at which the account was created. An existing
+    /// profile is never overwritten.
+    pub fn create_watch_only(
+        profile_path: impl AsRef<Path>,
+        passphrase: &str,
+        account: WatchOnlyAccount<N>,
+        start_height: BlockHeight,
+        api_client: AleoAPIClient<N>,
+    ) -> Result<Self, WalletError> {
+        let profile_path = profile_path.as_ref();
+        let profile_lock = StoreLock::exclusive(profile_path, Duration::ZERO).map_err(WalletError::Profile)?;
+        if profile_path.exists() {
+            let error = anyhow!("A wallet profile already exists at '{}'", profile_path.display());
+            return Err(WalletError::Profile(error));
+        }
+        let snapshot = WalletSnapshot::new(vec![], RecordStore::new(), ScanState::new(start_height))
+            .with_watch_only(vec![*account.view_key()])
+            .with_settings(BTreeMap::from([(NETWORK_SETTING.to_string(), api_client.chain().to_string())]));
+        let wallet = Self::from_snapshot(profile_lock, passphrase, snapshot, api_client)?;
+        wallet.save()?;
+        Ok(wallet)
+    }
+
+    /// Open the wallet whose profile is at the given path, decrypting its private key with the passphrase.
+    ///
+    /// The profile stays locked until the wallet is dropped. Opening a profile another wallet holds open fails
+    /// with [`crate::StoreLocked`], naming the process of the other wallet. Profiles record the chain they were
+    /// created for, and opening one with a client of another chain fails with [`WrongNetworkAddress`].
+    pub fn open(
+        profile_path: impl AsRef<Path>,
+        passphrase: &str,
+        api_client: AleoAPIClient<N>,
+    ) -> Result<Self, WalletError> {
+        let profile_lock = StoreLock::exclusive(profile_path, Duration::ZERO).map_err(WalletError::Profile)?;
+        let snapshot = WalletSnapshot::read(profile_lock.path(), passphrase).map_err(WalletError::Profile)?;
+        Self::from_snapshot(profile_lock, passphrase, snapshot, api_client)
+    }
+
+    // Assemble a wallet from the account of a snapshot, which holds either a private key or a watch-only view key
+    fn from_snapshot(
+        profile_lock: StoreLock,
+        passphrase: &str,
+        snapshot: WalletSnapshot<N>,
+        api_client: AleoAPIClient<N>,
+    ) -> Result<Self, WalletError> {
+        // Profiles written before wallets recorded their chain are opened on any chain.
+        if let Some(chain) = snapshot.settings().get(NETWORK_SETTING).filter(|chain| *chain != api_client.chain()) {
+            let error = WrongNetworkAddress::new("wallet profile", api_client.chain(), chain);
+            return Err(WalletError::Profile(error.into()));
+        }
+        let (view_key, program_manager) = match (snapshot.accounts(), snapshot.watch_only()) {
+            ([private_key], []) => {
+                let view_key = ViewKey::try_from(private_key).map_err(WalletError::Profile)?;
+                (view_key, ProgramManager::new(*private_key, api_client))
+            }
+            ([], [view_key]) => (*view_key, ProgramManager::watch_only(api_client)),
+            (accounts, watch_only) => {
+                let count = accounts.len() + watch_only.len();
+                let error = anyhow!("A wallet profile holds one account, but this one holds {count}");
+                return Err(WalletError::Profile(error));
+            }
+        };
+        let program_manager = program_manager.with_record_store(snapshot.records().clone());
+        Ok(Self {
+            profile_lock,
+            passphrase: passphrase.to_string(),
+            view_key,
+            program_manager,
+            scan_state: snapshot.scan_state().clone(),
+            history: snapshot.history().to_vec(),
+            settings: snapshot.settings().clone(),
+            fee: Self::DEFAULT_FEE,
+            dirty: false,
+            shutdown: CancellationToken::new(),
+        })
+    }
+
+    /// Set the fee paid by transfers.
+    pub fn with_fee(mut self, fee: Microcredits) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Set the fee in gates paid by transfers.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `Wallet::with_fee`; removed in the next release")]
+    pub fn with_fee_raw(self, fee: u64) -> Self {
+        self.with_fee(Microcredits(fee))
+    }
+
+    /// Check the transfers of the wallet against the given policy, whose daily limit counts the spends kept in
+    /// the profile.
+    pub fn with_spending_policy(mut self, spending_policy: SpendingPolicy<N>) -> Self {
+        self.program_manager.set_spending_policy(spending_policy);
+        self
+    }
+
+    /// Returns the fee paid by transfers.
+    pub fn fee(&self) -> Microcredits {
+        self.fee
+    }
+
+    /// Returns the address of the account.
+    pub fn address(&self) -> Address<N> {
+        self.view_key.to_address()
+    }
+
+    /// Returns `true` if the wallet holds a view key in place of the private key.
+    pub fn is_watch_only(&self) -> bool {
+        self.private_key().is_none()
+    }
+
+    /// Returns the sum of the gates held by the unspent records found by the last sync.
+    ///
+    /// The balance of a watch-only wallet includes the records it received, even if they were spent since.
+    pub fn balance(&self) -> Microcredits {
+        let records = self.record_store().into_iter().flat_map(RecordStore::iter);
+        records.map(|(_, stored)| Microcredits(***stored.record().gates())).sum()
+    }
+
+    /// Returns the records received and spent by the wallet in blocks within the given heights, in the order of
+    /// the chain.
+    pub fn history(&self, block_heights: impl RangeBounds<BlockHeight>) -> Vec<&HistoryEntry<N>> {
+        self.history.iter().filter(|entry| block_heights.contains(&entry.height())).collect()
+    }
+
+    /// Scan the blocks added since the last sync, up to the latest block, and save the profile.
+    ///
+    /// Records created for the account are added to the unspent records and the history, and records it
+    /// spent are moved from the unspent records to the history. Returns the height of the latest block.
+    ///
+    /// Watch-only wallets add the records they receive as watch-only records, and cannot detect spends.
+    ///
+    /// Once the [`Wallet::shutdown_token`] is cancelled, the sync stops after the chunk of blocks it is
+    /// processing, and fails with [`WalletError::Cancelled`].
+    pub fn sync(&mut self) -> Result<BlockHeight, WalletError> {
+        let api_client = self.api_client().clone();
+        let latest_height = api_client.latest_height().map_err(WalletError::Network)?;
+        let start_height = self.scan_state.next_height();
+        if start_height <= latest_height {
+            let mut serial_numbers = self.serial_numbers().map_err(WalletError::Profile)?;
+            let mut result = Ok(());
+            let shutdown = self.shutdown.clone();
+            let fetched = api_client.for_each_block_cancellable(start_height..=latest_height, &shutdown, |block| {
+                if result.is_ok() {
+                    result = self.sync_block(&block, &mut serial_numbers);
+                }
+            });
+            // Save the blocks synced before a failure, so that the next sync resumes after them.
+            self.persist()?;
+            fetched.map_err(|error| match error.downcast::<Cancelled<()>>() {
+                Ok(cancelled) => WalletError::Cancelled(cancelled),
+                Err(error) => WalletError::Network(error),
+            })?;
+            result?;
+        }
+        Ok(latest_height)
+    }
+
+    // Add the records the block creates for the account, and remove the records it spends
+    fn sync_block(
+        &mut self,
+        block: &Block<N>,
+        serial_numbers: &mut HashMap<Field<N>, Field<N>>,
+    ) -> Result<(), WalletError> {
+        if self.scan_state.last_hash().map_or(false, |last_hash| block.previous_hash() != last_hash) {
+            return Err(WalletError::Reorganized { height: block.height() });
+        }
+        let (private_key, view_key, height) = (self.private_key().copied(), self.view_key, block.height());
+        for transaction in block.transactions().iter() {
+            for transition in transaction.transitions() {
+                for serial_number in transition.serial_numbers() {
+                    let commitment = match serial_numbers.remove(serial_number) {
+                        Some(commitment) => commitment,
+                        None => continue,
+                    };
+                    if let Some(stored) = self.program_manager.record_store_or_default().remove(&commitment) {
+                        let gates = ***stored.record().gates();
+                        let entry = HistoryEntry::new(
+                            HistoryKind::Spent,
+                            BlockHeight(height),
+                            transaction.id(),
+                            commitment,
+                            gates,
+                        );
+                        self.history.push(entry);
+                        let spent = WalletEvent::RecordSpent { commitment, transaction_id: transaction.id(), height };
+                        self.program_manager.log_event(spent).map_err(WalletError::Profile)?;
+                    }
+                }
+                for (commitment, record) in transition.records() {
+                    if !record.is_owner(&view_key) {
+                        continue;
+                    }
+                    let record = record.decrypt(&view_key).map_err(WalletError::Network)?;
+                    let (gates, memo) = (***record.gates(), crate::extract_memo(&record).ok().flatten());
+                    let records = self.program_manager.record_store_or_default();
+                    match private_key {
+                        Some(private_key) => {
+                            let serial_number = Record::<N, Plaintext<N>>::serial_number(private_key, *commitment)
+                                .map_err(WalletError::Network)?;
+                            serial_numbers.insert(serial_number, *commitment);
+                            records.insert(*commitment, record, height);
+                        }
+                        None => {
+                            records.insert_watch_only(*commitment, record, height);
+                        }
+                    }
+                    records.attribute(commitment, *transition.program_id());
+                    let entry = HistoryEntry::new(
+                        HistoryKind::Received,
+                        BlockHeight(height),
+                        transaction.id(),
+                        *commitment,
+                        gates,
+                    );
+                    self.history.push(entry.with_memo(memo));
+                }
+            }
+        }
+        self.scan_state.advance(block);
+        self.dirty = true;
+        Ok(())
+    }
+
+    // Returns the commitments of the unspent records, by serial number, or none for a watch-only wallet
+    fn serial_numbers(&self) -> anyhow::Result<HashMap<Field<N>, Field<N>>> {
+        let private_key = match self.private_key() {
+            Some(private_key) => *private_key,
+            None => return Ok(HashMap::new()),
+        };
+        let records = self.record_store().into_iter().flat_map(RecordStore::iter);
+        records
+            .map(|(commitment, _)| {
+                Ok((Record::<N, Plaintext<N>>::serial_number(private_key, *commitment)?, *commitment))
+            })
+            .collect()
+    }
+
+    /// Send `amount` gates to the recipient with a `credits.aleo/transfer`, and return the ID of the transaction.
+    ///
+    /// The transfer spends the smallest unspent record holding the amount, and the fee is paid with another
+    /// record, selected by [`ProgramManager::select_fee_record`]. The spent records count towards the balance
+    /// until a sync finds the transaction on chain. Watch-only wallets fail with [`WalletError::SigningUnavailable`].
+    ///
+    /// Transfers refused by the [`SpendingPolicy`] of the wallet fail with [`WalletError::PolicyViolation`]
+    /// before any proving, and the profile is saved after each broadcast to keep the count of the daily spends.
+    pub fn send(&mut self, recipient: Address<N>, amount: Microcredits) -> Result<N::TransactionID, WalletError> {
+        self.program_manager.signer().map_err(WalletError::SigningUnavailable)?;
+        let balance = self.balance();
+        match amount.checked_add(self.fee) {
+            Some(total) if total <= balance => (),
+            _ => return Err(WalletError::InsufficientFunds { amount, fee: self.fee, balance }),
+        }
+        let records = self.record_store().into_iter().flat_map(RecordStore::iter);
+        let input_record = records
+            .map(|(_, stored)| stored.record())
+            .filter(|record| ***record.gates() >= amount.0)
+            .min_by_key(|record| ***record.gates())
+            .cloned();
+        let input_record = match input_record {
+            Some(input_record) => input_record,
+            None => {
+                let error = anyhow!("No unspent record holds {amount} gates, so records must be joined first");
+                return Err(WalletError::Transaction(error));
+            }
+        };
+        let pending =
+            PendingTransaction::transfer(recipient, amount.0, self.fee.0).map_err(WalletError::Transaction)?;
+        self.program_manager.enforce_spending_policy(&pending).map_err(|error| match error.downcast() {
+            Ok(violation) => WalletError::PolicyViolation(violation),
+            Err(error) => WalletError::Transaction(error),
+        })?;
+        let (transaction, _) = self
+            .program_manager
+            .build_transfer_auto_fee(amount, self.fee, recipient, input_record)
+            .map_err(WalletError::Transaction)?;
+        let transaction_id = transaction.id();
+        self.program_manager.broadcast(transaction).map_err(WalletError::Transaction)?;
+        self.program_manager.record_spend(&pending);
+        self.dirty = true;
+        self.persist()?;
+        Ok(transaction_id)
+    }
+
+    /// Send the given number of gates to the recipient, as [`Wallet::send`] does.
+    #[deprecated(since = "0.3.6", note = "pass `Microcredits` to `Wallet::send`; removed in the next release")]
+    pub fn send_raw(&mut self, recipient: Address<N>, amount: u64) -> Result<N::TransactionID, WalletError> {
+        self.send(recipient, Microcredits(amount))
+    }
+
+    /// Returns a token that shuts the wallet down once cancelled, e.g. from a signal handler while the wallet
+    /// syncs on another thread.
+    ///
+    /// Syncs stop at the next chunk of blocks once the token is cancelled, and later syncs stop before fetching
+    /// any block.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Stop the syncs of the wallet, and flush its state to its profile, returning what the profile holds.
+    ///
+    /// Shutting down again writes nothing, and returns the same summary.
+    pub fn shutdown(&mut self) -> Result<FlushSummary, WalletError> {
+        self.shutdown.cancel();
+        self.flush().map_err(WalletError::Profile)
+    }
+
+    /// Write the state of the wallet to its profile.
+    pub fn save(&self) -> Result<(), WalletError> {
+        let records = self.record_store().cloned().unwrap_or_default();
+        let accounts = self.private_key().into_iter().copied().collect();
+        let watch_only = if self.is_watch_only() { vec![self.view_key] } else { vec![] };
+        let snapshot = WalletSnapshot::new(accounts, records, self.scan_state.clone())
+            .with_watch_only(watch_only)
+            .with_history(self.history.clone())
+            .with_settings(self.settings.clone());
+        snapshot.write(self.profile_lock.path(), &self.passphrase).map_err(WalletError::Profile)
+    }
+
+    /// Import the private key of a watch-only wallet, so that it can send transfers, and save the profile.
+    ///
+    /// The records found while watching are claimed without rescanning: the node is asked for the transition
+    /// spending each of them, and those already spent are moved to the history.
+    pub fn import_private_key(&mut self, private_key: PrivateKey<N>) -> Result<(), WalletError> {
+        if !WatchOnlyAccount::new(self.view_key).is_owned_by(&private_key) {
+            return Err(WalletError::Profile(anyhow!("The private key does not belong to the account of the wallet")));
+        }
+        // Find the spent records before changing the wallet, so that a failed query leaves it watch-only.
+        let mut spent = vec![];
+        let records = self.record_store().into_iter().flat_map(RecordStore::iter);
+        for (commitment, stored) in records.filter(|(_, stored)| stored.is_watch_only()) {
+            let serial_number =
+                Record::<N, Plaintext<N>>::serial_number(private_key, *commitment).map_err(WalletError::Profile)?;
+            if let Some((height, transaction_id)) = self.find_spend(serial_number).map_err(WalletError::Network)? {
+                let gates = ***stored.record().gates();
+                let height = BlockHeight(height);
+                spent.push(HistoryEntry::new(HistoryKind::Spent, height, transaction_id, *commitment, gates));
+            }
+        }
+        let records = self.program_manager.record_store_or_default();
+        for entry in &spent {
+            records.remove(&entry.commitment());
+        }
+        records.claim(self.view_key.to_address());
+        self.program_manager.set_private_key(private_key);
+        self.history.extend(spent);
+        self.history.sort_by_key(HistoryEntry::height);
+        self.dirty = true;
+        self.persist()
+    }
+
+    // Save the state of the wallet to its profile, which then holds every change
+    fn persist(&mut self) -> Result<(), WalletError> {
+        self.save()?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    // Returns the height and transaction of the transition spending the record with the given serial number
+    fn find_spend(&self, serial_number: Field<N>) -> anyhow::Result<Option<(u32, N::TransactionID)>> {
+        let api_client = self.api_client();
+        let transition_id = match api_client.find_transition_id(serial_number) {
+            Ok(transition_id) => transition_id,
+            Err(error) if is_not_found(&error) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let transaction_id = api_client.find_transaction_id(transition_id)?;
+        let height = api_client.get_height(api_client.find_block_hash(transaction_id)?)?.0;
+        Ok(Some((height, transaction_id)))
+    }
+
+    /// Returns the path of the profile.
+    pub fn profile_path(&self) -> &Path {
+        self.profile_lock.path()
+    }
+
+    /// Returns the private key of the account, or `None` for a watch-only wallet.
+    pub fn private_key(&self) -> Option<&PrivateKey<N>> {
+        self.program_manager.private_key()
+    }
+
+    /// Returns the view key of the account.
+    pub fn view_key(&self) -> &ViewKey<N> {
+        &self.view_key
+    }
+
+    /// Returns the API client used to query the network.
+    pub fn api_client(&self) -> &AleoAPIClient<N> {
+        self.program_manager.api_client()
+    }
+
+    /// Returns the program manager that builds the transactions of the wallet.
+    pub fn program_manager(&self) -> &ProgramManager<N> {
+        &self.program_manager
+    }
+
+    /// Returns the unspent records of the account.
+    pub fn record_store(&self) -> Option<&RecordStore<N>> {
+        self.program_manager.record_store()
+    }
+
+    /// Returns the progress of the sync.
+    pub fn scan_state(&self) -> &ScanState<N> {
+        &self.scan_state
+    }
+}
+
+impl<N: Network> Flush for Wallet<N> {
+    /// Save the profile if the wallet changed since it was last saved.
+    fn flush(&mut self) -> anyhow::Result<FlushSummary> {
+        let written = self.dirty;
+        if written {
+            self.persist()?;
+        }
+        let records = self.record_store().map_or(0, RecordStore::len);
+        Ok(FlushSummary::new(self.profile_path().to_path_buf(), written, records, self.scan_state.next_height()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{
+            genesis_block,
+            sample_block_with_transactions,
+            sample_output,
+            sample_transaction,
+            sample_transition,
+            CurrentNetwork,
+            MockRequest,
+            MockResponse,
+            MockServer,
+        },
+        testnet3,
+    };
+    use snarkvm_console::prelude::Uniform;
+    use snarkvm_synthesizer::Transaction;
+    use snarkvm_utilities::TestRng;
+
+    use std::{
+        env,
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    type N = CurrentNetwork;
+
+    // Start a mock node serving the given chain, which may be extended while the node runs
+    fn mock_node(chain: Arc<Mutex<Vec<Block<N>>>>) -> MockServer {
+        MockServer::start(move |request| respond(&chain.lock().unwrap(), request))
+    }
+
+    // Answer a request to the node from the blocks of the chain
+    fn respond(blocks: &[Block<N>], request: &MockRequest) -> Option<MockResponse> {
+        if request.path == "/testnet3/latest/height" {
+            return Some(MockResponse::json(blocks.len() - 1));
+        }
+        if let Some(path) = request.path.strip_prefix("/testnet3/find/") {
+            return find(blocks, path);
+        }
+        if let Some(hash) = request.path.strip_prefix("/testnet3/height/") {
+            let block = blocks.iter().find(|block| block.hash().to_string() == hash)?;
+            return Some(MockResponse::json(block.height()));
+        }
+        let (start, end) = request.path.strip_prefix("/testnet3/blocks?start=")?.split_once("&end=")?;
+        let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+        let blocks = blocks.get(start..end.min(blocks.len()))?.iter().map(ToString::to_string);
+        Some(MockResponse::json(format!("[{}]", blocks.collect::<Vec<_>>().join(","))))
+    }
+
+    // Answer the lookups of the node from the blocks of the chain
+    fn find(blocks: &[Block<N>], path: &str) -> Option<MockResponse> {
+        let (kind, id) = path.split_once('/')?;
+        let mut transactions = blocks.iter().flat_map(|block| block.transactions().iter().map(move |tx| (block, tx)));
+        let found = match kind {
+            "transitionID" => transactions
+                .flat_map(|(_, transaction)| transaction.transitions())
+                .find(|transition| transition.serial_numbers().any(|serial_number| serial_number.to_string() == id))?
+                .id()
+                .to_string(),
+            "transactionID" => transactions
+                .find(|(_, transaction)| transaction.transitions().any(|transition| transition.id().to_string() == id))?
+                .1
+                .id()
+                .to_string(),
+            "blockHash" => {
+                transactions.find(|(_, transaction)| transaction.id().to_string() == id)?.0.hash().to_string()
+            }
+            _ => return None,
+        };
+        Some(MockResponse::json(format!("\"{found}\"")))
+    }
+
+    // Append a block holding the given transaction to the chain
+    fn extend_chain(chain: &Mutex<Vec<Block<N>>>, transaction: Transaction<N>, rng: &mut TestRng) {
+        let mut blocks = chain.lock().unwrap();
+        let (height, previous_hash) = (blocks.len() as u32, blocks.last().unwrap().hash());
+        blocks.push(sample_block_with_transactions(height, previous_hash, [transaction].into_iter().collect(), rng));
+    }
+
+    #[test]
+    fn test_wallet_flow() {
+        let rng = &mut TestRng::default();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        let server = mock_node(chain.clone());
+        let path = env::temp_dir().join(format!("aleo-wallet-flow-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        // A new wallet is empty, and its profile stays locked while it is open.
+        let mut wallet = Wallet::create(&path, "passphrase", testnet3(server.base_url())).unwrap();
+        assert_eq!(wallet.sync().unwrap(), BlockHeight(0));
+        assert_eq!(wallet.balance(), Microcredits(0));
+        let error = Wallet::create(&path, "passphrase", testnet3(server.base_url())).err().unwrap();
+        let pid = std::process::id();
+        assert_eq!(
+            error.to_string(),
+            format!("Failed to access the wallet profile: The store at '{}' is locked by process {pid}", path.display())
+        );
+
+        // The wallet is funded with two records, then spends one of them.
+        let (funding, change) = (sample_output(wallet.address(), 500, rng), sample_output(wallet.address(), 20, rng));
+        let stranger = sample_output(Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap(), 7, rng);
+        let fund =
+            sample_transaction([sample_transition(&[Field::rand(rng)], &[funding.clone(), change.clone()], rng)]);
+        extend_chain(&chain, fund.clone(), rng);
+        assert_eq!(wallet.sync().unwrap(), BlockHeight(1));
+        assert_eq!(wallet.balance(), Microcredits(520));
+        let serial_number = Record::<N, Plaintext<N>>::serial_number(*wallet.private_key().unwrap(), change.0).unwrap();
+        let spend = sample_transaction([sample_transition(&[serial_number], &[stranger], rng)]);
+        extend_chain(&chain, spend.clone(), rng);
+        assert_eq!(wallet.sync().unwrap(), BlockHeight(2));
+        assert_eq!(wallet.balance(), Microcredits(500));
+
+        // The history holds both records, and the spend of one of them.
+        let entry = |entry: &HistoryEntry<N>| (entry.kind(), entry.height(), entry.transaction_id(), entry.gates());
+        let history = wallet.history(..).into_iter().map(entry).collect::<Vec<_>>();
+        assert_eq!(history, [
+            (HistoryKind::Received, BlockHeight(1), fund.id(), 500),
+            (HistoryKind::Received, BlockHeight(1), fund.id(), 20),
+            (HistoryKind::Spent, BlockHeight(2), spend.id(), 20)
+        ]);
+        assert_eq!(wallet.history(BlockHeight(2)..).len(), 1);
+        assert_eq!(wallet.history(..BlockHeight(1)).len(), 0);
+
+        // Transfers beyond the balance are refused before any proving.
+        let recipient = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let error = wallet.send(recipient, Microcredits(500)).unwrap_err();
+        assert_eq!(error.to_string(), "The wallet holds 500 gates, which cannot pay 500 gates and a fee of 1 gates");
+
+        // Once the wallet is closed, its profile is never overwritten, keeps its state, and only opens with its
+        // passphrase.
+        let history = wallet.history(..).into_iter().cloned().collect::<Vec<_>>();
+        let (address, scan_state) = (wallet.address(), wallet.scan_state().clone());
+        drop(wallet);
+        let error = Wallet::create(&path, "passphrase", testnet3(server.base_url())).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!("Failed to access the wallet profile: A wallet profile already exists at '{}'", path.display())
+        );
+        assert!(matches!(Wallet::open(&path, "wrong", testnet3(server.base_url())), Err(WalletError::Profile(_))));
+        let client = AleoAPIClient::<N>::new(server.base_url(), "private");
+        let error = Wallet::open(&path, "passphrase", client).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Failed to access the wallet profile: The wallet profile is for the 'testnet3' network, but this client \
+             is for 'private'"
+        );
+        let reopened = Wallet::open(&path, "passphrase", testnet3(server.base_url())).unwrap();
+        assert_eq!((reopened.address(), reopened.balance()), (address, Microcredits(500)));
+        assert_eq!(reopened.history(..).into_iter().cloned().collect::<Vec<_>>(), history);
+        assert_eq!(reopened.scan_state(), &scan_state);
+
+        // Exports and imports of the profile wait for the wallet holding it open.
+        let snapshot = WalletSnapshot::<N>::new(vec![], RecordStore::new(), ScanState::new(BlockHeight::GENESIS));
+        let error = snapshot.export(&path, "passphrase").unwrap_err();
+        assert_eq!(error.downcast_ref::<crate::StoreLocked>().unwrap().pid(), Some(pid));
+        assert!(WalletSnapshot::<N>::import(&path, "passphrase").is_err());
+        drop(reopened);
+        assert!(WalletSnapshot::<N>::import(&path, "passphrase").is_ok());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_wallet_detects_reorganization() {
+        let rng = &mut TestRng::default();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        let server = mock_node(chain.clone());
+        let path = env::temp_dir().join(format!("aleo-wallet-reorg-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut wallet = Wallet::create(&path, "passphrase", testnet3(server.base_url())).unwrap();
+        let funding = sample_output(wallet.address(), 500, rng);
+        extend_chain(&chain, sample_transaction([sample_transition(&[], &[funding], rng)]), rng);
+        assert_eq!(wallet.sync().unwrap(), BlockHeight(1));
+
+        // Replace the synced block with another one, and extend the new chain. Sampled blocks are hashed from
+        // their height and parent only, so the fork starts from another parent.
+        let other = sample_output(wallet.address(), 1, rng);
+        let genesis = genesis_block();
+        let fork = sample_block_with_transactions(1, Default::default(), genesis.transactions().clone(), rng);
+        chain.lock().unwrap()[1] = fork;
+        extend_chain(&chain, sample_transaction([sample_transition(&[], &[other], rng)]), rng);
+        assert!(matches!(wallet.sync(), Err(WalletError::Reorganized { height: 2 })));
+        assert_eq!(wallet.balance(), Microcredits(500));
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_wallet_shutdown_mid_sync() {
+        let rng = &mut TestRng::default();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        let server = mock_node(chain.clone());
+        let path = env::temp_dir().join(format!("aleo-wallet-shutdown-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        // The wallet receives a record of 1 to 6 gates in each of the blocks at heights 1 to 6.
+        let address = Wallet::create(&path, "passphrase", testnet3(server.base_url())).unwrap().address();
+        for gates in 1..=6 {
+            let output = sample_output(address, gates, rng);
+            extend_chain(&chain, sample_transaction([sample_transition(&[], &[output], rng)]), rng);
+        }
+
+        // The daemon is shut down while the node serves the first chunk of blocks, from 0 to 2.
+        let shutdown = Arc::new(Mutex::new(None::<CancellationToken>));
+        let (signal, blocks) = (shutdown.clone(), chain.clone());
+        let interrupting_server = MockServer::start(move |request| {
+            if let Some(token) =
+                signal.lock().unwrap().as_ref().filter(|_| request.path.starts_with("/testnet3/blocks?"))
+            {
+                token.cancel();
+            }
+            respond(&blocks.lock().unwrap(), request)
+        });
+        let api_client = testnet3(interrupting_server.base_url()).with_max_block_request(2);
+        let mut wallet = Wallet::open(&path, "passphrase", api_client).unwrap();
+        *shutdown.lock().unwrap() = Some(wallet.shutdown_token());
+        match wallet.sync() {
+            Err(WalletError::Cancelled(cancelled)) => assert_eq!(cancelled.resume_height(), Some(2)),
+            result => panic!("Expected the sync to be cancelled, found {result:?}"),
+        }
+        assert_eq!(wallet.balance(), Microcredits(1));
+
+        // The sync saved the chunk in flight, so shutting down writes nothing, and shutting down again is a no-op.
+        let summary = wallet.shutdown().unwrap();
+        assert_eq!((summary.path(), summary.written()), (path.as_path(), false));
+        assert_eq!((summary.records(), summary.next_height()), (1, BlockHeight(2)));
+        assert_eq!(wallet.shutdown().unwrap(), summary);
+        assert!(matches!(wallet.sync(), Err(WalletError::Cancelled(_))));
+        drop(wallet);
+
+        // The restarted wallet resumes from the saved height, without losing or repeating a record.
+        let mut wallet = Wallet::open(&path, "passphrase", testnet3(server.base_url())).unwrap();
+        assert_eq!(wallet.scan_state().next_height(), BlockHeight(2));
+        assert_eq!(wallet.sync().unwrap(), BlockHeight(6));
+        assert_eq!(wallet.balance(), Microcredits(21));
+        let received = wallet.history(..).into_iter().map(|entry| (entry.height(), entry.gates())).collect::<Vec<_>>();
+        assert_eq!(received, (1..=6).map(|gates| (BlockHeight(gates as u32), gates)).collect::<Vec<_>>());
+        assert_eq!(wallet.record_store().unwrap().len(), 6);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_watch_only_wallet() {
+        let rng = &mut TestRng::default();
+        let chain = Arc::new(Mutex::new(vec![genesis_block()]));
+        let server = mock_node(chain.clone());
+        let path = env::temp_dir().join(format!("aleo-wallet-watch-only-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let private_key = PrivateKey::<N>::new(rng).unwrap();
+        let account = WatchOnlyAccount::new(ViewKey::try_from(&private_key).unwrap());
+        let api_client = testnet3(server.base_url());
+        let mut wallet = Wallet::create_watch_only(&path, "passphrase", account, BlockHeight(0), api_client).unwrap();
+        assert!(wallet.is_watch_only());
+        assert_eq!(wallet.address(), account.address());
+
+        // Deposits are detected with the view key only, but spends are not.
+        let (deposit, change) = (sample_output(account.address(), 500, rng), sample_output(account.address(), 20, rng));
+        let fund = sample_transaction([sample_transition(&[Field::rand(rng)], &[deposit, change.clone()], rng)]);
+        extend_chain(&chain, fund.clone(), rng);
+        assert_eq!(wallet.sync().unwrap(), BlockHeight(1));
+        assert_eq!(wallet.balance(), Microcredits(520));
+        assert!(wallet.record_store().unwrap().iter().all(|(_, stored)| stored.is_watch_only()));
+        let serial_number = Record::<N, Plaintext<N>>::serial_number(private_key, change.0).unwrap();
+        let spend = sample_transaction([sample_transition(&[serial_number], &[], rng)]);
+        extend_chain(&chain, spend.clone(), rng);
+        assert_eq!(wallet.sync().unwrap(), BlockHeight(2));
+        assert_eq!(wallet.balance(), Microcredits(520));
+
+        // Transfers fail for lack of the private key.
+        let recipient = Address::try_from(PrivateKey::<N>::new(rng).unwrap()).unwrap();
+        let error = wallet.send(recipient, Microcredits(100)).unwrap_err();
+        assert!(matches!(error, WalletError::SigningUnavailable(SigningUnavailable)));
+        assert_eq!(error.to_string(), "The account is watch-only, so it cannot sign transactions");
+        let records = wallet.record_store().unwrap().iter().map(|(_, stored)| stored.record().clone());
+        let [input_record, fee_record]: [_; 2] = records.collect::<Vec<_>>().try_into().unwrap();
+        let error = wallet
+            .program_manager()
+            .build_transfer(Microcredits(10), Microcredits(1), recipient, input_record, fee_record)
+            .unwrap_err();
+        assert!(error.is::<SigningUnavailable>());
+
+        // The profile keeps the watch-only account.
+        drop(wallet);
+        let mut wallet = Wallet::open(&path, "passphrase", testnet3(server.base_url())).unwrap();
+        assert!(wallet.is_watch_only());
+        assert_eq!((wallet.address(), wallet.balance()), (account.address(), Microcredits(520)));
+
+        // Importing the private key claims the records without rescanning, finding the spend of one of them.
+        let error = wallet.import_private_key(PrivateKey::new(rng).unwrap()).unwrap_err();
+        assert!(matches!(error, WalletError::Profile(_)));
+        wallet.import_private_key(private_key).unwrap();
+        assert!(!wallet.is_watch_only());
+        assert_eq!(wallet.balance(), Microcredits(500));
+        assert!(wallet.record_store().unwrap().iter().all(|(_, stored)| !stored.is_watch_only()));
+        let last = wallet.history(..).pop().unwrap();
+        assert_eq!(
+            (last.kind(), last.height(), last.transaction_id(), last.gates()),
+            (HistoryKind::Spent, BlockHeight(2), spend.id(), 20)
+        );
+        drop(wallet);
+        let reopened = Wallet::open(&path, "passphrase", testnet3(server.base_url())).unwrap();
+        assert_eq!((reopened.private_key(), reopened.balance()), (Some(&private_key), Microcredits(500)));
+        fs::remove_file(path).unwrap();
+    }
+}
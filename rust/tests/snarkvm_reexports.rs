@@ -0,0 +1,63 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the Aleo library.
+
+// The Aleo library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The Aleo library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the Aleo library. If not, see <https://www.gnu.org/licenses/>.
+
+// A downstream crate using snarkVM only through the re-exports of this crate, without depending on it.
+
+use aleo_rust::{
+    snarkvm::{
+        console::program::Literal,
+        synthesizer::Program as SynthesizerProgram,
+        utilities::TestRng,
+        Address,
+        Field,
+        Identifier,
+        Network,
+        Plaintext,
+        PrivateKey,
+        Program,
+        ProgramID,
+        Testnet3,
+        ViewKey,
+    },
+    version_info,
+    Encryptor,
+    SNARKVM_VERSION,
+};
+
+use std::str::FromStr;
+
+#[test]
+fn test_snarkvm_reexports() {
+    let rng = &mut TestRng::default();
+
+    // The re-exported types are the ones of the API of this crate.
+    let private_key = PrivateKey::<Testnet3>::new(rng).unwrap();
+    let ciphertext = Encryptor::<Testnet3>::encrypt_private_key_with_secret(&private_key, "secret").unwrap();
+    let decrypted = Encryptor::<Testnet3>::decrypt_private_key_with_secret(&ciphertext, "secret").unwrap();
+    assert_eq!(decrypted, private_key);
+    let view_key = ViewKey::try_from(&private_key).unwrap();
+    assert_eq!(Address::try_from(&view_key).unwrap(), Address::try_from(&private_key).unwrap());
+
+    // The crates themselves are reachable for the types that are not re-exported individually.
+    let credits: Program<Testnet3> = SynthesizerProgram::credits().unwrap();
+    assert_eq!(*credits.id(), ProgramID::from_str("credits.aleo").unwrap());
+    assert!(credits.contains_function(&Identifier::from_str("transfer").unwrap()));
+    let literal = Plaintext::<Testnet3>::from(Literal::Field(Field::from_u64(1)));
+    assert_eq!(literal.to_string(), "1field");
+    assert_eq!(<Testnet3 as Network>::ID, 3);
+
+    assert_eq!(version_info().snarkvm, SNARKVM_VERSION);
+}
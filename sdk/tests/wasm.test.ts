@@ -293,8 +293,8 @@ describe('WASM Objects', () => {
         it('decryption of PrivateKeyCiphertext with edge cases', () => {
             const privateKeyString = "APrivateKey1zkpAYS46Dq4rnt9wdohyWMwdmjmTeMJKPZdp5AhvjXZDsVG";
             const privateKey = PrivateKey.from_string(privateKeyString);
-            const ciphertext = "ciphertext1qvqg7rgvam3xdcu55pwu6sl8rxwefxaj5gwthk0yzln6jv5fastzup0qn0qftqlqq7jcckyx03fzv9kke0z9puwd7cl7jzyhxfy2f2juplz39dkqs6p24urhxymhv364qm3z8mvyklv5gr52n4fxr2z59jgqytyddj8";
-            const bad_ciphertext = "ciphertext1qvqg7rgvam3xdcu55pwu6sl8rxwefxaj5gwthk0yzln6jv5fastzup0qn0qftqlqq7jcckyx03fzv9kke0z9puwd7cl7jzyhxfy2f2juplz39dkqs6p24urhxymhv364qm3z8mvyklv5er52n4fxr2z59jgqytyddj8";
+            const ciphertext = "ciphertext1qsqg7rgvam3xdcu55pw6m3v8zs4mt66nja3wvr7mxs5sq58g7c4yvrvqgnu8dczjj7nsq2yd03fzv9kke0z9puwd7c3s2vppcndsq26wpenr8g3fjv2xd92akrtwd8lxlu6tw87sczzg2r52n4fxr2z59jgq92hruanrywrgnty9j9x0jusyswepru2lffcdl02qkmywhy30j3ctty8zq4";
+            const bad_ciphertext = "ciphertext1qsqg7rgvam3xdcu55pw6m3v8zs4mt66nja3wvr7mxs5sq58g7c4yvrvqgnu8dczjj7nsq2yd03fzv9kke0z9puwd7c3s2vppcndsq26wpenr8g3fjv2xd92akrtwd8lxlu6tw87sczze2r52n4fxr2z59jgq92hruanrywrgnty9j9x0jusyswepru2lffcdl02qkmywhy30j3ctty8zq4";
             const privateKeyCiphertext = PrivateKeyCiphertext.fromString(ciphertext);
             const decryptedPrivateKey = privateKeyCiphertext.decryptToPrivateKey("mypassword");
 
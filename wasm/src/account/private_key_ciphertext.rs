@@ -105,7 +105,7 @@ mod tests {
     fn test_private_key_from_string_decryption_edge_cases() {
         let private_key =
             PrivateKey::from_string("APrivateKey1zkpAYS46Dq4rnt9wdohyWMwdmjmTeMJKPZdp5AhvjXZDsVG").unwrap();
-        let ciphertext = "ciphertext1qvqg7rgvam3xdcu55pwu6sl8rxwefxaj5gwthk0yzln6jv5fastzup0qn0qftqlqq7jcckyx03fzv9kke0z9puwd7cl7jzyhxfy2f2juplz39dkqs6p24urhxymhv364qm3z8mvyklv5gr52n4fxr2z59jgqytyddj8";
+        let ciphertext = "ciphertext1qsqg7rgvam3xdcu55pw6m3v8zs4mt66nja3wvr7mxs5sq58g7c4yvrvqgnu8dczjj7nsq2yd03fzv9kke0z9puwd7c3s2vppcndsq26wpenr8g3fjv2xd92akrtwd8lxlu6tw87sczzg2r52n4fxr2z59jgq92hruanrywrgnty9j9x0jusyswepru2lffcdl02qkmywhy30j3ctty8zq4";
         let private_key_ciphertext = PrivateKeyCiphertext::from_string(ciphertext.to_string()).unwrap();
         let decrypted_private_key = private_key_ciphertext.decrypt_to_private_key("mypassword").unwrap();
 
@@ -114,7 +114,7 @@ mod tests {
         // Assert the incorrect secret fails
         assert!(private_key_ciphertext.decrypt_to_private_key("badpassword").is_err());
         // Ensure invalid ciphertexts fail
-        let bad_ciphertext = "ciphertext1qvqg7rgvam3xdcu55pwu6sl8rxwefxaj5gwthk0yzln6jv5fastzup0qn0qftqlqq7jcckyx03fzv9kke0z9puwd7cl7jzyhxfy2f2juplz39dkqs6p24urhxymhv364qm3z8mvyklv5er52n4fxr2z59jgqytyddj8";
+        let bad_ciphertext = "ciphertext1qsqg7rgvam3xdcu55pw6m3v8zs4mt66nja3wvr7mxs5sq58g7c4yvrvqgnu8dczjj7nsq2yd03fzv9kke0z9puwd7c3s2vppcndsq26wpenr8g3fjv2xd92akrtwd8lxlu6tw87sczze2r52n4fxr2z59jgq92hruanrywrgnty9j9x0jusyswepru2lffcdl02qkmywhy30j3ctty8zq4";
         assert!(PrivateKeyCiphertext::from_string(bad_ciphertext.to_string()).is_err());
     }
 